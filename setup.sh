@@ -41,7 +41,7 @@ check_requirements() {
 
     # Check for Node.js
     if ! command -v node &> /dev/null; then
-        print_error "Node.js is not installed. Please install Node.js 20+ first."
+        print_error "Node.js is not installed. Please install Node.js 22.15+ first."
         exit 1
     else
         NODE_VERSION=$(node -v)
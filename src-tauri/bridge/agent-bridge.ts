@@ -5,11 +5,13 @@ import { ensureBrowserLikeGlobals, setupProcessHandlers } from './agent/environm
 import { BridgeChannel } from './agent/bridge-channel';
 import { BridgeRuntime } from './agent/runtime';
 import { startBridgeIO } from './agent/io';
+import { installRateLimitCapture } from './agent/rate-limits';
 
 const logging = installLogging();
 
 setupProcessHandlers(logging.writeStderr);
 ensureBrowserLikeGlobals();
+installRateLimitCapture();
 
 const channel = new BridgeChannel(
   logging.writeJsonLine,
@@ -0,0 +1,77 @@
+import type { ProviderRateLimitHeaders } from './types';
+
+const PROVIDER_HOSTS: ReadonlyArray<[string, string]> = [
+  ['api.openai.com', 'openai'],
+  ['api.anthropic.com', 'anthropic'],
+  ['openrouter.ai', 'openrouter'],
+  ['generativelanguage.googleapis.com', 'gemini'],
+];
+
+const RATE_LIMIT_HEADER_PREFIXES = [
+  'x-ratelimit-',
+  'anthropic-ratelimit-',
+  'retry-after',
+];
+
+let captured: ProviderRateLimitHeaders[] = [];
+let installed = false;
+
+const providerForUrl = (input: unknown): string | undefined => {
+  let href: string | undefined;
+  if (typeof input === 'string') {
+    href = input;
+  } else if (input instanceof URL) {
+    href = input.href;
+  } else if (input && typeof (input as { url?: unknown }).url === 'string') {
+    href = (input as { url: string }).url;
+  }
+  if (!href) {
+    return undefined;
+  }
+  try {
+    const host = new URL(href).hostname;
+    return PROVIDER_HOSTS.find(
+      ([candidate]) => host === candidate || host.endsWith(`.${candidate}`)
+    )?.[1];
+  } catch {
+    return undefined;
+  }
+};
+
+/**
+ * Wraps the global fetch so rate-limit headers from LLM provider responses are
+ * kept for the desktop, which budgets requests from them. Responses are
+ * returned untouched.
+ */
+export const installRateLimitCapture = (): void => {
+  if (installed || typeof globalThis.fetch !== 'function') {
+    return;
+  }
+  installed = true;
+  const originalFetch = globalThis.fetch.bind(globalThis);
+
+  globalThis.fetch = async (input, init) => {
+    const response = await originalFetch(input, init);
+    const provider = providerForUrl(input);
+    if (provider) {
+      const headers: Record<string, string> = {};
+      response.headers.forEach((value, name) => {
+        const lower = name.toLowerCase();
+        if (RATE_LIMIT_HEADER_PREFIXES.some((prefix) => lower.startsWith(prefix))) {
+          headers[lower] = value;
+        }
+      });
+      if (Object.keys(headers).length > 0) {
+        captured.push({ provider, headers });
+      }
+    }
+    return response;
+  };
+};
+
+/** Returns the headers captured since the last call, oldest first. */
+export const drainRateLimitHeaders = (): ProviderRateLimitHeaders[] => {
+  const drained = captured;
+  captured = [];
+  return drained;
+};
@@ -11,24 +11,32 @@ import type {
   WalletNetwork,
 } from './types';
 import type {
+  AgentAnsweredBy,
   AgentFormSubmission,
   AgentHistoryEntry,
   AgentInitializePayload,
+  AgentLlmOverride,
   AgentMessagePayload,
   AgentProcessResult,
   AgentResponsePayload,
   AttachmentDescriptor,
   BridgeRequest,
   BridgeResponse,
+  TopicCreatePayload,
+  TopicSubmitPayload,
 } from './types';
 import { buildPageContextPrompt, extractPageContext, normalizeAttachments } from './attachments';
 import { InscriptionService, toDashedTransactionId } from './inscription';
 import { configureWalletBridge } from './wallet';
+import { TopicService } from './topics';
+import { drainRateLimitHeaders } from './rate-limits';
 import { summarizeKeys } from './logging';
 import type { BridgeChannel } from './bridge-channel';
 import { toRecord } from '../inscriber-helpers';
 import type { ContentStoreManager } from '@hashgraphonline/conversational-agent/dist/types/services/content-store-manager';
 
+const inheritedOpenAIBaseURL = process.env.OPENAI_BASE_URL;
+
 type ChatHistoryItem = {
   type: 'human' | 'ai' | 'system';
   content: string;
@@ -45,8 +53,17 @@ export interface BridgeRuntimeDependencies {
   readonly writeStderr: WriteStderr;
 }
 
+type SessionAgent = {
+  readonly apiKey: string;
+  readonly instance: ConversationalAgentInstance;
+};
+
 export class BridgeRuntime {
   private agent: ConversationalAgentInstance | null = null;
+  private agentOptions: ConversationalAgentOptions | null = null;
+  /** Agents for sessions that picked their own provider and model. */
+  private readonly sessionAgents = new Map<string, SessionAgent>();
+  private topics: TopicService | null = null;
   private readonly attachmentProcessor = new AttachmentProcessor();
   private readonly inscriptionService: InscriptionService;
 
@@ -83,6 +100,18 @@ export class BridgeRuntime {
         return this.wrapResponse(request, startTime, Promise.resolve(this.handleStatus()));
       case 'disconnect':
         return this.wrapResponse(request, startTime, this.handleDisconnect());
+      case 'createTopic':
+        return this.wrapResponse(
+          request,
+          startTime,
+          this.handleCreateTopic(request.payload as TopicCreatePayload | undefined)
+        );
+      case 'submitTopicMessage':
+        return this.wrapResponse(
+          request,
+          startTime,
+          this.handleSubmitTopicMessage(request.payload as TopicSubmitPayload | undefined)
+        );
       default:
         return this.wrapResponse(
           request,
@@ -109,7 +138,8 @@ export class BridgeRuntime {
         durationMs: Date.now() - startTime,
         success: response.success,
       });
-      return response;
+      const rateLimits = drainRateLimitHeaders();
+      return rateLimits.length > 0 ? { ...response, rateLimits } : response;
     } catch (error) {
       this.deps.logBridgeEvent('bridge_dispatch_failure', {
         action: request.action,
@@ -205,7 +235,18 @@ export class BridgeRuntime {
       };
     }
 
+    await this.cleanupSessionAgents();
     this.agent = instance;
+    this.agentOptions = options;
+    this.topics?.close();
+    this.topics =
+      options.accountId && options.privateKey
+        ? new TopicService({
+            accountId: options.accountId,
+            privateKey: options.privateKey,
+            network: options.network === 'mainnet' ? 'mainnet' : 'testnet',
+          })
+        : null;
     this.deps.writeStderr('Bridge initialize success');
 
     return {
@@ -234,6 +275,18 @@ export class BridgeRuntime {
       };
     }
 
+    let agent: ConversationalAgentInstance;
+    let answeredBy: AgentAnsweredBy;
+    try {
+      ({ agent, answeredBy } = await this.agentFor(payload.llm));
+    } catch (error) {
+      return {
+        id: null,
+        success: false,
+        error: `Failed to switch model: ${(error as Error).message ?? String(error)}`,
+      };
+    }
+
     const history = this.normalizeHistory(payload.chatHistory);
     const attachments = Array.isArray(payload.attachments)
       ? payload.attachments
@@ -245,16 +298,20 @@ export class BridgeRuntime {
 
     if (payload.formSubmission) {
       const submission = this.buildFormSubmission(payload.formSubmission, history);
-      result = await this.agent.processFormSubmission(submission);
+      result = await agent.processFormSubmission(submission);
     } else {
       const content = await this.buildMessageContent(payload, normalizedAttachments);
       const augmentedHistory = this.applyPageContext(history, pageContext);
-      result = await this.agent.processMessage(content, augmentedHistory);
+      result = await agent.processMessage(content, augmentedHistory);
     }
 
     result = await this.inscriptionService.ensureJsonTopicMetadata(result);
     const normalizedResult = this.inscriptionService.rewriteHashLinkTopic(result);
-    const responsePayload = this.toResponsePayload(normalizedResult, attachments);
+    const responsePayload = this.toResponsePayload(
+      normalizedResult,
+      attachments,
+      answeredBy
+    );
 
     const normalizedRecord = toRecord(responsePayload.response);
     const metadataRecord = normalizedRecord
@@ -292,7 +349,83 @@ export class BridgeRuntime {
     };
   }
 
+  /**
+   * The agent to answer with, and its provider and model: the initialized
+   * one, or one built from the same options for the provider and model the
+   * session picked. Those are kept per provider and model, and rebuilt when
+   * the key changes.
+   */
+  private async agentFor(
+    llm: AgentLlmOverride | undefined
+  ): Promise<{ agent: ConversationalAgentInstance; answeredBy: AgentAnsweredBy }> {
+    const options = this.agentOptions;
+    const base = {
+      agent: this.agent as ConversationalAgentInstance,
+      answeredBy: {
+        provider: options?.llmProvider ?? 'openai',
+        model: options?.openAIModelName,
+      },
+    };
+    const provider = normalizeLlmProvider(llm?.provider);
+    const modelName =
+      typeof llm?.modelName === 'string' && llm.modelName.trim().length > 0
+        ? llm.modelName.trim()
+        : undefined;
+    const apiKey = typeof llm?.apiKey === 'string' ? llm.apiKey.trim() : '';
+    if (!options || !provider || apiKey.length === 0) {
+      return base;
+    }
+    if (
+      provider === (options.llmProvider ?? 'openai') &&
+      modelName === options.openAIModelName &&
+      apiKey === options.openAIApiKey
+    ) {
+      return base;
+    }
+
+    const answeredBy = { provider, model: modelName };
+    const key = `${provider}:${modelName ?? ''}`;
+    const cached = this.sessionAgents.get(key);
+    if (cached && cached.apiKey === apiKey) {
+      return { agent: cached.instance, answeredBy };
+    }
+    if (cached) {
+      this.sessionAgents.delete(key);
+      await this.cleanupAgent(cached.instance);
+    }
+
+    const instance = new ConversationalAgent({
+      ...options,
+      llmProvider: provider,
+      openAIApiKey: apiKey,
+      openAIModelName: modelName,
+    });
+    await instance.initialize();
+    this.sessionAgents.set(key, { apiKey, instance });
+    this.deps.writeStderr('Bridge initialized session agent', { provider, modelName });
+    return { agent: instance, answeredBy };
+  }
+
+  private async cleanupSessionAgents(): Promise<void> {
+    const agents = Array.from(this.sessionAgents.values());
+    this.sessionAgents.clear();
+    for (const agent of agents) {
+      await this.cleanupAgent(agent.instance);
+    }
+  }
+
+  private async cleanupAgent(agent: ConversationalAgentInstance): Promise<void> {
+    try {
+      if (typeof (agent as { cleanup?: () => Promise<void> }).cleanup === 'function') {
+        await (agent as { cleanup: () => Promise<void> }).cleanup();
+      }
+    } catch (error) {
+      this.deps.writeStderr('Bridge disconnect cleanup error', error);
+    }
+  }
+
   private async handleDisconnect(): Promise<BridgeResponse> {
+    await this.cleanupSessionAgents();
     if (!this.agent) {
       return {
         id: null,
@@ -301,15 +434,12 @@ export class BridgeRuntime {
       };
     }
 
-    try {
-      if (typeof (this.agent as { cleanup?: () => Promise<void> }).cleanup === 'function') {
-        await (this.agent as { cleanup: () => Promise<void> }).cleanup();
-      }
-    } catch (error) {
-      this.deps.writeStderr('Bridge disconnect cleanup error', error);
-    }
+    await this.cleanupAgent(this.agent);
 
     this.agent = null;
+    this.agentOptions = null;
+    this.topics?.close();
+    this.topics = null;
     return {
       id: null,
       success: true,
@@ -317,6 +447,52 @@ export class BridgeRuntime {
     };
   }
 
+  private async handleCreateTopic(
+    payload: TopicCreatePayload | undefined
+  ): Promise<BridgeResponse> {
+    if (!this.topics) {
+      return {
+        id: null,
+        success: false,
+        error: 'Creating topics requires a Hedera private key',
+      };
+    }
+
+    const topicId = await this.topics.createPrivateTopic(payload?.memo ?? '');
+    return {
+      id: null,
+      success: true,
+      data: { topicId },
+    };
+  }
+
+  private async handleSubmitTopicMessage(
+    payload: TopicSubmitPayload | undefined
+  ): Promise<BridgeResponse> {
+    if (!this.topics) {
+      return {
+        id: null,
+        success: false,
+        error: 'Submitting topic messages requires a Hedera private key',
+      };
+    }
+
+    if (!payload?.topicId || typeof payload.message !== 'string') {
+      return {
+        id: null,
+        success: false,
+        error: 'Missing topic message payload',
+      };
+    }
+
+    const sequenceNumber = await this.topics.submitMessage(payload.topicId, payload.message);
+    return {
+      id: null,
+      success: true,
+      data: { sequenceNumber },
+    };
+  }
+
   private handleStatus(): BridgeResponse {
     return {
       id: null,
@@ -356,19 +532,6 @@ export class BridgeRuntime {
       return 'returnBytes';
     };
 
-    const normalizeLlmProvider = (
-      provider?: string
-    ): 'openai' | 'anthropic' | 'openrouter' | undefined => {
-      if (typeof provider !== 'string') {
-        return undefined;
-      }
-      const normalized = provider.trim().toLowerCase();
-      if (normalized === 'openai' || normalized === 'anthropic' || normalized === 'openrouter') {
-        return normalized;
-      }
-      return undefined;
-    };
-
     const accountId =
       typeof payload.accountId === 'string' ? payload.accountId.trim() : '';
     const privateKey =
@@ -404,6 +567,20 @@ export class BridgeRuntime {
       payload.openRouterBaseURL.trim().length > 0
         ? payload.openRouterBaseURL
         : undefined;
+    const openAIBaseURL =
+      typeof payload.openAIBaseURL === 'string' &&
+      payload.openAIBaseURL.trim().length > 0
+        ? payload.openAIBaseURL.trim()
+        : undefined;
+    // The OpenAI client reads its endpoint from OPENAI_BASE_URL, which lets a
+    // self-hosted OpenAI-compatible gateway stand in for api.openai.com.
+    if (openAIBaseURL) {
+      process.env.OPENAI_BASE_URL = openAIBaseURL;
+    } else if (inheritedOpenAIBaseURL) {
+      process.env.OPENAI_BASE_URL = inheritedOpenAIBaseURL;
+    } else {
+      delete process.env.OPENAI_BASE_URL;
+    }
         
     const additionalPlugins: BasePlugin[] = [];
     if (Array.isArray(payload.additionalPlugins)) {
@@ -555,12 +732,14 @@ export class BridgeRuntime {
 
   private toResponsePayload(
     result: AgentProcessResult,
-    attachments: ReadonlyArray<AttachmentDescriptor>
+    attachments: ReadonlyArray<AttachmentDescriptor>,
+    answeredBy: AgentAnsweredBy
   ): AgentResponsePayload {
     return {
       response: {
         ...result,
         metadata: result.metadata ?? null,
+        answeredBy,
       },
       attachments,
     };
@@ -569,6 +748,19 @@ export class BridgeRuntime {
 
 type FormSubmissionPayload = NonNullable<AgentMessagePayload['formSubmission']>;
 
+const normalizeLlmProvider = (
+  provider?: string
+): 'openai' | 'anthropic' | 'openrouter' | undefined => {
+  if (typeof provider !== 'string') {
+    return undefined;
+  }
+  const normalized = provider.trim().toLowerCase();
+  if (normalized === 'openai' || normalized === 'anthropic' || normalized === 'openrouter') {
+    return normalized;
+  }
+  return undefined;
+};
+
 const getStringField = (
   value: unknown,
   key: string
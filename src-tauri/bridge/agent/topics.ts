@@ -0,0 +1,62 @@
+import {
+  Client,
+  PrivateKey,
+  TopicCreateTransaction,
+  TopicMessageSubmitTransaction,
+} from '@hashgraph/sdk';
+
+const MAX_MESSAGE_CHUNKS = 20;
+
+export interface TopicOperator {
+  readonly accountId: string;
+  readonly privateKey: string;
+  readonly network: 'mainnet' | 'testnet';
+}
+
+/**
+ * Creates and writes to private topics owned by the operator. The submit key
+ * is the operator key, so only devices holding it can publish.
+ */
+export class TopicService {
+  private readonly client: Client;
+  private readonly operatorKey: PrivateKey;
+
+  constructor(operator: TopicOperator) {
+    this.operatorKey = PrivateKey.fromString(operator.privateKey);
+    this.client = Client.forName(operator.network).setOperator(
+      operator.accountId,
+      this.operatorKey
+    );
+  }
+
+  async createPrivateTopic(memo: string): Promise<string> {
+    const response = await new TopicCreateTransaction()
+      .setTopicMemo(memo)
+      .setAdminKey(this.operatorKey.publicKey)
+      .setSubmitKey(this.operatorKey.publicKey)
+      .execute(this.client);
+    const receipt = await response.getReceipt(this.client);
+    if (!receipt.topicId) {
+      throw new Error('Topic creation did not return a topic ID');
+    }
+    return receipt.topicId.toString();
+  }
+
+  async submitMessage(topicId: string, message: string): Promise<number> {
+    const responses = await new TopicMessageSubmitTransaction()
+      .setTopicId(topicId)
+      .setMessage(message)
+      .setMaxChunks(MAX_MESSAGE_CHUNKS)
+      .executeAll(this.client);
+    const last = responses[responses.length - 1];
+    if (!last) {
+      throw new Error('Topic message submission returned no response');
+    }
+    const receipt = await last.getReceipt(this.client);
+    return receipt.topicSequenceNumber?.toNumber() ?? 0;
+  }
+
+  close(): void {
+    this.client.close();
+  }
+}
@@ -35,6 +35,7 @@ export interface AgentInitializePayload {
   readonly network?: string;
   readonly openAIApiKey?: string;
   readonly openAIModelName?: string;
+  readonly openAIBaseURL?: string;
   readonly llmProvider?: string;
   readonly userAccountId?: string;
   readonly operationalMode?: string;
@@ -47,18 +48,55 @@ export interface AgentInitializePayload {
   readonly additionalPlugins?: Array<{ pluginType: string; config: Record<string, unknown>}>;
 }
 
+export interface AgentLlmOverride {
+  readonly provider?: string;
+  readonly apiKey?: string;
+  readonly modelName?: string;
+}
+
+/** The provider and model of the agent that produced a response. */
+export interface AgentAnsweredBy {
+  readonly provider: string;
+  readonly model?: string;
+}
+
 export interface AgentMessagePayload {
   readonly sessionId?: string;
   readonly content?: string;
   readonly chatHistory?: ReadonlyArray<AgentHistoryEntry>;
   readonly attachments?: ReadonlyArray<AttachmentDescriptor>;
   readonly formSubmission?: FormSubmissionPayload;
+  readonly llm?: AgentLlmOverride;
+}
+
+export interface TopicCreatePayload {
+  readonly memo?: string;
+}
+
+export interface TopicSubmitPayload {
+  readonly topicId?: string;
+  readonly message?: string;
 }
 
 export interface BridgeRequest {
   readonly id?: number;
-  readonly action: 'initialize' | 'sendMessage' | 'status' | 'disconnect';
-  readonly payload?: AgentInitializePayload | AgentMessagePayload;
+  readonly action:
+    | 'initialize'
+    | 'sendMessage'
+    | 'status'
+    | 'disconnect'
+    | 'createTopic'
+    | 'submitTopicMessage';
+  readonly payload?:
+    | AgentInitializePayload
+    | AgentMessagePayload
+    | TopicCreatePayload
+    | TopicSubmitPayload;
+}
+
+export interface ProviderRateLimitHeaders {
+  readonly provider: string;
+  readonly headers: Record<string, string>;
 }
 
 export interface BridgeResponse {
@@ -66,6 +104,7 @@ export interface BridgeResponse {
   readonly success: boolean;
   readonly data?: Record<string, unknown> | null;
   readonly error?: string;
+  readonly rateLimits?: ReadonlyArray<ProviderRateLimitHeaders>;
 }
 
 export interface BridgeResponsePayload {
@@ -104,6 +143,7 @@ export interface AgentProcessResult {
   readonly message?: string;
   readonly output?: string;
   readonly metadata?: Record<string, unknown> | null;
+  readonly answeredBy?: AgentAnsweredBy;
   readonly transactionId?: string;
   readonly scheduleId?: string;
   readonly notes?: string | string[];
@@ -16,6 +16,8 @@ import {
   HCS10ProfileSchema,
   type HCS10ProfileFormData,
   type HederaCredentials,
+  assertAcceptConnectionRequestPayload,
+  assertFetchConnectionRequestsPayload,
   assertRegisterProfilePayload,
   assertRetrieveProfilePayload,
 } from './hcs10-schemas';
@@ -26,7 +28,9 @@ interface BridgeRequest {
     | 'hcs10_register_profile'
     | 'hcs10_validate_profile'
     | 'hcs10_retrieve_profile'
-    | 'hcs10_cancel_registration';
+    | 'hcs10_cancel_registration'
+    | 'hcs10_fetch_connection_requests'
+    | 'hcs10_accept_connection_request';
   readonly payload?: Record<string, unknown>;
 }
 
@@ -91,6 +95,9 @@ rl.on('line', async (line) => {
 
   try {
     switch (request.action) {
+      case 'ping':
+        send({ id: request.id ?? null, type: 'result', success: true, data: { pong: true } });
+        break;
       case 'hcs10_register_profile':
         await handleRegisterProfile(request);
         break;
@@ -103,6 +110,12 @@ rl.on('line', async (line) => {
       case 'hcs10_cancel_registration':
         await handleCancelRegistration(request);
         break;
+      case 'hcs10_fetch_connection_requests':
+        await handleFetchConnectionRequests(request);
+        break;
+      case 'hcs10_accept_connection_request':
+        await handleAcceptConnectionRequest(request);
+        break;
       default:
         send({
           id: request.id ?? null,
@@ -129,6 +142,16 @@ function resolveNetwork(network?: string): 'mainnet' | 'testnet' {
   return network === 'mainnet' ? 'mainnet' : 'testnet';
 }
 
+function createClient(hedera: HederaCredentials): HCS10Client {
+  return new HCS10Client({
+    network: resolveNetwork(hedera.network),
+    operatorId: hedera.accountId,
+    operatorPrivateKey: hedera.privateKey,
+    logLevel: 'info',
+    prettyPrint: false,
+  });
+}
+
 function slugifyName(name: string): string {
   return `${name.toLowerCase().replace(/[^a-z0-9]/g, '-')}-${Date.now()}`;
 }
@@ -481,6 +504,80 @@ async function handleCancelRegistration(request: BridgeRequest): Promise<void> {
   });
 }
 
+async function handleFetchConnectionRequests(request: BridgeRequest): Promise<void> {
+  const payload = assertFetchConnectionRequestsPayload(request.payload);
+  const client = createClient(payload.hedera);
+
+  let inboundTopicId = payload.inboundTopicId;
+  if (!inboundTopicId) {
+    const profile = await client.retrieveProfile(payload.hedera.accountId);
+    inboundTopicId = profile?.topicInfo?.inboundTopic;
+  }
+  if (!inboundTopicId) {
+    throw new Error(`No HCS-10 inbound topic found for ${payload.hedera.accountId}`);
+  }
+
+  const afterSequence = payload.afterSequence ?? 0;
+  const { messages } = await client.getMessages(inboundTopicId);
+  const requests: Array<Record<string, unknown>> = [];
+  const handled: Record<string, string> = {};
+
+  for (const message of messages) {
+    if (message.op === 'connection_created' && message.connection_id != null) {
+      handled[String(message.connection_id)] = message.connection_topic_id ?? '';
+      continue;
+    }
+    if (
+      message.op !== 'connection_request' ||
+      !message.operator_id ||
+      (message.sequence_number ?? 0) <= afterSequence
+    ) {
+      continue;
+    }
+    requests.push({
+      sequenceNumber: message.sequence_number,
+      requesterAccountId: client.extractAccountFromOperatorId(message.operator_id),
+      requesterInboundTopicId: client.extractTopicFromOperatorId(message.operator_id),
+      memo: message.m ?? null,
+      createdAt: message.created ? new Date(message.created).toISOString() : null,
+    });
+  }
+
+  send({
+    id: request.id ?? null,
+    type: 'result',
+    success: true,
+    data: {
+      inboundTopicId,
+      requests: requests.map((entry) => ({
+        ...entry,
+        connectionTopicId: handled[String(entry.sequenceNumber)] || null,
+      })),
+    },
+  });
+}
+
+async function handleAcceptConnectionRequest(request: BridgeRequest): Promise<void> {
+  const payload = assertAcceptConnectionRequestPayload(request.payload);
+  const client = createClient(payload.hedera);
+
+  const result = await client.handleConnectionRequest(
+    payload.inboundTopicId,
+    payload.requesterAccountId,
+    payload.sequenceNumber
+  );
+
+  send({
+    id: request.id ?? null,
+    type: 'result',
+    success: true,
+    data: {
+      connectionTopicId: result.connectionTopicId,
+      confirmedSequenceNumber: result.confirmedConnectionSequenceNumber,
+    },
+  });
+}
+
 rl.on('close', () => {
   if (currentAbortController) {
     currentAbortController.abort();
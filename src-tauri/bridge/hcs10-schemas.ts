@@ -89,8 +89,23 @@ const retrieveProfilePayloadSchema = z.object({
   hedera: HederaCredentialsSchema,
 });
 
+const fetchConnectionRequestsPayloadSchema = z.object({
+  hedera: HederaCredentialsSchema,
+  inboundTopicId: z.string().optional(),
+  afterSequence: z.number().int().nonnegative().optional(),
+});
+
+const acceptConnectionRequestPayloadSchema = z.object({
+  hedera: HederaCredentialsSchema,
+  inboundTopicId: z.string().min(1, 'Inbound topic ID is required'),
+  requesterAccountId: z.string().min(1, 'Requester account ID is required'),
+  sequenceNumber: z.number().int().positive(),
+});
+
 export type RegisterProfilePayload = z.infer<typeof registerProfilePayloadSchema>;
 export type RetrieveProfilePayload = z.infer<typeof retrieveProfilePayloadSchema>;
+export type FetchConnectionRequestsPayload = z.infer<typeof fetchConnectionRequestsPayloadSchema>;
+export type AcceptConnectionRequestPayload = z.infer<typeof acceptConnectionRequestPayloadSchema>;
 
 function formatIssues(issues: readonly z.ZodIssue[]): string {
   return issues
@@ -116,3 +131,27 @@ export function assertRetrieveProfilePayload(payload: unknown): RetrieveProfileP
   }
   return result.data;
 }
+
+export function assertFetchConnectionRequestsPayload(
+  payload: unknown
+): FetchConnectionRequestsPayload {
+  const result = fetchConnectionRequestsPayloadSchema.safeParse(payload);
+  if (!result.success) {
+    throw new Error(
+      `Invalid fetch connection requests payload: ${formatIssues(result.error.issues)}`
+    );
+  }
+  return result.data;
+}
+
+export function assertAcceptConnectionRequestPayload(
+  payload: unknown
+): AcceptConnectionRequestPayload {
+  const result = acceptConnectionRequestPayloadSchema.safeParse(payload);
+  if (!result.success) {
+    throw new Error(
+      `Invalid accept connection request payload: ${formatIssues(result.error.issues)}`
+    );
+  }
+  return result.data;
+}
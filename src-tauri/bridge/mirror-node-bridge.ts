@@ -1,5 +1,6 @@
 
 
+import * as zlib from 'node:zlib';
 import { HederaMirrorNode, Logger } from '@hashgraphonline/standards-sdk';
 
 type MirrorNetwork = 'mainnet' | 'testnet';
@@ -7,6 +8,7 @@ type MirrorNetwork = 'mainnet' | 'testnet';
 interface MirrorNodeSchedulePayload {
   readonly scheduleId?: string;
   readonly network?: MirrorNetwork;
+  readonly mirrorUrl?: string;
 }
 
 interface MirrorNodeTimestampPayload {
@@ -16,10 +18,27 @@ interface MirrorNodeTimestampPayload {
 
 interface MirrorNodeTransactionPayload {
   readonly transactionId?: string;
+  readonly tokenId?: string;
   readonly network?: MirrorNetwork;
 }
 
-type BridgePayload = MirrorNodeSchedulePayload | MirrorNodeTimestampPayload | MirrorNodeTransactionPayload;
+interface MirrorNodeEntityPayload {
+  readonly accountId?: string;
+  readonly topicId?: string;
+  readonly network?: MirrorNetwork;
+}
+
+interface DecompressPayload {
+  readonly algorithm?: string;
+  readonly data?: string;
+}
+
+type BridgePayload =
+  | MirrorNodeSchedulePayload
+  | MirrorNodeTimestampPayload
+  | MirrorNodeTransactionPayload
+  | MirrorNodeEntityPayload
+  | DecompressPayload;
 
 interface BridgeRequest {
   readonly id?: number;
@@ -28,7 +47,10 @@ interface BridgeRequest {
     | 'mirror_node_get_scheduled_transaction_status'
     | 'mirror_node_get_transaction_by_timestamp'
     | 'mirror_node_get_transaction'
-    | 'mirror_node_get_token_info';
+    | 'mirror_node_get_token_info'
+    | 'mirror_node_get_account_info'
+    | 'mirror_node_get_topic_info'
+    | 'mirror_node_decompress';
   readonly payload?: BridgePayload;
 }
 
@@ -59,6 +81,35 @@ function assertTransactionPayload(payload: BridgePayload | undefined): asserts p
   }
 }
 
+function requireString(payload: BridgePayload | undefined, key: string): string {
+  const value = payload ? (payload as Record<string, unknown>)[key] : undefined;
+  if (typeof value !== 'string' || value.trim().length === 0) {
+    throw new Error(`${key} is required`);
+  }
+  return value.trim();
+}
+
+type Decompressor = (input: Buffer) => Buffer;
+
+function resolveDecompressor(algorithm: string): Decompressor {
+  switch (algorithm) {
+    case 'zstd': {
+      // Added to node:zlib in Node 22.15; older runtimes cannot read zstd.
+      const zstd = (zlib as unknown as { zstdDecompressSync?: Decompressor }).zstdDecompressSync;
+      if (!zstd) {
+        throw new Error(`zstd decompression needs Node 22.15 or later (running ${process.version})`);
+      }
+      return zstd;
+    }
+    case 'brotli':
+      return zlib.brotliDecompressSync;
+    case 'gzip':
+      return zlib.gunzipSync;
+    default:
+      throw new Error(`Unsupported compression: ${algorithm}`);
+  }
+}
+
 function resolveNetwork(network?: MirrorNetwork): MirrorNetwork {
   if (network === 'mainnet') {
     return 'mainnet';
@@ -122,9 +173,8 @@ async function handleRequest(request: BridgeRequest): Promise<BridgeResponse> {
       };
     }
     case 'mirror_node_get_token_info': {
-      assertTransactionPayload(request.payload);
-      const tokenPayload = request.payload as MirrorNodeTransactionPayload;
-      const tokenId = tokenPayload.transactionId!;
+      const tokenPayload = (request.payload ?? {}) as MirrorNodeTransactionPayload;
+      const tokenId = tokenPayload.tokenId ?? requireString(request.payload, 'transactionId');
       const service = buildMirrorNode(tokenPayload.network);
       const tokenInfo = await service.getTokenInfo(tokenId);
       return {
@@ -132,6 +182,48 @@ async function handleRequest(request: BridgeRequest): Promise<BridgeResponse> {
         success: true,
         data: tokenInfo ?? null,
       };
+    }
+    case 'mirror_node_get_account_info': {
+      const accountId = requireString(request.payload, 'accountId');
+      const network = (request.payload as MirrorNodeEntityPayload).network;
+      const account = await buildMirrorNode(network).requestAccount(accountId);
+      return {
+        id: request.id ?? null,
+        success: true,
+        data: account ?? null,
+      };
+    }
+    case 'mirror_node_decompress': {
+      const algorithm = requireString(request.payload, 'algorithm').toLowerCase();
+      const data = Buffer.from(requireString(request.payload, 'data'), 'base64');
+      const decompressed = resolveDecompressor(algorithm)(data);
+      return {
+        id: request.id ?? null,
+        success: true,
+        data: { data: decompressed.toString('base64') },
+      };
+    }
+    case 'mirror_node_get_topic_info': {
+      const topicId = requireString(request.payload, 'topicId');
+      const payload = request.payload as MirrorNodeEntityPayload;
+      const network = resolveNetwork(payload.network);
+      const mirrorUrl = payload.mirrorUrl ?? `https://${network}.mirrornode.hedera.com/api/v1`;
+      const service = buildMirrorNode(network);
+      const topic = await service.getTopicInfo(topicId);
+      // The topic endpoint has no running sequence number; the newest message does.
+      const latest = await fetch(
+        `${mirrorUrl}/topics/${topicId}/messages?order=desc&limit=1`
+      );
+      const messages = latest.ok
+        ? ((await latest.json()) as { messages?: Array<{ sequence_number?: number }> }).messages
+        : undefined;
+      return {
+        id: request.id ?? null,
+        success: true,
+        data: topic
+          ? { ...topic, sequence_number: messages?.[0]?.sequence_number ?? 0 }
+          : null,
+      };
     }
       default: {
         return {
@@ -0,0 +1,183 @@
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::connection::HederaMirrorConfig;
+use crate::entity_id::parse_entity_id;
+use crate::mirror::MirrorNetwork;
+use crate::proxy;
+
+const DEFAULT_PAGE_LIMIT: u32 = 25;
+const MAX_PAGE_LIMIT: u32 = 100;
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountTransactionsPage {
+    pub account_id: String,
+    /// Mirror node transaction records, newest first.
+    pub transactions: Vec<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MirrorTransactionsResponse {
+    #[serde(default)]
+    transactions: Vec<Value>,
+    #[serde(default)]
+    links: MirrorLinks,
+}
+
+#[derive(Default, Deserialize)]
+struct MirrorLinks {
+    #[serde(default)]
+    next: Option<String>,
+}
+
+/// An account's transaction history from the mirror node `/transactions`
+/// endpoint, one page at a time.
+pub struct AccountHistoryService {
+    client: Client,
+    mirror_config: HederaMirrorConfig,
+}
+
+impl AccountHistoryService {
+    pub fn new() -> Result<Self, String> {
+        Self::with_mirror_config(HederaMirrorConfig::default())
+    }
+
+    pub fn with_mirror_config(mirror_config: HederaMirrorConfig) -> Result<Self, String> {
+        let client = proxy::client_builder()
+            .user_agent("hol-desktop-tauri/0.0.1")
+            .build()
+            .map_err(|error| error.to_string())?;
+        Ok(Self {
+            client,
+            mirror_config,
+        })
+    }
+
+    /// `cursor` is the `next` value of a previous page.
+    pub async fn get_page(
+        &self,
+        account_id: &str,
+        network: MirrorNetwork,
+        limit: Option<u32>,
+        cursor: Option<&str>,
+    ) -> Result<AccountTransactionsPage, String> {
+        let account_id = parse_entity_id(account_id, network.as_str())?.to_string();
+        let base = match network {
+            MirrorNetwork::Mainnet => self.mirror_config.mainnet.trim_end_matches('/'),
+            MirrorNetwork::Testnet => self.mirror_config.testnet.trim_end_matches('/'),
+        };
+        let url = match cursor {
+            Some(cursor) => {
+                let suffix = cursor
+                    .find("/transactions?")
+                    .map(|index| &cursor[index..])
+                    .filter(|suffix| suffix.contains(&format!("account.id={account_id}")))
+                    .ok_or_else(|| "Invalid transaction history cursor".to_string())?;
+                format!("{base}{suffix}")
+            }
+            None => {
+                let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT);
+                format!("{base}/transactions?account.id={account_id}&order=desc&limit={limit}")
+            }
+        };
+
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|error| format!("Failed to contact Hedera mirror node: {error}"))?;
+        if !response.status().is_success() {
+            return Err(format!(
+                "Mirror node returned {} for account {account_id} transactions",
+                response.status()
+            ));
+        }
+        let body: MirrorTransactionsResponse = response
+            .json()
+            .await
+            .map_err(|error| format!("Failed to parse mirror node transactions: {error}"))?;
+
+        Ok(AccountTransactionsPage {
+            account_id,
+            transactions: body.transactions,
+            next: body.links.next.filter(|next| !next.is_empty()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::MockServer;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn pages_follow_the_mirror_cursor() {
+        let server = MockServer::start();
+        let next = "/api/v1/transactions?account.id=0.0.100&order=desc&limit=2&timestamp=lt:2.0";
+        server.mock(|when, then| {
+            when.method("GET")
+                .path("/api/v1/transactions")
+                .query_param("account.id", "0.0.100")
+                .query_param("limit", "1");
+            then.status(200).json_body(json!({
+                "transactions": [{ "transaction_id": "0.0.100-2-0" }],
+                "links": { "next": next }
+            }));
+        });
+        server.mock(|when, then| {
+            when.method("GET")
+                .path("/api/v1/transactions")
+                .query_param("limit", "2")
+                .query_param("timestamp", "lt:2.0");
+            then.status(200).json_body(json!({
+                "transactions": [{ "transaction_id": "0.0.100-1-0" }],
+                "links": { "next": null }
+            }));
+        });
+
+        let service = AccountHistoryService::with_mirror_config(HederaMirrorConfig {
+            mainnet: server.url("/api/v1"),
+            testnet: server.url("/api/v1"),
+        })
+        .unwrap();
+        let first = service
+            .get_page("0.0.100", MirrorNetwork::Testnet, Some(1), None)
+            .await
+            .unwrap();
+        assert_eq!(first.transactions.len(), 1);
+        assert_eq!(first.next.as_deref(), Some(next));
+
+        let second = service
+            .get_page(
+                "0.0.100",
+                MirrorNetwork::Testnet,
+                None,
+                first.next.as_deref(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            second.transactions[0]["transaction_id"],
+            json!("0.0.100-1-0")
+        );
+        assert!(second.next.is_none());
+
+        assert!(
+            service
+                .get_page(
+                    "0.0.100",
+                    MirrorNetwork::Testnet,
+                    None,
+                    Some("/api/v1/tokens/x")
+                )
+                .await
+                .is_err()
+        );
+    }
+}
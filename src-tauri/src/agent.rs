@@ -6,8 +6,13 @@ use std::sync::Arc;
 use tokio::sync::Mutex;
 
 use crate::AgentBackend;
+use crate::BridgeTimeout;
+use crate::agent_registry::{AgentBackendRegistry, BackendDescriptor};
 use crate::agent_services::{InitializationService, MessageService};
-use crate::session::SessionService;
+use crate::config::{AppConfig, LlmProvider};
+use crate::rate_limit::ProviderRateLimiter;
+use crate::secret::Secret;
+use crate::session::{SessionLlm, SessionService};
 use crate::wallet_bridge::{WalletBridgeInfo, WalletBridgeState};
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -21,12 +26,16 @@ pub struct AdditionalPluginConfig {
 #[serde(rename_all = "camelCase")]
 pub struct AgentInitializeConfig {
     pub account_id: String,
-    pub private_key: String,
+    pub private_key: Secret,
     pub network: String,
     #[serde(rename = "openAIApiKey")]
-    pub open_ai_api_key: String,
+    pub open_ai_api_key: Secret,
     #[serde(default)]
     pub model_name: Option<String>,
+    /// OpenAI-compatible endpoint to use instead of api.openai.com. Taken
+    /// from the saved OpenAI settings when not given.
+    #[serde(default, rename = "openAIBaseURL")]
+    pub open_ai_base_url: Option<String>,
     #[serde(default)]
     pub llm_provider: Option<String>,
     #[serde(default)]
@@ -35,6 +44,10 @@ pub struct AgentInitializeConfig {
     pub operational_mode: Option<String>,
     #[serde(default)]
     pub mcp_servers: Option<Value>,
+    /// Name of an MCP server group to hand the agent when `mcp_servers` is
+    /// not given, instead of every configured server.
+    #[serde(default)]
+    pub mcp_group: Option<String>,
     #[serde(default)]
     pub verbose: Option<bool>,
     #[serde(default)]
@@ -43,6 +56,9 @@ pub struct AgentInitializeConfig {
     pub disabled_plugins: Option<Vec<String>>,
     #[serde(default)]
     pub additional_plugins: Option<Vec<AdditionalPluginConfig>>,
+    /// Id of the registered backend to run this session on.
+    #[serde(default)]
+    pub backend: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -69,6 +85,9 @@ pub struct AgentStatusResponse {
     pub session_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub active_messages: Option<usize>,
+    /// The running backend and what it supports.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<BackendDescriptor>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -83,6 +102,78 @@ pub struct AgentMessageRequest {
     pub attachments: Option<Vec<Attachment>>,
     #[serde(default)]
     pub form_submission: Option<FormSubmission>,
+    /// LLM to answer with instead of the one the agent was initialized with.
+    /// Filled in from the chat session, never by the frontend.
+    #[serde(skip)]
+    pub llm: Option<AgentLlmSettings>,
+}
+
+/// The LLM the agent session was initialized with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AgentLlmSettings {
+    pub provider: LlmProvider,
+    pub api_key: Secret,
+    pub model: Option<String>,
+    /// Replaces the OpenAI endpoint when set.
+    pub base_url: Option<String>,
+}
+
+impl AgentLlmSettings {
+    /// The settings for a session that picked its own provider and model.
+    /// The key is the running one when the provider matches, else the one
+    /// saved in `config`.
+    pub fn for_session(
+        session: &SessionLlm,
+        current: Option<&AgentLlmSettings>,
+        config: &AppConfig,
+    ) -> Result<Self, String> {
+        let (api_key, base_url) = match current {
+            Some(current) if current.provider == session.provider => {
+                (current.api_key.clone(), current.base_url.clone())
+            }
+            _ => Self::saved(session.provider, config),
+        };
+        if api_key.expose().trim().is_empty() {
+            return Err(format!(
+                "No API key is configured for {:?}, which this session uses",
+                session.provider
+            ));
+        }
+        Ok(Self {
+            provider: session.provider,
+            api_key,
+            model: Some(session.model.clone()),
+            base_url,
+        })
+    }
+
+    /// These settings with the key saved in `config` for their provider.
+    /// The running session carries a single key, which the renderer may have
+    /// taken from another provider's settings; it is kept only when nothing
+    /// is saved.
+    pub fn with_saved_key(self, config: &AppConfig) -> Self {
+        let (api_key, base_url) = Self::saved(self.provider, config);
+        if api_key.expose().trim().is_empty() {
+            return self;
+        }
+        Self {
+            api_key,
+            base_url: base_url.or(self.base_url),
+            ..self
+        }
+    }
+
+    fn saved(provider: LlmProvider, config: &AppConfig) -> (Secret, Option<String>) {
+        match provider {
+            LlmProvider::Openai => (
+                config.openai.api_key.clone(),
+                config.openai.base_url.clone(),
+            ),
+            LlmProvider::Anthropic => (config.anthropic.api_key.clone(), None),
+            LlmProvider::Openrouter => (config.openrouter.api_key.clone(), None),
+            LlmProvider::Gemini => (config.gemini.api_key.clone(), None),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -91,6 +182,9 @@ pub struct ChatEntry {
     #[serde(rename = "type")]
     pub entry_type: String,
     pub content: String,
+    /// Id of the stored message the entry stands for, when it has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -126,6 +220,9 @@ pub struct AgentMessageResponse {
     pub response: Option<AgentMessageData>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// Set when the bridge never answered, so the UI can offer a retry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<BridgeTimeout>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -156,6 +253,20 @@ pub struct AgentSessionContext {
     pub topic_id: Option<String>,
 }
 
+/// Sent with `agent_bridge_restarted` once a dead or hung bridge has been
+/// replaced.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgeRestart {
+    /// `agent` or `hcs10`.
+    pub bridge: String,
+    pub reason: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_context: Option<AgentSessionContext>,
+}
+
 impl AgentService {
     pub fn new() -> Self {
         Self::with_bridge(None)
@@ -169,6 +280,13 @@ impl AgentService {
         }
     }
 
+    /// Shares `rate_limiter` with the bridge so provider headers feed it and
+    /// outgoing messages wait on it.
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<ProviderRateLimiter>) -> Self {
+        self.initialization.get_mut().set_rate_limiter(rate_limiter);
+        self
+    }
+
     pub async fn initialize(
         &self,
         config: AgentInitializeConfig,
@@ -187,6 +305,32 @@ impl AgentService {
         initialization.status(active_messages)
     }
 
+    pub async fn registry(&self) -> Arc<AgentBackendRegistry> {
+        self.initialization.lock().await.registry()
+    }
+
+    /// The registered backends and the id of the running one.
+    pub async fn backends(&self) -> (Vec<BackendDescriptor>, Option<String>) {
+        let initialization = self.initialization.lock().await;
+        (
+            initialization.registry().list(),
+            initialization.backend_id(),
+        )
+    }
+
+    pub async fn switch_backend(
+        &self,
+        backend_id: &str,
+        wallet_bridge: WalletBridgeState,
+        wallet_info: Arc<Mutex<Option<WalletBridgeInfo>>>,
+    ) -> AgentInitializeResponse {
+        self.initialization
+            .lock()
+            .await
+            .switch_backend(backend_id, wallet_bridge, wallet_info)
+            .await
+    }
+
     pub async fn disconnect(&self) {
         {
             let mut initialization = self.initialization.lock().await;
@@ -202,7 +346,7 @@ impl AgentService {
         session_service: &SessionService,
         request: AgentMessageRequest,
     ) -> Result<AgentMessageResponse> {
-        let (session_id, backend) = {
+        let (session_id, backend, throttle) = {
             let initialization = self.initialization.lock().await;
             let session_id = match initialization.session_id() {
                 Some(id) => id.to_string(),
@@ -211,6 +355,7 @@ impl AgentService {
                         success: false,
                         response: None,
                         error: Some("Agent session is not initialized".to_string()),
+                        timeout: None,
                     });
                 }
             };
@@ -222,19 +367,118 @@ impl AgentService {
                         success: false,
                         response: None,
                         error: Some("Agent backend is not available".to_string()),
+                        timeout: None,
                     });
                 }
             };
 
-            (session_id, backend)
+            let throttle = request
+                .llm
+                .as_ref()
+                .map(|llm| llm.provider)
+                .or_else(|| initialization.llm_provider())
+                .map(|provider| (provider, initialization.rate_limiter()));
+            (session_id, backend, throttle)
         };
 
+        if let Some((provider, rate_limiter)) = throttle {
+            rate_limiter.acquire(provider).await;
+        }
+
         self.message_service
             .process(session_service, &session_id, request, backend)
             .await
             .map_err(|error| anyhow!(error))
     }
 
+    /// Restarts the backend when its health check fails. The chat session and
+    /// its context carry over to the new backend. `None` when the backend is
+    /// healthy or the agent is not running.
+    pub async fn restart_if_unhealthy(
+        &self,
+        wallet_bridge: WalletBridgeState,
+        wallet_info: Arc<Mutex<Option<WalletBridgeInfo>>>,
+    ) -> Result<Option<BridgeRestart>, String> {
+        let Some(backend) = self.initialization.lock().await.backend() else {
+            return Ok(None);
+        };
+        let Err(error) = backend.health_check().await else {
+            return Ok(None);
+        };
+        let reason = error.to_string();
+        log::warn!("Agent backend failed its health check: {}", reason);
+
+        let session_id = {
+            let mut initialization = self.initialization.lock().await;
+            // Nothing to restart if the backend was switched or stopped meanwhile.
+            if !initialization
+                .backend()
+                .is_some_and(|current| Arc::ptr_eq(&current, &backend))
+            {
+                return Ok(None);
+            }
+            initialization
+                .restart_backend(wallet_bridge, wallet_info)
+                .await?
+        };
+        Ok(Some(BridgeRestart {
+            bridge: "agent".to_string(),
+            reason,
+            session_id: Some(session_id),
+            session_context: self.session_context().await,
+        }))
+    }
+
+    /// Restarts the backend when the running session uses one of
+    /// `server_ids`, since the agent wires up MCP tools only when it starts.
+    /// `None` when the session uses none of them.
+    pub async fn rewire_mcp_tools(
+        &self,
+        server_ids: &[String],
+        wallet_bridge: WalletBridgeState,
+        wallet_info: Arc<Mutex<Option<WalletBridgeInfo>>>,
+    ) -> Result<Option<BridgeRestart>, String> {
+        let (reason, session_id) = {
+            let mut initialization = self.initialization.lock().await;
+            let Some(server_id) = server_ids
+                .iter()
+                .find(|server_id| initialization.uses_mcp_server(server_id))
+            else {
+                return Ok(None);
+            };
+            let reason = format!("Tools of MCP server {server_id} changed");
+            log::info!("{}; restarting the agent backend", reason);
+            let session_id = initialization
+                .restart_backend(wallet_bridge, wallet_info)
+                .await?;
+            (reason, session_id)
+        };
+        Ok(Some(BridgeRestart {
+            bridge: "agent".to_string(),
+            reason,
+            session_id: Some(session_id),
+            session_context: self.session_context().await,
+        }))
+    }
+
+    /// The active backend, for bridge features outside the chat flow.
+    pub async fn backend(&self) -> Result<Arc<dyn AgentBackend + Send + Sync>, String> {
+        self.initialization
+            .lock()
+            .await
+            .backend()
+            .ok_or_else(|| "Agent backend is not available".to_string())
+    }
+
+    /// The configured LLM, for features that call it directly.
+    pub async fn llm_settings(&self) -> Result<AgentLlmSettings, String> {
+        self.initialization
+            .lock()
+            .await
+            .llm_settings()
+            .ok_or_else(|| "Agent session is not initialized".to_string())
+    }
+
     pub async fn update_session_context(&self, context: AgentSessionContext) {
         let mut guard = self.session_context.lock().await;
         *guard = Some(context);
@@ -269,18 +513,21 @@ mod tests {
     fn sample_config() -> AgentInitializeConfig {
         AgentInitializeConfig {
             account_id: "0.0.1234".to_string(),
-            private_key: "302e...".to_string(),
+            private_key: "302e...".into(),
             network: "testnet".to_string(),
-            open_ai_api_key: "sk-test".to_string(),
+            open_ai_api_key: "sk-test".into(),
             model_name: Some("gpt-test".to_string()),
+            open_ai_base_url: None,
             llm_provider: Some("openai".to_string()),
             user_account_id: None,
             operational_mode: Some("provideBytes".to_string()),
             mcp_servers: None,
+            mcp_group: None,
             verbose: None,
             disable_logging: None,
             disabled_plugins: None,
             additional_plugins: None,
+            backend: None,
         }
     }
 
@@ -314,6 +561,60 @@ mod tests {
         assert!(result.error.unwrap().contains("Account ID"));
     }
 
+    #[test]
+    fn session_llm_uses_the_running_key_or_the_configured_one() {
+        let current = AgentLlmSettings {
+            provider: LlmProvider::Openai,
+            api_key: "sk-running".into(),
+            model: Some("gpt-4o".to_string()),
+            base_url: None,
+        };
+        let mut config = AppConfig::default();
+        config.anthropic.api_key = "sk-ant-saved".into();
+
+        let openai = SessionLlm {
+            provider: LlmProvider::Openai,
+            model: "gpt-4.1-mini".to_string(),
+        };
+        let settings = AgentLlmSettings::for_session(&openai, Some(&current), &config).unwrap();
+        assert_eq!(settings.api_key, "sk-running");
+        assert_eq!(settings.model.as_deref(), Some("gpt-4.1-mini"));
+
+        let anthropic = SessionLlm {
+            provider: LlmProvider::Anthropic,
+            model: "claude-3-7-sonnet-latest".to_string(),
+        };
+        let settings = AgentLlmSettings::for_session(&anthropic, Some(&current), &config).unwrap();
+        assert_eq!(settings.provider, LlmProvider::Anthropic);
+        assert_eq!(settings.api_key, "sk-ant-saved");
+
+        let openrouter = SessionLlm {
+            provider: LlmProvider::Openrouter,
+            model: "meta-llama/llama-3-70b".to_string(),
+        };
+        assert!(AgentLlmSettings::for_session(&openrouter, Some(&current), &config).is_err());
+        config.openrouter.api_key = "sk-or-saved".into();
+        let settings = AgentLlmSettings::for_session(&openrouter, Some(&current), &config).unwrap();
+        assert_eq!(settings.api_key, "sk-or-saved");
+    }
+
+    #[test]
+    fn running_llm_prefers_the_key_saved_for_its_provider() {
+        let running = AgentLlmSettings {
+            provider: LlmProvider::Anthropic,
+            api_key: "sk-openai".into(),
+            model: Some("claude-3-7-sonnet-latest".to_string()),
+            base_url: None,
+        };
+        let mut config = AppConfig::default();
+        assert_eq!(running.clone().with_saved_key(&config), running);
+
+        config.anthropic.api_key = "sk-ant-saved".into();
+        let settings = running.with_saved_key(&config);
+        assert_eq!(settings.api_key, "sk-ant-saved");
+        assert_eq!(settings.model.as_deref(), Some("claude-3-7-sonnet-latest"));
+    }
+
     #[tokio::test]
     async fn send_message_requires_session() {
         let service = AgentService::new();
@@ -327,6 +628,7 @@ mod tests {
                     chat_history: None,
                     attachments: None,
                     form_submission: None,
+                    llm: None,
                 },
             )
             .await
@@ -360,6 +662,11 @@ mod tests {
                 last_message_at: None,
                 is_active: true,
                 messages: vec![],
+                tags: vec![],
+                pinned_message_ids: vec![],
+                last_read_at: None,
+                unread_count: 0,
+                llm: None,
             })
             .await
             .unwrap();
@@ -373,6 +680,7 @@ mod tests {
                     chat_history: Some(vec![ChatEntry {
                         entry_type: "human".into(),
                         content: "Hello world".into(),
+                        id: None,
                     }]),
                     attachments: Some(vec![Attachment {
                         name: "note.txt".into(),
@@ -381,6 +689,7 @@ mod tests {
                         size: 5,
                     }]),
                     form_submission: None,
+                    llm: None,
                 },
             )
             .await
@@ -446,6 +755,11 @@ mod tests {
                 last_message_at: None,
                 is_active: true,
                 messages: vec![],
+                tags: vec![],
+                pinned_message_ids: vec![],
+                last_read_at: None,
+                unread_count: 0,
+                llm: None,
             })
             .await
             .unwrap();
@@ -461,6 +775,7 @@ mod tests {
                     chat_history: None,
                     attachments: None,
                     form_submission: None,
+                    llm: None,
                 },
             )
             .await
@@ -533,6 +848,11 @@ mod tests {
                 last_message_at: None,
                 is_active: true,
                 messages: vec![],
+                tags: vec![],
+                pinned_message_ids: vec![],
+                last_read_at: None,
+                unread_count: 0,
+                llm: None,
             })
             .await
             .unwrap();
@@ -548,6 +868,7 @@ mod tests {
                     chat_history: None,
                     attachments: None,
                     form_submission: None,
+                    llm: None,
                 },
             )
             .await
@@ -576,6 +897,94 @@ mod tests {
         assert_eq!(stored.topic_id, Some("topic".into()));
     }
 
+    struct ExitedBackend;
+
+    #[async_trait]
+    impl AgentBackend for ExitedBackend {
+        async fn send_message(
+            &self,
+            _request: &AgentMessageRequest,
+            _metadata: Value,
+            _timestamp: &str,
+        ) -> Result<AgentMessageData, BackendError> {
+            Err(BackendError::Failure("bridge is gone".into()))
+        }
+
+        async fn health_check(&self) -> Result<(), BackendError> {
+            Err(BackendError::Failure("Agent bridge exited (code 1)".into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn unhealthy_backend_is_restarted_for_the_same_session() {
+        let service = AgentService::new();
+        let (bridge, info) = test_wallet_bridge();
+        assert_eq!(
+            service
+                .restart_if_unhealthy(bridge.clone(), Arc::clone(&info))
+                .await,
+            Ok(None)
+        );
+        let session_id = service
+            .initialize(sample_config(), bridge.clone(), Arc::clone(&info))
+            .await
+            .unwrap()
+            .data
+            .unwrap()
+            .session_id;
+        let context = AgentSessionContext {
+            session_id: "chat-1".into(),
+            mode: "personal".into(),
+            topic_id: None,
+        };
+        service.update_session_context(context.clone()).await;
+        service.set_backend(Arc::new(ExitedBackend)).await;
+
+        let restart = service
+            .restart_if_unhealthy(bridge.clone(), Arc::clone(&info))
+            .await
+            .unwrap()
+            .expect("restart");
+        assert_eq!(restart.bridge, "agent");
+        assert!(restart.reason.contains("exited"));
+        assert_eq!(restart.session_id.as_ref(), Some(&session_id));
+        assert_eq!(restart.session_context, Some(context));
+
+        assert_eq!(service.status().await.session_id, Some(session_id));
+        assert_eq!(service.restart_if_unhealthy(bridge, info).await, Ok(None));
+    }
+
+    #[tokio::test]
+    async fn changed_mcp_tools_restart_a_backend_that_uses_the_server() {
+        let service = AgentService::new();
+        let (bridge, info) = test_wallet_bridge();
+        let mut config = sample_config();
+        config.mcp_servers = Some(json!([{ "id": "server-1" }]));
+        let session_id = service
+            .initialize(config, bridge.clone(), Arc::clone(&info))
+            .await
+            .unwrap()
+            .data
+            .unwrap()
+            .session_id;
+
+        assert_eq!(
+            service
+                .rewire_mcp_tools(&["server-2".to_string()], bridge.clone(), Arc::clone(&info))
+                .await,
+            Ok(None)
+        );
+        let restart = service
+            .rewire_mcp_tools(&["server-1".to_string()], bridge, info)
+            .await
+            .unwrap()
+            .expect("restart");
+        assert_eq!(restart.bridge, "agent");
+        assert!(restart.reason.contains("server-1"));
+        assert_eq!(restart.session_id.as_ref(), Some(&session_id));
+        assert_eq!(service.status().await.session_id, Some(session_id));
+    }
+
     #[tokio::test]
     async fn disconnect_resets_state() {
         let service = AgentService::new();
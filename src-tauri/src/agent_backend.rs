@@ -1,6 +1,7 @@
 use async_trait::async_trait;
 use thiserror::Error;
 use crate::agent::{AgentMessageData, AgentMessageRequest};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use uuid::Uuid;
 
@@ -16,6 +17,36 @@ pub trait AgentBackend {
     async fn disconnect(&self) -> Result<(), BackendError> {
         Ok(())
     }
+
+    /// Fails when the process behind the backend has died or stopped
+    /// answering, so it can be restarted.
+    async fn health_check(&self) -> Result<(), BackendError> {
+        Ok(())
+    }
+
+    /// Creates an HCS topic that only the operator account can submit to.
+    async fn create_private_topic(&self, _memo: &str) -> Result<String, BackendError> {
+        Err(BackendError::Failure(
+            "Topic creation is not supported by this agent backend".to_string(),
+        ))
+    }
+
+    /// Submits `message` to `topic_id`, returning the assigned sequence number.
+    async fn submit_topic_message(
+        &self,
+        _topic_id: &str,
+        _message: &str,
+    ) -> Result<u64, BackendError> {
+        Err(BackendError::Failure(
+            "Topic publishing is not supported by this agent backend".to_string(),
+        ))
+    }
+    /// Sends an arbitrary bridge action and returns its raw response data.
+    async fn raw_request(&self, _action: &str, _payload: Value) -> Result<Value, BackendError> {
+        Err(BackendError::Failure(
+            "Raw requests are not supported by this agent backend".to_string(),
+        ))
+    }
 }
 
 pub struct EchoAgent;
@@ -49,6 +80,24 @@ impl AgentBackend for EchoAgent {
 pub enum BackendError {
     #[error("agent backend failure: {0}")]
     Failure(String),
+    #[error("agent bridge did not answer {} within {} ms", .0.action, .0.timeout_ms)]
+    Timeout(BridgeTimeout),
+}
+
+/// A bridge request that went unanswered on every attempt.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgeTimeout {
+    pub action: String,
+    /// The limit each attempt had.
+    pub timeout_ms: u64,
+    pub attempts: u32,
+}
+
+impl From<String> for BackendError {
+    fn from(error: String) -> Self {
+        Self::Failure(error)
+    }
 }
 
 impl From<anyhow::Error> for BackendError {
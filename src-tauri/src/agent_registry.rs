@@ -0,0 +1,401 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::agent::{AgentInitializeConfig, AgentMessageData, AgentMessageRequest};
+use crate::node_agent::{AgentInitializeConfigPayload, NodeAgentBackend};
+use crate::proxy;
+use crate::rate_limit::ProviderRateLimiter;
+use crate::wallet_bridge::{WalletBridgeInfo, WalletBridgeState};
+use crate::{AgentBackend, BackendError, EchoAgent};
+
+pub const NODE_BACKEND_ID: &str = "node";
+pub const ECHO_BACKEND_ID: &str = "echo";
+pub const REMOTE_BACKEND_ID: &str = "remote-http";
+
+/// What a backend can do beyond answering chat messages.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendCapabilities {
+    /// Runs Hedera and MCP tools on the user's behalf.
+    pub tools: bool,
+    /// Returns forms for the user to fill in.
+    pub forms: bool,
+    /// Creates and publishes to HCS topics.
+    pub topics: bool,
+    /// Accepts arbitrary bridge actions through `raw_request`.
+    pub raw_requests: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendDescriptor {
+    pub id: String,
+    pub name: String,
+    pub capabilities: BackendCapabilities,
+}
+
+/// Everything a factory may need to start a backend.
+pub struct BackendContext {
+    pub config: AgentInitializeConfig,
+    pub wallet_bridge: WalletBridgeState,
+    pub wallet_info: Arc<Mutex<Option<WalletBridgeInfo>>>,
+    pub rate_limiter: Arc<ProviderRateLimiter>,
+    pub bridge_script: Option<PathBuf>,
+}
+
+#[async_trait]
+pub trait AgentBackendFactory: Send + Sync {
+    fn name(&self) -> String;
+
+    fn capabilities(&self) -> BackendCapabilities;
+
+    async fn create(
+        &self,
+        context: &BackendContext,
+    ) -> Result<Arc<dyn AgentBackend + Send + Sync>, String>;
+}
+
+/// The backends the agent can run on, keyed by id. Backends can be added or
+/// replaced while the app runs; a session picks up the change the next time
+/// its backend is selected.
+pub struct AgentBackendRegistry {
+    factories: RwLock<BTreeMap<String, Arc<dyn AgentBackendFactory>>>,
+}
+
+impl Default for AgentBackendRegistry {
+    /// A registry with the Node bridge and echo backends.
+    fn default() -> Self {
+        let registry = Self {
+            factories: RwLock::new(BTreeMap::new()),
+        };
+        registry.register(NODE_BACKEND_ID, Arc::new(NodeBackendFactory));
+        registry.register(ECHO_BACKEND_ID, Arc::new(EchoBackendFactory));
+        registry
+    }
+}
+
+impl AgentBackendRegistry {
+    pub fn register(&self, id: &str, factory: Arc<dyn AgentBackendFactory>) {
+        self.factories
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(id.to_string(), factory);
+    }
+
+    pub fn unregister(&self, id: &str) -> bool {
+        self.factories
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .remove(id)
+            .is_some()
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<dyn AgentBackendFactory>> {
+        self.factories
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(id)
+            .cloned()
+    }
+
+    pub fn describe(&self, id: &str) -> Option<BackendDescriptor> {
+        self.get(id).map(|factory| BackendDescriptor {
+            id: id.to_string(),
+            name: factory.name(),
+            capabilities: factory.capabilities(),
+        })
+    }
+
+    pub fn list(&self) -> Vec<BackendDescriptor> {
+        self.factories
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .map(|(id, factory)| BackendDescriptor {
+                id: id.clone(),
+                name: factory.name(),
+                capabilities: factory.capabilities(),
+            })
+            .collect()
+    }
+}
+
+/// The conversational agent running in the bundled Node bridge.
+pub struct NodeBackendFactory;
+
+#[async_trait]
+impl AgentBackendFactory for NodeBackendFactory {
+    fn name(&self) -> String {
+        "Node agent bridge".to_string()
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            tools: true,
+            forms: true,
+            topics: true,
+            raw_requests: true,
+        }
+    }
+
+    async fn create(
+        &self,
+        context: &BackendContext,
+    ) -> Result<Arc<dyn AgentBackend + Send + Sync>, String> {
+        let script = context
+            .bridge_script
+            .clone()
+            .ok_or_else(|| "Agent bridge script was not found".to_string())?;
+        let backend = NodeAgentBackend::spawn(
+            script,
+            context.wallet_bridge.clone(),
+            context.wallet_info.clone(),
+            Arc::clone(&context.rate_limiter),
+        )
+        .await?;
+        let payload: AgentInitializeConfigPayload = context.config.clone().into();
+        backend.initialize(&payload).await?;
+        log::info!("Node conversational agent backend initialized");
+        Ok(Arc::new(backend))
+    }
+}
+
+pub struct EchoBackendFactory;
+
+#[async_trait]
+impl AgentBackendFactory for EchoBackendFactory {
+    fn name(&self) -> String {
+        "Echo".to_string()
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities::default()
+    }
+
+    async fn create(
+        &self,
+        _context: &BackendContext,
+    ) -> Result<Arc<dyn AgentBackend + Send + Sync>, String> {
+        Ok(Arc::new(EchoAgent::new()))
+    }
+}
+
+/// An agent served over HTTP. Each message is POSTed to `url` and the reply
+/// is read from `content` (or `message`), with optional `metadata` and
+/// `formMessage`.
+pub struct RemoteHttpFactory {
+    url: String,
+    api_key: Option<String>,
+}
+
+impl RemoteHttpFactory {
+    pub fn new(url: String, api_key: Option<String>) -> Self {
+        Self { url, api_key }
+    }
+}
+
+#[async_trait]
+impl AgentBackendFactory for RemoteHttpFactory {
+    fn name(&self) -> String {
+        format!("Remote agent ({})", self.url)
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            forms: true,
+            ..BackendCapabilities::default()
+        }
+    }
+
+    async fn create(
+        &self,
+        _context: &BackendContext,
+    ) -> Result<Arc<dyn AgentBackend + Send + Sync>, String> {
+        let client = proxy::client_builder()
+            .user_agent("hol-desktop-tauri/0.0.1")
+            .build()
+            .map_err(|error| error.to_string())?;
+        Ok(Arc::new(RemoteHttpAgent {
+            client,
+            url: self.url.clone(),
+            api_key: self.api_key.clone(),
+        }))
+    }
+}
+
+pub struct RemoteHttpAgent {
+    client: Client,
+    url: String,
+    api_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RemoteAgentReply {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    metadata: Option<Value>,
+    #[serde(default)]
+    form_message: Option<Value>,
+}
+
+#[async_trait]
+impl AgentBackend for RemoteHttpAgent {
+    async fn send_message(
+        &self,
+        request: &AgentMessageRequest,
+        metadata: Value,
+        timestamp: &str,
+    ) -> Result<AgentMessageData, BackendError> {
+        let mut builder = self.client.post(&self.url).json(&json!({
+            "sessionId": request.session_id,
+            "content": request.content,
+            "chatHistory": request.chat_history,
+            "attachments": request.attachments,
+            "formSubmission": request.form_submission,
+            "metadata": metadata,
+            "llm": request.llm.as_ref().map(|llm| json!({
+                "provider": llm.provider,
+                "modelName": llm.model,
+            })),
+        }));
+        if let Some(api_key) = &self.api_key {
+            builder = builder.bearer_auth(api_key);
+        }
+        let response = builder
+            .send()
+            .await
+            .map_err(|error| BackendError::Failure(format!("Remote agent unreachable: {error}")))?;
+        if !response.status().is_success() {
+            return Err(BackendError::Failure(format!(
+                "Remote agent returned {}",
+                response.status()
+            )));
+        }
+        let reply: RemoteAgentReply = response.json().await.map_err(|error| {
+            BackendError::Failure(format!("Remote agent sent an invalid reply: {error}"))
+        })?;
+
+        let mut reply_metadata = reply.metadata.unwrap_or_else(|| json!({}));
+        if let (Value::Object(reply_map), Value::Object(request_map)) =
+            (&mut reply_metadata, metadata)
+        {
+            for (key, value) in request_map {
+                reply_map.entry(key).or_insert(value);
+            }
+        }
+        Ok(AgentMessageData {
+            id: format!("msg-{}", Uuid::new_v4()),
+            role: "assistant".to_string(),
+            content: reply.content.or(reply.message).unwrap_or_default(),
+            timestamp: timestamp.to_string(),
+            metadata: Some(reply_metadata),
+            form_message: reply.form_message,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::MockServer;
+
+    fn context() -> BackendContext {
+        BackendContext {
+            config: serde_json::from_value(json!({
+                "accountId": "0.0.1234",
+                "privateKey": "",
+                "network": "testnet",
+                "openAIApiKey": "sk-test"
+            }))
+            .unwrap(),
+            wallet_bridge: WalletBridgeState::default(),
+            wallet_info: Arc::new(Mutex::new(None)),
+            rate_limiter: Arc::new(ProviderRateLimiter::new()),
+            bridge_script: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn registered_backends_are_listed_and_created() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method("POST")
+                .path("/agent")
+                .header("authorization", "Bearer secret")
+                .json_body_partial(r#"{ "content": "hi" }"#);
+            then.status(200)
+                .json_body(json!({ "content": "hello", "metadata": { "model": "remote" } }));
+        });
+
+        let registry = AgentBackendRegistry::default();
+        registry.register(
+            REMOTE_BACKEND_ID,
+            Arc::new(RemoteHttpFactory::new(
+                server.url("/agent"),
+                Some("secret".to_string()),
+            )),
+        );
+        let ids: Vec<String> = registry
+            .list()
+            .into_iter()
+            .map(|backend| backend.id)
+            .collect();
+        assert_eq!(ids, vec!["echo", "node", "remote-http"]);
+        assert!(
+            registry
+                .describe(NODE_BACKEND_ID)
+                .unwrap()
+                .capabilities
+                .tools
+        );
+
+        let context = context();
+        assert!(
+            registry
+                .get(NODE_BACKEND_ID)
+                .unwrap()
+                .create(&context)
+                .await
+                .is_err()
+        );
+
+        let remote = registry
+            .get(REMOTE_BACKEND_ID)
+            .unwrap()
+            .create(&context)
+            .await
+            .unwrap();
+        let request = AgentMessageRequest {
+            session_id: Some("session-1".to_string()),
+            content: "hi".to_string(),
+            chat_history: None,
+            attachments: None,
+            form_submission: None,
+            llm: None,
+        };
+        let reply = remote
+            .send_message(&request, json!({ "mode": "personal" }), "now")
+            .await
+            .unwrap();
+        mock.assert();
+        assert_eq!(reply.content, "hello");
+        assert_eq!(
+            reply.metadata,
+            Some(json!({ "model": "remote", "mode": "personal" }))
+        );
+
+        assert!(registry.unregister(REMOTE_BACKEND_ID));
+        assert!(registry.get(REMOTE_BACKEND_ID).is_none());
+    }
+}
@@ -6,14 +6,21 @@ use serde_json::{Map, Value, json};
 use tokio::sync::Mutex;
 use uuid::Uuid;
 
+use crate::AgentBackend;
+use crate::BackendError;
 use crate::agent::{
-    AgentInitializeConfig, AgentInitializeData, AgentInitializeResponse, AgentMessageData,
-    AgentMessageRequest, AgentMessageResponse, AgentStatusResponse, Attachment, FormSubmission,
+    AgentInitializeConfig, AgentInitializeData, AgentInitializeResponse, AgentLlmSettings,
+    AgentMessageData, AgentMessageRequest, AgentMessageResponse, AgentStatusResponse, Attachment,
+    FormSubmission,
 };
-use crate::node_agent::{AgentInitializeConfigPayload, NodeAgentBackend};
-use crate::session::{ChatMessage, SessionService};
+use crate::agent_registry::{
+    AgentBackendRegistry, BackendContext, ECHO_BACKEND_ID, NODE_BACKEND_ID,
+};
+use crate::config::LlmProvider;
+use crate::rate_limit::ProviderRateLimiter;
+use crate::secret::Secret;
+use crate::session::{AgentToolCall, ChatMessage, SessionService, SessionUsage};
 use crate::wallet_bridge::{WalletBridgeInfo, WalletBridgeState};
-use crate::{AgentBackend, EchoAgent};
 use std::path::PathBuf;
 
 #[derive(Default)]
@@ -22,15 +29,21 @@ pub struct InitializationService {
     session_id: Option<String>,
     last_config: Option<ConfigSnapshot>,
     backend: Option<Arc<dyn AgentBackend + Send + Sync>>,
+    backend_id: Option<String>,
+    /// The config of the running session, kept to start another backend for
+    /// it without a new `initialize`.
+    last_request: Option<AgentInitializeConfig>,
     bridge_script: Option<PathBuf>,
+    rate_limiter: Arc<ProviderRateLimiter>,
+    registry: Arc<AgentBackendRegistry>,
 }
 
 #[derive(Clone, PartialEq, Eq)]
 struct ConfigSnapshot {
     account_id: String,
-    private_key: String,
+    private_key: Secret,
     network: String,
-    open_ai_api_key: String,
+    open_ai_api_key: Secret,
     model_name: Option<String>,
     llm_provider: Option<String>,
     user_account_id: Option<String>,
@@ -38,6 +51,7 @@ struct ConfigSnapshot {
     mcp_signature: Option<String>,
     verbose: Option<bool>,
     disable_logging: Option<bool>,
+    backend: String,
 }
 
 impl InitializationService {
@@ -53,13 +67,57 @@ impl InitializationService {
         self.bridge_script = bridge_script;
     }
 
+    pub fn registry(&self) -> Arc<AgentBackendRegistry> {
+        Arc::clone(&self.registry)
+    }
+
+    /// The backend a config asks for, or the Node bridge when its script is
+    /// bundled and echo otherwise.
+    fn backend_id_for(&self, config: &AgentInitializeConfig) -> String {
+        config
+            .backend
+            .as_deref()
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| {
+                if self.bridge_script.is_some() {
+                    NODE_BACKEND_ID.to_string()
+                } else {
+                    ECHO_BACKEND_ID.to_string()
+                }
+            })
+    }
+
+    async fn create_backend(
+        &self,
+        backend_id: &str,
+        config: &AgentInitializeConfig,
+        wallet_bridge: WalletBridgeState,
+        wallet_info: Arc<Mutex<Option<WalletBridgeInfo>>>,
+    ) -> Result<Arc<dyn AgentBackend + Send + Sync>, String> {
+        let factory = self
+            .registry
+            .get(backend_id)
+            .ok_or_else(|| format!("Agent backend {backend_id} is not registered"))?;
+        let context = BackendContext {
+            config: config.clone(),
+            wallet_bridge,
+            wallet_info,
+            rate_limiter: Arc::clone(&self.rate_limiter),
+            bridge_script: self.bridge_script.clone(),
+        };
+        factory.create(&context).await
+    }
+
     pub async fn initialize(
         &mut self,
         config: AgentInitializeConfig,
         wallet_bridge: WalletBridgeState,
         wallet_info: Arc<Mutex<Option<WalletBridgeInfo>>>,
     ) -> AgentInitializeResponse {
-        if config.account_id.trim().is_empty() || config.open_ai_api_key.trim().is_empty() {
+        if config.account_id.trim().is_empty() || config.open_ai_api_key.expose().trim().is_empty()
+        {
             return AgentInitializeResponse {
                 success: false,
                 data: None,
@@ -72,7 +130,7 @@ impl InitializationService {
             Some("provideBytes") | Some("returnBytes")
         );
 
-        if config.private_key.trim().is_empty() && !wallet_operational_mode {
+        if config.private_key.expose().trim().is_empty() && !wallet_operational_mode {
             return AgentInitializeResponse {
                 success: false,
                 data: None,
@@ -95,6 +153,7 @@ impl InitializationService {
                 .and_then(|value| serde_json::to_string(value).ok()),
             verbose: config.verbose,
             disable_logging: config.disable_logging,
+            backend: self.backend_id_for(&config),
         };
 
         if let Some(previous) = &self.last_config {
@@ -112,48 +171,82 @@ impl InitializationService {
         }
 
         let session_id = Uuid::new_v4().to_string();
-        let mut backend: Option<Arc<dyn AgentBackend + Send + Sync>> = None;
-
-        if let Some(ref path) = self.bridge_script {
-            match NodeAgentBackend::spawn(path.clone(), wallet_bridge.clone(), wallet_info.clone())
-                .await
-            {
-                Ok(node_backend) => {
-                    let payload: AgentInitializeConfigPayload = config.clone().into();
-                    if let Err(error) = node_backend.initialize(&payload).await {
-                        self.initialized = false;
-                        self.session_id = None;
-                        self.backend = None;
-                        return AgentInitializeResponse {
-                            success: false,
-                            data: None,
-                            error: Some(error),
-                        };
-                    }
-                    log::info!("Node conversational agent backend initialized");
-                    backend = Some(Arc::new(node_backend));
-                }
-                Err(error) => {
-                    self.initialized = false;
-                    self.session_id = None;
-                    self.backend = None;
-                    return AgentInitializeResponse {
-                        success: false,
-                        data: None,
-                        error: Some(error),
-                    };
-                }
+        let backend = match self
+            .create_backend(&snapshot.backend, &config, wallet_bridge, wallet_info)
+            .await
+        {
+            Ok(backend) => backend,
+            Err(error) => {
+                self.initialized = false;
+                self.session_id = None;
+                self.backend = None;
+                self.backend_id = None;
+                return AgentInitializeResponse {
+                    success: false,
+                    data: None,
+                    error: Some(error),
+                };
             }
-        } else {
-            log::info!("No bridge script found; using echo backend");
-            backend = Some(Arc::new(EchoAgent::new()));
-        }
+        };
 
         self.initialized = true;
         self.session_id = Some(session_id.clone());
+        self.backend_id = Some(snapshot.backend.clone());
         self.last_config = Some(snapshot);
-        self.backend = backend;
+        self.last_request = Some(config);
+        self.backend = Some(backend);
+
+        AgentInitializeResponse {
+            success: true,
+            data: Some(AgentInitializeData { session_id }),
+            error: None,
+        }
+    }
+
+    /// Starts `backend_id` for the running session and retires the current
+    /// backend once the new one is up. The session ID is kept, so the chat
+    /// carries on; on failure the current backend stays in place.
+    pub async fn switch_backend(
+        &mut self,
+        backend_id: &str,
+        wallet_bridge: WalletBridgeState,
+        wallet_info: Arc<Mutex<Option<WalletBridgeInfo>>>,
+    ) -> AgentInitializeResponse {
+        let (Some(session_id), Some(mut config)) =
+            (self.session_id.clone(), self.last_request.clone())
+        else {
+            return AgentInitializeResponse {
+                success: false,
+                data: None,
+                error: Some("Agent session is not initialized".to_string()),
+            };
+        };
+        config.backend = Some(backend_id.to_string());
+        let backend = match self
+            .create_backend(backend_id, &config, wallet_bridge, wallet_info)
+            .await
+        {
+            Ok(backend) => backend,
+            Err(error) => {
+                return AgentInitializeResponse {
+                    success: false,
+                    data: None,
+                    error: Some(error),
+                };
+            }
+        };
 
+        if let Some(previous) = self.backend.replace(backend)
+            && let Err(error) = previous.disconnect().await
+        {
+            log::warn!("Failed to disconnect the previous agent backend: {}", error);
+        }
+        log::info!("Agent backend switched to {}", backend_id);
+        self.backend_id = Some(backend_id.to_string());
+        if let Some(snapshot) = self.last_config.as_mut() {
+            snapshot.backend = backend_id.to_string();
+        }
+        self.last_request = Some(config);
         AgentInitializeResponse {
             success: true,
             data: Some(AgentInitializeData { session_id }),
@@ -161,6 +254,43 @@ impl InitializationService {
         }
     }
 
+    /// Replaces a backend whose process died or hung with a fresh one,
+    /// initialized from the session's config. The session ID is kept, and the
+    /// old backend is dropped without a disconnect it could not answer.
+    pub async fn restart_backend(
+        &mut self,
+        wallet_bridge: WalletBridgeState,
+        wallet_info: Arc<Mutex<Option<WalletBridgeInfo>>>,
+    ) -> Result<String, String> {
+        let (Some(session_id), Some(backend_id), Some(config)) = (
+            self.session_id.clone(),
+            self.backend_id.clone(),
+            self.last_request.clone(),
+        ) else {
+            return Err("Agent session is not initialized".to_string());
+        };
+        let backend = self
+            .create_backend(&backend_id, &config, wallet_bridge, wallet_info)
+            .await?;
+        self.backend = Some(backend);
+        log::info!("Agent backend {} restarted", backend_id);
+        Ok(session_id)
+    }
+
+    /// Whether the running session handed the agent the MCP server
+    /// `server_id`.
+    pub fn uses_mcp_server(&self, server_id: &str) -> bool {
+        self.last_request
+            .as_ref()
+            .and_then(|config| config.mcp_servers.as_ref())
+            .and_then(Value::as_array)
+            .is_some_and(|servers| {
+                servers
+                    .iter()
+                    .any(|server| server.get("id").and_then(Value::as_str) == Some(server_id))
+            })
+    }
+
     pub fn status(&self, active_messages: usize) -> AgentStatusResponse {
         AgentStatusResponse {
             connected: self.initialized,
@@ -170,12 +300,22 @@ impl InitializationService {
             } else {
                 Some(active_messages)
             },
+            backend: self
+                .backend_id
+                .as_deref()
+                .and_then(|id| self.registry.describe(id)),
         }
     }
 
+    pub fn backend_id(&self) -> Option<String> {
+        self.backend_id.clone()
+    }
+
     pub async fn disconnect(&mut self) {
         self.initialized = false;
         self.session_id = None;
+        self.backend_id = None;
+        self.last_request = None;
         if let Some(backend) = self.backend.take() {
             let _ = backend.disconnect().await;
         }
@@ -192,6 +332,40 @@ impl InitializationService {
     pub fn set_backend(&mut self, backend: Arc<dyn AgentBackend + Send + Sync>) {
         self.backend = Some(backend);
     }
+
+    pub fn set_rate_limiter(&mut self, rate_limiter: Arc<ProviderRateLimiter>) {
+        self.rate_limiter = rate_limiter;
+    }
+
+    pub fn rate_limiter(&self) -> Arc<ProviderRateLimiter> {
+        Arc::clone(&self.rate_limiter)
+    }
+
+    /// The provider the agent was initialized with; the bridge defaults to
+    /// OpenAI when none is given.
+    pub fn llm_provider(&self) -> Option<LlmProvider> {
+        let config = self.last_config.as_ref()?;
+        LlmProvider::from_id(config.llm_provider.as_deref().unwrap_or("openai"))
+    }
+
+    /// The provider, key and model of the running session, for LLM calls the
+    /// app makes outside the agent.
+    pub fn llm_settings(&self) -> Option<AgentLlmSettings> {
+        let config = self.last_request.as_ref()?;
+        let provider = self.llm_provider()?;
+        Some(AgentLlmSettings {
+            provider,
+            api_key: config.open_ai_api_key.clone(),
+            model: config
+                .model_name
+                .clone()
+                .filter(|model| !model.trim().is_empty()),
+            base_url: config
+                .open_ai_base_url
+                .clone()
+                .filter(|_| provider == LlmProvider::Openai),
+        })
+    }
 }
 
 pub struct MessageService {
@@ -226,6 +400,7 @@ impl MessageService {
                 success: false,
                 response: None,
                 error: Some("Message content must not be empty".to_string()),
+                timeout: None,
             });
         }
 
@@ -235,6 +410,7 @@ impl MessageService {
                     success: false,
                     response: None,
                     error: Some("Unknown session ID".to_string()),
+                    timeout: None,
                 });
             }
         }
@@ -288,16 +464,44 @@ impl MessageService {
 
         let metadata_value = Value::Object(metadata_map.clone());
 
-        let assistant_message = backend
+        let assistant_message = match backend
             .send_message(&request, metadata_value.clone(), &timestamp)
             .await
-            .map_err(|error| error.to_string())?;
+        {
+            Ok(message) => message,
+            Err(BackendError::Timeout(timeout)) => {
+                return Ok(AgentMessageResponse {
+                    success: false,
+                    response: None,
+                    error: Some(BackendError::Timeout(timeout.clone()).to_string()),
+                    timeout: Some(timeout),
+                });
+            }
+            Err(error) => return Err(error.to_string()),
+        };
 
-        let persisted_metadata = assistant_message
+        let mut persisted_metadata = assistant_message
             .metadata
             .clone()
             .unwrap_or(Value::Object(metadata_map));
 
+        // Raw steps can carry secrets the agent handled, so only the masked
+        // calls are kept, in their own table, and the metadata keeps a count.
+        let tool_calls = AgentToolCall::from_agent_metadata(&persisted_metadata);
+        if let Value::Object(map) = &mut persisted_metadata {
+            if let Some(Value::Array(calls)) = map.remove("toolCalls") {
+                map.entry("toolCallCount").or_insert(json!(calls.len()));
+            }
+            map.remove("intermediateSteps");
+        }
+
+        let usage = SessionUsage::from_agent_metadata(&persisted_metadata);
+        if usage.has_tokens()
+            && let Value::Object(map) = &mut persisted_metadata
+        {
+            map.insert("tokenUsage".to_string(), usage.to_metadata());
+        }
+
         let message_type = assistant_message
             .metadata
             .as_ref()
@@ -313,12 +517,25 @@ impl MessageService {
             timestamp: assistant_message.timestamp.clone(),
             message_type: Some(message_type),
             metadata: Some(persisted_metadata.clone()),
+            thread_id: None,
         };
 
         session_service
             .save_message(session_id, chat_message)
             .await?;
 
+        // The reply is already saved, so a failed transcript only loses the audit trail.
+        if !tool_calls.is_empty()
+            && let Err(error) = session_service
+                .record_tool_calls(session_id, &assistant_message.id, &tool_calls)
+                .await
+        {
+            log::warn!(
+                "Failed to record tool calls for {}: {error}",
+                assistant_message.id
+            );
+        }
+
         Ok(AgentMessageResponse {
             success: true,
             response: Some(AgentMessageData {
@@ -327,6 +544,7 @@ impl MessageService {
                 ..assistant_message
             }),
             error: None,
+            timeout: None,
         })
     }
 }
@@ -359,26 +577,30 @@ fn form_submission_metadata(form_submission: &FormSubmission) -> Value {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::BackendError;
     use crate::agent::ChatEntry;
+    use crate::agent_registry::{AgentBackendFactory, BackendCapabilities};
+    use crate::{BridgeTimeout, EchoAgent};
     use async_trait::async_trait;
     use std::sync::Arc;
 
     fn sample_config() -> AgentInitializeConfig {
         AgentInitializeConfig {
             account_id: "0.0.5005".to_string(),
-            private_key: "302e020100300506032b657004220420".to_string(),
+            private_key: "302e020100300506032b657004220420".into(),
             network: "testnet".to_string(),
-            open_ai_api_key: "sk-test".to_string(),
+            open_ai_api_key: "sk-test".into(),
             model_name: Some("gpt-test".to_string()),
+            open_ai_base_url: None,
             llm_provider: Some("openai".to_string()),
             user_account_id: Some("0.0.7007".to_string()),
             operational_mode: Some("provideBytes".to_string()),
             mcp_servers: None,
+            mcp_group: None,
             verbose: None,
             disable_logging: None,
             disabled_plugins: None,
             additional_plugins: None,
+            backend: None,
         }
     }
 
@@ -404,11 +626,70 @@ mod tests {
         assert_eq!(first_session, second_session);
     }
 
+    struct LabelledEcho;
+
+    #[async_trait]
+    impl AgentBackendFactory for LabelledEcho {
+        fn name(&self) -> String {
+            "Labelled echo".to_string()
+        }
+
+        fn capabilities(&self) -> BackendCapabilities {
+            BackendCapabilities {
+                forms: true,
+                ..BackendCapabilities::default()
+            }
+        }
+
+        async fn create(
+            &self,
+            _context: &BackendContext,
+        ) -> Result<Arc<dyn AgentBackend + Send + Sync>, String> {
+            Ok(Arc::new(EchoAgent::new()))
+        }
+    }
+
+    #[tokio::test]
+    async fn backends_are_switched_without_a_new_session() {
+        let mut service = InitializationService::new(None);
+        let (bridge, info) = test_wallet_bridge();
+        let session_id = service
+            .initialize(sample_config(), bridge.clone(), Arc::clone(&info))
+            .await
+            .data
+            .unwrap()
+            .session_id;
+        assert_eq!(service.backend_id().as_deref(), Some(ECHO_BACKEND_ID));
+
+        let failed = service
+            .switch_backend(NODE_BACKEND_ID, bridge.clone(), Arc::clone(&info))
+            .await;
+        assert!(!failed.success);
+        assert_eq!(service.backend_id().as_deref(), Some(ECHO_BACKEND_ID));
+
+        service
+            .registry()
+            .register("labelled", Arc::new(LabelledEcho));
+        let switched = service
+            .switch_backend("labelled", bridge.clone(), Arc::clone(&info))
+            .await;
+        assert_eq!(switched.data.unwrap().session_id, session_id);
+        let status = service.status(0);
+        let backend = status.backend.unwrap();
+        assert_eq!(backend.id, "labelled");
+        assert!(backend.capabilities.forms);
+
+        let mut config = sample_config();
+        config.backend = Some("missing".to_string());
+        let missing = service.initialize(config, bridge, info).await;
+        assert!(missing.error.unwrap().contains("not registered"));
+    }
+
     #[tokio::test]
     async fn initialization_allows_missing_private_key_for_wallet_modes() {
         let mut service = InitializationService::new(None);
         let mut config = sample_config();
-        config.private_key = String::new();
+        config.private_key = Secret::default();
         config.operational_mode = Some("provideBytes".to_string());
         let (bridge, info) = test_wallet_bridge();
         let result = service.initialize(config, bridge, info).await;
@@ -437,6 +718,7 @@ mod tests {
             chat_history: Some(vec![ChatEntry {
                 entry_type: "human".to_string(),
                 content: "Hello agent".to_string(),
+                id: None,
             }]),
             attachments: Some(vec![Attachment {
                 name: "info.txt".to_string(),
@@ -452,6 +734,7 @@ mod tests {
                 original_prompt: Some("prompt".to_string()),
                 partial_input: None,
             }),
+            llm: None,
         };
 
         let response = service
@@ -513,6 +796,7 @@ mod tests {
                     chat_history: None,
                     attachments: None,
                     form_submission: None,
+                    llm: None,
                 },
                 Arc::new(FailingBackend),
             )
@@ -524,4 +808,66 @@ mod tests {
             "agent backend failure: backend error".to_string()
         );
     }
+
+    #[tokio::test]
+    async fn message_service_reports_bridge_timeouts() {
+        struct SilentBackend;
+
+        #[async_trait]
+        impl AgentBackend for SilentBackend {
+            async fn send_message(
+                &self,
+                _request: &AgentMessageRequest,
+                _metadata: Value,
+                _timestamp: &str,
+            ) -> Result<AgentMessageData, BackendError> {
+                Err(BackendError::Timeout(BridgeTimeout {
+                    action: "sendMessage".to_string(),
+                    timeout_ms: 5000,
+                    attempts: 1,
+                }))
+            }
+        }
+
+        let session_service = SessionService::new_in_memory();
+        let session = session_service
+            .create_session(crate::session::CreateSessionInput {
+                name: "Silent".to_string(),
+                mode: "personal".to_string(),
+                topic_id: None,
+                is_active: true,
+            })
+            .await;
+
+        let response = MessageService::new()
+            .process(
+                &session_service,
+                &session.id,
+                AgentMessageRequest {
+                    session_id: Some(session.id.clone()),
+                    content: "Hello".to_string(),
+                    chat_history: None,
+                    attachments: None,
+                    form_submission: None,
+                    llm: None,
+                },
+                Arc::new(SilentBackend),
+            )
+            .await
+            .expect("a timeout is a response, not an error");
+
+        assert!(!response.success);
+        assert_eq!(response.timeout.unwrap().timeout_ms, 5000);
+        assert_eq!(
+            response.error.as_deref(),
+            Some("agent bridge did not answer sendMessage within 5000 ms")
+        );
+        assert!(
+            session_service
+                .load_messages(&session.id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
 }
@@ -288,6 +288,21 @@ impl<R: Runtime> BrowserManager<R> {
         .await
     }
 
+    /// Runs `script` only while the page is on `origin`, checked in the page
+    /// itself so a navigation after approval cannot redirect it.
+    pub async fn execute_js_in_origin(
+        &self,
+        app: &AppHandle<R>,
+        origin: &str,
+        script: String,
+    ) -> Result<serde_json::Value, String> {
+        let expected = serde_json::to_string(origin).map_err(map_error)?;
+        let guarded = format!(
+            "(() => {{ if (window.location.origin !== {expected}) {{ throw new Error('The page left ' + {expected}); }} const value = {script}; return value; }})()"
+        );
+        self.execute_js(app, guarded).await
+    }
+
     pub async fn execute_js(
         &self,
         _app: &AppHandle<R>,
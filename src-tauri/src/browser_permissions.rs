@@ -0,0 +1,224 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use tokio::sync::oneshot;
+
+const SCRIPT_PREVIEW_CHARS: usize = 500;
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptApprovalRequest {
+    pub request_id: String,
+    pub origin: String,
+    pub url: String,
+    pub script_preview: String,
+    pub script_length: usize,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptApprovalDecision {
+    pub approved: bool,
+    #[serde(default)]
+    pub remember: bool,
+}
+
+/// Approval state for browser scripts. Remembered decisions are kept
+/// per site origin and persisted; pending requests wait on a oneshot channel
+/// until the renderer answers.
+pub struct BrowserScriptPermissions {
+    path: Option<PathBuf>,
+    remembered: BTreeMap<String, bool>,
+    pending: HashMap<String, (String, oneshot::Sender<ScriptApprovalDecision>)>,
+}
+
+impl BrowserScriptPermissions {
+    pub fn load(path: PathBuf) -> Self {
+        let remembered = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        Self {
+            path: Some(path),
+            remembered,
+            pending: HashMap::new(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            remembered: BTreeMap::new(),
+            pending: HashMap::new(),
+        }
+    }
+
+    pub fn remembered_decision(&self, origin: &str) -> Option<bool> {
+        self.remembered.get(origin).copied()
+    }
+
+    pub fn remembered(&self) -> BTreeMap<String, bool> {
+        self.remembered.clone()
+    }
+
+    pub fn request(
+        &mut self,
+        url: &str,
+        script: &str,
+    ) -> Result<
+        (
+            ScriptApprovalRequest,
+            oneshot::Receiver<ScriptApprovalDecision>,
+        ),
+        String,
+    > {
+        let origin = site_origin(url)?;
+        let (sender, receiver) = oneshot::channel();
+        let request = ScriptApprovalRequest {
+            request_id: uuid::Uuid::new_v4().to_string(),
+            origin: origin.clone(),
+            url: url.to_string(),
+            script_preview: script.chars().take(SCRIPT_PREVIEW_CHARS).collect(),
+            script_length: script.chars().count(),
+        };
+        self.pending
+            .insert(request.request_id.clone(), (origin, sender));
+        Ok((request, receiver))
+    }
+
+    pub fn resolve(
+        &mut self,
+        request_id: &str,
+        decision: ScriptApprovalDecision,
+    ) -> Result<(), String> {
+        let (origin, sender) = self
+            .pending
+            .remove(request_id)
+            .ok_or_else(|| "Script approval request not found or already resolved".to_string())?;
+        if decision.remember {
+            self.remembered.insert(origin, decision.approved);
+            self.persist()?;
+        }
+        let _ = sender.send(decision);
+        Ok(())
+    }
+
+    pub fn cancel(&mut self, request_id: &str) {
+        self.pending.remove(request_id);
+    }
+
+    pub fn forget(&mut self, origin: &str) -> Result<bool, String> {
+        let removed = self.remembered.remove(origin).is_some();
+        if removed {
+            self.persist()?;
+        }
+        Ok(removed)
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+        }
+        let serialized =
+            serde_json::to_string_pretty(&self.remembered).map_err(|error| error.to_string())?;
+        fs::write(path, serialized)
+            .map_err(|error| format!("Failed to save browser script permissions: {error}"))
+    }
+}
+
+/// Reduces a page URL to `scheme://host[:port]`, the key remembered decisions
+/// are stored under.
+pub fn site_origin(url: &str) -> Result<String, String> {
+    let (scheme, rest) = url
+        .split_once("://")
+        .ok_or_else(|| format!("Browser page {url} has no stable origin"))?;
+    let authority = rest
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or_default()
+        .rsplit('@')
+        .next()
+        .unwrap_or_default();
+    if scheme.is_empty() || authority.is_empty() {
+        return Err(format!("Browser page {url} has no stable origin"));
+    }
+    Ok(format!(
+        "{}://{}",
+        scheme.to_ascii_lowercase(),
+        authority.to_ascii_lowercase()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn site_origin_strips_path_and_keeps_port() {
+        assert_eq!(
+            site_origin("https://hashscan.io/testnet/account?x=1").unwrap(),
+            "https://hashscan.io"
+        );
+        assert_eq!(
+            site_origin("http://localhost:3000/app").unwrap(),
+            "http://localhost:3000"
+        );
+        assert!(site_origin("data:text/html,hi").is_err());
+    }
+
+    #[tokio::test]
+    async fn resolve_delivers_decision_and_remembers_origin() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("browser-script-permissions.json");
+        let mut permissions = BrowserScriptPermissions::load(path.clone());
+
+        let (request, receiver) = permissions
+            .request("https://hashscan.io/testnet", "document.title")
+            .unwrap();
+        assert_eq!(request.origin, "https://hashscan.io");
+        assert_eq!(request.script_preview, "document.title");
+
+        permissions
+            .resolve(
+                &request.request_id,
+                ScriptApprovalDecision {
+                    approved: true,
+                    remember: true,
+                },
+            )
+            .unwrap();
+        assert!(receiver.await.unwrap().approved);
+        assert!(
+            permissions
+                .resolve(
+                    &request.request_id,
+                    ScriptApprovalDecision {
+                        approved: true,
+                        remember: false,
+                    }
+                )
+                .is_err()
+        );
+
+        let reloaded = BrowserScriptPermissions::load(path);
+        assert_eq!(
+            reloaded.remembered_decision("https://hashscan.io"),
+            Some(true)
+        );
+    }
+
+    #[tokio::test]
+    async fn cancelled_request_drops_sender() {
+        let mut permissions = BrowserScriptPermissions::in_memory();
+        let (request, receiver) = permissions.request("https://example.com", "1 + 1").unwrap();
+        permissions.cancel(&request.request_id);
+        assert!(receiver.await.is_err());
+        assert!(permissions.remembered().is_empty());
+    }
+}
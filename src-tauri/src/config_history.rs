@@ -0,0 +1,394 @@
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::config::AppConfig;
+use crate::migrations::{self, Migration};
+
+pub const CONFIG_HISTORY_MIGRATION_SCOPE: &str = "config_history";
+
+const HISTORY_LIMIT: i64 = 200;
+const MASKED_VALUE: &str = "********";
+const SECRET_KEYS: &[&str] = &["privatekey", "apikey", "beefeedpk"];
+
+pub const CONFIG_HISTORY_MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    description: "create config history table",
+    up: |connection| {
+        connection.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS config_history (
+                version INTEGER PRIMARY KEY AUTOINCREMENT,
+                saved_at TEXT NOT NULL,
+                changes TEXT NOT NULL,
+                snapshot TEXT NOT NULL
+            );
+            "#,
+        )
+    },
+    down: |connection| connection.execute_batch("DROP TABLE IF EXISTS config_history;"),
+}];
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigChange {
+    pub path: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigHistoryEntry {
+    pub version: i64,
+    pub saved_at: String,
+    pub changes: Vec<ConfigChange>,
+}
+
+/// Versioned record of config saves. Each version keeps the masked diff for
+/// display and the config as written to disk (secrets still encrypted) so it
+/// can be restored.
+pub struct ConfigHistory {
+    db: Mutex<Connection>,
+}
+
+impl ConfigHistory {
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        let connection = Connection::open(path).map_err(|err| err.to_string())?;
+        migrations::apply(
+            &connection,
+            CONFIG_HISTORY_MIGRATION_SCOPE,
+            CONFIG_HISTORY_MIGRATIONS,
+        )?;
+        Ok(Self {
+            db: Mutex::new(connection),
+        })
+    }
+
+    #[cfg(test)]
+    pub fn new_in_memory() -> Self {
+        let connection = Connection::open_in_memory().expect("in-memory sqlite");
+        migrations::apply(
+            &connection,
+            CONFIG_HISTORY_MIGRATION_SCOPE,
+            CONFIG_HISTORY_MIGRATIONS,
+        )
+        .expect("init schema");
+        Self {
+            db: Mutex::new(connection),
+        }
+    }
+
+    /// Records a save. `previous` and `current` are plaintext configs used for
+    /// the diff; `persisted` is the encrypted form kept for reverts. Saves that
+    /// change nothing are skipped and return `None`.
+    pub fn record(
+        &self,
+        previous: &AppConfig,
+        current: &AppConfig,
+        persisted: &AppConfig,
+    ) -> Result<Option<i64>, String> {
+        let previous = serde_json::to_value(previous).map_err(|err| err.to_string())?;
+        let current = serde_json::to_value(current).map_err(|err| err.to_string())?;
+        let changes = diff_config(&previous, &current);
+        if changes.is_empty() {
+            return Ok(None);
+        }
+
+        let changes = serde_json::to_string(&changes).map_err(|err| err.to_string())?;
+        let snapshot = serde_json::to_string(persisted).map_err(|err| err.to_string())?;
+        let connection = self.lock()?;
+        connection
+            .execute(
+                "INSERT INTO config_history (saved_at, changes, snapshot) VALUES (?1, ?2, ?3)",
+                params![Utc::now().to_rfc3339(), changes, snapshot],
+            )
+            .map_err(|err| err.to_string())?;
+        let version = connection.last_insert_rowid();
+        connection
+            .execute(
+                "DELETE FROM config_history WHERE version <= ?1",
+                params![version - HISTORY_LIMIT],
+            )
+            .map_err(|err| err.to_string())?;
+        Ok(Some(version))
+    }
+
+    /// Returns the newest entries first.
+    pub fn entries(&self, limit: usize) -> Result<Vec<ConfigHistoryEntry>, String> {
+        let connection = self.lock()?;
+        let mut statement = connection
+            .prepare(
+                "SELECT version, saved_at, changes FROM config_history
+                 ORDER BY version DESC LIMIT ?1",
+            )
+            .map_err(|err| err.to_string())?;
+        let rows = statement
+            .query_map(params![limit as i64], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })
+            .map_err(|err| err.to_string())?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            let (version, saved_at, changes) = row.map_err(|err| err.to_string())?;
+            entries.push(ConfigHistoryEntry {
+                version,
+                saved_at,
+                changes: serde_json::from_str(&changes).unwrap_or_default(),
+            });
+        }
+        Ok(entries)
+    }
+
+    /// The config as persisted by `version`, with secrets still encrypted.
+    pub fn snapshot(&self, version: i64) -> Result<AppConfig, String> {
+        let snapshot = self
+            .lock()?
+            .query_row(
+                "SELECT snapshot FROM config_history WHERE version = ?1",
+                params![version],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+            .map_err(|err| err.to_string())?
+            .ok_or_else(|| format!("Config version {version} not found"))?;
+        serde_json::from_str(&snapshot)
+            .map_err(|error| format!("Failed to parse config version {version}: {error}"))
+    }
+
+    /// Applies `rewrap` to every snapshot and stores the ones it changed.
+    /// Returns how many snapshots were rewritten.
+    pub fn rewrap_snapshots(
+        &self,
+        rewrap: impl Fn(&mut AppConfig) -> Result<usize, String>,
+    ) -> Result<usize, String> {
+        let connection = self.lock()?;
+        let snapshots = {
+            let mut statement = connection
+                .prepare("SELECT version, snapshot FROM config_history")
+                .map_err(|err| err.to_string())?;
+            let rows = statement
+                .query_map([], |row| {
+                    Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+                })
+                .map_err(|err| err.to_string())?;
+            rows.collect::<Result<Vec<_>, _>>()
+                .map_err(|err| err.to_string())?
+        };
+
+        let mut rewritten = 0;
+        for (version, snapshot) in snapshots {
+            let mut config: AppConfig = serde_json::from_str(&snapshot)
+                .map_err(|error| format!("Failed to parse config version {version}: {error}"))?;
+            if rewrap(&mut config)? == 0 {
+                continue;
+            }
+            let snapshot = serde_json::to_string(&config).map_err(|err| err.to_string())?;
+            connection
+                .execute(
+                    "UPDATE config_history SET snapshot = ?1 WHERE version = ?2",
+                    params![snapshot, version],
+                )
+                .map_err(|err| err.to_string())?;
+            rewritten += 1;
+        }
+        Ok(rewritten)
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Connection>, String> {
+        self.db
+            .lock()
+            .map_err(|error| format!("Failed to lock config history: {error}"))
+    }
+}
+
+/// Lists changed leaf values as dotted paths. Arrays are compared whole and
+/// secret fields are masked on both sides.
+pub fn diff_config(previous: &Value, current: &Value) -> Vec<ConfigChange> {
+    let mut changes = Vec::new();
+    collect_changes("", previous, current, &mut changes);
+    changes
+}
+
+fn collect_changes(path: &str, previous: &Value, current: &Value, changes: &mut Vec<ConfigChange>) {
+    if previous == current {
+        return;
+    }
+
+    if let (Value::Object(before), Value::Object(after)) = (previous, current) {
+        let mut keys: Vec<&String> = before.keys().chain(after.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            let child = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{path}.{key}")
+            };
+            collect_changes(
+                &child,
+                before.get(key).unwrap_or(&Value::Null),
+                after.get(key).unwrap_or(&Value::Null),
+                changes,
+            );
+        }
+        return;
+    }
+
+    let secret = is_secret_path(path);
+    changes.push(ConfigChange {
+        path: path.to_string(),
+        before: mask_if(secret, previous),
+        after: mask_if(secret, current),
+    });
+}
+
+pub(crate) fn is_secret_path(path: &str) -> bool {
+    let key = path.rsplit('.').next().unwrap_or(path).to_ascii_lowercase();
+    SECRET_KEYS.contains(&key.as_str()) || key.contains("password") || key.contains("secret")
+}
+
+/// Masks the secret fields anywhere inside `value`.
+pub(crate) fn mask_secrets(value: &Value) -> Value {
+    mask_if(false, value)
+}
+
+pub(crate) fn mask_if(secret: bool, value: &Value) -> Value {
+    let blank = match value {
+        Value::Null => true,
+        Value::String(text) => text.is_empty(),
+        _ => false,
+    };
+    if secret && !blank {
+        return Value::String(MASKED_VALUE.into());
+    }
+    // Arrays are compared whole, so secrets inside their entries, such as
+    // the keys of the saved Hedera accounts, are masked here.
+    match value {
+        Value::Array(items) => Value::Array(items.iter().map(mask_secrets).collect()),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, field)| (key.clone(), mask_if(is_secret_path(key), field)))
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn diff_masks_secrets_and_lists_changed_leaves() {
+        let changes = diff_config(
+            &json!({
+                "hedera": { "network": "testnet", "privateKey": "", "accounts": [] },
+                "openai": { "apiKey": "sk-old", "model": "gpt-5" }
+            }),
+            &json!({
+                "hedera": {
+                    "network": "mainnet",
+                    "privateKey": "302e...",
+                    "accounts": [{ "name": "ops", "privateKey": "302e..." }]
+                },
+                "openai": { "apiKey": "sk-new", "model": "gpt-5" }
+            }),
+        );
+
+        assert_eq!(
+            changes,
+            vec![
+                ConfigChange {
+                    path: "hedera.accounts".into(),
+                    before: json!([]),
+                    after: json!([{ "name": "ops", "privateKey": MASKED_VALUE }]),
+                },
+                ConfigChange {
+                    path: "hedera.network".into(),
+                    before: json!("testnet"),
+                    after: json!("mainnet"),
+                },
+                ConfigChange {
+                    path: "hedera.privateKey".into(),
+                    before: json!(""),
+                    after: json!(MASKED_VALUE),
+                },
+                ConfigChange {
+                    path: "openai.apiKey".into(),
+                    before: json!(MASKED_VALUE),
+                    after: json!(MASKED_VALUE),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn record_skips_unchanged_saves_and_keeps_snapshots() {
+        let history = ConfigHistory::new_in_memory();
+        let original = AppConfig::default();
+        let mut updated = original.clone();
+        updated.hedera.account_id = "0.0.1234".into();
+
+        assert_eq!(
+            history.record(&original, &original, &original).unwrap(),
+            None
+        );
+        let version = history
+            .record(&original, &updated, &updated)
+            .unwrap()
+            .unwrap();
+
+        let entries = history.entries(10).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].version, version);
+        assert_eq!(entries[0].changes[0].path, "hedera.accountId");
+        assert_eq!(
+            history.snapshot(version).unwrap().hedera.account_id,
+            "0.0.1234"
+        );
+        assert!(history.snapshot(version + 1).is_err());
+    }
+
+    #[test]
+    fn rewrap_snapshots_rewrites_only_changed_snapshots() {
+        let history = ConfigHistory::new_in_memory();
+        let original = AppConfig::default();
+        let mut updated = original.clone();
+        updated.hedera.private_key = "ENC:old".into();
+        let version = history
+            .record(&original, &updated, &updated)
+            .unwrap()
+            .unwrap();
+
+        let rewritten = history
+            .rewrap_snapshots(|config| {
+                if config.hedera.private_key != "ENC:old" {
+                    return Ok(0);
+                }
+                config.hedera.private_key = "ENC:v2:new".into();
+                Ok(1)
+            })
+            .unwrap();
+
+        assert_eq!(rewritten, 1);
+        assert_eq!(
+            history.snapshot(version).unwrap().hedera.private_key,
+            "ENC:v2:new"
+        );
+        assert_eq!(history.rewrap_snapshots(|_| Ok(0)).unwrap(), 0);
+    }
+}
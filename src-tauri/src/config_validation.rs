@@ -0,0 +1,364 @@
+use serde::Serialize;
+
+use crate::config::{AppConfig, LlmProvider, Network, OperationalMode};
+use crate::credentials::CredentialScope;
+use crate::entity_id::{EntityIdError, parse_entity_id};
+
+/// Models the settings screen offers, kept in step with
+/// `src/renderer/lib/models.ts`.
+pub const OPENAI_MODELS: &[&str] = &[
+    "gpt-5",
+    "gpt-4.1",
+    "gpt-4.1-mini",
+    "gpt-4.1-nano",
+    "gpt-4o",
+    "gpt-4o-mini",
+    "o4-mini",
+    "gpt-4",
+    "gpt-3.5-turbo",
+];
+
+pub const ANTHROPIC_MODELS: &[&str] = &[
+    "claude-3-7-sonnet-latest",
+    "claude-3-5-haiku-20241022",
+    "claude-3-opus-20240229",
+];
+
+pub const GEMINI_MODELS: &[&str] = &[
+    "gemini-2.5-pro",
+    "gemini-2.5-flash",
+    "gemini-2.5-flash-lite",
+    "gemini-2.0-flash",
+];
+
+const ED25519_DER_PREFIX: &str = "302e020100300506032b657004220420";
+const ECDSA_DER_PREFIX: &str = "3030020100300706052b8104000a04220420";
+
+/// A problem with one config value. `field` is the dotted camelCase path the
+/// settings form uses, such as `hedera.accountId`.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigFieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl ConfigFieldError {
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigValidationReport {
+    pub valid: bool,
+    pub errors: Vec<ConfigFieldError>,
+}
+
+impl From<Vec<ConfigFieldError>> for ConfigValidationReport {
+    fn from(errors: Vec<ConfigFieldError>) -> Self {
+        Self {
+            valid: errors.is_empty(),
+            errors,
+        }
+    }
+}
+
+/// Checks the values that can be judged without the network. Mirror node
+/// reachability is left to the caller.
+pub fn validate(config: &AppConfig) -> Vec<ConfigFieldError> {
+    let mut errors = Vec::new();
+
+    if let Err(error) = parse_entity_id(
+        &config.hedera.account_id,
+        network_name(&config.hedera.network),
+    ) {
+        let message = match error {
+            EntityIdError::Empty => "Account ID is required".to_string(),
+            EntityIdError::Malformed { .. } => {
+                "Account ID must match format shard.realm.num".to_string()
+            }
+            other => other.to_string(),
+        };
+        errors.push(ConfigFieldError::new("hedera.accountId", message));
+    }
+
+    let private_key = config.hedera.private_key.expose().trim();
+    if private_key.is_empty() {
+        if matches!(
+            config.advanced.operational_mode,
+            OperationalMode::Autonomous
+        ) {
+            errors.push(ConfigFieldError::new(
+                "hedera.privateKey",
+                "Private key is required in autonomous mode",
+            ));
+        }
+    } else if !is_private_key(private_key) {
+        errors.push(ConfigFieldError::new(
+            "hedera.privateKey",
+            "Private key must be a hex or DER encoded ED25519 or ECDSA key",
+        ));
+    }
+
+    let provider = config.llm_provider;
+    let field = format!("{}.model", provider.id());
+    let model = config.provider(provider).model.trim();
+    let gateway = config
+        .openai
+        .base_url
+        .as_deref()
+        .is_some_and(|url| !url.trim().is_empty());
+    let models = match provider {
+        // A gateway names its models however it is configured to.
+        LlmProvider::Openai if gateway => None,
+        LlmProvider::Openai => Some(OPENAI_MODELS),
+        LlmProvider::Anthropic => Some(ANTHROPIC_MODELS),
+        LlmProvider::Gemini => Some(GEMINI_MODELS),
+        // OpenRouter serves hundreds of models, named `vendor/model`.
+        LlmProvider::Openrouter => None,
+    };
+    if model.is_empty() {
+        errors.push(ConfigFieldError::new(&field, "Model is required"));
+    } else if let Some(models) = models
+        && !models.contains(&model)
+    {
+        errors.push(ConfigFieldError::new(
+            &field,
+            format!("Unknown model {model}"),
+        ));
+    } else if provider == LlmProvider::Openrouter && !model.contains('/') {
+        errors.push(ConfigFieldError::new(
+            &field,
+            "OpenRouter models are named vendor/model, such as openai/gpt-4o",
+        ));
+    }
+
+    if let Err(error) = crate::proxy::check(&config.advanced.proxy) {
+        errors.push(ConfigFieldError::new("advanced.proxy.url", error));
+    }
+
+    errors
+}
+
+/// Checks a secret `credential_store` is about to save. Under the `hedera`
+/// scope it must be a private key; under `llm`, or for a service naming a
+/// provider such as `openai` or `gemini`, an API key. Malformed values are refused and
+/// well formed but unusual ones come back as warnings.
+pub fn check_credential(
+    scope: Option<CredentialScope>,
+    service: &str,
+    secret: &str,
+) -> Result<Vec<String>, String> {
+    let service = service.to_ascii_lowercase();
+    let provider = [
+        LlmProvider::Anthropic,
+        LlmProvider::Openrouter,
+        LlmProvider::Gemini,
+        LlmProvider::Openai,
+    ]
+    .into_iter()
+    .find(|provider| service.contains(provider.id()));
+
+    if scope == Some(CredentialScope::Hedera) {
+        check_private_key(secret)
+    } else if scope == Some(CredentialScope::Llm) || provider.is_some() {
+        check_api_key(provider, secret)
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+fn check_private_key(key: &str) -> Result<Vec<String>, String> {
+    if key.chars().any(char::is_whitespace) {
+        return Err("Private key must not contain spaces or line breaks".to_string());
+    }
+    if !is_private_key(key) {
+        return Err("Private key must be a hex or DER encoded ED25519 or ECDSA key".to_string());
+    }
+    let lower = key.to_ascii_lowercase();
+    let der = [ED25519_DER_PREFIX, ECDSA_DER_PREFIX]
+        .iter()
+        .any(|prefix| lower.trim_start_matches("0x").starts_with(prefix));
+    if der {
+        Ok(Vec::new())
+    } else {
+        Ok(vec![
+            "A raw 32 byte key does not say whether it is ED25519 or ECDSA; the DER encoding does"
+                .to_string(),
+        ])
+    }
+}
+
+fn check_api_key(provider: Option<LlmProvider>, key: &str) -> Result<Vec<String>, String> {
+    if key.chars().any(char::is_whitespace) {
+        return Err("API key must not contain spaces or line breaks".to_string());
+    }
+    if key.len() < 20 {
+        return Err("API key is too short to be a provider key".to_string());
+    }
+    let anthropic = key.starts_with("sk-ant-");
+    let openrouter = key.starts_with("sk-or-");
+    match provider {
+        Some(LlmProvider::Openai) if anthropic => {
+            Err("This is an Anthropic API key, not an OpenAI one".to_string())
+        }
+        Some(LlmProvider::Openai) if openrouter => {
+            Err("This is an OpenRouter API key, not an OpenAI one".to_string())
+        }
+        Some(LlmProvider::Openai) if !key.starts_with("sk-") => Ok(vec![
+            "OpenAI API keys start with sk-; this one may be for a gateway".to_string(),
+        ]),
+        Some(LlmProvider::Anthropic) if !anthropic && key.starts_with("sk-") => {
+            Err("This is an OpenAI API key, not an Anthropic one".to_string())
+        }
+        Some(LlmProvider::Anthropic) if !anthropic => Ok(vec![
+            "Anthropic API keys start with sk-ant-; this one may be for a gateway".to_string(),
+        ]),
+        Some(LlmProvider::Openrouter) if !openrouter && key.starts_with("sk-") => {
+            Err("This is an OpenAI or Anthropic API key, not an OpenRouter one".to_string())
+        }
+        Some(LlmProvider::Openrouter) if !openrouter => Ok(vec![
+            "OpenRouter API keys start with sk-or-; this one may be for a gateway".to_string(),
+        ]),
+        Some(LlmProvider::Gemini) if key.starts_with("sk-") => {
+            Err("Gemini API keys start with AIza, not sk-".to_string())
+        }
+        Some(LlmProvider::Gemini) if !key.starts_with("AIza") => Ok(vec![
+            "Gemini API keys start with AIza; this one may be for a gateway".to_string(),
+        ]),
+        _ => Ok(Vec::new()),
+    }
+}
+
+fn network_name(network: &Network) -> &'static str {
+    match network {
+        Network::Mainnet => "mainnet",
+        Network::Testnet => "testnet",
+    }
+}
+
+/// Accepts a raw 32 byte key or its DER encoding, in hex with an optional
+/// `0x` prefix.
+fn is_private_key(key: &str) -> bool {
+    let key = key.strip_prefix("0x").unwrap_or(key).to_ascii_lowercase();
+    if !key.chars().all(|c| c.is_ascii_hexdigit()) {
+        return false;
+    }
+    let raw = [ED25519_DER_PREFIX, ECDSA_DER_PREFIX]
+        .iter()
+        .find_map(|prefix| key.strip_prefix(prefix))
+        .unwrap_or(&key);
+    raw.len() == 64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(config: &AppConfig) -> Vec<String> {
+        validate(config)
+            .into_iter()
+            .map(|error| error.field)
+            .collect()
+    }
+
+    #[test]
+    fn invalid_fields_are_reported_by_path() {
+        let mut config = AppConfig::default();
+        config.hedera.account_id = "0.0.1234".to_string();
+        config.hedera.private_key = format!("{ED25519_DER_PREFIX}{}", "ab".repeat(32)).into();
+        config.openai.model = "gpt-4o-mini".to_string();
+        assert!(validate(&config).is_empty());
+
+        config.hedera.private_key = format!("0x{}", "CD".repeat(32)).into();
+        assert!(validate(&config).is_empty());
+
+        config.hedera.account_id = "0.0".to_string();
+        config.hedera.private_key = "302e...".into();
+        config.openai.model = "gpt-9".to_string();
+        assert_eq!(
+            fields(&config),
+            ["hedera.accountId", "hedera.privateKey", "openai.model"]
+        );
+        config.openai.base_url = Some("http://localhost:4000/v1".to_string());
+        assert_eq!(fields(&config), ["hedera.accountId", "hedera.privateKey"]);
+        config.openai.base_url = None;
+
+        config.hedera.account_id = "0.0.1234".to_string();
+        config.hedera.private_key = Default::default();
+        config.llm_provider = LlmProvider::Anthropic;
+        config.anthropic.model = "claude-3-7-sonnet-latest".to_string();
+        config.advanced.operational_mode = OperationalMode::ProvideBytes;
+        assert!(validate(&config).is_empty());
+        config.advanced.operational_mode = OperationalMode::Autonomous;
+        assert_eq!(fields(&config), ["hedera.privateKey"]);
+        config.advanced.operational_mode = OperationalMode::ProvideBytes;
+
+        config.llm_provider = LlmProvider::Gemini;
+        assert!(validate(&config).is_empty());
+        config.gemini.model = "gemini-9".to_string();
+        assert_eq!(fields(&config), ["gemini.model"]);
+
+        config.llm_provider = LlmProvider::Openrouter;
+        config.openrouter.model = "meta-llama/llama-3.3-70b-instruct".to_string();
+        assert!(validate(&config).is_empty());
+        config.openrouter.model = "llama".to_string();
+        assert_eq!(fields(&config), ["openrouter.model"]);
+        config.openrouter.model = "meta-llama/llama-3.3-70b-instruct".to_string();
+
+        config.advanced.proxy.enabled = true;
+        config.advanced.proxy.url = "proxy.corp.example:8080".to_string();
+        assert_eq!(fields(&config), ["advanced.proxy.url"]);
+    }
+
+    #[test]
+    fn stored_credentials_are_checked_by_scope_and_service() {
+        let der_key = format!("{ECDSA_DER_PREFIX}{}", "ab".repeat(32));
+        let hedera = Some(CredentialScope::Hedera);
+        assert_eq!(check_credential(hedera, "operator", &der_key), Ok(vec![]));
+        assert_eq!(
+            check_credential(hedera, "operator", &"ab".repeat(32))
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(check_credential(hedera, "operator", &der_key[..40]).is_err());
+        assert!(check_credential(hedera, "operator", &format!("{der_key}\n")).is_err());
+
+        let openai_key = format!("sk-proj-{}", "x".repeat(40));
+        let anthropic_key = format!("sk-ant-api03-{}", "x".repeat(40));
+        assert_eq!(check_credential(None, "openai", &openai_key), Ok(vec![]));
+        assert!(check_credential(None, "openai", &anthropic_key).is_err());
+        assert!(check_credential(None, "Anthropic", &openai_key).is_err());
+        assert_eq!(
+            check_credential(Some(CredentialScope::Llm), "anthropic", &anthropic_key),
+            Ok(vec![])
+        );
+        assert_eq!(
+            check_credential(Some(CredentialScope::Llm), "openai", &"x".repeat(40))
+                .unwrap()
+                .len(),
+            1
+        );
+        let openrouter_key = format!("sk-or-v1-{}", "x".repeat(40));
+        let gemini_key = format!("AIzaSy{}", "x".repeat(33));
+        assert_eq!(
+            check_credential(None, "openrouter", &openrouter_key),
+            Ok(vec![])
+        );
+        assert!(check_credential(None, "openai", &openrouter_key).is_err());
+        assert!(check_credential(None, "openrouter", &openai_key).is_err());
+        assert_eq!(check_credential(None, "gemini", &gemini_key), Ok(vec![]));
+        assert!(check_credential(None, "gemini", &openai_key).is_err());
+        assert!(check_credential(Some(CredentialScope::Llm), "gateway", "short").is_err());
+        assert_eq!(
+            check_credential(None, "github", "anything goes"),
+            Ok(vec![])
+        );
+    }
+}
@@ -1,6 +1,23 @@
-use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::config::LlmProvider;
+use crate::entity_id::{EntityIdError, parse_entity_id};
+use crate::proxy;
+use crate::rate_limit::ProviderRateLimiter;
+use crate::secret::Secret;
+
+const MIRROR_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+const LLM_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Gemini's OpenAI-compatible surface, which the agent bridge talks to
+/// through its OpenAI client.
+pub const GEMINI_OPENAI_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta/openai";
 
 #[derive(Clone)]
 pub struct HederaMirrorConfig {
@@ -17,33 +34,79 @@ impl Default for HederaMirrorConfig {
     }
 }
 
+/// Base URLs of the LLM provider APIs that completions are sent to.
+#[derive(Clone)]
+pub struct LlmEndpoints {
+    pub openai: String,
+    pub anthropic: String,
+    pub openrouter: String,
+    pub gemini: String,
+}
+
+impl Default for LlmEndpoints {
+    fn default() -> Self {
+        Self {
+            openai: "https://api.openai.com/v1".to_string(),
+            anthropic: "https://api.anthropic.com/v1".to_string(),
+            openrouter: "https://openrouter.ai/api/v1".to_string(),
+            gemini: GEMINI_OPENAI_BASE_URL.to_string(),
+        }
+    }
+}
+
+/// A configured endpoint without its trailing slash, or `None` when blank.
+fn custom_base_url(base_url: Option<&str>) -> Option<&str> {
+    base_url
+        .map(|url| url.trim().trim_end_matches('/'))
+        .filter(|url| !url.is_empty())
+}
+
 #[derive(Clone)]
 pub struct ConnectionService {
     client: Client,
     mirror_config: HederaMirrorConfig,
+    llm_endpoints: LlmEndpoints,
+    rate_limiter: Option<Arc<ProviderRateLimiter>>,
 }
 
 impl ConnectionService {
     pub fn new() -> Result<Self> {
-        let client = Client::builder()
+        let client = proxy::client_builder()
             .user_agent("hol-desktop-tauri/0.0.1")
             .build()?;
         Ok(Self {
             client,
             mirror_config: HederaMirrorConfig::default(),
+            llm_endpoints: LlmEndpoints::default(),
+            rate_limiter: None,
         })
     }
 
     pub fn with_mirror_config(mirror_config: HederaMirrorConfig) -> Result<Self> {
-        let client = Client::builder()
+        let client = proxy::client_builder()
             .user_agent("hol-desktop-tauri/0.0.1")
             .build()?;
         Ok(Self {
             client,
             mirror_config,
+            llm_endpoints: LlmEndpoints::default(),
+            rate_limiter: None,
         })
     }
 
+    /// Points completions at other servers, such as test fixtures.
+    #[cfg(test)]
+    pub fn with_llm_endpoints(mut self, llm_endpoints: LlmEndpoints) -> Self {
+        self.llm_endpoints = llm_endpoints;
+        self
+    }
+
+    /// Makes connection tests that reach a provider wait on its budget.
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<ProviderRateLimiter>) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
     pub async fn test_hedera(&self, credentials: HederaCredentials) -> Result<HederaTestResponse> {
         if credentials.account_id.trim().is_empty() || credentials.private_key.trim().is_empty() {
             return Ok(HederaTestResponse {
@@ -53,24 +116,29 @@ impl ConnectionService {
             });
         }
 
-        if !is_valid_account_id(&credentials.account_id) {
-            return Ok(HederaTestResponse {
-                success: false,
-                balance: None,
-                error: Some("Account ID must match format shard.realm.num".to_string()),
-            });
-        }
+        let (base_url, network) = match credentials.network {
+            HederaNetwork::Mainnet => (&self.mirror_config.mainnet, "mainnet"),
+            HederaNetwork::Testnet => (&self.mirror_config.testnet, "testnet"),
+        };
 
-        let base_url = match credentials.network {
-            HederaNetwork::Mainnet => &self.mirror_config.mainnet,
-            HederaNetwork::Testnet => &self.mirror_config.testnet,
+        let account_id = match parse_entity_id(&credentials.account_id, network) {
+            Ok(account_id) => account_id,
+            Err(error) => {
+                let message = match error {
+                    EntityIdError::Empty | EntityIdError::Malformed { .. } => {
+                        "Account ID must match format shard.realm.num".to_string()
+                    }
+                    other => other.to_string(),
+                };
+                return Ok(HederaTestResponse {
+                    success: false,
+                    balance: None,
+                    error: Some(message),
+                });
+            }
         };
 
-        let url = format!(
-            "{}/accounts/{}",
-            base_url.trim_end_matches('/'),
-            credentials.account_id
-        );
+        let url = format!("{}/accounts/{}", base_url.trim_end_matches('/'), account_id);
 
         let response = self
             .client
@@ -103,6 +171,30 @@ impl ConnectionService {
         })
     }
 
+    /// Fails when the mirror node for `network` cannot be reached or does not
+    /// answer successfully.
+    pub async fn test_mirror(&self, network: HederaNetwork) -> Result<()> {
+        let base_url = match network {
+            HederaNetwork::Mainnet => &self.mirror_config.mainnet,
+            HederaNetwork::Testnet => &self.mirror_config.testnet,
+        };
+        let url = format!("{}/network/supply", base_url.trim_end_matches('/'));
+        let response = self
+            .client
+            .get(&url)
+            .timeout(MIRROR_PROBE_TIMEOUT)
+            .send()
+            .await
+            .with_context(|| format!("Failed to contact Hedera mirror node: {url}"))?;
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Mirror node {url} answered with {}",
+                response.status()
+            ));
+        }
+        Ok(())
+    }
+
     pub async fn test_openai(&self, credentials: LlmCredentials) -> Result<LlmTestResponse> {
         if credentials.api_key.trim().is_empty() {
             return Ok(LlmTestResponse {
@@ -111,6 +203,31 @@ impl ConnectionService {
             });
         }
 
+        // Self-hosted gateways issue keys in their own formats, so they are
+        // checked by listing the models instead.
+        if let Some(base_url) = custom_base_url(credentials.base_url.as_deref()) {
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.acquire(LlmProvider::Openai).await;
+            }
+            let url = format!("{base_url}/models");
+            let error = match self
+                .client
+                .get(&url)
+                .bearer_auth(credentials.api_key.trim())
+                .timeout(LLM_PROBE_TIMEOUT)
+                .send()
+                .await
+            {
+                Ok(response) if response.status().is_success() => None,
+                Ok(response) => Some(format!("{url} answered with {}", response.status())),
+                Err(error) => Some(format!("Failed to contact {url}: {error}")),
+            };
+            return Ok(LlmTestResponse {
+                success: error.is_none(),
+                error,
+            });
+        }
+
         if !credentials.api_key.starts_with("sk-") {
             return Ok(LlmTestResponse {
                 success: false,
@@ -144,6 +261,317 @@ impl ConnectionService {
             error: None,
         })
     }
+
+    pub async fn test_openrouter(&self, credentials: LlmCredentials) -> Result<LlmTestResponse> {
+        if credentials.api_key.trim().is_empty() {
+            return Ok(LlmTestResponse {
+                success: false,
+                error: Some("OpenRouter API key is required".to_string()),
+            });
+        }
+
+        if !credentials.api_key.starts_with("sk-or-") {
+            return Ok(LlmTestResponse {
+                success: false,
+                error: Some("Invalid OpenRouter API key format".to_string()),
+            });
+        }
+
+        Ok(LlmTestResponse {
+            success: true,
+            error: None,
+        })
+    }
+
+    pub async fn test_gemini(&self, credentials: LlmCredentials) -> Result<LlmTestResponse> {
+        if credentials.api_key.trim().is_empty() {
+            return Ok(LlmTestResponse {
+                success: false,
+                error: Some("Gemini API key is required".to_string()),
+            });
+        }
+
+        if !credentials.api_key.starts_with("AIza") {
+            return Ok(LlmTestResponse {
+                success: false,
+                error: Some("Invalid Gemini API key format".to_string()),
+            });
+        }
+
+        Ok(LlmTestResponse {
+            success: true,
+            error: None,
+        })
+    }
+
+    /// Sends a single, non-streaming completion to `request.provider` and
+    /// returns the text of its reply along with the response headers, so the
+    /// caller can feed them to the provider rate limiter.
+    pub async fn complete(&self, request: LlmCompletionRequest) -> Result<LlmCompletion> {
+        if request.api_key.expose().trim().is_empty() {
+            return Err(anyhow!("{:?} API key is required", request.provider));
+        }
+
+        let (url, body) = match request.provider {
+            LlmProvider::Anthropic => (
+                format!(
+                    "{}/messages",
+                    self.llm_endpoints.anthropic.trim_end_matches('/')
+                ),
+                anthropic_body(&request),
+            ),
+            LlmProvider::Openai => (
+                format!(
+                    "{}/chat/completions",
+                    custom_base_url(request.base_url.as_deref())
+                        .unwrap_or_else(|| self.llm_endpoints.openai.trim_end_matches('/'))
+                ),
+                chat_completions_body(&request),
+            ),
+            LlmProvider::Openrouter => (
+                format!(
+                    "{}/chat/completions",
+                    self.llm_endpoints.openrouter.trim_end_matches('/')
+                ),
+                chat_completions_body(&request),
+            ),
+            LlmProvider::Gemini => (
+                format!(
+                    "{}/chat/completions",
+                    self.llm_endpoints.gemini.trim_end_matches('/')
+                ),
+                chat_completions_body(&request),
+            ),
+        };
+
+        let builder = self.client.post(&url).json(&body);
+        let builder = match request.provider {
+            LlmProvider::Anthropic => builder
+                .header("x-api-key", request.api_key.expose().trim())
+                .header("anthropic-version", ANTHROPIC_VERSION),
+            LlmProvider::Openai | LlmProvider::Openrouter | LlmProvider::Gemini => {
+                builder.bearer_auth(request.api_key.expose().trim())
+            }
+        };
+        let response = builder
+            .send()
+            .await
+            .with_context(|| format!("Failed to contact {url}"))?;
+
+        let status = response.status();
+        let headers: HashMap<String, String> = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                value
+                    .to_str()
+                    .ok()
+                    .map(|value| (name.as_str().to_string(), value.to_string()))
+            })
+            .collect();
+        let payload: Value = response
+            .json()
+            .await
+            .with_context(|| "Failed to parse completion response")?;
+        if !status.is_success() {
+            let message = payload
+                .pointer("/error/message")
+                .and_then(Value::as_str)
+                .unwrap_or("no error message");
+            return Err(anyhow!(
+                "{:?} completion failed with {status}: {message}",
+                request.provider
+            ));
+        }
+
+        let (text, stop_reason) =
+            match request.provider {
+                LlmProvider::Anthropic => {
+                    let text = payload
+                        .get("content")
+                        .and_then(Value::as_array)
+                        .map(|blocks| {
+                            blocks
+                                .iter()
+                                .filter(|block| {
+                                    block.get("type").and_then(Value::as_str) == Some("text")
+                                })
+                                .filter_map(|block| block.get("text").and_then(Value::as_str))
+                                .collect::<Vec<_>>()
+                                .join("")
+                        })
+                        .unwrap_or_default();
+                    let stop_reason = payload.get("stop_reason").and_then(Value::as_str).map(
+                        |reason| match reason {
+                            "end_turn" => LlmStopReason::EndTurn,
+                            "max_tokens" => LlmStopReason::MaxTokens,
+                            "stop_sequence" => LlmStopReason::StopSequence,
+                            other => LlmStopReason::Other(other.to_string()),
+                        },
+                    );
+                    (text, stop_reason)
+                }
+                LlmProvider::Openai | LlmProvider::Openrouter | LlmProvider::Gemini => {
+                    let choice = payload.pointer("/choices/0");
+                    let text = choice
+                        .and_then(|choice| choice.pointer("/message/content"))
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    let stop_reason = choice
+                        .and_then(|choice| choice.get("finish_reason"))
+                        .and_then(Value::as_str)
+                        .map(|reason| match reason {
+                            "stop" => LlmStopReason::EndTurn,
+                            "length" => LlmStopReason::MaxTokens,
+                            other => LlmStopReason::Other(other.to_string()),
+                        });
+                    (text, stop_reason)
+                }
+            };
+
+        Ok(LlmCompletion {
+            model: payload
+                .get("model")
+                .and_then(Value::as_str)
+                .unwrap_or(&request.model)
+                .to_string(),
+            text,
+            stop_reason,
+            headers,
+        })
+    }
+
+    /// Embeds `request.inputs` through the provider's OpenAI-compatible
+    /// embeddings endpoint, returning one vector per input in order.
+    pub async fn embed(&self, request: EmbeddingRequest) -> Result<Embeddings> {
+        if request.api_key.expose().trim().is_empty() {
+            return Err(anyhow!("{:?} API key is required", request.provider));
+        }
+        let base_url = match request.provider {
+            LlmProvider::Anthropic => {
+                return Err(anyhow!("Anthropic does not offer embeddings"));
+            }
+            LlmProvider::Openai => custom_base_url(request.base_url.as_deref())
+                .unwrap_or_else(|| self.llm_endpoints.openai.trim_end_matches('/')),
+            LlmProvider::Openrouter => self.llm_endpoints.openrouter.trim_end_matches('/'),
+            LlmProvider::Gemini => self.llm_endpoints.gemini.trim_end_matches('/'),
+        };
+        let url = format!("{base_url}/embeddings");
+
+        let response = self
+            .client
+            .post(&url)
+            .bearer_auth(request.api_key.expose().trim())
+            .json(&json!({ "model": request.model, "input": request.inputs }))
+            .send()
+            .await
+            .with_context(|| format!("Failed to contact {url}"))?;
+
+        let status = response.status();
+        let headers: HashMap<String, String> = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                value
+                    .to_str()
+                    .ok()
+                    .map(|value| (name.as_str().to_string(), value.to_string()))
+            })
+            .collect();
+        let payload: Value = response
+            .json()
+            .await
+            .with_context(|| "Failed to parse embeddings response")?;
+        if !status.is_success() {
+            let message = payload
+                .pointer("/error/message")
+                .and_then(Value::as_str)
+                .unwrap_or("no error message");
+            return Err(anyhow!(
+                "{:?} embeddings failed with {status}: {message}",
+                request.provider
+            ));
+        }
+
+        let mut data: Vec<EmbeddingData> =
+            serde_json::from_value(payload.get("data").cloned().unwrap_or(Value::Null))
+                .with_context(|| "Embeddings response has no data")?;
+        if data.len() != request.inputs.len() {
+            return Err(anyhow!(
+                "Expected {} embeddings but received {}",
+                request.inputs.len(),
+                data.len()
+            ));
+        }
+        data.sort_by_key(|item| item.index);
+
+        Ok(Embeddings {
+            model: payload
+                .get("model")
+                .and_then(Value::as_str)
+                .unwrap_or(&request.model)
+                .to_string(),
+            vectors: data.into_iter().map(|item| item.embedding).collect(),
+            headers,
+        })
+    }
+}
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+fn anthropic_body(request: &LlmCompletionRequest) -> Value {
+    let mut body = json!({
+        "model": request.model,
+        "max_tokens": request.max_tokens,
+        "messages": request
+            .messages
+            .iter()
+            .map(|message| json!({ "role": message.role, "content": message.content }))
+            .collect::<Vec<_>>(),
+    });
+    if let Some(system) = request.system.as_ref() {
+        body["system"] = json!(system);
+    }
+    if let Some(temperature) = request.temperature {
+        body["temperature"] = json!(temperature);
+    }
+    if !request.stop_sequences.is_empty() {
+        body["stop_sequences"] = json!(request.stop_sequences);
+    }
+    body
+}
+
+fn chat_completions_body(request: &LlmCompletionRequest) -> Value {
+    let mut messages = Vec::new();
+    if let Some(system) = request.system.as_ref() {
+        messages.push(json!({ "role": "system", "content": system }));
+    }
+    messages.extend(
+        request
+            .messages
+            .iter()
+            .map(|message| json!({ "role": message.role, "content": message.content })),
+    );
+    let mut body = json!({
+        "model": request.model,
+        "max_completion_tokens": request.max_tokens,
+        "messages": messages,
+    });
+    if let Some(temperature) = request.temperature {
+        body["temperature"] = json!(temperature);
+    }
+    if !request.stop_sequences.is_empty() {
+        body["stop"] = json!(request.stop_sequences);
+    }
+    body
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    #[serde(default)]
+    index: usize,
+    embedding: Vec<f32>,
 }
 
 #[derive(Deserialize)]
@@ -156,18 +584,6 @@ struct MirrorAccountBalance {
     balance: u64,
 }
 
-fn is_valid_account_id(account_id: &str) -> bool {
-    let mut parts = account_id.split('.');
-    if let (Some(shard), Some(realm), Some(num), None) =
-        (parts.next(), parts.next(), parts.next(), parts.next())
-    {
-        return shard.chars().all(|c| c.is_ascii_digit())
-            && realm.chars().all(|c| c.is_ascii_digit())
-            && num.chars().all(|c| c.is_ascii_digit());
-    }
-    false
-}
-
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct HederaCredentials {
     pub account_id: String,
@@ -193,6 +609,9 @@ pub struct HederaTestResponse {
 pub struct LlmCredentials {
     pub api_key: String,
     pub model: String,
+    /// OpenAI-compatible endpoint to use instead of api.openai.com.
+    #[serde(default)]
+    pub base_url: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -201,6 +620,60 @@ pub struct LlmTestResponse {
     pub error: Option<String>,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LlmMessage {
+    /// `user` or `assistant`.
+    pub role: String,
+    pub content: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LlmCompletionRequest {
+    pub provider: LlmProvider,
+    pub api_key: Secret,
+    pub model: String,
+    /// Replaces the OpenAI endpoint; ignored for the other providers.
+    pub base_url: Option<String>,
+    pub system: Option<String>,
+    pub messages: Vec<LlmMessage>,
+    pub max_tokens: u32,
+    pub temperature: Option<f32>,
+    pub stop_sequences: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LlmStopReason {
+    EndTurn,
+    MaxTokens,
+    StopSequence,
+    Other(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LlmCompletion {
+    pub model: String,
+    pub text: String,
+    pub stop_reason: Option<LlmStopReason>,
+    pub headers: HashMap<String, String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EmbeddingRequest {
+    pub provider: LlmProvider,
+    pub api_key: Secret,
+    pub model: String,
+    /// Replaces the OpenAI endpoint; ignored for the other providers.
+    pub base_url: Option<String>,
+    pub inputs: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Embeddings {
+    pub model: String,
+    pub vectors: Vec<Vec<f32>>,
+    pub headers: HashMap<String, String>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -238,6 +711,28 @@ mod tests {
         assert!(result.error.is_none());
     }
 
+    #[tokio::test]
+    async fn mirror_test_fails_when_the_mirror_errors() {
+        let server = MockServer::start();
+        let mirror_config = HederaMirrorConfig {
+            mainnet: server.url("/mainnet/api/v1"),
+            testnet: server.url("/testnet/api/v1"),
+        };
+        let service = ConnectionService::with_mirror_config(mirror_config).unwrap();
+
+        server.mock(|when, then| {
+            when.method("GET").path("/testnet/api/v1/network/supply");
+            then.status(200).json_body(serde_json::json!({}));
+        });
+        server.mock(|when, then| {
+            when.method("GET").path("/mainnet/api/v1/network/supply");
+            then.status(503);
+        });
+
+        assert!(service.test_mirror(HederaNetwork::Testnet).await.is_ok());
+        assert!(service.test_mirror(HederaNetwork::Mainnet).await.is_err());
+    }
+
     #[tokio::test]
     async fn hedera_test_invalid_account_returns_error() {
         let service = ConnectionService::new().unwrap();
@@ -261,6 +756,22 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn hedera_test_reports_checksum_for_wrong_network() {
+        let service = ConnectionService::new().unwrap();
+        let result = service
+            .test_hedera(HederaCredentials {
+                account_id: "0.0.123-vfmkw".to_string(),
+                private_key: "key".to_string(),
+                network: HederaNetwork::Testnet,
+            })
+            .await
+            .unwrap();
+
+        assert!(!result.success);
+        assert!(result.error.as_deref().unwrap().contains("0.0.123-esxsf"));
+    }
+
     #[tokio::test]
     async fn openai_test_rejects_invalid_key() {
         let service = ConnectionService::new().unwrap();
@@ -268,6 +779,7 @@ mod tests {
             .test_openai(LlmCredentials {
                 api_key: "wrong".to_string(),
                 model: "gpt".to_string(),
+                base_url: None,
             })
             .await
             .unwrap();
@@ -289,6 +801,7 @@ mod tests {
             .test_anthropic(LlmCredentials {
                 api_key: "sk-ant-valid".to_string(),
                 model: "claude".to_string(),
+                base_url: None,
             })
             .await
             .unwrap();
@@ -296,4 +809,253 @@ mod tests {
         assert!(result.success);
         assert!(result.error.is_none());
     }
+
+    #[tokio::test]
+    async fn openrouter_and_gemini_tests_check_key_prefixes() {
+        let service = ConnectionService::new().unwrap();
+        let credentials = |api_key: &str| LlmCredentials {
+            api_key: api_key.to_string(),
+            model: "model".to_string(),
+            base_url: None,
+        };
+
+        assert!(
+            service
+                .test_openrouter(credentials("sk-or-v1-valid"))
+                .await
+                .unwrap()
+                .success
+        );
+        let result = service
+            .test_openrouter(credentials("sk-ant-valid"))
+            .await
+            .unwrap();
+        assert_eq!(
+            result.error.as_deref(),
+            Some("Invalid OpenRouter API key format")
+        );
+
+        assert!(
+            service
+                .test_gemini(credentials("AIzaSyValid"))
+                .await
+                .unwrap()
+                .success
+        );
+        let result = service.test_gemini(credentials(" ")).await.unwrap();
+        assert_eq!(result.error.as_deref(), Some("Gemini API key is required"));
+    }
+
+    #[tokio::test]
+    async fn gemini_completion_uses_the_openai_compatible_endpoint() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method("POST")
+                .path("/v1beta/openai/chat/completions")
+                .header("authorization", "Bearer AIzaSyValid");
+            then.status(200).json_body(serde_json::json!({
+                "model": "gemini-2.5-flash",
+                "choices": [{
+                    "message": { "role": "assistant", "content": "Hello." },
+                    "finish_reason": "stop"
+                }]
+            }));
+        });
+        let service = ConnectionService::new()
+            .unwrap()
+            .with_llm_endpoints(LlmEndpoints {
+                gemini: server.url("/v1beta/openai"),
+                ..LlmEndpoints::default()
+            });
+
+        let completion = service
+            .complete(LlmCompletionRequest {
+                provider: LlmProvider::Gemini,
+                api_key: "AIzaSyValid".into(),
+                model: "gemini-2.5-flash".to_string(),
+                base_url: None,
+                system: None,
+                messages: vec![LlmMessage {
+                    role: "user".to_string(),
+                    content: "Hi".to_string(),
+                }],
+                max_tokens: 16,
+                temperature: None,
+                stop_sequences: Vec::new(),
+            })
+            .await
+            .unwrap();
+
+        mock.assert();
+        assert_eq!(completion.text, "Hello.");
+        assert_eq!(completion.stop_reason, Some(LlmStopReason::EndTurn));
+    }
+
+    #[tokio::test]
+    async fn anthropic_completion_sends_system_prompt_and_reads_text() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method("POST")
+                .path("/v1/messages")
+                .header("x-api-key", "sk-ant-valid")
+                .json_body(serde_json::json!({
+                    "model": "claude-3-7-sonnet-latest",
+                    "max_tokens": 64,
+                    "system": "Be brief.",
+                    "messages": [{ "role": "user", "content": "Summarize" }]
+                }));
+            then.status(200)
+                .header("anthropic-ratelimit-requests-remaining", "49")
+                .json_body(serde_json::json!({
+                    "model": "claude-3-7-sonnet-20250219",
+                    "stop_reason": "end_turn",
+                    "content": [{ "type": "text", "text": "Short summary." }]
+                }));
+        });
+        let service = ConnectionService::new()
+            .unwrap()
+            .with_llm_endpoints(LlmEndpoints {
+                anthropic: server.url("/v1"),
+                ..LlmEndpoints::default()
+            });
+
+        let completion = service
+            .complete(LlmCompletionRequest {
+                provider: LlmProvider::Anthropic,
+                api_key: "sk-ant-valid".into(),
+                model: "claude-3-7-sonnet-latest".to_string(),
+                base_url: None,
+                system: Some("Be brief.".to_string()),
+                messages: vec![LlmMessage {
+                    role: "user".to_string(),
+                    content: "Summarize".to_string(),
+                }],
+                max_tokens: 64,
+                temperature: None,
+                stop_sequences: Vec::new(),
+            })
+            .await
+            .unwrap();
+
+        mock.assert();
+        assert_eq!(completion.text, "Short summary.");
+        assert_eq!(completion.model, "claude-3-7-sonnet-20250219");
+        assert_eq!(completion.stop_reason, Some(LlmStopReason::EndTurn));
+        assert_eq!(
+            completion
+                .headers
+                .get("anthropic-ratelimit-requests-remaining")
+                .map(String::as_str),
+            Some("49")
+        );
+    }
+
+    #[tokio::test]
+    async fn embeddings_are_returned_in_input_order() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method("POST")
+                .path("/v1/embeddings")
+                .header("authorization", "Bearer sk-valid")
+                .json_body(serde_json::json!({
+                    "model": "text-embedding-3-small",
+                    "input": ["first", "second"]
+                }));
+            then.status(200).json_body(serde_json::json!({
+                "model": "text-embedding-3-small",
+                "data": [
+                    { "index": 1, "embedding": [0.0, 1.0] },
+                    { "index": 0, "embedding": [1.0, 0.0] }
+                ]
+            }));
+        });
+        let service = ConnectionService::new()
+            .unwrap()
+            .with_llm_endpoints(LlmEndpoints {
+                openai: server.url("/v1"),
+                ..LlmEndpoints::default()
+            });
+        let request = EmbeddingRequest {
+            provider: LlmProvider::Openai,
+            api_key: "sk-valid".into(),
+            model: "text-embedding-3-small".to_string(),
+            base_url: None,
+            inputs: vec!["first".to_string(), "second".to_string()],
+        };
+
+        let embeddings = service.embed(request.clone()).await.unwrap();
+        mock.assert();
+        assert_eq!(embeddings.vectors, vec![vec![1.0, 0.0], vec![0.0, 1.0]]);
+
+        let error = service
+            .embed(EmbeddingRequest {
+                provider: LlmProvider::Anthropic,
+                ..request
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Anthropic does not offer embeddings");
+    }
+
+    #[tokio::test]
+    async fn custom_openai_endpoint_is_probed_and_used_for_completions() {
+        let server = MockServer::start();
+        let models = server.mock(|when, then| {
+            when.method("GET")
+                .path("/gateway/v1/models")
+                .header("authorization", "Bearer litellm-key");
+            then.status(200)
+                .json_body(serde_json::json!({ "data": [] }));
+        });
+        let completions = server.mock(|when, then| {
+            when.method("POST").path("/gateway/v1/chat/completions");
+            then.status(200).json_body(serde_json::json!({
+                "model": "llama-3-70b",
+                "choices": [{
+                    "message": { "role": "assistant", "content": "Hi." },
+                    "finish_reason": "stop"
+                }]
+            }));
+        });
+        let rate_limiter = Arc::new(ProviderRateLimiter::new());
+        let service = ConnectionService::new()
+            .unwrap()
+            .with_rate_limiter(rate_limiter.clone());
+        let base_url = Some(format!("{}/", server.url("/gateway/v1")));
+
+        let result = service
+            .test_openai(LlmCredentials {
+                api_key: "litellm-key".to_string(),
+                model: "llama-3-70b".to_string(),
+                base_url: base_url.clone(),
+            })
+            .await
+            .unwrap();
+        assert!(result.success, "{:?}", result.error);
+        let status = rate_limiter.status(chrono::Utc::now());
+        assert_eq!(status.providers[0].provider, LlmProvider::Openai);
+        assert_eq!(status.providers[0].sent_last_minute, 1);
+
+        let completion = service
+            .complete(LlmCompletionRequest {
+                provider: LlmProvider::Openai,
+                api_key: "litellm-key".into(),
+                model: "llama-3-70b".to_string(),
+                base_url,
+                system: None,
+                messages: vec![LlmMessage {
+                    role: "user".to_string(),
+                    content: "Hello".to_string(),
+                }],
+                max_tokens: 16,
+                temperature: None,
+                stop_sequences: Vec::new(),
+            })
+            .await
+            .unwrap();
+
+        models.assert();
+        completions.assert();
+        assert_eq!(completion.text, "Hi.");
+    }
 }
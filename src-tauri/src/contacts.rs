@@ -0,0 +1,470 @@
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use tokio::sync::Mutex;
+
+use crate::entity_id::parse_entity_id;
+use crate::migrations::{self, Migration};
+
+pub const CONTACTS_MIGRATION_SCOPE: &str = "contacts";
+
+pub const CONTACTS_MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    description: "create contacts and hcs10 profile cache",
+    up: |connection| {
+        connection.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS contacts (
+                account_id TEXT PRIMARY KEY,
+                display_name TEXT NOT NULL,
+                avatar_url TEXT,
+                notes TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS hcs10_profile_cache (
+                account_id TEXT PRIMARY KEY,
+                display_name TEXT,
+                avatar_url TEXT,
+                profile TEXT NOT NULL,
+                fetched_at TEXT NOT NULL
+            );
+            "#,
+        )
+    },
+    down: |connection| {
+        connection.execute_batch(
+            "DROP TABLE IF EXISTS contacts; DROP TABLE IF EXISTS hcs10_profile_cache;",
+        )
+    },
+}];
+
+/// Accounts that show up in the transfer list of most transactions without
+/// being a party to them: the fee collection and staking reward accounts.
+const SYSTEM_ACCOUNTS: &[&str] = &["0.0.98", "0.0.800", "0.0.801"];
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Contact {
+    pub account_id: String,
+    pub display_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CounterpartySource {
+    Contact,
+    Hcs10Profile,
+}
+
+/// How an account is shown in transaction history.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Counterparty {
+    pub account_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<CounterpartySource>,
+}
+
+/// Names the user gave to accounts, plus the HCS-10 profiles fetched for
+/// accounts so far. A contact takes precedence over a cached profile.
+pub struct ContactBook {
+    db: Mutex<Connection>,
+}
+
+impl ContactBook {
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        let connection = Connection::open(path).map_err(|err| err.to_string())?;
+        migrations::apply(&connection, CONTACTS_MIGRATION_SCOPE, CONTACTS_MIGRATIONS)?;
+        Ok(Self {
+            db: Mutex::new(connection),
+        })
+    }
+
+    /// Moves over to the database of `other`, as when another workspace is
+    /// opened.
+    pub async fn replace(&self, other: Self) {
+        *self.db.lock().await = other.db.into_inner();
+    }
+
+    #[cfg(test)]
+    pub fn new_in_memory() -> Self {
+        let connection = Connection::open_in_memory().expect("in-memory sqlite");
+        migrations::apply(&connection, CONTACTS_MIGRATION_SCOPE, CONTACTS_MIGRATIONS)
+            .expect("init schema");
+        Self {
+            db: Mutex::new(connection),
+        }
+    }
+
+    fn map_row(row: &Row<'_>) -> Result<Contact, rusqlite::Error> {
+        Ok(Contact {
+            account_id: row.get("account_id")?,
+            display_name: row.get("display_name")?,
+            avatar_url: row.get("avatar_url")?,
+            notes: row.get("notes")?,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+
+    /// Stores the contact under the HIP-15 checksum form of `account_id` on
+    /// `network`, taking over a row saved before ids were normalized.
+    pub async fn upsert(
+        &self,
+        network: &str,
+        account_id: &str,
+        display_name: &str,
+        avatar_url: Option<&str>,
+        notes: Option<&str>,
+    ) -> Result<Contact, String> {
+        let (account_id, plain) = normalize_account_id(account_id, network)?;
+        let connection = self.db.lock().await;
+        let now = Utc::now().to_rfc3339();
+        connection
+            .execute(
+                "UPDATE OR IGNORE contacts SET account_id = ?1 WHERE account_id = ?2",
+                params![account_id, plain],
+            )
+            .map_err(|err| err.to_string())?;
+        connection
+            .execute("DELETE FROM contacts WHERE account_id = ?1", params![plain])
+            .map_err(|err| err.to_string())?;
+        connection
+            .execute(
+                "INSERT INTO contacts (account_id, display_name, avatar_url, notes, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?5)
+                 ON CONFLICT(account_id) DO UPDATE SET
+                     display_name = excluded.display_name,
+                     avatar_url = excluded.avatar_url,
+                     notes = excluded.notes,
+                     updated_at = excluded.updated_at",
+                params![account_id, display_name, avatar_url, notes, now],
+            )
+            .map_err(|err| err.to_string())?;
+        connection
+            .query_row(
+                "SELECT * FROM contacts WHERE account_id = ?1",
+                params![account_id],
+                Self::map_row,
+            )
+            .map_err(|err| err.to_string())
+    }
+
+    pub async fn list(&self) -> Result<Vec<Contact>, String> {
+        let connection = self.db.lock().await;
+        let mut statement = connection
+            .prepare("SELECT * FROM contacts ORDER BY display_name COLLATE NOCASE")
+            .map_err(|err| err.to_string())?;
+        statement
+            .query_map([], Self::map_row)
+            .map_err(|err| err.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| err.to_string())
+    }
+
+    pub async fn delete(&self, network: &str, account_id: &str) -> Result<bool, String> {
+        let (account_id, plain) = normalize_account_id(account_id, network)?;
+        let connection = self.db.lock().await;
+        let changes = connection
+            .execute(
+                "DELETE FROM contacts WHERE account_id IN (?1, ?2)",
+                params![account_id, plain],
+            )
+            .map_err(|err| err.to_string())?;
+        Ok(changes > 0)
+    }
+
+    /// Keeps the name and image of a profile returned by the HCS-10 bridge.
+    pub async fn cache_profile(&self, account_id: &str, profile: &Value) -> Result<(), String> {
+        let profile = profile.get("profile").unwrap_or(profile);
+        let field = |keys: &[&str]| {
+            keys.iter()
+                .find_map(|key| profile.get(*key).and_then(Value::as_str))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        let display_name = field(&["display_name", "displayName", "alias", "name"]);
+        let avatar_url = field(&["profileImage", "profile_image", "avatar"]);
+
+        self.db
+            .lock()
+            .await
+            .execute(
+                "INSERT OR REPLACE INTO hcs10_profile_cache (account_id, display_name, avatar_url, profile, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    account_id,
+                    display_name,
+                    avatar_url,
+                    profile.to_string(),
+                    Utc::now().to_rfc3339()
+                ],
+            )
+            .map_err(|err| err.to_string())?;
+        Ok(())
+    }
+
+    /// Display details for each of `account_ids` that is a contact or has a
+    /// cached profile, in one pass over the database.
+    pub async fn lookup(
+        &self,
+        network: &str,
+        account_ids: &BTreeSet<String>,
+    ) -> Result<HashMap<String, Counterparty>, String> {
+        let connection = self.db.lock().await;
+        let mut statement = connection
+            .prepare(
+                "SELECT display_name, avatar_url, 'contact' AS source, 0 AS priority
+                 FROM contacts WHERE account_id IN (?1, ?2)
+                 UNION ALL
+                 SELECT display_name, avatar_url, 'profile' AS source, 1 AS priority
+                 FROM hcs10_profile_cache WHERE account_id = ?1 AND display_name IS NOT NULL
+                 ORDER BY priority
+                 LIMIT 1",
+            )
+            .map_err(|err| err.to_string())?;
+
+        let mut found = HashMap::new();
+        for account_id in account_ids {
+            let checksummed = normalize_account_id(account_id, network)
+                .map(|(checksummed, _)| checksummed)
+                .unwrap_or_else(|_| account_id.clone());
+            let row = statement
+                .query_row(params![account_id, checksummed], |row| {
+                    Ok((
+                        row.get::<_, Option<String>>(0)?,
+                        row.get::<_, Option<String>>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                })
+                .optional()
+                .map_err(|err| err.to_string())?;
+            if let Some((display_name, avatar_url, source)) = row {
+                found.insert(
+                    account_id.clone(),
+                    Counterparty {
+                        account_id: account_id.clone(),
+                        display_name,
+                        avatar_url,
+                        source: Some(if source == "contact" {
+                            CounterpartySource::Contact
+                        } else {
+                            CounterpartySource::Hcs10Profile
+                        }),
+                    },
+                );
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// `account_id` with its HIP-15 checksum for `network`, and without. A
+/// checksum that was typed must match the network.
+fn normalize_account_id(account_id: &str, network: &str) -> Result<(String, String), String> {
+    let id = parse_entity_id(account_id, network)?;
+    Ok((id.with_checksum(network)?, id.to_string()))
+}
+
+/// The accounts on the other side of a mirror node transaction: everyone in
+/// its HBAR, token and NFT transfers except `own_account`, the submitting
+/// node and the system accounts.
+pub fn counterparty_accounts(transaction: &Value, own_account: Option<&str>) -> BTreeSet<String> {
+    let node = transaction.get("node").and_then(Value::as_str);
+    let mut accounts = BTreeSet::new();
+    let mut add = |account: Option<&str>| {
+        if let Some(account) = account
+            && Some(account) != own_account
+            && Some(account) != node
+            && !SYSTEM_ACCOUNTS.contains(&account)
+        {
+            accounts.insert(account.to_string());
+        }
+    };
+
+    for list in ["transfers", "token_transfers"] {
+        for transfer in transaction
+            .get(list)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            add(transfer.get("account").and_then(Value::as_str));
+        }
+    }
+    for transfer in transaction
+        .get("nft_transfers")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        add(transfer.get("sender_account_id").and_then(Value::as_str));
+        add(transfer.get("receiver_account_id").and_then(Value::as_str));
+    }
+    accounts
+}
+
+/// Adds a `counterparties` list to each transaction, with the display name
+/// and avatar of the accounts found in `known`.
+pub fn annotate_transactions(
+    transactions: &mut [Value],
+    own_account: Option<&str>,
+    known: &HashMap<String, Counterparty>,
+) {
+    for transaction in transactions.iter_mut() {
+        let counterparties: Vec<Counterparty> = counterparty_accounts(transaction, own_account)
+            .into_iter()
+            .map(|account_id| {
+                known.get(&account_id).cloned().unwrap_or(Counterparty {
+                    account_id,
+                    display_name: None,
+                    avatar_url: None,
+                    source: None,
+                })
+            })
+            .collect();
+        if let Some(object) = transaction.as_object_mut() {
+            object.insert("counterparties".to_string(), json!(counterparties));
+        }
+    }
+}
+
+/// Every counterparty across `transactions`, for a single `lookup`.
+pub fn all_counterparty_accounts(
+    transactions: &[Value],
+    own_account: Option<&str>,
+) -> BTreeSet<String> {
+    transactions
+        .iter()
+        .flat_map(|transaction| counterparty_accounts(transaction, own_account))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn history_is_annotated_from_contacts_then_profiles() {
+        let book = ContactBook::new_in_memory();
+        book.upsert(
+            "testnet",
+            "0.0.200",
+            "Alice",
+            Some("https://example.com/a.png"),
+            None,
+        )
+        .await
+        .unwrap();
+        book.cache_profile(
+            "0.0.300",
+            &json!({ "success": true, "profile": { "display_name": "Bot", "profileImage": "hcs://1/0.0.9" } }),
+        )
+        .await
+        .unwrap();
+        book.cache_profile("0.0.200", &json!({ "display_name": "Alice's agent" }))
+            .await
+            .unwrap();
+
+        let mut transactions = vec![
+            json!({
+                "node": "0.0.3",
+                "transfers": [
+                    { "account": "0.0.100", "amount": -105 },
+                    { "account": "0.0.200", "amount": 100 },
+                    { "account": "0.0.3", "amount": 2 },
+                    { "account": "0.0.98", "amount": 3 }
+                ],
+                "nft_transfers": [
+                    { "sender_account_id": "0.0.100", "receiver_account_id": "0.0.300" }
+                ]
+            }),
+            json!({ "transfers": [{ "account": "0.0.400", "amount": 1 }] }),
+        ];
+        let accounts = all_counterparty_accounts(&transactions, Some("0.0.100"));
+        assert_eq!(
+            accounts.iter().map(String::as_str).collect::<Vec<_>>(),
+            vec!["0.0.200", "0.0.300", "0.0.400"]
+        );
+
+        let known = book.lookup("testnet", &accounts).await.unwrap();
+        assert_eq!(known.len(), 2);
+        annotate_transactions(&mut transactions, Some("0.0.100"), &known);
+
+        let first = transactions[0]["counterparties"].as_array().unwrap();
+        assert_eq!(first[0]["displayName"], json!("Alice"));
+        assert_eq!(first[0]["source"], json!("contact"));
+        assert_eq!(first[1]["displayName"], json!("Bot"));
+        assert_eq!(first[1]["avatarUrl"], json!("hcs://1/0.0.9"));
+        assert_eq!(first[1]["source"], json!("hcs10Profile"));
+        assert_eq!(
+            transactions[1]["counterparties"],
+            json!([{ "accountId": "0.0.400" }])
+        );
+
+        assert!(book.delete("testnet", "0.0.200").await.unwrap());
+        let known = book.lookup("testnet", &accounts).await.unwrap();
+        assert_eq!(
+            known["0.0.200"].display_name.as_deref(),
+            Some("Alice's agent")
+        );
+    }
+
+    #[tokio::test]
+    async fn contacts_are_stored_once_in_checksum_form() {
+        let book = ContactBook::new_in_memory();
+        book.db
+            .lock()
+            .await
+            .execute(
+                "INSERT INTO contacts (account_id, display_name, created_at, updated_at)
+                 VALUES ('0.0.200', 'Legacy', 'then', 'then')",
+                [],
+            )
+            .unwrap();
+        let checksummed = parse_entity_id("0.0.200", "testnet")
+            .unwrap()
+            .with_checksum("testnet")
+            .unwrap();
+
+        let contact = book
+            .upsert("testnet", "0.0.200", "Alice", None, None)
+            .await
+            .unwrap();
+        assert_eq!(contact.account_id, checksummed);
+        assert_eq!(contact.created_at, "then");
+        book.upsert("testnet", &checksummed, "Alice B", None, None)
+            .await
+            .unwrap();
+        let contacts = book.list().await.unwrap();
+        assert_eq!(contacts.len(), 1);
+        assert_eq!(contacts[0].display_name, "Alice B");
+
+        assert!(
+            book.upsert("testnet", "0.0.200-aaaaa", "Mallory", None, None)
+                .await
+                .is_err()
+        );
+        assert!(book.delete("testnet", "0.0.200").await.unwrap());
+        assert!(book.list().await.unwrap().is_empty());
+    }
+}
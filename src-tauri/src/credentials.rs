@@ -5,72 +5,635 @@ use aes_gcm::{
 };
 use anyhow::{Result, anyhow};
 use base64::{Engine as _, engine::general_purpose::STANDARD};
+use once_cell::sync::Lazy;
 use rand::RngCore;
-use scrypt::{Params as ScryptParams, scrypt};
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex as StdMutex, MutexGuard, RwLock};
+use std::time::{Duration, Instant};
 use tokio::{fs, io::AsyncWriteExt, sync::Mutex};
+use zeroize::Zeroizing;
+
+use crate::config::LEGACY_MASTER_PASSWORD;
+use crate::kdf::Kdf;
+use crate::secret::Secret;
+use crate::totp::{self, TotpCode};
+
+const ACCESS_LOG_CAPACITY: usize = 200;
+/// A read only rewrites credentials.dat to record its time when the last
+/// recorded read is at least this old.
+const LAST_ACCESS_INTERVAL_MS: u64 = 60_000;
+const REFERENCE_PREFIX: &str = "${credential:";
+/// Keyring service names are this prefix followed by `/<service>`, or by
+/// `/<profile>/<service>` for entries of a named profile.
+const KEYRING_SERVICE_PREFIX: &str = "com.hashgraphonline.desktop.credentials";
+
+/// The profile of the active Hedera account, which commands and
+/// `${credential:..}` references use.
+static ACTIVE_PROFILE: Lazy<RwLock<Option<String>>> = Lazy::new(|| RwLock::new(None));
+
+/// Makes `profile`, the name of the active Hedera account, the one
+/// credentials are read and written under. `None` selects the default.
+pub fn set_active_profile(profile: Option<&str>) {
+    *ACTIVE_PROFILE
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = profile.map(str::to_owned);
+}
+
+fn active_profile() -> Option<String> {
+    ACTIVE_PROFILE
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+/// credentials.dat starts with this, the key derivation id, `:`, the
+/// encrypted canary and a newline. Files from before the canary end the
+/// line after the id, and ones from before the header used scrypt.
+const FILE_HEADER: &[u8] = b"hol-credentials:";
+/// What the canary decrypts to under the right master password.
+const CANARY: &str = "hol-credentials-canary";
+
+/// What a credential may be used for. Credentials stored before scopes
+/// existed are `general`, which only the user can read.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CredentialScope {
+    #[default]
+    General,
+    Mcp,
+    Llm,
+    Hedera,
+}
+
+impl fmt::Display for CredentialScope {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            Self::General => "general",
+            Self::Mcp => "mcp",
+            Self::Llm => "llm",
+            Self::Hedera => "hedera",
+        })
+    }
+}
+
+/// What a credential's secret is. A `totp` secret is an `otpauth://` URI
+/// that one-time codes are generated from.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CredentialKind {
+    #[default]
+    Password,
+    Totp,
+}
+
+/// The subsystem reading a credential.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CredentialConsumer {
+    /// The renderer, acting on behalf of the user.
+    User,
+    /// Environment resolution for MCP server processes.
+    Mcp,
+    /// The conversational agent bridge.
+    AgentBridge,
+}
+
+impl CredentialConsumer {
+    pub fn allows(self, scope: CredentialScope) -> bool {
+        match self {
+            Self::User => true,
+            Self::Mcp => scope == CredentialScope::Mcp,
+            Self::AgentBridge => matches!(scope, CredentialScope::Llm | CredentialScope::Hedera),
+        }
+    }
+}
+
+impl fmt::Display for CredentialConsumer {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            Self::User => "user",
+            Self::Mcp => "MCP",
+            Self::AgentBridge => "agent bridge",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialAccess {
+    pub at: String,
+    pub consumer: CredentialConsumer,
+    pub profile: Option<String>,
+    pub service: String,
+    pub account: String,
+    pub granted: bool,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct StoredCredential {
+    /// Hedera account the entry belongs to. Entries without one, including
+    /// all from before profiles, belong to the default profile.
+    #[serde(default)]
+    pub profile: Option<String>,
     pub service: String,
     pub account: String,
     pub encrypted_password: String,
     pub created_at: u64,
+    #[serde(default)]
+    pub scope: CredentialScope,
+    #[serde(default)]
+    pub kind: CredentialKind,
+    /// The secret is in the OS keyring and `encrypted_password` is empty.
+    #[serde(default)]
+    pub in_keyring: bool,
+    /// When the secret was last handed out, in milliseconds.
+    #[serde(default)]
+    pub last_accessed_at: Option<u64>,
+    /// Key derivation `encrypted_password` was written with. Entries from
+    /// before it was recorded used scrypt.
+    #[serde(default = "legacy_kdf")]
+    pub kdf: Kdf,
+}
+
+fn legacy_kdf() -> Kdf {
+    Kdf::Scrypt
+}
+
+impl StoredCredential {
+    fn keyring_service(&self) -> String {
+        keyring_service(self.profile.as_deref(), &self.service)
+    }
+}
+
+fn keyring_service(profile: Option<&str>, service: &str) -> String {
+    match profile {
+        Some(profile) => format!("{profile}/{service}"),
+        None => service.to_owned(),
+    }
+}
+
+/// A stored credential without its secret.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialSummary {
+    pub profile: Option<String>,
+    pub service: String,
+    pub account: String,
+    pub scope: CredentialScope,
+    pub kind: CredentialKind,
+    pub created_at: u64,
+    pub last_accessed_at: Option<u64>,
+    pub in_keyring: bool,
+}
+
+/// Outcome of checking the store's master password against credentials.dat.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum MasterPasswordCheck {
+    Correct,
+    /// The canary does not decrypt under the password, as after the
+    /// keychain entry holding it was replaced.
+    Wrong,
+    /// The canary decrypts but the credentials do not.
+    Corrupted,
+    /// Nothing is stored yet.
+    Empty,
+    /// The file predates the canary and does not decrypt, which looks the
+    /// same whether the password is wrong or the file is damaged.
+    Inconclusive,
+}
+
+/// The parts of credentials.dat.
+struct StoreFile<'a> {
+    kdf: Kdf,
+    canary: Option<&'a str>,
+    payload: &'a [u8],
+}
+
+fn parse_store(data: &[u8]) -> Result<StoreFile<'_>> {
+    let Some(rest) = data.strip_prefix(FILE_HEADER) else {
+        return Ok(StoreFile {
+            kdf: Kdf::Scrypt,
+            canary: None,
+            payload: data,
+        });
+    };
+    let end = rest
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .ok_or_else(|| anyhow!("credentials file header is incomplete"))?;
+    let line = std::str::from_utf8(&rest[..end])
+        .map_err(|_| anyhow!("credentials file header is not valid UTF-8"))?;
+    let (id, canary) = match line.split_once(':') {
+        Some((id, canary)) => (id, Some(canary)),
+        None => (line, None),
+    };
+    let kdf = Kdf::from_id(id).ok_or_else(|| anyhow!("unsupported key derivation: {id}"))?;
+    Ok(StoreFile {
+        kdf,
+        canary,
+        payload: &rest[end + 1..],
+    })
+}
+
+/// Keeps credential secrets outside credentials.dat, which then only holds
+/// their service, account and scope.
+pub trait KeyringStore: Send + Sync {
+    fn get(&self, service: &str, account: &str) -> Result<Option<Secret>>;
+    fn set(&self, service: &str, account: &str, secret: &str) -> Result<()>;
+    fn delete(&self, service: &str, account: &str) -> Result<()>;
+}
+
+/// The OS keyring: Keychain on macOS, the Credential Manager on Windows and
+/// the Secret Service on Linux.
+pub struct OsKeyring;
+
+impl OsKeyring {
+    /// The keyring when this machine has a usable one.
+    pub fn detect() -> Option<Self> {
+        let probe = Self::entry("probe", "probe").and_then(|entry| match entry.get_password() {
+            Ok(_) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(error) => Err(anyhow!(error)),
+        });
+        match probe {
+            Ok(()) => Some(Self),
+            Err(error) => {
+                log::info!(
+                    "OS keyring unavailable, keeping credentials in credentials.dat: {error}"
+                );
+                None
+            }
+        }
+    }
+
+    fn entry(service: &str, account: &str) -> Result<keyring::Entry> {
+        keyring::Entry::new(&format!("{KEYRING_SERVICE_PREFIX}/{service}"), account)
+            .map_err(|error| anyhow!("Failed to open keyring entry: {error}"))
+    }
+}
+
+impl KeyringStore for OsKeyring {
+    fn get(&self, service: &str, account: &str) -> Result<Option<Secret>> {
+        match Self::entry(service, account)?.get_password() {
+            Ok(secret) => Ok(Some(secret.into())),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(error) => Err(anyhow!(
+                "Failed to read {service}/{account} from keyring: {error}"
+            )),
+        }
+    }
+
+    fn set(&self, service: &str, account: &str, secret: &str) -> Result<()> {
+        Self::entry(service, account)?
+            .set_password(secret)
+            .map_err(|error| anyhow!("Failed to store {service}/{account} in keyring: {error}"))
+    }
+
+    fn delete(&self, service: &str, account: &str) -> Result<()> {
+        match Self::entry(service, account)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(error) => Err(anyhow!(
+                "Failed to delete {service}/{account} from keyring: {error}"
+            )),
+        }
+    }
 }
 
+/// Clones share the file lock, the lock state and the access log. With a
+/// keyring, new secrets go there and credentials.dat keeps the rest of each
+/// entry. Reads and writes only see entries of the manager's profile.
+#[derive(Clone)]
 pub struct CredentialManager {
     path: PathBuf,
-    master_password: String,
+    profile: Option<String>,
     lock: Arc<Mutex<()>>,
+    lock_state: Arc<StdMutex<LockState>>,
+    access_log: Arc<StdMutex<VecDeque<CredentialAccess>>>,
+    keyring: Option<Arc<dyn KeyringStore>>,
+}
+
+/// Holds the master password while the store is unlocked. Locking swaps it
+/// for a key derived from it, which `unlock_store` checks passwords against.
+///
+/// The lock only gates credential reads and writes through this manager. It
+/// is not at-rest or in-memory protection: `ConfigState` keeps the same key
+/// for config secrets while the store is locked, and `credential_unlock`
+/// reuses it after OS authentication.
+struct LockState {
+    master_password: Option<Secret>,
+    verifier: Option<([u8; 32], Zeroizing<Vec<u8>>)>,
+    last_used: Instant,
+    idle_timeout: Option<Duration>,
+}
+
+impl LockState {
+    fn lock(&mut self) -> Result<()> {
+        let Some(password) = self.master_password.as_ref() else {
+            return Ok(());
+        };
+        let mut salt = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let key = Kdf::CURRENT
+            .derive_key(password.expose(), &salt)
+            .map_err(|error| anyhow!(error))?;
+        self.verifier = Some((salt, key));
+        self.master_password = None;
+        Ok(())
+    }
+
+    fn is_idle(&self) -> bool {
+        self.idle_timeout
+            .is_some_and(|timeout| self.last_used.elapsed() >= timeout)
+    }
+}
+
+/// Read access to the credentials one subsystem's scopes allow.
+pub struct ScopedCredentials<'a> {
+    manager: &'a CredentialManager,
+    consumer: CredentialConsumer,
 }
 
 impl CredentialManager {
     pub fn new(path: PathBuf, master_password: impl Into<String>) -> Self {
         Self {
             path,
-            master_password: master_password.into(),
+            profile: None,
             lock: Arc::new(Mutex::new(())),
+            lock_state: Arc::new(StdMutex::new(LockState {
+                master_password: Some(Secret::from(master_password.into())),
+                verifier: None,
+                last_used: Instant::now(),
+                idle_timeout: None,
+            })),
+            access_log: Arc::new(StdMutex::new(VecDeque::new())),
+            keyring: None,
+        }
+    }
+
+    pub fn with_keyring(mut self, keyring: Arc<dyn KeyringStore>) -> Self {
+        self.keyring = Some(keyring);
+        self
+    }
+
+    /// Locks the store after `timeout` without use. `None` keeps it unlocked
+    /// until `lock_store`.
+    pub fn set_idle_timeout(&self, timeout: Option<Duration>) {
+        self.lock_state().idle_timeout = timeout;
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.lock_state().master_password.is_none()
+    }
+
+    /// Forgets the master password until `unlock_store` is given it again.
+    pub fn lock_store(&self) -> Result<()> {
+        self.lock_state().lock()
+    }
+
+    /// Locks the store when it has been idle past its timeout. Returns
+    /// whether it was locked by this call.
+    pub fn lock_if_idle(&self) -> Result<bool> {
+        let mut state = self.lock_state();
+        if state.master_password.is_none() || !state.is_idle() {
+            return Ok(false);
+        }
+        state.lock()?;
+        Ok(true)
+    }
+
+    pub fn unlock_store(&self, master_password: &str) -> Result<()> {
+        let mut state = self.lock_state();
+        let matches = match (&state.master_password, &state.verifier) {
+            (Some(current), _) => current.expose() == master_password,
+            (None, Some((salt, key))) => {
+                Kdf::CURRENT
+                    .derive_key(master_password, salt)
+                    .map_err(|error| anyhow!(error))?
+                    == *key
+            }
+            (None, None) => false,
+        };
+        if !matches {
+            return Err(anyhow!("wrong master password"));
+        }
+        state.master_password = Some(master_password.into());
+        state.verifier = None;
+        state.last_used = Instant::now();
+        Ok(())
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, LockState> {
+        self.lock_state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The master password, counting as use of the store. Fails while the
+    /// store is locked, locking it first when it has been idle too long.
+    fn master_password(&self) -> Result<Secret> {
+        let mut state = self.lock_state();
+        if state.master_password.is_some() && state.is_idle() {
+            state.lock()?;
+        }
+        let password = state
+            .master_password
+            .clone()
+            .ok_or_else(|| anyhow!("credential store is locked"))?;
+        state.last_used = Instant::now();
+        Ok(password)
+    }
+
+    /// A manager over the entries of `profile`, sharing this one's file lock
+    /// and access log. `None` or a blank name selects the default profile.
+    pub fn for_profile(&self, profile: Option<&str>) -> Result<Self> {
+        let profile = profile.map(str::trim).filter(|name| !name.is_empty());
+        if profile.is_some_and(|name| name.contains('/')) {
+            return Err(anyhow!("profile names must not contain '/'"));
+        }
+        Ok(Self {
+            profile: profile.map(str::to_owned),
+            ..self.clone()
+        })
+    }
+
+    /// A manager over the entries of the active Hedera account.
+    pub fn active(&self) -> Result<Self> {
+        self.for_profile(active_profile().as_deref())
+    }
+
+    fn holds(&self, item: &StoredCredential, service: &str, account: &str) -> bool {
+        item.profile == self.profile && item.service == service && item.account == account
+    }
+
+    pub fn scoped(&self, consumer: CredentialConsumer) -> ScopedCredentials<'_> {
+        ScopedCredentials {
+            manager: self,
+            consumer,
         }
     }
 
     pub async fn store(&self, service: &str, account: &str, password: &str) -> Result<bool> {
+        self.store_with_scope(service, account, password, None)
+            .await
+    }
+
+    /// Stores a credential under `scope`, or under the scope it already had
+    /// when `scope` is `None`.
+    pub async fn store_with_scope(
+        &self,
+        service: &str,
+        account: &str,
+        password: &str,
+        scope: Option<CredentialScope>,
+    ) -> Result<bool> {
+        self.store_entry(service, account, password, scope, CredentialKind::Password)
+            .await
+    }
+
+    /// Stores a 2FA secret, given as an `otpauth://totp/` URI or a base32
+    /// secret, that codes can then be generated from.
+    pub async fn store_totp(
+        &self,
+        service: &str,
+        account: &str,
+        secret: &str,
+        scope: Option<CredentialScope>,
+    ) -> Result<bool> {
+        let uri = Secret::from(totp::normalize(secret, account).map_err(|error| anyhow!(error))?);
+        self.store_entry(service, account, uri.expose(), scope, CredentialKind::Totp)
+            .await
+    }
+
+    async fn store_entry(
+        &self,
+        service: &str,
+        account: &str,
+        password: &str,
+        scope: Option<CredentialScope>,
+        kind: CredentialKind,
+    ) -> Result<bool> {
         if service.trim().is_empty() || account.trim().is_empty() {
             return Err(anyhow!("service and account must not be empty"));
         }
 
         let _guard = self.lock.lock().await;
         let mut credentials = self.load_credentials().await?;
-        let encrypted_password = self.encrypt_password(password)?;
+        let previous = credentials
+            .iter()
+            .find(|item| self.holds(item, service, account));
+        let scope = scope.unwrap_or_else(|| previous.map(|item| item.scope).unwrap_or_default());
+        let was_in_keyring = previous.is_some_and(|item| item.in_keyring);
+        let last_accessed_at = previous.and_then(|item| item.last_accessed_at);
 
-        credentials.retain(|item| !(item.service == service && item.account == account));
+        let keyring_name = keyring_service(self.profile.as_deref(), service);
+        let in_keyring = match &self.keyring {
+            Some(keyring) => match keyring.set(&keyring_name, account, password) {
+                Ok(()) => true,
+                Err(error) => {
+                    log::warn!("{error}; keeping it in credentials.dat");
+                    false
+                }
+            },
+            None => false,
+        };
+        let encrypted_password = if in_keyring {
+            String::new()
+        } else {
+            if was_in_keyring {
+                self.delete_from_keyring(&keyring_name, account);
+            }
+            self.encrypt_password(password)?
+        };
+
+        credentials.retain(|item| !self.holds(item, service, account));
         credentials.push(StoredCredential {
+            profile: self.profile.clone(),
             service: service.to_owned(),
             account: account.to_owned(),
             encrypted_password,
             created_at: chrono::Utc::now().timestamp_millis() as u64,
+            scope,
+            kind,
+            in_keyring,
+            last_accessed_at,
+            kdf: Kdf::CURRENT,
         });
 
         self.save_credentials(&credentials).await?;
         Ok(true)
     }
 
-    pub async fn get(&self, service: &str, account: &str) -> Result<Option<String>> {
-        if service.trim().is_empty() || account.trim().is_empty() {
-            return Err(anyhow!("service and account must not be empty"));
-        }
+    pub async fn get(&self, service: &str, account: &str) -> Result<Option<Secret>> {
+        self.scoped(CredentialConsumer::User)
+            .get(service, account)
+            .await
+    }
+
+    pub async fn generate_totp(&self, service: &str, account: &str) -> Result<Option<TotpCode>> {
+        self.scoped(CredentialConsumer::User)
+            .totp(service, account)
+            .await
+    }
 
+    /// Every credential of the manager's profile, sorted by service and
+    /// account. Secrets are left out.
+    pub async fn list(&self) -> Result<Vec<CredentialSummary>> {
         let _guard = self.lock.lock().await;
-        let credentials = self.load_credentials().await?;
-        if let Some(credential) = credentials
+        let mut summaries: Vec<CredentialSummary> = self
+            .load_credentials()
+            .await?
             .into_iter()
-            .find(|item| item.service == service && item.account == account)
-        {
-            return Ok(Some(self.decrypt_password(&credential.encrypted_password)?));
-        }
+            .filter(|credential| credential.profile == self.profile)
+            .map(|credential| CredentialSummary {
+                profile: credential.profile,
+                service: credential.service,
+                account: credential.account,
+                scope: credential.scope,
+                kind: credential.kind,
+                created_at: credential.created_at,
+                last_accessed_at: credential.last_accessed_at,
+                in_keyring: credential.in_keyring,
+            })
+            .collect();
+        summaries.sort_by(|a, b| (&a.service, &a.account).cmp(&(&b.service, &b.account)));
+        Ok(summaries)
+    }
+
+    /// Recent reads, newest first, including refused ones.
+    pub fn access_log(&self) -> Vec<CredentialAccess> {
+        self.access_log
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .rev()
+            .cloned()
+            .collect()
+    }
 
-        Ok(None)
+    fn record_access(
+        &self,
+        consumer: CredentialConsumer,
+        service: &str,
+        account: &str,
+        granted: bool,
+    ) {
+        let mut log = self
+            .access_log
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if log.len() == ACCESS_LOG_CAPACITY {
+            log.pop_front();
+        }
+        log.push_back(CredentialAccess {
+            at: chrono::Utc::now().to_rfc3339(),
+            consumer,
+            profile: self.profile.clone(),
+            service: service.to_owned(),
+            account: account.to_owned(),
+            granted,
+        });
     }
 
     pub async fn delete(&self, service: &str, account: &str) -> Result<bool> {
@@ -78,15 +641,20 @@ impl CredentialManager {
             return Err(anyhow!("service and account must not be empty"));
         }
 
+        let _guard = self.lock.lock().await;
         let mut credentials = self.load_credentials().await?;
-        let original_len = credentials.len();
-        credentials.retain(|item| !(item.service == service && item.account == account));
-
-        if credentials.len() == original_len {
+        let Some(index) = credentials
+            .iter()
+            .position(|item| self.holds(item, service, account))
+        else {
             return Ok(false);
-        }
+        };
 
+        let removed = credentials.remove(index);
         self.save_credentials(&credentials).await?;
+        if removed.in_keyring {
+            self.delete_from_keyring(&removed.keyring_service(), account);
+        }
         Ok(true)
     }
 
@@ -96,72 +664,262 @@ impl CredentialManager {
         }
 
         let _guard = self.lock.lock().await;
-        let mut credentials = self.load_credentials().await?;
-        let removed = credentials
-            .iter()
-            .filter(|item| item.service == service)
-            .count() as u32;
+        let (removed, kept): (Vec<_>, Vec<_>) = self
+            .load_credentials()
+            .await?
+            .into_iter()
+            .partition(|item| item.profile == self.profile && item.service == service);
 
-        if removed == 0 {
+        if removed.is_empty() {
             return Ok(0);
         }
 
-        credentials.retain(|item| item.service != service);
-        self.save_credentials(&credentials).await?;
-        Ok(removed)
+        self.save_credentials(&kept).await?;
+        for item in removed.iter().filter(|item| item.in_keyring) {
+            self.delete_from_keyring(&item.keyring_service(), &item.account);
+        }
+        Ok(removed.len() as u32)
+    }
+
+    /// Moves secrets still kept in credentials.dat into the keyring. Ones that
+    /// fail to decrypt or that the keyring refuses stay in the file. Returns
+    /// how many were moved.
+    pub async fn migrate_to_keyring(&self) -> Result<usize> {
+        let Some(keyring) = self.keyring.as_ref() else {
+            return Ok(0);
+        };
+
+        let _guard = self.lock.lock().await;
+        let mut credentials = self.load_credentials().await?;
+        let mut moved = Vec::new();
+        for credential in credentials.iter_mut().filter(|item| !item.in_keyring) {
+            let password = match self
+                .decrypt_password(&credential.encrypted_password, credential.kdf)
+            {
+                Ok(password) => password,
+                Err(error) => {
+                    log::warn!(
+                        "Failed to decrypt credential {}/{}: {error}; keeping it in credentials.dat",
+                        credential.service,
+                        credential.account
+                    );
+                    continue;
+                }
+            };
+            match keyring.set(
+                &credential.keyring_service(),
+                &credential.account,
+                password.expose(),
+            ) {
+                Ok(()) => {
+                    credential.encrypted_password = String::new();
+                    credential.in_keyring = true;
+                    moved.push((credential.keyring_service(), credential.account.clone()));
+                }
+                Err(error) => log::warn!("{error}; keeping it in credentials.dat"),
+            }
+        }
+        if moved.is_empty() {
+            return Ok(0);
+        }
+        // credentials.dat still holds every secret until this save, so the
+        // keyring copies are dropped again when it fails.
+        if let Err(error) = self.save_credentials(&credentials).await {
+            for (service, account) in &moved {
+                self.delete_from_keyring(service, account);
+            }
+            return Err(error);
+        }
+        Ok(moved.len())
+    }
+
+    fn delete_from_keyring(&self, service: &str, account: &str) {
+        if let Some(keyring) = self.keyring.as_ref()
+            && let Err(error) = keyring.delete(service, account)
+        {
+            log::warn!("{error}");
+        }
+    }
+
+    /// The secret of `credential`, wherever it is kept. `None` when its
+    /// keyring entry was removed outside the app.
+    fn read_secret(&self, credential: &StoredCredential) -> Result<Option<Secret>> {
+        if !credential.in_keyring {
+            return self
+                .decrypt_password(&credential.encrypted_password, credential.kdf)
+                .map(Some);
+        }
+        let keyring = self.keyring.as_ref().ok_or_else(|| {
+            anyhow!(
+                "credential {}/{} is in the OS keyring, which is unavailable",
+                credential.service,
+                credential.account
+            )
+        })?;
+        keyring.get(&credential.keyring_service(), &credential.account)
+    }
+
+    /// Re-encrypts the stored credentials under `master_password` and
+    /// switches this manager to it. Returns how many were moved.
+    pub async fn rekey(&mut self, master_password: &str) -> Result<usize> {
+        let _guard = self.lock.lock().await;
+        let target = CredentialManager::new(self.path.clone(), master_password);
+        let credentials = self.rewrap_for(&target, self.load_credentials().await?)?;
+        if fs::try_exists(&self.path).await? {
+            target.save_credentials(&credentials).await?;
+        }
+        self.lock_state().master_password = Some(master_password.into());
+        Ok(credentials.len())
     }
 
+    /// Re-encrypts the file-backed entries of `credentials` for `target`.
+    fn rewrap_for(
+        &self,
+        target: &CredentialManager,
+        credentials: Vec<StoredCredential>,
+    ) -> Result<Vec<StoredCredential>> {
+        credentials
+            .into_iter()
+            .map(|credential| {
+                if credential.in_keyring {
+                    return Ok(credential);
+                }
+                let password =
+                    self.decrypt_password(&credential.encrypted_password, credential.kdf)?;
+                Ok(StoredCredential {
+                    encrypted_password: target.encrypt_password(password.expose())?,
+                    kdf: Kdf::CURRENT,
+                    ..credential
+                })
+            })
+            .collect()
+    }
+
+    /// Reads credentials.dat. A file still under the legacy password, which
+    /// moving to the keychain key missed, is moved to this manager's key.
     async fn load_credentials(&self) -> Result<Vec<StoredCredential>> {
+        let master_password = self.master_password()?;
         let data = match fs::read(&self.path).await {
             Ok(data) => data,
             Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
             Err(error) => return Err(anyhow!(error)),
         };
-        let decrypted = self.decrypt_password_bytes(&data)?;
+        let file = parse_store(&data)?;
+        let error = match self.read_store(&file) {
+            Ok(credentials) => return Ok(credentials),
+            Err(error) => error,
+        };
+        if master_password.expose() == LEGACY_MASTER_PASSWORD {
+            return Err(error);
+        }
+        let legacy = CredentialManager::new(self.path.clone(), LEGACY_MASTER_PASSWORD);
+        let Ok(credentials) = legacy.read_store(&file) else {
+            return Err(error);
+        };
+        let credentials = legacy.rewrap_for(self, credentials)?;
+        self.save_credentials(&credentials).await?;
+        log::info!("Moved credentials.dat from the legacy password to the master key");
+        Ok(credentials)
+    }
+
+    fn read_store(&self, file: &StoreFile) -> Result<Vec<StoredCredential>> {
+        let decrypted = match self.decrypt_password_bytes(file.payload, file.kdf) {
+            Ok(decrypted) => decrypted,
+            Err(error) => {
+                return Err(match self.canary_matches(file) {
+                    Some(true) => anyhow!("credentials.dat is corrupted"),
+                    Some(false) => anyhow!("wrong master password for credentials.dat"),
+                    None => error,
+                });
+            }
+        };
         let credentials: Vec<StoredCredential> = serde_json::from_slice(&decrypted)?;
         Ok(credentials)
     }
 
+    /// Whether the canary decrypts under this manager's master password.
+    /// `None` when the file has no canary.
+    fn canary_matches(&self, file: &StoreFile) -> Option<bool> {
+        let canary = file.canary?;
+        Some(
+            self.decrypt_password(canary, file.kdf)
+                .is_ok_and(|plaintext| plaintext.expose() == CANARY),
+        )
+    }
+
+    /// Checks the master password the store holds, usually the keychain
+    /// key, against the canary in credentials.dat, so a key that no longer
+    /// matches can be told apart from a damaged file. Fails while the store
+    /// is locked.
+    pub async fn verify_master_password(&self) -> Result<MasterPasswordCheck> {
+        self.master_password()?;
+        let _guard = self.lock.lock().await;
+        let data = match fs::read(&self.path).await {
+            Ok(data) => data,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(MasterPasswordCheck::Empty);
+            }
+            Err(error) => return Err(anyhow!(error)),
+        };
+        let Ok(file) = parse_store(&data) else {
+            return Ok(MasterPasswordCheck::Corrupted);
+        };
+        let opens = self
+            .decrypt_password_bytes(file.payload, file.kdf)
+            .is_ok_and(|decrypted| {
+                serde_json::from_slice::<Vec<StoredCredential>>(&decrypted).is_ok()
+            });
+        Ok(match (opens, self.canary_matches(&file)) {
+            (true, _) => MasterPasswordCheck::Correct,
+            (false, Some(true)) => MasterPasswordCheck::Corrupted,
+            (false, Some(false)) => MasterPasswordCheck::Wrong,
+            (false, None) => MasterPasswordCheck::Inconclusive,
+        })
+    }
+
     async fn save_credentials(&self, credentials: &[StoredCredential]) -> Result<()> {
         let serialized = serde_json::to_vec(credentials)?;
-        let encrypted = self.encrypt_password_bytes(&serialized)?;
+        // The canary and the payload share a salt, so saving runs the key
+        // derivation once.
+        let mut salt = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let key = self.derive_key(&salt, Kdf::CURRENT)?;
+        let canary = STANDARD.encode(seal(&key, &salt, CANARY.as_bytes())?);
+        let mut contents = [
+            FILE_HEADER,
+            Kdf::CURRENT.id().as_bytes(),
+            b":",
+            canary.as_bytes(),
+            b"\n",
+        ]
+        .concat();
+        contents.extend(seal(&key, &salt, &serialized)?);
         let mut file = fs::File::create(&self.path).await?;
-        file.write_all(&encrypted).await?;
+        file.write_all(&contents).await?;
         file.flush().await?;
         Ok(())
     }
 
     fn encrypt_password(&self, password: &str) -> Result<String> {
-        let encrypted = self.encrypt_password_bytes(password.as_bytes())?;
+        let encrypted = self.encrypt_password_bytes(password.as_bytes(), Kdf::CURRENT)?;
         Ok(STANDARD.encode(encrypted))
     }
 
-    fn encrypt_password_bytes(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+    fn encrypt_password_bytes(&self, plaintext: &[u8], kdf: Kdf) -> Result<Vec<u8>> {
         let mut salt = [0u8; 32];
         rand::thread_rng().fill_bytes(&mut salt);
 
-        let key = self.derive_key(&salt)?;
-        let cipher = Aes256Gcm::new(GenericArray::from_slice(&key));
-
-        let mut nonce_bytes = [0u8; 12];
-        rand::thread_rng().fill_bytes(&mut nonce_bytes);
-        let nonce = Nonce::from_slice(&nonce_bytes);
-        let ciphertext = cipher.encrypt(nonce, plaintext)?;
-
-        let mut combined = Vec::with_capacity(salt.len() + nonce_bytes.len() + ciphertext.len());
-        combined.extend_from_slice(&salt);
-        combined.extend_from_slice(&nonce_bytes);
-        combined.extend_from_slice(&ciphertext);
-        Ok(combined)
+        let key = self.derive_key(&salt, kdf)?;
+        seal(&key, &salt, plaintext)
     }
 
-    fn decrypt_password(&self, encrypted: &str) -> Result<String> {
+    fn decrypt_password(&self, encrypted: &str, kdf: Kdf) -> Result<Secret> {
         let decoded = STANDARD.decode(encrypted)?;
-        let decrypted = self.decrypt_password_bytes(&decoded)?;
-        Ok(String::from_utf8(decrypted)?)
+        let decrypted = self.decrypt_password_bytes(&decoded, kdf)?;
+        Ok(std::str::from_utf8(&decrypted)?.into())
     }
 
-    fn decrypt_password_bytes(&self, combined: &[u8]) -> Result<Vec<u8>> {
+    fn decrypt_password_bytes(&self, combined: &[u8], kdf: Kdf) -> Result<Zeroizing<Vec<u8>>> {
         if combined.len() < 32 + 12 {
             return Err(anyhow!("encrypted payload too short"));
         }
@@ -169,18 +927,150 @@ impl CredentialManager {
         let (salt, rest) = combined.split_at(32);
         let (nonce_bytes, ciphertext) = rest.split_at(12);
 
-        let key = self.derive_key(salt)?;
+        let key = self.derive_key(salt, kdf)?;
         let cipher = Aes256Gcm::new(GenericArray::from_slice(&key));
         let nonce = Nonce::from_slice(nonce_bytes);
-        let plaintext = cipher.decrypt(nonce, ciphertext)?;
-        Ok(plaintext)
+        Ok(Zeroizing::new(cipher.decrypt(nonce, ciphertext)?))
+    }
+
+    fn derive_key(&self, salt: &[u8], kdf: Kdf) -> Result<Zeroizing<Vec<u8>>> {
+        kdf.derive_key(self.master_password()?.expose(), salt)
+            .map_err(|error| anyhow!(error))
+    }
+}
+
+/// Encrypts `plaintext` under `key`, derived from `salt`, into the salt,
+/// a fresh nonce and the ciphertext.
+fn seal(key: &[u8], salt: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new(GenericArray::from_slice(key));
+
+    let mut nonce_bytes = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut nonce_bytes);
+    let nonce = Nonce::from_slice(&nonce_bytes);
+    let ciphertext = cipher.encrypt(nonce, plaintext)?;
+
+    let mut combined = Vec::with_capacity(salt.len() + nonce_bytes.len() + ciphertext.len());
+    combined.extend_from_slice(salt);
+    combined.extend_from_slice(&nonce_bytes);
+    combined.extend_from_slice(&ciphertext);
+    Ok(combined)
+}
+
+impl ScopedCredentials<'_> {
+    /// Reads a credential, refusing ones outside the consumer's scopes. Every
+    /// read of an existing credential is recorded in the access log, and one
+    /// still under an older key derivation is re-encrypted under the current.
+    pub async fn get(&self, service: &str, account: &str) -> Result<Option<Secret>> {
+        Ok(self.read(service, account).await?.map(|(secret, _)| secret))
+    }
+
+    /// The current code of a TOTP credential, under the same scope checks
+    /// and access logging as `get`.
+    pub async fn totp(&self, service: &str, account: &str) -> Result<Option<TotpCode>> {
+        let Some((uri, kind)) = self.read(service, account).await? else {
+            return Ok(None);
+        };
+        if kind != CredentialKind::Totp {
+            return Err(anyhow!(
+                "credential {service}/{account} is not a TOTP secret"
+            ));
+        }
+        let now = chrono::Utc::now().timestamp() as u64;
+        totp::generate(uri.expose(), now)
+            .map(Some)
+            .map_err(|error| anyhow!(error))
+    }
+
+    async fn read(&self, service: &str, account: &str) -> Result<Option<(Secret, CredentialKind)>> {
+        if service.trim().is_empty() || account.trim().is_empty() {
+            return Err(anyhow!("service and account must not be empty"));
+        }
+
+        let manager = self.manager;
+        let _guard = manager.lock.lock().await;
+        let mut credentials = manager.load_credentials().await?;
+        let Some(credential) = credentials
+            .iter_mut()
+            .find(|item| manager.holds(item, service, account))
+        else {
+            return Ok(None);
+        };
+
+        let granted = self.consumer.allows(credential.scope);
+        manager.record_access(self.consumer, service, account, granted);
+        if !granted {
+            log::warn!(
+                "Refused {} access to {} credential {}/{}",
+                self.consumer,
+                credential.scope,
+                service,
+                account
+            );
+            return Err(anyhow!(
+                "{} may not read the {} credential {}/{}",
+                self.consumer,
+                credential.scope,
+                service,
+                account
+            ));
+        }
+
+        let kind = credential.kind;
+        let secret = manager.read_secret(credential)?;
+        if let Some(secret) = secret.as_ref() {
+            let mut changed = false;
+            if !credential.in_keyring
+                && credential.kdf != Kdf::CURRENT
+                && let Ok(encrypted) = manager.encrypt_password(secret.expose())
+            {
+                credential.encrypted_password = encrypted;
+                credential.kdf = Kdf::CURRENT;
+                changed = true;
+            }
+            let now = chrono::Utc::now().timestamp_millis() as u64;
+            if credential
+                .last_accessed_at
+                .is_none_or(|last| now.saturating_sub(last) >= LAST_ACCESS_INTERVAL_MS)
+            {
+                credential.last_accessed_at = Some(now);
+                changed = true;
+            }
+            // The read already succeeded; bookkeeping must not fail it.
+            if changed && let Err(error) = manager.save_credentials(&credentials).await {
+                log::warn!("Failed to record access to credential {service}/{account}: {error}");
+            }
+        }
+        Ok(secret.map(|secret| (secret, kind)))
     }
 
-    fn derive_key(&self, salt: &[u8]) -> Result<Vec<u8>> {
-        let params = ScryptParams::new(15, 8, 1, 32)?;
-        let mut key = vec![0u8; 32];
-        scrypt(self.master_password.as_bytes(), salt, &params, &mut key)?;
-        Ok(key)
+    /// Replaces `${credential:<service>/<account>}` references in `value`
+    /// with the active Hedera account's entry, or the default profile's when
+    /// it has none. Values without references are returned unchanged.
+    pub async fn resolve(&self, value: &str) -> Result<String> {
+        let active = self.manager.active()?;
+        let fallback = self.manager.for_profile(None)?;
+        let mut resolved = String::with_capacity(value.len());
+        let mut rest = value;
+        while let Some(start) = rest.find(REFERENCE_PREFIX) {
+            resolved.push_str(&rest[..start]);
+            let reference = &rest[start + REFERENCE_PREFIX.len()..];
+            let end = reference
+                .find('}')
+                .ok_or_else(|| anyhow!("unterminated credential reference"))?;
+            let (service, account) = reference[..end].split_once('/').ok_or_else(|| {
+                anyhow!("credential references take the form ${{credential:<service>/<account>}}")
+            })?;
+            let mut secret = active.scoped(self.consumer).get(service, account).await?;
+            if secret.is_none() && active.profile.is_some() {
+                secret = fallback.scoped(self.consumer).get(service, account).await?;
+            }
+            let secret =
+                secret.ok_or_else(|| anyhow!("credential {service}/{account} not found"))?;
+            resolved.push_str(secret.expose());
+            rest = &reference[end + 1..];
+        }
+        resolved.push_str(rest);
+        Ok(resolved)
     }
 }
 
@@ -201,7 +1091,7 @@ mod tests {
             .unwrap();
 
         let retrieved = manager.get("service", "account").await.unwrap();
-        assert_eq!(retrieved, Some("super-secret".to_string()));
+        assert_eq!(retrieved, Some(Secret::from("super-secret")));
     }
 
     #[tokio::test]
@@ -245,7 +1135,509 @@ mod tests {
         assert_eq!(manager.get("service", "account2").await.unwrap(), None);
         assert_eq!(
             manager.get("other", "account").await.unwrap(),
-            Some("secret3".to_string())
+            Some(Secret::from("secret3"))
+        );
+    }
+
+    #[tokio::test]
+    async fn scoped_reads_are_limited_and_logged() {
+        let temp = tempdir().unwrap();
+        let manager = CredentialManager::new(temp.path().join("credentials.dat"), "master-secret");
+        manager
+            .store_with_scope("github", "token", "gh-secret", Some(CredentialScope::Mcp))
+            .await
+            .unwrap();
+        manager
+            .store_with_scope("openai", "api-key", "sk-secret", Some(CredentialScope::Llm))
+            .await
+            .unwrap();
+        manager.store("wallet", "seed", "words").await.unwrap();
+
+        let mcp = manager.scoped(CredentialConsumer::Mcp);
+        assert_eq!(
+            mcp.resolve("Bearer ${credential:github/token}")
+                .await
+                .unwrap(),
+            "Bearer gh-secret"
+        );
+        assert!(mcp.get("openai", "api-key").await.is_err());
+        assert!(mcp.resolve("${credential:wallet/seed}").await.is_err());
+        assert_eq!(mcp.resolve("plain").await.unwrap(), "plain");
+
+        let agent = manager.scoped(CredentialConsumer::AgentBridge);
+        assert_eq!(
+            agent.get("openai", "api-key").await.unwrap(),
+            Some(Secret::from("sk-secret"))
+        );
+        assert!(agent.get("github", "token").await.is_err());
+
+        manager.store("github", "token", "rotated").await.unwrap();
+        assert_eq!(
+            mcp.get("github", "token").await.unwrap(),
+            Some(Secret::from("rotated"))
+        );
+
+        let log = manager.access_log();
+        assert_eq!(log.len(), 6);
+        assert_eq!(log[0].consumer, CredentialConsumer::Mcp);
+        assert!(log[0].granted);
+        assert_eq!(log.iter().filter(|entry| !entry.granted).count(), 3);
+    }
+
+    #[tokio::test]
+    async fn list_reports_entries_and_last_access_without_secrets() {
+        let temp = tempdir().unwrap();
+        let manager = CredentialManager::new(temp.path().join("credentials.dat"), "master-secret");
+        manager
+            .store_with_scope("openai", "api-key", "sk-secret", Some(CredentialScope::Llm))
+            .await
+            .unwrap();
+        manager.store("github", "token", "gh-secret").await.unwrap();
+        manager.get("openai", "api-key").await.unwrap();
+
+        let listed = manager.list().await.unwrap();
+        assert_eq!(
+            listed
+                .iter()
+                .map(|item| (item.service.as_str(), item.account.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("github", "token"), ("openai", "api-key")]
+        );
+        assert_eq!(listed[0].last_accessed_at, None);
+        assert_eq!(listed[1].scope, CredentialScope::Llm);
+        let accessed = listed[1].last_accessed_at.unwrap();
+        assert!(accessed >= listed[1].created_at);
+        assert!(!serde_json::to_string(&listed).unwrap().contains("secret"));
+
+        manager.store("openai", "api-key", "rotated").await.unwrap();
+        assert_eq!(
+            manager.list().await.unwrap()[1].last_accessed_at,
+            Some(accessed)
+        );
+    }
+
+    #[tokio::test]
+    async fn repeated_reads_do_not_rewrite_the_file() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("credentials.dat");
+        let manager = CredentialManager::new(path.clone(), "master-secret");
+        manager.store("github", "token", "gh-secret").await.unwrap();
+        manager.get("github", "token").await.unwrap();
+        let after_first_read = std::fs::read(&path).unwrap();
+
+        let secret = manager.get("github", "token").await.unwrap().unwrap();
+        assert_eq!(secret.expose(), "gh-secret");
+        assert_eq!(std::fs::read(&path).unwrap(), after_first_read);
+    }
+
+    #[tokio::test]
+    async fn reading_a_scrypt_entry_re_encrypts_it_with_argon2id() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("credentials.dat");
+        let manager = CredentialManager::new(path.clone(), "master-secret");
+        let legacy_entry = serde_json::json!([{
+            "service": "openai",
+            "account": "api-key",
+            "encrypted_password": STANDARD.encode(
+                manager
+                    .encrypt_password_bytes(b"sk-secret", Kdf::Scrypt)
+                    .unwrap()
+            ),
+            "created_at": 1,
+        }]);
+        let legacy_file = manager
+            .encrypt_password_bytes(legacy_entry.to_string().as_bytes(), Kdf::Scrypt)
+            .unwrap();
+        std::fs::write(&path, legacy_file).unwrap();
+
+        assert_eq!(
+            manager.load_credentials().await.unwrap()[0].kdf,
+            Kdf::Scrypt
+        );
+        assert_eq!(
+            manager.get("openai", "api-key").await.unwrap(),
+            Some(Secret::from("sk-secret"))
+        );
+
+        assert!(
+            std::fs::read(&path)
+                .unwrap()
+                .starts_with(b"hol-credentials:argon2id:")
+        );
+        let stored = manager.load_credentials().await.unwrap();
+        assert_eq!(stored[0].kdf, Kdf::Argon2id);
+        assert_eq!(
+            manager
+                .decrypt_password(&stored[0].encrypted_password, Kdf::Argon2id)
+                .unwrap(),
+            Secret::from("sk-secret")
+        );
+    }
+
+    #[tokio::test]
+    async fn rekey_moves_credentials_to_the_new_password() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("credentials.dat");
+        let mut manager = CredentialManager::new(path.clone(), "old-secret");
+        manager
+            .store("openai", "api-key", "sk-secret")
+            .await
+            .unwrap();
+
+        assert_eq!(manager.rekey("new-secret").await.unwrap(), 1);
+        assert!(
+            CredentialManager::new(path.clone(), "old-secret")
+                .get("openai", "api-key")
+                .await
+                .is_err()
+        );
+        let reopened = CredentialManager::new(path, "new-secret");
+        assert_eq!(
+            reopened.get("openai", "api-key").await.unwrap(),
+            Some(Secret::from("sk-secret"))
+        );
+        assert_eq!(
+            manager.get("openai", "api-key").await.unwrap(),
+            Some(Secret::from("sk-secret"))
+        );
+    }
+
+    #[tokio::test]
+    async fn legacy_store_moves_to_the_master_key_on_read() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("credentials.dat");
+        CredentialManager::new(path.clone(), LEGACY_MASTER_PASSWORD)
+            .store("openai", "api-key", "sk-secret")
+            .await
+            .unwrap();
+
+        let manager = CredentialManager::new(path.clone(), "keychain-key");
+        assert_eq!(
+            manager.get("openai", "api-key").await.unwrap(),
+            Some(Secret::from("sk-secret"))
+        );
+        assert_eq!(
+            manager.verify_master_password().await.unwrap(),
+            MasterPasswordCheck::Correct
+        );
+    }
+
+    #[derive(Default)]
+    struct MemoryKeyring {
+        secrets: StdMutex<std::collections::HashMap<(String, String), String>>,
+        refuse: bool,
+    }
+
+    impl KeyringStore for MemoryKeyring {
+        fn get(&self, service: &str, account: &str) -> Result<Option<Secret>> {
+            let secrets = self.secrets.lock().unwrap();
+            Ok(secrets
+                .get(&(service.into(), account.into()))
+                .map(|secret| secret.as_str().into()))
+        }
+
+        fn set(&self, service: &str, account: &str, secret: &str) -> Result<()> {
+            if self.refuse {
+                return Err(anyhow!("keyring locked"));
+            }
+            let mut secrets = self.secrets.lock().unwrap();
+            secrets.insert((service.into(), account.into()), secret.into());
+            Ok(())
+        }
+
+        fn delete(&self, service: &str, account: &str) -> Result<()> {
+            let mut secrets = self.secrets.lock().unwrap();
+            secrets.remove(&(service.into(), account.into()));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn keyring_migration_skips_entries_it_cannot_decrypt() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("credentials.dat");
+        let manager = CredentialManager::new(path.clone(), "master-secret");
+        manager.store("github", "token", "gh-secret").await.unwrap();
+        manager
+            .store("openai", "api-key", "sk-secret")
+            .await
+            .unwrap();
+        let mut stored = manager.load_credentials().await.unwrap();
+        stored
+            .iter_mut()
+            .find(|item| item.service == "github")
+            .unwrap()
+            .encrypted_password = "corrupt".into();
+        manager.save_credentials(&stored).await.unwrap();
+
+        let keyring = Arc::new(MemoryKeyring::default());
+        let manager = CredentialManager::new(path, "master-secret").with_keyring(keyring.clone());
+        assert_eq!(manager.migrate_to_keyring().await.unwrap(), 1);
+        assert_eq!(
+            keyring.get("openai", "api-key").unwrap(),
+            Some(Secret::from("sk-secret"))
+        );
+        let stored = manager.load_credentials().await.unwrap();
+        let github = stored.iter().find(|item| item.service == "github").unwrap();
+        assert!(!github.in_keyring);
+        assert_eq!(github.encrypted_password, "corrupt");
+    }
+
+    #[tokio::test]
+    async fn keyring_holds_new_and_migrated_secrets() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("credentials.dat");
+        CredentialManager::new(path.clone(), "master-secret")
+            .store("openai", "api-key", "sk-secret")
+            .await
+            .unwrap();
+
+        let keyring = Arc::new(MemoryKeyring::default());
+        let manager =
+            CredentialManager::new(path.clone(), "master-secret").with_keyring(keyring.clone());
+        assert_eq!(
+            manager.get("openai", "api-key").await.unwrap(),
+            Some(Secret::from("sk-secret"))
+        );
+        assert_eq!(manager.migrate_to_keyring().await.unwrap(), 1);
+        assert_eq!(manager.migrate_to_keyring().await.unwrap(), 0);
+        manager.store("github", "token", "gh-secret").await.unwrap();
+
+        let stored = manager.load_credentials().await.unwrap();
+        assert!(
+            stored
+                .iter()
+                .all(|item| item.in_keyring && item.encrypted_password.is_empty())
+        );
+        assert_eq!(
+            keyring.get("openai", "api-key").unwrap(),
+            Some(Secret::from("sk-secret"))
+        );
+        assert_eq!(
+            manager.get("github", "token").await.unwrap(),
+            Some(Secret::from("gh-secret"))
+        );
+
+        assert!(manager.delete("github", "token").await.unwrap());
+        assert_eq!(keyring.get("github", "token").unwrap(), None);
+
+        let refusing =
+            CredentialManager::new(path, "master-secret").with_keyring(Arc::new(MemoryKeyring {
+                refuse: true,
+                ..MemoryKeyring::default()
+            }));
+        refusing.store("wallet", "seed", "words").await.unwrap();
+        assert_eq!(
+            refusing.get("wallet", "seed").await.unwrap(),
+            Some(Secret::from("words"))
+        );
+    }
+
+    #[tokio::test]
+    async fn profiles_keep_separate_entries_under_the_same_names() {
+        let temp = tempdir().unwrap();
+        let keyring = Arc::new(MemoryKeyring::default());
+        let manager = CredentialManager::new(temp.path().join("credentials.dat"), "master-secret")
+            .with_keyring(keyring.clone());
+        let testnet = manager.for_profile(Some("testnet-dev")).unwrap();
+        let mainnet = manager.for_profile(Some("mainnet-prod")).unwrap();
+
+        manager
+            .store("hedera", "operator", "default-key")
+            .await
+            .unwrap();
+        testnet
+            .store("hedera", "operator", "testnet-key")
+            .await
+            .unwrap();
+        mainnet
+            .store("hedera", "operator", "mainnet-key")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            testnet.get("hedera", "operator").await.unwrap(),
+            Some(Secret::from("testnet-key"))
+        );
+        assert_eq!(
+            mainnet.get("hedera", "operator").await.unwrap(),
+            Some(Secret::from("mainnet-key"))
+        );
+        assert_eq!(
+            keyring.get("testnet-dev/hedera", "operator").unwrap(),
+            Some(Secret::from("testnet-key"))
+        );
+        let listed = mainnet.list().await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].profile.as_deref(), Some("mainnet-prod"));
+
+        assert_eq!(testnet.clear("hedera").await.unwrap(), 1);
+        assert_eq!(testnet.get("hedera", "operator").await.unwrap(), None);
+        assert_eq!(
+            manager.get("hedera", "operator").await.unwrap(),
+            Some(Secret::from("default-key"))
+        );
+        assert_eq!(manager.access_log()[0].profile, None);
+        assert_eq!(
+            manager
+                .for_profile(Some("  "))
+                .unwrap()
+                .list()
+                .await
+                .unwrap()[0]
+                .profile,
+            None
+        );
+        assert!(manager.for_profile(Some("test/net")).is_err());
+    }
+
+    #[tokio::test]
+    async fn references_resolve_through_the_active_account() {
+        let temp = tempdir().unwrap();
+        let manager = CredentialManager::new(temp.path().join("credentials.dat"), "master-secret");
+        let treasury = manager.for_profile(Some("resolve-treasury")).unwrap();
+        manager
+            .store("github", "token", "default-token")
+            .await
+            .unwrap();
+        manager
+            .store("slack", "token", "default-slack")
+            .await
+            .unwrap();
+        treasury
+            .store("github", "token", "treasury-token")
+            .await
+            .unwrap();
+        let user = manager.scoped(CredentialConsumer::User);
+
+        set_active_profile(Some("resolve-treasury"));
+        let resolved = user
+            .resolve("${credential:github/token} ${credential:slack/token}")
+            .await;
+        set_active_profile(None);
+        assert_eq!(resolved.unwrap(), "treasury-token default-slack");
+        assert_eq!(
+            user.resolve("${credential:github/token}").await.unwrap(),
+            "default-token"
+        );
+    }
+
+    #[tokio::test]
+    async fn totp_credentials_generate_codes_but_passwords_do_not() {
+        let temp = tempdir().unwrap();
+        let manager = CredentialManager::new(temp.path().join("credentials.dat"), "master-secret");
+        manager
+            .store_totp(
+                "exchange",
+                "trader",
+                "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ",
+                Some(CredentialScope::Mcp),
+            )
+            .await
+            .unwrap();
+        manager.store("github", "token", "gh-secret").await.unwrap();
+
+        let code = manager
+            .scoped(CredentialConsumer::Mcp)
+            .totp("exchange", "trader")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(code.code.len(), 6);
+        assert!(code.expires_at > chrono::Utc::now().timestamp_millis() as u64);
+        assert_eq!(manager.list().await.unwrap()[0].kind, CredentialKind::Totp);
+
+        assert!(manager.generate_totp("github", "token").await.is_err());
+        assert_eq!(
+            manager.generate_totp("exchange", "nobody").await.unwrap(),
+            None
+        );
+        assert!(
+            manager
+                .store_totp("exchange", "trader", "not base32!", None)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn locked_store_refuses_reads_until_unlocked() {
+        let temp = tempdir().unwrap();
+        let manager = CredentialManager::new(temp.path().join("credentials.dat"), "master-secret");
+        let mcp_view = manager.clone();
+        manager.store("github", "token", "gh-secret").await.unwrap();
+
+        manager.lock_store().unwrap();
+        assert!(mcp_view.is_locked());
+        let error = manager.get("github", "token").await.unwrap_err();
+        assert_eq!(error.to_string(), "credential store is locked");
+        assert!(manager.list().await.is_err());
+        assert!(manager.unlock_store("wrong-secret").is_err());
+        assert!(manager.is_locked());
+
+        manager.unlock_store("master-secret").unwrap();
+        assert_eq!(
+            mcp_view.get("github", "token").await.unwrap(),
+            Some(Secret::from("gh-secret"))
+        );
+
+        manager.set_idle_timeout(Some(Duration::from_millis(50)));
+        assert!(!manager.lock_if_idle().unwrap());
+        std::thread::sleep(Duration::from_millis(80));
+        assert!(manager.get("github", "token").await.is_err());
+        assert!(!manager.lock_if_idle().unwrap());
+        manager.unlock_store("master-secret").unwrap();
+        assert!(manager.get("github", "token").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn master_password_check_tells_a_changed_key_from_damage() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("credentials.dat");
+        let manager = CredentialManager::new(path.clone(), "master-secret");
+        assert_eq!(
+            manager.verify_master_password().await.unwrap(),
+            MasterPasswordCheck::Empty
+        );
+        manager.store("github", "token", "gh-secret").await.unwrap();
+
+        assert_eq!(
+            manager.verify_master_password().await.unwrap(),
+            MasterPasswordCheck::Correct
         );
+        let wrong = CredentialManager::new(path.clone(), "other");
+        assert_eq!(
+            wrong.verify_master_password().await.unwrap(),
+            MasterPasswordCheck::Wrong
+        );
+        let error = wrong.get("github", "token").await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "wrong master password for credentials.dat"
+        );
+
+        let mut data = std::fs::read(&path).unwrap();
+        *data.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&path, &data).unwrap();
+        assert_eq!(
+            manager.verify_master_password().await.unwrap(),
+            MasterPasswordCheck::Corrupted
+        );
+        let error = manager.get("github", "token").await.unwrap_err();
+        assert_eq!(error.to_string(), "credentials.dat is corrupted");
+
+        let payload = manager.encrypt_password_bytes(b"[]", Kdf::CURRENT).unwrap();
+        std::fs::write(&path, [FILE_HEADER, b"argon2id\n", &payload].concat()).unwrap();
+        assert_eq!(
+            manager.verify_master_password().await.unwrap(),
+            MasterPasswordCheck::Correct
+        );
+        assert_eq!(
+            wrong.verify_master_password().await.unwrap(),
+            MasterPasswordCheck::Inconclusive
+        );
+
+        manager.lock_store().unwrap();
+        assert!(manager.verify_master_password().await.is_err());
     }
 }
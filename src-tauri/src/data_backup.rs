@@ -0,0 +1,490 @@
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use chrono::Utc;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{LEGACY_MASTER_PASSWORD, decrypt_value, encrypt_value, rewrap_config_contents};
+
+const BACKUP_FORMAT: &str = "hol-desktop-backup";
+/// Version 2 archives are encrypted with a passphrase instead of the
+/// device's master key, so they can be restored on another device.
+const BACKUP_VERSION: u32 = 2;
+const MIN_PASSPHRASE_CHARS: usize = 8;
+const CONFIG_FILE: &str = "config.json";
+const PENDING_RESTORE_DIR: &str = "pending-restore";
+const STAGING_RESTORE_DIR: &str = "pending-restore.tmp";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BackupFileKind {
+    Sqlite,
+    Json,
+}
+
+const BACKUP_FILES: &[(&str, BackupFileKind)] = &[
+    ("chat.sqlite", BackupFileKind::Sqlite),
+    ("mcp-registry.sqlite", BackupFileKind::Sqlite),
+    (CONFIG_FILE, BackupFileKind::Json),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupFileInfo {
+    pub name: String,
+    pub size: u64,
+}
+
+/// On-disk archive. The manifest stays readable; file contents live in the
+/// encrypted `payload`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BackupArchive {
+    format: String,
+    version: u32,
+    created_at: String,
+    files: Vec<BackupFileInfo>,
+    payload: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BackupPayload {
+    files: Vec<BackupFileData>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BackupFileData {
+    name: String,
+    data: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSummary {
+    pub path: String,
+    pub created_at: String,
+    pub files: Vec<BackupFileInfo>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreSummary {
+    pub created_at: String,
+    pub files: Vec<BackupFileInfo>,
+    pub restart_required: bool,
+}
+
+/// Writes an archive of the application databases and config, encrypted
+/// with `passphrase`. The config's secrets move from `master_password` to
+/// the passphrase too. SQLite files are captured with `VACUUM INTO` so open
+/// connections do not produce a torn copy.
+pub fn create_backup(
+    data_dir: &Path,
+    destination: &Path,
+    master_password: &str,
+    passphrase: &str,
+) -> Result<BackupSummary, String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+        return Err(format!(
+            "Backup passphrase must be at least {MIN_PASSPHRASE_CHARS} characters"
+        ));
+    }
+    let mut manifest = Vec::new();
+    let mut files = Vec::new();
+
+    for (name, kind) in BACKUP_FILES {
+        let path = data_dir.join(name);
+        if !path.exists() {
+            continue;
+        }
+        let bytes = match kind {
+            BackupFileKind::Sqlite => snapshot_sqlite(&path)?,
+            BackupFileKind::Json => {
+                let bytes =
+                    fs::read(&path).map_err(|error| format!("Failed to read {name}: {error}"))?;
+                if *name == CONFIG_FILE {
+                    rewrap_config_contents(&bytes, master_password, passphrase)?
+                } else {
+                    bytes
+                }
+            }
+        };
+        manifest.push(BackupFileInfo {
+            name: name.to_string(),
+            size: bytes.len() as u64,
+        });
+        files.push(BackupFileData {
+            name: name.to_string(),
+            data: STANDARD.encode(bytes),
+        });
+    }
+
+    if files.is_empty() {
+        return Err("No application data found to back up".to_string());
+    }
+
+    let plaintext = serde_json::to_string(&BackupPayload { files })
+        .map_err(|error| format!("Failed to serialize backup: {error}"))?;
+    let payload = encrypt_value(&plaintext, passphrase)?
+        .ok_or_else(|| "Failed to encrypt backup".to_string())?;
+    let created_at = Utc::now().to_rfc3339();
+    let archive = BackupArchive {
+        format: BACKUP_FORMAT.to_string(),
+        version: BACKUP_VERSION,
+        created_at: created_at.clone(),
+        files: manifest.clone(),
+        payload,
+    };
+
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|error| format!("Failed to create backup directory: {error}"))?;
+    }
+    let serialized = serde_json::to_vec(&archive)
+        .map_err(|error| format!("Failed to serialize backup: {error}"))?;
+    fs::write(destination, serialized)
+        .map_err(|error| format!("Failed to write backup: {error}"))?;
+
+    Ok(BackupSummary {
+        path: destination.display().to_string(),
+        created_at,
+        files: manifest,
+    })
+}
+
+/// Decrypts and verifies an archive, then stages its files to replace the
+/// live data on next launch, with the config's secrets moved to
+/// `master_password`. Archives from before backups had a passphrase open
+/// with this device's master key or the legacy password. Nothing is staged
+/// unless every file checks out.
+pub fn restore_backup(
+    data_dir: &Path,
+    source: &Path,
+    master_password: &str,
+    passphrase: Option<&str>,
+) -> Result<RestoreSummary, String> {
+    let contents = fs::read(source).map_err(|error| format!("Failed to read backup: {error}"))?;
+    let archive: BackupArchive = serde_json::from_slice(&contents)
+        .map_err(|_| "File is not a desktop backup archive".to_string())?;
+    if archive.format != BACKUP_FORMAT {
+        return Err("File is not a desktop backup archive".to_string());
+    }
+    if archive.version > BACKUP_VERSION {
+        return Err(format!(
+            "Backup version {} is newer than this app supports",
+            archive.version
+        ));
+    }
+
+    let keys = if archive.version >= 2 {
+        let passphrase =
+            passphrase.ok_or_else(|| "This backup needs its passphrase to restore".to_string())?;
+        vec![passphrase]
+    } else {
+        vec![master_password, LEGACY_MASTER_PASSWORD]
+    };
+    let (plaintext, archive_key) = keys
+        .into_iter()
+        .find_map(|key| {
+            decrypt_value(&archive.payload, key)
+                .ok()
+                .map(|plaintext| (plaintext, key))
+        })
+        .ok_or_else(|| "Failed to decrypt backup; check the passphrase".to_string())?;
+    let plaintext = plaintext.ok_or_else(|| "Backup payload is not encrypted".to_string())?;
+    let payload: BackupPayload = serde_json::from_str(&plaintext)
+        .map_err(|error| format!("Backup payload is invalid: {error}"))?;
+
+    if payload.files.len() != archive.files.len() {
+        return Err("Backup manifest does not match its contents".to_string());
+    }
+
+    let staging = data_dir.join(STAGING_RESTORE_DIR);
+    if staging.exists() {
+        fs::remove_dir_all(&staging)
+            .map_err(|error| format!("Failed to clear restore staging: {error}"))?;
+    }
+    fs::create_dir_all(&staging)
+        .map_err(|error| format!("Failed to create restore staging: {error}"))?;
+
+    let staged = stage_files(&staging, &archive.files, &payload.files)
+        .and_then(|()| rewrap_staged_config(&staging, archive_key, master_password));
+    if let Err(error) = staged {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(error);
+    }
+
+    let pending = data_dir.join(PENDING_RESTORE_DIR);
+    if pending.exists() {
+        fs::remove_dir_all(&pending)
+            .map_err(|error| format!("Failed to replace pending restore: {error}"))?;
+    }
+    fs::rename(&staging, &pending).map_err(|error| format!("Failed to stage restore: {error}"))?;
+
+    Ok(RestoreSummary {
+        created_at: archive.created_at,
+        files: archive.files,
+        restart_required: true,
+    })
+}
+
+/// Moves a staged restore into place. Must run before any database is opened.
+pub fn apply_pending_restore(data_dir: &Path) -> Result<Vec<String>, String> {
+    let pending = data_dir.join(PENDING_RESTORE_DIR);
+    if !pending.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut restored = Vec::new();
+    for (name, kind) in BACKUP_FILES {
+        let staged = pending.join(name);
+        if !staged.exists() {
+            continue;
+        }
+        let target = data_dir.join(name);
+        if *kind == BackupFileKind::Sqlite {
+            for suffix in ["-wal", "-shm"] {
+                let sidecar = data_dir.join(format!("{name}{suffix}"));
+                if sidecar.exists() {
+                    fs::remove_file(&sidecar)
+                        .map_err(|error| format!("Failed to remove {name}{suffix}: {error}"))?;
+                }
+            }
+        }
+        fs::rename(&staged, &target)
+            .map_err(|error| format!("Failed to restore {name}: {error}"))?;
+        restored.push(name.to_string());
+    }
+
+    fs::remove_dir_all(&pending)
+        .map_err(|error| format!("Failed to clean up pending restore: {error}"))?;
+    Ok(restored)
+}
+
+/// Rewraps the config of a restore still waiting for a restart under the
+/// current envelope. Returns whether there was one.
+pub fn rewrap_pending_restore(data_dir: &Path, master_password: &str) -> Result<bool, String> {
+    let pending = data_dir.join(PENDING_RESTORE_DIR);
+    if !pending.join(CONFIG_FILE).exists() {
+        return Ok(false);
+    }
+    rewrap_staged_config(&pending, master_password, master_password)?;
+    Ok(true)
+}
+
+fn snapshot_sqlite(path: &Path) -> Result<Vec<u8>, String> {
+    let snapshot = snapshot_path(path);
+    let connection = Connection::open(path)
+        .map_err(|error| format!("Failed to open {}: {error}", path.display()))?;
+    connection
+        .execute("VACUUM INTO ?1", [snapshot.to_string_lossy().as_ref()])
+        .map_err(|error| format!("Failed to snapshot {}: {error}", path.display()))?;
+    drop(connection);
+
+    let bytes = fs::read(&snapshot);
+    let _ = fs::remove_file(&snapshot);
+    bytes.map_err(|error| format!("Failed to read database snapshot: {error}"))
+}
+
+fn snapshot_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!("{name}.backup-{}", uuid::Uuid::new_v4().simple()))
+}
+
+fn stage_files(
+    staging: &Path,
+    manifest: &[BackupFileInfo],
+    files: &[BackupFileData],
+) -> Result<(), String> {
+    for file in files {
+        let Some((name, kind)) = BACKUP_FILES.iter().find(|(name, _)| *name == file.name) else {
+            return Err(format!("Backup contains unexpected file {}", file.name));
+        };
+        let bytes = STANDARD
+            .decode(&file.data)
+            .map_err(|error| format!("Backup entry {name} is corrupt: {error}"))?;
+        let expected = manifest
+            .iter()
+            .find(|entry| entry.name == *name)
+            .ok_or_else(|| format!("Backup manifest is missing {name}"))?;
+        if expected.size != bytes.len() as u64 {
+            return Err(format!(
+                "Backup entry {name} does not match its manifest size"
+            ));
+        }
+
+        let path = staging.join(name);
+        fs::write(&path, &bytes).map_err(|error| format!("Failed to stage {name}: {error}"))?;
+        match kind {
+            BackupFileKind::Sqlite => verify_sqlite(&path)
+                .map_err(|error| format!("Backup entry {name} failed verification: {error}"))?,
+            BackupFileKind::Json => {
+                serde_json::from_slice::<serde_json::Value>(&bytes)
+                    .map_err(|error| format!("Backup entry {name} is not valid JSON: {error}"))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Moves the secrets of a staged config from the archive's key to this
+/// device's master key.
+fn rewrap_staged_config(staging: &Path, from: &str, master_password: &str) -> Result<(), String> {
+    let path = staging.join(CONFIG_FILE);
+    if !path.exists() {
+        return Ok(());
+    }
+    let contents =
+        fs::read(&path).map_err(|error| format!("Failed to read staged config: {error}"))?;
+    let rewrapped = rewrap_config_contents(&contents, from, master_password)?;
+    fs::write(&path, rewrapped).map_err(|error| format!("Failed to stage config: {error}"))
+}
+
+fn verify_sqlite(path: &Path) -> Result<(), String> {
+    let connection = Connection::open(path).map_err(|error| error.to_string())?;
+    let result: String = connection
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(|error| error.to_string())?;
+    if result == "ok" { Ok(()) } else { Err(result) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use tempfile::tempdir;
+
+    fn seed_data_dir(dir: &Path, note: &str, master_password: &str) {
+        let connection = Connection::open(dir.join("chat.sqlite")).unwrap();
+        connection
+            .execute_batch("CREATE TABLE IF NOT EXISTS notes (body TEXT NOT NULL);")
+            .unwrap();
+        connection
+            .execute("INSERT INTO notes (body) VALUES (?1)", [note])
+            .unwrap();
+        let mut config = AppConfig::default();
+        config.openai.api_key = encrypt_value(note, master_password)
+            .unwrap()
+            .unwrap()
+            .into();
+        fs::write(dir.join(CONFIG_FILE), serde_json::to_vec(&config).unwrap()).unwrap();
+    }
+
+    fn read_note(dir: &Path) -> String {
+        let connection = Connection::open(dir.join("chat.sqlite")).unwrap();
+        connection
+            .query_row("SELECT body FROM notes", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    fn read_config_note(dir: &Path, master_password: &str) -> String {
+        let config: AppConfig =
+            serde_json::from_slice(&fs::read(dir.join(CONFIG_FILE)).unwrap()).unwrap();
+        decrypt_value(config.openai.api_key.expose(), master_password)
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn backup_round_trips_through_pending_restore() {
+        let source = tempdir().unwrap();
+        seed_data_dir(source.path(), "original", "device-a");
+        let archive = source.path().join("backup.holbackup");
+
+        assert!(create_backup(source.path(), &archive, "device-a", "short").is_err());
+        let summary =
+            create_backup(source.path(), &archive, "device-a", "backup passphrase").unwrap();
+        let names: Vec<_> = summary
+            .files
+            .iter()
+            .map(|file| file.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["chat.sqlite", "config.json"]);
+
+        let target = tempdir().unwrap();
+        seed_data_dir(target.path(), "replaced", "device-b");
+        assert!(restore_backup(target.path(), &archive, "device-b", None).is_err());
+        let restore = restore_backup(
+            target.path(),
+            &archive,
+            "device-b",
+            Some("backup passphrase"),
+        )
+        .unwrap();
+        assert!(restore.restart_required);
+        assert_eq!(read_note(target.path()), "replaced");
+
+        let restored = apply_pending_restore(target.path()).unwrap();
+        assert_eq!(restored, vec!["chat.sqlite", "config.json"]);
+        assert_eq!(read_note(target.path()), "original");
+        assert_eq!(read_config_note(target.path(), "device-b"), "original");
+        assert!(!target.path().join(PENDING_RESTORE_DIR).exists());
+        assert!(apply_pending_restore(target.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn restore_opens_version_one_archives_with_the_master_or_legacy_key() {
+        let source = tempdir().unwrap();
+        seed_data_dir(source.path(), "original", LEGACY_MASTER_PASSWORD);
+        let archive_path = source.path().join("backup.holbackup");
+        create_backup(
+            source.path(),
+            &archive_path,
+            LEGACY_MASTER_PASSWORD,
+            LEGACY_MASTER_PASSWORD,
+        )
+        .unwrap();
+        let mut archive: BackupArchive =
+            serde_json::from_slice(&fs::read(&archive_path).unwrap()).unwrap();
+        archive.version = 1;
+        fs::write(&archive_path, serde_json::to_vec(&archive).unwrap()).unwrap();
+
+        let target = tempdir().unwrap();
+        restore_backup(target.path(), &archive_path, "device-b", None).unwrap();
+        apply_pending_restore(target.path()).unwrap();
+        assert_eq!(read_config_note(target.path(), "device-b"), "original");
+    }
+
+    #[test]
+    fn restore_rejects_wrong_passphrase_and_tampered_manifest() {
+        let source = tempdir().unwrap();
+        seed_data_dir(source.path(), "original", "device-a");
+        let archive_path = source.path().join("backup.holbackup");
+        create_backup(
+            source.path(),
+            &archive_path,
+            "device-a",
+            "backup passphrase",
+        )
+        .unwrap();
+
+        let target = tempdir().unwrap();
+        assert!(
+            restore_backup(
+                target.path(),
+                &archive_path,
+                "device-a",
+                Some("wrong passphrase")
+            )
+            .is_err()
+        );
+
+        let mut archive: BackupArchive =
+            serde_json::from_slice(&fs::read(&archive_path).unwrap()).unwrap();
+        archive.files[0].size += 1;
+        fs::write(&archive_path, serde_json::to_vec(&archive).unwrap()).unwrap();
+        let error = restore_backup(
+            target.path(),
+            &archive_path,
+            "device-a",
+            Some("backup passphrase"),
+        )
+        .unwrap_err();
+        assert!(error.contains("manifest size"));
+        assert!(!target.path().join(PENDING_RESTORE_DIR).exists());
+        assert!(!target.path().join(STAGING_RESTORE_DIR).exists());
+    }
+}
@@ -0,0 +1,327 @@
+use crate::config_history::{CONFIG_HISTORY_MIGRATION_SCOPE, CONFIG_HISTORY_MIGRATIONS};
+use crate::contacts::{CONTACTS_MIGRATION_SCOPE, CONTACTS_MIGRATIONS};
+use crate::entity::{ENTITY_MIGRATION_SCOPE, ENTITY_MIGRATIONS};
+use crate::hcs10_inbox::{HCS10_INBOX_MIGRATION_SCOPE, HCS10_INBOX_MIGRATIONS};
+use crate::migrations::{self, Migration, MigrationPlanStep};
+use crate::outbox::{OUTBOX_MIGRATION_SCOPE, OUTBOX_MIGRATIONS};
+use crate::rag::{RAG_MIGRATION_SCOPE, RAG_MIGRATIONS};
+use crate::session::{SESSION_MIGRATION_SCOPE, SESSION_MIGRATIONS};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use rusqlite::types::ValueRef;
+use rusqlite::{Batch, Connection, OpenFlags, params_from_iter};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Commands that are only routed while developer mode is on.
+pub const DEVELOPER_COMMANDS: &[&str] = &[
+    "dev_bridge_rpc",
+    "dev_sql_query",
+    "dev_emit_event",
+    "dev_migration_status",
+    "dev_migration_rollback",
+];
+
+pub const QUERY_ROW_LIMIT: usize = 500;
+
+/// Local databases open to `dev_sql_query`.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeveloperDatabase {
+    Chat,
+    McpRegistry,
+    ConfigHistory,
+    Outbox,
+    Documents,
+}
+
+impl DeveloperDatabase {
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Self::Chat => "chat.sqlite",
+            Self::McpRegistry => "mcp-registry.sqlite",
+            Self::ConfigHistory => "config-history.sqlite",
+            Self::Outbox => "outbox.sqlite",
+            Self::Documents => "documents.sqlite",
+        }
+    }
+
+    /// Where the database lives; the chat database belongs to the active
+    /// workspace, the others to the app data directory.
+    pub fn path(self, data_dir: &Path, chat_db: &Path) -> PathBuf {
+        match self {
+            Self::Chat => chat_db.to_path_buf(),
+            _ => data_dir.join(self.file_name()),
+        }
+    }
+}
+
+/// Every migration scope, with the database that holds it.
+const MIGRATION_SCOPES: &[(&str, DeveloperDatabase, &[Migration])] = &[
+    (
+        SESSION_MIGRATION_SCOPE,
+        DeveloperDatabase::Chat,
+        SESSION_MIGRATIONS,
+    ),
+    (
+        ENTITY_MIGRATION_SCOPE,
+        DeveloperDatabase::Chat,
+        ENTITY_MIGRATIONS,
+    ),
+    (
+        HCS10_INBOX_MIGRATION_SCOPE,
+        DeveloperDatabase::Chat,
+        HCS10_INBOX_MIGRATIONS,
+    ),
+    (
+        CONTACTS_MIGRATION_SCOPE,
+        DeveloperDatabase::Chat,
+        CONTACTS_MIGRATIONS,
+    ),
+    (
+        OUTBOX_MIGRATION_SCOPE,
+        DeveloperDatabase::Outbox,
+        OUTBOX_MIGRATIONS,
+    ),
+    (
+        RAG_MIGRATION_SCOPE,
+        DeveloperDatabase::Documents,
+        RAG_MIGRATIONS,
+    ),
+    (
+        CONFIG_HISTORY_MIGRATION_SCOPE,
+        DeveloperDatabase::ConfigHistory,
+        CONFIG_HISTORY_MIGRATIONS,
+    ),
+];
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeveloperBridge {
+    Agent,
+    Hcs10,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationStatus {
+    pub scope: String,
+    pub database: String,
+    pub current_version: i64,
+    pub pending: Vec<MigrationPlanStep>,
+}
+
+/// Reports the applied version and pending steps of every migration scope
+/// whose database exists, with `chat_db` standing in for the chat database.
+pub fn migration_status(data_dir: &Path, chat_db: &Path) -> Result<Vec<MigrationStatus>, String> {
+    let mut statuses = Vec::new();
+    for (scope, database, steps) in MIGRATION_SCOPES {
+        let path = database.path(data_dir, chat_db);
+        if !path.exists() {
+            continue;
+        }
+        let connection = open_database(&path)?;
+        statuses.push(MigrationStatus {
+            scope: scope.to_string(),
+            database: database.file_name().to_string(),
+            current_version: migrations::current_version(&connection, scope)?,
+            pending: migrations::plan(&connection, scope, steps)?,
+        });
+    }
+    Ok(statuses)
+}
+
+/// Reverts `scope` down to `target_version` and returns the reverted versions.
+pub fn rollback_migrations(
+    data_dir: &Path,
+    chat_db: &Path,
+    scope: &str,
+    target_version: i64,
+) -> Result<Vec<i64>, String> {
+    let (_, database, steps) = MIGRATION_SCOPES
+        .iter()
+        .find(|(name, _, _)| *name == scope)
+        .ok_or_else(|| format!("Unknown migration scope {scope}"))?;
+    let path = database.path(data_dir, chat_db);
+    if !path.exists() {
+        return Err(format!("{} does not exist", database.file_name()));
+    }
+    migrations::rollback(&open_database(&path)?, scope, steps, target_version)
+}
+
+fn open_database(path: &Path) -> Result<Connection, String> {
+    Connection::open(path).map_err(|error| format!("Failed to open {}: {error}", path.display()))
+}
+
+/// Runs a single statement against `path` on a read-only connection. The
+/// statement itself must also be read-only, so writes fail before reaching
+/// SQLite's own read-only check. SQLite would silently ignore anything after
+/// the first statement, so more than one is rejected outright.
+pub fn query_read_only(path: &Path, sql: &str, params: &[Value]) -> Result<QueryResult, String> {
+    let connection = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|error| format!("Failed to open {}: {error}", path.display()))?;
+    let mut batch = Batch::new(&connection, sql);
+    let mut statement = batch
+        .next()
+        .map_err(|error| error.to_string())?
+        .ok_or_else(|| "No statement to run".to_string())?;
+    if !matches!(batch.next(), Ok(None)) {
+        return Err("Only a single statement is allowed".to_string());
+    }
+    if !statement.readonly() {
+        return Err("Only read-only statements are allowed".to_string());
+    }
+
+    let columns: Vec<String> = statement
+        .column_names()
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    let bound = params.iter().map(to_sql_value);
+    let mut rows = statement
+        .query(params_from_iter(bound))
+        .map_err(|error| error.to_string())?;
+
+    let mut result = QueryResult {
+        columns,
+        rows: Vec::new(),
+        truncated: false,
+    };
+    while let Some(row) = rows.next().map_err(|error| error.to_string())? {
+        if result.rows.len() == QUERY_ROW_LIMIT {
+            result.truncated = true;
+            break;
+        }
+        let values = (0..result.columns.len())
+            .map(|index| row.get_ref(index).map(to_json_value))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|error| error.to_string())?;
+        result.rows.push(values);
+    }
+    Ok(result)
+}
+
+fn to_sql_value(value: &Value) -> rusqlite::types::Value {
+    match value {
+        Value::Null => rusqlite::types::Value::Null,
+        Value::Bool(flag) => rusqlite::types::Value::Integer(i64::from(*flag)),
+        Value::Number(number) => match number.as_i64() {
+            Some(integer) => rusqlite::types::Value::Integer(integer),
+            None => rusqlite::types::Value::Real(number.as_f64().unwrap_or_default()),
+        },
+        Value::String(text) => rusqlite::types::Value::Text(text.clone()),
+        other => rusqlite::types::Value::Text(other.to_string()),
+    }
+}
+
+fn to_json_value(value: ValueRef<'_>) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(integer) => Value::from(integer),
+        ValueRef::Real(real) => Value::from(real),
+        ValueRef::Text(text) => Value::String(String::from_utf8_lossy(text).into_owned()),
+        ValueRef::Blob(blob) => Value::String(STANDARD.encode(blob)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn queries_are_read_only_and_capped() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("chat.sqlite");
+        {
+            let connection = Connection::open(&path).unwrap();
+            connection
+                .execute_batch("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT);")
+                .unwrap();
+            for index in 0..(QUERY_ROW_LIMIT + 5) {
+                connection
+                    .execute(
+                        "INSERT INTO items (name) VALUES (?1)",
+                        [format!("item-{index}")],
+                    )
+                    .unwrap();
+            }
+        }
+
+        let result = query_read_only(
+            &path,
+            "SELECT id, name FROM items WHERE id = ?1",
+            &[json!(3)],
+        )
+        .unwrap();
+        assert_eq!(result.columns, vec!["id", "name"]);
+        assert_eq!(result.rows, vec![vec![json!(3), json!("item-2")]]);
+
+        let all = query_read_only(&path, "SELECT * FROM items", &[]).unwrap();
+        assert_eq!(all.rows.len(), QUERY_ROW_LIMIT);
+        assert!(all.truncated);
+
+        assert!(query_read_only(&path, "DELETE FROM items", &[]).is_err());
+        assert!(query_read_only(&path, "SELECT 1; DELETE FROM items", &[]).is_err());
+        assert!(query_read_only(&path, "SELECT 1; SELECT 2;", &[]).is_err());
+        assert_eq!(
+            query_read_only(&path, "SELECT 'a;b' AS text;", &[])
+                .unwrap()
+                .rows,
+            vec![vec![json!("a;b")]]
+        );
+        assert!(query_read_only(&path, "  -- nothing", &[]).is_err());
+        assert!(query_read_only(&dir.path().join("missing.sqlite"), "SELECT 1", &[]).is_err());
+    }
+
+    #[test]
+    fn migration_status_and_rollback_cover_existing_databases() {
+        let dir = tempdir().unwrap();
+        let chat_db = dir
+            .path()
+            .join("workspaces")
+            .join("team")
+            .join("chat.sqlite");
+        let connection = Connection::open(dir.path().join("outbox.sqlite")).unwrap();
+        migrations::apply(&connection, OUTBOX_MIGRATION_SCOPE, OUTBOX_MIGRATIONS).unwrap();
+        let latest = OUTBOX_MIGRATIONS.last().unwrap().version;
+
+        let statuses = migration_status(dir.path(), &chat_db).unwrap();
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].scope, OUTBOX_MIGRATION_SCOPE);
+        assert_eq!(statuses[0].current_version, latest);
+        assert!(statuses[0].pending.is_empty());
+
+        let reverted =
+            rollback_migrations(dir.path(), &chat_db, OUTBOX_MIGRATION_SCOPE, 0).unwrap();
+        assert_eq!(reverted.first(), Some(&latest));
+        let statuses = migration_status(dir.path(), &chat_db).unwrap();
+        assert_eq!(statuses[0].current_version, 0);
+        assert_eq!(statuses[0].pending.len(), OUTBOX_MIGRATIONS.len());
+
+        assert!(rollback_migrations(dir.path(), &chat_db, "unknown", 0).is_err());
+        assert!(rollback_migrations(dir.path(), &chat_db, SESSION_MIGRATION_SCOPE, 0).is_err());
+
+        std::fs::create_dir_all(chat_db.parent().unwrap()).unwrap();
+        let connection = Connection::open(&chat_db).unwrap();
+        migrations::apply(&connection, SESSION_MIGRATION_SCOPE, SESSION_MIGRATIONS).unwrap();
+        let statuses = migration_status(dir.path(), &chat_db).unwrap();
+        assert!(
+            statuses
+                .iter()
+                .any(|status| status.scope == SESSION_MIGRATION_SCOPE)
+        );
+    }
+}
@@ -0,0 +1,374 @@
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+
+use crate::hcs10::Hcs10StateSummary;
+use crate::proxy;
+use crate::storage_recovery::StorageRecoveryReport;
+
+/// A snapshot of local state to attach to bug reports. It never includes
+/// keys or message contents.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsBundle {
+    pub generated_at: String,
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub storage: StorageRecoveryReport,
+    pub hcs10_states: Vec<Hcs10StateSummary>,
+}
+
+impl DiagnosticsBundle {
+    pub fn new(
+        app_version: String,
+        storage: StorageRecoveryReport,
+        hcs10_states: Vec<Hcs10StateSummary>,
+    ) -> Self {
+        Self {
+            generated_at: Utc::now().to_rfc3339(),
+            app_version,
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            storage,
+            hcs10_states,
+        }
+    }
+}
+
+/// The oldest Node version, as major and minor, the bundled bridges run on.
+/// 22.15 is the first with zstd in `node:zlib`.
+pub const MIN_NODE_VERSION: (u32, u32) = (22, 15);
+
+const STDERR_LIMIT: usize = 4096;
+
+/// A canned request for one Node bridge.
+pub struct BridgeProbe {
+    pub bridge: &'static str,
+    pub script: Option<PathBuf>,
+    pub action: &'static str,
+    pub payload: Value,
+    /// Whether the bridge handles a single request and exits at the end of
+    /// stdin, as the mirror node and parser bridges do, rather than
+    /// answering line by line.
+    pub one_shot: bool,
+}
+
+impl BridgeProbe {
+    pub fn agent(script: Option<PathBuf>) -> Self {
+        Self {
+            bridge: "agent",
+            script,
+            action: "status",
+            payload: json!({}),
+            one_shot: false,
+        }
+    }
+
+    pub fn hcs10(script: Option<PathBuf>) -> Self {
+        Self {
+            bridge: "hcs10",
+            script,
+            action: "hcs10_validate_profile",
+            payload: json!({ "profileData": { "name": "Self-test", "description": "Bridge self-test" } }),
+            one_shot: false,
+        }
+    }
+
+    /// Decompresses a gzip of `ok`, which exercises the bundled
+    /// dependencies without touching the network.
+    pub fn mirror(script: Option<PathBuf>) -> Self {
+        Self {
+            bridge: "mirror",
+            script,
+            action: "mirror_node_decompress",
+            payload: json!({ "algorithm": "gzip", "data": "H4sIAAAAAAACA8vPBgBH3dx5AgAAAA==" }),
+            one_shot: true,
+        }
+    }
+
+    /// Looks up the testnet fee collection account through the mirror node.
+    pub fn mirror_testnet(script: Option<PathBuf>) -> Self {
+        Self {
+            bridge: "mirror-testnet",
+            script,
+            action: "mirror_node_get_account_info",
+            payload: json!({ "accountId": "0.0.98", "network": "testnet" }),
+            one_shot: true,
+        }
+    }
+
+    pub fn parser(script: Option<PathBuf>) -> Self {
+        Self {
+            bridge: "parser",
+            script,
+            action: "transaction_parser_validate",
+            payload: json!({ "transactionBytes": "CgA=" }),
+            one_shot: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgeSelftestResult {
+    pub bridge: String,
+    pub passed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+    /// From writing the request to reading the reply, including start-up.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub round_trip_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The end of what the bridge wrote to stderr.
+    pub stderr: String,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgeSelftestReport {
+    pub ran_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_version: Option<String>,
+    /// Whether `node` was found and is at least `MIN_NODE_VERSION`.
+    pub node_supported: bool,
+    pub bridges: Vec<BridgeSelftestResult>,
+    pub passed: bool,
+}
+
+/// Runs each probe in turn with `node`, giving each one `timeout`.
+pub async fn run_bridge_selftest(
+    node: &str,
+    probes: &[BridgeProbe],
+    timeout: Duration,
+) -> BridgeSelftestReport {
+    let node_version = detect_node_version(node).await;
+    let node_supported = node_version
+        .as_deref()
+        .and_then(node_major_minor)
+        .is_some_and(|version| version >= MIN_NODE_VERSION);
+    let mut bridges = Vec::new();
+    for probe in probes {
+        bridges.push(run_probe(node, probe, timeout).await);
+    }
+    BridgeSelftestReport {
+        ran_at: Utc::now().to_rfc3339(),
+        passed: node_supported && bridges.iter().all(|result| result.passed),
+        node_version,
+        node_supported,
+        bridges,
+    }
+}
+
+async fn detect_node_version(node: &str) -> Option<String> {
+    let output = Command::new(node).arg("--version").output().await.ok()?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !version.is_empty()).then_some(version)
+}
+
+fn node_major_minor(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.trim().trim_start_matches('v').split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().map_or(Some(0), |minor| minor.parse().ok())?;
+    Some((major, minor))
+}
+
+pub async fn run_probe(node: &str, probe: &BridgeProbe, timeout: Duration) -> BridgeSelftestResult {
+    let mut result = BridgeSelftestResult {
+        bridge: probe.bridge.to_string(),
+        passed: false,
+        script: probe
+            .script
+            .as_ref()
+            .map(|script| script.display().to_string()),
+        round_trip_ms: None,
+        error: None,
+        stderr: String::new(),
+    };
+    let Some(script) = &probe.script else {
+        result.error = Some("Bridge script was not found in the app resources".to_string());
+        return result;
+    };
+
+    let mut command = Command::new(node);
+    command
+        .arg(script)
+        .envs(proxy::env_vars())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(parent) = script.parent() {
+        command.current_dir(parent);
+    }
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(error) => {
+            result.error = Some(format!("Failed to start {node}: {error}"));
+            return result;
+        }
+    };
+
+    let stderr_task = child.stderr.take().map(|mut stderr| {
+        tokio::spawn(async move {
+            let mut buffer = Vec::new();
+            let _ = stderr.read_to_end(&mut buffer).await;
+            buffer
+        })
+    });
+
+    let started = Instant::now();
+    let outcome = tokio::time::timeout(timeout, exchange(&mut child, probe)).await;
+    let elapsed = started.elapsed();
+    let _ = child.start_kill();
+    let _ = child.wait().await;
+
+    match outcome {
+        Ok(Ok(())) => {
+            result.passed = true;
+            result.round_trip_ms = Some(elapsed.as_millis() as u64);
+        }
+        Ok(Err(error)) => result.error = Some(error),
+        Err(_) => result.error = Some(format!("No reply within {} seconds", timeout.as_secs_f32())),
+    }
+
+    if let Some(task) = stderr_task
+        && let Ok(Ok(buffer)) = tokio::time::timeout(Duration::from_secs(1), task).await
+    {
+        result.stderr = tail(&String::from_utf8_lossy(&buffer), STDERR_LIMIT);
+    }
+    result
+}
+
+/// Sends the probe's request and waits for its result line.
+async fn exchange(child: &mut Child, probe: &BridgeProbe) -> Result<(), String> {
+    let request = json!({ "id": 1, "action": probe.action, "payload": probe.payload });
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| "Bridge stdin unavailable".to_string())?;
+    stdin
+        .write_all(format!("{request}\n").as_bytes())
+        .await
+        .map_err(|error| format!("Failed to write the request: {error}"))?;
+    stdin
+        .flush()
+        .await
+        .map_err(|error| format!("Failed to write the request: {error}"))?;
+    // Keep line-based bridges' stdin open; they exit when it closes.
+    let _stdin = if probe.one_shot {
+        drop(stdin);
+        None
+    } else {
+        Some(stdin)
+    };
+
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| "Bridge stdout unavailable".to_string())?;
+    let mut lines = BufReader::new(stdout).lines();
+    while let Some(line) = lines
+        .next_line()
+        .await
+        .map_err(|error| format!("Failed to read the reply: {error}"))?
+    {
+        let Ok(reply) = serde_json::from_str::<Value>(line.trim()) else {
+            continue;
+        };
+        let is_result = reply.get("id") == Some(&json!(1))
+            && reply
+                .get("type")
+                .and_then(Value::as_str)
+                .is_none_or(|kind| kind == "result" || kind == "response");
+        if !is_result {
+            continue;
+        }
+        if reply.get("success").and_then(Value::as_bool) == Some(true) {
+            return Ok(());
+        }
+        return Err(reply
+            .get("error")
+            .and_then(Value::as_str)
+            .unwrap_or("The bridge reported a failure")
+            .to_string());
+    }
+    Err("The bridge exited without replying".to_string())
+}
+
+fn tail(text: &str, limit: usize) -> String {
+    let text = text.trim();
+    let mut start = text.len().saturating_sub(limit);
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    text[start..].to_string()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn fake_bridge(dir: &std::path::Path, name: &str, body: &str) -> Option<PathBuf> {
+        let path = dir.join(name);
+        std::fs::write(&path, body).unwrap();
+        Some(path)
+    }
+
+    #[tokio::test]
+    async fn probes_report_replies_failures_and_stderr() {
+        let dir = tempfile::tempdir().unwrap();
+        let probes = [
+            BridgeProbe::agent(fake_bridge(
+                dir.path(),
+                "agent.sh",
+                "read line\necho 'booting' >&2\necho '{\"id\":1,\"type\":\"progress\"}'\necho '{\"id\":1,\"success\":true,\"data\":{}}'\nexec sleep 5\n",
+            )),
+            BridgeProbe::parser(fake_bridge(
+                dir.path(),
+                "parser.sh",
+                "cat > /dev/null\necho 'Cannot find module x' >&2\necho '{\"id\":1,\"success\":false,\"error\":\"missing module\"}'\n",
+            )),
+            BridgeProbe::hcs10(fake_bridge(dir.path(), "hcs10.sh", "exec sleep 5\n")),
+            BridgeProbe::mirror(None),
+        ];
+
+        let report = run_bridge_selftest("sh", &probes, Duration::from_millis(500)).await;
+        assert!(!report.passed);
+        let agent = &report.bridges[0];
+        assert!(agent.passed, "{agent:?}");
+        assert!(agent.round_trip_ms.is_some());
+        assert_eq!(agent.stderr, "booting");
+
+        let parser = &report.bridges[1];
+        assert!(!parser.passed);
+        assert_eq!(parser.error.as_deref(), Some("missing module"));
+        assert_eq!(parser.stderr, "Cannot find module x");
+
+        assert!(
+            report.bridges[2]
+                .error
+                .as_ref()
+                .unwrap()
+                .starts_with("No reply")
+        );
+        assert!(report.bridges[3].script.is_none());
+        assert!(!report.bridges[3].passed);
+    }
+
+    #[test]
+    fn node_versions_are_parsed() {
+        assert_eq!(node_major_minor("v22.15.1"), Some((22, 15)));
+        assert_eq!(node_major_minor("18.0.0\n"), Some((18, 0)));
+        assert_eq!(node_major_minor("garbage"), None);
+        assert!(node_major_minor("v22.9.0").unwrap() < MIN_NODE_VERSION);
+        assert!(node_major_minor("v23.1.0").unwrap() >= MIN_NODE_VERSION);
+        assert_eq!(tail("ééé", 3), "é");
+    }
+}
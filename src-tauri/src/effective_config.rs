@@ -0,0 +1,221 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::config_history::{is_secret_path, mask_if};
+
+/// Config paths that `get_environment_config` fills and the variable each is
+/// read from.
+const ENV_VARIABLES: &[(&str, &str)] = &[
+    ("hedera.accountId", "HEDERA_OPERATOR_ID"),
+    ("hedera.privateKey", "HEDERA_OPERATOR_KEY"),
+    ("hedera.network", "HEDERA_NETWORK"),
+    ("swarm.beeApiUrl", "SWARM_BEE_API_URL"),
+    ("swarm.beeFeedPK", "SWARM_BEE_FEED_PK"),
+    ("swarm.autoAssignStamp", "SWARM_AUTO_ASSIGN_STAMP"),
+    (
+        "swarm.deferredUploadSizeThresholdMB",
+        "SWARM_DEFERRED_UPLOAD_SIZE_THRESHOLD_MB",
+    ),
+    ("openai.apiKey", "OPENAI_API_KEY"),
+    ("openai.model", "OPENAI_MODEL"),
+    ("anthropic.apiKey", "ANTHROPIC_API_KEY"),
+    ("anthropic.model", "ANTHROPIC_MODEL"),
+    ("openrouter.apiKey", "OPENROUTER_API_KEY"),
+    ("openrouter.model", "OPENROUTER_MODEL"),
+    ("gemini.apiKey", "GEMINI_API_KEY"),
+    ("gemini.model", "GEMINI_MODEL"),
+    ("llmProvider", "LLM_PROVIDER"),
+];
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigSource {
+    Default,
+    File,
+    Environment,
+}
+
+/// Where one leaf value of the effective config came from. `variable` names
+/// the environment variable when it was set directly rather than derived.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveField {
+    pub path: String,
+    pub value: Value,
+    pub source: ConfigSource,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variable: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveConfig {
+    pub config: Value,
+    pub fields: Vec<EffectiveField>,
+}
+
+/// The entries of `ENV_VARIABLES` whose variable is set in this process.
+pub fn set_variables() -> Vec<(&'static str, &'static str)> {
+    ENV_VARIABLES
+        .iter()
+        .filter(|(_, variable)| std::env::var_os(variable).is_some())
+        .copied()
+        .collect()
+}
+
+/// Merges the serialized default config, the raw config.json and the
+/// environment overrides. A value in config.json wins unless it is missing,
+/// null or blank, in which case the environment and then the default apply.
+/// Environment entries outside the config, such as `walletConnect`, are
+/// ignored. Secrets are masked in both the config and the field list.
+pub fn resolve(
+    defaults: &Value,
+    file: Option<&Value>,
+    environment: &Value,
+    variables: &[(&str, &str)],
+) -> EffectiveConfig {
+    let known =
+        |key: &String| defaults.get(key).is_some() || file.and_then(|file| file.get(key)).is_some();
+    let environment = match environment {
+        Value::Object(entries) => Value::Object(
+            entries
+                .iter()
+                .filter(|(key, _)| known(key))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        ),
+        _ => Value::Null,
+    };
+
+    let mut fields = Vec::new();
+    let config = merge(
+        "",
+        [Some(defaults), file, Some(&environment)],
+        variables,
+        &mut fields,
+    );
+    EffectiveConfig { config, fields }
+}
+
+fn merge(
+    path: &str,
+    [default, file, environment]: [Option<&Value>; 3],
+    variables: &[(&str, &str)],
+    fields: &mut Vec<EffectiveField>,
+) -> Value {
+    let objects: Vec<&Map<String, Value>> = [default, file, environment]
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object)
+        .collect();
+    if !objects.is_empty() {
+        let mut keys: Vec<&String> = objects.iter().flat_map(|object| object.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        return Value::Object(
+            keys.into_iter()
+                .map(|key| {
+                    let child = if path.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{path}.{key}")
+                    };
+                    let sources = [default, file, environment]
+                        .map(|source| source.and_then(|source| source.get(key)));
+                    let value = merge(&child, sources, variables, fields);
+                    (key.clone(), value)
+                })
+                .collect(),
+        );
+    }
+
+    let (value, source) = if let Some(value) = file.filter(|value| is_set(value)) {
+        (value, ConfigSource::File)
+    } else if let Some(value) = environment.filter(|value| is_set(value)) {
+        (value, ConfigSource::Environment)
+    } else {
+        (default.unwrap_or(&Value::Null), ConfigSource::Default)
+    };
+    let value = mask_if(is_secret_path(path), value);
+    let variable = (source == ConfigSource::Environment)
+        .then(|| {
+            variables
+                .iter()
+                .find(|(field, _)| *field == path)
+                .map(|(_, variable)| variable.to_string())
+        })
+        .flatten();
+    fields.push(EffectiveField {
+        path: path.to_string(),
+        value: value.clone(),
+        source,
+        variable,
+    });
+    value
+}
+
+fn is_set(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::String(text) => !text.trim().is_empty(),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn file_values_win_over_environment_and_defaults() {
+        let defaults = json!({
+            "hedera": { "accountId": "", "privateKey": "", "network": "testnet" },
+            "openai": { "apiKey": "", "model": "gpt-5" },
+            "swarm": null
+        });
+        let file = json!({
+            "hedera": { "accountId": "0.0.1234", "privateKey": "" },
+            "openai": { "apiKey": "ENC:v2:abc", "model": "" }
+        });
+        let environment = json!({
+            "enableMainnet": true,
+            "hedera": { "accountId": "0.0.9999", "privateKey": "302e..." },
+            "openai": { "apiKey": "sk-env", "model": "gpt-4o-mini" }
+        });
+
+        let effective = resolve(
+            &defaults,
+            Some(&file),
+            &environment,
+            &[("hedera.privateKey", "HEDERA_OPERATOR_KEY")],
+        );
+        let field = |path: &str| {
+            effective
+                .fields
+                .iter()
+                .find(|field| field.path == path)
+                .cloned()
+                .unwrap()
+        };
+
+        assert_eq!(field("hedera.accountId").value, json!("0.0.1234"));
+        assert_eq!(field("hedera.accountId").source, ConfigSource::File);
+        assert_eq!(
+            field("hedera.privateKey"),
+            EffectiveField {
+                path: "hedera.privateKey".into(),
+                value: json!("********"),
+                source: ConfigSource::Environment,
+                variable: Some("HEDERA_OPERATOR_KEY".into()),
+            }
+        );
+        assert_eq!(field("hedera.network").source, ConfigSource::Default);
+        assert_eq!(field("openai.apiKey").source, ConfigSource::File);
+        assert_eq!(field("openai.model").value, json!("gpt-4o-mini"));
+        assert_eq!(field("openai.model").variable, None);
+        assert_eq!(field("swarm").source, ConfigSource::Default);
+        assert!(effective.config.get("enableMainnet").is_none());
+        assert_eq!(effective.config["openai"]["apiKey"], json!("********"));
+    }
+}
@@ -1,16 +1,132 @@
-use chrono::Utc;
-use rusqlite::{Connection, OptionalExtension, Row, params};
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use rusqlite::types::{FromSql, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use rusqlite::{
+    Connection, OptionalExtension, Row, params, params_from_iter, types::Value as SqlValue,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
 use std::path::Path;
 use tokio::sync::Mutex;
 
+use crate::entity_schema;
+use crate::json_schema;
+use crate::migrations::{self, Migration};
+
+/// The kinds of Hedera entity the app keeps track of.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum EntityType {
+    TokenId,
+    NftSerial,
+    AccountId,
+    TopicId,
+    ScheduleId,
+    ContractId,
+    FileId,
+    /// A type the app does not recognize, such as one an agent made up.
+    Other,
+}
+
+impl EntityType {
+    pub const ALL: [Self; 8] = [
+        Self::TokenId,
+        Self::NftSerial,
+        Self::AccountId,
+        Self::TopicId,
+        Self::ScheduleId,
+        Self::ContractId,
+        Self::FileId,
+        Self::Other,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::TokenId => "tokenId",
+            Self::NftSerial => "nftSerial",
+            Self::AccountId => "accountId",
+            Self::TopicId => "topicId",
+            Self::ScheduleId => "scheduleId",
+            Self::ContractId => "contractId",
+            Self::FileId => "fileId",
+            Self::Other => "other",
+        }
+    }
+
+    /// Accepts the canonical names as well as the spellings older builds
+    /// and the agent used, such as `token`, `TOKEN_ID` or `nft`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let normalized: String = value
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .collect::<String>()
+            .to_ascii_lowercase();
+        match normalized.strip_suffix("id").unwrap_or(&normalized) {
+            "token" => Some(Self::TokenId),
+            "nft" | "nftserial" | "serial" => Some(Self::NftSerial),
+            "account" => Some(Self::AccountId),
+            "topic" => Some(Self::TopicId),
+            "schedule" => Some(Self::ScheduleId),
+            "contract" | "smartcontract" => Some(Self::ContractId),
+            "file" => Some(Self::FileId),
+            "other" => Some(Self::Other),
+            _ => None,
+        }
+    }
+
+    /// Parses `value`, guessing from `entity_id` when it is not a known
+    /// type, and falls back to `Other`.
+    fn resolve(value: &str, entity_id: &str) -> Self {
+        Self::parse(value)
+            .or_else(|| Self::infer_from_id(entity_id))
+            .unwrap_or(Self::Other)
+    }
+
+    /// Guesses the type from the id alone. Only NFT serials
+    /// (`0.0.123/4`, `0.0.123#4`) can be told apart this way.
+    fn infer_from_id(entity_id: &str) -> Option<Self> {
+        let (token, serial) = entity_id.split_once(['/', '#'])?;
+        let is_token = token.split('.').count() == 3
+            && token
+                .split('.')
+                .all(|part| !part.is_empty() && part.bytes().all(|byte| byte.is_ascii_digit()));
+        (is_token && !serial.is_empty() && serial.bytes().all(|byte| byte.is_ascii_digit()))
+            .then_some(Self::NftSerial)
+    }
+}
+
+impl std::fmt::Display for EntityType {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str(self.as_str())
+    }
+}
+
+impl ToSql for EntityType {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for EntityType {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        Ok(Self::parse(value.as_str()?).unwrap_or(Self::Other))
+    }
+}
+
+/// Filters match the stored canonical names, so `token` finds `tokenId` rows.
+fn canonical_type_filter(entity_type: &str) -> String {
+    EntityType::parse(entity_type)
+        .map(|parsed| parsed.as_str().to_string())
+        .unwrap_or_else(|| entity_type.to_string())
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct EntityAssociation {
     pub entity_id: String,
     pub entity_name: String,
-    pub entity_type: String,
+    pub entity_type: EntityType,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub transaction_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -20,6 +136,15 @@ pub struct EntityAssociation {
     pub is_active: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<Value>,
+    /// Free-text notes the user keeps about the entity.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// Whether the background watcher polls the mirror node for changes.
+    #[serde(default)]
+    pub watched: bool,
+    /// Starred by the user; `EntitySortOrder::FavoritesFirst` lists these first.
+    #[serde(default)]
+    pub favorite: bool,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -29,8 +154,308 @@ pub struct EntityStoreResult {
     pub created: bool,
 }
 
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum EntitySortOrder {
+    #[default]
+    Newest,
+    /// Favorites, newest first, then everything else, newest first.
+    FavoritesFirst,
+}
+
+/// Filters for a page of `list_entities_page`. Dates are RFC 3339; ones
+/// that do not parse are ignored.
+#[derive(Clone, Debug, Default)]
+pub struct EntityListQuery {
+    pub search: Option<String>,
+    pub entity_type: Option<String>,
+    pub session_id: Option<String>,
+    pub created_after: Option<String>,
+    pub created_before: Option<String>,
+    pub include_inactive: bool,
+    pub sort: EntitySortOrder,
+    /// The `next_cursor` of the previous page. Cursors only apply to the
+    /// sort order they were returned for.
+    pub cursor: Option<String>,
+    pub page_size: Option<usize>,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityPage {
+    pub entities: Vec<EntityAssociation>,
+    /// Pass back as `cursor` for the next page; absent on the last one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityCount {
+    /// The group's value; `None` for entities without one, such as those
+    /// not tied to a session.
+    pub key: Option<String>,
+    pub count: u64,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityTimelinePoint {
+    /// A UTC day, `YYYY-MM-DD`.
+    pub date: String,
+    pub count: u64,
+}
+
+/// Counts over the active entities.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityStats {
+    pub total: u64,
+    pub favorites: u64,
+    pub watched: u64,
+    pub by_type: Vec<EntityCount>,
+    pub by_session: Vec<EntityCount>,
+    /// Grouped by the `network` recorded in the entity's metadata.
+    pub by_network: Vec<EntityCount>,
+    /// Entities created per day, oldest first; days without any are left out.
+    pub timeline: Vec<EntityTimelinePoint>,
+}
+
+const DEFAULT_TIMELINE_DAYS: u32 = 30;
+const MAX_TIMELINE_DAYS: u32 = 365;
+
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 500;
+
+/// Position of the last row on a page, in `sort_key DESC, id DESC` order.
+fn encode_cursor(created_at: &str, id: i64) -> String {
+    URL_SAFE_NO_PAD.encode(format!("{created_at}|{id}"))
+}
+
+fn decode_cursor(cursor: &str) -> Result<(String, i64), String> {
+    let invalid = || "Invalid entity cursor".to_string();
+    let decoded = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+    let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+    let (created_at, id) = decoded.rsplit_once('|').ok_or_else(invalid)?;
+    Ok((created_at.to_string(), id.parse().map_err(|_| invalid())?))
+}
+
+/// Converts an RFC 3339 timestamp to the form SQLite's `datetime()` returns.
+fn sqlite_datetime(value: &str) -> Option<String> {
+    DateTime::parse_from_rfc3339(value).ok().map(|parsed| {
+        parsed
+            .with_timezone(&Utc)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    })
+}
+
+/// A change made to an entity's bookkeeping.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityAuditAction {
+    Renamed,
+    Deleted,
+    Restored,
+    Purged,
+    MetadataUpdated,
+    NotesUpdated,
+}
+
+impl EntityAuditAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Renamed => "renamed",
+            Self::Deleted => "deleted",
+            Self::Restored => "restored",
+            Self::Purged => "purged",
+            Self::MetadataUpdated => "metadata_updated",
+            Self::NotesUpdated => "notes_updated",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        [
+            Self::Renamed,
+            Self::Deleted,
+            Self::Restored,
+            Self::Purged,
+            Self::MetadataUpdated,
+            Self::NotesUpdated,
+        ]
+        .into_iter()
+        .find(|action| action.as_str() == value)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityAuditEntry {
+    pub id: i64,
+    pub entity_id: String,
+    pub action: EntityAuditAction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<Value>,
+    pub created_at: String,
+}
+
+/// Metadata keys written by background refreshes rather than by the user.
+/// `liveState` is a mirror node snapshot and changes on every poll.
+const UNAUDITED_METADATA_KEYS: &[&str] = &["liveState"];
+
+/// The columns `map_row` reads, for every query returning whole entities.
+const ENTITY_COLUMNS: &str = "entity_id, entity_name, entity_type, transaction_id, session_id, \
+    created_at, updated_at, is_active, metadata, notes, watched, favorite";
+
+const DEFAULT_AUDIT_LIMIT: u32 = 100;
+const MAX_AUDIT_LIMIT: u32 = 500;
+
 pub struct EntityService {
     db: Mutex<Connection>,
+    /// Metadata passed to `store_entity` must match the schema for its type
+    /// while `validate_metadata` is set.
+    metadata_schemas: HashMap<EntityType, Value>,
+    validate_metadata: bool,
+}
+
+pub const ENTITY_MIGRATION_SCOPE: &str = "entity";
+
+pub const ENTITY_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create entity associations",
+        up: |connection| {
+            connection.execute_batch(
+                r#"
+            CREATE TABLE IF NOT EXISTS entity_associations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                entity_id TEXT NOT NULL,
+                entity_name TEXT NOT NULL,
+                entity_type TEXT NOT NULL,
+                transaction_id TEXT,
+                session_id TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                is_active INTEGER NOT NULL DEFAULT 1,
+                metadata TEXT
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_entity_associations_entity_id
+                ON entity_associations(entity_id);
+            CREATE INDEX IF NOT EXISTS idx_entity_associations_entity_type
+                ON entity_associations(entity_type);
+            CREATE INDEX IF NOT EXISTS idx_entity_associations_session_id
+                ON entity_associations(session_id);
+            CREATE INDEX IF NOT EXISTS idx_entity_associations_created_at
+                ON entity_associations(created_at);
+            CREATE INDEX IF NOT EXISTS idx_entity_associations_active
+                ON entity_associations(is_active);
+            "#,
+            )
+        },
+        down: |connection| connection.execute_batch("DROP TABLE IF EXISTS entity_associations;"),
+    },
+    Migration {
+        version: 2,
+        description: "add entity notes",
+        up: |connection| {
+            migrations::add_column_if_missing(connection, "entity_associations", "notes", "TEXT")
+        },
+        down: |connection| {
+            connection.execute_batch("ALTER TABLE entity_associations DROP COLUMN notes;")
+        },
+    },
+    Migration {
+        version: 3,
+        description: "add entity watch flag",
+        up: |connection| {
+            migrations::add_column_if_missing(
+                connection,
+                "entity_associations",
+                "watched",
+                "INTEGER NOT NULL DEFAULT 0",
+            )
+        },
+        down: |connection| {
+            connection.execute_batch("ALTER TABLE entity_associations DROP COLUMN watched;")
+        },
+    },
+    Migration {
+        version: 4,
+        description: "create entity audit log",
+        up: |connection| {
+            connection.execute_batch(
+                r#"
+            CREATE TABLE IF NOT EXISTS entity_audit (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                entity_id TEXT NOT NULL,
+                action TEXT NOT NULL,
+                previous TEXT,
+                current TEXT,
+                created_at TEXT NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_entity_audit_entity_id
+                ON entity_audit(entity_id);
+            "#,
+            )
+        },
+        down: |connection| connection.execute_batch("DROP TABLE IF EXISTS entity_audit;"),
+    },
+    Migration {
+        version: 5,
+        description: "normalize entity types",
+        up: normalize_entity_types,
+        // The old spellings are not kept; canonical names read fine either way.
+        down: |_| Ok(()),
+    },
+    Migration {
+        version: 6,
+        description: "add entity favorites",
+        up: |connection| {
+            migrations::add_column_if_missing(
+                connection,
+                "entity_associations",
+                "favorite",
+                "INTEGER NOT NULL DEFAULT 0",
+            )
+        },
+        down: |connection| {
+            connection.execute_batch("ALTER TABLE entity_associations DROP COLUMN favorite;")
+        },
+    },
+];
+
+/// Rewrites every `entity_type` to its canonical name. Rows whose type
+/// cannot be worked out are kept as `other`.
+fn normalize_entity_types(connection: &Connection) -> rusqlite::Result<()> {
+    let rows = connection
+        .prepare("SELECT id, entity_id, entity_type FROM entity_associations")?
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for (id, entity_id, raw_type) in rows {
+        let entity_type = EntityType::resolve(&raw_type, &entity_id);
+        if entity_type.as_str() == raw_type {
+            continue;
+        }
+        if entity_type == EntityType::Other {
+            log::warn!("Keeping entity {entity_id} with unknown type {raw_type} as other");
+        }
+        connection.execute(
+            "UPDATE entity_associations SET entity_type = ?1 WHERE id = ?2",
+            params![entity_type, id],
+        )?;
+    }
+    Ok(())
 }
 
 impl EntityService {
@@ -42,6 +467,8 @@ impl EntityService {
         Self::configure_connection(&connection)?;
         Ok(Self {
             db: Mutex::new(connection),
+            metadata_schemas: entity_schema::default_metadata_schemas(),
+            validate_metadata: true,
         })
     }
 
@@ -51,6 +478,8 @@ impl EntityService {
         Self::configure_connection(&connection).expect("init schema");
         Self {
             db: Mutex::new(connection),
+            metadata_schemas: entity_schema::default_metadata_schemas(),
+            validate_metadata: true,
         }
     }
 
@@ -58,35 +487,7 @@ impl EntityService {
         connection
             .pragma_update(None, "foreign_keys", &"ON")
             .map_err(|err| err.to_string())?;
-        connection
-            .execute_batch(
-                r#"
-                CREATE TABLE IF NOT EXISTS entity_associations (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    entity_id TEXT NOT NULL,
-                    entity_name TEXT NOT NULL,
-                    entity_type TEXT NOT NULL,
-                    transaction_id TEXT,
-                    session_id TEXT,
-                    created_at TEXT NOT NULL,
-                    updated_at TEXT NOT NULL,
-                    is_active INTEGER NOT NULL DEFAULT 1,
-                    metadata TEXT
-                );
-
-                CREATE INDEX IF NOT EXISTS idx_entity_associations_entity_id
-                    ON entity_associations(entity_id);
-                CREATE INDEX IF NOT EXISTS idx_entity_associations_entity_type
-                    ON entity_associations(entity_type);
-                CREATE INDEX IF NOT EXISTS idx_entity_associations_session_id
-                    ON entity_associations(session_id);
-                CREATE INDEX IF NOT EXISTS idx_entity_associations_created_at
-                    ON entity_associations(created_at);
-                CREATE INDEX IF NOT EXISTS idx_entity_associations_active
-                    ON entity_associations(is_active);
-                "#,
-            )
-            .map_err(|err| err.to_string())?;
+        migrations::apply(connection, ENTITY_MIGRATION_SCOPE, ENTITY_MIGRATIONS)?;
         Ok(())
     }
 
@@ -104,9 +505,98 @@ impl EntityService {
             updated_at: row.get("updated_at")?,
             is_active: row.get::<_, i64>("is_active")? == 1,
             metadata: metadata_value,
+            notes: row.get("notes")?,
+            watched: row.get::<_, i64>("watched")? == 1,
+            favorite: row.get::<_, i64>("favorite")? == 1,
         })
     }
 
+    fn record_audit(
+        connection: &Connection,
+        entity_id: &str,
+        action: EntityAuditAction,
+        previous: Option<Value>,
+        current: Option<Value>,
+    ) -> Result<(), String> {
+        connection
+            .execute(
+                "INSERT INTO entity_audit (entity_id, action, previous, current, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    entity_id,
+                    action.as_str(),
+                    previous.map(|value| value.to_string()),
+                    current.map(|value| value.to_string()),
+                    Utc::now().to_rfc3339()
+                ],
+            )
+            .map_err(|err| err.to_string())?;
+        Ok(())
+    }
+
+    /// Audit entries, newest first, for one entity or for all of them.
+    pub async fn get_audit_log(
+        &self,
+        entity_id: Option<&str>,
+        limit: Option<u32>,
+    ) -> Result<Vec<EntityAuditEntry>, String> {
+        let limit = limit
+            .unwrap_or(DEFAULT_AUDIT_LIMIT)
+            .clamp(1, MAX_AUDIT_LIMIT);
+        let connection = self.db.lock().await;
+        let mut statement = connection
+            .prepare(
+                "SELECT id, entity_id, action, previous, current, created_at
+                 FROM entity_audit
+                 WHERE ?1 IS NULL OR entity_id = ?1
+                 ORDER BY id DESC
+                 LIMIT ?2",
+            )
+            .map_err(|err| err.to_string())?;
+        let rows = statement
+            .query_map(params![entity_id, limit], |row| {
+                let action: String = row.get("action")?;
+                let previous: Option<String> = row.get("previous")?;
+                let current: Option<String> = row.get("current")?;
+                Ok((
+                    row.get::<_, i64>("id")?,
+                    row.get::<_, String>("entity_id")?,
+                    action,
+                    previous,
+                    current,
+                    row.get::<_, String>("created_at")?,
+                ))
+            })
+            .map_err(|err| err.to_string())?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            let (id, entity_id, action, previous, current, created_at) =
+                row.map_err(|err| err.to_string())?;
+            let Some(action) = EntityAuditAction::parse(&action) else {
+                continue;
+            };
+            entries.push(EntityAuditEntry {
+                id,
+                entity_id,
+                action,
+                previous: previous.and_then(|raw| serde_json::from_str(&raw).ok()),
+                current: current.and_then(|raw| serde_json::from_str(&raw).ok()),
+                created_at,
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Turns schema validation of stored metadata on or off, following
+    /// `advanced.validateEntityMetadata`.
+    pub fn set_metadata_validation(&mut self, enabled: bool) {
+        self.validate_metadata = enabled;
+    }
+
+    /// `entity_type` may use any spelling `EntityType::parse` accepts; it is
+    /// stored under its canonical name, or as `other` when unknown. While
+    /// validation is on, metadata that fails the type's schema is rejected
+    /// rather than stored.
     pub async fn store_entity(
         &self,
         entity_id: &str,
@@ -116,11 +606,25 @@ impl EntityService {
         session_id: Option<&str>,
         metadata: Option<&Value>,
     ) -> Result<EntityStoreResult, String> {
+        let entity_type = EntityType::resolve(entity_type, entity_id);
+        if let (true, Some(metadata), Some(schema)) = (
+            self.validate_metadata,
+            metadata,
+            self.metadata_schemas.get(&entity_type),
+        ) {
+            let errors = json_schema::validate(schema, metadata);
+            if !errors.is_empty() {
+                return Err(format!(
+                    "Invalid {entity_type} metadata: {}",
+                    errors.join("; ")
+                ));
+            }
+        }
         let connection = self.db.lock().await;
 
         let mut statement = connection
             .prepare(
-                "SELECT entity_id, entity_name, entity_type, transaction_id, session_id, created_at, updated_at, is_active, metadata\n                 FROM entity_associations\n                 WHERE entity_id = ?1 AND is_active = 1\n                 ORDER BY datetime(created_at) DESC\n                 LIMIT 1",
+                &format!("SELECT {ENTITY_COLUMNS}\n                 FROM entity_associations\n                 WHERE entity_id = ?1 AND is_active = 1\n                 ORDER BY datetime(created_at) DESC\n                 LIMIT 1"),
             )
             .map_err(|err| err.to_string())?;
 
@@ -168,7 +672,7 @@ impl EntityService {
 
         let mut statement = connection
             .prepare(
-                "SELECT entity_id, entity_name, entity_type, transaction_id, session_id, created_at, updated_at, is_active, metadata\n                 FROM entity_associations\n                 WHERE entity_id = ?1 AND is_active = 1\n                 ORDER BY datetime(created_at) DESC\n                 LIMIT 1",
+                &format!("SELECT {ENTITY_COLUMNS}\n                 FROM entity_associations\n                 WHERE entity_id = ?1 AND is_active = 1\n                 ORDER BY datetime(created_at) DESC\n                 LIMIT 1"),
             )
             .map_err(|err| err.to_string())?;
 
@@ -187,8 +691,10 @@ impl EntityService {
         entity_type: Option<String>,
         session_id: Option<String>,
         limit: Option<usize>,
+        include_inactive: bool,
     ) -> Result<Vec<EntityAssociation>, String> {
         let limit_value = limit.unwrap_or(1000) as i64;
+        let entity_type = entity_type.as_deref().map(canonical_type_filter);
         let connection = self.db.lock().await;
         let mut entities = Vec::new();
 
@@ -196,11 +702,14 @@ impl EntityService {
             (Some(entity_type), Some(session_id)) => {
                 let mut statement = connection
                     .prepare(
-                        "SELECT entity_id, entity_name, entity_type, transaction_id, session_id, created_at, updated_at, is_active, metadata\n             FROM entity_associations\n             WHERE is_active = 1 AND entity_type = ?1 AND session_id = ?2\n             ORDER BY datetime(created_at) DESC\n             LIMIT ?3",
+                        &format!("SELECT {ENTITY_COLUMNS}\n             FROM entity_associations\n             WHERE (is_active = 1 OR ?4) AND entity_type = ?1 AND session_id = ?2\n             ORDER BY datetime(created_at) DESC\n             LIMIT ?3"),
                     )
                     .map_err(|err| err.to_string())?;
                 let rows = statement
-                    .query_map(params![entity_type, session_id, limit_value], Self::map_row)
+                    .query_map(
+                        params![entity_type, session_id, limit_value, include_inactive],
+                        Self::map_row,
+                    )
                     .map_err(|err| err.to_string())?;
                 for row in rows {
                     entities.push(row.map_err(|err| err.to_string())?);
@@ -209,11 +718,14 @@ impl EntityService {
             (Some(entity_type), None) => {
                 let mut statement = connection
                     .prepare(
-                        "SELECT entity_id, entity_name, entity_type, transaction_id, session_id, created_at, updated_at, is_active, metadata\n             FROM entity_associations\n             WHERE is_active = 1 AND entity_type = ?1\n             ORDER BY datetime(created_at) DESC\n             LIMIT ?2",
+                        &format!("SELECT {ENTITY_COLUMNS}\n             FROM entity_associations\n             WHERE (is_active = 1 OR ?3) AND entity_type = ?1\n             ORDER BY datetime(created_at) DESC\n             LIMIT ?2"),
                     )
                     .map_err(|err| err.to_string())?;
                 let rows = statement
-                    .query_map(params![entity_type, limit_value], Self::map_row)
+                    .query_map(
+                        params![entity_type, limit_value, include_inactive],
+                        Self::map_row,
+                    )
                     .map_err(|err| err.to_string())?;
                 for row in rows {
                     entities.push(row.map_err(|err| err.to_string())?);
@@ -222,11 +734,14 @@ impl EntityService {
             (None, Some(session_id)) => {
                 let mut statement = connection
                     .prepare(
-                        "SELECT entity_id, entity_name, entity_type, transaction_id, session_id, created_at, updated_at, is_active, metadata\n             FROM entity_associations\n             WHERE is_active = 1 AND session_id = ?1\n             ORDER BY datetime(created_at) DESC\n             LIMIT ?2",
+                        &format!("SELECT {ENTITY_COLUMNS}\n             FROM entity_associations\n             WHERE (is_active = 1 OR ?3) AND session_id = ?1\n             ORDER BY datetime(created_at) DESC\n             LIMIT ?2"),
                     )
                     .map_err(|err| err.to_string())?;
                 let rows = statement
-                    .query_map(params![session_id, limit_value], Self::map_row)
+                    .query_map(
+                        params![session_id, limit_value, include_inactive],
+                        Self::map_row,
+                    )
                     .map_err(|err| err.to_string())?;
                 for row in rows {
                     entities.push(row.map_err(|err| err.to_string())?);
@@ -235,11 +750,11 @@ impl EntityService {
             (None, None) => {
                 let mut statement = connection
                     .prepare(
-                        "SELECT entity_id, entity_name, entity_type, transaction_id, session_id, created_at, updated_at, is_active, metadata\n             FROM entity_associations\n             WHERE is_active = 1\n             ORDER BY datetime(created_at) DESC\n             LIMIT ?1",
+                        &format!("SELECT {ENTITY_COLUMNS}\n             FROM entity_associations\n             WHERE (is_active = 1 OR ?2)\n             ORDER BY datetime(created_at) DESC\n             LIMIT ?1"),
                     )
                     .map_err(|err| err.to_string())?;
                 let rows = statement
-                    .query_map(params![limit_value], Self::map_row)
+                    .query_map(params![limit_value, include_inactive], Self::map_row)
                     .map_err(|err| err.to_string())?;
                 for row in rows {
                     entities.push(row.map_err(|err| err.to_string())?);
@@ -250,11 +765,101 @@ impl EntityService {
         Ok(entities)
     }
 
+    /// One page of entities, newest first. Search and date filters run in
+    /// the query so every page is full until the last.
+    pub async fn list_entities_page(&self, query: &EntityListQuery) -> Result<EntityPage, String> {
+        let page_size = query
+            .page_size
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+        let mut values = Vec::new();
+        let mut bind = |value: SqlValue| {
+            values.push(value);
+            format!("?{}", values.len())
+        };
+        let mut conditions = vec![format!(
+            "(is_active = 1 OR {})",
+            bind(query.include_inactive.into())
+        )];
+        if let Some(entity_type) = &query.entity_type {
+            conditions.push(format!(
+                "entity_type = {}",
+                bind(canonical_type_filter(entity_type).into())
+            ));
+        }
+        if let Some(session_id) = &query.session_id {
+            conditions.push(format!("session_id = {}", bind(session_id.clone().into())));
+        }
+        if let Some(search) = query.search.as_deref().filter(|search| !search.is_empty()) {
+            let search = bind(search.to_lowercase().into());
+            conditions.push(format!(
+                "(instr(lower(entity_name), {search}) > 0 OR instr(lower(entity_id), {search}) > 0 OR instr(lower(coalesce(transaction_id, '')), {search}) > 0)"
+            ));
+        }
+        if let Some(start) = query.created_after.as_deref().and_then(sqlite_datetime) {
+            conditions.push(format!("datetime(created_at) >= {}", bind(start.into())));
+        }
+        if let Some(end) = query.created_before.as_deref().and_then(sqlite_datetime) {
+            conditions.push(format!("datetime(created_at) <= {}", bind(end.into())));
+        }
+        // Favorites sort ahead because "1 ..." is greater than "0 ...". A
+        // `created_at` that `datetime` can't parse sorts by its raw text.
+        let sort_key = match query.sort {
+            EntitySortOrder::Newest => "COALESCE(datetime(created_at), created_at, '')",
+            EntitySortOrder::FavoritesFirst => {
+                "(favorite || ' ' || COALESCE(datetime(created_at), created_at, ''))"
+            }
+        };
+        if let Some(cursor) = &query.cursor {
+            let (position, id) = decode_cursor(cursor)?;
+            conditions.push(format!(
+                "({sort_key}, id) < ({}, {})",
+                bind(position.into()),
+                bind(id.into())
+            ));
+        }
+        let limit = bind((page_size as i64 + 1).into());
+
+        let sql = format!(
+            "SELECT id, {sort_key} AS sort_key, {ENTITY_COLUMNS}
+             FROM entity_associations
+             WHERE {}
+             ORDER BY sort_key DESC, id DESC
+             LIMIT {limit}",
+            conditions.join(" AND ")
+        );
+        let connection = self.db.lock().await;
+        let mut statement = connection.prepare(&sql).map_err(|err| err.to_string())?;
+        let mut rows = statement
+            .query_map(params_from_iter(values), |row| {
+                Ok((
+                    row.get::<_, i64>("id")?,
+                    row.get::<_, String>("sort_key")?,
+                    Self::map_row(row)?,
+                ))
+            })
+            .map_err(|err| err.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| err.to_string())?;
+
+        let next_cursor = if rows.len() > page_size {
+            rows.truncate(page_size);
+            rows.last()
+                .map(|(id, sort_key, _)| encode_cursor(sort_key, *id))
+        } else {
+            None
+        };
+        Ok(EntityPage {
+            entities: rows.into_iter().map(|(_, _, entity)| entity).collect(),
+            next_cursor,
+        })
+    }
+
     pub async fn get_entity(&self, entity_id: &str) -> Result<Option<EntityAssociation>, String> {
         let connection = self.db.lock().await;
         let mut statement = connection
             .prepare(
-                "SELECT entity_id, entity_name, entity_type, transaction_id, session_id, created_at, updated_at, is_active, metadata\n                 FROM entity_associations\n                 WHERE entity_id = ?1 AND is_active = 1\n                 ORDER BY datetime(created_at) DESC\n                 LIMIT 1",
+                &format!("SELECT {ENTITY_COLUMNS}\n                 FROM entity_associations\n                 WHERE entity_id = ?1 AND is_active = 1\n                 ORDER BY datetime(created_at) DESC\n                 LIMIT 1"),
             )
             .map_err(|err| err.to_string())?;
 
@@ -271,35 +876,143 @@ impl EntityService {
         let now = Utc::now().to_rfc3339();
         let changes = connection
             .execute(
-                "UPDATE entity_associations SET is_active = 0, updated_at = ?2 WHERE entity_id = ?1",
+                "UPDATE entity_associations SET is_active = 0, updated_at = ?2 WHERE entity_id = ?1 AND is_active = 1",
                 params![entity_id, now],
             )
             .map_err(|err| err.to_string())?;
 
+        if changes > 0 {
+            Self::record_audit(
+                &connection,
+                entity_id,
+                EntityAuditAction::Deleted,
+                None,
+                None,
+            )?;
+        }
         Ok(changes > 0)
     }
 
+    /// Reactivates the most recently deactivated row for `entity_id`. Returns
+    /// `None` when there is nothing to restore, including when the entity was
+    /// stored again since it was deleted.
+    pub async fn restore_entity(
+        &self,
+        entity_id: &str,
+    ) -> Result<Option<EntityAssociation>, String> {
+        {
+            let connection = self.db.lock().await;
+            let changes = connection
+                .execute(
+                    "UPDATE entity_associations SET is_active = 1, updated_at = ?2
+                     WHERE rowid = (
+                         SELECT rowid FROM entity_associations
+                         WHERE entity_id = ?1 AND is_active = 0
+                         ORDER BY datetime(updated_at) DESC
+                         LIMIT 1
+                     )
+                     AND NOT EXISTS (
+                         SELECT 1 FROM entity_associations WHERE entity_id = ?1 AND is_active = 1
+                     )",
+                    params![entity_id, Utc::now().to_rfc3339()],
+                )
+                .map_err(|err| err.to_string())?;
+
+            if changes == 0 {
+                return Ok(None);
+            }
+            Self::record_audit(
+                &connection,
+                entity_id,
+                EntityAuditAction::Restored,
+                None,
+                None,
+            )?;
+        }
+
+        self.get_entity(entity_id).await
+    }
+
+    /// Permanently deletes the deactivated rows for `entity_id`, metadata
+    /// and notes included. Active rows are left alone. Once nothing is left,
+    /// the names, notes and metadata kept in the audit log are cleared too.
+    /// Returns how many rows were removed.
+    pub async fn purge_entity(&self, entity_id: &str) -> Result<usize, String> {
+        let connection = self.db.lock().await;
+        // Overwrite the freed pages so purged data does not linger in the file.
+        connection
+            .pragma_update(None, "secure_delete", true)
+            .map_err(|err| err.to_string())?;
+        let removed = connection
+            .execute(
+                "DELETE FROM entity_associations WHERE entity_id = ?1 AND is_active = 0",
+                params![entity_id],
+            )
+            .map_err(|err| err.to_string())?;
+        if removed > 0 {
+            let remaining: i64 = connection
+                .query_row(
+                    "SELECT COUNT(*) FROM entity_associations WHERE entity_id = ?1",
+                    params![entity_id],
+                    |row| row.get(0),
+                )
+                .map_err(|err| err.to_string())?;
+            if remaining == 0 {
+                connection
+                    .execute(
+                        "UPDATE entity_audit SET previous = NULL, current = NULL WHERE entity_id = ?1",
+                        params![entity_id],
+                    )
+                    .map_err(|err| err.to_string())?;
+            }
+            Self::record_audit(
+                &connection,
+                entity_id,
+                EntityAuditAction::Purged,
+                None,
+                Some(Value::from(removed)),
+            )?;
+        }
+        Ok(removed)
+    }
+
     pub async fn rename_entity(
         &self,
         entity_id: &str,
         new_name: &str,
     ) -> Result<Option<EntityAssociation>, String> {
         let connection = self.db.lock().await;
+        let previous: Option<String> = connection
+            .query_row(
+                "SELECT entity_name FROM entity_associations WHERE entity_id = ?1 AND is_active = 1 ORDER BY datetime(updated_at) DESC LIMIT 1",
+                params![entity_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|err| err.to_string())?;
+        let Some(previous) = previous else {
+            return Ok(None);
+        };
         let now = Utc::now().to_rfc3339();
-        let changes = connection
+        connection
             .execute(
                 "UPDATE entity_associations SET entity_name = ?2, updated_at = ?3 WHERE entity_id = ?1 AND is_active = 1",
                 params![entity_id, new_name, now],
             )
             .map_err(|err| err.to_string())?;
-
-        if changes == 0 {
-            return Ok(None);
+        if previous != new_name {
+            Self::record_audit(
+                &connection,
+                entity_id,
+                EntityAuditAction::Renamed,
+                Some(Value::from(previous)),
+                Some(Value::from(new_name)),
+            )?;
         }
 
         let mut statement = connection
             .prepare(
-                "SELECT entity_id, entity_name, entity_type, transaction_id, session_id, created_at, updated_at, is_active, metadata\n                 FROM entity_associations\n                 WHERE entity_id = ?1 AND is_active = 1\n                 ORDER BY datetime(updated_at) DESC\n                 LIMIT 1",
+                &format!("SELECT {ENTITY_COLUMNS}\n                 FROM entity_associations\n                 WHERE entity_id = ?1 AND is_active = 1\n                 ORDER BY datetime(updated_at) DESC\n                 LIMIT 1"),
             )
             .map_err(|err| err.to_string())?;
 
@@ -311,6 +1024,249 @@ impl EntityService {
         Ok(entity)
     }
 
+    /// Sets one top-level key in the entity's metadata, keeping the rest.
+    pub async fn set_metadata_field(
+        &self,
+        entity_id: &str,
+        key: &str,
+        value: Value,
+    ) -> Result<Option<EntityAssociation>, String> {
+        let connection = self.db.lock().await;
+        let select = format!(
+            "SELECT {ENTITY_COLUMNS}\n                 FROM entity_associations\n                 WHERE entity_id = ?1 AND is_active = 1\n                 ORDER BY datetime(updated_at) DESC\n                 LIMIT 1"
+        );
+        let Some(current) = connection
+            .query_row(&select, params![entity_id], Self::map_row)
+            .optional()
+            .map_err(|err| err.to_string())?
+        else {
+            return Ok(None);
+        };
+
+        let mut metadata = match current.metadata {
+            Some(Value::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        };
+        let previous = metadata.insert(key.to_string(), value.clone());
+        if !UNAUDITED_METADATA_KEYS.contains(&key) && previous.as_ref() != Some(&value) {
+            let field = |value: Option<Value>| {
+                let mut field = serde_json::Map::new();
+                field.insert(key.to_string(), value.unwrap_or(Value::Null));
+                Some(Value::Object(field))
+            };
+            Self::record_audit(
+                &connection,
+                entity_id,
+                EntityAuditAction::MetadataUpdated,
+                field(previous),
+                field(Some(value)),
+            )?;
+        }
+        let serialized = serde_json::to_string(&metadata).map_err(|err| err.to_string())?;
+        let now = Utc::now().to_rfc3339();
+        connection
+            .execute(
+                "UPDATE entity_associations SET metadata = ?2, updated_at = ?3 WHERE entity_id = ?1 AND is_active = 1",
+                params![entity_id, serialized, now],
+            )
+            .map_err(|err| err.to_string())?;
+
+        connection
+            .query_row(&select, params![entity_id], Self::map_row)
+            .optional()
+            .map_err(|err| err.to_string())
+    }
+
+    /// Replaces the entity's notes; blank notes are cleared.
+    pub async fn update_notes(
+        &self,
+        entity_id: &str,
+        notes: &str,
+    ) -> Result<Option<EntityAssociation>, String> {
+        let notes = Some(notes.trim()).filter(|notes| !notes.is_empty());
+        let connection = self.db.lock().await;
+        let previous: Option<Option<String>> = connection
+            .query_row(
+                "SELECT notes FROM entity_associations WHERE entity_id = ?1 AND is_active = 1 ORDER BY datetime(updated_at) DESC LIMIT 1",
+                params![entity_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|err| err.to_string())?;
+        let Some(previous) = previous else {
+            return Ok(None);
+        };
+        let now = Utc::now().to_rfc3339();
+        connection
+            .execute(
+                "UPDATE entity_associations SET notes = ?2, updated_at = ?3 WHERE entity_id = ?1 AND is_active = 1",
+                params![entity_id, notes, now],
+            )
+            .map_err(|err| err.to_string())?;
+        if previous.as_deref() != notes {
+            Self::record_audit(
+                &connection,
+                entity_id,
+                EntityAuditAction::NotesUpdated,
+                previous.map(Value::from),
+                notes.map(Value::from),
+            )?;
+        }
+
+        connection
+            .query_row(
+                &format!("SELECT {ENTITY_COLUMNS}\n                 FROM entity_associations\n                 WHERE entity_id = ?1 AND is_active = 1\n                 ORDER BY datetime(updated_at) DESC\n                 LIMIT 1"),
+                params![entity_id],
+                Self::map_row,
+            )
+            .optional()
+            .map_err(|err| err.to_string())
+    }
+
+    pub async fn set_watched(
+        &self,
+        entity_id: &str,
+        watched: bool,
+    ) -> Result<Option<EntityAssociation>, String> {
+        let connection = self.db.lock().await;
+        let now = Utc::now().to_rfc3339();
+        let changes = connection
+            .execute(
+                "UPDATE entity_associations SET watched = ?2, updated_at = ?3 WHERE entity_id = ?1 AND is_active = 1",
+                params![entity_id, watched, now],
+            )
+            .map_err(|err| err.to_string())?;
+
+        if changes == 0 {
+            return Ok(None);
+        }
+
+        connection
+            .query_row(
+                &format!("SELECT {ENTITY_COLUMNS}\n                 FROM entity_associations\n                 WHERE entity_id = ?1 AND is_active = 1\n                 ORDER BY datetime(updated_at) DESC\n                 LIMIT 1"),
+                params![entity_id],
+                Self::map_row,
+            )
+            .optional()
+            .map_err(|err| err.to_string())
+    }
+
+    pub async fn set_favorite(
+        &self,
+        entity_id: &str,
+        favorite: bool,
+    ) -> Result<Option<EntityAssociation>, String> {
+        let connection = self.db.lock().await;
+        let now = Utc::now().to_rfc3339();
+        let changes = connection
+            .execute(
+                "UPDATE entity_associations SET favorite = ?2, updated_at = ?3 WHERE entity_id = ?1 AND is_active = 1",
+                params![entity_id, favorite, now],
+            )
+            .map_err(|err| err.to_string())?;
+
+        if changes == 0 {
+            return Ok(None);
+        }
+
+        connection
+            .query_row(
+                &format!("SELECT {ENTITY_COLUMNS}\n                 FROM entity_associations\n                 WHERE entity_id = ?1 AND is_active = 1\n                 ORDER BY datetime(updated_at) DESC\n                 LIMIT 1"),
+                params![entity_id],
+                Self::map_row,
+            )
+            .optional()
+            .map_err(|err| err.to_string())
+    }
+
+    /// Grouped counts for a dashboard, with a creation timeline covering the
+    /// last `timeline_days` days (30 by default, at most 365).
+    pub async fn get_stats(&self, timeline_days: Option<u32>) -> Result<EntityStats, String> {
+        let days = timeline_days
+            .unwrap_or(DEFAULT_TIMELINE_DAYS)
+            .clamp(1, MAX_TIMELINE_DAYS);
+        let connection = self.db.lock().await;
+        let counts = |group: &str| -> Result<Vec<EntityCount>, String> {
+            let mut statement = connection
+                .prepare(&format!(
+                    "SELECT {group} AS key, COUNT(*) AS count
+                     FROM entity_associations
+                     WHERE is_active = 1
+                     GROUP BY key
+                     ORDER BY count DESC, key"
+                ))
+                .map_err(|err| err.to_string())?;
+            statement
+                .query_map([], |row| {
+                    Ok(EntityCount {
+                        key: row.get("key")?,
+                        count: row.get("count")?,
+                    })
+                })
+                .map_err(|err| err.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| err.to_string())
+        };
+        let by_type = counts("entity_type")?;
+        let by_session = counts("session_id")?;
+        let by_network =
+            counts("CASE WHEN json_valid(metadata) THEN json_extract(metadata, '$.network') END")?;
+
+        let (total, favorites, watched) = connection
+            .query_row(
+                "SELECT COUNT(*), COALESCE(SUM(favorite), 0), COALESCE(SUM(watched), 0)
+                 FROM entity_associations
+                 WHERE is_active = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .map_err(|err| err.to_string())?;
+
+        let mut statement = connection
+            .prepare(
+                "SELECT date(created_at) AS day, COUNT(*) AS count
+                 FROM entity_associations
+                 WHERE is_active = 1 AND date(created_at) > date('now', ?1)
+                 GROUP BY day
+                 ORDER BY day",
+            )
+            .map_err(|err| err.to_string())?;
+        let timeline = statement
+            .query_map(params![format!("-{days} days")], |row| {
+                Ok(EntityTimelinePoint {
+                    date: row.get("day")?,
+                    count: row.get("count")?,
+                })
+            })
+            .map_err(|err| err.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| err.to_string())?;
+
+        Ok(EntityStats {
+            total,
+            favorites,
+            watched,
+            by_type,
+            by_session,
+            by_network,
+            timeline,
+        })
+    }
+
+    pub async fn list_watched(&self) -> Result<Vec<EntityAssociation>, String> {
+        let connection = self.db.lock().await;
+        let mut statement = connection
+            .prepare(
+                &format!("SELECT {ENTITY_COLUMNS}\n                 FROM entity_associations\n                 WHERE is_active = 1 AND watched = 1\n                 ORDER BY datetime(created_at) DESC"),
+            )
+            .map_err(|err| err.to_string())?;
+        statement
+            .query_map([], Self::map_row)
+            .map_err(|err| err.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| err.to_string())
+    }
+
     pub async fn search_entities(
         &self,
         query: &str,
@@ -318,13 +1274,14 @@ impl EntityService {
         limit: usize,
     ) -> Result<Vec<EntityAssociation>, String> {
         let search_term = format!("%{}%", query.to_lowercase());
+        let entity_type = entity_type.map(canonical_type_filter);
         let connection = self.db.lock().await;
         let mut entities = Vec::new();
 
         if let Some(entity_type) = entity_type {
             let mut statement = connection
                 .prepare(
-                    "SELECT entity_id, entity_name, entity_type, transaction_id, session_id, created_at, updated_at, is_active, metadata\n             FROM entity_associations\n             WHERE is_active = 1\n             AND (LOWER(entity_name) LIKE ?1 OR LOWER(entity_id) LIKE ?1 OR LOWER(COALESCE(transaction_id, '')) LIKE ?1)\n             AND entity_type = ?2\n             ORDER BY datetime(created_at) DESC\n             LIMIT ?3",
+                    &format!("SELECT {ENTITY_COLUMNS}\n             FROM entity_associations\n             WHERE is_active = 1\n             AND (LOWER(entity_name) LIKE ?1 OR LOWER(entity_id) LIKE ?1 OR LOWER(COALESCE(transaction_id, '')) LIKE ?1)\n             AND entity_type = ?2\n             ORDER BY datetime(created_at) DESC\n             LIMIT ?3"),
                 )
                 .map_err(|err| err.to_string())?;
             let rows = statement
@@ -339,7 +1296,7 @@ impl EntityService {
         } else {
             let mut statement = connection
                 .prepare(
-                    "SELECT entity_id, entity_name, entity_type, transaction_id, session_id, created_at, updated_at, is_active, metadata\n             FROM entity_associations\n             WHERE is_active = 1\n             AND (LOWER(entity_name) LIKE ?1 OR LOWER(entity_id) LIKE ?1 OR LOWER(COALESCE(transaction_id, '')) LIKE ?1)\n             ORDER BY datetime(created_at) DESC\n             LIMIT ?2",
+                    &format!("SELECT {ENTITY_COLUMNS}\n             FROM entity_associations\n             WHERE is_active = 1\n             AND (LOWER(entity_name) LIKE ?1 OR LOWER(entity_id) LIKE ?1 OR LOWER(COALESCE(transaction_id, '')) LIKE ?1)\n             ORDER BY datetime(created_at) DESC\n             LIMIT ?2"),
                 )
                 .map_err(|err| err.to_string())?;
             let rows = statement
@@ -356,7 +1313,11 @@ impl EntityService {
 
 #[cfg(test)]
 mod tests {
-    use super::EntityService;
+    use super::{
+        ENTITY_MIGRATION_SCOPE, ENTITY_MIGRATIONS, EntityAuditAction, EntityListQuery,
+        EntityService, EntitySortOrder, EntityType, migrations,
+    };
+    use chrono::Utc;
     use serde_json::json;
 
     #[tokio::test]
@@ -406,6 +1367,101 @@ mod tests {
         assert!(remaining.is_none());
     }
 
+    #[tokio::test]
+    async fn restore_reactivates_deleted_entity() {
+        let service = EntityService::new_in_memory();
+        {
+            let connection = service.db.lock().await;
+            connection
+                .execute(
+                    "INSERT INTO entity_associations (entity_id, entity_name, entity_type, created_at, updated_at, is_active) VALUES (?1, ?2, ?3, datetime('now'), datetime('now'), 1)",
+                    rusqlite::params!["entity-3", "Entity", "topic"],
+                )
+                .expect("insert entity");
+        }
+
+        assert!(service.restore_entity("entity-3").await.unwrap().is_none());
+        service.deactivate_entity("entity-3").await.unwrap();
+
+        let listed = service
+            .list_entities(None, None, None, true)
+            .await
+            .expect("list entities");
+        assert_eq!(listed.len(), 1);
+        assert!(!listed[0].is_active);
+        assert!(
+            service
+                .list_entities(None, None, None, false)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let restored = service
+            .restore_entity("entity-3")
+            .await
+            .expect("restore entity")
+            .expect("restored entity");
+        assert!(restored.is_active);
+        assert_eq!(restored.entity_name, "Entity");
+        assert!(service.restore_entity("entity-3").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn purge_removes_only_deactivated_rows() {
+        let service = EntityService::new_in_memory();
+        {
+            let connection = service.db.lock().await;
+            for (entity_id, is_active) in [("entity-4", 0), ("entity-4", 1), ("entity-5", 1)] {
+                connection
+                    .execute(
+                        "INSERT INTO entity_associations (entity_id, entity_name, entity_type, created_at, updated_at, is_active, metadata) VALUES (?1, 'Entity', 'token', datetime('now'), datetime('now'), ?2, '{}')",
+                        rusqlite::params![entity_id, is_active],
+                    )
+                    .expect("insert entity");
+            }
+        }
+
+        assert_eq!(service.purge_entity("entity-5").await.unwrap(), 0);
+        assert_eq!(service.purge_entity("entity-4").await.unwrap(), 1);
+        assert!(service.get_entity("entity-4").await.unwrap().is_some());
+
+        service.deactivate_entity("entity-4").await.unwrap();
+        assert_eq!(service.purge_entity("entity-4").await.unwrap(), 1);
+        assert!(
+            service
+                .list_entities(None, None, None, true)
+                .await
+                .unwrap()
+                .iter()
+                .all(|entity| entity.entity_id == "entity-5")
+        );
+    }
+
+    #[tokio::test]
+    async fn watched_entities_are_listed_until_unwatched() {
+        let service = EntityService::new_in_memory();
+        for entity_id in ["0.0.10", "0.0.11"] {
+            service
+                .store_entity(entity_id, "Account", "accountId", None, None, None)
+                .await
+                .unwrap();
+        }
+
+        let watched = service.set_watched("0.0.10", true).await.unwrap().unwrap();
+        assert!(watched.watched);
+        assert!(service.set_watched("0.0.99", true).await.unwrap().is_none());
+        let listed = service.list_watched().await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].entity_id, "0.0.10");
+
+        service.deactivate_entity("0.0.10").await.unwrap();
+        assert!(service.list_watched().await.unwrap().is_empty());
+        service.restore_entity("0.0.10").await.unwrap();
+        service.set_watched("0.0.10", false).await.unwrap();
+        assert!(service.list_watched().await.unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn store_entity_inserts_new_entity() {
         let service = EntityService::new_in_memory();
@@ -425,7 +1481,7 @@ mod tests {
         assert!(result.created);
         assert_eq!(result.entity.entity_id, "0.0.1001");
         assert_eq!(result.entity.entity_name, "DemoToken");
-        assert_eq!(result.entity.entity_type, "tokenId");
+        assert_eq!(result.entity.entity_type, EntityType::TokenId);
         assert_eq!(
             result.entity.transaction_id.as_deref(),
             Some("0.0.2001@123456")
@@ -434,7 +1490,7 @@ mod tests {
         assert!(result.entity.metadata.is_none());
 
         let all = service
-            .list_entities(None, None, None)
+            .list_entities(None, None, None, false)
             .await
             .expect("list entities");
         assert_eq!(all.len(), 1);
@@ -472,12 +1528,454 @@ mod tests {
         assert!(!second.created);
         assert_eq!(second.entity.entity_name, "Initial");
         let all = service
-            .list_entities(None, None, None)
+            .list_entities(None, None, None, false)
             .await
             .expect("list entities");
         assert_eq!(all.len(), 1);
     }
 
+    #[tokio::test]
+    async fn notes_are_updated_and_cleared() {
+        let service = EntityService::new_in_memory();
+        service
+            .store_entity("0.0.42", "Treasury", "account", None, None, None)
+            .await
+            .expect("store entity");
+
+        let updated = service
+            .update_notes("0.0.42", "  Client treasury, rotate keys quarterly ")
+            .await
+            .expect("update notes")
+            .expect("entity exists");
+        assert_eq!(
+            updated.notes.as_deref(),
+            Some("Client treasury, rotate keys quarterly")
+        );
+        let fetched = service.get_entity("0.0.42").await.unwrap().unwrap();
+        assert_eq!(fetched.notes, updated.notes);
+
+        let cleared = service.update_notes("0.0.42", "  ").await.unwrap().unwrap();
+        assert!(cleared.notes.is_none());
+        assert!(
+            service
+                .update_notes("0.0.404", "missing")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn entity_pages_follow_the_cursor() {
+        let service = EntityService::new_in_memory();
+        {
+            let connection = service.db.lock().await;
+            for (entity_id, name, created_at) in [
+                ("0.0.1", "Alpha", "2024-01-01T00:00:00+00:00"),
+                ("0.0.2", "Beta", "2024-01-02T00:00:00+00:00"),
+                ("0.0.3", "Gamma", "2024-01-02T00:00:00+00:00"),
+                ("0.0.4", "Delta token", "2024-01-03T00:00:00+00:00"),
+                ("0.0.5", "Epsilon token", "2024-01-04T00:00:00+00:00"),
+            ] {
+                connection
+                    .execute(
+                        "INSERT INTO entity_associations (entity_id, entity_name, entity_type, created_at, updated_at, is_active) VALUES (?1, ?2, 'tokenId', ?3, ?3, 1)",
+                        rusqlite::params![entity_id, name, created_at],
+                    )
+                    .expect("insert entity");
+            }
+        }
+
+        let mut query = EntityListQuery {
+            page_size: Some(2),
+            ..Default::default()
+        };
+        let mut seen = Vec::new();
+        loop {
+            let page = service.list_entities_page(&query).await.unwrap();
+            assert!(page.entities.len() <= 2);
+            seen.extend(page.entities.into_iter().map(|entity| entity.entity_id));
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => break,
+            }
+        }
+        assert_eq!(seen, vec!["0.0.5", "0.0.4", "0.0.3", "0.0.2", "0.0.1"]);
+
+        for entity_id in ["0.0.2", "0.0.4"] {
+            let starred = service
+                .set_favorite(entity_id, true)
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(starred.favorite);
+        }
+        assert!(
+            service
+                .set_favorite("0.0.99", true)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        let mut query = EntityListQuery {
+            page_size: Some(2),
+            sort: EntitySortOrder::FavoritesFirst,
+            ..Default::default()
+        };
+        let mut seen = Vec::new();
+        loop {
+            let page = service.list_entities_page(&query).await.unwrap();
+            seen.extend(page.entities.into_iter().map(|entity| entity.entity_id));
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => break,
+            }
+        }
+        assert_eq!(seen, vec!["0.0.4", "0.0.2", "0.0.5", "0.0.3", "0.0.1"]);
+
+        let tokens = service
+            .list_entities_page(&EntityListQuery {
+                search: Some("TOKEN".to_string()),
+                created_before: Some("2024-01-03T12:00:00Z".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(tokens.entities.len(), 1);
+        assert_eq!(tokens.entities[0].entity_id, "0.0.4");
+        assert!(tokens.next_cursor.is_none());
+
+        assert!(
+            service
+                .list_entities_page(&EntityListQuery {
+                    cursor: Some("not a cursor".to_string()),
+                    ..Default::default()
+                })
+                .await
+                .is_err()
+        );
+
+        {
+            let connection = service.db.lock().await;
+            connection
+                .execute(
+                    "INSERT INTO entity_associations (entity_id, entity_name, entity_type, created_at, updated_at, is_active) VALUES ('0.0.6', 'Undated', 'tokenId', 'not a date', 'not a date', 1)",
+                    [],
+                )
+                .expect("insert entity");
+        }
+        let first = service
+            .list_entities_page(&EntityListQuery {
+                page_size: Some(1),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(first.entities[0].entity_id, "0.0.6");
+        let second = service
+            .list_entities_page(&EntityListQuery {
+                page_size: Some(1),
+                cursor: first.next_cursor,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(second.entities[0].entity_id, "0.0.5");
+    }
+
+    #[tokio::test]
+    async fn stats_group_active_entities() {
+        let service = EntityService::new_in_memory();
+        let today = Utc::now().to_rfc3339();
+        let earlier = (Utc::now() - chrono::Duration::days(3)).to_rfc3339();
+        let long_ago = (Utc::now() - chrono::Duration::days(90)).to_rfc3339();
+        {
+            let connection = service.db.lock().await;
+            for (entity_id, entity_type, session_id, metadata, created_at, is_active) in [
+                (
+                    "0.0.1",
+                    "tokenId",
+                    Some("s1"),
+                    r#"{"network":"testnet"}"#,
+                    &today,
+                    1,
+                ),
+                (
+                    "0.0.2",
+                    "tokenId",
+                    Some("s1"),
+                    r#"{"network":"mainnet"}"#,
+                    &today,
+                    1,
+                ),
+                (
+                    "0.0.3",
+                    "accountId",
+                    None,
+                    r#"{"network":"testnet"}"#,
+                    &earlier,
+                    1,
+                ),
+                ("0.0.4", "topicId", Some("s2"), "not json", &long_ago, 1),
+                ("0.0.5", "topicId", Some("s2"), "{}", &today, 0),
+            ] {
+                connection
+                    .execute(
+                        "INSERT INTO entity_associations (entity_id, entity_name, entity_type, session_id, metadata, created_at, updated_at, is_active) VALUES (?1, 'Entity', ?2, ?3, ?4, ?5, ?5, ?6)",
+                        rusqlite::params![entity_id, entity_type, session_id, metadata, created_at, is_active],
+                    )
+                    .expect("insert entity");
+            }
+        }
+        service.set_favorite("0.0.1", true).await.unwrap();
+
+        let stats = service.get_stats(None).await.unwrap();
+        assert_eq!(stats.total, 4);
+        assert_eq!(stats.favorites, 1);
+        let count = |counts: &[super::EntityCount], key: Option<&str>| {
+            counts
+                .iter()
+                .find(|count| count.key.as_deref() == key)
+                .map(|count| count.count)
+        };
+        assert_eq!(stats.by_type[0].key.as_deref(), Some("tokenId"));
+        assert_eq!(count(&stats.by_type, Some("topicId")), Some(1));
+        assert_eq!(count(&stats.by_session, None), Some(1));
+        assert_eq!(count(&stats.by_network, Some("testnet")), Some(2));
+        assert_eq!(count(&stats.by_network, None), Some(1));
+        let timeline: Vec<u64> = stats.timeline.iter().map(|point| point.count).collect();
+        assert_eq!(timeline, vec![1, 2]);
+        assert_eq!(
+            service.get_stats(Some(365)).await.unwrap().timeline.len(),
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn bookkeeping_changes_are_audited() {
+        let service = EntityService::new_in_memory();
+        service
+            .store_entity("0.0.9", "Treasury", "accountId", None, None, None)
+            .await
+            .expect("store entity");
+
+        service.rename_entity("0.0.9", "Ops").await.unwrap();
+        service.rename_entity("0.0.9", "Ops").await.unwrap();
+        service.update_notes("0.0.9", "pays fees").await.unwrap();
+        service
+            .set_metadata_field("0.0.9", "liveState", json!({ "balance": 1 }))
+            .await
+            .unwrap();
+        service
+            .set_metadata_field("0.0.9", "label", json!("hot"))
+            .await
+            .unwrap();
+        service.deactivate_entity("0.0.9").await.unwrap();
+        assert!(!service.deactivate_entity("0.0.9").await.unwrap());
+        service.restore_entity("0.0.9").await.unwrap();
+        service.deactivate_entity("0.0.9").await.unwrap();
+
+        let log = service.get_audit_log(Some("0.0.9"), None).await.unwrap();
+        let actions: Vec<EntityAuditAction> = log.iter().map(|entry| entry.action).collect();
+        assert_eq!(
+            actions,
+            vec![
+                EntityAuditAction::Deleted,
+                EntityAuditAction::Restored,
+                EntityAuditAction::Deleted,
+                EntityAuditAction::MetadataUpdated,
+                EntityAuditAction::NotesUpdated,
+                EntityAuditAction::Renamed,
+            ]
+        );
+        assert_eq!(log[5].previous, Some(json!("Treasury")));
+        assert_eq!(log[5].current, Some(json!("Ops")));
+        assert_eq!(log[3].previous, Some(json!({ "label": null })));
+        assert_eq!(log[3].current, Some(json!({ "label": "hot" })));
+
+        service.purge_entity("0.0.9").await.unwrap();
+        let log = service.get_audit_log(Some("0.0.9"), None).await.unwrap();
+        assert_eq!(log.len(), 7);
+        assert_eq!(log[0].action, EntityAuditAction::Purged);
+        assert!(
+            log[1..]
+                .iter()
+                .all(|entry| entry.previous.is_none() && entry.current.is_none())
+        );
+        assert_eq!(service.get_audit_log(None, Some(2)).await.unwrap().len(), 2);
+        assert!(
+            service
+                .get_audit_log(Some("0.0.10"), None)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn metadata_fields_are_merged() {
+        let service = EntityService::new_in_memory();
+        service
+            .store_entity(
+                "0.0.7",
+                "Points",
+                "tokenId",
+                None,
+                None,
+                Some(&json!({ "network": "testnet" })),
+            )
+            .await
+            .expect("store entity");
+
+        let updated = service
+            .set_metadata_field("0.0.7", "liveState", json!({ "totalSupply": "100" }))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            updated.metadata,
+            Some(json!({ "network": "testnet", "liveState": { "totalSupply": "100" } }))
+        );
+        assert!(
+            service
+                .set_metadata_field("0.0.8", "liveState", json!({}))
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn entity_types_accept_legacy_spellings() {
+        assert_eq!(EntityType::parse("tokenId"), Some(EntityType::TokenId));
+        assert_eq!(EntityType::parse("TOKEN_ID"), Some(EntityType::TokenId));
+        assert_eq!(EntityType::parse("topic"), Some(EntityType::TopicId));
+        assert_eq!(EntityType::parse("nft"), Some(EntityType::NftSerial));
+        assert_eq!(EntityType::parse("unknown"), None);
+        assert_eq!(
+            EntityType::infer_from_id("0.0.42/7"),
+            Some(EntityType::NftSerial)
+        );
+        assert_eq!(EntityType::infer_from_id("0.0.42"), None);
+        for entity_type in EntityType::ALL {
+            assert_eq!(EntityType::parse(entity_type.as_str()), Some(entity_type));
+            assert_eq!(
+                serde_json::to_value(entity_type).unwrap(),
+                json!(entity_type.as_str())
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn store_entity_keeps_unknown_types_as_other() {
+        let service = EntityService::new_in_memory();
+        let stored = service
+            .store_entity("0.0.1", "Mystery", "unknown", None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(stored.entity.entity_type, EntityType::Other);
+        let stored = service
+            .store_entity("0.0.2", "Topic", "topic", None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(stored.entity.entity_type, EntityType::TopicId);
+        let filtered = service
+            .list_entities(Some("TOPIC".to_string()), None, None, false)
+            .await
+            .unwrap();
+        assert_eq!(filtered.len(), 1);
+    }
+
+    #[test]
+    fn migration_normalizes_stored_entity_types() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        migrations::apply(&connection, ENTITY_MIGRATION_SCOPE, &ENTITY_MIGRATIONS[..4]).unwrap();
+        for (entity_id, entity_type) in [
+            ("0.0.1", "token"),
+            ("0.0.2", "ACCOUNT"),
+            ("0.0.3/4", "unknown"),
+            ("0.0.5", "unknown"),
+            ("0.0.6", "fileId"),
+        ] {
+            connection
+                .execute(
+                    "INSERT INTO entity_associations (entity_id, entity_name, entity_type, created_at, updated_at, is_active) VALUES (?1, 'Entity', ?2, datetime('now'), datetime('now'), 1)",
+                    rusqlite::params![entity_id, entity_type],
+                )
+                .unwrap();
+        }
+
+        migrations::apply(&connection, ENTITY_MIGRATION_SCOPE, ENTITY_MIGRATIONS).unwrap();
+        let types: Vec<(String, String)> = connection
+            .prepare("SELECT entity_id, entity_type FROM entity_associations ORDER BY entity_id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            types,
+            vec![
+                ("0.0.1".to_string(), "tokenId".to_string()),
+                ("0.0.2".to_string(), "accountId".to_string()),
+                ("0.0.3/4".to_string(), "nftSerial".to_string()),
+                ("0.0.5".to_string(), "other".to_string()),
+                ("0.0.6".to_string(), "fileId".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn store_entity_validates_metadata_against_the_type_schema() {
+        let mut service = EntityService::new_in_memory();
+        let error = service
+            .store_entity(
+                "0.0.7",
+                "Topic",
+                "topicId",
+                None,
+                None,
+                Some(&json!({ "source": "agentToolCall", "network": 3 })),
+            )
+            .await
+            .unwrap_err();
+        assert!(error.contains("/network"), "{error}");
+        assert!(service.get_entity("0.0.7").await.unwrap().is_none());
+
+        service.set_metadata_validation(false);
+        assert!(
+            service
+                .store_entity(
+                    "0.0.8",
+                    "Topic",
+                    "topicId",
+                    None,
+                    None,
+                    Some(&json!({ "network": 3 })),
+                )
+                .await
+                .unwrap()
+                .created
+        );
+        service.set_metadata_validation(true);
+
+        service.metadata_schemas.remove(&EntityType::TopicId);
+        assert!(
+            service
+                .store_entity(
+                    "0.0.7",
+                    "Topic",
+                    "topicId",
+                    None,
+                    None,
+                    Some(&json!({ "network": 3 })),
+                )
+                .await
+                .unwrap()
+                .created
+        );
+    }
+
     #[tokio::test]
     async fn store_entity_persists_metadata() {
         let service = EntityService::new_in_memory();
@@ -0,0 +1,237 @@
+use serde::Serialize;
+use std::fmt;
+
+const CHECKSUM_LENGTH: usize = 5;
+
+/// Why a pasted Hedera entity id was rejected.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum EntityIdError {
+    Empty,
+    Malformed {
+        input: String,
+    },
+    UnsupportedNetwork {
+        network: String,
+    },
+    ChecksumMismatch {
+        entity_id: String,
+        provided: String,
+        expected: String,
+    },
+}
+
+impl fmt::Display for EntityIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "Entity ID is required"),
+            Self::Malformed { input } => {
+                write!(f, "{input} must match format shard.realm.num")
+            }
+            Self::UnsupportedNetwork { network } => {
+                write!(f, "Unsupported Hedera network: {network}")
+            }
+            Self::ChecksumMismatch {
+                entity_id,
+                provided,
+                expected,
+            } => write!(
+                f,
+                "Checksum {provided} does not match {entity_id} on this network (expected {entity_id}-{expected})"
+            ),
+        }
+    }
+}
+
+impl From<EntityIdError> for String {
+    fn from(error: EntityIdError) -> Self {
+        error.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntityId {
+    pub shard: u64,
+    pub realm: u64,
+    pub num: u64,
+}
+
+impl fmt::Display for EntityId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.shard, self.realm, self.num)
+    }
+}
+
+impl EntityId {
+    /// The HIP-15 checksum of this id on `network`.
+    pub fn checksum(&self, network: &str) -> Result<String, EntityIdError> {
+        Ok(compute_checksum(&self.to_string(), ledger_id(network)?))
+    }
+
+    pub fn with_checksum(&self, network: &str) -> Result<String, EntityIdError> {
+        Ok(format!("{}-{}", self, self.checksum(network)?))
+    }
+}
+
+/// Parses `shard.realm.num` with an optional `-abcde` checksum suffix. A
+/// checksum, when present, must match `network`.
+pub fn parse_entity_id(input: &str, network: &str) -> Result<EntityId, EntityIdError> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        return Err(EntityIdError::Empty);
+    }
+
+    let (address, provided) = match trimmed.split_once('-') {
+        Some((address, checksum)) => (address, Some(checksum)),
+        None => (trimmed, None),
+    };
+    let id = parse_address(address).ok_or_else(|| EntityIdError::Malformed {
+        input: trimmed.to_string(),
+    })?;
+
+    if let Some(provided) = provided {
+        if provided.len() != CHECKSUM_LENGTH || !provided.chars().all(|c| c.is_ascii_lowercase()) {
+            return Err(EntityIdError::Malformed {
+                input: trimmed.to_string(),
+            });
+        }
+        let expected = id.checksum(network)?;
+        if provided != expected {
+            return Err(EntityIdError::ChecksumMismatch {
+                entity_id: id.to_string(),
+                provided: provided.to_string(),
+                expected,
+            });
+        }
+    }
+
+    Ok(id)
+}
+
+/// Finds checksummed ids in free text whose checksum does not match
+/// `network`. Ids without a checksum are left alone.
+pub fn find_checksum_mismatches(text: &str, network: &str) -> Vec<EntityIdError> {
+    text.split(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | '(' | ')' | '"' | '\''))
+        .map(|token| token.trim_end_matches(['.', ':', '!', '?']))
+        .filter(|token| {
+            token
+                .split_once('-')
+                .is_some_and(|(address, _)| parse_address(address).is_some())
+        })
+        .filter_map(|token| match parse_entity_id(token, network) {
+            Err(error @ EntityIdError::ChecksumMismatch { .. }) => Some(error),
+            _ => None,
+        })
+        .collect()
+}
+
+fn parse_address(address: &str) -> Option<EntityId> {
+    let mut parts = address.split('.');
+    let (Some(shard), Some(realm), Some(num), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return None;
+    };
+    let parse = |part: &str| {
+        if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+            None
+        } else {
+            part.parse::<u64>().ok()
+        }
+    };
+    Some(EntityId {
+        shard: parse(shard)?,
+        realm: parse(realm)?,
+        num: parse(num)?,
+    })
+}
+
+fn ledger_id(network: &str) -> Result<u8, EntityIdError> {
+    match network.trim().to_ascii_lowercase().as_str() {
+        "mainnet" => Ok(0),
+        "testnet" => Ok(1),
+        "previewnet" => Ok(2),
+        other => Err(EntityIdError::UnsupportedNetwork {
+            network: other.to_string(),
+        }),
+    }
+}
+
+/// HIP-15 checksum: a weighted digit sum of the address mixed with the ledger
+/// id, rendered as five base-26 letters.
+fn compute_checksum(address: &str, ledger_id: u8) -> String {
+    const P3: u64 = 26 * 26 * 26;
+    const P5: u64 = 26 * 26 * 26 * 26 * 26;
+    const M: u64 = 1_000_003;
+    const W: u64 = 31;
+
+    let digits: Vec<u64> = address
+        .chars()
+        .map(|c| c.to_digit(10).map(u64::from).unwrap_or(10))
+        .collect();
+
+    let (mut s, mut s0, mut s1) = (0u64, 0u64, 0u64);
+    for (index, digit) in digits.iter().enumerate() {
+        s = (W * s + digit) % P3;
+        if index % 2 == 0 {
+            s0 = (s0 + digit) % 11;
+        } else {
+            s1 = (s1 + digit) % 11;
+        }
+    }
+
+    let mut sh = 0u64;
+    for byte in std::iter::once(ledger_id).chain([0u8; 6]) {
+        sh = (W * sh + u64::from(byte)) % P5;
+    }
+
+    let mut c = ((((digits.len() as u64 % 5) * 11 + s0) * 11 + s1) * P3 + s + sh) % P5;
+    c = (c * M) % P5;
+
+    let mut letters = [b'a'; CHECKSUM_LENGTH];
+    for slot in letters.iter_mut().rev() {
+        *slot = b'a' + (c % 26) as u8;
+        c /= 26;
+    }
+    String::from_utf8_lossy(&letters).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksum_matches_hip15_vectors() {
+        let id = parse_entity_id("0.0.123", "mainnet").unwrap();
+        assert_eq!(id.checksum("mainnet").unwrap(), "vfmkw");
+        assert_eq!(id.with_checksum("testnet").unwrap(), "0.0.123-esxsf");
+        assert_eq!(parse_entity_id("0.0.1-dfkxr", "mainnet").unwrap().num, 1);
+    }
+
+    #[test]
+    fn mismatched_checksum_reports_expected_value() {
+        assert_eq!(
+            parse_entity_id("0.0.123-vfmkw", "testnet"),
+            Err(EntityIdError::ChecksumMismatch {
+                entity_id: "0.0.123".into(),
+                provided: "vfmkw".into(),
+                expected: "esxsf".into(),
+            })
+        );
+        assert!(matches!(
+            parse_entity_id("0.0.abc", "testnet"),
+            Err(EntityIdError::Malformed { .. })
+        ));
+        assert_eq!(parse_entity_id("  ", "testnet"), Err(EntityIdError::Empty));
+
+        let mismatches = find_checksum_mismatches(
+            "send 5 hbar to 0.0.123-vfmkw, not 0.0.123-esxsf.",
+            "testnet",
+        );
+        assert_eq!(mismatches.len(), 1);
+    }
+}
@@ -0,0 +1,69 @@
+use serde_json::{Value, json};
+use std::collections::HashMap;
+
+use crate::entity::EntityType;
+
+/// The metadata every stored entity may carry, as written by the agent,
+/// transaction execution and wallet hydration paths.
+fn base_metadata_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "source": { "type": "string", "minLength": 1 },
+            "recordedAt": { "type": "string" },
+            "transactionId": { "type": ["string", "null"] },
+            "messageId": { "type": ["string", "null"] },
+            "sessionId": { "type": ["string", "null"] },
+            "network": { "type": ["string", "null"], "minLength": 1 },
+            "entityContext": { "type": ["object", "null"] },
+            "liveState": { "type": "object" }
+        }
+    })
+}
+
+/// Schemas for the metadata of each entity type. Types without a schema
+/// are stored unchecked.
+pub fn default_metadata_schemas() -> HashMap<EntityType, Value> {
+    let mut schemas: HashMap<EntityType, Value> = EntityType::ALL
+        .into_iter()
+        .map(|entity_type| (entity_type, base_metadata_schema()))
+        .collect();
+    if let Some(Value::Object(properties)) = schemas
+        .get_mut(&EntityType::NftSerial)
+        .and_then(|schema| schema.get_mut("properties"))
+    {
+        properties.insert(
+            "serialNumber".to_string(),
+            json!({ "type": ["integer", "string"], "minimum": 1 }),
+        );
+    }
+    schemas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_schema::validate;
+
+    #[test]
+    fn default_schemas_cover_every_type() {
+        let schemas = default_metadata_schemas();
+        assert_eq!(schemas.len(), EntityType::ALL.len());
+        let metadata = json!({
+            "source": "agentToolCall",
+            "recordedAt": "2024-01-01T00:00:00Z",
+            "transactionId": null,
+            "network": "testnet",
+            "entityContext": { "name": "Points" }
+        });
+        assert!(validate(&schemas[&EntityType::TokenId], &metadata).is_empty());
+        assert_eq!(
+            validate(
+                &schemas[&EntityType::NftSerial],
+                &json!({ "serialNumber": 0, "network": 3 })
+            )
+            .len(),
+            2
+        );
+    }
+}
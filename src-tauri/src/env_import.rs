@@ -0,0 +1,386 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use zeroize::Zeroizing;
+
+use crate::config::{AppConfig, LlmProvider, Network, SwarmConfig};
+use crate::credentials::CredentialScope;
+use crate::secret::Secret;
+
+/// Service under which unmapped secrets from a `.env` file are stored, so
+/// MCP server settings can reference them as `${credential:env/<KEY>}`.
+pub const ENV_CREDENTIAL_SERVICE: &str = "env";
+
+const SECRET_SUFFIXES: &[&str] = &["_KEY", "_TOKEN", "_SECRET", "_PASSWORD", "_PK"];
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvImportTarget {
+    Config,
+    Credential,
+}
+
+/// One variable from the file and where it would go.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvImportEntry {
+    pub key: String,
+    pub target: EnvImportTarget,
+    /// A config path such as `hedera.accountId`, or `<service>/<account>`
+    /// for credentials.
+    pub field: String,
+    /// The imported value, masked for secrets.
+    pub value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<String>,
+    pub sensitive: bool,
+    pub changed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<CredentialScope>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvImportPlan {
+    pub entries: Vec<EnvImportEntry>,
+    /// Keys that were read but are neither config nor secrets.
+    pub ignored: Vec<String>,
+    /// Values that could not be used, such as an unknown network.
+    pub warnings: Vec<String>,
+    /// Whether the plan was written, rather than only previewed.
+    pub applied: bool,
+    #[serde(skip)]
+    pub config: AppConfig,
+    #[serde(skip)]
+    pub credentials: Vec<(String, String, CredentialScope)>,
+}
+
+pub fn read_env_file(path: &Path) -> Result<Vec<(String, String)>, String> {
+    let file =
+        File::open(path).map_err(|error| format!("Failed to open {}: {error}", path.display()))?;
+    parse_env(file)
+}
+
+pub fn parse_env<R: Read>(reader: R) -> Result<Vec<(String, String)>, String> {
+    dotenvy::from_read_iter(reader)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| format!("Failed to parse .env file: {error}"))
+}
+
+/// Keeps the last four characters of values long enough to spare them.
+fn mask(value: &str) -> String {
+    let length = value.chars().count();
+    if length <= 8 {
+        return "********".to_string();
+    }
+    let visible: String = value.chars().skip(length - 4).collect();
+    format!("********{visible}")
+}
+
+/// Puts `value` in a secret config field and returns what it held.
+fn replace_secret(field: &mut Secret, value: &str) -> Zeroizing<String> {
+    Zeroizing::new(std::mem::replace(field, value.into()).expose().to_string())
+}
+
+fn is_secret(key: &str) -> bool {
+    SECRET_SUFFIXES.iter().any(|suffix| key.ends_with(suffix))
+}
+
+/// Maps the variables `get_environment_config` understands onto a copy of
+/// `current`, and other secrets onto credentials. When `only` is given, the
+/// remaining keys are left out. Later duplicates win, as they do in dotenv.
+pub fn plan_import(
+    current: &AppConfig,
+    vars: &[(String, String)],
+    only: Option<&[String]>,
+) -> EnvImportPlan {
+    let mut plan = EnvImportPlan {
+        entries: Vec::new(),
+        ignored: Vec::new(),
+        warnings: Vec::new(),
+        applied: false,
+        config: current.clone(),
+        credentials: Vec::new(),
+    };
+
+    for (key, raw) in vars {
+        let value = raw.trim();
+        if value.is_empty() || only.is_some_and(|keys| !keys.iter().any(|selected| selected == key))
+        {
+            continue;
+        }
+
+        let config = &mut plan.config;
+        let (field, sensitive, previous, next) = match key.as_str() {
+            "HEDERA_OPERATOR_ID" => (
+                "hedera.accountId",
+                false,
+                Zeroizing::new(std::mem::replace(
+                    &mut config.hedera.account_id,
+                    value.to_string(),
+                )),
+                Zeroizing::new(value.to_string()),
+            ),
+            "HEDERA_OPERATOR_KEY" => (
+                "hedera.privateKey",
+                true,
+                replace_secret(&mut config.hedera.private_key, value),
+                value.to_string().into(),
+            ),
+            "HEDERA_NETWORK" => {
+                let network = match value.to_lowercase().as_str() {
+                    "mainnet" => Network::Mainnet,
+                    "testnet" => Network::Testnet,
+                    _ => {
+                        plan.warnings
+                            .push(format!("{key}: unknown network {value}"));
+                        continue;
+                    }
+                };
+                let previous = std::mem::replace(&mut config.hedera.network, network);
+                (
+                    "hedera.network",
+                    false,
+                    enum_name(&previous).into(),
+                    enum_name(&config.hedera.network).into(),
+                )
+            }
+            "OPENAI_API_KEY" => (
+                "openai.apiKey",
+                true,
+                replace_secret(&mut config.openai.api_key, value),
+                value.to_string().into(),
+            ),
+            "OPENAI_MODEL" => (
+                "openai.model",
+                false,
+                std::mem::replace(&mut config.openai.model, value.to_string()).into(),
+                value.to_string().into(),
+            ),
+            "ANTHROPIC_API_KEY" => (
+                "anthropic.apiKey",
+                true,
+                replace_secret(&mut config.anthropic.api_key, value),
+                value.to_string().into(),
+            ),
+            "ANTHROPIC_MODEL" => (
+                "anthropic.model",
+                false,
+                std::mem::replace(&mut config.anthropic.model, value.to_string()).into(),
+                value.to_string().into(),
+            ),
+            "OPENROUTER_API_KEY" => (
+                "openrouter.apiKey",
+                true,
+                replace_secret(&mut config.openrouter.api_key, value),
+                value.to_string().into(),
+            ),
+            "OPENROUTER_MODEL" => (
+                "openrouter.model",
+                false,
+                std::mem::replace(&mut config.openrouter.model, value.to_string()).into(),
+                value.to_string().into(),
+            ),
+            "GEMINI_API_KEY" => (
+                "gemini.apiKey",
+                true,
+                replace_secret(&mut config.gemini.api_key, value),
+                value.to_string().into(),
+            ),
+            "GEMINI_MODEL" => (
+                "gemini.model",
+                false,
+                std::mem::replace(&mut config.gemini.model, value.to_string()).into(),
+                value.to_string().into(),
+            ),
+            "LLM_PROVIDER" => {
+                let Some(provider) = LlmProvider::from_id(value) else {
+                    plan.warnings
+                        .push(format!("{key}: unknown provider {value}"));
+                    continue;
+                };
+                let previous = std::mem::replace(&mut config.llm_provider, provider);
+                (
+                    "llmProvider",
+                    false,
+                    enum_name(&previous).into(),
+                    enum_name(&config.llm_provider).into(),
+                )
+            }
+            "SWARM_BEE_API_URL" => (
+                "swarm.beeApiUrl",
+                false,
+                std::mem::replace(&mut swarm(config).bee_api_url, value.to_string()).into(),
+                value.to_string().into(),
+            ),
+            "SWARM_BEE_FEED_PK" => (
+                "swarm.beeFeedPK",
+                true,
+                replace_secret(&mut swarm(config).bee_feed_pk, value),
+                value.to_string().into(),
+            ),
+            "SWARM_AUTO_ASSIGN_STAMP" => {
+                let Ok(enabled) = value.parse::<bool>() else {
+                    plan.warnings.push(format!("{key}: expected true or false"));
+                    continue;
+                };
+                let previous = std::mem::replace(&mut swarm(config).auto_assign_stamp, enabled);
+                (
+                    "swarm.autoAssignStamp",
+                    false,
+                    previous.to_string().into(),
+                    enabled.to_string().into(),
+                )
+            }
+            "SWARM_DEFERRED_UPLOAD_SIZE_THRESHOLD_MB" => {
+                let Ok(threshold) = value.parse::<i32>() else {
+                    plan.warnings
+                        .push(format!("{key}: expected a whole number"));
+                    continue;
+                };
+                let previous = std::mem::replace(
+                    &mut swarm(config).deferred_upload_size_threshold_mb,
+                    threshold,
+                );
+                (
+                    "swarm.deferredUploadSizeThresholdMB",
+                    false,
+                    previous.to_string().into(),
+                    threshold.to_string().into(),
+                )
+            }
+            _ if is_secret(key) => {
+                plan.entries.retain(|entry| entry.key != *key);
+                plan.credentials.retain(|(_, account, _)| account != key);
+                plan.credentials.push((
+                    ENV_CREDENTIAL_SERVICE.to_string(),
+                    key.clone(),
+                    CredentialScope::Mcp,
+                ));
+                plan.entries.push(EnvImportEntry {
+                    key: key.clone(),
+                    target: EnvImportTarget::Credential,
+                    field: format!("{ENV_CREDENTIAL_SERVICE}/{key}"),
+                    value: mask(value),
+                    current: None,
+                    sensitive: true,
+                    changed: true,
+                    scope: Some(CredentialScope::Mcp),
+                });
+                continue;
+            }
+            _ => {
+                if !plan.ignored.contains(key) {
+                    plan.ignored.push(key.clone());
+                }
+                continue;
+            }
+        };
+
+        plan.entries.retain(|entry| entry.key != *key);
+        let shown = |text: &str| {
+            if sensitive {
+                mask(text)
+            } else {
+                text.to_string()
+            }
+        };
+        plan.entries.push(EnvImportEntry {
+            key: key.clone(),
+            target: EnvImportTarget::Config,
+            field: field.to_string(),
+            value: shown(&next),
+            current: (!previous.is_empty()).then(|| shown(&previous)),
+            sensitive,
+            changed: previous != next,
+            scope: None,
+        });
+    }
+
+    plan
+}
+
+fn swarm(config: &mut AppConfig) -> &mut SwarmConfig {
+    config.swarm.get_or_insert_with(SwarmConfig::default)
+}
+
+fn enum_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// The value to store for a planned credential.
+pub fn credential_value<'a>(vars: &'a [(String, String)], account: &str) -> Option<&'a str> {
+    vars.iter()
+        .rev()
+        .find(|(key, _)| key == account)
+        .map(|(_, value)| value.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_keys_map_to_config_and_other_secrets_to_credentials() {
+        let contents = "\
+# operator
+HEDERA_OPERATOR_ID=0.0.1234
+HEDERA_OPERATOR_KEY=\"302e020100300506032b657004220420aaaa\"
+HEDERA_NETWORK=mainnet
+OPENAI_API_KEY=sk-first
+OPENAI_API_KEY=sk-test-1234567890
+LLM_PROVIDER=anthropic
+SWARM_AUTO_ASSIGN_STAMP=maybe
+GITHUB_TOKEN=ghp_abcdefgh12345
+NODE_ENV=development
+";
+        let vars = parse_env(contents.as_bytes()).unwrap();
+        let current = AppConfig::default();
+        let plan = plan_import(&current, &vars, None);
+
+        assert_eq!(plan.config.hedera.account_id, "0.0.1234");
+        assert!(matches!(plan.config.hedera.network, Network::Mainnet));
+        assert_eq!(plan.config.openai.api_key, "sk-test-1234567890");
+        assert!(matches!(plan.config.llm_provider, LlmProvider::Anthropic));
+        assert_eq!(plan.ignored, vec!["NODE_ENV".to_string()]);
+        assert_eq!(plan.warnings.len(), 1);
+
+        let openai = plan
+            .entries
+            .iter()
+            .filter(|entry| entry.key == "OPENAI_API_KEY")
+            .collect::<Vec<_>>();
+        assert_eq!(openai.len(), 1);
+        assert_eq!(openai[0].value, "********7890");
+        let network = plan
+            .entries
+            .iter()
+            .find(|entry| entry.key == "HEDERA_NETWORK")
+            .unwrap();
+        assert_eq!(network.current.as_deref(), Some("testnet"));
+        assert!(network.changed);
+
+        assert_eq!(
+            plan.credentials,
+            vec![(
+                "env".to_string(),
+                "GITHUB_TOKEN".to_string(),
+                CredentialScope::Mcp
+            )]
+        );
+        assert_eq!(
+            credential_value(&vars, "GITHUB_TOKEN"),
+            Some("ghp_abcdefgh12345")
+        );
+
+        let only = vec!["HEDERA_OPERATOR_ID".to_string()];
+        let selected = plan_import(&current, &vars, Some(&only));
+        assert_eq!(selected.entries.len(), 1);
+        assert!(selected.credentials.is_empty());
+        assert_eq!(selected.config.openai.api_key, current.openai.api_key);
+    }
+}
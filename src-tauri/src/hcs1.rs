@@ -0,0 +1,371 @@
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::connection::HederaMirrorConfig;
+use crate::mirror::MirrorNetwork;
+use crate::proxy;
+
+const HCS1_SCHEME: &str = "hcs://1/";
+const MIRROR_PAGE_LIMIT: usize = 100;
+/// HCS-1 chunks are about 1 KB, so this caps inscriptions at roughly 10 MB.
+const MAX_MIRROR_PAGES: usize = 100;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// The topic memo of an HCS-1 inscription: `<sha256>:<compression>:<encoding>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hcs1Memo {
+    pub hash: String,
+    pub compression: String,
+    pub encoding: String,
+}
+
+/// An inscription as read from its topic: the memo and the reassembled
+/// content, base64-decoded but still compressed.
+#[derive(Debug, Clone)]
+pub struct Hcs1Payload {
+    pub topic_id: String,
+    pub memo: Hcs1Memo,
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+/// A decoded inscription whose hash matched its memo.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct InscribedFile {
+    pub topic_id: String,
+    pub mime_type: String,
+    pub hash: String,
+    pub size: usize,
+    #[serde(skip_deserializing)]
+    pub data_url: String,
+}
+
+#[derive(Deserialize)]
+struct TopicInfo {
+    #[serde(default)]
+    memo: String,
+}
+
+#[derive(Deserialize)]
+struct TopicMessagesResponse {
+    #[serde(default)]
+    messages: Vec<TopicMessage>,
+}
+
+#[derive(Deserialize)]
+struct TopicMessage {
+    sequence_number: u64,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct Hcs1Chunk {
+    o: u64,
+    c: String,
+}
+
+/// Resolves `hcs://1/<topic>` references: reads the chunks from the mirror
+/// node, checks the reassembled file against the hash in the topic memo and
+/// keeps verified files under `cache_dir`.
+pub struct Hcs1Store {
+    client: Client,
+    mirror_config: HederaMirrorConfig,
+    cache_dir: PathBuf,
+}
+
+impl Hcs1Store {
+    pub fn new(cache_dir: PathBuf) -> Result<Self, String> {
+        Self::with_mirror_config(cache_dir, HederaMirrorConfig::default())
+    }
+
+    pub fn with_mirror_config(
+        cache_dir: PathBuf,
+        mirror_config: HederaMirrorConfig,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            client: proxy::client_builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .map_err(|error| format!("Failed to build HTTP client: {error}"))?,
+            mirror_config,
+            cache_dir,
+        })
+    }
+
+    pub async fn nft_info(
+        &self,
+        token_id: &str,
+        serial_number: u64,
+        network: MirrorNetwork,
+    ) -> Result<Value, String> {
+        self.get_json(network, &format!("/tokens/{token_id}/nfts/{serial_number}"))
+            .await
+    }
+
+    /// A previously verified file, re-checked against its hash so a damaged
+    /// cache entry is fetched again instead of served.
+    pub fn cached(&self, topic_id: &str) -> Option<InscribedFile> {
+        let meta = fs::read_to_string(self.cache_dir.join(format!("{topic_id}.json"))).ok()?;
+        let mut file: InscribedFile = serde_json::from_str(&meta).ok()?;
+        let data = fs::read(self.cache_dir.join(format!("{topic_id}.bin"))).ok()?;
+        if sha256_hex(&data) != file.hash {
+            log::warn!("Discarding cached inscription {topic_id} with a mismatched hash");
+            return None;
+        }
+        file.data_url = format!("data:{};base64,{}", file.mime_type, STANDARD.encode(&data));
+        Some(file)
+    }
+
+    pub async fn fetch(
+        &self,
+        topic_id: &str,
+        network: MirrorNetwork,
+    ) -> Result<Hcs1Payload, String> {
+        let info: TopicInfo = serde_json::from_value(
+            self.get_json(network, &format!("/topics/{topic_id}"))
+                .await?,
+        )
+        .map_err(|error| format!("Failed to parse topic {topic_id}: {error}"))?;
+        let memo = parse_hcs1_memo(&info.memo)?;
+
+        let mut chunks = Vec::new();
+        let mut after = 0;
+        for _ in 0..MAX_MIRROR_PAGES {
+            let page: TopicMessagesResponse = serde_json::from_value(
+                self.get_json(
+                    network,
+                    &format!(
+                        "/topics/{topic_id}/messages?sequencenumber=gt:{after}&order=asc&limit={MIRROR_PAGE_LIMIT}"
+                    ),
+                )
+                .await?,
+            )
+            .map_err(|error| format!("Failed to parse topic {topic_id} messages: {error}"))?;
+            let page_len = page.messages.len();
+            for message in page.messages {
+                after = message.sequence_number;
+                let bytes = STANDARD
+                    .decode(message.message.as_bytes())
+                    .map_err(|error| format!("Invalid topic message encoding: {error}"))?;
+                chunks.push(bytes);
+            }
+            if page_len < MIRROR_PAGE_LIMIT {
+                let (mime_type, data) = reassemble(&chunks)?;
+                return Ok(Hcs1Payload {
+                    topic_id: topic_id.to_string(),
+                    memo,
+                    mime_type,
+                    data,
+                });
+            }
+        }
+        Err(format!(
+            "Inscription {topic_id} is larger than this app reads"
+        ))
+    }
+
+    /// Checks `decoded`, the payload after decompression, against the memo
+    /// hash and caches it.
+    pub fn store(&self, payload: &Hcs1Payload, decoded: Vec<u8>) -> Result<InscribedFile, String> {
+        let hash = sha256_hex(&decoded);
+        if !hash.eq_ignore_ascii_case(&payload.memo.hash) {
+            return Err(format!(
+                "Inscription {} failed verification: expected hash {}, got {}",
+                payload.topic_id, payload.memo.hash, hash
+            ));
+        }
+
+        let file = InscribedFile {
+            topic_id: payload.topic_id.clone(),
+            mime_type: payload.mime_type.clone(),
+            hash,
+            size: decoded.len(),
+            data_url: format!(
+                "data:{};base64,{}",
+                payload.mime_type,
+                STANDARD.encode(&decoded)
+            ),
+        };
+        let cache = || -> Result<(), String> {
+            fs::create_dir_all(&self.cache_dir)
+                .map_err(|error| format!("Failed to create directory: {error}"))?;
+            fs::write(
+                self.cache_dir.join(format!("{}.bin", file.topic_id)),
+                &decoded,
+            )
+            .map_err(|error| format!("Failed to cache inscription: {error}"))?;
+            let meta = InscribedFile {
+                data_url: String::new(),
+                ..file.clone()
+            };
+            let meta = serde_json::to_string(&meta).map_err(|error| error.to_string())?;
+            fs::write(self.cache_dir.join(format!("{}.json", file.topic_id)), meta)
+                .map_err(|error| format!("Failed to cache inscription: {error}"))
+        };
+        if let Err(error) = cache() {
+            log::warn!("{}", error);
+        }
+        Ok(file)
+    }
+
+    async fn get_json(&self, network: MirrorNetwork, path: &str) -> Result<Value, String> {
+        let base = match network {
+            MirrorNetwork::Mainnet => self.mirror_config.mainnet.trim_end_matches('/'),
+            MirrorNetwork::Testnet => self.mirror_config.testnet.trim_end_matches('/'),
+        };
+        let url = format!("{base}{path}");
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|error| format!("Failed to contact Hedera mirror node: {error}"))?;
+        if !response.status().is_success() {
+            return Err(format!(
+                "Mirror node returned {} for {path}",
+                response.status()
+            ));
+        }
+        response
+            .json()
+            .await
+            .map_err(|error| format!("Failed to parse mirror node response: {error}"))
+    }
+}
+
+/// The topic id in an `hcs://1/<topic>` URI.
+pub fn parse_hcs1_uri(uri: &str) -> Option<&str> {
+    let topic_id = uri.trim().strip_prefix(HCS1_SCHEME)?.trim_end_matches('/');
+    let parts: Vec<&str> = topic_id.split('.').collect();
+    let valid = parts.len() == 3
+        && parts
+            .iter()
+            .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()));
+    valid.then_some(topic_id)
+}
+
+pub fn parse_hcs1_memo(memo: &str) -> Result<Hcs1Memo, String> {
+    let mut parts = memo.trim().split(':');
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(hash), Some(compression), Some(encoding), None)
+            if hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit()) =>
+        {
+            Ok(Hcs1Memo {
+                hash: hash.to_ascii_lowercase(),
+                compression: compression.to_ascii_lowercase(),
+                encoding: encoding.to_ascii_lowercase(),
+            })
+        }
+        _ => Err(format!("Topic memo is not an HCS-1 memo: {memo}")),
+    }
+}
+
+/// Orders the `{ "o": index, "c": content }` chunks and splits the joined
+/// data URI into its MIME type and decoded bytes.
+fn reassemble(messages: &[Vec<u8>]) -> Result<(String, Vec<u8>), String> {
+    let mut chunks = messages
+        .iter()
+        .map(|bytes| serde_json::from_slice::<Hcs1Chunk>(bytes))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| format!("Invalid HCS-1 chunk: {error}"))?;
+    if chunks.is_empty() {
+        return Err("Inscription topic has no messages".to_string());
+    }
+    chunks.sort_by_key(|chunk| chunk.o);
+    let content: String = chunks.into_iter().map(|chunk| chunk.c).collect();
+
+    let (header, body) = content
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(','))
+        .ok_or_else(|| "Inscription content is not a data URI".to_string())?;
+    let mime_type = header
+        .strip_suffix(";base64")
+        .ok_or_else(|| "Inscription content is not base64 encoded".to_string())?;
+    let data = STANDARD
+        .decode(body.as_bytes())
+        .map_err(|error| format!("Invalid inscription content: {error}"))?;
+    Ok((mime_type.to_string(), data))
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::MockServer;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn chunk_message(sequence: u64, order: u64, content: &str) -> Value {
+        json!({
+            "sequence_number": sequence,
+            "message": STANDARD.encode(json!({ "o": order, "c": content }).to_string()),
+        })
+    }
+
+    #[test]
+    fn references_and_memos_are_parsed() {
+        assert_eq!(parse_hcs1_uri("hcs://1/0.0.4567"), Some("0.0.4567"));
+        assert_eq!(parse_hcs1_uri("ipfs://bafy"), None);
+        assert_eq!(parse_hcs1_uri("hcs://1/0.0.x"), None);
+
+        let hash = sha256_hex(b"hello");
+        let memo = parse_hcs1_memo(&format!("{}:zstd:base64", hash.to_uppercase())).unwrap();
+        assert_eq!(memo.hash, hash);
+        assert_eq!(memo.compression, "zstd");
+        assert!(parse_hcs1_memo("not-a-memo").is_err());
+    }
+
+    #[tokio::test]
+    async fn inscriptions_are_reassembled_verified_and_cached() {
+        let server = MockServer::start();
+        let file = b"\x89PNG tiny image".to_vec();
+        let encoded = format!("data:image/png;base64,{}", STANDARD.encode(&file));
+        let (first, second) = encoded.split_at(20);
+
+        server.mock(|when, then| {
+            when.method("GET").path("/api/v1/topics/0.0.99");
+            then.status(200)
+                .json_body(json!({ "memo": format!("{}:none:base64", sha256_hex(&file)) }));
+        });
+        server.mock(|when, then| {
+            when.method("GET").path("/api/v1/topics/0.0.99/messages");
+            then.status(200).json_body(json!({
+                "messages": [chunk_message(1, 1, second), chunk_message(2, 0, first)]
+            }));
+        });
+
+        let dir = tempdir().unwrap();
+        let store = Hcs1Store::with_mirror_config(
+            dir.path().join("inscriptions"),
+            HederaMirrorConfig {
+                mainnet: server.url("/api/v1"),
+                testnet: server.url("/api/v1"),
+            },
+        )
+        .unwrap();
+        assert!(store.cached("0.0.99").is_none());
+
+        let payload = store.fetch("0.0.99", MirrorNetwork::Testnet).await.unwrap();
+        assert_eq!(payload.mime_type, "image/png");
+        assert_eq!(payload.data, file);
+        assert!(store.store(&payload, b"tampered".to_vec()).is_err());
+
+        let stored = store.store(&payload, payload.data.clone()).unwrap();
+        assert_eq!(stored.size, file.len());
+        assert!(stored.data_url.starts_with("data:image/png;base64,"));
+        assert_eq!(store.cached("0.0.99"), Some(stored));
+    }
+}
@@ -1,9 +1,9 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
@@ -12,14 +12,17 @@ use tokio::sync::Mutex;
 use uuid::Uuid;
 
 use crate::config::{
-    ConfigState, LoadConfigResponse, Network, OperationalMode, ProfileSocials, ProfileStatus,
-    StoredHcs10Profile, load_config, save_config,
+    ConfigState, Hcs10StateRetentionConfig, LoadConfigResponse, Network, OperationalMode,
+    ProfileSocials, ProfileStatus, StoredHcs10Profile, load_config, store_config,
 };
+use crate::proxy;
 use tauri::Emitter;
 
 const EVENT_REGISTRATION_PROGRESS: &str = "hcs10_registration_progress";
 const REGISTRATION_TIMEOUT: Duration = Duration::from_secs(600);
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(10);
 const STATE_EXPIRY_HOURS: i64 = 24;
+const STATE_FILE_SUFFIX: &str = "_registration_state.json";
 
 #[derive(Serialize)]
 struct BridgeRequest {
@@ -50,16 +53,26 @@ struct Hcs10Process {
 #[derive(Clone)]
 pub struct Hcs10Bridge {
     process: Arc<Mutex<Hcs10Process>>,
+    script_path: PathBuf,
 }
 
 impl Hcs10Bridge {
     pub async fn spawn(script_path: PathBuf) -> Result<Self, String> {
+        Ok(Self {
+            process: Arc::new(Mutex::new(Self::start(&script_path)?)),
+            script_path,
+        })
+    }
+
+    fn start(script_path: &Path) -> Result<Hcs10Process, String> {
         let mut command = Command::new("node");
         command
-            .arg(&script_path)
+            .arg(script_path)
+            .envs(proxy::env_vars())
             .stdin(std::process::Stdio::piped())
             .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::inherit());
+            .stderr(std::process::Stdio::inherit())
+            .kill_on_drop(true);
 
         if let Some(parent) = script_path.parent() {
             command.current_dir(parent);
@@ -78,16 +91,46 @@ impl Hcs10Bridge {
             .take()
             .ok_or_else(|| "HCS10 bridge stdout unavailable".to_string())?;
 
-        Ok(Self {
-            process: Arc::new(Mutex::new(Hcs10Process {
-                child,
-                stdin,
-                stdout: BufReader::new(stdout),
-                next_id: 0,
-            })),
+        Ok(Hcs10Process {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+            next_id: 0,
         })
     }
 
+    /// Fails when the bridge process has exited or does not answer a ping.
+    /// A bridge busy with a request counts as healthy; the request has its
+    /// own timeout.
+    pub async fn health_check(&self) -> Result<(), String> {
+        {
+            let Ok(mut process) = self.process.try_lock() else {
+                return Ok(());
+            };
+            if let Ok(Some(status)) = process.child.try_wait() {
+                return Err(format!("HCS10 bridge exited ({status})"));
+            }
+        }
+        tokio::time::timeout(
+            HEARTBEAT_TIMEOUT,
+            self.send_request("ping", json!({}), |_| Ok(())),
+        )
+        .await
+        .map_err(|_| "HCS10 bridge stopped responding".to_string())?
+        .map(|_| ())
+    }
+
+    /// Replaces the bridge process with a fresh one. Registrations in flight
+    /// are lost, but their saved state lets them be resumed.
+    pub async fn restart(&self) -> Result<(), String> {
+        let fresh = Self::start(&self.script_path)?;
+        let mut process = self.process.lock().await;
+        let _ = process.child.start_kill();
+        *process = fresh;
+        log::info!("HCS10 bridge restarted");
+        Ok(())
+    }
+
     async fn send_request<F>(
         &self,
         action: &str,
@@ -210,6 +253,101 @@ impl Hcs10Bridge {
         self.send_request("hcs10_cancel_registration", Value::Null, |_| Ok(()))
             .await
     }
+
+    pub async fn fetch_connection_requests(&self, payload: Value) -> Result<Value, String> {
+        self.send_request("hcs10_fetch_connection_requests", payload, |_| Ok(()))
+            .await
+    }
+
+    pub async fn accept_connection_request(&self, payload: Value) -> Result<Value, String> {
+        self.send_request("hcs10_accept_connection_request", payload, |_| Ok(()))
+            .await
+    }
+
+    /// Sends any bridge action, ignoring progress messages.
+    pub async fn raw_request(&self, action: &str, payload: Value) -> Result<Value, String> {
+        self.send_request(action, payload, |_| Ok(())).await
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Hcs10StateStatus {
+    InProgress,
+    Completed,
+    Cancelled,
+    /// The registration failed, or the file could not be read.
+    Failed,
+}
+
+impl Hcs10StateStatus {
+    fn is_finished(self) -> bool {
+        self != Self::InProgress
+    }
+}
+
+/// A saved registration state, as listed to the user.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Hcs10StateSummary {
+    /// File name, as passed to `hcs10_delete_state`.
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile_name: Option<String>,
+    pub status: Hcs10StateStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stage: Option<String>,
+    pub last_updated: String,
+    pub size: u64,
+    /// Whether the registration in progress is using it.
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Hcs10StatePruneSummary {
+    pub removed: Vec<String>,
+    pub freed_bytes: u64,
+}
+
+fn summarize_state(path: &Path, active_name: Option<&str>) -> Option<Hcs10StateSummary> {
+    let name = path.file_name()?.to_str()?.to_string();
+    if !name.ends_with(STATE_FILE_SUFFIX) {
+        return None;
+    }
+    let metadata = fs::metadata(path).ok()?;
+    let state = fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok());
+    let field = |key: &str| {
+        state
+            .as_ref()
+            .and_then(|state| state.get(key))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+    let stage = field("currentStage").or_else(|| field("stage"));
+    let status = match (&state, stage.as_deref().map(str::to_lowercase).as_deref()) {
+        (None, _) => Hcs10StateStatus::Failed,
+        (_, Some("completed" | "complete")) => Hcs10StateStatus::Completed,
+        (_, Some("cancelled" | "canceled")) => Hcs10StateStatus::Cancelled,
+        (_, Some("failed" | "error")) => Hcs10StateStatus::Failed,
+        _ => Hcs10StateStatus::InProgress,
+    };
+    let last_updated = field("lastUpdated")
+        .and_then(|value| value.parse::<DateTime<Utc>>().ok())
+        .or_else(|| metadata.modified().ok().map(DateTime::<Utc>::from))
+        .unwrap_or_else(Utc::now);
+
+    Some(Hcs10StateSummary {
+        active: active_name == Some(name.as_str()),
+        name,
+        profile_name: field("profileName"),
+        status,
+        stage,
+        last_updated: last_updated.to_rfc3339(),
+        size: metadata.len(),
+    })
 }
 
 #[derive(Debug, Clone)]
@@ -256,7 +394,7 @@ impl Hcs10Service {
             .collect::<String>()
             .to_lowercase();
         self.states_dir
-            .join(format!("{}_registration_state.json", sanitized))
+            .join(format!("{}{}", sanitized, STATE_FILE_SUFFIX))
     }
 
     fn save_registration_state(&self, profile_name: &str, state: &Value) -> Result<(), String> {
@@ -323,6 +461,101 @@ impl Hcs10Service {
         Ok(())
     }
 
+    async fn active_state_name(&self) -> Option<String> {
+        let active = self.active.lock().await;
+        let path = self.state_file_path(&active.as_ref()?.profile_name);
+        Some(path.file_name()?.to_string_lossy().into_owned())
+    }
+
+    /// Saved registration states, newest first.
+    pub async fn list_states(&self) -> Result<Vec<Hcs10StateSummary>, String> {
+        let active_name = self.active_state_name().await;
+        let entries = match fs::read_dir(&self.states_dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(format!("Failed to read state directory: {error}")),
+        };
+        let mut states: Vec<Hcs10StateSummary> = entries
+            .flatten()
+            .filter_map(|entry| summarize_state(&entry.path(), active_name.as_deref()))
+            .collect();
+        states.sort_by(|left, right| right.last_updated.cmp(&left.last_updated));
+        Ok(states)
+    }
+
+    /// Deletes one saved state by file name. The state of the registration
+    /// in progress cannot be deleted.
+    pub async fn delete_state(&self, name: &str) -> Result<bool, String> {
+        if !name.ends_with(STATE_FILE_SUFFIX)
+            || Path::new(name).file_name().and_then(|file| file.to_str()) != Some(name)
+        {
+            return Err(format!("{name} is not a registration state"));
+        }
+        if self.active_state_name().await.as_deref() == Some(name) {
+            return Err("That state belongs to the registration in progress".to_string());
+        }
+        match fs::remove_file(self.states_dir.join(name)) {
+            Ok(()) => Ok(true),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(error) => Err(format!("Failed to delete registration state: {error}")),
+        }
+    }
+
+    /// Removes finished states past their retention and in-progress ones
+    /// past `STATE_EXPIRY_HOURS`, then the oldest states until the directory
+    /// fits `max_directory_bytes`. The active registration's state is kept.
+    pub async fn prune_states(
+        &self,
+        retention: &Hcs10StateRetentionConfig,
+    ) -> Result<Hcs10StatePruneSummary, String> {
+        let now = Utc::now();
+        let mut summary = Hcs10StatePruneSummary::default();
+        let mut kept = Vec::new();
+        for state in self.list_states().await? {
+            let age_hours = state
+                .last_updated
+                .parse::<DateTime<Utc>>()
+                .map(|updated| (now - updated).num_hours())
+                .unwrap_or(0);
+            let expired = if state.status.is_finished() {
+                age_hours >= i64::from(retention.finished_retention_hours)
+            } else {
+                age_hours >= STATE_EXPIRY_HOURS
+            };
+            if expired && !state.active {
+                self.remove_state(state, &mut summary);
+            } else {
+                kept.push(state);
+            }
+        }
+
+        let mut total: u64 = kept.iter().map(|state| state.size).sum();
+        // `kept` is newest first, so evict from the back.
+        while total > retention.max_directory_bytes {
+            let Some(index) = kept.iter().rposition(|state| !state.active) else {
+                break;
+            };
+            let state = kept.remove(index);
+            total -= state.size;
+            self.remove_state(state, &mut summary);
+        }
+        Ok(summary)
+    }
+
+    fn remove_state(&self, state: Hcs10StateSummary, summary: &mut Hcs10StatePruneSummary) {
+        match fs::remove_file(self.states_dir.join(&state.name)) {
+            Ok(()) => {
+                summary.freed_bytes += state.size;
+                summary.removed.push(state.name);
+            }
+            Err(error) => log::warn!(
+                "Failed to remove registration state {}: {}",
+                state.name,
+                error
+            ),
+        }
+    }
+
     pub fn clear_all_states(&self) -> Result<(), String> {
         if let Ok(entries) = fs::read_dir(&self.states_dir) {
             for entry in entries.flatten() {
@@ -330,7 +563,7 @@ impl Hcs10Service {
                 if path
                     .file_name()
                     .and_then(|name| name.to_str())
-                    .map(|name| name.ends_with("_registration_state.json"))
+                    .map(|name| name.ends_with(STATE_FILE_SUFFIX))
                     .unwrap_or(false)
                 {
                     let _ = fs::remove_file(path);
@@ -370,7 +603,8 @@ impl Hcs10Service {
             load_config(app_handle.clone(), config_state.clone())?;
         let config = load_response.config;
 
-        if config.hedera.account_id.trim().is_empty() || config.hedera.private_key.trim().is_empty()
+        if config.hedera.account_id.trim().is_empty()
+            || config.hedera.private_key.expose().trim().is_empty()
         {
             self.active.lock().await.take();
             return Err(
@@ -489,7 +723,7 @@ impl Hcs10Service {
             config.hcs10_profiles.push(stored);
         }
 
-        save_config(app_handle.clone(), config_state.clone(), config)
+        store_config(app_handle, config_state, config, true)
     }
 
     pub fn get_registration_progress(&self, profile_name: &str) -> Result<Option<Value>, String> {
@@ -651,6 +885,74 @@ mod tests {
         assert!(path.ends_with("agent_name___registration_state.json"));
     }
 
+    #[tokio::test]
+    async fn states_expire_and_fit_the_size_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = Hcs10Service::new(None, dir.path().to_path_buf());
+        let write = |name: &str, stage: &str, hours_ago: i64| {
+            let updated = Utc::now() - chrono::Duration::hours(hours_ago);
+            fs::write(
+                dir.path().join(format!("{name}{STATE_FILE_SUFFIX}")),
+                json!({ "currentStage": stage, "lastUpdated": updated.to_rfc3339(), "profileName": name })
+                    .to_string(),
+            )
+            .unwrap();
+        };
+        write("done", "completed", 30);
+        write("stale", "profile", 30);
+        write("old", "profile", 3);
+        write("recent", "profile", 1);
+        write("fresh", "completed", 1);
+        fs::write(dir.path().join("notes.txt"), "not a state").unwrap();
+
+        let states = service.list_states().await.unwrap();
+        assert_eq!(states.len(), 5);
+        assert_eq!(states[0].profile_name.as_deref(), Some("fresh"));
+        assert_eq!(states[0].status, Hcs10StateStatus::Completed);
+
+        let size = states[0].size;
+        let summary = service
+            .prune_states(&Hcs10StateRetentionConfig {
+                finished_retention_hours: 24,
+                max_directory_bytes: size * 2 + size / 2,
+            })
+            .await
+            .unwrap();
+        let mut removed = summary.removed.clone();
+        removed.sort();
+        assert_eq!(
+            removed,
+            vec![
+                "done_registration_state.json",
+                "old_registration_state.json",
+                "stale_registration_state.json"
+            ]
+        );
+        let names: Vec<String> = service
+            .list_states()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|state| state.name)
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "fresh_registration_state.json",
+                "recent_registration_state.json"
+            ]
+        );
+
+        assert!(
+            service
+                .delete_state("recent_registration_state.json")
+                .await
+                .unwrap()
+        );
+        assert!(service.delete_state("../config.json").await.is_err());
+        assert!(dir.path().join("notes.txt").exists());
+    }
+
     #[test]
     fn registration_progress_event_is_snake_cased() {
         assert_eq!(EVENT_REGISTRATION_PROGRESS, "hcs10_registration_progress");
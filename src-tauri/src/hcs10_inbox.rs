@@ -0,0 +1,442 @@
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tokio::sync::Mutex;
+
+use crate::migrations::{self, Migration};
+
+pub const EVENT_CONNECTION_REQUESTS: &str = "hcs10_connection_requests_received";
+
+pub const HCS10_INBOX_MIGRATION_SCOPE: &str = "hcs10_inbox";
+
+pub const HCS10_INBOX_MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    description: "create hcs10 connection requests",
+    up: |connection| {
+        connection.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS hcs10_connection_requests (
+                id TEXT PRIMARY KEY,
+                account_id TEXT NOT NULL,
+                inbound_topic_id TEXT NOT NULL,
+                sequence_number INTEGER NOT NULL,
+                requester_account_id TEXT NOT NULL,
+                requester_inbound_topic_id TEXT,
+                memo TEXT,
+                received_at TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'pending',
+                connection_topic_id TEXT,
+                responded_at TEXT
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_hcs10_connection_requests_topic
+                ON hcs10_connection_requests(inbound_topic_id, sequence_number);
+            CREATE INDEX IF NOT EXISTS idx_hcs10_connection_requests_status
+                ON hcs10_connection_requests(status);
+            "#,
+        )
+    },
+    down: |connection| connection.execute_batch("DROP TABLE IF EXISTS hcs10_connection_requests;"),
+}];
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionRequestStatus {
+    Pending,
+    Accepted,
+    Rejected,
+}
+
+impl ConnectionRequestStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Accepted => "accepted",
+            Self::Rejected => "rejected",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "accepted" => Self::Accepted,
+            "rejected" => Self::Rejected,
+            _ => Self::Pending,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionRequest {
+    pub id: String,
+    pub account_id: String,
+    pub inbound_topic_id: String,
+    pub sequence_number: i64,
+    pub requester_account_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requester_inbound_topic_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    pub received_at: String,
+    pub status: ConnectionRequestStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connection_topic_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub responded_at: Option<String>,
+}
+
+/// A `connection_request` found on an inbound topic by the HCS10 bridge.
+/// `connection_topic_id` is set when the topic already holds a matching
+/// `connection_created`, i.e. the request was answered elsewhere.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredRequest {
+    pub sequence_number: i64,
+    pub requester_account_id: String,
+    #[serde(default)]
+    pub requester_inbound_topic_id: Option<String>,
+    #[serde(default)]
+    pub memo: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub connection_topic_id: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredRequests {
+    pub inbound_topic_id: String,
+    #[serde(default)]
+    pub requests: Vec<DiscoveredRequest>,
+}
+
+/// Inbound HCS-10 connection requests for the local profile, kept so they
+/// survive restarts and can be accepted or rejected later.
+pub struct Hcs10Inbox {
+    db: Mutex<Connection>,
+}
+
+impl Hcs10Inbox {
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        let connection = Connection::open(path).map_err(|err| err.to_string())?;
+        migrations::apply(
+            &connection,
+            HCS10_INBOX_MIGRATION_SCOPE,
+            HCS10_INBOX_MIGRATIONS,
+        )?;
+        Ok(Self {
+            db: Mutex::new(connection),
+        })
+    }
+
+    /// Moves over to the database of `other`, as when another workspace is
+    /// opened.
+    pub async fn replace(&self, other: Self) {
+        *self.db.lock().await = other.db.into_inner();
+    }
+
+    #[cfg(test)]
+    pub fn new_in_memory() -> Self {
+        let connection = Connection::open_in_memory().expect("in-memory sqlite");
+        migrations::apply(
+            &connection,
+            HCS10_INBOX_MIGRATION_SCOPE,
+            HCS10_INBOX_MIGRATIONS,
+        )
+        .expect("init schema");
+        Self {
+            db: Mutex::new(connection),
+        }
+    }
+
+    fn map_row(row: &Row<'_>) -> Result<ConnectionRequest, rusqlite::Error> {
+        Ok(ConnectionRequest {
+            id: row.get("id")?,
+            account_id: row.get("account_id")?,
+            inbound_topic_id: row.get("inbound_topic_id")?,
+            sequence_number: row.get("sequence_number")?,
+            requester_account_id: row.get("requester_account_id")?,
+            requester_inbound_topic_id: row.get("requester_inbound_topic_id")?,
+            memo: row.get("memo")?,
+            received_at: row.get("received_at")?,
+            status: ConnectionRequestStatus::parse(&row.get::<_, String>("status")?),
+            connection_topic_id: row.get("connection_topic_id")?,
+            responded_at: row.get("responded_at")?,
+        })
+    }
+
+    /// The inbound topic seen for `account_id` on an earlier fetch, so the
+    /// bridge does not have to resolve the profile every time.
+    pub async fn inbound_topic_for(&self, account_id: &str) -> Result<Option<String>, String> {
+        self.db
+            .lock()
+            .await
+            .query_row(
+                "SELECT inbound_topic_id FROM hcs10_connection_requests
+                 WHERE account_id = ?1 ORDER BY sequence_number DESC LIMIT 1",
+                params![account_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|err| err.to_string())
+    }
+
+    /// Where the next fetch should start: just before the oldest pending
+    /// request, so answers posted elsewhere are still noticed, or after the
+    /// newest known request.
+    pub async fn fetch_after(&self, inbound_topic_id: &str) -> Result<i64, String> {
+        self.db
+            .lock()
+            .await
+            .query_row(
+                "SELECT COALESCE(
+                    (SELECT MIN(sequence_number) - 1 FROM hcs10_connection_requests
+                     WHERE inbound_topic_id = ?1 AND status = 'pending'),
+                    (SELECT MAX(sequence_number) FROM hcs10_connection_requests
+                     WHERE inbound_topic_id = ?1),
+                    0
+                 )",
+                params![inbound_topic_id],
+                |row| row.get(0),
+            )
+            .map_err(|err| err.to_string())
+    }
+
+    /// Stores newly discovered requests and marks pending ones that were
+    /// answered elsewhere as accepted. Returns the requests that are new and
+    /// still waiting for an answer.
+    pub async fn record(
+        &self,
+        account_id: &str,
+        discovered: &DiscoveredRequests,
+    ) -> Result<Vec<ConnectionRequest>, String> {
+        let connection = self.db.lock().await;
+        let transaction = connection
+            .unchecked_transaction()
+            .map_err(|err| err.to_string())?;
+        let now = Utc::now().to_rfc3339();
+        let mut received = Vec::new();
+
+        for request in &discovered.requests {
+            let id = request_id(&discovered.inbound_topic_id, request.sequence_number);
+            let status = if request.connection_topic_id.is_some() {
+                ConnectionRequestStatus::Accepted
+            } else {
+                ConnectionRequestStatus::Pending
+            };
+            let inserted = transaction
+                .execute(
+                    "INSERT INTO hcs10_connection_requests (
+                        id, account_id, inbound_topic_id, sequence_number, requester_account_id,
+                        requester_inbound_topic_id, memo, received_at, status, connection_topic_id
+                     ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                     ON CONFLICT(id) DO NOTHING",
+                    params![
+                        &id,
+                        account_id,
+                        &discovered.inbound_topic_id,
+                        request.sequence_number,
+                        &request.requester_account_id,
+                        request.requester_inbound_topic_id.as_deref(),
+                        request.memo.as_deref(),
+                        request.created_at.as_deref().unwrap_or(&now),
+                        status.as_str(),
+                        request.connection_topic_id.as_deref(),
+                    ],
+                )
+                .map_err(|err| err.to_string())?;
+
+            if inserted == 0 {
+                if let Some(topic_id) = request.connection_topic_id.as_deref() {
+                    transaction
+                        .execute(
+                            "UPDATE hcs10_connection_requests
+                             SET status = 'accepted', connection_topic_id = ?1, responded_at = ?2
+                             WHERE id = ?3 AND status = 'pending'",
+                            params![topic_id, &now, &id],
+                        )
+                        .map_err(|err| err.to_string())?;
+                }
+                continue;
+            }
+
+            if status == ConnectionRequestStatus::Pending
+                && let Some(stored) = Self::fetch(&transaction, &id)?
+            {
+                received.push(stored);
+            }
+        }
+
+        transaction.commit().map_err(|err| err.to_string())?;
+        Ok(received)
+    }
+
+    /// Newest first, optionally limited to one status.
+    pub async fn list(
+        &self,
+        status: Option<ConnectionRequestStatus>,
+    ) -> Result<Vec<ConnectionRequest>, String> {
+        let connection = self.db.lock().await;
+        let mut statement = connection
+            .prepare(
+                "SELECT * FROM hcs10_connection_requests
+                 WHERE ?1 IS NULL OR status = ?1
+                 ORDER BY datetime(received_at) DESC, sequence_number DESC",
+            )
+            .map_err(|err| err.to_string())?;
+        statement
+            .query_map(
+                params![status.map(ConnectionRequestStatus::as_str)],
+                Self::map_row,
+            )
+            .map_err(|err| err.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| err.to_string())
+    }
+
+    /// A request that is still waiting for an answer.
+    pub async fn pending(&self, id: &str) -> Result<ConnectionRequest, String> {
+        let connection = self.db.lock().await;
+        let request = Self::fetch(&connection, id)?
+            .ok_or_else(|| format!("Connection request {id} not found"))?;
+        if request.status != ConnectionRequestStatus::Pending {
+            return Err(format!(
+                "Connection request {id} was already {}",
+                request.status.as_str()
+            ));
+        }
+        Ok(request)
+    }
+
+    pub async fn respond(
+        &self,
+        id: &str,
+        status: ConnectionRequestStatus,
+        connection_topic_id: Option<&str>,
+    ) -> Result<ConnectionRequest, String> {
+        let connection = self.db.lock().await;
+        let updated = connection
+            .execute(
+                "UPDATE hcs10_connection_requests
+                 SET status = ?1, connection_topic_id = ?2, responded_at = ?3
+                 WHERE id = ?4 AND status = 'pending'",
+                params![
+                    status.as_str(),
+                    connection_topic_id,
+                    Utc::now().to_rfc3339(),
+                    id
+                ],
+            )
+            .map_err(|err| err.to_string())?;
+        if updated == 0 {
+            return Err(format!("Connection request {id} is not pending"));
+        }
+        Self::fetch(&connection, id)?.ok_or_else(|| format!("Connection request {id} not found"))
+    }
+
+    fn fetch(connection: &Connection, id: &str) -> Result<Option<ConnectionRequest>, String> {
+        connection
+            .query_row(
+                "SELECT * FROM hcs10_connection_requests WHERE id = ?1",
+                params![id],
+                Self::map_row,
+            )
+            .optional()
+            .map_err(|err| err.to_string())
+    }
+}
+
+fn request_id(inbound_topic_id: &str, sequence_number: i64) -> String {
+    format!("{inbound_topic_id}@{sequence_number}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn discovered(sequence_number: i64, connection_topic_id: Option<&str>) -> DiscoveredRequest {
+        DiscoveredRequest {
+            sequence_number,
+            requester_account_id: format!("0.0.{}", 700 + sequence_number),
+            requester_inbound_topic_id: Some("0.0.800".into()),
+            memo: None,
+            created_at: Some(format!("2030-01-01T00:00:0{sequence_number}+00:00")),
+            connection_topic_id: connection_topic_id.map(str::to_string),
+        }
+    }
+
+    #[tokio::test]
+    async fn record_reports_only_new_pending_requests() {
+        let inbox = Hcs10Inbox::new_in_memory();
+        let batch = DiscoveredRequests {
+            inbound_topic_id: "0.0.500".into(),
+            requests: vec![discovered(1, None), discovered(2, Some("0.0.900"))],
+        };
+
+        let received = inbox.record("0.0.42", &batch).await.unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].id, "0.0.500@1");
+        assert!(inbox.record("0.0.42", &batch).await.unwrap().is_empty());
+
+        assert_eq!(
+            inbox.inbound_topic_for("0.0.42").await.unwrap().as_deref(),
+            Some("0.0.500")
+        );
+        assert_eq!(inbox.fetch_after("0.0.500").await.unwrap(), 0);
+
+        let answered = DiscoveredRequests {
+            inbound_topic_id: "0.0.500".into(),
+            requests: vec![discovered(1, Some("0.0.901"))],
+        };
+        inbox.record("0.0.42", &answered).await.unwrap();
+        assert!(
+            inbox
+                .list(Some(ConnectionRequestStatus::Pending))
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(inbox.fetch_after("0.0.500").await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn respond_only_answers_pending_requests() {
+        let inbox = Hcs10Inbox::new_in_memory();
+        inbox
+            .record(
+                "0.0.42",
+                &DiscoveredRequests {
+                    inbound_topic_id: "0.0.500".into(),
+                    requests: vec![discovered(3, None)],
+                },
+            )
+            .await
+            .unwrap();
+
+        let request = inbox.pending("0.0.500@3").await.unwrap();
+        let rejected = inbox
+            .respond(&request.id, ConnectionRequestStatus::Rejected, None)
+            .await
+            .unwrap();
+        assert_eq!(rejected.status, ConnectionRequestStatus::Rejected);
+        assert!(rejected.responded_at.is_some());
+
+        assert!(inbox.pending("0.0.500@3").await.is_err());
+        assert!(
+            inbox
+                .respond(
+                    &request.id,
+                    ConnectionRequestStatus::Accepted,
+                    Some("0.0.1")
+                )
+                .await
+                .is_err()
+        );
+        assert!(inbox.pending("0.0.500@99").await.is_err());
+    }
+}
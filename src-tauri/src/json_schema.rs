@@ -0,0 +1,185 @@
+use serde_json::Value;
+
+/// Checks `instance` against `schema` and returns one message per problem,
+/// each prefixed with the JSON pointer of the offending value.
+///
+/// Only the keywords entity metadata and MCP tool inputs use are understood: `type`, `enum`,
+/// `properties`, `required`, `additionalProperties`, `items`, `minLength`,
+/// `maxLength`, `minimum` and `maximum`. Anything else is ignored, as JSON
+/// Schema does for unknown keywords.
+pub fn validate(schema: &Value, instance: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    validate_at(schema, instance, "", &mut errors);
+    errors
+}
+
+fn validate_at(schema: &Value, instance: &Value, path: &str, errors: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        if schema == &Value::Bool(false) {
+            errors.push(format!("{}: is not allowed", pointer(path)));
+        }
+        return;
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|name| has_type(instance, name)) {
+            errors.push(format!(
+                "{}: expected {}, found {}",
+                pointer(path),
+                allowed.join(" or "),
+                type_name(instance)
+            ));
+            return;
+        }
+    }
+
+    if let Some(options) = schema.get("enum").and_then(Value::as_array)
+        && !options.contains(instance)
+    {
+        errors.push(format!(
+            "{}: {} is not an allowed value",
+            pointer(path),
+            instance
+        ));
+    }
+
+    match instance {
+        Value::Object(map) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for required in schema
+                .get("required")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+            {
+                if !map.contains_key(required) {
+                    errors.push(format!("{}: missing {}", pointer(path), required));
+                }
+            }
+            for (key, value) in map {
+                let child = format!("{path}/{}", key.replace('~', "~0").replace('/', "~1"));
+                match properties.and_then(|properties| properties.get(key)) {
+                    Some(property) => validate_at(property, value, &child, errors),
+                    None => {
+                        if let Some(additional) = schema.get("additionalProperties") {
+                            validate_at(additional, value, &child, errors);
+                        }
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    validate_at(item_schema, item, &format!("{path}/{index}"), errors);
+                }
+            }
+        }
+        Value::String(text) => {
+            let length = text.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64)
+                && length < min
+            {
+                errors.push(format!(
+                    "{}: shorter than {} characters",
+                    pointer(path),
+                    min
+                ));
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64)
+                && length > max
+            {
+                errors.push(format!("{}: longer than {} characters", pointer(path), max));
+            }
+        }
+        Value::Number(number) => {
+            let value = number.as_f64().unwrap_or_default();
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64)
+                && value < min
+            {
+                errors.push(format!("{}: below the minimum of {}", pointer(path), min));
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64)
+                && value > max
+            {
+                errors.push(format!("{}: above the maximum of {}", pointer(path), max));
+            }
+        }
+        _ => {}
+    }
+}
+
+fn pointer(path: &str) -> &str {
+    if path.is_empty() { "/" } else { path }
+}
+
+fn has_type(instance: &Value, name: &str) -> bool {
+    match name {
+        "integer" => instance.as_i64().is_some() || instance.as_u64().is_some(),
+        other => type_name(instance) == other || (other == "number" && instance.is_number()),
+    }
+}
+
+fn type_name(instance: &Value) -> &'static str {
+    match instance {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reports_each_problem_with_its_path() {
+        let schema = json!({
+            "type": "object",
+            "required": ["name"],
+            "properties": {
+                "name": { "type": "string", "minLength": 2 },
+                "decimals": { "type": "integer", "minimum": 0, "maximum": 18 },
+                "keys": { "type": "array", "items": { "type": "string" } },
+                "network": { "enum": ["mainnet", "testnet"] }
+            },
+            "additionalProperties": false
+        });
+        assert!(validate(&schema, &json!({ "name": "Points", "decimals": 2 })).is_empty());
+
+        let mut errors = validate(
+            &schema,
+            &json!({
+                "decimals": 2.5,
+                "keys": ["a", 1],
+                "network": "devnet",
+                "extra/key": true
+            }),
+        );
+        errors.sort();
+        assert_eq!(
+            errors,
+            vec![
+                "/: missing name",
+                "/decimals: expected integer, found number",
+                "/extra~1key: is not allowed",
+                "/keys/1: expected string, found number",
+                "/network: \"devnet\" is not an allowed value",
+            ]
+        );
+        assert_eq!(
+            validate(&schema, &json!("text")),
+            vec!["/: expected object, found string"]
+        );
+    }
+}
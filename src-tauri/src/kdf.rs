@@ -0,0 +1,79 @@
+use argon2::{Algorithm, Argon2, Params as Argon2Params, Version};
+use scrypt::{Params as ScryptParams, scrypt};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+/// Key derivations an encrypted config field or credential can name. New
+/// writes use `Kdf::CURRENT`; older entries stay readable and are
+/// re-encrypted under it when next read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kdf {
+    /// scrypt with log2(N) = 15, r = 8, p = 1.
+    Scrypt,
+    /// Argon2id v1.3 with 19 MiB of memory, 2 passes and 1 lane.
+    Argon2id,
+}
+
+impl Kdf {
+    pub const CURRENT: Kdf = Kdf::Argon2id;
+
+    pub fn id(self) -> &'static str {
+        match self {
+            Kdf::Scrypt => "scrypt",
+            Kdf::Argon2id => "argon2id",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "scrypt" => Some(Kdf::Scrypt),
+            "argon2id" => Some(Kdf::Argon2id),
+            _ => None,
+        }
+    }
+
+    /// Derives a 32-byte AES-256 key from `password` and `salt`.
+    pub fn derive_key(self, password: &str, salt: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+        let mut key = Zeroizing::new(vec![0u8; 32]);
+        match self {
+            Kdf::Scrypt => {
+                let params = ScryptParams::new(15, 8, 1, 32).map_err(|error| {
+                    format!("Failed to configure key derivation parameters: {error}")
+                })?;
+                scrypt(password.as_bytes(), salt, &params, &mut key)
+                    .map_err(|error| format!("Failed to derive encryption key: {error}"))?;
+            }
+            Kdf::Argon2id => {
+                let params = Argon2Params::new(19 * 1024, 2, 1, Some(32)).map_err(|error| {
+                    format!("Failed to configure key derivation parameters: {error}")
+                })?;
+                Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                    .hash_password_into(password.as_bytes(), salt, &mut key)
+                    .map_err(|error| format!("Failed to derive encryption key: {error}"))?;
+            }
+        }
+        Ok(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_kdf_derives_its_own_stable_key() {
+        let salt = [7u8; 32];
+        let argon2id = Kdf::Argon2id.derive_key("master-secret", &salt).unwrap();
+        let scrypt = Kdf::Scrypt.derive_key("master-secret", &salt).unwrap();
+
+        assert_eq!(argon2id.len(), 32);
+        assert_ne!(argon2id, scrypt);
+        assert_eq!(
+            Kdf::Argon2id.derive_key("master-secret", &salt).unwrap(),
+            argon2id
+        );
+        assert_eq!(Kdf::from_id(Kdf::CURRENT.id()), Some(Kdf::CURRENT));
+        assert_eq!(Kdf::from_id("pbkdf2"), None);
+    }
+}
@@ -1,25 +1,73 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod account_history;
 mod agent;
 mod agent_backend;
+mod agent_registry;
 mod agent_services;
 #[cfg(not(test))]
 mod browser;
+mod browser_permissions;
 mod config;
+mod config_history;
+mod config_validation;
 mod connection;
+mod contacts;
 mod credentials;
+mod data_backup;
+mod developer;
+mod diagnostics;
+mod effective_config;
 mod entity;
+mod entity_id;
+mod entity_schema;
+mod env_import;
+mod hcs1;
 mod hcs10;
+mod hcs10_inbox;
+mod json_schema;
+mod kdf;
 mod logging;
+mod master_key;
 mod mcp;
+mod mcp_claude_config;
+mod mcp_groups;
+mod mcp_install_variables;
+mod mcp_oauth;
 mod mcp_registry;
+mod mcp_registry_source;
+mod mcp_sampling;
+mod mcp_sandbox;
+mod mcp_tool_cache;
+mod mcp_tool_calls;
+mod mcp_updates;
+mod migrations;
 mod mirror;
+mod mirror_poll;
+mod network;
 mod node_agent;
+mod os_auth;
+mod outbox;
+mod power;
+mod proxy;
+mod rag;
+mod rate_limit;
+mod relay;
+mod secret;
 mod session;
+mod session_sync;
+mod settings_sync;
+mod startup;
+mod storage_recovery;
+mod summarization;
+mod token_holders;
+mod totp;
 mod transaction_parser;
+mod usage;
 mod wallet_bridge;
+mod workspace;
 
-pub use agent_backend::{AgentBackend, BackendError, EchoAgent};
+pub use agent_backend::{AgentBackend, BackendError, BridgeTimeout, EchoAgent};
 #[cfg(test)]
 mod browser {
     use serde::{Deserialize, Serialize};
@@ -30,7 +78,9 @@ mod browser {
 
     #[derive(Clone, Serialize, Deserialize, Default)]
     #[serde(rename_all = "camelCase")]
-    pub struct BrowserStateDto {}
+    pub struct BrowserStateDto {
+        pub current_url: String,
+    }
 
     #[derive(Clone, Serialize, Deserialize, Default)]
     pub struct BoundsPayload {}
@@ -93,7 +143,7 @@ mod browser {
         }
 
         pub async fn get_state(&self) -> Result<BrowserStateDto, String> {
-            Ok(BrowserStateDto {})
+            Ok(BrowserStateDto::default())
         }
 
         pub async fn execute_js(
@@ -118,45 +168,120 @@ use browser::{BrowserManager, BrowserStateDto};
 mod main_tests;
 
 use agent::{
-    AgentInitializeConfig, AgentInitializeResponse, AgentMessageRequest, AgentMessageResponse,
-    AgentService, AgentSessionContext, AgentStatusResponse,
+    AgentInitializeConfig, AgentInitializeResponse, AgentLlmSettings, AgentMessageData,
+    AgentMessageRequest, AgentMessageResponse, AgentService, AgentSessionContext,
+    AgentStatusResponse, BridgeRestart, ChatEntry,
 };
+use base64::{Engine as _, engine::general_purpose::STANDARD};
 use chrono::{DateTime, Duration, Utc};
 use config::{
-    ConfigState, LoadConfigResponse, Network, StoredHcs10Profile, load_config, plugin_disable,
-    plugin_enable, save_config, set_auto_start, set_log_level, set_theme,
+    AppConfig, ConfigSection, ConfigState, ContextStrategy, LegalAcceptanceConfig,
+    LoadConfigResponse, Network, PowerPolicyConfig, RagConfig, RagEmbedder, StoredHcs10Profile,
+    load_config, plugin_disable, plugin_enable, save_config, set_auto_start, set_log_level,
+    set_theme,
 };
 use connection::{
-    ConnectionService, HederaCredentials, HederaNetwork, HederaTestResponse, LlmCredentials,
-    LlmTestResponse,
+    ConnectionService, EmbeddingRequest, HederaCredentials, HederaMirrorConfig, HederaNetwork,
+    HederaTestResponse, LlmCredentials, LlmTestResponse,
+};
+use credentials::{
+    CredentialAccess, CredentialConsumer, CredentialManager, CredentialScope, CredentialSummary,
+    MasterPasswordCheck, OsKeyring,
 };
-use credentials::CredentialManager;
-use hcs10::{Hcs10Bridge, Hcs10Service};
+use hcs10::{Hcs10Bridge, Hcs10Service, Hcs10StateSummary};
 use log::LevelFilter;
+use rust_xlsxwriter::{Format, Workbook, XlsxError};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
-use session::{ChatMessage, ChatSession, CreateSessionInput, SessionContext, SessionService};
+use session::{
+    ChatMessage, ChatSession, ChatThread, CreateSessionInput, MessageScope, PruneSummary,
+    SessionContext, SessionDeleteFilter, SessionLlm, SessionReadState, SessionService,
+    SessionStats, SessionSummary, SessionUsage, ToolCallRecord, is_later,
+};
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryFrom;
 use std::fs;
 use std::io::Write as _;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tauri::path::BaseDirectory;
-use tauri::{AppHandle, Emitter, Manager, State, Wry};
+use tauri::{AppHandle, Emitter, Manager, RunEvent, State, WindowEvent, Wry};
+use tauri_plugin_dialog::DialogExt;
 use tokio::sync::Mutex;
 use tokio::time::{Duration as TokioDuration, sleep};
 
-use crate::entity::{EntityAssociation, EntityService};
+use crate::account_history::{AccountHistoryService, AccountTransactionsPage};
+use crate::agent_registry::{BackendDescriptor, REMOTE_BACKEND_ID, RemoteHttpFactory};
+use crate::browser_permissions::{BrowserScriptPermissions, ScriptApprovalDecision};
+use crate::config_history::{ConfigHistory, ConfigHistoryEntry};
+use crate::config_validation::{ConfigFieldError, ConfigValidationReport};
+use crate::contacts::{Contact, ContactBook};
+use crate::data_backup::{BackupSummary, RestoreSummary};
+use crate::developer::{
+    DEVELOPER_COMMANDS, DeveloperBridge, DeveloperDatabase, MigrationStatus, QueryResult,
+    migration_status, query_read_only, rollback_migrations,
+};
+use crate::diagnostics::{
+    BridgeProbe, BridgeSelftestReport, DiagnosticsBundle, run_bridge_selftest,
+};
+use crate::entity::{
+    EntityAssociation, EntityAuditEntry, EntityListQuery, EntityPage, EntityService,
+    EntitySortOrder, EntityStats, EntityType,
+};
+use crate::entity_id::{EntityIdError, find_checksum_mismatches, parse_entity_id};
+use crate::env_import::{EnvImportPlan, EnvImportTarget};
+use crate::hcs1::{Hcs1Store, parse_hcs1_uri};
+use crate::hcs10_inbox::{
+    ConnectionRequest, ConnectionRequestStatus, DiscoveredRequests, EVENT_CONNECTION_REQUESTS,
+    Hcs10Inbox,
+};
+use crate::master_key::MasterKeySource;
 use crate::mcp::{
-    McpConnectionResult, McpRegistrySearchResult, McpService, remote_registry_enabled,
+    MCP_RECONNECT_MAX_ATTEMPTS, McpConnectionResult, McpRegistrySearchResult, McpServerHealth,
+    McpServerStatus, McpServerStatusEvent, McpService, McpToolCallResult, mcp_reconnect_delay,
+    remote_registry_enabled,
 };
+use crate::mcp_claude_config::{McpConfigExport, McpConfigImport};
+use crate::mcp_groups::{McpGroupActivation, McpServerGroup};
+use crate::mcp_sampling::{SamplingApprovals, SamplingCall, SamplingDecision};
+use crate::mcp_sandbox::ProcessGroups;
+use crate::mcp_tool_calls::{McpToolCalls, McpToolProgress};
+use crate::mcp_updates::McpServerUpdate;
 use crate::mirror::{MirrorBridgeState, MirrorNetwork, MirrorNodeBridge};
+use crate::mirror_poll::{EVENT_ENTITY_HYDRATED, IngestLatency, PollSchedule, RecordStatus};
+use crate::network::{EVENT_NETWORK_STATE_CHANGED, NetworkMonitor, NetworkState};
+use crate::os_auth::OsAuthGate;
+use crate::outbox::{EVENT_OUTBOX_UPDATED, Outbox, OutboxItem, OutboxKind};
+use crate::power::{PowerMonitor, PowerState, detect_on_battery};
+use crate::rag::{
+    DocumentStore, Embedder, IndexedDocument, RagIndexSummary, RetrievedChunk, SourceFile,
+};
+use crate::rate_limit::{ProviderHeaders, ProviderRateLimiter, RateStatusReport};
+use crate::relay::{
+    LocalRelay, LocalRelayEnv, RelayEndpoint, RelayHealth, RelayToolSettings, check_health,
+    resolve_endpoint, tool_settings,
+};
+use crate::secret::Secret;
+use crate::session_sync::{
+    PublishWatermark, SessionSyncReport, SessionSyncService, SessionSyncStatus,
+};
+use crate::settings_sync::{
+    ConflictChoice, SettingsSnapshot, SettingsSyncService, SyncCheck, SyncSettings, SyncState,
+};
+use crate::startup::{StartupPlan, plan_startup};
+use crate::storage_recovery::StorageRecoveryReport;
+use crate::token_holders::{TokenDistributionSummary, TokenHolderService, TokenHoldersPage};
+use crate::totp::TotpCode;
 use crate::transaction_parser::{TransactionParserBridge, TransactionParserState};
+use crate::usage::UsageSummary;
 use crate::wallet_bridge::{
     WalletBridgeInfo, WalletBridgeState, wallet_execute_bytes, wallet_status_json,
 };
+use crate::workspace::{
+    DEFAULT_WORKSPACE_ID, EVENT_WORKSPACE_SWITCHED, Workspace, WorkspaceList, WorkspaceRegistry,
+};
 
 type ActiveBrowserManager = BrowserManager<Wry>;
 
@@ -169,7 +294,7 @@ async fn initialize_mcp_service(handle: AppHandle<Wry>) {
     let servers = {
         let state = handle.state::<Mutex<McpService>>();
         let service = state.lock().await;
-        match service.load().await {
+        match service.startup_servers().await {
             Ok(servers) => servers,
             Err(error) => {
                 log::warn!("Failed to load MCP servers at startup: {}", error);
@@ -178,22 +303,10 @@ async fn initialize_mcp_service(handle: AppHandle<Wry>) {
         }
     };
 
-    for server in servers {
-        let enabled = server
-            .get("enabled")
-            .and_then(Value::as_bool)
-            .unwrap_or(true);
-        if !enabled {
-            continue;
-        }
-
-        let Some(server_id) = server.get("id").and_then(Value::as_str) else {
-            continue;
-        };
-
+    for server_id in servers {
         let state = handle.state::<Mutex<McpService>>();
         let service = state.lock().await;
-        match service.connect(server_id).await {
+        match service.connect(&server_id).await {
             Ok(result) if result.success => {
                 log::info!("Connected to MCP server {}", server_id);
             }
@@ -216,6 +329,52 @@ async fn initialize_mcp_service(handle: AppHandle<Wry>) {
             }
         }
     }
+
+    let state = handle.state::<Mutex<McpService>>();
+    let service = state.lock().await;
+    emit_mcp_tool_changes(&handle, &service).await;
+}
+
+fn power_policy(app: &AppHandle<Wry>) -> PowerPolicyConfig {
+    load_config(app.clone(), app.state::<ConfigState>())
+        .map(|response| response.config.advanced.power_policy)
+        .unwrap_or_default()
+}
+
+/// Waits between runs of a background loop, backing off per the power policy
+/// while the window is unfocused or the machine is on battery.
+async fn power_aware_sleep(handle: &AppHandle<Wry>, base: TokioDuration) {
+    let policy = power_policy(handle);
+    handle.state::<PowerMonitor>().sleep(base, &policy).await;
+}
+
+fn emit_power_state(handle: &AppHandle<Wry>) {
+    let state = handle.state::<PowerMonitor>().state(&power_policy(handle));
+    if let Err(error) = handle.emit("power_state_changed", state) {
+        log::warn!("Failed to emit power_state_changed: {}", error);
+    }
+}
+
+async fn monitor_power_source(handle: AppHandle<Wry>) {
+    loop {
+        sleep(TokioDuration::from_secs(60)).await;
+
+        if let Some(on_battery) = detect_on_battery()
+            && handle.state::<PowerMonitor>().set_on_battery(on_battery)
+        {
+            emit_power_state(&handle);
+        }
+    }
+}
+
+async fn watch_hcs10_connection_requests(handle: AppHandle<Wry>) {
+    loop {
+        power_aware_sleep(&handle, TokioDuration::from_secs(60)).await;
+
+        if let Err(error) = refresh_connection_requests(&handle).await {
+            log::debug!("HCS10 connection request refresh failed: {}", error);
+        }
+    }
 }
 
 async fn schedule_mcp_background_sync(handle: AppHandle<Wry>) {
@@ -234,2196 +393,7381 @@ async fn schedule_mcp_background_sync(handle: AppHandle<Wry>) {
             }
         }
 
-        sleep(TokioDuration::from_secs(30 * 60)).await;
+        power_aware_sleep(&handle, TokioDuration::from_secs(30 * 60)).await;
     }
 }
 
-fn main() {
-    let _ = dotenvy::dotenv();
-    let context = tauri::generate_context!();
-    let master_password = std::env::var("MASTER_PASSWORD")
-        .unwrap_or_else(|_| "default-secure-password-change-me".to_string());
-
-    logging::init(LevelFilter::Info);
-
-    tauri::Builder::default()
-        .plugin(tauri_plugin_autostart::Builder::new().build())
-        .manage(ConfigState::new(master_password.clone()))
-        .manage(Mutex::new(
-            ConnectionService::new().expect("Failed to initialize ConnectionService"),
-        ))
-        .manage(Mutex::new(AgentService::new()))
-        .manage(WalletBridgeState::default())
-        .manage(ActiveBrowserManager::new(None))
-        .setup(move |app| {
-            if app.try_state::<ActiveBrowserManager>().is_none() {
-                log::error!("ActiveBrowserManager state not available during setup");
-            } else {
-                log::info!("ActiveBrowserManager state successfully registered");
-            }
-            let app_handle = app.handle();
-            let config_dir = app_handle
-                .path()
-                .app_data_dir()
-                .map_err(|error| error.to_string())?;
-
-            if !config_dir.exists() {
-                fs::create_dir_all(&config_dir)?;
-            }
-
-            let credential_path = config_dir.join("credentials.dat");
-            let session_db_path = config_dir.join("chat.sqlite");
-            let mcp_path = config_dir.join("mcp-servers.json");
-            let registry_db_path = config_dir.join("mcp-registry.sqlite");
-
-            let credential_manager =
-                CredentialManager::new(credential_path, master_password.clone());
-            let session_service = SessionService::from_path(&session_db_path)?;
-            let entity_service = EntityService::from_path(&session_db_path)?;
-            let mcp_service = McpService::new(mcp_path, Some(registry_db_path));
+/// Reconnects MCP servers whose session dropped, such as a server process
+/// that crashed.
+async fn supervise_mcp_connections(handle: AppHandle<Wry>) {
+    loop {
+        power_aware_sleep(&handle, TokioDuration::from_secs(15)).await;
 
-            app.manage(Mutex::new(credential_manager));
-            app.manage(Mutex::new(session_service));
-            app.manage(Mutex::new(entity_service));
-            app.manage(Mutex::new(mcp_service));
-            let hcs10_bridge = resolve_hcs10_bridge_script(&app_handle).and_then(|script| {
-                match tauri::async_runtime::block_on(Hcs10Bridge::spawn(script.clone())) {
-                    Ok(bridge) => Some(Arc::new(bridge)),
-                    Err(error) => {
-                        log::warn!(
-                            "Failed to spawn HCS10 bridge at {}: {}",
-                            script.display(),
-                            error
-                        );
-                        None
-                    }
-                }
-            });
-            let hcs10_service = Hcs10Service::new(hcs10_bridge, config_dir.join("hcs10-states"));
-            app.manage(hcs10_service);
-            let mirror_bridge =
-                resolve_mirror_bridge_script(&app_handle).map(MirrorNodeBridge::new);
-            app.manage(MirrorBridgeState::new(mirror_bridge));
-            let transaction_bridge =
-                resolve_transaction_parser_script(&app_handle).map(TransactionParserBridge::new);
-            app.manage(TransactionParserState::new(transaction_bridge));
-            app.manage(Arc::new(Mutex::new(None::<WalletBridgeInfo>)));
+        let dropped = {
+            let state = handle.state::<Mutex<McpService>>();
+            let service = state.lock().await;
+            service.take_dropped_sessions().await
+        };
+        for server_id in dropped {
+            tauri::async_runtime::spawn(reconnect_mcp_server(handle.clone(), server_id));
+        }
+    }
+}
 
-            let init_handle = app.app_handle().clone();
-            tauri::async_runtime::spawn(async move {
-                initialize_mcp_service(init_handle.clone()).await;
-            });
+fn emit_mcp_server_status(
+    handle: &AppHandle<Wry>,
+    server_id: &str,
+    status: McpServerStatus,
+    attempt: u32,
+    error: Option<String>,
+) {
+    let event = McpServerStatusEvent {
+        server_id: server_id.to_string(),
+        status,
+        attempt,
+        error,
+    };
+    if let Err(error) = handle.emit("mcp_server_status", event) {
+        log::warn!("Failed to emit mcp_server_status: {}", error);
+    }
+}
 
-            let sync_handle = app.app_handle().clone();
-            tauri::async_runtime::spawn(async move {
-                schedule_mcp_background_sync(sync_handle.clone()).await;
-            });
+async fn reconnect_mcp_server(handle: AppHandle<Wry>, server_id: String) {
+    let mut last_error = None;
+    for attempt in 1..=MCP_RECONNECT_MAX_ATTEMPTS {
+        let state = handle.state::<Mutex<McpService>>();
+        if !state.lock().await.reconnect_pending(&server_id).await {
+            return;
+        }
+        emit_mcp_server_status(
+            &handle,
+            &server_id,
+            McpServerStatus::Connecting,
+            attempt,
+            last_error.take(),
+        );
 
-            match resolve_bridge_script(&app_handle) {
-                Some(bridge_path) => {
-                    log::info!(
-                        "Configuring conversational agent bridge: {}",
-                        bridge_path.display()
-                    );
-                    let agent_service = app.state::<Mutex<AgentService>>();
-                    tauri::async_runtime::block_on(async {
-                        let guard = agent_service.lock().await;
-                        guard.set_bridge_script(Some(bridge_path.clone())).await;
-                        log::info!(
-                            "Conversational agent bridge registered: {}",
-                            bridge_path.display()
-                        );
-                    });
-                }
-                None => {
-                    log::warn!(
-                        "Conversational agent bridge script not found; falling back to echo backend"
-                    );
-                }
+        let result = state.lock().await.connect(&server_id).await;
+        match result {
+            Ok(result) if result.success => {
+                log::info!("Reconnected to MCP server {}", server_id);
+                emit_mcp_server_status(
+                    &handle,
+                    &server_id,
+                    McpServerStatus::Connected,
+                    attempt,
+                    None,
+                );
+                return;
             }
-            Ok(())
-        })
-        .invoke_handler(tauri::generate_handler![
-            version_info,
-            load_config,
-            save_config,
-            set_theme,
-            set_auto_start,
-            set_log_level,
-            plugin_enable,
-            plugin_disable,
-            config::get_environment_config,
-            connection_test_hedera,
-            connection_test_openai,
-            connection_test_anthropic,
-            agent_initialize,
-            agent_status,
-            agent_disconnect,
-            agent_send_message,
-            agent_update_session_context,
-            chat_create_session,
-            chat_load_session,
-            chat_save_session,
-            chat_delete_session,
-            chat_load_all_sessions,
-            chat_save_message,
-            chat_load_session_messages,
-            chat_update_session_context,
-            chat_update_form_state,
-            chat_update_message_metadata,
-            credential_store,
-            credential_get,
-            credential_delete,
-            credential_clear,
-            mirror_node_get_schedule_info,
-            mirror_node_get_scheduled_transaction_status,
-            mirror_node_get_transaction_by_timestamp,
-            mirror_node_get_transaction,
-            mirror_node_get_token_info,
-            transaction_parser_validate,
-            transaction_parser_parse,
-            execute_transaction_bytes,
-            wallet_hydrate_entity,
-            wallet_set_current,
-            wallet_status,
-            entity_get_all,
-            entity_delete,
-            entity_bulk_delete,
-            entity_rename,
-            entity_export,
-            entity_get_by_id,
-            entity_search,
-            browser_attach,
-            browser_detach,
-            browser_navigate,
-            browser_reload,
-            browser_go_back,
-            browser_go_forward,
-            browser_set_bounds,
-            browser_capture_context,
-            browser_set_layout,
-            browser_get_state,
-            browser_execute_js,
-            browser_open_devtools,
-            browser_open_external,
-            mcp_load_servers,
-            mcp_save_servers,
-            mcp_test_connection,
-            mcp_connect_server,
-            mcp_disconnect_server,
-            mcp_get_server_tools,
-            mcp_refresh_server_tools,
-            mcp_search_registry,
-            mcp_get_registry_server_details,
-            mcp_install_from_registry,
-            mcp_clear_registry_cache,
-            mcp_get_cache_stats,
-            mcp_trigger_background_sync,
-            mcp_enrich_metrics,
-            hcs10_register_profile,
-            hcs10_validate_profile,
-            hcs10_get_profiles,
-            hcs10_get_registration_progress,
-            hcs10_is_registration_in_progress,
-            hcs10_cancel_registration,
-            hcs10_clear_all_states,
-            hcs10_retrieve_profile
-        ])
-        .run(context)
-        .expect("failed to run Tauri application");
-}
+            Ok(result) => {
+                last_error = Some(
+                    result
+                        .error
+                        .unwrap_or_else(|| "Unknown MCP connection failure".to_string()),
+                );
+            }
+            Err(error) => last_error = Some(error),
+        }
+        log::debug!(
+            "Reconnect attempt {} for MCP server {} failed: {:?}",
+            attempt,
+            server_id,
+            last_error
+        );
+        if attempt < MCP_RECONNECT_MAX_ATTEMPTS {
+            sleep(mcp_reconnect_delay(attempt)).await;
+        }
+    }
 
-#[derive(Serialize)]
-struct CommandResponse<T> {
-    success: bool,
-    data: Option<T>,
-    error: Option<String>,
+    let state = handle.state::<Mutex<McpService>>();
+    state.lock().await.finish_reconnect(&server_id).await;
+    log::warn!(
+        "Giving up on MCP server {} after {} attempts",
+        server_id,
+        MCP_RECONNECT_MAX_ATTEMPTS
+    );
+    emit_mcp_server_status(
+        &handle,
+        &server_id,
+        McpServerStatus::Failed,
+        MCP_RECONNECT_MAX_ATTEMPTS,
+        last_error,
+    );
 }
 
-impl<T> CommandResponse<T> {
-    fn ok(data: T) -> Self {
-        Self {
-            success: true,
-            data: Some(data),
-            error: None,
+/// Reports tools added, removed or changed since a server was last listed,
+/// and rewires the agent's MCP tools when it uses one of those servers.
+async fn emit_mcp_tool_changes(handle: &AppHandle<Wry>, service: &McpService) {
+    let mut server_ids = Vec::new();
+    for change in service.take_tool_changes().await {
+        server_ids.push(change.server_id.clone());
+        if let Err(error) = handle.emit("mcp_tools_changed", change) {
+            log::warn!("Failed to emit mcp_tools_changed: {}", error);
         }
     }
+    if !server_ids.is_empty() {
+        tauri::async_runtime::spawn(rewire_agent_mcp_tools(handle.clone(), server_ids));
+    }
+}
 
-    fn error(message: String) -> Self {
-        Self {
-            success: false,
-            data: None,
-            error: Some(message),
+/// Restarts the agent backend so it lists the changed servers' tools again.
+async fn rewire_agent_mcp_tools(handle: AppHandle<Wry>, server_ids: Vec<String>) {
+    let agent_state = handle.state::<Mutex<AgentService>>();
+    let wallet_bridge = handle.state::<WalletBridgeState>().inner().clone();
+    let wallet_info = handle.state::<Arc<Mutex<Option<WalletBridgeInfo>>>>();
+    let restart = agent_state
+        .lock()
+        .await
+        .rewire_mcp_tools(&server_ids, wallet_bridge, Arc::clone(wallet_info.inner()))
+        .await;
+    match restart {
+        Ok(Some(restart)) => {
+            if let Err(error) = handle.emit("agent_bridge_restarted", restart) {
+                log::warn!("Failed to emit agent_bridge_restarted: {}", error);
+            }
         }
+        Ok(None) => {}
+        Err(error) => log::warn!("Failed to rewire the agent's MCP tools: {}", error),
     }
 }
 
-fn filter_entities_in_place(
-    entities: &mut Vec<EntityAssociation>,
-    search: Option<&str>,
-    date_range: Option<&EntityDateRangePayload>,
+/// Lists a server's tools again each time it sends `tools/list_changed`.
+async fn serve_mcp_tool_list_changes(
+    handle: AppHandle<Wry>,
+    mut receiver: tokio::sync::mpsc::UnboundedReceiver<String>,
 ) {
-    if let Some(search) = search {
-        let search_lower = search.to_lowercase();
-        entities.retain(|entity| {
-            entity.entity_name.to_lowercase().contains(&search_lower)
-                || entity.entity_id.to_lowercase().contains(&search_lower)
-                || entity
-                    .transaction_id
-                    .as_ref()
-                    .map(|value| value.to_lowercase().contains(&search_lower))
-                    .unwrap_or(false)
-        });
+    while let Some(server_id) = receiver.recv().await {
+        let state = handle.state::<Mutex<McpService>>();
+        let service = state.lock().await;
+        if let Err(error) = service.reload_tools(&server_id).await {
+            log::warn!(
+                "Failed to reload tools for MCP server {}: {}",
+                server_id,
+                error
+            );
+        }
+        emit_mcp_tool_changes(&handle, &service).await;
     }
+}
 
-    if let Some(range) = date_range {
-        let start = range
-            .start
-            .as_deref()
-            .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
-            .map(|dt| dt.with_timezone(&Utc));
-        let end = range
-            .end
-            .as_deref()
-            .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
-            .map(|dt| dt.with_timezone(&Utc));
-
-        entities.retain(
-            |entity| match DateTime::parse_from_rfc3339(&entity.created_at) {
-                Ok(parsed) => {
-                    let created = parsed.with_timezone(&Utc);
-                    if let Some(start) = start {
-                        if created < start {
-                            return false;
-                        }
-                    }
-                    if let Some(end) = end {
-                        if created > end {
-                            return false;
-                        }
-                    }
-                    true
-                }
-                Err(_) => true,
-            },
-        );
+/// Relays the progress servers report for running tool calls.
+async fn serve_mcp_tool_progress(
+    handle: AppHandle<Wry>,
+    mut receiver: tokio::sync::mpsc::UnboundedReceiver<McpToolProgress>,
+) {
+    while let Some(progress) = receiver.recv().await {
+        if let Err(error) = handle.emit("mcp_tool_progress", progress) {
+            log::warn!("Failed to emit mcp_tool_progress: {}", error);
+        }
     }
 }
 
-fn derive_entity_name(context: Option<&Value>, entity_type: &str, entity_id: &str) -> String {
-    if let Some(name_value) = context
-        .and_then(|value| value.get("name"))
-        .and_then(Value::as_str)
-    {
-        let trimmed = name_value.trim();
-        if !trimmed.is_empty() {
-            return trimmed.to_string();
-        }
+/// Answers `sampling/createMessage` requests from MCP servers, each on its
+/// own task so a request waiting on the user does not hold up the others.
+async fn serve_mcp_sampling(
+    handle: AppHandle<Wry>,
+    mut receiver: tokio::sync::mpsc::UnboundedReceiver<SamplingCall>,
+) {
+    while let Some(call) = receiver.recv().await {
+        let handle = handle.clone();
+        tauri::async_runtime::spawn(async move {
+            let result = run_mcp_sampling(&handle, &call).await;
+            if let Err(error) = &result {
+                log::warn!(
+                    "Sampling for MCP server {} failed: {}",
+                    call.server_id,
+                    error
+                );
+            }
+            let _ = call.reply.send(result);
+        });
     }
+}
 
-    if let Some(description_value) = context
-        .and_then(|value| value.get("description"))
-        .and_then(Value::as_str)
-    {
-        let tokens: Vec<&str> = description_value.split_whitespace().collect();
-        for window in tokens.windows(2) {
-            let indicator = window[0]
-                .trim_matches(|c: char| !c.is_ascii_alphabetic())
-                .to_ascii_lowercase();
-            if is_entity_indicator(&indicator) {
-                let candidate = window[1]
-                    .trim_matches(|c: char| !is_allowed_entity_char(c))
-                    .to_string();
-                if !candidate.trim().is_empty() {
-                    return candidate.trim().to_string();
-                }
+/// Holds a server's sampling request until the user approves it, unless they
+/// already trusted the server, then runs it on the agent's LLM.
+async fn run_mcp_sampling(
+    handle: &AppHandle<Wry>,
+    call: &SamplingCall,
+) -> Result<rmcp::model::CreateMessageResult, String> {
+    let settings = handle
+        .state::<Mutex<AgentService>>()
+        .lock()
+        .await
+        .llm_settings()
+        .await?;
+    let config = load_config(handle.clone(), handle.state::<ConfigState>())?.config;
+    let mut settings = settings.with_saved_key(&config);
+    settings.api_key = handle
+        .state::<Mutex<CredentialManager>>()
+        .lock()
+        .await
+        .scoped(CredentialConsumer::AgentBridge)
+        .resolve(settings.api_key.expose())
+        .await
+        .map_err(|error| error.to_string())?
+        .into();
+    let request = mcp_sampling::completion_request(&call.params, &settings)?;
+
+    let approvals = handle.state::<Mutex<SamplingApprovals>>();
+    let pending = {
+        let mut approvals = approvals.lock().await;
+        if approvals.is_trusted(&call.server_id) {
+            None
+        } else {
+            Some(approvals.request(&call.server_id, &call.params))
+        }
+    };
+    if let Some((approval, receiver)) = pending {
+        handle
+            .emit("mcp_sampling_requested", &approval)
+            .map_err(|error| error.to_string())?;
+        let decision = tokio::time::timeout(TokioDuration::from_secs(120), receiver).await;
+        let approved = match decision {
+            Ok(Ok(decision)) => decision.approved,
+            Ok(Err(_)) => false,
+            Err(_) => {
+                approvals.lock().await.cancel(&approval.request_id);
+                return Err("Sampling request approval timed out".to_string());
             }
+        };
+        if !approved {
+            return Err("User declined the sampling request".to_string());
         }
     }
 
-    if !entity_id.trim().is_empty() {
-        return entity_id.to_string();
-    }
-
-    format!("{entity_type}_{}", Utc::now().timestamp())
-}
-
-fn is_entity_indicator(value: &str) -> bool {
-    matches!(
-        value,
-        "token" | "account" | "topic" | "schedule" | "contract"
-    )
+    let rate_limiter = handle.state::<Arc<ProviderRateLimiter>>().inner().clone();
+    rate_limiter.acquire(request.provider).await;
+    let connection = handle
+        .state::<Mutex<ConnectionService>>()
+        .lock()
+        .await
+        .clone();
+    let provider = request.provider;
+    let completion = connection
+        .complete(request)
+        .await
+        .map_err(|error| error.to_string())?;
+    rate_limiter.observe(
+        &ProviderHeaders {
+            provider: format!("{provider:?}").to_ascii_lowercase(),
+            headers: completion.headers.clone(),
+        },
+        Utc::now(),
+    );
+    Ok(mcp_sampling::into_result(completion))
 }
 
-fn is_allowed_entity_char(character: char) -> bool {
-    character.is_ascii_alphanumeric() || character == '_' || character == '-'
-}
+/// Pings connected MCP servers and tells the renderer when one turns
+/// unhealthy or recovers.
+async fn monitor_mcp_health(handle: AppHandle<Wry>) {
+    loop {
+        power_aware_sleep(&handle, TokioDuration::from_secs(60)).await;
 
-async fn persist_entity_association(
-    app: &AppHandle<Wry>,
-    entity_state: &State<'_, Mutex<EntityService>>,
-    agent_state: &State<'_, Mutex<AgentService>>,
-    entity_id: &str,
-    entity_type: &str,
-    entity_name: &str,
-    transaction_id: Option<&String>,
-    session_override: Option<&str>,
-    metadata_value: &Value,
-) -> Option<EntityAssociation> {
-    let session_id = if let Some(override_id) = session_override {
-        Some(override_id.to_string())
-    } else {
-        let agent_guard = agent_state.lock().await;
-        agent_guard
-            .session_context()
+        let check = handle
+            .state::<Mutex<McpService>>()
+            .lock()
             .await
-            .map(|context| context.session_id)
-    };
+            .prepare_health_check()
+            .await;
+        let changed = check.run().await;
+        for health in changed {
+            if let Err(error) = handle.emit("mcp_server_health", health) {
+                log::warn!("Failed to emit mcp_server_health: {}", error);
+            }
+        }
+    }
+}
 
-    let persist_outcome = {
-        let service = entity_state.lock().await;
-        service
-            .store_entity(
-                entity_id,
-                entity_name,
-                entity_type,
-                transaction_id.map(|value| value.as_str()),
-                session_id.as_deref(),
-                Some(metadata_value),
-            )
-            .await
-    };
+/// Heartbeats the agent and HCS-10 bridges, restarting a bridge whose Node
+/// process died or stopped answering.
+async fn monitor_bridges(handle: AppHandle<Wry>) {
+    loop {
+        power_aware_sleep(&handle, TokioDuration::from_secs(30)).await;
+
+        let agent_state = handle.state::<Mutex<AgentService>>();
+        let wallet_bridge = handle.state::<WalletBridgeState>().inner().clone();
+        let wallet_info = handle.state::<Arc<Mutex<Option<WalletBridgeInfo>>>>();
+        // A locked agent is busy with a message, which is a sign of life.
+        let agent_restart = match agent_state.try_lock() {
+            Ok(agent) => {
+                agent
+                    .restart_if_unhealthy(wallet_bridge, Arc::clone(wallet_info.inner()))
+                    .await
+            }
+            Err(_) => Ok(None),
+        };
+        let mut restarts = Vec::new();
+        match agent_restart {
+            Ok(Some(restart)) => restarts.push(restart),
+            Ok(None) => {}
+            Err(error) => log::warn!("Failed to restart the agent bridge: {}", error),
+        }
 
-    match persist_outcome {
-        Ok(result) => {
-            let stored = result.entity.clone();
-            if result.created {
-                log::info!(
-                    "persist_entity_association: stored new entity {} of type {}",
-                    entity_id,
-                    entity_type
-                );
-                if let Err(error) = app.emit("entity_created", stored.clone()) {
-                    log::warn!(
-                        "Failed to emit entity_created event for {}: {}",
-                        entity_id,
-                        error
-                    );
-                }
+        if let Some(bridge) = handle.state::<Hcs10Service>().bridge()
+            && let Err(reason) = bridge.health_check().await
+        {
+            log::warn!("HCS10 bridge failed its health check: {}", reason);
+            match bridge.restart().await {
+                Ok(()) => restarts.push(BridgeRestart {
+                    bridge: "hcs10".to_string(),
+                    reason,
+                    session_id: None,
+                    session_context: None,
+                }),
+                Err(error) => log::warn!("Failed to restart the HCS10 bridge: {}", error),
             }
-            Some(stored)
         }
-        Err(error) => {
-            log::warn!(
-                "Failed to persist entity association for {}: {}",
-                entity_id,
-                error
-            );
-            None
+
+        for restart in restarts {
+            if let Err(error) = handle.emit("agent_bridge_restarted", restart) {
+                log::warn!("Failed to emit agent_bridge_restarted: {}", error);
+            }
         }
     }
 }
 
-fn extract_transaction_id(value: &Value) -> Option<String> {
-    value
-        .get("transactionId")
-        .and_then(Value::as_str)
-        .map(|raw| raw.to_string())
-        .or_else(|| {
-            value
-                .get("transaction_id")
-                .and_then(Value::as_str)
-                .map(|raw| raw.to_string())
-        })
-        .or_else(|| {
-            value
-                .get("data")
-                .and_then(|data| data.get("transactionId"))
-                .and_then(Value::as_str)
-                .map(|raw| raw.to_string())
-        })
-        .or_else(|| {
-            value
-                .get("data")
-                .and_then(|data| data.get("transaction_id"))
-                .and_then(Value::as_str)
-                .map(|raw| raw.to_string())
-        })
-}
-
-fn extract_entity_from_response(value: &Value) -> Option<(String, String)> {
-    let entity_id = value
-        .get("entityId")
-        .and_then(Value::as_str)
-        .or_else(|| {
-            value
-                .get("data")
-                .and_then(|data| data.get("entityId"))
-                .and_then(Value::as_str)
-        })?
-        .to_string();
+async fn schedule_mcp_memory_compaction(handle: AppHandle<Wry>) {
+    loop {
+        power_aware_sleep(&handle, TokioDuration::from_secs(15 * 60)).await;
 
-    let entity_type = value
-        .get("entityType")
-        .and_then(Value::as_str)
-        .or_else(|| {
-            value
-                .get("data")
-                .and_then(|data| data.get("entityType"))
-                .and_then(Value::as_str)
-        })
-        .map(|raw| raw.to_string())
-        .unwrap_or_else(|| "unknown".to_string());
+        let state = handle.state::<Mutex<McpService>>();
+        let service = state.lock().await;
+        if let Err(error) = service.compact_memory().await {
+            log::debug!("MCP memory compaction failed: {}", error);
+        }
+    }
+}
 
-    Some((entity_id, entity_type))
+async fn prune_chat_history(app: &AppHandle<Wry>) -> Result<PruneSummary, String> {
+    let retention = load_config(app.clone(), app.state::<ConfigState>())?
+        .config
+        .advanced
+        .message_retention;
+    app.state::<Mutex<SessionService>>()
+        .lock()
+        .await
+        .prune_messages(retention.max_age_days, retention.max_messages_per_session)
+        .await
 }
 
-async fn hydrate_entity_via_mirror(
-    mirror_state: &MirrorBridgeState,
-    transaction_id: &str,
-    network: &str,
-) -> Option<(String, String)> {
-    let normalized_transaction_id = normalize_transaction_id_for_mirror(transaction_id);
+async fn schedule_message_retention(handle: AppHandle<Wry>) {
+    loop {
+        power_aware_sleep(&handle, TokioDuration::from_secs(60 * 60)).await;
 
-    let Some(bridge) = mirror_state.bridge() else {
-        log::debug!(
-            "hydrate_entity_via_mirror: mirror bridge unavailable for transaction {}",
-            transaction_id
-        );
-        return None;
-    };
+        match prune_chat_history(&handle).await {
+            Ok(summary) if summary.total_removed > 0 => {
+                log::info!("Pruned {} chat messages", summary.total_removed);
+            }
+            Ok(_) => {}
+            Err(error) => log::debug!("Chat history pruning failed: {}", error),
+        }
+    }
+}
 
-    let mirror_network = match MirrorNetwork::try_from_str(Some(network)) {
-        Ok(value) => value,
-        Err(error) => {
-            log::warn!(
-                "hydrate_entity_via_mirror: unsupported network '{}' for transaction {}: {}",
-                network,
-                transaction_id,
-                error
-            );
-            return None;
+async fn schedule_hcs10_state_pruning(handle: AppHandle<Wry>) {
+    loop {
+        let retention = load_config(handle.clone(), handle.state::<ConfigState>())
+            .map(|response| response.config.advanced.hcs10_state_retention);
+        match retention {
+            Ok(retention) => match handle
+                .state::<Hcs10Service>()
+                .prune_states(&retention)
+                .await
+            {
+                Ok(summary) if !summary.removed.is_empty() => log::info!(
+                    "Removed {} HCS-10 registration states ({} bytes)",
+                    summary.removed.len(),
+                    summary.freed_bytes
+                ),
+                Ok(_) => {}
+                Err(error) => log::debug!("HCS-10 state pruning failed: {}", error),
+            },
+            Err(error) => log::debug!("HCS-10 state pruning skipped: {}", error),
         }
-    };
 
-    log::debug!(
-        "hydrate_entity_via_mirror: polling mirror node for transaction {} (normalized: {})",
-        transaction_id,
-        normalized_transaction_id
-    );
+        power_aware_sleep(&handle, TokioDuration::from_secs(60 * 60)).await;
+    }
+}
 
-    for attempt in 1..=12 {
-        match bridge
-            .get_transaction(normalized_transaction_id.as_ref(), mirror_network)
-            .await
-        {
-            Ok(Some(transaction)) => {
-                if let Some(entity) = extract_entity_from_transaction(&transaction) {
-                    log::info!(
-                        "hydrate_entity_via_mirror: resolved entity {} (type {}) for transaction {} on attempt {}",
-                        entity.0,
-                        entity.1,
-                        transaction_id,
-                        attempt
-                    );
-                    return Some(entity);
-                }
-            }
-            Ok(None) => {
-                log::debug!(
-                    "hydrate_entity_via_mirror: mirror returned no records for transaction {} (attempt {})",
-                    transaction_id,
-                    attempt
-                );
+/// Applies the configured idle timeout to the credential store and locks it
+/// once it has gone unused for that long.
+async fn schedule_credential_auto_lock(handle: AppHandle<Wry>) {
+    loop {
+        let lock_config = load_config(handle.clone(), handle.state::<ConfigState>())
+            .map(|response| response.config.advanced.credential_lock);
+        let state = handle.state::<Mutex<CredentialManager>>();
+        let manager = state.lock().await.clone();
+        match lock_config {
+            Ok(lock_config) => {
+                let seconds = lock_config.idle_timeout_seconds;
+                manager.set_idle_timeout((seconds > 0).then(|| TokioDuration::from_secs(seconds)));
             }
-            Err(error) => {
-                log::debug!(
-                    "hydrate_entity_via_mirror: lookup error for transaction {} on attempt {}: {}",
-                    transaction_id,
-                    attempt,
-                    error
-                );
+            Err(error) => log::debug!("Credential auto-lock settings unavailable: {}", error),
+        }
+        match manager.lock_if_idle() {
+            Ok(true) => {
+                log::info!("Locked the credential store after inactivity");
+                if let Err(error) = handle.emit("credentials_locked", ()) {
+                    log::warn!("Failed to emit credentials_locked: {}", error);
+                }
             }
+            Ok(false) => {}
+            Err(error) => log::warn!("Failed to lock the credential store: {}", error),
         }
 
-        sleep(TokioDuration::from_millis(500)).await;
+        sleep(TokioDuration::from_secs(15)).await;
     }
-
-    log::warn!(
-        "hydrate_entity_via_mirror: unable to resolve entity metadata for transaction {} after polling",
-        transaction_id
-    );
-    None
 }
 
-fn normalize_transaction_id_for_mirror(transaction_id: &str) -> Cow<'_, str> {
-    if let Some((account, remainder)) = transaction_id.split_once('@') {
-        if account.is_empty() || remainder.is_empty() {
-            return Cow::Owned(transaction_id.replace('@', "-"));
+/// Picks up edits made to config.json outside the app, so the cached copy
+/// is not served after the file changed underneath it.
+async fn watch_config_file(handle: AppHandle<Wry>) {
+    let path = match config::config_path(&handle) {
+        Ok(path) => path,
+        Err(error) => {
+            log::warn!("Not watching config.json: {}", error);
+            return;
         }
+    };
+    let mut last_stamp = config::file_stamp(&path);
 
-        let mut parts = remainder.split('.');
-        let seconds = parts.next().unwrap_or_default();
-        let nanos = parts.next().unwrap_or_default();
+    loop {
+        power_aware_sleep(&handle, TokioDuration::from_secs(5)).await;
 
-        if !seconds.is_empty() && !nanos.is_empty() {
-            let extra = parts.collect::<Vec<_>>().join("");
-            let mut normalized_nanos = nanos.to_string();
-            if !extra.is_empty() {
-                normalized_nanos.push_str(&extra);
+        let stamp = config::file_stamp(&path);
+        if stamp == last_stamp {
+            continue;
+        }
+        last_stamp = stamp;
+
+        match config::reload_config(&path, &handle.state::<ConfigState>()) {
+            Ok(Some(_)) => {
+                log::info!("Reloaded config.json after an outside edit");
+                if let Err(error) = handle.emit(
+                    "config_changed",
+                    ConfigChangedEvent {
+                        section: None,
+                        backup_path: None,
+                    },
+                ) {
+                    log::warn!("Failed to emit config_changed: {}", error);
+                }
             }
-            return Cow::Owned(format!("{}-{}-{}", account, seconds, normalized_nanos));
+            Ok(None) => {}
+            Err(error) => log::warn!("Ignoring unreadable config.json edit: {}", error),
         }
-
-        let sanitized = remainder.replace('.', "-");
-        return Cow::Owned(format!("{}-{}", account, sanitized));
     }
-
-    Cow::Borrowed(transaction_id)
 }
 
-fn extract_entity_from_transaction(transaction: &Value) -> Option<(String, String)> {
-    let transaction_name = transaction.get("name").and_then(Value::as_str);
-    let map_from_name = map_transaction_name_to_entity_type(transaction_name);
+async fn watch_settings_sync_folder(handle: AppHandle<Wry>) {
+    let mut last_notified = 0;
 
-    let candidates: [(&str, Option<&str>); 7] = [
-        ("entity_id", map_from_name.as_deref()),
-        ("token_id", Some("tokenId")),
-        ("schedule_id", Some("scheduleId")),
-        ("topic_id", Some("topicId")),
-        ("contract_id", Some("contractId")),
-        ("file_id", Some("fileId")),
-        ("account_id", Some("accountId")),
-    ];
+    loop {
+        power_aware_sleep(&handle, TokioDuration::from_secs(60)).await;
 
-    for (key, explicit_type) in candidates {
-        if let Some(id) = transaction.get(key).and_then(Value::as_str) {
-            let entity_type = explicit_type
-                .map(|value| value.to_string())
-                .or_else(|| map_from_name.clone())
-                .unwrap_or_else(|| infer_entity_type_from_key(key));
-            return Some((id.to_string(), entity_type));
+        let envelope = {
+            let state = handle.state::<Mutex<SettingsSyncService>>();
+            let service = state.lock().await;
+            if !service.settings().enabled || !service.has_unseen_remote_revision() {
+                continue;
+            }
+            match service.remote_envelope() {
+                Ok(Some(envelope)) => envelope,
+                Ok(None) => continue,
+                Err(error) => {
+                    log::debug!("Failed to read settings sync file: {}", error);
+                    continue;
+                }
+            }
+        };
+
+        if envelope.revision == last_notified {
+            continue;
+        }
+        last_notified = envelope.revision;
+
+        if let Err(error) = handle.emit(
+            "settings_sync_remote_changed",
+            json!({
+                "revision": envelope.revision,
+                "deviceId": envelope.device_id,
+                "updatedAt": envelope.updated_at,
+            }),
+        ) {
+            log::warn!("Failed to emit settings_sync_remote_changed: {}", error);
         }
     }
-
-    None
 }
 
-fn infer_entity_type_from_key(key: &str) -> String {
-    match key {
-        "token_id" => "tokenId".to_string(),
-        "schedule_id" => "scheduleId".to_string(),
-        "topic_id" => "topicId".to_string(),
-        "contract_id" => "contractId".to_string(),
-        "file_id" => "fileId".to_string(),
-        "account_id" => "accountId".to_string(),
-        _ => "unknown".to_string(),
+/// Publishes local session changes to the sync topic, then merges what other
+/// desktops have published since the last run. The sync lock is held
+/// throughout so manual and scheduled runs never overlap.
+async fn run_session_sync(app: &AppHandle<Wry>) -> Result<SessionSyncReport, String> {
+    // The sync topic is shared between devices, not workspaces; publishing
+    // another workspace's sessions to it would undo the isolation.
+    if app
+        .state::<Mutex<WorkspaceRegistry>>()
+        .lock()
+        .await
+        .active()
+        .id
+        != DEFAULT_WORKSPACE_ID
+    {
+        return Err("Session sync only runs in the default workspace".to_string());
     }
-}
+    let sync_state = app.state::<Mutex<SessionSyncService>>();
+    let session_state = app.state::<Mutex<SessionService>>();
+    let mut sync = sync_state.lock().await;
+    let topic_id = sync.topic_id()?;
 
-fn enrich_transaction_response(
-    value: &mut Value,
-    entity_id: &str,
-    entity_type: &str,
-    entity_name: &str,
-    transaction_id: Option<&str>,
-) {
-    if let Some(root) = value.as_object_mut() {
-        let data_entry = root
-            .entry("data".to_string())
-            .or_insert_with(|| Value::Object(serde_json::Map::new()));
-        if let Some(data) = data_entry.as_object_mut() {
-            data.insert("entityId".to_string(), Value::String(entity_id.to_string()));
-            data.insert(
-                "entityType".to_string(),
-                Value::String(entity_type.to_string()),
-            );
-            data.insert(
-                "entityName".to_string(),
-                Value::String(entity_name.to_string()),
-            );
-            if let Some(tx_id) = transaction_id {
-                data.entry("transactionId".to_string())
-                    .or_insert_with(|| Value::String(tx_id.to_string()));
+    let watermark = sync.watermark();
+    let changes = session_state
+        .lock()
+        .await
+        .changes_since(
+            watermark.updated_through.as_deref(),
+            watermark.message_rowid,
+        )
+        .await?;
+    let mut messages_published = 0;
+    if !changes.sessions.is_empty() {
+        let backend = app
+            .state::<Mutex<AgentService>>()
+            .lock()
+            .await
+            .backend()
+            .await?;
+        for envelope in sync.seal(changes.sessions)? {
+            backend
+                .submit_topic_message(&topic_id, &envelope)
+                .await
+                .map_err(|error| error.to_string())?;
+            messages_published += 1;
+        }
+    }
+    sync.mark_published(PublishWatermark {
+        updated_through: changes.updated_through,
+        message_rowid: changes.last_message_rowid,
+    })?;
+
+    let network = MirrorNetwork::try_from_str(Some(&configured_network(app)))?;
+    let remote = sync.fetch_remote(network).await?;
+    let reconciled = session_state
+        .lock()
+        .await
+        .reconcile_remote(&remote.sessions)
+        .await?;
+
+    // Nothing local was written since publishing, so move the watermark past
+    // the merged rows instead of echoing them back to the topic.
+    let mut watermark = sync.watermark();
+    if watermark.message_rowid >= reconciled.rowids.0 {
+        watermark.message_rowid = reconciled.rowids.1;
+        for session in &remote.sessions {
+            if watermark
+                .updated_through
+                .as_deref()
+                .is_none_or(|through| is_later(&session.updated_at, through))
+            {
+                watermark.updated_through = Some(session.updated_at.clone());
             }
         }
+        sync.mark_published(watermark)?;
     }
+    sync.mark_consumed(remote.consumed_through)?;
+
+    Ok(SessionSyncReport {
+        messages_published,
+        sessions_received: remote.sessions.len(),
+        reconciled,
+        status: sync.status(),
+    })
 }
 
-fn map_transaction_name_to_entity_type(name: Option<&str>) -> Option<String> {
-    let normalized = name?.to_ascii_uppercase();
-    match normalized.as_str() {
-        "TOKENCREATION" => Some("tokenId".to_string()),
-        "CRYPTOCREATEACCOUNT" => Some("accountId".to_string()),
-        "CONSENSUSCREATETOPIC" => Some("topicId".to_string()),
-        "CONTRACTCREATEINSTANCE" => Some("contractId".to_string()),
-        "SCHEDULECREATE" => Some("scheduleId".to_string()),
-        "FILECREATE" => Some("fileId".to_string()),
-        _ => None,
+async fn schedule_session_sync(handle: AppHandle<Wry>) {
+    loop {
+        power_aware_sleep(&handle, TokioDuration::from_secs(120)).await;
+
+        if !handle
+            .state::<Mutex<SessionSyncService>>()
+            .lock()
+            .await
+            .status()
+            .enabled
+        {
+            continue;
+        }
+
+        match run_session_sync(&handle).await {
+            Ok(report) => {
+                let changed = report.reconciled.sessions_created
+                    + report.reconciled.sessions_updated
+                    + report.reconciled.messages_added;
+                if changed > 0
+                    && let Err(error) = handle.emit("session_sync_updated", &report)
+                {
+                    log::warn!("Failed to emit session_sync_updated: {}", error);
+                }
+            }
+            Err(error) => log::debug!("Session sync failed: {}", error),
+        }
     }
 }
 
-#[cfg(test)]
-mod helper_tests {
-    use super::*;
-    use serde_json::json;
+fn configured_mirror_url(app: &AppHandle<Wry>) -> String {
+    mirror_url_for(&configured_network(app))
+}
 
-    #[test]
-    fn transaction_name_mapping_returns_expected_type() {
-        assert_eq!(
-            map_transaction_name_to_entity_type(Some("TOKENCREATION")),
-            Some("tokenId".to_string())
-        );
-        assert_eq!(
-            map_transaction_name_to_entity_type(Some("contractcreateinstance")),
-            Some("contractId".to_string())
+/// Mirror node REST base URL (ending in `/api/v1`) for `network`.
+fn mirror_url_for(network: &str) -> String {
+    let mirrors = HederaMirrorConfig::default();
+    if network == "mainnet" {
+        mirrors.mainnet
+    } else {
+        mirrors.testnet
+    }
+}
+
+/// Probes the mirror node and tells the frontend when reachability changed.
+/// Returns whether the network is reachable.
+async fn check_network(app: &AppHandle<Wry>) -> bool {
+    let (state, changed) = app
+        .state::<NetworkMonitor>()
+        .check(&configured_mirror_url(app))
+        .await;
+    if changed {
+        log::info!(
+            "Network is {}",
+            if state.online {
+                "reachable"
+            } else {
+                "unreachable"
+            }
         );
-        assert_eq!(map_transaction_name_to_entity_type(Some("UNKNOWN")), None);
+        if let Err(error) = app.emit(EVENT_NETWORK_STATE_CHANGED, &state) {
+            log::warn!("Failed to emit {}: {}", EVENT_NETWORK_STATE_CHANGED, error);
+        }
     }
+    state.online
+}
 
-    #[test]
-    fn derive_entity_name_prefers_context_name() {
-        let context = json!({ "name": "DemoToken" });
-        let derived = derive_entity_name(Some(&context), "tokenId", "0.0.1234");
-        assert_eq!(derived, "DemoToken");
+/// Checks connectivity quickly while offline and drains the outbox whenever
+/// the network is reachable, including right after it comes back.
+async fn monitor_network(handle: AppHandle<Wry>) {
+    loop {
+        let online = check_network(&handle).await;
+        if online {
+            dispatch_outbox(handle.clone()).await;
+        }
+
+        let base = if online { 60 } else { 15 };
+        power_aware_sleep(&handle, TokioDuration::from_secs(base)).await;
     }
+}
 
-    #[test]
-    fn derive_entity_name_falls_back_to_entity_id() {
-        let derived = derive_entity_name(None, "tokenId", "0.0.9999");
-        assert_eq!(derived, "0.0.9999");
+const ENTITY_WATCH_INTERVAL_SECS: u64 = 30;
+const EVENT_ENTITY_WATCH: &str = "entity_watch_event";
+
+/// Polls the mirror node for watched entities while online and reports
+/// balance, supply and topic message changes as `entity_watch_event`.
+async fn watch_entities(handle: AppHandle<Wry>) {
+    loop {
+        power_aware_sleep(
+            &handle,
+            TokioDuration::from_secs(ENTITY_WATCH_INTERVAL_SECS),
+        )
+        .await;
+        if !handle.state::<NetworkMonitor>().state().online {
+            continue;
+        }
+        let Some(bridge) = handle.state::<MirrorBridgeState>().bridge() else {
+            continue;
+        };
+        let watched = handle
+            .state::<Mutex<EntityService>>()
+            .lock()
+            .await
+            .list_watched()
+            .await;
+        match watched {
+            Ok(entities) => {
+                for entity in entities {
+                    poll_watched_entity(&handle, &bridge, entity).await;
+                }
+            }
+            Err(error) => log::warn!("Failed to list watched entities: {}", error),
+        }
     }
+}
 
-    #[test]
-    fn extract_entity_from_transaction_handles_token_creation() {
-        let transaction = json!({
-            "name": "TOKENCREATION",
-            "entity_id": "0.0.5678"
-        });
-        let entity = extract_entity_from_transaction(&transaction).expect("entity");
-        assert_eq!(entity.0, "0.0.5678");
-        assert_eq!(entity.1, "tokenId");
+async fn poll_watched_entity(
+    app: &AppHandle<Wry>,
+    bridge: &MirrorNodeBridge,
+    entity: EntityAssociation,
+) {
+    let Some(kind) = EntityRefreshKind::from_entity_type(entity.entity_type) else {
+        return;
+    };
+    let live_state = match fetch_entity_live_state(app, bridge, &entity).await {
+        Ok(live_state) => live_state,
+        Err(error) => {
+            log::debug!(
+                "Failed to poll watched entity {}: {}",
+                entity.entity_id,
+                error
+            );
+            return;
+        }
+    };
+    let previous = entity
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.get("liveState"));
+    let has_reading = previous
+        .and_then(|state| state.get(watched_field(kind).0))
+        .is_some();
+    let change = entity_watch_change(kind, previous, &live_state);
+    if has_reading && change.is_none() {
+        return;
     }
 
-    #[test]
-    fn enrich_transaction_response_populates_data_block() {
-        let mut value = json!({ "success": true, "data": {} });
-        enrich_transaction_response(
-            &mut value,
-            "0.0.3333",
-            "tokenId",
-            "Demo",
-            Some("0.0.1111@123"),
-        );
+    let updated = app
+        .state::<Mutex<EntityService>>()
+        .lock()
+        .await
+        .set_metadata_field(&entity.entity_id, "liveState", live_state)
+        .await;
+    match updated {
+        Ok(Some(updated)) => {
+            if let Err(error) = app.emit("entity_updated", updated) {
+                log::warn!("Failed to emit entity_updated event: {}", error);
+            }
+        }
+        Ok(None) => return,
+        Err(error) => {
+            log::warn!(
+                "Failed to store live state for {}: {}",
+                entity.entity_id,
+                error
+            );
+            return;
+        }
+    }
 
-        let data = value
-            .get("data")
-            .and_then(Value::as_object)
-            .expect("data object");
-        assert_eq!(data.get("entityId").unwrap(), "0.0.3333");
-        assert_eq!(data.get("entityType").unwrap(), "tokenId");
-        assert_eq!(data.get("entityName").unwrap(), "Demo");
-        assert_eq!(data.get("transactionId").unwrap(), "0.0.1111@123");
+    if let Some(mut change) = change {
+        change["entityId"] = json!(entity.entity_id);
+        change["entityType"] = json!(entity.entity_type);
+        change["entityName"] = json!(entity.entity_name);
+        change["detectedAt"] = json!(Utc::now().to_rfc3339());
+        if let Err(error) = app.emit(EVENT_ENTITY_WATCH, change) {
+            log::warn!("Failed to emit {}: {}", EVENT_ENTITY_WATCH, error);
+        }
     }
 }
 
-fn escape_csv(value: &str) -> String {
-    let escaped = value.replace('"', "\"\"");
-    format!("\"{}\"", escaped)
+fn emit_outbox_updated(app: &AppHandle<Wry>, items: &[OutboxItem]) {
+    if let Err(error) = app.emit(EVENT_OUTBOX_UPDATED, items) {
+        log::warn!("Failed to emit {}: {}", EVENT_OUTBOX_UPDATED, error);
+    }
 }
 
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
-struct McpConnectionResponse {
-    success: bool,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    tools: Vec<Value>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    error: Option<String>,
+async fn outbox_item_ready(app: &AppHandle<Wry>, kind: OutboxKind) -> bool {
+    match kind {
+        OutboxKind::Message => {
+            app.state::<Mutex<AgentService>>()
+                .lock()
+                .await
+                .status()
+                .await
+                .connected
+        }
+        OutboxKind::Transaction => app
+            .state::<Arc<Mutex<Option<WalletBridgeInfo>>>>()
+            .lock()
+            .await
+            .is_some(),
+    }
 }
 
-impl From<McpConnectionResult> for McpConnectionResponse {
-    fn from(result: McpConnectionResult) -> Self {
-        Self {
-            success: result.success,
-            tools: result.tools,
-            error: result.error,
-        }
+async fn dispatch_outbox_message(app: &AppHandle<Wry>, payload: Value) -> Result<Value, String> {
+    let request: AgentMessageRequest = serde_json::from_value(payload)
+        .map_err(|error| format!("Invalid queued message: {error}"))?;
+    let response = agent_send_message(
+        app.clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+        request,
+    )
+    .await?;
+    if !response.success {
+        return Err(response
+            .error
+            .unwrap_or_else(|| "The agent did not accept the message".to_string()));
     }
+    serde_json::to_value(response).map_err(|error| error.to_string())
 }
 
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
-struct McpRegistrySearchResponse {
-    servers: Vec<Value>,
-    total: usize,
-    has_more: bool,
-    categories: Vec<Value>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    metric_statuses: Option<HashMap<String, Value>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    metric_freshness: Option<HashMap<String, Value>>,
+async fn dispatch_outbox_transaction(
+    app: &AppHandle<Wry>,
+    payload: Value,
+) -> Result<Value, String> {
+    let payload: ExecuteTransactionPayload = serde_json::from_value(payload)
+        .map_err(|error| format!("Invalid queued transaction: {error}"))?;
+    let response = execute_transaction_bytes(
+        app.clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+        payload,
+    )
+    .await?;
+    match response.data {
+        Some(value) if value.get("success").and_then(Value::as_bool) == Some(true) => Ok(value),
+        data => Err(response
+            .error
+            .or_else(|| {
+                data.as_ref()
+                    .and_then(|value| value.get("error"))
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(|| "The wallet did not execute the transaction".to_string())),
+    }
 }
 
-impl From<McpRegistrySearchResult> for McpRegistrySearchResponse {
-    fn from(result: McpRegistrySearchResult) -> Self {
-        let McpRegistrySearchResult {
-            servers,
-            total,
-            has_more,
-            categories,
-            metric_statuses,
-            metric_freshness,
-        } = result;
+/// Sends the open workspace's queued outbox items, oldest first. Stops at the
+/// first item that cannot go out yet, because the agent or wallet is not
+/// ready or the network dropped again, so later items never overtake it.
+async fn dispatch_outbox(app: AppHandle<Wry>) {
+    let outbox = app.state::<Outbox>();
+    let Some(_running) = outbox.begin_dispatch() else {
+        return;
+    };
+    let workspace_id = active_workspace_id(&app).await;
 
-        Self {
-            servers,
-            total,
-            has_more,
-            categories,
-            metric_statuses: if metric_statuses.is_empty() {
-                None
-            } else {
-                Some(metric_statuses)
-            },
-            metric_freshness: if metric_freshness.is_empty() {
-                None
-            } else {
-                Some(metric_freshness)
-            },
+    loop {
+        let item = match outbox.next_ready(&workspace_id).await {
+            Ok(Some(item)) => item,
+            Ok(None) => break,
+            Err(error) => {
+                log::warn!("Failed to read the outbox: {}", error);
+                break;
+            }
+        };
+        if !outbox_item_ready(&app, item.kind).await {
+            break;
         }
-    }
-}
+        let item = match outbox.mark_dispatching(&item.id).await {
+            Ok(item) => item,
+            Err(error) => {
+                log::warn!("Failed to dispatch outbox item {}: {}", item.id, error);
+                break;
+            }
+        };
+        emit_outbox_updated(&app, std::slice::from_ref(&item));
 
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct McpSavePayload {
-    servers: Vec<Value>,
+        let outcome = match item.kind {
+            OutboxKind::Message => dispatch_outbox_message(&app, item.payload.clone()).await,
+            OutboxKind::Transaction => {
+                dispatch_outbox_transaction(&app, item.payload.clone()).await
+            }
+        };
+        let mut stop = false;
+        let changed = match outcome {
+            Ok(result) => outbox
+                .mark_sent(&item.id, &result)
+                .await
+                .map(|item| vec![item]),
+            Err(error) if !check_network(&app).await => {
+                stop = true;
+                outbox
+                    .requeue(&item.id, &error)
+                    .await
+                    .map(|item| vec![item])
+            }
+            Err(error) => {
+                log::warn!("Outbox item {} failed: {}", item.id, error);
+                outbox.mark_failed(&item.id, &error).await
+            }
+        };
+        match changed {
+            Ok(items) => emit_outbox_updated(&app, &items),
+            Err(error) => {
+                log::warn!("Failed to update outbox item {}: {}", item.id, error);
+                break;
+            }
+        }
+        if stop {
+            break;
+        }
+    }
 }
 
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct MirrorNodeSchedulePayload {
-    schedule_id: String,
-    #[serde(default)]
-    network: Option<String>,
+/// Routes the `dev_*` commands only when developer mode was on at startup;
+/// otherwise they are unknown to the IPC layer like any unregistered command.
+fn with_developer_commands<H>(
+    enabled: Arc<AtomicBool>,
+    app_handler: H,
+) -> impl Fn(tauri::ipc::Invoke<Wry>) -> bool + Send + Sync + 'static
+where
+    H: Fn(tauri::ipc::Invoke<Wry>) -> bool + Send + Sync + 'static,
+{
+    let developer_handler = tauri::generate_handler![
+        dev_bridge_rpc,
+        dev_sql_query,
+        dev_emit_event,
+        dev_migration_status,
+        dev_migration_rollback
+    ];
+    move |invoke| {
+        if enabled.load(Ordering::Relaxed) && DEVELOPER_COMMANDS.contains(&invoke.message.command())
+        {
+            developer_handler(invoke)
+        } else {
+            app_handler(invoke)
+        }
+    }
 }
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct MirrorNodeTimestampPayload {
-    timestamp: String,
+struct DevBridgeRpcPayload {
+    bridge: DeveloperBridge,
+    action: String,
     #[serde(default)]
-    network: Option<String>,
+    payload: Value,
 }
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct MirrorNodeTransactionPayload {
-    transaction_id: String,
+struct DevSqlQueryPayload {
+    database: DeveloperDatabase,
+    sql: String,
     #[serde(default)]
-    network: Option<String>,
-}
-
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct WalletSetPayload {
-    info: Option<WalletBridgeInfo>,
-}
-
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct TransactionBytesPayload {
-    transaction_bytes: String,
+    params: Vec<Value>,
 }
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct ExecuteTransactionPayload {
-    #[serde(rename = "transactionBytes")]
-    transaction_bytes: String,
+struct DevEmitEventPayload {
+    event: String,
     #[serde(default)]
-    entity_context: Option<Value>,
+    payload: Value,
 }
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct WalletHydratePayload {
-    transaction_id: String,
-    #[serde(default)]
-    network: Option<String>,
-    session_id: String,
-    #[serde(default)]
-    entity_context: Option<Value>,
-}
-
-#[derive(Debug, Default, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct EntityFiltersPayload {
-    #[serde(default)]
-    search: Option<String>,
-    #[serde(default)]
-    entity_type: Option<String>,
-    #[serde(default)]
-    session_id: Option<String>,
-    #[serde(default)]
-    date_range: Option<EntityDateRangePayload>,
-    #[serde(default)]
-    limit: Option<usize>,
-}
-
-#[derive(Clone, Debug, Default, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct EntityDateRangePayload {
-    #[serde(default)]
-    start: Option<String>,
-    #[serde(default)]
-    end: Option<String>,
-}
-
-#[derive(Debug, Deserialize)]
-struct EntityIdPayload {
-    #[serde(rename = "entityId")]
-    entity_id: String,
+struct DevMigrationRollbackPayload {
+    scope: String,
+    target_version: i64,
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct EntityBulkDeletePayload {
-    entity_ids: Vec<String>,
-}
+/// Sends a raw action to one of the Node bridges and returns its response.
+#[tauri::command]
+async fn dev_bridge_rpc(
+    app: AppHandle<Wry>,
+    payload: DevBridgeRpcPayload,
+) -> Result<CommandResponse<Value>, String> {
+    let action = payload.action.trim();
+    if action.is_empty() {
+        return Ok(CommandResponse::error(
+            "Bridge action is required".to_string(),
+        ));
+    }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct EntityRenamePayload {
-    entity_id: String,
-    new_name: String,
-}
+    let result = match payload.bridge {
+        DeveloperBridge::Agent => {
+            match app
+                .state::<Mutex<AgentService>>()
+                .lock()
+                .await
+                .backend()
+                .await
+            {
+                Ok(backend) => backend
+                    .raw_request(action, payload.payload)
+                    .await
+                    .map_err(|error| error.to_string()),
+                Err(error) => Err(error),
+            }
+        }
+        DeveloperBridge::Hcs10 => match app.state::<Hcs10Service>().bridge() {
+            Some(bridge) => bridge.raw_request(action, payload.payload).await,
+            None => Err("HCS10 bridge not available".to_string()),
+        },
+    };
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct EntityExportPayload {
-    #[serde(default)]
-    filters: Option<EntityFiltersPayload>,
-    #[serde(default)]
-    format: Option<String>,
+    match result {
+        Ok(value) => Ok(CommandResponse::ok(value)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct EntitySearchPayload {
-    query: String,
-    #[serde(default)]
-    entity_type: Option<String>,
+/// The app data directory and the active workspace's chat database.
+async fn developer_database_paths(app: &AppHandle<Wry>) -> Result<(PathBuf, PathBuf), String> {
+    let data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|error| error.to_string())?;
+    let registry = app.state::<Mutex<WorkspaceRegistry>>();
+    let registry = registry.lock().await;
+    let chat_db = registry.paths(&registry.active().id).chat_db;
+    Ok((data_dir, chat_db))
 }
 
 #[tauri::command]
-async fn credential_store(
-    state: State<'_, Mutex<CredentialManager>>,
-    service: String,
-    account: String,
-    password: String,
-) -> Result<CommandResponse<bool>, String> {
-    match state
-        .lock()
-        .await
-        .store(&service, &account, &password)
-        .await
-    {
+async fn dev_sql_query(
+    app: AppHandle<Wry>,
+    payload: DevSqlQueryPayload,
+) -> Result<CommandResponse<QueryResult>, String> {
+    let (data_dir, chat_db) = developer_database_paths(&app).await?;
+    let path = payload.database.path(&data_dir, &chat_db);
+    match query_read_only(&path, &payload.sql, &payload.params) {
         Ok(result) => Ok(CommandResponse::ok(result)),
-        Err(error) => Ok(CommandResponse::error(error.to_string())),
+        Err(error) => Ok(CommandResponse::error(error)),
     }
 }
 
+/// Emits an arbitrary event to the renderer, as if a backend service had.
 #[tauri::command]
-async fn credential_get(
-    state: State<'_, Mutex<CredentialManager>>,
-    service: String,
-    account: String,
-) -> Result<CommandResponse<Option<String>>, String> {
-    match state.lock().await.get(&service, &account).await {
-        Ok(result) => Ok(CommandResponse::ok(result)),
+async fn dev_emit_event(
+    app: AppHandle<Wry>,
+    payload: DevEmitEventPayload,
+) -> Result<CommandResponse<bool>, String> {
+    let event = payload.event.trim();
+    if event.is_empty() {
+        return Ok(CommandResponse::error("Event name is required".to_string()));
+    }
+    match app.emit(event, payload.payload) {
+        Ok(()) => Ok(CommandResponse::ok(true)),
         Err(error) => Ok(CommandResponse::error(error.to_string())),
     }
 }
 
 #[tauri::command]
-async fn credential_delete(
-    state: State<'_, Mutex<CredentialManager>>,
-    service: String,
-    account: String,
-) -> Result<CommandResponse<bool>, String> {
-    match state.lock().await.delete(&service, &account).await {
-        Ok(result) => Ok(CommandResponse::ok(result)),
-        Err(error) => Ok(CommandResponse::error(error.to_string())),
+async fn dev_migration_status(
+    app: AppHandle<Wry>,
+) -> Result<CommandResponse<Vec<MigrationStatus>>, String> {
+    let (data_dir, chat_db) = developer_database_paths(&app).await?;
+    match migration_status(&data_dir, &chat_db) {
+        Ok(statuses) => Ok(CommandResponse::ok(statuses)),
+        Err(error) => Ok(CommandResponse::error(error)),
     }
 }
 
+/// Reverts one migration scope; the app should be restarted afterwards.
 #[tauri::command]
-async fn credential_clear(
-    state: State<'_, Mutex<CredentialManager>>,
-    service: Option<String>,
-) -> Result<CommandResponse<u32>, String> {
-    let resolved_service = service.unwrap_or_else(|| "conversational-agent".to_string());
-    match state.lock().await.clear(&resolved_service).await {
-        Ok(result) => Ok(CommandResponse::ok(result)),
-        Err(error) => Ok(CommandResponse::error(error.to_string())),
+async fn dev_migration_rollback(
+    app: AppHandle<Wry>,
+    payload: DevMigrationRollbackPayload,
+) -> Result<CommandResponse<Vec<i64>>, String> {
+    let (data_dir, chat_db) = developer_database_paths(&app).await?;
+    match rollback_migrations(&data_dir, &chat_db, &payload.scope, payload.target_version) {
+        Ok(reverted) => Ok(CommandResponse::ok(reverted)),
+        Err(error) => Ok(CommandResponse::error(error)),
     }
 }
 
-#[tauri::command]
-async fn mirror_node_get_schedule_info(
-    state: State<'_, MirrorBridgeState>,
-    payload: MirrorNodeSchedulePayload,
-) -> Result<CommandResponse<Value>, String> {
-    let MirrorNodeSchedulePayload {
-        schedule_id,
-        network,
-    } = payload;
+fn main() {
+    let _ = dotenvy::dotenv();
+    let context = tauri::generate_context!();
 
-    let bridge = match state.bridge() {
-        Some(bridge) => bridge,
-        None => {
-            return Ok(CommandResponse::error(
-                "Mirror node bridge is not available".to_string(),
-            ));
-        }
-    };
+    logging::init(LevelFilter::Info);
+    let developer_mode = Arc::new(AtomicBool::new(false));
+    let developer_mode_setup = developer_mode.clone();
+    let rate_limiter = Arc::new(ProviderRateLimiter::new());
 
-    let network = MirrorNetwork::try_from_str(network.as_deref())?;
-    let result = bridge.get_schedule_info(&schedule_id, network).await?;
-    Ok(CommandResponse::ok(result.unwrap_or(Value::Null)))
-}
+    tauri::Builder::default()
+        .plugin(tauri_plugin_autostart::Builder::new().build())
+        .plugin(tauri_plugin_dialog::init())
+        .manage(Mutex::new(
+            ConnectionService::new()
+                .expect("Failed to initialize ConnectionService")
+                .with_rate_limiter(rate_limiter.clone()),
+        ))
+        .manage(TokenHolderService::new().expect("Failed to initialize TokenHolderService"))
+        .manage(Mutex::new(
+            AgentService::new().with_rate_limiter(rate_limiter.clone()),
+        ))
+        .manage(rate_limiter)
+        .manage(WalletBridgeState::default())
+        .manage(ActiveBrowserManager::new(None))
+        .manage(PowerMonitor::new(detect_on_battery().unwrap_or(false)))
+        .manage(NetworkMonitor::new())
+        .manage(LocalRelay::new())
+        .manage(IngestLatency::new())
+        .on_window_event(|window, event| {
+            if let WindowEvent::Focused(focused) = event
+                && window.state::<PowerMonitor>().set_window_focused(*focused)
+            {
+                emit_power_state(window.app_handle());
+            }
+        })
+        .setup(move |app| {
+            if app.try_state::<ActiveBrowserManager>().is_none() {
+                log::error!("ActiveBrowserManager state not available during setup");
+            } else {
+                log::info!("ActiveBrowserManager state successfully registered");
+            }
+            let app_handle = app.handle();
+            let config_dir = app_handle
+                .path()
+                .app_data_dir()
+                .map_err(|error| error.to_string())?;
 
-#[tauri::command]
-async fn mirror_node_get_scheduled_transaction_status(
-    state: State<'_, MirrorBridgeState>,
-    payload: MirrorNodeSchedulePayload,
-) -> Result<CommandResponse<Value>, String> {
-    let MirrorNodeSchedulePayload {
-        schedule_id,
-        network,
-    } = payload;
+            if !config_dir.exists() {
+                fs::create_dir_all(&config_dir)?;
+            }
 
-    let bridge = match state.bridge() {
-        Some(bridge) => bridge,
-        None => {
-            return Ok(CommandResponse::error(
-                "Mirror node bridge is not available".to_string(),
-            ));
-        }
-    };
+            let master_key = master_key::load(
+                std::env::var("MASTER_PASSWORD").ok(),
+                &master_key::Keychain,
+                &config_dir,
+            )?;
+            let master_password = master_key.password;
+            app.manage(ConfigState::new(master_password.clone()));
+
+            match data_backup::apply_pending_restore(&config_dir) {
+                Ok(restored) if !restored.is_empty() => {
+                    log::info!("Restored {} from backup", restored.join(", "));
+                }
+                Ok(_) => {}
+                Err(error) => log::error!("Failed to apply pending backup restore: {}", error),
+            }
 
-    let network = MirrorNetwork::try_from_str(network.as_deref())?;
-    let status = bridge
-        .get_scheduled_transaction_status(&schedule_id, network)
-        .await?;
-    Ok(CommandResponse::ok(status))
-}
+            if master_key.source == MasterKeySource::Created {
+                log::info!("Created a master key in the OS keychain");
+                tauri::async_runtime::block_on(master_key::migrate_legacy_data(
+                    &config_dir,
+                    &master_password,
+                ));
+            }
 
-#[tauri::command]
-async fn mirror_node_get_transaction_by_timestamp(
-    state: State<'_, MirrorBridgeState>,
-    payload: MirrorNodeTimestampPayload,
-) -> Result<CommandResponse<Value>, String> {
-    let MirrorNodeTimestampPayload { timestamp, network } = payload;
+            if load_config(app_handle.clone(), app.state::<ConfigState>())
+                .is_ok_and(|response| response.config.advanced.developer_mode)
+            {
+                log::warn!("Developer mode is enabled; dev_* commands are available");
+                developer_mode_setup.store(true, Ordering::Relaxed);
+            }
 
-    let bridge = match state.bridge() {
-        Some(bridge) => bridge,
-        None => {
-            return Ok(CommandResponse::error(
-                "Mirror node bridge is not available".to_string(),
-            ));
-        }
-    };
+            let workspaces = WorkspaceRegistry::new(&config_dir);
+            let workspace_paths = workspaces.paths(&workspaces.active().id);
+            log::info!("Opening workspace {}", workspaces.active().name);
 
-    let network = MirrorNetwork::try_from_str(network.as_deref())?;
-    let transactions = bridge
-        .get_transaction_by_timestamp(&timestamp, network)
-        .await?;
-    Ok(CommandResponse::ok(Value::Array(transactions)))
-}
+            let credential_path = config_dir.join("credentials.dat");
+            let session_db_path = workspace_paths.chat_db;
+            let mcp_path = workspace_paths.mcp_servers;
+            let registry_db_path = config_dir.join("mcp-registry.sqlite");
 
-#[tauri::command]
-async fn mirror_node_get_transaction(
-    state: State<'_, MirrorBridgeState>,
-    payload: MirrorNodeTransactionPayload,
-) -> Result<CommandResponse<Value>, String> {
-    let MirrorNodeTransactionPayload {
-        transaction_id,
-        network,
-    } = payload;
+            let recovery_report = storage_recovery::check_databases(
+                &config_dir,
+                &[
+                    config_dir.join("chat.sqlite"),
+                    session_db_path.clone(),
+                    registry_db_path.clone(),
+                ],
+            );
+            if recovery_report.needs_attention {
+                log::error!("Corrupt databases were found at startup; see storage_recovery_report");
+            }
+            app.manage(recovery_report);
 
-    let bridge = match state.bridge() {
-        Some(bridge) => bridge,
-        None => {
-            return Ok(CommandResponse::error(
-                "Mirror node bridge is not available".to_string(),
-            ));
-        }
-    };
+            let mut credential_manager =
+                CredentialManager::new(credential_path, master_password.clone());
+            if let Some(keyring) = OsKeyring::detect() {
+                credential_manager = credential_manager.with_keyring(Arc::new(keyring));
+                match tauri::async_runtime::block_on(credential_manager.migrate_to_keyring()) {
+                    Ok(0) => {}
+                    Ok(count) => log::info!("Moved {} credentials to the OS keyring", count),
+                    Err(error) => {
+                        log::error!("Failed to move credentials to the OS keyring: {}", error)
+                    }
+                }
+            }
+            let session_service = SessionService::from_path(&session_db_path)?;
+            let entity_service = EntityService::from_path(&session_db_path)?;
+            let hcs10_inbox = Hcs10Inbox::from_path(&session_db_path)?;
+            let (sampling_sender, sampling_receiver) = tokio::sync::mpsc::unbounded_channel();
+            let (tool_list_sender, tool_list_receiver) = tokio::sync::mpsc::unbounded_channel();
+            let (progress_sender, progress_receiver) = tokio::sync::mpsc::unbounded_channel();
+            let mcp_tool_calls = Arc::new(McpToolCalls::new(progress_sender));
+            let mcp_process_groups = Arc::new(ProcessGroups::default());
+            let mcp_service = McpService::new(mcp_path, Some(registry_db_path))
+                .with_credentials(credential_manager.clone())
+                .with_sampling(sampling_sender.clone())
+                .with_tool_list_changes(tool_list_sender.clone())
+                .with_tool_calls(mcp_tool_calls.clone())
+                .with_process_groups(mcp_process_groups.clone());
+            let settings_sync_service =
+                SettingsSyncService::new(&config_dir, master_password.clone());
+            let session_sync_service =
+                SessionSyncService::new(&config_dir, master_password.clone());
 
-    let network = MirrorNetwork::try_from_str(network.as_deref())?;
-    let transaction = bridge.get_transaction(&transaction_id, network).await?;
-    Ok(CommandResponse::ok(transaction.unwrap_or(Value::Null)))
-}
+            app.manage(Mutex::new(credential_manager));
+            app.manage(OsAuthGate::default());
+            app.manage(Mutex::new(session_service));
+            app.manage(Mutex::new(entity_service));
+            app.manage(Mutex::new(mcp_service));
+            app.manage(Mutex::new(settings_sync_service));
+            app.manage(Mutex::new(session_sync_service));
+            app.manage(Mutex::new(workspaces));
+            app.manage(Hcs1Store::new(config_dir.join("inscriptions"))?);
+            app.manage(ContactBook::from_path(&session_db_path)?);
+            app.manage(AccountHistoryService::new()?);
+            app.manage(Outbox::from_path(&config_dir.join("outbox.sqlite"))?);
+            app.manage(DocumentStore::from_path(
+                &config_dir.join("documents.sqlite"),
+            )?);
+            app.manage(ConfigHistory::from_path(
+                &config_dir.join("config-history.sqlite"),
+            )?);
+            app.manage(Mutex::new(BrowserScriptPermissions::load(
+                config_dir.join("browser-script-permissions.json"),
+            )));
+            app.manage(sampling_sender);
+            app.manage(tool_list_sender);
+            app.manage(mcp_tool_calls);
+            app.manage(mcp_process_groups);
+            app.manage(Mutex::new(SamplingApprovals::new()));
+            let hcs10_bridge = resolve_hcs10_bridge_script(&app_handle).and_then(|script| {
+                match tauri::async_runtime::block_on(Hcs10Bridge::spawn(script.clone())) {
+                    Ok(bridge) => Some(Arc::new(bridge)),
+                    Err(error) => {
+                        log::warn!(
+                            "Failed to spawn HCS10 bridge at {}: {}",
+                            script.display(),
+                            error
+                        );
+                        None
+                    }
+                }
+            });
+            let hcs10_service = Hcs10Service::new(hcs10_bridge, config_dir.join("hcs10-states"));
+            app.manage(hcs10_service);
+            app.manage(hcs10_inbox);
+            let mirror_bridge =
+                resolve_mirror_bridge_script(&app_handle).map(MirrorNodeBridge::new);
+            app.manage(MirrorBridgeState::new(mirror_bridge));
+            let transaction_bridge =
+                resolve_transaction_parser_script(&app_handle).map(TransactionParserBridge::new);
+            app.manage(TransactionParserState::new(transaction_bridge));
+            app.manage(Arc::new(Mutex::new(None::<WalletBridgeInfo>)));
 
-#[tauri::command]
-async fn mirror_node_get_token_info(
-    state: State<'_, MirrorBridgeState>,
-    payload: MirrorNodeTransactionPayload,
-) -> Result<CommandResponse<Value>, String> {
-    let MirrorNodeTransactionPayload {
-        transaction_id,
-        network,
-    } = payload;
+            let init_handle = app.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                initialize_mcp_service(init_handle.clone()).await;
+            });
 
-    let bridge = match state.bridge() {
-        Some(bridge) => bridge,
-        None => {
-            return Ok(CommandResponse::error(
-                "Mirror node bridge is not available".to_string(),
-            ));
-        }
-    };
+            let sampling_handle = app.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                serve_mcp_sampling(sampling_handle, sampling_receiver).await;
+            });
 
-    let network = MirrorNetwork::try_from_str(network.as_deref())?;
-    let info = bridge.get_token_info(&transaction_id, network).await?;
-    Ok(CommandResponse::ok(info.unwrap_or(Value::Null)))
-}
+            let tool_list_handle = app.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                serve_mcp_tool_list_changes(tool_list_handle, tool_list_receiver).await;
+            });
 
-#[tauri::command]
-async fn transaction_parser_validate(
-    state: State<'_, TransactionParserState>,
-    payload: TransactionBytesPayload,
-) -> Result<CommandResponse<Value>, String> {
-    let bridge = match state.bridge() {
-        Some(bridge) => bridge,
-        None => {
-            return Ok(CommandResponse::error(
-                "Transaction parser bridge is not available".to_string(),
-            ));
-        }
-    };
+            let progress_handle = app.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                serve_mcp_tool_progress(progress_handle, progress_receiver).await;
+            });
 
-    let result = bridge.validate(&payload.transaction_bytes).await?;
-    Ok(CommandResponse::ok(result))
-}
+            let sync_handle = app.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                schedule_mcp_background_sync(sync_handle.clone()).await;
+            });
 
-#[tauri::command]
-async fn transaction_parser_parse(
-    state: State<'_, TransactionParserState>,
-    payload: TransactionBytesPayload,
-) -> Result<CommandResponse<Value>, String> {
-    let bridge = match state.bridge() {
-        Some(bridge) => bridge,
-        None => {
-            return Ok(CommandResponse::error(
-                "Transaction parser bridge is not available".to_string(),
-            ));
-        }
-    };
+            let compaction_handle = app.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                schedule_mcp_memory_compaction(compaction_handle).await;
+            });
 
-    let result = bridge.parse(&payload.transaction_bytes).await?;
-    Ok(CommandResponse::ok(result))
-}
+            let supervisor_handle = app.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                supervise_mcp_connections(supervisor_handle).await;
+            });
 
-#[tauri::command(rename = "execute-transaction-bytes")]
-async fn execute_transaction_bytes(
-    app: AppHandle<Wry>,
-    wallet_bridge: State<'_, WalletBridgeState>,
-    wallet_info: State<'_, Arc<Mutex<Option<WalletBridgeInfo>>>>,
-    entity_state: State<'_, Mutex<EntityService>>,
-    agent_state: State<'_, Mutex<AgentService>>,
-    mirror_state: State<'_, MirrorBridgeState>,
-    payload: ExecuteTransactionPayload,
-) -> Result<CommandResponse<Value>, String> {
-    wallet_bridge.set_app_handle(&app);
+            let health_handle = app.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                monitor_mcp_health(health_handle).await;
+            });
 
-    let network = {
-        let guard = wallet_info.lock().await;
-        match guard.as_ref() {
-            Some(info) => info.network.clone(),
-            None => {
-                return Ok(CommandResponse::error("No wallet connected".to_string()));
-            }
-        }
-    };
+            let bridge_monitor_handle = app.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                monitor_bridges(bridge_monitor_handle).await;
+            });
 
-    let ExecuteTransactionPayload {
-        transaction_bytes,
-        entity_context,
-    } = payload;
+            let retention_handle = app.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                schedule_message_retention(retention_handle).await;
+            });
 
-    let network_clone = network.clone();
+            let hcs10_states_handle = app.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                schedule_hcs10_state_pruning(hcs10_states_handle).await;
+            });
 
-    match wallet_execute_bytes(wallet_bridge.inner(), transaction_bytes, network).await {
-        Ok(mut value) => {
-            if value
-                .get("success")
-                .and_then(Value::as_bool)
-                .unwrap_or(false)
-            {
-                let transaction_id = extract_transaction_id(&value);
+            let auto_lock_handle = app.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                schedule_credential_auto_lock(auto_lock_handle).await;
+            });
 
-                let mut derived_entity = extract_entity_from_response(&value);
+            let config_watch_handle = app.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                watch_config_file(config_watch_handle).await;
+            });
 
-                if derived_entity.is_none() {
-                    if let Some(ref tx_id) = transaction_id {
-                        log::debug!(
-                            "execute_transaction_bytes: wallet response missing entity id; polling mirror for transaction {}",
-                            tx_id
-                        );
-                        if let Some(entity) =
-                            hydrate_entity_via_mirror(&mirror_state, tx_id, &network_clone).await
-                        {
-                            derived_entity = Some(entity);
-                        }
-                    }
-                }
+            let settings_sync_handle = app.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                watch_settings_sync_folder(settings_sync_handle).await;
+            });
 
-                if let Some((entity_id, entity_type)) = derived_entity {
-                    let entity_name =
-                        derive_entity_name(entity_context.as_ref(), &entity_type, &entity_id);
-                    let metadata_value = json!({
-                        "entityContext": entity_context.clone().unwrap_or(Value::Null),
-                        "source": "executeTransactionBytes",
-                        "recordedAt": Utc::now().to_rfc3339(),
-                        "transactionId": transaction_id,
-                        "network": network_clone,
-                    });
+            let inbox_handle = app.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                watch_hcs10_connection_requests(inbox_handle).await;
+            });
 
-                    let _ = persist_entity_association(
-                        &app,
-                        &entity_state,
-                        &agent_state,
-                        &entity_id,
-                        &entity_type,
-                        &entity_name,
-                        transaction_id.as_ref(),
-                        None,
-                        &metadata_value,
-                    )
-                    .await;
+            let power_handle = app.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                monitor_power_source(power_handle).await;
+            });
 
-                    enrich_transaction_response(
-                        &mut value,
-                        &entity_id,
-                        &entity_type,
-                        &entity_name,
-                        transaction_id.as_deref(),
-                    );
-                } else if let Some(ref tx_id) = transaction_id {
-                    log::info!(
-                        "execute_transaction_bytes: transaction {} completed without detectable entity metadata",
-                        tx_id
-                    );
-                } else {
-                    log::info!(
-                        "execute_transaction_bytes: wallet execution completed without transaction id"
-                    );
-                }
-            }
+            let session_sync_handle = app.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                schedule_session_sync(session_sync_handle).await;
+            });
 
-            Ok(CommandResponse::ok(value))
-        }
-        Err(error) => Ok(CommandResponse::error(error)),
-    }
-}
-
-#[tauri::command]
-async fn wallet_hydrate_entity(
-    app: AppHandle<Wry>,
-    entity_state: State<'_, Mutex<EntityService>>,
-    agent_state: State<'_, Mutex<AgentService>>,
-    mirror_state: State<'_, MirrorBridgeState>,
-    payload: WalletHydratePayload,
-) -> Result<CommandResponse<Value>, String> {
-    let network = payload.network.as_deref().unwrap_or("testnet");
-    let session_id_trimmed = payload.session_id.trim();
-    if session_id_trimmed.is_empty() {
-        return Ok(CommandResponse::error(
-            "Session ID is required to hydrate entity data".to_string(),
-        ));
-    }
-
-    log::debug!(
-        "wallet_hydrate_entity: hydrating transaction {} on network {}",
-        payload.transaction_id,
-        network
-    );
-
-    match hydrate_entity_via_mirror(&mirror_state, &payload.transaction_id, network).await {
-        Some((entity_id, entity_type)) => {
-            let entity_name =
-                derive_entity_name(payload.entity_context.as_ref(), &entity_type, &entity_id);
-            let metadata_value = json!({
-                "entityContext": payload.entity_context.unwrap_or(Value::Null),
-                "source": "walletHydrateEntity",
-                "recordedAt": Utc::now().to_rfc3339(),
-                "transactionId": payload.transaction_id,
-                "network": network,
-                "sessionId": session_id_trimmed,
+            let network_handle = app.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                monitor_network(network_handle).await;
             });
 
-            let stored = persist_entity_association(
-                &app,
-                &entity_state,
-                &agent_state,
-                &entity_id,
-                &entity_type,
-                &entity_name,
-                Some(&payload.transaction_id),
-                Some(session_id_trimmed),
-                &metadata_value,
-            )
-            .await;
+            let watch_handle = app.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                watch_entities(watch_handle).await;
+            });
 
-            match stored {
-                Some(entity) => Ok(CommandResponse::ok(json!({ "entity": entity }))),
-                None => Ok(CommandResponse::error(
-                    "Failed to persist entity association".to_string(),
-                )),
+            match resolve_bridge_script(&app_handle) {
+                Some(bridge_path) => {
+                    log::info!(
+                        "Configuring conversational agent bridge: {}",
+                        bridge_path.display()
+                    );
+                    let agent_service = app.state::<Mutex<AgentService>>();
+                    tauri::async_runtime::block_on(async {
+                        let guard = agent_service.lock().await;
+                        guard.set_bridge_script(Some(bridge_path.clone())).await;
+                        log::info!(
+                            "Conversational agent bridge registered: {}",
+                            bridge_path.display()
+                        );
+                    });
+                }
+                None => {
+                    log::warn!(
+                        "Conversational agent bridge script not found; falling back to echo backend"
+                    );
+                }
             }
-        }
-        None => {
-            log::warn!(
-                "wallet_hydrate_entity: mirror did not return entity metadata for {}",
-                payload.transaction_id
-            );
-            Ok(CommandResponse::error(
-                "Entity metadata is not available yet".to_string(),
-            ))
-        }
-    }
+            Ok(())
+        })
+        .invoke_handler(with_developer_commands(
+            developer_mode,
+            tauri::generate_handler![
+                version_info,
+                load_config,
+                save_config,
+                set_theme,
+                set_auto_start,
+                set_log_level,
+                plugin_enable,
+                plugin_disable,
+                config::get_environment_config,
+                config::get_effective_config,
+                startup_get_plan,
+                config_get_history,
+                config_revert_to,
+                config_import_env,
+                config_rotate_keys,
+                config_validate,
+                config_reset,
+                config_set_active_account,
+                legal_record_acceptance,
+                power_get_state,
+                data_backup_create,
+                data_backup_restore,
+                storage_recovery_report,
+                diagnostics_bundle,
+                diagnostics_run_bridge_selftest,
+                settings_sync_get_status,
+                settings_sync_enable,
+                settings_sync_disable,
+                settings_sync_push,
+                settings_sync_pull,
+                session_sync_get_status,
+                session_sync_enable,
+                session_sync_disable,
+                session_sync_now,
+                workspace_list,
+                workspace_create,
+                workspace_switch,
+                network_get_status,
+                outbox_enqueue,
+                outbox_list,
+                outbox_cancel,
+                connection_test_hedera,
+                connection_test_openai,
+                connection_test_anthropic,
+                connection_test_openrouter,
+                connection_test_gemini,
+                provider_get_rate_status,
+                relay_get_endpoint,
+                relay_tool_settings,
+                relay_health,
+                relay_start_local,
+                relay_stop_local,
+                agent_initialize,
+                agent_status,
+                agent_list_backends,
+                agent_select_backend,
+                agent_disconnect,
+                agent_send_message,
+                agent_update_session_context,
+                chat_create_session,
+                chat_load_session,
+                chat_save_session,
+                chat_delete_session,
+                chat_bulk_delete_sessions,
+                chat_session_stats,
+                usage_get_summary,
+                chat_mark_read,
+                chat_set_session_llm,
+                chat_load_all_sessions,
+                chat_save_message,
+                chat_load_session_messages,
+                chat_load_tool_calls,
+                rag_index_path,
+                rag_list_documents,
+                rag_remove_document,
+                chat_update_session_context,
+                chat_update_form_state,
+                chat_update_message_metadata,
+                chat_fork_session,
+                chat_prune_now,
+                chat_create_thread,
+                chat_list_threads,
+                chat_add_session_tag,
+                chat_remove_session_tag,
+                chat_pin_message,
+                chat_list_pinned,
+                credential_store,
+                credential_get,
+                credential_delete,
+                credential_clear,
+                credential_list,
+                credential_store_totp,
+                credential_generate_totp,
+                credential_lock,
+                credential_unlock,
+                credential_is_locked,
+                credential_verify_master_password,
+                credential_access_log,
+                mirror_node_get_schedule_info,
+                mirror_node_get_scheduled_transaction_status,
+                mirror_node_get_transaction_by_timestamp,
+                mirror_node_get_transaction,
+                mirror_node_get_token_info,
+                mirror_node_get_nft_info,
+                token_get_holders,
+                mirror_node_get_account_transactions,
+                transaction_annotate,
+                contact_upsert,
+                contact_list,
+                contact_delete,
+                token_get_distribution_summary,
+                transaction_parser_validate,
+                transaction_parser_parse,
+                execute_transaction_bytes,
+                wallet_hydrate_entity,
+                wallet_set_current,
+                wallet_status,
+                entity_get_all,
+                entity_delete,
+                entity_restore,
+                entity_purge,
+                entity_bulk_purge,
+                entity_bulk_delete,
+                entity_rename,
+                entity_update_notes,
+                entity_refresh,
+                entity_set_watched,
+                entity_set_favorite,
+                entity_stats,
+                entity_list_watched,
+                entity_get_audit_log,
+                entity_export,
+                entity_get_by_id,
+                entity_validate_id,
+                entity_search,
+                browser_attach,
+                browser_detach,
+                browser_navigate,
+                browser_reload,
+                browser_go_back,
+                browser_go_forward,
+                browser_set_bounds,
+                browser_capture_context,
+                browser_set_layout,
+                browser_get_state,
+                browser_execute_js,
+                browser_resolve_script_approval,
+                browser_list_script_permissions,
+                browser_forget_script_permission,
+                browser_open_devtools,
+                browser_open_external,
+                mcp_load_servers,
+                mcp_save_servers,
+                mcp_test_connection,
+                mcp_connect_server,
+                mcp_disconnect_server,
+                mcp_call_tool,
+                mcp_cancel_tool_call,
+                mcp_get_health,
+                mcp_oauth_start,
+                mcp_oauth_sign_out,
+                mcp_check_updates,
+                mcp_upgrade_server,
+                mcp_import_config,
+                mcp_export_config,
+                mcp_list_groups,
+                mcp_save_group,
+                mcp_delete_group,
+                mcp_activate_group,
+                mcp_deactivate_group,
+                mcp_get_server_tools,
+                mcp_get_tool_catalog,
+                mcp_refresh_server_tools,
+                mcp_search_registry,
+                mcp_get_registry_server_details,
+                mcp_install_from_registry,
+                mcp_resolve_install_variables,
+                mcp_resolve_sampling,
+                mcp_clear_registry_cache,
+                mcp_get_cache_stats,
+                mcp_get_memory_stats,
+                mcp_trigger_background_sync,
+                mcp_enrich_metrics,
+                hcs10_register_profile,
+                hcs10_validate_profile,
+                hcs10_get_profiles,
+                hcs10_get_registration_progress,
+                hcs10_is_registration_in_progress,
+                hcs10_cancel_registration,
+                hcs10_clear_all_states,
+                hcs10_list_states,
+                hcs10_delete_state,
+                hcs10_retrieve_profile,
+                hcs10_list_connection_requests,
+                hcs10_accept_connection_request,
+                hcs10_reject_connection_request
+            ],
+        ))
+        .build(context)
+        .expect("failed to build Tauri application")
+        .run(|app, event| {
+            if let RunEvent::Exit = event {
+                tauri::async_runtime::block_on(shutdown_mcp_servers(app.clone()));
+            }
+        });
 }
 
-#[tauri::command]
-async fn wallet_set_current(
-    state: State<'_, Arc<Mutex<Option<WalletBridgeInfo>>>>,
-    payload: WalletSetPayload,
-) -> Result<CommandResponse<bool>, String> {
-    if let Some(ref info) = payload.info {
-        match info.network.as_str() {
-            "mainnet" | "testnet" => {}
-            other => {
-                return Ok(CommandResponse::error(format!(
-                    "Unsupported wallet network: {other}"
-                )));
-            }
+const MCP_SHUTDOWN_TIMEOUT: TokioDuration = TokioDuration::from_secs(10);
+
+/// Closes the MCP sessions before the process exits, when the service is
+/// not busy, then kills the process groups custom servers left, so none of
+/// them or anything they spawned outlive the app.
+async fn shutdown_mcp_servers(app: AppHandle<Wry>) {
+    if let Ok(service) = app.state::<Mutex<McpService>>().try_lock() {
+        if tokio::time::timeout(MCP_SHUTDOWN_TIMEOUT, service.shutdown())
+            .await
+            .is_err()
+        {
+            log::warn!("Timed out shutting down MCP servers on exit");
         }
+    } else {
+        log::warn!("MCP service busy on exit, killing its servers");
     }
-
-    let arc = Arc::clone(&*state);
-    let mut guard = arc.lock().await;
-    *guard = payload.info;
-    Ok(CommandResponse::ok(true))
-}
-
-#[tauri::command]
-async fn wallet_status(
-    bridge: State<'_, WalletBridgeState>,
-    info: State<'_, Arc<Mutex<Option<WalletBridgeInfo>>>>,
-) -> Result<CommandResponse<Value>, String> {
-    let snapshot = wallet_status_json(bridge.inner(), &info).await;
-    Ok(CommandResponse::ok(snapshot))
+    app.state::<Arc<ProcessGroups>>().kill_all();
 }
 
-#[tauri::command]
-async fn entity_get_all(
-    state: State<'_, Mutex<EntityService>>,
-    payload: Option<EntityFiltersPayload>,
-) -> Result<CommandResponse<Vec<EntityAssociation>>, String> {
-    let filters = payload.unwrap_or_default();
-
-    let mut entities = {
-        let service = state.lock().await;
-        service
-            .list_entities(
-                filters.entity_type.clone(),
-                filters.session_id.clone(),
-                filters.limit,
-            )
-            .await?
-    };
-
-    filter_entities_in_place(
-        &mut entities,
-        filters.search.as_deref(),
-        filters.date_range.as_ref(),
-    );
-
-    Ok(CommandResponse::ok(entities))
+#[derive(Serialize)]
+struct CommandResponse<T> {
+    success: bool,
+    data: Option<T>,
+    error: Option<String>,
 }
 
-#[tauri::command]
-async fn entity_delete(
-    app: AppHandle<Wry>,
-    state: State<'_, Mutex<EntityService>>,
-    payload: EntityIdPayload,
-) -> Result<CommandResponse<Value>, String> {
-    let deleted = {
-        let service = state.lock().await;
-        service.deactivate_entity(&payload.entity_id).await?
-    };
-
-    if !deleted {
-        return Ok(CommandResponse::error(format!(
-            "Entity {} not found",
-            payload.entity_id
-        )));
+impl<T> CommandResponse<T> {
+    fn ok(data: T) -> Self {
+        Self {
+            success: true,
+            data: Some(data),
+            error: None,
+        }
     }
 
-    if let Err(error) = app.emit("entity_deleted", payload.entity_id.clone()) {
-        log::warn!("Failed to emit entity_deleted event: {}", error);
+    fn error(message: String) -> Self {
+        Self {
+            success: false,
+            data: None,
+            error: Some(message),
+        }
     }
-
-    Ok(CommandResponse::ok(json!({})))
 }
 
-#[tauri::command]
-async fn entity_bulk_delete(
-    app: AppHandle<Wry>,
-    state: State<'_, Mutex<EntityService>>,
-    payload: EntityBulkDeletePayload,
-) -> Result<CommandResponse<Value>, String> {
-    if payload.entity_ids.is_empty() {
-        return Ok(CommandResponse::error("No entity IDs provided".to_string()));
+fn filter_entities_in_place(
+    entities: &mut Vec<EntityAssociation>,
+    search: Option<&str>,
+    date_range: Option<&EntityDateRangePayload>,
+) {
+    if let Some(search) = search {
+        let search_lower = search.to_lowercase();
+        entities.retain(|entity| {
+            entity.entity_name.to_lowercase().contains(&search_lower)
+                || entity.entity_id.to_lowercase().contains(&search_lower)
+                || entity
+                    .transaction_id
+                    .as_ref()
+                    .map(|value| value.to_lowercase().contains(&search_lower))
+                    .unwrap_or(false)
+        });
     }
 
-    let mut successful: Vec<String> = Vec::new();
-    let mut failed: Vec<Value> = Vec::new();
+    if let Some(range) = date_range {
+        let start = range
+            .start
+            .as_deref()
+            .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+            .map(|dt| dt.with_timezone(&Utc));
+        let end = range
+            .end
+            .as_deref()
+            .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+            .map(|dt| dt.with_timezone(&Utc));
 
-    {
-        let service = state.lock().await;
-        for entity_id in &payload.entity_ids {
-            match service.deactivate_entity(entity_id).await {
-                Ok(true) => {
-                    successful.push(entity_id.clone());
-                    if let Err(error) = app.emit("entity_deleted", entity_id.clone()) {
-                        log::warn!("Failed to emit entity_deleted event: {}", error);
+        entities.retain(
+            |entity| match DateTime::parse_from_rfc3339(&entity.created_at) {
+                Ok(parsed) => {
+                    let created = parsed.with_timezone(&Utc);
+                    if let Some(start) = start {
+                        if created < start {
+                            return false;
+                        }
+                    }
+                    if let Some(end) = end {
+                        if created > end {
+                            return false;
+                        }
                     }
+                    true
                 }
-                Ok(false) => failed.push(json!({
-                    "entityId": entity_id,
-                    "error": "Entity not found"
-                })),
-                Err(error) => failed.push(json!({
-                    "entityId": entity_id,
-                    "error": error
-                })),
-            }
-        }
+                Err(_) => true,
+            },
+        );
     }
-
-    Ok(CommandResponse::ok(json!({
-        "successful": successful,
-        "failed": failed,
-        "totalRequested": payload.entity_ids.len()
-    })))
 }
 
-#[tauri::command]
-async fn entity_rename(
-    app: AppHandle<Wry>,
-    state: State<'_, Mutex<EntityService>>,
-    payload: EntityRenamePayload,
-) -> Result<CommandResponse<EntityAssociation>, String> {
-    let trimmed = payload.new_name.trim();
-    if trimmed.is_empty() {
-        return Ok(CommandResponse::error(
-            "Entity name cannot be empty".to_string(),
-        ));
+fn derive_entity_name(context: Option<&Value>, entity_type: &str, entity_id: &str) -> String {
+    if let Some(name_value) = context
+        .and_then(|value| value.get("name"))
+        .and_then(Value::as_str)
+    {
+        let trimmed = name_value.trim();
+        if !trimmed.is_empty() {
+            return trimmed.to_string();
+        }
     }
 
-    let renamed = {
-        let service = state.lock().await;
-        service.rename_entity(&payload.entity_id, trimmed).await?
-    };
-
-    let Some(entity) = renamed else {
-        return Ok(CommandResponse::error("Entity not found".to_string()));
-    };
+    if let Some(description_value) = context
+        .and_then(|value| value.get("description"))
+        .and_then(Value::as_str)
+    {
+        let tokens: Vec<&str> = description_value.split_whitespace().collect();
+        for window in tokens.windows(2) {
+            let indicator = window[0]
+                .trim_matches(|c: char| !c.is_ascii_alphabetic())
+                .to_ascii_lowercase();
+            if is_entity_indicator(&indicator) {
+                let candidate = window[1]
+                    .trim_matches(|c: char| !is_allowed_entity_char(c))
+                    .to_string();
+                if !candidate.trim().is_empty() {
+                    return candidate.trim().to_string();
+                }
+            }
+        }
+    }
 
-    if let Err(error) = app.emit("entity_updated", entity.clone()) {
-        log::warn!("Failed to emit entity_updated event: {}", error);
+    if !entity_id.trim().is_empty() {
+        return entity_id.to_string();
     }
 
-    Ok(CommandResponse::ok(entity))
+    format!("{entity_type}_{}", Utc::now().timestamp())
 }
 
-#[tauri::command]
-async fn entity_export(
-    state: State<'_, Mutex<EntityService>>,
-    payload: EntityExportPayload,
-) -> Result<CommandResponse<Value>, String> {
-    let filters = payload.filters.unwrap_or_default();
-    let format = payload.format.unwrap_or_else(|| "json".to_string());
+fn is_entity_indicator(value: &str) -> bool {
+    matches!(
+        value,
+        "token" | "account" | "topic" | "schedule" | "contract"
+    )
+}
 
-    let mut entities = {
-        let service = state.lock().await;
+fn is_allowed_entity_char(character: char) -> bool {
+    character.is_ascii_alphanumeric() || character == '_' || character == '-'
+}
+
+async fn persist_entity_association(
+    app: &AppHandle<Wry>,
+    entity_state: &State<'_, Mutex<EntityService>>,
+    agent_state: &State<'_, Mutex<AgentService>>,
+    entity_id: &str,
+    entity_type: &str,
+    entity_name: &str,
+    transaction_id: Option<&String>,
+    session_override: Option<&str>,
+    metadata_value: &Value,
+) -> Option<EntityAssociation> {
+    let session_id = if let Some(override_id) = session_override {
+        Some(override_id.to_string())
+    } else {
+        let agent_guard = agent_state.lock().await;
+        agent_guard
+            .session_context()
+            .await
+            .map(|context| context.session_id)
+    };
+
+    let validate_metadata = load_config(app.clone(), app.state::<ConfigState>())
+        .map(|response| response.config.advanced.validate_entity_metadata)
+        .unwrap_or(true);
+    let persist_outcome = {
+        let mut service = entity_state.lock().await;
+        service.set_metadata_validation(validate_metadata);
         service
-            .list_entities(
-                filters.entity_type.clone(),
-                filters.session_id.clone(),
-                filters.limit.or(Some(10_000)),
+            .store_entity(
+                entity_id,
+                entity_name,
+                entity_type,
+                transaction_id.map(|value| value.as_str()),
+                session_id.as_deref(),
+                Some(metadata_value),
             )
-            .await?
+            .await
     };
 
-    filter_entities_in_place(
-        &mut entities,
-        filters.search.as_deref(),
-        filters.date_range.as_ref(),
-    );
+    match persist_outcome {
+        Ok(result) => {
+            let stored = result.entity.clone();
+            if result.created {
+                log::info!(
+                    "persist_entity_association: stored new entity {} of type {}",
+                    entity_id,
+                    entity_type
+                );
+                if let Err(error) = app.emit("entity_created", stored.clone()) {
+                    log::warn!(
+                        "Failed to emit entity_created event for {}: {}",
+                        entity_id,
+                        error
+                    );
+                }
+            }
+            Some(stored)
+        }
+        Err(error) => {
+            log::warn!(
+                "Failed to persist entity association for {}: {}",
+                entity_id,
+                error
+            );
+            None
+        }
+    }
+}
 
-    let timestamp = Utc::now().format("%Y-%m-%dT%H-%M-%S").to_string();
-    let (data, filename) = if format.eq_ignore_ascii_case("csv") {
-        let headers = [
-            "entityId",
-            "entityName",
-            "entityType",
-            "transactionId",
-            "sessionId",
-            "createdAt",
-            "updatedAt",
-            "isActive",
-            "metadata",
-        ];
+fn extract_transaction_id(value: &Value) -> Option<String> {
+    value
+        .get("transactionId")
+        .and_then(Value::as_str)
+        .map(|raw| raw.to_string())
+        .or_else(|| {
+            value
+                .get("transaction_id")
+                .and_then(Value::as_str)
+                .map(|raw| raw.to_string())
+        })
+        .or_else(|| {
+            value
+                .get("data")
+                .and_then(|data| data.get("transactionId"))
+                .and_then(Value::as_str)
+                .map(|raw| raw.to_string())
+        })
+        .or_else(|| {
+            value
+                .get("data")
+                .and_then(|data| data.get("transaction_id"))
+                .and_then(Value::as_str)
+                .map(|raw| raw.to_string())
+        })
+}
 
-        let mut lines = Vec::with_capacity(entities.len() + 1);
-        lines.push(headers.join(","));
+fn extract_entity_from_response(value: &Value) -> Option<(String, String)> {
+    let entity_id = value
+        .get("entityId")
+        .and_then(Value::as_str)
+        .or_else(|| {
+            value
+                .get("data")
+                .and_then(|data| data.get("entityId"))
+                .and_then(Value::as_str)
+        })?
+        .to_string();
 
-        for entity in &entities {
-            let metadata = entity
-                .metadata
-                .as_ref()
-                .map(|value| value.to_string())
-                .unwrap_or_default();
+    let entity_type = value
+        .get("entityType")
+        .and_then(Value::as_str)
+        .or_else(|| {
+            value
+                .get("data")
+                .and_then(|data| data.get("entityType"))
+                .and_then(Value::as_str)
+        })
+        .map(|raw| raw.to_string())
+        .unwrap_or_else(|| "unknown".to_string());
 
-            let row = [
-                entity.entity_id.as_str(),
-                entity.entity_name.as_str(),
-                entity.entity_type.as_str(),
-                entity.transaction_id.as_deref().unwrap_or(""),
-                entity.session_id.as_deref().unwrap_or(""),
-                entity.created_at.as_str(),
-                entity.updated_at.as_str(),
-                if entity.is_active { "true" } else { "false" },
-                metadata.as_str(),
-            ];
+    Some((entity_id, entity_type))
+}
 
-            let escaped = row
-                .iter()
-                .map(|value| escape_csv(value))
-                .collect::<Vec<_>>()
-                .join(",");
-            lines.push(escaped);
+fn extract_entity_from_agent_metadata(metadata: &Value) -> Option<(String, String)> {
+    if let Some(entity) = extract_entity_from_response(metadata) {
+        return Some(entity);
+    }
+
+    if let Some(receipt) = metadata.get("receipt") {
+        let candidates: [(&str, &str); 6] = [
+            ("tokenId", "token_id"),
+            ("topicId", "topic_id"),
+            ("contractId", "contract_id"),
+            ("scheduleId", "schedule_id"),
+            ("fileId", "file_id"),
+            ("accountId", "account_id"),
+        ];
+
+        for (camel_key, snake_key) in candidates {
+            let entity_id = receipt
+                .get(camel_key)
+                .or_else(|| receipt.get(snake_key))
+                .and_then(format_receipt_entity_id);
+            if let Some(entity_id) = entity_id {
+                return Some((entity_id, camel_key.to_string()));
+            }
         }
+    }
 
-        (
-            lines.join("\n"),
-            format!("entities-export-{}.csv", timestamp),
-        )
-    } else {
-        let json_data = serde_json::to_string_pretty(&entities).map_err(|err| err.to_string())?;
-        (json_data, format!("entities-export-{}.json", timestamp))
-    };
+    metadata
+        .get("scheduleId")
+        .and_then(format_receipt_entity_id)
+        .map(|entity_id| (entity_id, "scheduleId".to_string()))
+}
 
-    Ok(CommandResponse::ok(json!({
-        "data": data,
-        "filename": filename,
-        "count": entities.len()
-    })))
+fn format_receipt_entity_id(value: &Value) -> Option<String> {
+    match value {
+        Value::String(raw) => {
+            let trimmed = raw.trim();
+            if trimmed.is_empty() {
+                None
+            } else {
+                Some(trimmed.to_string())
+            }
+        }
+        Value::Object(parts) => {
+            let num = parts.get("num").and_then(Value::as_u64)?;
+            let shard = parts.get("shard").and_then(Value::as_u64).unwrap_or(0);
+            let realm = parts.get("realm").and_then(Value::as_u64).unwrap_or(0);
+            Some(format!("{shard}.{realm}.{num}"))
+        }
+        _ => None,
+    }
 }
 
-#[tauri::command]
-async fn entity_get_by_id(
-    state: State<'_, Mutex<EntityService>>,
-    payload: EntityIdPayload,
-) -> Result<CommandResponse<EntityAssociation>, String> {
+async fn associate_agent_tool_entities(
+    app: &AppHandle<Wry>,
+    entity_state: &State<'_, Mutex<EntityService>>,
+    agent_state: &State<'_, Mutex<AgentService>>,
+    session_id: &str,
+    message: &AgentMessageData,
+) {
+    let Some(metadata) = message.metadata.as_ref() else {
+        return;
+    };
+
+    let Some((entity_id, entity_type)) = extract_entity_from_agent_metadata(metadata) else {
+        return;
+    };
+
+    let transaction_id = extract_transaction_id(metadata);
+    let entity_context = metadata.get("entityContext");
+    let entity_name = derive_entity_name(entity_context, &entity_type, &entity_id);
+    let metadata_value = json!({
+        "entityContext": entity_context.cloned().unwrap_or(Value::Null),
+        "source": "agentToolCall",
+        "recordedAt": Utc::now().to_rfc3339(),
+        "transactionId": transaction_id,
+        "messageId": message.id,
+    });
+
+    log::debug!(
+        "associate_agent_tool_entities: agent response for session {} referenced entity {} ({})",
+        session_id,
+        entity_id,
+        entity_type
+    );
+
+    let _ = persist_entity_association(
+        app,
+        entity_state,
+        agent_state,
+        &entity_id,
+        &entity_type,
+        &entity_name,
+        transaction_id.as_ref(),
+        Some(session_id),
+        &metadata_value,
+    )
+    .await;
+}
+
+fn hashscan_transaction_url(network: &str, transaction_id: &str) -> String {
+    format!(
+        "https://hashscan.io/{}/transaction/{}",
+        network,
+        normalize_transaction_id_for_mirror(transaction_id)
+    )
+}
+
+fn first_string_field(value: &Value, keys: &[&str]) -> Option<String> {
+    let candidates = [Some(value), value.get("data"), value.get("receipt")];
+    candidates.into_iter().flatten().find_map(|candidate| {
+        keys.iter().find_map(|key| {
+            candidate
+                .get(*key)
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+    })
+}
+
+/// Builds the on-chain evidence recorded on the chat message that triggered an
+/// execution. Fields missing from the executor's response fall back to the
+/// mirror node record when one is available.
+fn build_execution_receipt(
+    response: &Value,
+    mirror_transaction: Option<&Value>,
+    transaction_id: &str,
+    network: &str,
+    source: &str,
+) -> Value {
+    let consensus_timestamp =
+        first_string_field(response, &["consensusTimestamp", "consensus_timestamp"]).or_else(
+            || {
+                mirror_transaction
+                    .and_then(|transaction| transaction.get("consensus_timestamp"))
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+            },
+        );
+    let result_code =
+        first_string_field(response, &["status", "result", "resultCode"]).or_else(|| {
+            mirror_transaction
+                .and_then(|transaction| transaction.get("result"))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        });
+
+    json!({
+        "transactionId": transaction_id,
+        "consensusTimestamp": consensus_timestamp,
+        "resultCode": result_code,
+        "network": network,
+        "hashscanUrl": hashscan_transaction_url(network, transaction_id),
+        "source": source,
+        "recordedAt": Utc::now().to_rfc3339(),
+    })
+}
+
+/// Agent replies in provideBytes mode carry unsigned bytes or a schedule that
+/// has not executed yet; their receipt is recorded once the user approves.
+fn awaits_approval(metadata: &Value) -> bool {
+    ["transactionBytes", "scheduleId"]
+        .iter()
+        .any(|key| metadata.get(*key).is_some_and(|value| !value.is_null()))
+}
+
+async fn fetch_mirror_transaction(
+    mirror_state: &MirrorBridgeState,
+    transaction_id: &str,
+    network: &str,
+) -> Option<Value> {
+    let bridge = mirror_state.bridge()?;
+    let mirror_network = MirrorNetwork::try_from_str(Some(network)).ok()?;
+    let normalized_transaction_id = normalize_transaction_id_for_mirror(transaction_id);
+
+    for attempt in 1..=4 {
+        match bridge
+            .get_transaction(normalized_transaction_id.as_ref(), mirror_network)
+            .await
+        {
+            Ok(Some(transaction)) => return Some(transaction),
+            Ok(None) => {}
+            Err(error) => {
+                log::debug!(
+                    "fetch_mirror_transaction: lookup error for transaction {} on attempt {}: {}",
+                    transaction_id,
+                    attempt,
+                    error
+                );
+            }
+        }
+        sleep(TokioDuration::from_millis(500)).await;
+    }
+
+    None
+}
+
+async fn resolve_execution_receipt(
+    mirror_state: &MirrorBridgeState,
+    response: &Value,
+    transaction_id: &str,
+    network: &str,
+    source: &str,
+) -> Value {
+    let has_consensus =
+        first_string_field(response, &["consensusTimestamp", "consensus_timestamp"]).is_some();
+    let mirror_transaction = if has_consensus {
+        None
+    } else {
+        fetch_mirror_transaction(mirror_state, transaction_id, network).await
+    };
+    build_execution_receipt(
+        response,
+        mirror_transaction.as_ref(),
+        transaction_id,
+        network,
+        source,
+    )
+}
+
+async fn record_execution_receipt(
+    app: &AppHandle<Wry>,
+    session_id: &str,
+    message_id: &str,
+    receipt: Value,
+) {
+    let session_state = app.state::<Mutex<SessionService>>();
+    let result = session_state
+        .lock()
+        .await
+        .append_message_metadata_entry(session_id, message_id, "executionReceipts", receipt)
+        .await;
+    match result {
+        Ok(Some(_)) => {}
+        Ok(None) => log::warn!(
+            "record_execution_receipt: message {} not found in session {}",
+            message_id,
+            session_id
+        ),
+        Err(error) => log::warn!(
+            "record_execution_receipt: failed to update message {}: {}",
+            message_id,
+            error
+        ),
+    }
+}
+
+fn configured_network(app: &AppHandle<Wry>) -> String {
+    load_config(app.clone(), app.state::<ConfigState>())
+        .ok()
+        .and_then(|response| serde_json::to_value(response.config.hedera.network).ok())
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_else(|| "testnet".to_string())
+}
+
+enum MirrorHydration {
+    Resolved(String, String),
+    /// The record is in and no entity will come of it.
+    Settled(String),
+    Pending,
+}
+
+/// One mirror node lookup. The first sighting of a record also feeds the
+/// ingest latency estimate used to schedule later polls.
+async fn lookup_entity_via_mirror(
+    bridge: &MirrorNodeBridge,
+    latency: &IngestLatency,
+    transaction_id: &str,
+    mirror_network: MirrorNetwork,
+) -> MirrorHydration {
+    let normalized_transaction_id = normalize_transaction_id_for_mirror(transaction_id);
+    let transaction = match bridge
+        .get_transaction(normalized_transaction_id.as_ref(), mirror_network)
+        .await
+    {
+        Ok(Some(transaction)) => transaction,
+        Ok(None) => return MirrorHydration::Pending,
+        Err(error) => {
+            log::debug!(
+                "hydrate_entity_via_mirror: lookup error for transaction {}: {}",
+                transaction_id,
+                error
+            );
+            return MirrorHydration::Pending;
+        }
+    };
+
+    if let Some(lag) = mirror_poll::consensus_time(&transaction)
+        .and_then(|consensus| std::time::SystemTime::now().duration_since(consensus).ok())
+    {
+        latency.observe(lag);
+    }
+    if let Some((entity_id, entity_type)) = extract_entity_from_transaction(&transaction) {
+        return MirrorHydration::Resolved(entity_id, entity_type);
+    }
+    match mirror_poll::record_status(&transaction) {
+        RecordStatus::Failed(result) => {
+            MirrorHydration::Settled(format!("Transaction failed with {result}"))
+        }
+        RecordStatus::Success => {
+            MirrorHydration::Settled("Transaction did not create an entity".to_string())
+        }
+        RecordStatus::Unknown => MirrorHydration::Pending,
+    }
+}
+
+/// Polls until the record settles or the transaction can no longer reach
+/// the mirror node, waiting longer between polls as time goes on.
+async fn hydrate_entity_via_mirror(
+    mirror_state: &MirrorBridgeState,
+    latency: &IngestLatency,
+    transaction_id: &str,
+    network: &str,
+    valid_duration: Option<TokioDuration>,
+) -> Result<(String, String), String> {
+    let bridge = mirror_state
+        .bridge()
+        .ok_or_else(|| "Mirror node bridge is unavailable".to_string())?;
+    let mirror_network = MirrorNetwork::try_from_str(Some(network))
+        .map_err(|error| format!("Unsupported network '{network}': {error}"))?;
+    let mut schedule = PollSchedule::new(
+        transaction_id,
+        valid_duration,
+        latency.estimate(),
+        std::time::SystemTime::now(),
+    );
+
+    let mut attempt = 0;
+    while let Some(delay) = schedule.next_delay(std::time::Instant::now()) {
+        sleep(delay).await;
+        attempt += 1;
+        match lookup_entity_via_mirror(&bridge, latency, transaction_id, mirror_network).await {
+            MirrorHydration::Resolved(entity_id, entity_type) => {
+                log::info!(
+                    "hydrate_entity_via_mirror: resolved entity {} (type {}) for transaction {} on attempt {}",
+                    entity_id,
+                    entity_type,
+                    transaction_id,
+                    attempt
+                );
+                return Ok((entity_id, entity_type));
+            }
+            MirrorHydration::Settled(reason) => return Err(reason),
+            MirrorHydration::Pending => {}
+        }
+    }
+
+    log::warn!(
+        "hydrate_entity_via_mirror: unable to resolve entity metadata for transaction {} after {} polls",
+        transaction_id,
+        attempt
+    );
+    Err("Transaction did not appear on the mirror node".to_string())
+}
+
+struct EntityHydration {
+    transaction_id: String,
+    network: String,
+    valid_duration: Option<TokioDuration>,
+    entity_context: Option<Value>,
+    session_id: Option<String>,
+    metadata: Value,
+}
+
+/// Looks the entity up once. If the mirror node has not caught up, polling
+/// continues in the background and the outcome is emitted as
+/// `entity_hydrated`, so `Ok(None)` means the answer comes later.
+async fn hydrate_entity_or_defer(
+    app: &AppHandle<Wry>,
+    hydration: EntityHydration,
+) -> Result<Option<(String, String)>, String> {
+    let mirror_state = app.state::<MirrorBridgeState>();
+    let bridge = mirror_state
+        .bridge()
+        .ok_or_else(|| "Mirror node bridge is unavailable".to_string())?;
+    let mirror_network = MirrorNetwork::try_from_str(Some(&hydration.network))
+        .map_err(|error| format!("Unsupported network '{}': {error}", hydration.network))?;
+    match lookup_entity_via_mirror(
+        &bridge,
+        &app.state::<IngestLatency>(),
+        &hydration.transaction_id,
+        mirror_network,
+    )
+    .await
+    {
+        MirrorHydration::Resolved(entity_id, entity_type) => {
+            return Ok(Some((entity_id, entity_type)));
+        }
+        MirrorHydration::Settled(reason) => return Err(reason),
+        MirrorHydration::Pending => {}
+    }
+
+    log::debug!(
+        "hydrate_entity_or_defer: polling mirror node in the background for transaction {}",
+        hydration.transaction_id
+    );
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let outcome = hydrate_entity_via_mirror(
+            &app.state::<MirrorBridgeState>(),
+            &app.state::<IngestLatency>(),
+            &hydration.transaction_id,
+            &hydration.network,
+            hydration.valid_duration,
+        )
+        .await;
+        let event = match outcome {
+            Ok((entity_id, entity_type)) => {
+                let entity_name =
+                    derive_entity_name(hydration.entity_context.as_ref(), &entity_type, &entity_id);
+                let entity = persist_entity_association(
+                    &app,
+                    &app.state(),
+                    &app.state(),
+                    &entity_id,
+                    &entity_type,
+                    &entity_name,
+                    Some(&hydration.transaction_id),
+                    hydration.session_id.as_deref(),
+                    &hydration.metadata,
+                )
+                .await;
+                match entity {
+                    Some(entity) => json!({
+                        "transactionId": hydration.transaction_id,
+                        "status": "resolved",
+                        "entity": entity,
+                    }),
+                    None => json!({
+                        "transactionId": hydration.transaction_id,
+                        "status": "failed",
+                        "error": "Failed to persist entity association",
+                    }),
+                }
+            }
+            Err(error) => json!({
+                "transactionId": hydration.transaction_id,
+                "status": "failed",
+                "error": error,
+            }),
+        };
+        if let Err(error) = app.emit(EVENT_ENTITY_HYDRATED, event) {
+            log::warn!("Failed to emit {}: {}", EVENT_ENTITY_HYDRATED, error);
+        }
+    });
+    Ok(None)
+}
+
+fn normalize_transaction_id_for_mirror(transaction_id: &str) -> Cow<'_, str> {
+    if let Some((account, remainder)) = transaction_id.split_once('@') {
+        if account.is_empty() || remainder.is_empty() {
+            return Cow::Owned(transaction_id.replace('@', "-"));
+        }
+
+        let mut parts = remainder.split('.');
+        let seconds = parts.next().unwrap_or_default();
+        let nanos = parts.next().unwrap_or_default();
+
+        if !seconds.is_empty() && !nanos.is_empty() {
+            let extra = parts.collect::<Vec<_>>().join("");
+            let mut normalized_nanos = nanos.to_string();
+            if !extra.is_empty() {
+                normalized_nanos.push_str(&extra);
+            }
+            return Cow::Owned(format!("{}-{}-{}", account, seconds, normalized_nanos));
+        }
+
+        let sanitized = remainder.replace('.', "-");
+        return Cow::Owned(format!("{}-{}", account, sanitized));
+    }
+
+    Cow::Borrowed(transaction_id)
+}
+
+fn extract_entity_from_transaction(transaction: &Value) -> Option<(String, String)> {
+    let transaction_name = transaction.get("name").and_then(Value::as_str);
+    let map_from_name = map_transaction_name_to_entity_type(transaction_name);
+
+    let candidates: [(&str, Option<&str>); 7] = [
+        ("entity_id", map_from_name.as_deref()),
+        ("token_id", Some("tokenId")),
+        ("schedule_id", Some("scheduleId")),
+        ("topic_id", Some("topicId")),
+        ("contract_id", Some("contractId")),
+        ("file_id", Some("fileId")),
+        ("account_id", Some("accountId")),
+    ];
+
+    for (key, explicit_type) in candidates {
+        if let Some(id) = transaction.get(key).and_then(Value::as_str) {
+            let entity_type = explicit_type
+                .map(|value| value.to_string())
+                .or_else(|| map_from_name.clone())
+                .unwrap_or_else(|| infer_entity_type_from_key(key));
+            return Some((id.to_string(), entity_type));
+        }
+    }
+
+    None
+}
+
+fn infer_entity_type_from_key(key: &str) -> String {
+    match key {
+        "token_id" => "tokenId".to_string(),
+        "schedule_id" => "scheduleId".to_string(),
+        "topic_id" => "topicId".to_string(),
+        "contract_id" => "contractId".to_string(),
+        "file_id" => "fileId".to_string(),
+        "account_id" => "accountId".to_string(),
+        _ => "unknown".to_string(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EntityRefreshKind {
+    Token,
+    Account,
+    Topic,
+}
+
+impl EntityRefreshKind {
+    fn from_entity_type(entity_type: EntityType) -> Option<Self> {
+        match entity_type {
+            EntityType::TokenId => Some(Self::Token),
+            EntityType::AccountId => Some(Self::Account),
+            EntityType::TopicId => Some(Self::Topic),
+            _ => None,
+        }
+    }
+}
+
+/// The fields of a mirror node record that `entity_refresh` keeps under the
+/// entity's `liveState` metadata.
+fn entity_live_state(kind: EntityRefreshKind, raw: &Value, network: &str) -> Value {
+    let pick = |keys: &[(&str, &str)]| {
+        let mut state = serde_json::Map::new();
+        for (target, source) in keys {
+            let value = raw.pointer(source).cloned().unwrap_or(Value::Null);
+            if !value.is_null() {
+                state.insert(target.to_string(), value);
+            }
+        }
+        state
+    };
+    let mut state = match kind {
+        EntityRefreshKind::Token => pick(&[
+            ("name", "/name"),
+            ("symbol", "/symbol"),
+            ("decimals", "/decimals"),
+            ("totalSupply", "/total_supply"),
+            ("maxSupply", "/max_supply"),
+            ("pauseStatus", "/pause_status"),
+            ("deleted", "/deleted"),
+        ]),
+        EntityRefreshKind::Account => pick(&[
+            ("balance", "/balance/balance"),
+            ("balanceTimestamp", "/balance/timestamp"),
+            ("memo", "/memo"),
+            ("deleted", "/deleted"),
+        ]),
+        EntityRefreshKind::Topic => pick(&[
+            ("sequenceNumber", "/sequence_number"),
+            ("memo", "/memo"),
+            ("deleted", "/deleted"),
+        ]),
+    };
+    state.insert("network".to_string(), json!(network));
+    state.insert("fetchedAt".to_string(), json!(Utc::now().to_rfc3339()));
+    Value::Object(state)
+}
+
+/// The live state field the watcher follows for each kind of entity, and
+/// the name of the change it reports.
+fn watched_field(kind: EntityRefreshKind) -> (&'static str, &'static str) {
+    match kind {
+        EntityRefreshKind::Token => ("totalSupply", "supplyChanged"),
+        EntityRefreshKind::Account => ("balance", "balanceChanged"),
+        EntityRefreshKind::Topic => ("sequenceNumber", "newTopicMessages"),
+    }
+}
+
+/// Compares a fresh live state with the stored one. `None` when the watched
+/// value is unchanged or there is no earlier reading to compare with.
+fn entity_watch_change(
+    kind: EntityRefreshKind,
+    previous: Option<&Value>,
+    current: &Value,
+) -> Option<Value> {
+    let (field, change) = watched_field(kind);
+    let before = previous?.get(field)?;
+    let after = current.get(field)?;
+    if before == after {
+        return None;
+    }
+    Some(json!({
+        "change": change,
+        "field": field,
+        "previous": before,
+        "current": after,
+    }))
+}
+
+fn enrich_transaction_response(
+    value: &mut Value,
+    entity_id: &str,
+    entity_type: &str,
+    entity_name: &str,
+    transaction_id: Option<&str>,
+) {
+    if let Some(root) = value.as_object_mut() {
+        let data_entry = root
+            .entry("data".to_string())
+            .or_insert_with(|| Value::Object(serde_json::Map::new()));
+        if let Some(data) = data_entry.as_object_mut() {
+            data.insert("entityId".to_string(), Value::String(entity_id.to_string()));
+            data.insert(
+                "entityType".to_string(),
+                Value::String(entity_type.to_string()),
+            );
+            data.insert(
+                "entityName".to_string(),
+                Value::String(entity_name.to_string()),
+            );
+            if let Some(tx_id) = transaction_id {
+                data.entry("transactionId".to_string())
+                    .or_insert_with(|| Value::String(tx_id.to_string()));
+            }
+        }
+    }
+}
+
+fn map_transaction_name_to_entity_type(name: Option<&str>) -> Option<String> {
+    let normalized = name?.to_ascii_uppercase();
+    match normalized.as_str() {
+        "TOKENCREATION" => Some("tokenId".to_string()),
+        "CRYPTOCREATEACCOUNT" => Some("accountId".to_string()),
+        "CONSENSUSCREATETOPIC" => Some("topicId".to_string()),
+        "CONTRACTCREATEINSTANCE" => Some("contractId".to_string()),
+        "SCHEDULECREATE" => Some("scheduleId".to_string()),
+        "FILECREATE" => Some("fileId".to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod helper_tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn live_state_keeps_the_refreshed_fields() {
+        let token = entity_live_state(
+            EntityRefreshKind::Token,
+            &json!({ "name": "Points", "total_supply": "1000", "decimals": "2", "memo": "x" }),
+            "testnet",
+        );
+        assert_eq!(token["totalSupply"], json!("1000"));
+        assert_eq!(token["network"], json!("testnet"));
+        assert!(token.get("memo").is_none());
+
+        let account = entity_live_state(
+            EntityRefreshKind::Account,
+            &json!({ "balance": { "balance": 250, "timestamp": "1.2" } }),
+            "mainnet",
+        );
+        assert_eq!(account["balance"], json!(250));
+        assert_eq!(
+            EntityRefreshKind::from_entity_type(EntityType::TopicId),
+            Some(EntityRefreshKind::Topic)
+        );
+        assert_eq!(
+            EntityRefreshKind::from_entity_type(EntityType::ScheduleId),
+            None
+        );
+    }
+
+    #[test]
+    fn watch_change_needs_an_earlier_reading() {
+        let previous = json!({ "balance": 100, "memo": "a" });
+        let change = entity_watch_change(
+            EntityRefreshKind::Account,
+            Some(&previous),
+            &json!({ "balance": 150, "memo": "b" }),
+        )
+        .unwrap();
+        assert_eq!(change["change"], json!("balanceChanged"));
+        assert_eq!(change["previous"], json!(100));
+        assert_eq!(change["current"], json!(150));
+
+        assert!(
+            entity_watch_change(EntityRefreshKind::Account, Some(&previous), &previous).is_none()
+        );
+        assert!(
+            entity_watch_change(
+                EntityRefreshKind::Topic,
+                None,
+                &json!({ "sequenceNumber": 4 })
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn xlsx_export_types_columns_and_flattens_metadata() {
+        let entity = EntityAssociation {
+            entity_id: "0.0.42".to_string(),
+            entity_name: "Points".to_string(),
+            entity_type: EntityType::TokenId,
+            transaction_id: None,
+            session_id: Some("session-1".to_string()),
+            created_at: "2024-01-02T03:04:05+00:00".to_string(),
+            updated_at: "2024-01-02 03:04:05".to_string(),
+            is_active: true,
+            metadata: Some(json!({ "liveState": { "decimals": 2, "keys": ["a", "b"] } })),
+            notes: None,
+            watched: false,
+            favorite: false,
+        };
+
+        let created = xlsx_serial_date(&entity.created_at).unwrap();
+        assert!((created - 45293.12783).abs() < 1e-4);
+        assert_eq!(xlsx_serial_date(&entity.updated_at), Some(created));
+        assert_eq!(xlsx_serial_date("yesterday"), None);
+
+        let mut fields = Vec::new();
+        flatten_metadata(
+            String::new(),
+            entity.metadata.as_ref().unwrap(),
+            &mut fields,
+        );
+        assert_eq!(
+            fields,
+            vec![
+                ("liveState.decimals".to_string(), json!(2)),
+                ("liveState.keys[0]".to_string(), json!("a")),
+                ("liveState.keys[1]".to_string(), json!("b")),
+            ]
+        );
+
+        let workbook = build_entities_xlsx(&[entity]).unwrap();
+        assert!(workbook.starts_with(b"PK"));
+        assert_eq!(xlsx_text(&"x".repeat(40_000)).len(), XLSX_MAX_TEXT);
+    }
+
+    #[test]
+    fn transaction_name_mapping_returns_expected_type() {
+        assert_eq!(
+            map_transaction_name_to_entity_type(Some("TOKENCREATION")),
+            Some("tokenId".to_string())
+        );
+        assert_eq!(
+            map_transaction_name_to_entity_type(Some("contractcreateinstance")),
+            Some("contractId".to_string())
+        );
+        assert_eq!(map_transaction_name_to_entity_type(Some("UNKNOWN")), None);
+    }
+
+    #[test]
+    fn derive_entity_name_prefers_context_name() {
+        let context = json!({ "name": "DemoToken" });
+        let derived = derive_entity_name(Some(&context), "tokenId", "0.0.1234");
+        assert_eq!(derived, "DemoToken");
+    }
+
+    #[test]
+    fn derive_entity_name_falls_back_to_entity_id() {
+        let derived = derive_entity_name(None, "tokenId", "0.0.9999");
+        assert_eq!(derived, "0.0.9999");
+    }
+
+    #[test]
+    fn extract_entity_from_transaction_handles_token_creation() {
+        let transaction = json!({
+            "name": "TOKENCREATION",
+            "entity_id": "0.0.5678"
+        });
+        let entity = extract_entity_from_transaction(&transaction).expect("entity");
+        assert_eq!(entity.0, "0.0.5678");
+        assert_eq!(entity.1, "tokenId");
+    }
+
+    #[test]
+    fn extract_entity_from_agent_metadata_reads_receipt_ids() {
+        let metadata = json!({
+            "transactionId": "0.0.1111@1700000000.000000001",
+            "receipt": {
+                "status": "SUCCESS",
+                "tokenId": { "shard": 0, "realm": 0, "num": 4242 }
+            }
+        });
+        let entity = extract_entity_from_agent_metadata(&metadata).expect("entity");
+        assert_eq!(entity.0, "0.0.4242");
+        assert_eq!(entity.1, "tokenId");
+    }
+
+    #[test]
+    fn extract_entity_from_agent_metadata_ignores_plain_responses() {
+        let metadata = json!({ "transactionId": "0.0.1111@1700000000.000000001" });
+        assert!(extract_entity_from_agent_metadata(&metadata).is_none());
+    }
+
+    #[test]
+    fn execution_receipt_prefers_response_fields_and_links_hashscan() {
+        let response = json!({
+            "success": true,
+            "data": { "consensusTimestamp": "1700000000.000000001", "status": "SUCCESS" }
+        });
+        let mirror = json!({ "consensus_timestamp": "1.2", "result": "FAIL" });
+        let receipt = build_execution_receipt(
+            &response,
+            Some(&mirror),
+            "0.0.1234@1700000000.000000000",
+            "testnet",
+            "executeTransactionBytes",
+        );
+        assert_eq!(receipt["consensusTimestamp"], "1700000000.000000001");
+        assert_eq!(receipt["resultCode"], "SUCCESS");
+        assert_eq!(
+            receipt["hashscanUrl"],
+            "https://hashscan.io/testnet/transaction/0.0.1234-1700000000-000000000"
+        );
+    }
+
+    #[test]
+    fn unexecuted_agent_transactions_await_approval() {
+        assert!(awaits_approval(&json!({
+            "transactionId": "0.0.1@1.1",
+            "transactionBytes": "AAEC"
+        })));
+        assert!(awaits_approval(&json!({ "scheduleId": "0.0.9" })));
+        assert!(!awaits_approval(&json!({
+            "transactionId": "0.0.1@1.1",
+            "scheduleId": null
+        })));
+    }
+
+    #[test]
+    fn execution_receipt_falls_back_to_mirror_record() {
+        let mirror = json!({ "consensus_timestamp": "1700000001.5", "result": "SUCCESS" });
+        let receipt = build_execution_receipt(
+            &json!({ "success": true }),
+            Some(&mirror),
+            "0.0.1234@1700000000.000000000",
+            "mainnet",
+            "agentToolCall",
+        );
+        assert_eq!(receipt["consensusTimestamp"], "1700000001.5");
+        assert_eq!(receipt["resultCode"], "SUCCESS");
+        assert_eq!(receipt["network"], "mainnet");
+    }
+
+    #[test]
+    fn enrich_transaction_response_populates_data_block() {
+        let mut value = json!({ "success": true, "data": {} });
+        enrich_transaction_response(
+            &mut value,
+            "0.0.3333",
+            "tokenId",
+            "Demo",
+            Some("0.0.1111@123"),
+        );
+
+        let data = value
+            .get("data")
+            .and_then(Value::as_object)
+            .expect("data object");
+        assert_eq!(data.get("entityId").unwrap(), "0.0.3333");
+        assert_eq!(data.get("entityType").unwrap(), "tokenId");
+        assert_eq!(data.get("entityName").unwrap(), "Demo");
+        assert_eq!(data.get("transactionId").unwrap(), "0.0.1111@123");
+    }
+}
+
+fn escape_csv(value: &str) -> String {
+    let escaped = value.replace('"', "\"\"");
+    format!("\"{}\"", escaped)
+}
+
+/// Excel rejects longer cell strings.
+const XLSX_MAX_TEXT: usize = 32_767;
+
+fn xlsx_text(value: &str) -> Cow<'_, str> {
+    match value.char_indices().nth(XLSX_MAX_TEXT) {
+        Some((index, _)) => Cow::Owned(value[..index].to_string()),
+        None => Cow::Borrowed(value),
+    }
+}
+
+/// Days since Excel's epoch, for timestamps stored as RFC 3339 or as
+/// SQLite's `datetime('now')`.
+fn xlsx_serial_date(value: &str) -> Option<f64> {
+    let timestamp = DateTime::parse_from_rfc3339(value)
+        .map(|parsed| parsed.naive_utc())
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S"))
+        .ok()?;
+    let epoch = chrono::NaiveDate::from_ymd_opt(1899, 12, 30)?.and_hms_opt(0, 0, 0)?;
+    Some((timestamp - epoch).num_milliseconds() as f64 / 86_400_000.0)
+}
+
+fn flatten_metadata(prefix: String, value: &Value, rows: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, nested) in map {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten_metadata(path, nested, rows);
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for (index, nested) in items.iter().enumerate() {
+                flatten_metadata(format!("{prefix}[{index}]"), nested, rows);
+            }
+        }
+        _ => rows.push((prefix, value.clone())),
+    }
+}
+
+/// An `Entities` sheet with typed columns and a `Metadata` sheet holding one
+/// row per metadata field, so JSON metadata stays readable in a spreadsheet.
+fn build_entities_xlsx(entities: &[EntityAssociation]) -> Result<Vec<u8>, XlsxError> {
+    let mut workbook = Workbook::new();
+    let header = Format::new().set_bold();
+    let date = Format::new().set_num_format("yyyy-mm-dd hh:mm:ss");
+
+    let sheet = workbook.add_worksheet().set_name("Entities")?;
+    let headers = [
+        "Entity ID",
+        "Name",
+        "Type",
+        "Transaction ID",
+        "Session ID",
+        "Created",
+        "Updated",
+        "Active",
+        "Notes",
+    ];
+    for (column, title) in headers.iter().enumerate() {
+        sheet.write_string_with_format(0, column as u16, *title, &header)?;
+    }
+    for (index, entity) in entities.iter().enumerate() {
+        let row = index as u32 + 1;
+        sheet.write_string(row, 0, xlsx_text(&entity.entity_id))?;
+        sheet.write_string(row, 1, xlsx_text(&entity.entity_name))?;
+        sheet.write_string(row, 2, entity.entity_type.as_str())?;
+        if let Some(transaction_id) = entity.transaction_id.as_deref() {
+            sheet.write_string(row, 3, xlsx_text(transaction_id))?;
+        }
+        if let Some(session_id) = entity.session_id.as_deref() {
+            sheet.write_string(row, 4, xlsx_text(session_id))?;
+        }
+        for (column, value) in [(5, &entity.created_at), (6, &entity.updated_at)] {
+            match xlsx_serial_date(value) {
+                Some(serial) => sheet.write_number_with_format(row, column, serial, &date)?,
+                None => sheet.write_string(row, column, xlsx_text(value))?,
+            };
+        }
+        sheet.write_boolean(row, 7, entity.is_active)?;
+        if let Some(notes) = entity.notes.as_deref() {
+            sheet.write_string(row, 8, xlsx_text(notes))?;
+        }
+    }
+    sheet.set_freeze_panes(1, 0)?;
+    sheet.autofilter(0, 0, entities.len() as u32, headers.len() as u16 - 1)?;
+    sheet.autofit();
+
+    let sheet = workbook.add_worksheet().set_name("Metadata")?;
+    for (column, title) in ["Entity ID", "Field", "Value"].iter().enumerate() {
+        sheet.write_string_with_format(0, column as u16, *title, &header)?;
+    }
+    let mut row = 0u32;
+    for entity in entities {
+        let mut fields = Vec::new();
+        if let Some(metadata) = entity.metadata.as_ref() {
+            flatten_metadata(String::new(), metadata, &mut fields);
+        }
+        for (field, value) in fields {
+            row += 1;
+            sheet.write_string(row, 0, xlsx_text(&entity.entity_id))?;
+            sheet.write_string(row, 1, xlsx_text(&field))?;
+            match value {
+                Value::Null => {}
+                Value::Bool(flag) => {
+                    sheet.write_boolean(row, 2, flag)?;
+                }
+                // Serials and tinybar amounts exceed f64's exact range.
+                Value::Number(number) => {
+                    sheet.write_string(row, 2, number.to_string())?;
+                }
+                Value::String(text) => {
+                    sheet.write_string(row, 2, xlsx_text(&text))?;
+                }
+                other => {
+                    sheet.write_string(row, 2, xlsx_text(&other.to_string()))?;
+                }
+            }
+        }
+    }
+    sheet.set_freeze_panes(1, 0)?;
+    sheet.autofilter(0, 0, row, 2)?;
+    sheet.autofit();
+
+    workbook.save_to_buffer()
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct McpConnectionResponse {
+    success: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl From<McpConnectionResult> for McpConnectionResponse {
+    fn from(result: McpConnectionResult) -> Self {
+        Self {
+            success: result.success,
+            tools: result.tools,
+            error: result.error,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct McpRegistrySearchResponse {
+    servers: Vec<Value>,
+    total: usize,
+    has_more: bool,
+    categories: Vec<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metric_statuses: Option<HashMap<String, Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metric_freshness: Option<HashMap<String, Value>>,
+}
+
+impl From<McpRegistrySearchResult> for McpRegistrySearchResponse {
+    fn from(result: McpRegistrySearchResult) -> Self {
+        let McpRegistrySearchResult {
+            servers,
+            total,
+            has_more,
+            categories,
+            metric_statuses,
+            metric_freshness,
+        } = result;
+
+        Self {
+            servers,
+            total,
+            has_more,
+            categories,
+            metric_statuses: if metric_statuses.is_empty() {
+                None
+            } else {
+                Some(metric_statuses)
+            },
+            metric_freshness: if metric_freshness.is_empty() {
+                None
+            } else {
+                Some(metric_freshness)
+            },
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct McpSavePayload {
+    servers: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MirrorNodeSchedulePayload {
+    schedule_id: String,
+    #[serde(default)]
+    network: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MirrorNodeTimestampPayload {
+    timestamp: String,
+    #[serde(default)]
+    network: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MirrorNodeTransactionPayload {
+    transaction_id: String,
+    #[serde(default)]
+    network: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MirrorNodeNftPayload {
+    token_id: String,
+    serial_number: u64,
+    #[serde(default)]
+    network: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenHoldersPayload {
+    token_id: String,
+    #[serde(default)]
+    network: Option<String>,
+    #[serde(default)]
+    limit: Option<u32>,
+    #[serde(default)]
+    cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountTransactionsPayload {
+    #[serde(default)]
+    account_id: Option<String>,
+    #[serde(default)]
+    network: Option<String>,
+    #[serde(default)]
+    limit: Option<u32>,
+    #[serde(default)]
+    cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionAnnotatePayload {
+    transactions: Vec<Value>,
+    #[serde(default)]
+    account_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ContactPayload {
+    account_id: String,
+    display_name: String,
+    #[serde(default)]
+    avatar_url: Option<String>,
+    #[serde(default)]
+    notes: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ContactIdPayload {
+    account_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenDistributionPayload {
+    token_id: String,
+    #[serde(default)]
+    network: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WalletSetPayload {
+    info: Option<WalletBridgeInfo>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionBytesPayload {
+    transaction_bytes: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExecuteTransactionPayload {
+    #[serde(rename = "transactionBytes")]
+    transaction_bytes: String,
+    #[serde(default)]
+    entity_context: Option<Value>,
+    #[serde(default)]
+    session_id: Option<String>,
+    #[serde(default)]
+    message_id: Option<String>,
+    #[serde(default)]
+    valid_duration_seconds: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WalletHydratePayload {
+    transaction_id: String,
+    #[serde(default)]
+    network: Option<String>,
+    session_id: String,
+    #[serde(default)]
+    entity_context: Option<Value>,
+    #[serde(default)]
+    valid_duration_seconds: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntityFiltersPayload {
+    #[serde(default)]
+    search: Option<String>,
+    #[serde(default)]
+    entity_type: Option<String>,
+    #[serde(default)]
+    session_id: Option<String>,
+    #[serde(default)]
+    date_range: Option<EntityDateRangePayload>,
+    #[serde(default)]
+    limit: Option<usize>,
+    #[serde(default)]
+    include_inactive: bool,
+    #[serde(default)]
+    cursor: Option<String>,
+    #[serde(default)]
+    page_size: Option<usize>,
+    #[serde(default)]
+    sort: EntitySortOrder,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntityDateRangePayload {
+    #[serde(default)]
+    start: Option<String>,
+    #[serde(default)]
+    end: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EntityIdPayload {
+    #[serde(rename = "entityId")]
+    entity_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntityBulkDeletePayload {
+    entity_ids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntityRenamePayload {
+    entity_id: String,
+    new_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntityNotesPayload {
+    entity_id: String,
+    notes: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntityExportPayload {
+    #[serde(default)]
+    filters: Option<EntityFiltersPayload>,
+    #[serde(default)]
+    format: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EntitySearchPayload {
+    query: String,
+    #[serde(default)]
+    entity_type: Option<String>,
+}
+
+#[tauri::command]
+async fn credential_store(
+    state: State<'_, Mutex<CredentialManager>>,
+    service: String,
+    account: String,
+    password: String,
+    scope: Option<CredentialScope>,
+) -> Result<CommandResponse<bool>, String> {
+    match config_validation::check_credential(scope, &service, &password) {
+        Ok(warnings) => {
+            for warning in warnings {
+                log::warn!("Storing credential {}/{}: {}", service, account, warning);
+            }
+        }
+        Err(error) => return Ok(CommandResponse::error(error)),
+    }
+    let manager = match state.lock().await.active() {
+        Ok(manager) => manager,
+        Err(error) => return Ok(CommandResponse::error(error.to_string())),
+    };
+    match manager
+        .store_with_scope(&service, &account, &password, scope)
+        .await
+    {
+        Ok(result) => Ok(CommandResponse::ok(result)),
+        Err(error) => Ok(CommandResponse::error(error.to_string())),
+    }
+}
+
+#[tauri::command]
+async fn credential_get(
+    app: AppHandle<Wry>,
+    state: State<'_, Mutex<CredentialManager>>,
+    gate: State<'_, OsAuthGate>,
+    service: String,
+    account: String,
+) -> Result<CommandResponse<Option<Secret>>, String> {
+    let auth = match load_config(app.clone(), app.state::<ConfigState>()) {
+        Ok(response) => response.config.advanced.credential_auth,
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+    if let Err(error) = gate
+        .authorize(&auth, &format!("reveal the {service}/{account} credential"))
+        .await
+    {
+        return Ok(CommandResponse::error(error));
+    }
+    let manager = match state.lock().await.active() {
+        Ok(manager) => manager,
+        Err(error) => return Ok(CommandResponse::error(error.to_string())),
+    };
+    match manager.get(&service, &account).await {
+        Ok(result) => Ok(CommandResponse::ok(result)),
+        Err(error) => Ok(CommandResponse::error(error.to_string())),
+    }
+}
+
+#[tauri::command]
+async fn credential_delete(
+    state: State<'_, Mutex<CredentialManager>>,
+    service: String,
+    account: String,
+) -> Result<CommandResponse<bool>, String> {
+    let manager = match state.lock().await.active() {
+        Ok(manager) => manager,
+        Err(error) => return Ok(CommandResponse::error(error.to_string())),
+    };
+    match manager.delete(&service, &account).await {
+        Ok(result) => Ok(CommandResponse::ok(result)),
+        Err(error) => Ok(CommandResponse::error(error.to_string())),
+    }
+}
+
+#[tauri::command]
+async fn credential_clear(
+    state: State<'_, Mutex<CredentialManager>>,
+    service: Option<String>,
+) -> Result<CommandResponse<u32>, String> {
+    let resolved_service = service.unwrap_or_else(|| "conversational-agent".to_string());
+    let manager = match state.lock().await.active() {
+        Ok(manager) => manager,
+        Err(error) => return Ok(CommandResponse::error(error.to_string())),
+    };
+    match manager.clear(&resolved_service).await {
+        Ok(result) => Ok(CommandResponse::ok(result)),
+        Err(error) => Ok(CommandResponse::error(error.to_string())),
+    }
+}
+
+#[tauri::command]
+async fn credential_list(
+    state: State<'_, Mutex<CredentialManager>>,
+) -> Result<CommandResponse<Vec<CredentialSummary>>, String> {
+    let manager = match state.lock().await.active() {
+        Ok(manager) => manager,
+        Err(error) => return Ok(CommandResponse::error(error.to_string())),
+    };
+    match manager.list().await {
+        Ok(result) => Ok(CommandResponse::ok(result)),
+        Err(error) => Ok(CommandResponse::error(error.to_string())),
+    }
+}
+
+#[tauri::command]
+async fn credential_store_totp(
+    state: State<'_, Mutex<CredentialManager>>,
+    service: String,
+    account: String,
+    secret: String,
+    scope: Option<CredentialScope>,
+) -> Result<CommandResponse<bool>, String> {
+    let manager = match state.lock().await.active() {
+        Ok(manager) => manager,
+        Err(error) => return Ok(CommandResponse::error(error.to_string())),
+    };
+    match manager.store_totp(&service, &account, &secret, scope).await {
+        Ok(result) => Ok(CommandResponse::ok(result)),
+        Err(error) => Ok(CommandResponse::error(error.to_string())),
+    }
+}
+
+#[tauri::command]
+async fn credential_generate_totp(
+    state: State<'_, Mutex<CredentialManager>>,
+    service: String,
+    account: String,
+) -> Result<CommandResponse<Option<TotpCode>>, String> {
+    let manager = match state.lock().await.active() {
+        Ok(manager) => manager,
+        Err(error) => return Ok(CommandResponse::error(error.to_string())),
+    };
+    match manager.generate_totp(&service, &account).await {
+        Ok(result) => Ok(CommandResponse::ok(result)),
+        Err(error) => Ok(CommandResponse::error(error.to_string())),
+    }
+}
+
+#[tauri::command]
+async fn credential_lock(
+    state: State<'_, Mutex<CredentialManager>>,
+) -> Result<CommandResponse<bool>, String> {
+    match state.lock().await.lock_store() {
+        Ok(()) => Ok(CommandResponse::ok(true)),
+        Err(error) => Ok(CommandResponse::error(error.to_string())),
+    }
+}
+
+/// Unlocks the store with `password` when the user set one through
+/// `MASTER_PASSWORD`, or else after OS authentication, since the keychain
+/// key is not something the user can type. The key stays in `ConfigState`
+/// while the store is locked, so locking only gates the credential commands.
+#[tauri::command]
+async fn credential_unlock(
+    app: AppHandle<Wry>,
+    state: State<'_, Mutex<CredentialManager>>,
+    gate: State<'_, OsAuthGate>,
+    password: Option<String>,
+) -> Result<CommandResponse<bool>, String> {
+    let password = match password.filter(|password| !password.is_empty()) {
+        Some(password) => password,
+        None => {
+            let auth = match load_config(app.clone(), app.state::<ConfigState>()) {
+                Ok(response) => response.config.advanced.credential_auth,
+                Err(error) => return Ok(CommandResponse::error(error)),
+            };
+            let grace = TokioDuration::from_secs(auth.grace_period_seconds);
+            if let Err(error) = gate.confirm(grace, "unlock the credential store").await {
+                return Ok(CommandResponse::error(error));
+            }
+            app.state::<ConfigState>().master_password.clone()
+        }
+    };
+    match state.lock().await.unlock_store(&password) {
+        Ok(()) => Ok(CommandResponse::ok(true)),
+        Err(error) => Ok(CommandResponse::error(error.to_string())),
+    }
+}
+
+#[tauri::command]
+async fn credential_is_locked(
+    state: State<'_, Mutex<CredentialManager>>,
+) -> Result<CommandResponse<bool>, String> {
+    Ok(CommandResponse::ok(state.lock().await.is_locked()))
+}
+
+/// Tells whether credentials.dat still opens under the store's key, and if
+/// not whether the key changed or the file is damaged.
+#[tauri::command]
+async fn credential_verify_master_password(
+    state: State<'_, Mutex<CredentialManager>>,
+) -> Result<CommandResponse<MasterPasswordCheck>, String> {
+    let manager = state.lock().await.clone();
+    match manager.verify_master_password().await {
+        Ok(result) => Ok(CommandResponse::ok(result)),
+        Err(error) => Ok(CommandResponse::error(error.to_string())),
+    }
+}
+
+#[tauri::command]
+async fn credential_access_log(
+    state: State<'_, Mutex<CredentialManager>>,
+) -> Result<CommandResponse<Vec<CredentialAccess>>, String> {
+    Ok(CommandResponse::ok(state.lock().await.access_log()))
+}
+
+#[tauri::command]
+async fn mirror_node_get_schedule_info(
+    state: State<'_, MirrorBridgeState>,
+    payload: MirrorNodeSchedulePayload,
+) -> Result<CommandResponse<Value>, String> {
+    let MirrorNodeSchedulePayload {
+        schedule_id,
+        network,
+    } = payload;
+
+    let bridge = match state.bridge() {
+        Some(bridge) => bridge,
+        None => {
+            return Ok(CommandResponse::error(
+                "Mirror node bridge is not available".to_string(),
+            ));
+        }
+    };
+
+    let network = MirrorNetwork::try_from_str(network.as_deref())?;
+    let result = bridge.get_schedule_info(&schedule_id, network).await?;
+    Ok(CommandResponse::ok(result.unwrap_or(Value::Null)))
+}
+
+#[tauri::command]
+async fn mirror_node_get_scheduled_transaction_status(
+    state: State<'_, MirrorBridgeState>,
+    payload: MirrorNodeSchedulePayload,
+) -> Result<CommandResponse<Value>, String> {
+    let MirrorNodeSchedulePayload {
+        schedule_id,
+        network,
+    } = payload;
+
+    let bridge = match state.bridge() {
+        Some(bridge) => bridge,
+        None => {
+            return Ok(CommandResponse::error(
+                "Mirror node bridge is not available".to_string(),
+            ));
+        }
+    };
+
+    let network = MirrorNetwork::try_from_str(network.as_deref())?;
+    let status = bridge
+        .get_scheduled_transaction_status(&schedule_id, network)
+        .await?;
+    Ok(CommandResponse::ok(status))
+}
+
+#[tauri::command]
+async fn mirror_node_get_transaction_by_timestamp(
+    state: State<'_, MirrorBridgeState>,
+    payload: MirrorNodeTimestampPayload,
+) -> Result<CommandResponse<Value>, String> {
+    let MirrorNodeTimestampPayload { timestamp, network } = payload;
+
+    let bridge = match state.bridge() {
+        Some(bridge) => bridge,
+        None => {
+            return Ok(CommandResponse::error(
+                "Mirror node bridge is not available".to_string(),
+            ));
+        }
+    };
+
+    let network = MirrorNetwork::try_from_str(network.as_deref())?;
+    let transactions = bridge
+        .get_transaction_by_timestamp(&timestamp, network)
+        .await?;
+    Ok(CommandResponse::ok(Value::Array(transactions)))
+}
+
+#[tauri::command]
+async fn mirror_node_get_transaction(
+    state: State<'_, MirrorBridgeState>,
+    payload: MirrorNodeTransactionPayload,
+) -> Result<CommandResponse<Value>, String> {
+    let MirrorNodeTransactionPayload {
+        transaction_id,
+        network,
+    } = payload;
+
+    let bridge = match state.bridge() {
+        Some(bridge) => bridge,
+        None => {
+            return Ok(CommandResponse::error(
+                "Mirror node bridge is not available".to_string(),
+            ));
+        }
+    };
+
+    let network = MirrorNetwork::try_from_str(network.as_deref())?;
+    let transaction = bridge.get_transaction(&transaction_id, network).await?;
+    Ok(CommandResponse::ok(transaction.unwrap_or(Value::Null)))
+}
+
+#[tauri::command]
+async fn mirror_node_get_token_info(
+    state: State<'_, MirrorBridgeState>,
+    payload: MirrorNodeTransactionPayload,
+) -> Result<CommandResponse<Value>, String> {
+    let MirrorNodeTransactionPayload {
+        transaction_id,
+        network,
+    } = payload;
+
+    let bridge = match state.bridge() {
+        Some(bridge) => bridge,
+        None => {
+            return Ok(CommandResponse::error(
+                "Mirror node bridge is not available".to_string(),
+            ));
+        }
+    };
+
+    let network = MirrorNetwork::try_from_str(network.as_deref())?;
+    let info = bridge.get_token_info(&transaction_id, network).await?;
+    Ok(CommandResponse::ok(info.unwrap_or(Value::Null)))
+}
+
+/// NFT info from the mirror node. When the metadata points at an HCS-1
+/// inscription, the verified file is attached as `inscription`; failures to
+/// resolve it are reported in `inscriptionError` without failing the call.
+#[tauri::command]
+async fn mirror_node_get_nft_info(
+    app: AppHandle<Wry>,
+    store: State<'_, Hcs1Store>,
+    mirror_state: State<'_, MirrorBridgeState>,
+    payload: MirrorNodeNftPayload,
+) -> Result<CommandResponse<Value>, String> {
+    let network = payload.network.unwrap_or_else(|| configured_network(&app));
+    let network = MirrorNetwork::try_from_str(Some(&network))?;
+    let mut info = match store
+        .nft_info(&payload.token_id, payload.serial_number, network)
+        .await
+    {
+        Ok(info) => info,
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+
+    let metadata_uri = info
+        .get("metadata")
+        .and_then(Value::as_str)
+        .and_then(|encoded| STANDARD.decode(encoded).ok())
+        .and_then(|bytes| String::from_utf8(bytes).ok());
+    let Some(metadata_uri) = metadata_uri else {
+        return Ok(CommandResponse::ok(info));
+    };
+    let topic_id = parse_hcs1_uri(&metadata_uri).map(str::to_string);
+    if let Some(object) = info.as_object_mut() {
+        object.insert("metadataUri".to_string(), json!(metadata_uri));
+    }
+    let Some(topic_id) = topic_id else {
+        return Ok(CommandResponse::ok(info));
+    };
+
+    let resolved = match store.cached(&topic_id) {
+        Some(file) => Ok(file),
+        None => resolve_inscription(&store, &mirror_state, &topic_id, network).await,
+    };
+    if let Some(object) = info.as_object_mut() {
+        match resolved {
+            Ok(file) => {
+                object.insert("inscription".to_string(), json!(file));
+            }
+            Err(error) => {
+                log::warn!("Failed to resolve inscription {}: {}", topic_id, error);
+                object.insert("inscriptionError".to_string(), json!(error));
+            }
+        }
+    }
+    Ok(CommandResponse::ok(info))
+}
+
+async fn resolve_inscription(
+    store: &Hcs1Store,
+    mirror_state: &MirrorBridgeState,
+    topic_id: &str,
+    network: MirrorNetwork,
+) -> Result<hcs1::InscribedFile, String> {
+    let payload = store.fetch(topic_id, network).await?;
+    let decoded = match payload.memo.compression.as_str() {
+        "" | "none" => payload.data.clone(),
+        algorithm => {
+            let bridge = mirror_state
+                .bridge()
+                .ok_or_else(|| "Mirror node bridge is not available".to_string())?;
+            bridge.decompress(algorithm, &payload.data).await?
+        }
+    };
+    store.store(&payload, decoded)
+}
+
+#[tauri::command]
+async fn token_get_holders(
+    app: AppHandle<Wry>,
+    state: State<'_, TokenHolderService>,
+    payload: TokenHoldersPayload,
+) -> Result<CommandResponse<TokenHoldersPage>, String> {
+    let network = payload.network.unwrap_or_else(|| configured_network(&app));
+    let network = MirrorNetwork::try_from_str(Some(&network))?;
+    let result = state
+        .get_holders(
+            &payload.token_id,
+            network,
+            payload.limit,
+            payload.cursor.as_deref(),
+        )
+        .await;
+    match result {
+        Ok(page) => Ok(CommandResponse::ok(page)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn token_get_distribution_summary(
+    app: AppHandle<Wry>,
+    state: State<'_, TokenHolderService>,
+    payload: TokenDistributionPayload,
+) -> Result<CommandResponse<TokenDistributionSummary>, String> {
+    let network = payload.network.unwrap_or_else(|| configured_network(&app));
+    let network = MirrorNetwork::try_from_str(Some(&network))?;
+    let result = state
+        .get_distribution_summary(&payload.token_id, network)
+        .await;
+    match result {
+        Ok(summary) => Ok(CommandResponse::ok(summary)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+/// Adds contact and HCS-10 profile names to each transaction's
+/// counterparties, looking every account up once per batch.
+async fn annotate_with_contacts(
+    contacts: &ContactBook,
+    network: &str,
+    transactions: &mut [Value],
+    own_account: Option<&str>,
+) -> Result<(), String> {
+    let accounts = contacts::all_counterparty_accounts(transactions, own_account);
+    let known = contacts.lookup(network, &accounts).await?;
+    contacts::annotate_transactions(transactions, own_account, &known);
+    Ok(())
+}
+
+fn configured_account_id(app: &AppHandle<Wry>) -> Option<String> {
+    load_config(app.clone(), app.state::<ConfigState>())
+        .ok()
+        .map(|response| response.config.hedera.account_id.trim().to_string())
+        .filter(|account_id| !account_id.is_empty())
+}
+
+#[tauri::command]
+async fn mirror_node_get_account_transactions(
+    app: AppHandle<Wry>,
+    history: State<'_, AccountHistoryService>,
+    contacts: State<'_, ContactBook>,
+    payload: AccountTransactionsPayload,
+) -> Result<CommandResponse<AccountTransactionsPage>, String> {
+    let Some(account_id) = payload.account_id.or_else(|| configured_account_id(&app)) else {
+        return Ok(CommandResponse::error(
+            "No account to load transactions for".to_string(),
+        ));
+    };
+    let network = payload.network.unwrap_or_else(|| configured_network(&app));
+    let network = MirrorNetwork::try_from_str(Some(&network))?;
+    let mut page = match history
+        .get_page(
+            &account_id,
+            network,
+            payload.limit,
+            payload.cursor.as_deref(),
+        )
+        .await
+    {
+        Ok(page) => page,
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+    if let Err(error) = annotate_with_contacts(
+        &contacts,
+        network.as_str(),
+        &mut page.transactions,
+        Some(&page.account_id),
+    )
+    .await
+    {
+        return Ok(CommandResponse::error(error));
+    }
+    Ok(CommandResponse::ok(page))
+}
+
+#[tauri::command]
+async fn transaction_annotate(
+    app: AppHandle<Wry>,
+    contacts: State<'_, ContactBook>,
+    payload: TransactionAnnotatePayload,
+) -> Result<CommandResponse<Vec<Value>>, String> {
+    let own_account = payload.account_id.or_else(|| configured_account_id(&app));
+    let mut transactions = payload.transactions;
+    match annotate_with_contacts(
+        &contacts,
+        &configured_network(&app),
+        &mut transactions,
+        own_account.as_deref(),
+    )
+    .await
+    {
+        Ok(()) => Ok(CommandResponse::ok(transactions)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn contact_upsert(
+    app: AppHandle<Wry>,
+    contacts: State<'_, ContactBook>,
+    payload: ContactPayload,
+) -> Result<CommandResponse<Contact>, String> {
+    let account_id = payload.account_id.trim();
+    let display_name = payload.display_name.trim();
+    if account_id.is_empty() || display_name.is_empty() {
+        return Ok(CommandResponse::error(
+            "A contact needs an account ID and a name".to_string(),
+        ));
+    }
+    let optional = |value: &Option<String>| {
+        value
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+    match contacts
+        .upsert(
+            &configured_network(&app),
+            account_id,
+            display_name,
+            optional(&payload.avatar_url).as_deref(),
+            optional(&payload.notes).as_deref(),
+        )
+        .await
+    {
+        Ok(contact) => Ok(CommandResponse::ok(contact)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn contact_list(
+    contacts: State<'_, ContactBook>,
+) -> Result<CommandResponse<Vec<Contact>>, String> {
+    match contacts.list().await {
+        Ok(list) => Ok(CommandResponse::ok(list)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn contact_delete(
+    app: AppHandle<Wry>,
+    contacts: State<'_, ContactBook>,
+    payload: ContactIdPayload,
+) -> Result<CommandResponse<bool>, String> {
+    match contacts
+        .delete(&configured_network(&app), &payload.account_id)
+        .await
+    {
+        Ok(deleted) => Ok(CommandResponse::ok(deleted)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn transaction_parser_validate(
+    state: State<'_, TransactionParserState>,
+    payload: TransactionBytesPayload,
+) -> Result<CommandResponse<Value>, String> {
+    let bridge = match state.bridge() {
+        Some(bridge) => bridge,
+        None => {
+            return Ok(CommandResponse::error(
+                "Transaction parser bridge is not available".to_string(),
+            ));
+        }
+    };
+
+    let result = bridge.validate(&payload.transaction_bytes).await?;
+    Ok(CommandResponse::ok(result))
+}
+
+#[tauri::command]
+async fn transaction_parser_parse(
+    state: State<'_, TransactionParserState>,
+    payload: TransactionBytesPayload,
+) -> Result<CommandResponse<Value>, String> {
+    let bridge = match state.bridge() {
+        Some(bridge) => bridge,
+        None => {
+            return Ok(CommandResponse::error(
+                "Transaction parser bridge is not available".to_string(),
+            ));
+        }
+    };
+
+    let result = bridge.parse(&payload.transaction_bytes).await?;
+    Ok(CommandResponse::ok(result))
+}
+
+#[tauri::command(rename = "execute-transaction-bytes")]
+async fn execute_transaction_bytes(
+    app: AppHandle<Wry>,
+    wallet_bridge: State<'_, WalletBridgeState>,
+    wallet_info: State<'_, Arc<Mutex<Option<WalletBridgeInfo>>>>,
+    entity_state: State<'_, Mutex<EntityService>>,
+    agent_state: State<'_, Mutex<AgentService>>,
+    mirror_state: State<'_, MirrorBridgeState>,
+    payload: ExecuteTransactionPayload,
+) -> Result<CommandResponse<Value>, String> {
+    wallet_bridge.set_app_handle(&app);
+
+    let network = {
+        let guard = wallet_info.lock().await;
+        match guard.as_ref() {
+            Some(info) => info.network.clone(),
+            None => {
+                return Ok(CommandResponse::error("No wallet connected".to_string()));
+            }
+        }
+    };
+
+    let ExecuteTransactionPayload {
+        transaction_bytes,
+        entity_context,
+        session_id,
+        message_id,
+        valid_duration_seconds,
+    } = payload;
+
+    let network_clone = network.clone();
+
+    match wallet_execute_bytes(wallet_bridge.inner(), transaction_bytes, network).await {
+        Ok(mut value) => {
+            if value
+                .get("success")
+                .and_then(Value::as_bool)
+                .unwrap_or(false)
+            {
+                let transaction_id = extract_transaction_id(&value);
+
+                if let (Some(tx_id), Some(session_id), Some(message_id)) = (
+                    transaction_id.as_deref(),
+                    session_id.as_deref(),
+                    message_id.as_deref(),
+                ) {
+                    let receipt = resolve_execution_receipt(
+                        &mirror_state,
+                        &value,
+                        tx_id,
+                        &network_clone,
+                        "executeTransactionBytes",
+                    )
+                    .await;
+                    record_execution_receipt(&app, session_id, message_id, receipt).await;
+                }
+
+                let mut derived_entity = extract_entity_from_response(&value);
+
+                if derived_entity.is_none()
+                    && let Some(ref tx_id) = transaction_id
+                {
+                    log::debug!(
+                        "execute_transaction_bytes: wallet response missing entity id; checking mirror for transaction {}",
+                        tx_id
+                    );
+                    let hydration = EntityHydration {
+                        transaction_id: tx_id.clone(),
+                        network: network_clone.clone(),
+                        valid_duration: valid_duration_seconds.map(TokioDuration::from_secs),
+                        entity_context: entity_context.clone(),
+                        session_id: None,
+                        metadata: json!({
+                            "entityContext": entity_context.clone().unwrap_or(Value::Null),
+                            "source": "executeTransactionBytes",
+                            "recordedAt": Utc::now().to_rfc3339(),
+                            "transactionId": tx_id,
+                            "network": network_clone,
+                        }),
+                    };
+                    match hydrate_entity_or_defer(&app, hydration).await {
+                        Ok(Some(entity)) => derived_entity = Some(entity),
+                        Ok(None) => value["entityHydration"] = json!("pending"),
+                        Err(reason) => log::debug!(
+                            "execute_transaction_bytes: no entity for transaction {}: {}",
+                            tx_id,
+                            reason
+                        ),
+                    }
+                }
+
+                if let Some((entity_id, entity_type)) = derived_entity {
+                    let entity_name =
+                        derive_entity_name(entity_context.as_ref(), &entity_type, &entity_id);
+                    let metadata_value = json!({
+                        "entityContext": entity_context.clone().unwrap_or(Value::Null),
+                        "source": "executeTransactionBytes",
+                        "recordedAt": Utc::now().to_rfc3339(),
+                        "transactionId": transaction_id,
+                        "network": network_clone,
+                    });
+
+                    let _ = persist_entity_association(
+                        &app,
+                        &entity_state,
+                        &agent_state,
+                        &entity_id,
+                        &entity_type,
+                        &entity_name,
+                        transaction_id.as_ref(),
+                        None,
+                        &metadata_value,
+                    )
+                    .await;
+
+                    enrich_transaction_response(
+                        &mut value,
+                        &entity_id,
+                        &entity_type,
+                        &entity_name,
+                        transaction_id.as_deref(),
+                    );
+                } else if let Some(ref tx_id) = transaction_id {
+                    log::info!(
+                        "execute_transaction_bytes: transaction {} completed without detectable entity metadata",
+                        tx_id
+                    );
+                } else {
+                    log::info!(
+                        "execute_transaction_bytes: wallet execution completed without transaction id"
+                    );
+                }
+            }
+
+            Ok(CommandResponse::ok(value))
+        }
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn wallet_hydrate_entity(
+    app: AppHandle<Wry>,
+    entity_state: State<'_, Mutex<EntityService>>,
+    agent_state: State<'_, Mutex<AgentService>>,
+    payload: WalletHydratePayload,
+) -> Result<CommandResponse<Value>, String> {
+    let network = payload.network.as_deref().unwrap_or("testnet");
+    let session_id_trimmed = payload.session_id.trim();
+    if session_id_trimmed.is_empty() {
+        return Ok(CommandResponse::error(
+            "Session ID is required to hydrate entity data".to_string(),
+        ));
+    }
+
+    log::debug!(
+        "wallet_hydrate_entity: hydrating transaction {} on network {}",
+        payload.transaction_id,
+        network
+    );
+
+    let metadata_value = json!({
+        "entityContext": payload.entity_context.clone().unwrap_or(Value::Null),
+        "source": "walletHydrateEntity",
+        "recordedAt": Utc::now().to_rfc3339(),
+        "transactionId": payload.transaction_id,
+        "network": network,
+        "sessionId": session_id_trimmed,
+    });
+    let hydration = EntityHydration {
+        transaction_id: payload.transaction_id.clone(),
+        network: network.to_string(),
+        valid_duration: payload.valid_duration_seconds.map(TokioDuration::from_secs),
+        entity_context: payload.entity_context.clone(),
+        session_id: Some(session_id_trimmed.to_string()),
+        metadata: metadata_value.clone(),
+    };
+
+    match hydrate_entity_or_defer(&app, hydration).await {
+        Ok(Some((entity_id, entity_type))) => {
+            let entity_name =
+                derive_entity_name(payload.entity_context.as_ref(), &entity_type, &entity_id);
+            let stored = persist_entity_association(
+                &app,
+                &entity_state,
+                &agent_state,
+                &entity_id,
+                &entity_type,
+                &entity_name,
+                Some(&payload.transaction_id),
+                Some(session_id_trimmed),
+                &metadata_value,
+            )
+            .await;
+
+            match stored {
+                Some(entity) => Ok(CommandResponse::ok(json!({ "entity": entity }))),
+                None => Ok(CommandResponse::error(
+                    "Failed to persist entity association".to_string(),
+                )),
+            }
+        }
+        Ok(None) => Ok(CommandResponse::ok(json!({
+            "entity": Value::Null,
+            "pending": true,
+        }))),
+        Err(reason) => {
+            log::warn!(
+                "wallet_hydrate_entity: no entity metadata for {}: {}",
+                payload.transaction_id,
+                reason
+            );
+            Ok(CommandResponse::error(reason))
+        }
+    }
+}
+
+#[tauri::command]
+async fn wallet_set_current(
+    app: AppHandle<Wry>,
+    state: State<'_, Arc<Mutex<Option<WalletBridgeInfo>>>>,
+    payload: WalletSetPayload,
+) -> Result<CommandResponse<bool>, String> {
+    if let Some(ref info) = payload.info {
+        match info.network.as_str() {
+            "mainnet" | "testnet" => {}
+            other => {
+                return Ok(CommandResponse::error(format!(
+                    "Unsupported wallet network: {other}"
+                )));
+            }
+        }
+    }
+
+    let connected = payload.info.is_some();
+    let arc = Arc::clone(&*state);
+    let mut guard = arc.lock().await;
+    *guard = payload.info;
+    drop(guard);
+    if connected {
+        tauri::async_runtime::spawn(dispatch_outbox(app.clone()));
+    }
+    Ok(CommandResponse::ok(true))
+}
+
+#[tauri::command]
+async fn wallet_status(
+    bridge: State<'_, WalletBridgeState>,
+    info: State<'_, Arc<Mutex<Option<WalletBridgeInfo>>>>,
+) -> Result<CommandResponse<Value>, String> {
+    let snapshot = wallet_status_json(bridge.inner(), &info).await;
+    Ok(CommandResponse::ok(snapshot))
+}
+
+#[tauri::command]
+async fn entity_get_all(
+    state: State<'_, Mutex<EntityService>>,
+    payload: Option<EntityFiltersPayload>,
+) -> Result<CommandResponse<EntityPage>, String> {
+    let filters = payload.unwrap_or_default();
+    let date_range = filters.date_range.unwrap_or_default();
+    let query = EntityListQuery {
+        search: filters.search,
+        entity_type: filters.entity_type,
+        session_id: filters.session_id,
+        created_after: date_range.start,
+        created_before: date_range.end,
+        include_inactive: filters.include_inactive,
+        sort: filters.sort,
+        cursor: filters.cursor,
+        page_size: filters.page_size.or(filters.limit),
+    };
+
+    match state.lock().await.list_entities_page(&query).await {
+        Ok(page) => Ok(CommandResponse::ok(page)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn entity_delete(
+    app: AppHandle<Wry>,
+    state: State<'_, Mutex<EntityService>>,
+    payload: EntityIdPayload,
+) -> Result<CommandResponse<Value>, String> {
+    let deleted = {
+        let service = state.lock().await;
+        service.deactivate_entity(&payload.entity_id).await?
+    };
+
+    if !deleted {
+        return Ok(CommandResponse::error(format!(
+            "Entity {} not found",
+            payload.entity_id
+        )));
+    }
+
+    if let Err(error) = app.emit("entity_deleted", payload.entity_id.clone()) {
+        log::warn!("Failed to emit entity_deleted event: {}", error);
+    }
+
+    Ok(CommandResponse::ok(json!({})))
+}
+
+#[tauri::command]
+async fn entity_restore(
+    app: AppHandle<Wry>,
+    state: State<'_, Mutex<EntityService>>,
+    payload: EntityIdPayload,
+) -> Result<CommandResponse<EntityAssociation>, String> {
+    let restored = {
+        let service = state.lock().await;
+        service.restore_entity(&payload.entity_id).await?
+    };
+
+    let Some(entity) = restored else {
+        return Ok(CommandResponse::error(format!(
+            "No deleted entity {} to restore",
+            payload.entity_id
+        )));
+    };
+
+    if let Err(error) = app.emit("entity_updated", entity.clone()) {
+        log::warn!("Failed to emit entity_updated event: {}", error);
+    }
+
+    Ok(CommandResponse::ok(entity))
+}
+
+#[tauri::command]
+async fn entity_bulk_delete(
+    app: AppHandle<Wry>,
+    state: State<'_, Mutex<EntityService>>,
+    payload: EntityBulkDeletePayload,
+) -> Result<CommandResponse<Value>, String> {
+    if payload.entity_ids.is_empty() {
+        return Ok(CommandResponse::error("No entity IDs provided".to_string()));
+    }
+
+    let mut successful: Vec<String> = Vec::new();
+    let mut failed: Vec<Value> = Vec::new();
+
+    {
+        let service = state.lock().await;
+        for entity_id in &payload.entity_ids {
+            match service.deactivate_entity(entity_id).await {
+                Ok(true) => {
+                    successful.push(entity_id.clone());
+                    if let Err(error) = app.emit("entity_deleted", entity_id.clone()) {
+                        log::warn!("Failed to emit entity_deleted event: {}", error);
+                    }
+                }
+                Ok(false) => failed.push(json!({
+                    "entityId": entity_id,
+                    "error": "Entity not found"
+                })),
+                Err(error) => failed.push(json!({
+                    "entityId": entity_id,
+                    "error": error
+                })),
+            }
+        }
+    }
+
+    Ok(CommandResponse::ok(json!({
+        "successful": successful,
+        "failed": failed,
+        "totalRequested": payload.entity_ids.len()
+    })))
+}
+
+#[tauri::command]
+async fn entity_purge(
+    app: AppHandle<Wry>,
+    state: State<'_, Mutex<EntityService>>,
+    payload: EntityIdPayload,
+) -> Result<CommandResponse<Value>, String> {
+    let purged = {
+        let service = state.lock().await;
+        service.purge_entity(&payload.entity_id).await?
+    };
+
+    if purged == 0 {
+        return Ok(CommandResponse::error(format!(
+            "No deleted entity {} to purge",
+            payload.entity_id
+        )));
+    }
+
+    if let Err(error) = app.emit("entity_purged", payload.entity_id.clone()) {
+        log::warn!("Failed to emit entity_purged event: {}", error);
+    }
+
+    Ok(CommandResponse::ok(json!({ "rowsPurged": purged })))
+}
+
+#[tauri::command]
+async fn entity_bulk_purge(
+    app: AppHandle<Wry>,
+    state: State<'_, Mutex<EntityService>>,
+    payload: EntityBulkDeletePayload,
+) -> Result<CommandResponse<Value>, String> {
+    if payload.entity_ids.is_empty() {
+        return Ok(CommandResponse::error("No entity IDs provided".to_string()));
+    }
+
+    let mut successful: Vec<String> = Vec::new();
+    let mut failed: Vec<Value> = Vec::new();
+
+    {
+        let service = state.lock().await;
+        for entity_id in &payload.entity_ids {
+            match service.purge_entity(entity_id).await {
+                Ok(0) => failed.push(json!({
+                    "entityId": entity_id,
+                    "error": "No deleted entity to purge"
+                })),
+                Ok(_) => {
+                    successful.push(entity_id.clone());
+                    if let Err(error) = app.emit("entity_purged", entity_id.clone()) {
+                        log::warn!("Failed to emit entity_purged event: {}", error);
+                    }
+                }
+                Err(error) => failed.push(json!({
+                    "entityId": entity_id,
+                    "error": error
+                })),
+            }
+        }
+    }
+
+    Ok(CommandResponse::ok(json!({
+        "successful": successful,
+        "failed": failed,
+        "totalRequested": payload.entity_ids.len()
+    })))
+}
+
+#[tauri::command]
+async fn entity_rename(
+    app: AppHandle<Wry>,
+    state: State<'_, Mutex<EntityService>>,
+    payload: EntityRenamePayload,
+) -> Result<CommandResponse<EntityAssociation>, String> {
+    let trimmed = payload.new_name.trim();
+    if trimmed.is_empty() {
+        return Ok(CommandResponse::error(
+            "Entity name cannot be empty".to_string(),
+        ));
+    }
+
+    let renamed = {
+        let service = state.lock().await;
+        service.rename_entity(&payload.entity_id, trimmed).await?
+    };
+
+    let Some(entity) = renamed else {
+        return Ok(CommandResponse::error("Entity not found".to_string()));
+    };
+
+    if let Err(error) = app.emit("entity_updated", entity.clone()) {
+        log::warn!("Failed to emit entity_updated event: {}", error);
+    }
+
+    Ok(CommandResponse::ok(entity))
+}
+
+#[tauri::command]
+async fn entity_update_notes(
+    app: AppHandle<Wry>,
+    state: State<'_, Mutex<EntityService>>,
+    payload: EntityNotesPayload,
+) -> Result<CommandResponse<EntityAssociation>, String> {
+    let updated = {
+        let service = state.lock().await;
+        service
+            .update_notes(&payload.entity_id, &payload.notes)
+            .await?
+    };
+
+    let Some(entity) = updated else {
+        return Ok(CommandResponse::error("Entity not found".to_string()));
+    };
+
+    if let Err(error) = app.emit("entity_updated", entity.clone()) {
+        log::warn!("Failed to emit entity_updated event: {}", error);
+    }
+
+    Ok(CommandResponse::ok(entity))
+}
+
+#[tauri::command]
+async fn entity_export(
+    state: State<'_, Mutex<EntityService>>,
+    payload: EntityExportPayload,
+) -> Result<CommandResponse<Value>, String> {
+    let filters = payload.filters.unwrap_or_default();
+    let format = payload.format.unwrap_or_else(|| "json".to_string());
+
+    let mut entities = {
+        let service = state.lock().await;
+        service
+            .list_entities(
+                filters.entity_type.clone(),
+                filters.session_id.clone(),
+                filters.limit.or(Some(10_000)),
+                filters.include_inactive,
+            )
+            .await?
+    };
+
+    filter_entities_in_place(
+        &mut entities,
+        filters.search.as_deref(),
+        filters.date_range.as_ref(),
+    );
+
+    let timestamp = Utc::now().format("%Y-%m-%dT%H-%M-%S").to_string();
+    let (data, filename) = if format.eq_ignore_ascii_case("csv") {
+        let headers = [
+            "entityId",
+            "entityName",
+            "entityType",
+            "transactionId",
+            "sessionId",
+            "createdAt",
+            "updatedAt",
+            "isActive",
+            "metadata",
+            "notes",
+        ];
+
+        let mut lines = Vec::with_capacity(entities.len() + 1);
+        lines.push(headers.join(","));
+
+        for entity in &entities {
+            let metadata = entity
+                .metadata
+                .as_ref()
+                .map(|value| value.to_string())
+                .unwrap_or_default();
+
+            let row = [
+                entity.entity_id.as_str(),
+                entity.entity_name.as_str(),
+                entity.entity_type.as_str(),
+                entity.transaction_id.as_deref().unwrap_or(""),
+                entity.session_id.as_deref().unwrap_or(""),
+                entity.created_at.as_str(),
+                entity.updated_at.as_str(),
+                if entity.is_active { "true" } else { "false" },
+                metadata.as_str(),
+                entity.notes.as_deref().unwrap_or(""),
+            ];
+
+            let escaped = row
+                .iter()
+                .map(|value| escape_csv(value))
+                .collect::<Vec<_>>()
+                .join(",");
+            lines.push(escaped);
+        }
+
+        (
+            lines.join("\n"),
+            format!("entities-export-{}.csv", timestamp),
+        )
+    } else if format.eq_ignore_ascii_case("xlsx") {
+        let workbook = build_entities_xlsx(&entities)
+            .map_err(|error| format!("Failed to build XLSX export: {error}"))?;
+        (
+            STANDARD.encode(workbook),
+            format!("entities-export-{}.xlsx", timestamp),
+        )
+    } else {
+        let json_data = serde_json::to_string_pretty(&entities).map_err(|err| err.to_string())?;
+        (json_data, format!("entities-export-{}.json", timestamp))
+    };
+
+    // XLSX is binary and travels base64-encoded; the text formats do not.
+    let encoding = if filename.ends_with(".xlsx") {
+        "base64"
+    } else {
+        "utf8"
+    };
+
+    Ok(CommandResponse::ok(json!({
+        "data": data,
+        "filename": filename,
+        "encoding": encoding,
+        "count": entities.len()
+    })))
+}
+
+/// The entity's current state on the mirror node, shaped by
+/// `entity_live_state`.
+async fn fetch_entity_live_state(
+    app: &AppHandle<Wry>,
+    bridge: &MirrorNodeBridge,
+    entity: &EntityAssociation,
+) -> Result<Value, String> {
+    let Some(kind) = EntityRefreshKind::from_entity_type(entity.entity_type) else {
+        return Err(format!(
+            "Refreshing {} entities is not supported",
+            entity.entity_type
+        ));
+    };
+    let network = entity
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.get("network"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| configured_network(app));
+    let mirror_network = MirrorNetwork::try_from_str(Some(&network))?;
+    let fetched = match kind {
+        EntityRefreshKind::Token => {
+            bridge
+                .get_token_info(&entity.entity_id, mirror_network)
+                .await
+        }
+        EntityRefreshKind::Account => {
+            bridge
+                .get_account_info(&entity.entity_id, mirror_network)
+                .await
+        }
+        EntityRefreshKind::Topic => {
+            bridge
+                .get_topic_info(
+                    &entity.entity_id,
+                    mirror_network,
+                    &mirror_url_for(mirror_network.as_str()),
+                )
+                .await
+        }
+    };
+    match fetched? {
+        Some(raw) => Ok(entity_live_state(kind, &raw, mirror_network.as_str())),
+        None => Err(format!("{} was not found on {}", entity.entity_id, network)),
+    }
+}
+
+/// Pulls the entity's current state from the mirror node into its metadata
+/// and tells the renderer through `entity_updated`.
+#[tauri::command]
+async fn entity_refresh(
+    app: AppHandle<Wry>,
+    state: State<'_, Mutex<EntityService>>,
+    mirror_state: State<'_, MirrorBridgeState>,
+    payload: EntityIdPayload,
+) -> Result<CommandResponse<EntityAssociation>, String> {
+    let Some(entity) = state.lock().await.get_entity(&payload.entity_id).await? else {
+        return Ok(CommandResponse::error("Entity not found".to_string()));
+    };
+    let Some(bridge) = mirror_state.bridge() else {
+        return Ok(CommandResponse::error(
+            "Mirror node bridge is not available".to_string(),
+        ));
+    };
+
+    let live_state = match fetch_entity_live_state(&app, &bridge, &entity).await {
+        Ok(live_state) => live_state,
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+    let Some(updated) = state
+        .lock()
+        .await
+        .set_metadata_field(&entity.entity_id, "liveState", live_state)
+        .await?
+    else {
+        return Ok(CommandResponse::error("Entity not found".to_string()));
+    };
+
+    if let Err(error) = app.emit("entity_updated", updated.clone()) {
+        log::warn!("Failed to emit entity_updated event: {}", error);
+    }
+    Ok(CommandResponse::ok(updated))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntityWatchPayload {
+    entity_id: String,
+    watched: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntityFavoritePayload {
+    entity_id: String,
+    favorite: bool,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntityStatsPayload {
+    #[serde(default)]
+    timeline_days: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntityAuditLogPayload {
+    #[serde(default)]
+    entity_id: Option<String>,
+    #[serde(default)]
+    limit: Option<u32>,
+}
+
+#[tauri::command]
+async fn entity_set_watched(
+    app: AppHandle<Wry>,
+    state: State<'_, Mutex<EntityService>>,
+    payload: EntityWatchPayload,
+) -> Result<CommandResponse<EntityAssociation>, String> {
+    let Some(entity) = state.lock().await.get_entity(&payload.entity_id).await? else {
+        return Ok(CommandResponse::error("Entity not found".to_string()));
+    };
+    if payload.watched && EntityRefreshKind::from_entity_type(entity.entity_type).is_none() {
+        return Ok(CommandResponse::error(format!(
+            "Watching {} entities is not supported",
+            entity.entity_type
+        )));
+    }
+
+    let Some(updated) = state
+        .lock()
+        .await
+        .set_watched(&payload.entity_id, payload.watched)
+        .await?
+    else {
+        return Ok(CommandResponse::error("Entity not found".to_string()));
+    };
+
+    if let Err(error) = app.emit("entity_updated", updated.clone()) {
+        log::warn!("Failed to emit entity_updated event: {}", error);
+    }
+    Ok(CommandResponse::ok(updated))
+}
+
+#[tauri::command]
+async fn entity_set_favorite(
+    app: AppHandle<Wry>,
+    state: State<'_, Mutex<EntityService>>,
+    payload: EntityFavoritePayload,
+) -> Result<CommandResponse<EntityAssociation>, String> {
+    let Some(updated) = state
+        .lock()
+        .await
+        .set_favorite(&payload.entity_id, payload.favorite)
+        .await?
+    else {
+        return Ok(CommandResponse::error("Entity not found".to_string()));
+    };
+
+    if let Err(error) = app.emit("entity_updated", updated.clone()) {
+        log::warn!("Failed to emit entity_updated event: {}", error);
+    }
+    Ok(CommandResponse::ok(updated))
+}
+
+#[tauri::command]
+async fn entity_list_watched(
+    state: State<'_, Mutex<EntityService>>,
+) -> Result<CommandResponse<Vec<EntityAssociation>>, String> {
+    match state.lock().await.list_watched().await {
+        Ok(entities) => Ok(CommandResponse::ok(entities)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn entity_stats(
+    state: State<'_, Mutex<EntityService>>,
+    payload: Option<EntityStatsPayload>,
+) -> Result<CommandResponse<EntityStats>, String> {
+    let payload = payload.unwrap_or_default();
+    match state.lock().await.get_stats(payload.timeline_days).await {
+        Ok(stats) => Ok(CommandResponse::ok(stats)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn entity_get_audit_log(
+    state: State<'_, Mutex<EntityService>>,
+    payload: EntityAuditLogPayload,
+) -> Result<CommandResponse<Vec<EntityAuditEntry>>, String> {
+    let entity_id = payload
+        .entity_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|entity_id| !entity_id.is_empty());
+    match state
+        .lock()
+        .await
+        .get_audit_log(entity_id, payload.limit)
+        .await
+    {
+        Ok(entries) => Ok(CommandResponse::ok(entries)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn entity_get_by_id(
+    state: State<'_, Mutex<EntityService>>,
+    payload: EntityIdPayload,
+) -> Result<CommandResponse<EntityAssociation>, String> {
     let entity = {
         let service = state.lock().await;
         service.get_entity(&payload.entity_id).await?
     };
 
-    match entity {
-        Some(current) => Ok(CommandResponse::ok(current)),
-        None => Ok(CommandResponse::error("Entity not found".to_string())),
+    match entity {
+        Some(current) => Ok(CommandResponse::ok(current)),
+        None => Ok(CommandResponse::error("Entity not found".to_string())),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ValidateEntityIdPayload {
+    entity_id: String,
+    #[serde(default)]
+    network: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EntityIdValidation {
+    valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entity_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    with_checksum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<EntityIdError>,
+}
+
+#[tauri::command]
+async fn entity_validate_id(
+    app: AppHandle<Wry>,
+    payload: ValidateEntityIdPayload,
+) -> Result<CommandResponse<EntityIdValidation>, String> {
+    let network = payload.network.unwrap_or_else(|| configured_network(&app));
+    let validation = match parse_entity_id(&payload.entity_id, &network)
+        .and_then(|id| Ok((id.to_string(), id.with_checksum(&network)?)))
+    {
+        Ok((entity_id, with_checksum)) => EntityIdValidation {
+            valid: true,
+            entity_id: Some(entity_id),
+            with_checksum: Some(with_checksum),
+            error: None,
+        },
+        Err(error) => EntityIdValidation {
+            valid: false,
+            entity_id: None,
+            with_checksum: None,
+            error: Some(error),
+        },
+    };
+    Ok(CommandResponse::ok(validation))
+}
+
+#[tauri::command]
+async fn entity_search(
+    state: State<'_, Mutex<EntityService>>,
+    payload: EntitySearchPayload,
+) -> Result<CommandResponse<Vec<EntityAssociation>>, String> {
+    let EntitySearchPayload { query, entity_type } = payload;
+    let trimmed = query.trim().to_string();
+
+    if trimmed.is_empty() {
+        return Ok(CommandResponse::error(
+            "Search query is required".to_string(),
+        ));
+    }
+
+    let results = {
+        let service = state.lock().await;
+        service
+            .search_entities(&trimmed, entity_type.as_deref(), 50)
+            .await?
+    };
+
+    Ok(CommandResponse::ok(results))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HederaCredentialsPayload {
+    account_id: String,
+    private_key: String,
+    network: String,
+}
+
+impl TryFrom<HederaCredentialsPayload> for HederaCredentials {
+    type Error = String;
+
+    fn try_from(value: HederaCredentialsPayload) -> Result<Self, Self::Error> {
+        let network = match value.network.to_lowercase().as_str() {
+            "mainnet" => HederaNetwork::Mainnet,
+            "testnet" => HederaNetwork::Testnet,
+            _ => return Err("Unsupported Hedera network".to_string()),
+        };
+
+        Ok(Self {
+            account_id: value.account_id,
+            private_key: value.private_key,
+            network,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct LlmCredentialsPayload {
+    api_key: String,
+    model: String,
+    #[serde(default)]
+    base_url: Option<String>,
+}
+
+impl From<LlmCredentialsPayload> for LlmCredentials {
+    fn from(value: LlmCredentialsPayload) -> Self {
+        Self {
+            api_key: value.api_key,
+            model: value.model,
+            base_url: value.base_url,
+        }
+    }
+}
+
+#[tauri::command]
+async fn connection_test_hedera(
+    state: State<'_, Mutex<ConnectionService>>,
+    credentials: HederaCredentialsPayload,
+) -> Result<HederaTestResponse, String> {
+    let converted = HederaCredentials::try_from(credentials)?;
+    state
+        .lock()
+        .await
+        .test_hedera(converted)
+        .await
+        .map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+async fn connection_test_openai(
+    state: State<'_, Mutex<ConnectionService>>,
+    credentials: LlmCredentialsPayload,
+) -> Result<LlmTestResponse, String> {
+    // Cloned so the lock is not held while a gateway check waits its turn.
+    let connection = state.lock().await.clone();
+    connection
+        .test_openai(credentials.into())
+        .await
+        .map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+async fn connection_test_anthropic(
+    state: State<'_, Mutex<ConnectionService>>,
+    credentials: LlmCredentialsPayload,
+) -> Result<LlmTestResponse, String> {
+    state
+        .lock()
+        .await
+        .test_anthropic(credentials.into())
+        .await
+        .map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+async fn connection_test_openrouter(
+    state: State<'_, Mutex<ConnectionService>>,
+    credentials: LlmCredentialsPayload,
+) -> Result<LlmTestResponse, String> {
+    state
+        .lock()
+        .await
+        .test_openrouter(credentials.into())
+        .await
+        .map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+async fn connection_test_gemini(
+    state: State<'_, Mutex<ConnectionService>>,
+    credentials: LlmCredentialsPayload,
+) -> Result<LlmTestResponse, String> {
+    state
+        .lock()
+        .await
+        .test_gemini(credentials.into())
+        .await
+        .map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+async fn provider_get_rate_status(
+    state: State<'_, Arc<ProviderRateLimiter>>,
+) -> Result<CommandResponse<RateStatusReport>, String> {
+    Ok(CommandResponse::ok(state.status(Utc::now())))
+}
+
+async fn relay_endpoint(app: &AppHandle<Wry>) -> Result<RelayEndpoint, String> {
+    let config = load_config(app.clone(), app.state::<ConfigState>())?.config;
+    let local_port = app.state::<LocalRelay>().port().await;
+    resolve_endpoint(
+        &configured_network(app),
+        &config.advanced.json_rpc_relay,
+        local_port,
+    )
+}
+
+#[tauri::command]
+async fn relay_get_endpoint(app: AppHandle<Wry>) -> Result<CommandResponse<RelayEndpoint>, String> {
+    match relay_endpoint(&app).await {
+        Ok(endpoint) => Ok(CommandResponse::ok(endpoint)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+/// MetaMask and Foundry settings pointing at the relay for the network the
+/// app is configured for.
+#[tauri::command]
+async fn relay_tool_settings(
+    app: AppHandle<Wry>,
+) -> Result<CommandResponse<RelayToolSettings>, String> {
+    match relay_endpoint(&app).await {
+        Ok(endpoint) => Ok(CommandResponse::ok(tool_settings(endpoint))),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn relay_health(app: AppHandle<Wry>) -> Result<CommandResponse<RelayHealth>, String> {
+    let endpoint = match relay_endpoint(&app).await {
+        Ok(endpoint) => endpoint,
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+    let client = match proxy::client() {
+        Ok(client) => client,
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+    Ok(CommandResponse::ok(check_health(&client, &endpoint).await))
+}
+
+/// Starts the configured local relay with the app's operator account.
+#[tauri::command]
+async fn relay_start_local(
+    app: AppHandle<Wry>,
+    relay: State<'_, LocalRelay>,
+) -> Result<CommandResponse<RelayEndpoint>, String> {
+    let config = load_config(app.clone(), app.state::<ConfigState>())?.config;
+    let network = configured_network(&app);
+    let mirror_node_url = configured_mirror_url(&app);
+    if config.hedera.account_id.trim().is_empty()
+        || config.hedera.private_key.expose().trim().is_empty()
+    {
+        return Ok(CommandResponse::error(
+            "The local relay needs a Hedera account and private key".to_string(),
+        ));
+    }
+
+    let env = LocalRelayEnv {
+        network: &network,
+        mirror_node_url: mirror_node_url.trim_end_matches("/api/v1"),
+        operator_id: &config.hedera.account_id,
+        operator_key: config.hedera.private_key.expose(),
+    };
+    if let Err(error) = relay.start(&config.advanced.json_rpc_relay, env).await {
+        return Ok(CommandResponse::error(error));
+    }
+    match relay_endpoint(&app).await {
+        Ok(endpoint) => Ok(CommandResponse::ok(endpoint)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn relay_stop_local(relay: State<'_, LocalRelay>) -> Result<CommandResponse<bool>, String> {
+    Ok(CommandResponse::ok(relay.stop().await))
+}
+
+#[tauri::command]
+async fn agent_initialize(
+    app: AppHandle<Wry>,
+    state: State<'_, Mutex<AgentService>>,
+    mcp_state: State<'_, Mutex<McpService>>,
+    credential_state: State<'_, Mutex<CredentialManager>>,
+    wallet_bridge: State<'_, WalletBridgeState>,
+    wallet_info: State<'_, Arc<Mutex<Option<WalletBridgeInfo>>>>,
+    mut config: AgentInitializeConfig,
+) -> Result<AgentInitializeResponse, String> {
+    {
+        let mcp = mcp_state.lock().await;
+        let group = config
+            .mcp_group
+            .as_deref()
+            .map(str::trim)
+            .filter(|group| !group.is_empty());
+        if config.mcp_servers.is_none() {
+            if let Some(group) = group {
+                config.mcp_servers = Some(Value::Array(mcp.group_servers(group).await?));
+            } else if let Ok(servers) = mcp.load().await {
+                config.mcp_servers = Some(Value::Array(servers));
+            }
+        }
+        // The bridge spawns these servers itself, so it gets resolved env
+        // values; a server whose references cannot be resolved is left out.
+        if let Some(Value::Array(servers)) = config.mcp_servers.take() {
+            let mut resolved = Vec::with_capacity(servers.len());
+            for server in servers {
+                match mcp.resolve_server_env(&server).await {
+                    Ok(server) => resolved.push(server),
+                    Err(error) => log::warn!(
+                        "Skipping MCP server {} for the agent: {}",
+                        server
+                            .get("id")
+                            .and_then(Value::as_str)
+                            .unwrap_or("<unknown>"),
+                        error
+                    ),
+                }
+            }
+            config.mcp_servers = Some(Value::Array(resolved));
+        }
+    }
+    let app_config = load_config(app.clone(), app.state::<ConfigState>())
+        .map(|response| response.config)
+        .ok();
+    let backend_config = app_config
+        .as_ref()
+        .map(|app_config| app_config.advanced.agent_backend.clone())
+        .unwrap_or_default();
+    // The bridge takes one key and endpoint for whichever provider is
+    // picked; blank ones fall back to that provider's saved settings.
+    let provider = config.llm_provider.as_deref().map_or(
+        Some(config::LlmProvider::Openai),
+        config::LlmProvider::from_id,
+    );
+    if let Some(app_config) = app_config.as_ref()
+        && let Some(provider) = provider
+    {
+        let saved = app_config.provider(provider);
+        if config.open_ai_base_url.is_none() {
+            config.open_ai_base_url = saved.base_url.clone();
+        }
+        if config.open_ai_api_key.expose().trim().is_empty() {
+            config.open_ai_api_key = saved.api_key.clone();
+        }
+        if config
+            .model_name
+            .as_deref()
+            .is_none_or(|model| model.trim().is_empty())
+            && !saved.model.trim().is_empty()
+        {
+            config.model_name = Some(saved.model.clone());
+        }
+    }
+    {
+        let credentials = credential_state.lock().await;
+        let bridge_credentials = credentials.scoped(CredentialConsumer::AgentBridge);
+        config.open_ai_api_key = bridge_credentials
+            .resolve(config.open_ai_api_key.expose())
+            .await
+            .map_err(|error| error.to_string())?
+            .into();
+        config.private_key = bridge_credentials
+            .resolve(config.private_key.expose())
+            .await
+            .map_err(|error| error.to_string())?
+            .into();
+
+        let registry = state.lock().await.registry().await;
+        match backend_config
+            .remote_url
+            .as_deref()
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+        {
+            Some(url) => {
+                let api_key = match backend_config.remote_api_key.as_ref() {
+                    Some(api_key) => Some(
+                        bridge_credentials
+                            .resolve(api_key.expose())
+                            .await
+                            .map_err(|error| error.to_string())?,
+                    ),
+                    None => None,
+                };
+                registry.register(
+                    REMOTE_BACKEND_ID,
+                    Arc::new(RemoteHttpFactory::new(url.to_string(), api_key)),
+                );
+            }
+            None => {
+                registry.unregister(REMOTE_BACKEND_ID);
+            }
+        }
+    }
+    if config.backend.is_none() {
+        config.backend = backend_config.backend;
+    }
+    wallet_bridge.set_app_handle(&app);
+    let bridge_clone = wallet_bridge.inner().clone();
+    let wallet_info_arc = Arc::clone(&*wallet_info);
+    let wallet_account_log = wallet_info_arc
+        .lock()
+        .await
+        .as_ref()
+        .map(|info| info.account_id.clone())
+        .unwrap_or_else(|| "<none>".to_string());
+    log::info!(
+        "agent_initialize payload: account={} user_account={} op_mode={} private_key_present={} wallet_active={}",
+        config.account_id,
+        config
+            .user_account_id
+            .clone()
+            .unwrap_or_else(|| "<none>".to_string()),
+        config
+            .operational_mode
+            .clone()
+            .unwrap_or_else(|| "<unspecified>".to_string()),
+        !config.private_key.expose().trim().is_empty(),
+        wallet_account_log
+    );
+    let response = state
+        .lock()
+        .await
+        .initialize(config, bridge_clone, wallet_info_arc)
+        .await
+        .map_err(|error| error.to_string())?;
+    if response.success {
+        tauri::async_runtime::spawn(dispatch_outbox(app.clone()));
+    }
+    Ok(response)
+}
+
+#[tauri::command]
+async fn agent_status(
+    state: State<'_, Mutex<AgentService>>,
+) -> Result<CommandResponse<AgentStatusResponse>, String> {
+    Ok(CommandResponse::ok(state.lock().await.status().await))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AgentBackendList {
+    backends: Vec<BackendDescriptor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    active: Option<String>,
+}
+
+#[tauri::command]
+async fn agent_list_backends(
+    state: State<'_, Mutex<AgentService>>,
+) -> Result<CommandResponse<AgentBackendList>, String> {
+    let (backends, active) = state.lock().await.backends().await;
+    Ok(CommandResponse::ok(AgentBackendList { backends, active }))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AgentSelectBackendPayload {
+    backend_id: String,
+}
+
+/// Moves the running agent session to another registered backend.
+#[tauri::command]
+async fn agent_select_backend(
+    app: AppHandle<Wry>,
+    state: State<'_, Mutex<AgentService>>,
+    wallet_bridge: State<'_, WalletBridgeState>,
+    wallet_info: State<'_, Arc<Mutex<Option<WalletBridgeInfo>>>>,
+    payload: AgentSelectBackendPayload,
+) -> Result<AgentInitializeResponse, String> {
+    wallet_bridge.set_app_handle(&app);
+    Ok(state
+        .lock()
+        .await
+        .switch_backend(
+            payload.backend_id.trim(),
+            wallet_bridge.inner().clone(),
+            Arc::clone(&*wallet_info),
+        )
+        .await)
+}
+
+#[tauri::command]
+async fn agent_disconnect(
+    state: State<'_, Mutex<AgentService>>,
+) -> Result<CommandResponse<()>, String> {
+    state.lock().await.disconnect().await;
+    Ok(CommandResponse::ok(()))
+}
+
+/// Fits `history` into the model's context window with the strategy picked
+/// in `advanced.contextWindow`. Takes the session lock only for its reads
+/// and writes, so summarizing does not block other chat commands.
+async fn compact_chat_history(
+    app: &AppHandle<Wry>,
+    sessions: &Mutex<SessionService>,
+    session_id: &str,
+    settings: Option<&AgentLlmSettings>,
+    history: Vec<ChatEntry>,
+) -> Vec<ChatEntry> {
+    let config = load_config(app.clone(), app.state::<ConfigState>())
+        .map(|response| response.config.advanced.context_window)
+        .unwrap_or_default();
+    let model = settings
+        .and_then(|settings| settings.model.as_deref())
+        .unwrap_or_default();
+    match config.strategy {
+        ContextStrategy::Summarize => {
+            summarize_history(app, sessions, session_id, settings, history).await
+        }
+        ContextStrategy::TruncateOldest => summarization::truncate_oldest(&history, model).to_vec(),
+        ContextStrategy::SlidingWindow => {
+            let pinned = sessions
+                .lock()
+                .await
+                .list_pinned_messages(session_id)
+                .await
+                .unwrap_or_else(|error| {
+                    log::warn!(
+                        "Failed to load the pinned messages of session {}: {}",
+                        session_id,
+                        error
+                    );
+                    Vec::new()
+                });
+            let pinned: Vec<ChatEntry> = pinned.iter().map(summarization::chat_entry).collect();
+            summarization::sliding_window(&history, &pinned, config.window_messages, model)
+        }
+    }
+}
+
+/// Stands the session's rolling summary in for the part of `history` that
+/// no longer fits the model's context window, extending the summary first
+/// when more has to go. The full history is sent if that fails.
+async fn summarize_history(
+    app: &AppHandle<Wry>,
+    sessions: &Mutex<SessionService>,
+    session_id: &str,
+    settings: Option<&AgentLlmSettings>,
+    history: Vec<ChatEntry>,
+) -> Vec<ChatEntry> {
+    let summary = sessions
+        .lock()
+        .await
+        .session_summary(session_id)
+        .await
+        .unwrap_or_else(|error| {
+            log::warn!(
+                "Failed to load the summary of session {}: {}",
+                session_id,
+                error
+            );
+            None
+        });
+    let model = settings
+        .and_then(|settings| settings.model.as_deref())
+        .unwrap_or_default();
+    let plan = summarization::plan(&history, summary.as_ref(), model);
+    let mut text = summary
+        .filter(|_| plan.covered > 0)
+        .map(|summary| summary.text);
+    let mut covered = plan.covered;
+
+    if plan.needs_summary() {
+        let folded = &history[plan.covered..plan.summarize_to];
+        let summarized = match settings {
+            Some(settings) => summarize_entries(app, settings, text.as_deref(), folded).await,
+            None => Err("Agent session is not initialized".to_string()),
+        };
+        match summarized {
+            Ok(summary) => {
+                // Entries without a message id can be summarized for this
+                // request but not matched again later.
+                if let Some(last) = history[plan.summarize_to - 1].id.clone() {
+                    let stored = SessionSummary {
+                        text: summary.clone(),
+                        covered_through: last,
+                        updated_at: Utc::now().to_rfc3339(),
+                    };
+                    if let Err(error) = sessions
+                        .lock()
+                        .await
+                        .set_session_summary(session_id, Some(&stored))
+                        .await
+                    {
+                        log::warn!(
+                            "Failed to save the summary of session {}: {}",
+                            session_id,
+                            error
+                        );
+                    }
+                }
+                text = Some(summary);
+                covered = plan.summarize_to;
+            }
+            Err(error) => {
+                log::warn!("Failed to summarize session {}: {}", session_id, error);
+            }
+        }
+    }
+
+    summarization::with_summary(text.as_deref(), &history[covered..])
+}
+
+async fn summarize_entries(
+    app: &AppHandle<Wry>,
+    settings: &AgentLlmSettings,
+    previous: Option<&str>,
+    entries: &[ChatEntry],
+) -> Result<String, String> {
+    let request = summarization::completion_request(settings, previous, entries)?;
+    let rate_limiter = app.state::<Arc<ProviderRateLimiter>>().inner().clone();
+    rate_limiter.acquire(request.provider).await;
+    let connection = app.state::<Mutex<ConnectionService>>().lock().await.clone();
+    let provider = request.provider;
+    let completion = connection
+        .complete(request)
+        .await
+        .map_err(|error| error.to_string())?;
+    rate_limiter.observe(
+        &ProviderHeaders {
+            provider: provider.id().to_string(),
+            headers: completion.headers.clone(),
+        },
+        Utc::now(),
+    );
+    let text = completion.text.trim();
+    if text.is_empty() {
+        return Err("The model returned an empty summary".to_string());
+    }
+    Ok(text.to_string())
+}
+
+/// Inputs per provider embeddings request.
+const EMBEDDING_BATCH: usize = 64;
+
+fn rag_config(app: &AppHandle<Wry>) -> RagConfig {
+    load_config(app.clone(), app.state::<ConfigState>())
+        .map(|response| response.config.advanced.rag)
+        .unwrap_or_default()
+}
+
+async fn embed_texts(
+    app: &AppHandle<Wry>,
+    embedder: &Embedder,
+    texts: Vec<String>,
+) -> Result<Vec<Vec<f32>>, String> {
+    let (settings, model) = match embedder {
+        Embedder::Local => {
+            return Ok(texts
+                .iter()
+                .map(|text| rag::local_embedding(text))
+                .collect());
+        }
+        Embedder::Provider { settings, model } => (settings, model),
+    };
+    let rate_limiter = app.state::<Arc<ProviderRateLimiter>>().inner().clone();
+    let connection = app.state::<Mutex<ConnectionService>>().lock().await.clone();
+    let mut vectors = Vec::with_capacity(texts.len());
+    for batch in texts.chunks(EMBEDDING_BATCH) {
+        rate_limiter.acquire(settings.provider).await;
+        let embeddings = connection
+            .embed(EmbeddingRequest {
+                provider: settings.provider,
+                api_key: settings.api_key.clone(),
+                model: model.clone(),
+                base_url: settings.base_url.clone(),
+                inputs: batch.to_vec(),
+            })
+            .await
+            .map_err(|error| error.to_string())?;
+        rate_limiter.observe(
+            &ProviderHeaders {
+                provider: settings.provider.id().to_string(),
+                headers: embeddings.headers,
+            },
+            Utc::now(),
+        );
+        vectors.extend(embeddings.vectors);
+    }
+    Ok(vectors)
+}
+
+async fn search_documents(
+    app: &AppHandle<Wry>,
+    agent: &AgentService,
+    config: &RagConfig,
+    content: &str,
+) -> Result<Vec<RetrievedChunk>, String> {
+    let embedder = match config.embedder {
+        RagEmbedder::Local => Embedder::Local,
+        RagEmbedder::Provider => Embedder::provider(agent.llm_settings().await?)?,
+    };
+    let query = embed_texts(app, &embedder, vec![content.to_string()])
+        .await?
+        .pop()
+        .unwrap_or_default();
+    app.state::<DocumentStore>()
+        .search(&embedder.id(), &query, config.top_k, config.min_score)
+        .await
+}
+
+/// The indexed chunks closest to `content`, as an entry to append to the chat
+/// history. A failed lookup is only logged so the message still goes out.
+async fn retrieve_documents(
+    app: &AppHandle<Wry>,
+    agent: &AgentService,
+    content: &str,
+) -> Option<ChatEntry> {
+    let config = rag_config(app);
+    if !config.retrieve || config.top_k == 0 || content.trim().is_empty() {
+        return None;
+    }
+    if app
+        .state::<DocumentStore>()
+        .is_empty()
+        .await
+        .unwrap_or(true)
+    {
+        return None;
+    }
+    match search_documents(app, agent, &config, content).await {
+        Ok(chunks) => rag::context_entry(&chunks),
+        Err(error) => {
+            log::warn!("Failed to retrieve document context: {}", error);
+            None
+        }
+    }
+}
+
+/// The LLM settings for a session that picked its own provider and model.
+async fn session_llm_settings(
+    app: &AppHandle<Wry>,
+    agent: &AgentService,
+    sessions: &SessionService,
+    session_id: &str,
+) -> Result<Option<AgentLlmSettings>, String> {
+    let Some(session_llm) = sessions.session_llm(session_id).await? else {
+        return Ok(None);
+    };
+    let config = load_config(app.clone(), app.state::<ConfigState>())?.config;
+    let current = agent.llm_settings().await.ok();
+    AgentLlmSettings::for_session(&session_llm, current.as_ref(), &config).map(Some)
+}
+
+#[tauri::command]
+async fn agent_send_message(
+    app: AppHandle<Wry>,
+    state: State<'_, Mutex<AgentService>>,
+    session_state: State<'_, Mutex<SessionService>>,
+    entity_state: State<'_, Mutex<EntityService>>,
+    request: AgentMessageRequest,
+) -> Result<AgentMessageResponse, String> {
+    if let Some(mismatch) = find_checksum_mismatches(&request.content, &configured_network(&app))
+        .into_iter()
+        .next()
+    {
+        return Err(mismatch.to_string());
+    }
+
+    let session_id_hint = request.session_id.clone();
+    let (resolved_session_id, llm, settings) = {
+        let agent = state.lock().await;
+        let resolved_session_id = match session_id_hint {
+            Some(id) => id,
+            None => agent
+                .status()
+                .await
+                .session_id
+                .ok_or_else(|| "Agent session is not initialized".to_string())?,
+        };
+
+        ensure_session_exists(&session_state, &resolved_session_id).await?;
+
+        let session_guard = session_state.lock().await;
+        let llm =
+            match session_llm_settings(&app, &agent, &session_guard, &resolved_session_id).await {
+                Ok(llm) => llm,
+                Err(error) => {
+                    return Ok(AgentMessageResponse {
+                        success: false,
+                        response: None,
+                        error: Some(error),
+                        timeout: None,
+                    });
+                }
+            };
+        let settings = match llm.clone() {
+            Some(llm) => Some(llm),
+            None => agent.llm_settings().await.ok(),
+        };
+        (resolved_session_id, llm, settings)
+    };
+    // Compacting may ask the LLM for a summary, so it runs without the
+    // agent and session locks.
+    let mut chat_history = match request.chat_history {
+        Some(history) => Some(
+            compact_chat_history(
+                &app,
+                &session_state,
+                &resolved_session_id,
+                settings.as_ref(),
+                history,
+            )
+            .await,
+        ),
+        None => None,
+    };
+
+    let agent = state.lock().await;
+    let session_guard = session_state.lock().await;
+    if let Some(context) = retrieve_documents(&app, &agent, &request.content).await {
+        chat_history.get_or_insert_with(Vec::new).push(context);
+    }
+    let mut response = agent
+        .send_message(
+            &session_guard,
+            AgentMessageRequest {
+                session_id: Some(resolved_session_id.clone()),
+                llm,
+                chat_history,
+                ..request
+            },
+        )
+        .await
+        .map_err(|error| error.to_string())?;
+    drop(session_guard);
+    drop(agent);
+
+    if let Some(message) = response.response.as_mut() {
+        associate_agent_tool_entities(&app, &entity_state, &state, &resolved_session_id, message)
+            .await;
+
+        if let Some(metadata) = message.metadata.as_ref() {
+            let usage = SessionUsage::from_agent_metadata(metadata);
+            if let Err(error) = session_state
+                .lock()
+                .await
+                .record_usage(&resolved_session_id, &usage)
+                .await
+            {
+                log::warn!(
+                    "agent_send_message: failed to record usage for session {}: {}",
+                    resolved_session_id,
+                    error
+                );
+            }
+        }
+
+        let transaction_id = message
+            .metadata
+            .as_ref()
+            .filter(|metadata| !awaits_approval(metadata))
+            .and_then(extract_transaction_id);
+        if let (Some(metadata), Some(tx_id)) = (message.metadata.as_mut(), transaction_id) {
+            let network = metadata
+                .get("network")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| configured_network(&app));
+            let receipt =
+                build_execution_receipt(metadata, None, &tx_id, &network, "agentToolCall");
+            record_execution_receipt(&app, &resolved_session_id, &message.id, receipt.clone())
+                .await;
+            if let Some(object) = metadata.as_object_mut() {
+                object.insert("executionReceipts".to_string(), json!([receipt]));
+            }
+        }
+    }
+
+    Ok(response)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateSessionPayload {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    mode: Option<String>,
+    #[serde(default)]
+    topic_id: Option<String>,
+    #[serde(default = "default_is_active")]
+    is_active: bool,
+}
+
+fn default_is_active() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionIdPayload {
+    session_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BulkDeleteSessionsPayload {
+    #[serde(default)]
+    older_than: Option<String>,
+    #[serde(default)]
+    mode: Option<String>,
+    #[serde(default)]
+    inactive_only: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BulkDeleteSessionsResponse {
+    deleted_count: usize,
+    session_ids: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionFilterPayload {
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionTagPayload {
+    session_id: String,
+    tag: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PinMessagePayload {
+    session_id: String,
+    message_id: String,
+    #[serde(default = "default_pinned")]
+    pinned: bool,
+}
+
+fn default_pinned() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionMessagesPayload {
+    session_id: String,
+    #[serde(default)]
+    thread_id: Option<String>,
+    #[serde(default)]
+    collapse_threads: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ForkSessionPayload {
+    session_id: String,
+    #[serde(default)]
+    up_to_message_id: Option<String>,
+    #[serde(default)]
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateThreadPayload {
+    session_id: String,
+    #[serde(default)]
+    parent_message_id: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SaveSessionPayload {
+    session: ChatSession,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SaveMessagePayload {
+    session_id: String,
+    message: ChatMessage,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionContextPayload {
+    session_id: String,
+    mode: String,
+    topic_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FormStatePayload {
+    session_id: String,
+    form_id: String,
+    completion_state: String,
+    completion_data: Option<Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MessageMetadataPayload {
+    session_id: String,
+    message_id: String,
+    metadata: Value,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Hcs10RegisterPayload {
+    #[serde(rename = "profileData")]
+    profile_data: Value,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Hcs10ProfileNamePayload {
+    #[serde(rename = "profileName")]
+    profile_name: String,
+}
+
+#[derive(Deserialize)]
+struct Hcs10StateNamePayload {
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Hcs10ValidatePayload {
+    #[serde(rename = "profileData")]
+    profile_data: Value,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Hcs10ConnectionRequestsPayload {
+    #[serde(default)]
+    status: Option<ConnectionRequestStatus>,
+    #[serde(default)]
+    refresh: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Hcs10ConnectionRequestPayload {
+    request_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Hcs10RetrievePayload {
+    #[serde(rename = "accountId")]
+    account_id: String,
+}
+
+#[tauri::command]
+async fn chat_create_session(
+    state: State<'_, Mutex<SessionService>>,
+    payload: Option<CreateSessionPayload>,
+) -> Result<CommandResponse<ChatSession>, String> {
+    let request = payload.unwrap_or(CreateSessionPayload {
+        name: None,
+        mode: None,
+        topic_id: None,
+        is_active: default_is_active(),
+    });
+
+    let CreateSessionPayload {
+        name,
+        mode,
+        topic_id,
+        is_active,
+    } = request;
+
+    let resolved_name = name.unwrap_or_else(|| "Untitled Session".to_string());
+    let resolved_mode = mode.unwrap_or_else(|| "personal".to_string());
+
+    let session = state
+        .lock()
+        .await
+        .create_session(CreateSessionInput {
+            name: resolved_name,
+            mode: resolved_mode,
+            topic_id,
+            is_active,
+        })
+        .await;
+    Ok(CommandResponse::ok(session))
+}
+
+#[tauri::command]
+async fn chat_load_session(
+    state: State<'_, Mutex<SessionService>>,
+    payload: SessionIdPayload,
+) -> Result<CommandResponse<ChatSession>, String> {
+    let service = state.lock().await;
+    match service.load_session(&payload.session_id).await {
+        Some(session) => Ok(CommandResponse::ok(session)),
+        None => Ok(CommandResponse::error("Session not found".to_string())),
+    }
+}
+
+#[tauri::command]
+async fn chat_save_session(
+    state: State<'_, Mutex<SessionService>>,
+    payload: SaveSessionPayload,
+) -> Result<CommandResponse<ChatSession>, String> {
+    let session = payload.session;
+    state
+        .lock()
+        .await
+        .save_session(session.clone())
+        .await
+        .map_err(|error| error.to_string())?;
+    Ok(CommandResponse::ok(session))
+}
+
+#[tauri::command]
+async fn chat_delete_session(
+    state: State<'_, Mutex<SessionService>>,
+    payload: SessionIdPayload,
+) -> Result<CommandResponse<bool>, String> {
+    let removed = state.lock().await.delete_session(&payload.session_id).await;
+    Ok(CommandResponse::ok(removed))
+}
+
+#[tauri::command]
+async fn chat_bulk_delete_sessions(
+    state: State<'_, Mutex<SessionService>>,
+    payload: BulkDeleteSessionsPayload,
+) -> Result<CommandResponse<BulkDeleteSessionsResponse>, String> {
+    let filter = SessionDeleteFilter {
+        older_than: payload.older_than,
+        mode: payload.mode,
+        inactive_only: payload.inactive_only,
+    };
+    match state.lock().await.delete_sessions_matching(&filter).await {
+        Ok(session_ids) => Ok(CommandResponse::ok(BulkDeleteSessionsResponse {
+            deleted_count: session_ids.len(),
+            session_ids,
+        })),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn chat_session_stats(
+    state: State<'_, Mutex<SessionService>>,
+    payload: SessionIdPayload,
+) -> Result<CommandResponse<SessionStats>, String> {
+    match state.lock().await.session_stats(&payload.session_id).await {
+        Ok(stats) => Ok(CommandResponse::ok(stats)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct UsageSummaryPayload {
+    /// One session's usage; every session's when left out.
+    #[serde(default)]
+    session_id: Option<String>,
+}
+
+#[tauri::command]
+async fn usage_get_summary(
+    state: State<'_, Mutex<SessionService>>,
+    payload: Option<UsageSummaryPayload>,
+) -> Result<CommandResponse<UsageSummary>, String> {
+    let session_id = payload.unwrap_or_default().session_id;
+    match state.lock().await.model_usage(session_id.as_deref()).await {
+        Ok(usage) => Ok(CommandResponse::ok(usage::summarize(session_id, usage))),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MarkReadPayload {
+    session_id: String,
+    #[serde(default)]
+    read_at: Option<String>,
+}
+
+#[tauri::command]
+async fn chat_mark_read(
+    state: State<'_, Mutex<SessionService>>,
+    payload: MarkReadPayload,
+) -> Result<CommandResponse<SessionReadState>, String> {
+    match state
+        .lock()
+        .await
+        .mark_read(&payload.session_id, payload.read_at.as_deref())
+        .await
+    {
+        Ok(read_state) => Ok(CommandResponse::ok(read_state)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionLlmPayload {
+    session_id: String,
+    #[serde(default)]
+    llm: Option<SessionLlm>,
+}
+
+/// Pins the session to a provider and model, used by `agent_send_message`
+/// instead of the configured ones. Without `llm` the override is cleared.
+#[tauri::command]
+async fn chat_set_session_llm(
+    state: State<'_, Mutex<SessionService>>,
+    payload: SessionLlmPayload,
+) -> Result<CommandResponse<Option<SessionLlm>>, String> {
+    match state
+        .lock()
+        .await
+        .set_session_llm(&payload.session_id, payload.llm)
+        .await
+    {
+        Ok(llm) => Ok(CommandResponse::ok(llm)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn chat_load_all_sessions(
+    state: State<'_, Mutex<SessionService>>,
+    payload: Option<SessionFilterPayload>,
+) -> Result<CommandResponse<Vec<ChatSession>>, String> {
+    let tags = payload.map(|filter| filter.tags).unwrap_or_default();
+    let sessions = state.lock().await.load_all_sessions(&tags).await;
+    Ok(CommandResponse::ok(sessions))
+}
+
+#[tauri::command]
+async fn chat_add_session_tag(
+    state: State<'_, Mutex<SessionService>>,
+    payload: SessionTagPayload,
+) -> Result<CommandResponse<Vec<String>>, String> {
+    let result = state
+        .lock()
+        .await
+        .add_session_tag(&payload.session_id, &payload.tag)
+        .await;
+    match result {
+        Ok(tags) => Ok(CommandResponse::ok(tags)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn chat_remove_session_tag(
+    state: State<'_, Mutex<SessionService>>,
+    payload: SessionTagPayload,
+) -> Result<CommandResponse<Vec<String>>, String> {
+    let result = state
+        .lock()
+        .await
+        .remove_session_tag(&payload.session_id, &payload.tag)
+        .await;
+    match result {
+        Ok(tags) => Ok(CommandResponse::ok(tags)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn chat_save_message(
+    state: State<'_, Mutex<SessionService>>,
+    payload: SaveMessagePayload,
+) -> Result<CommandResponse<ChatMessage>, String> {
+    state
+        .lock()
+        .await
+        .save_message(&payload.session_id, payload.message.clone())
+        .await
+        .map_err(|error| error.to_string())?;
+    Ok(CommandResponse::ok(payload.message))
+}
+
+#[tauri::command]
+async fn chat_load_session_messages(
+    state: State<'_, Mutex<SessionService>>,
+    payload: SessionMessagesPayload,
+) -> Result<CommandResponse<Vec<ChatMessage>>, String> {
+    let scope = match payload.thread_id {
+        Some(thread_id) => MessageScope::Thread(thread_id),
+        None if payload.collapse_threads => MessageScope::MainTimeline,
+        None => MessageScope::All,
+    };
+    let messages = state
+        .lock()
+        .await
+        .load_messages_in_scope(&payload.session_id, &scope)
+        .await
+        .unwrap_or_default();
+    Ok(CommandResponse::ok(messages))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ToolCallsPayload {
+    session_id: String,
+    /// Only the calls made for this reply; the whole session's when left out.
+    #[serde(default)]
+    message_id: Option<String>,
+}
+
+#[tauri::command]
+async fn chat_load_tool_calls(
+    state: State<'_, Mutex<SessionService>>,
+    payload: ToolCallsPayload,
+) -> Result<CommandResponse<Vec<ToolCallRecord>>, String> {
+    match state
+        .lock()
+        .await
+        .load_tool_calls(&payload.session_id, payload.message_id.as_deref())
+        .await
+    {
+        Ok(calls) => Ok(CommandResponse::ok(calls)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RagIndexPayload {
+    /// A file, or a directory to index recursively.
+    path: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RagDocumentPayload {
+    id: String,
+}
+
+/// Chunks and embeds the text files at `path`. Files indexed before with the
+/// same size, modification time and embedder are left as they are.
+async fn index_documents(
+    app: &AppHandle<Wry>,
+    agent_state: &Mutex<AgentService>,
+    path: &str,
+) -> Result<RagIndexSummary, String> {
+    let embedder = match rag_config(app).embedder {
+        RagEmbedder::Local => Embedder::Local,
+        RagEmbedder::Provider => {
+            Embedder::provider(agent_state.lock().await.llm_settings().await?)?
+        }
+    };
+    let root = fs::canonicalize(path.trim())
+        .map_err(|error| format!("Cannot read {}: {error}", path.trim()))?;
+    let app_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|error| error.to_string())?;
+    let app_dir = fs::canonicalize(&app_dir).unwrap_or(app_dir);
+    let files = tauri::async_runtime::spawn_blocking(move || rag::collect_files(&root, &app_dir))
+        .await
+        .map_err(|error| error.to_string())??;
+
+    let store = app.state::<DocumentStore>();
+    let mut summary = RagIndexSummary {
+        embedder: embedder.id(),
+        ..RagIndexSummary::default()
+    };
+    for file in files {
+        let Some(source) = SourceFile::read(&file) else {
+            summary.skipped.push(file.to_string_lossy().to_string());
+            continue;
+        };
+        if store.is_current(&source, &summary.embedder).await? {
+            summary.unchanged += 1;
+            continue;
+        }
+        let chunks = rag::chunk_text(&source.text);
+        let vectors = embed_texts(app, &embedder, chunks.clone()).await?;
+        let chunks: Vec<(String, Vec<f32>)> = chunks.into_iter().zip(vectors).collect();
+        store.replace(&source, &summary.embedder, &chunks).await?;
+        summary.indexed += 1;
+        summary.chunks += chunks.len();
+    }
+    Ok(summary)
+}
+
+#[tauri::command]
+async fn rag_index_path(
+    app: AppHandle<Wry>,
+    agent_state: State<'_, Mutex<AgentService>>,
+    payload: RagIndexPayload,
+) -> Result<CommandResponse<RagIndexSummary>, String> {
+    match index_documents(&app, &agent_state, &payload.path).await {
+        Ok(summary) => Ok(CommandResponse::ok(summary)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn rag_list_documents(
+    store: State<'_, DocumentStore>,
+) -> Result<CommandResponse<Vec<IndexedDocument>>, String> {
+    match store.list().await {
+        Ok(documents) => Ok(CommandResponse::ok(documents)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn rag_remove_document(
+    store: State<'_, DocumentStore>,
+    payload: RagDocumentPayload,
+) -> Result<CommandResponse<bool>, String> {
+    match store.remove(&payload.id).await {
+        Ok(removed) => Ok(CommandResponse::ok(removed)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn chat_prune_now(app: AppHandle<Wry>) -> Result<CommandResponse<PruneSummary>, String> {
+    match prune_chat_history(&app).await {
+        Ok(summary) => Ok(CommandResponse::ok(summary)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn chat_fork_session(
+    state: State<'_, Mutex<SessionService>>,
+    payload: ForkSessionPayload,
+) -> Result<CommandResponse<ChatSession>, String> {
+    let result = state
+        .lock()
+        .await
+        .fork_session(
+            &payload.session_id,
+            payload.up_to_message_id.as_deref(),
+            payload.name,
+        )
+        .await;
+    match result {
+        Ok(session) => Ok(CommandResponse::ok(session)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn chat_create_thread(
+    state: State<'_, Mutex<SessionService>>,
+    payload: CreateThreadPayload,
+) -> Result<CommandResponse<ChatThread>, String> {
+    let result = state
+        .lock()
+        .await
+        .create_thread(
+            &payload.session_id,
+            payload.parent_message_id,
+            payload.title,
+        )
+        .await;
+    match result {
+        Ok(thread) => Ok(CommandResponse::ok(thread)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn chat_list_threads(
+    state: State<'_, Mutex<SessionService>>,
+    payload: SessionIdPayload,
+) -> Result<CommandResponse<Vec<ChatThread>>, String> {
+    match state.lock().await.list_threads(&payload.session_id).await {
+        Ok(threads) => Ok(CommandResponse::ok(threads)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn agent_update_session_context(
+    agent_state: State<'_, Mutex<AgentService>>,
+    session_state: State<'_, Mutex<SessionService>>,
+    payload: SessionContextPayload,
+) -> Result<CommandResponse<()>, String> {
+    let context = AgentSessionContext {
+        session_id: payload.session_id.clone(),
+        mode: payload.mode.clone(),
+        topic_id: payload.topic_id.clone(),
+    };
+    agent_state
+        .lock()
+        .await
+        .update_session_context(context)
+        .await;
+    session_state
+        .lock()
+        .await
+        .update_session_context(SessionContext {
+            session_id: payload.session_id,
+            mode: payload.mode,
+            topic_id: payload.topic_id,
+        })
+        .await;
+    Ok(CommandResponse::ok(()))
+}
+
+#[tauri::command]
+async fn chat_pin_message(
+    state: State<'_, Mutex<SessionService>>,
+    payload: PinMessagePayload,
+) -> Result<CommandResponse<Vec<String>>, String> {
+    let result = state
+        .lock()
+        .await
+        .set_message_pinned(&payload.session_id, &payload.message_id, payload.pinned)
+        .await;
+    match result {
+        Ok(pinned) => Ok(CommandResponse::ok(pinned)),
+        Err(error) => Ok(CommandResponse::error(error)),
     }
 }
 
 #[tauri::command]
-async fn entity_search(
-    state: State<'_, Mutex<EntityService>>,
-    payload: EntitySearchPayload,
-) -> Result<CommandResponse<Vec<EntityAssociation>>, String> {
-    let EntitySearchPayload { query, entity_type } = payload;
-    let trimmed = query.trim().to_string();
+async fn chat_list_pinned(
+    state: State<'_, Mutex<SessionService>>,
+    payload: SessionIdPayload,
+) -> Result<CommandResponse<Vec<ChatMessage>>, String> {
+    let messages = state
+        .lock()
+        .await
+        .list_pinned_messages(&payload.session_id)
+        .await?;
+    Ok(CommandResponse::ok(messages))
+}
 
-    if trimmed.is_empty() {
-        return Ok(CommandResponse::error(
-            "Search query is required".to_string(),
-        ));
+#[tauri::command]
+async fn chat_update_session_context(
+    state: State<'_, Mutex<SessionService>>,
+    payload: SessionContextPayload,
+) -> Result<CommandResponse<()>, String> {
+    state
+        .lock()
+        .await
+        .update_session_context(SessionContext {
+            session_id: payload.session_id,
+            mode: payload.mode,
+            topic_id: payload.topic_id,
+        })
+        .await;
+    Ok(CommandResponse::ok(()))
+}
+
+#[tauri::command]
+async fn chat_update_form_state(
+    state: State<'_, Mutex<SessionService>>,
+    payload: FormStatePayload,
+) -> Result<CommandResponse<Option<ChatMessage>>, String> {
+    let result = state
+        .lock()
+        .await
+        .update_form_state(
+            &payload.session_id,
+            &payload.form_id,
+            payload.completion_state,
+            payload.completion_data,
+        )
+        .await?;
+    Ok(CommandResponse::ok(result))
+}
+
+#[tauri::command]
+async fn chat_update_message_metadata(
+    state: State<'_, Mutex<SessionService>>,
+    payload: MessageMetadataPayload,
+) -> Result<CommandResponse<Option<ChatMessage>>, String> {
+    let updated = state
+        .lock()
+        .await
+        .update_message_metadata(&payload.session_id, &payload.message_id, payload.metadata)
+        .await?;
+    Ok(CommandResponse::ok(updated))
+}
+
+#[tauri::command]
+async fn browser_attach(app: AppHandle<Wry>) -> Result<(), String> {
+    log::info!("browser_attach invoked");
+    append_browser_log(&app, "browser_attach invoked");
+    let result = with_browser_manager_async(app.clone(), |handle| async move {
+        let manager = handle.state::<ActiveBrowserManager>();
+        manager.attach(&handle).await
+    })
+    .await;
+    if let Err(error) = &result {
+        append_browser_log(&app, &format!("browser_attach error: {error}"));
     }
+    result
+}
 
-    let results = {
-        let service = state.lock().await;
-        service
-            .search_entities(&trimmed, entity_type.as_deref(), 50)
-            .await?
-    };
+#[tauri::command]
+async fn browser_detach(app: AppHandle<Wry>) -> Result<(), String> {
+    append_browser_log(&app, "browser_detach invoked");
+    let result = with_browser_manager_async(app.clone(), |handle| async move {
+        let manager = handle.state::<ActiveBrowserManager>();
+        manager.detach(&handle).await
+    })
+    .await;
+    if let Err(error) = &result {
+        append_browser_log(&app, &format!("browser_detach error: {error}"));
+    }
+    result
+}
 
-    Ok(CommandResponse::ok(results))
+#[tauri::command]
+async fn browser_navigate(app: AppHandle<Wry>, url: String) -> Result<(), String> {
+    append_browser_log(&app, &format!("browser_navigate: {url}"));
+    let result = with_browser_manager_async(app.clone(), |handle| async move {
+        let manager = handle.state::<ActiveBrowserManager>();
+        manager.navigate(&handle, url).await
+    })
+    .await;
+    if let Err(error) = &result {
+        append_browser_log(&app, &format!("browser_navigate error: {error}"));
+    }
+    result
+}
+
+#[tauri::command]
+async fn browser_reload(app: AppHandle<Wry>) -> Result<(), String> {
+    append_browser_log(&app, "browser_reload invoked");
+    let result = with_browser_manager_async(app.clone(), |handle| async move {
+        let manager = handle.state::<ActiveBrowserManager>();
+        manager.reload(&handle).await
+    })
+    .await;
+    if let Err(error) = &result {
+        append_browser_log(&app, &format!("browser_reload error: {error}"));
+    }
+    result
+}
+
+#[tauri::command]
+async fn browser_go_back(app: AppHandle<Wry>) -> Result<(), String> {
+    append_browser_log(&app, "browser_go_back invoked");
+    let result = with_browser_manager_async(app.clone(), |handle| async move {
+        let manager = handle.state::<ActiveBrowserManager>();
+        manager.go_back(&handle).await
+    })
+    .await;
+    if let Err(error) = &result {
+        append_browser_log(&app, &format!("browser_go_back error: {error}"));
+    }
+    result
+}
+
+#[tauri::command]
+async fn browser_go_forward(app: AppHandle<Wry>) -> Result<(), String> {
+    append_browser_log(&app, "browser_go_forward invoked");
+    let result = with_browser_manager_async(app.clone(), |handle| async move {
+        let manager = handle.state::<ActiveBrowserManager>();
+        manager.go_forward(&handle).await
+    })
+    .await;
+    if let Err(error) = &result {
+        append_browser_log(&app, &format!("browser_go_forward error: {error}"));
+    }
+    result
+}
+
+#[tauri::command]
+async fn browser_set_bounds(
+    app: AppHandle<Wry>,
+    bounds: browser::BoundsPayload,
+) -> Result<(), String> {
+    let result = with_browser_manager_async(app.clone(), |handle| async move {
+        let manager = handle.state::<ActiveBrowserManager>();
+        manager.set_bounds(&handle, bounds).await
+    })
+    .await;
+    if let Err(error) = &result {
+        append_browser_log(&app, &format!("browser_set_bounds error: {error}"));
+    }
+    result
+}
+
+#[tauri::command]
+async fn browser_set_layout(
+    app: AppHandle<Wry>,
+    layout: browser::LayoutPayload,
+) -> Result<(), String> {
+    let result = with_browser_manager_async(app.clone(), |handle| async move {
+        let manager = handle.state::<ActiveBrowserManager>();
+        manager.set_layout(&handle, layout).await
+    })
+    .await;
+    if let Err(error) = &result {
+        append_browser_log(&app, &format!("browser_set_layout error: {error}"));
+    }
+    result
+}
+
+#[tauri::command]
+async fn browser_capture_context(app: AppHandle<Wry>) -> Result<Option<Value>, String> {
+    with_browser_manager_async(app.clone(), |handle| async move {
+        let manager = handle.state::<ActiveBrowserManager>();
+        manager.capture_context(&handle).await
+    })
+    .await
 }
 
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct HederaCredentialsPayload {
-    account_id: String,
-    private_key: String,
-    network: String,
+#[tauri::command]
+async fn browser_get_state(app: AppHandle<Wry>) -> Result<BrowserStateDto, String> {
+    with_browser_manager_async(app.clone(), |handle| async move {
+        let manager = handle.state::<ActiveBrowserManager>();
+        manager.get_state().await
+    })
+    .await
 }
 
-impl TryFrom<HederaCredentialsPayload> for HederaCredentials {
-    type Error = String;
+/// Holds a script until the user approves it for the page's origin, unless a
+/// remembered decision for that origin already applies. Returns the origin
+/// the script may run in.
+async fn authorize_script(app: &AppHandle<Wry>, script: &str) -> Result<String, String> {
+    let url = app
+        .state::<ActiveBrowserManager>()
+        .get_state()
+        .await?
+        .current_url;
+    let permissions = app.state::<Mutex<BrowserScriptPermissions>>();
+    let (request, receiver) = {
+        let mut permissions = permissions.lock().await;
+        let origin = browser_permissions::site_origin(&url)?;
+        match permissions.remembered_decision(&origin) {
+            Some(true) => return Ok(origin),
+            Some(false) => {
+                return Err(format!("Scripts are blocked for {origin}"));
+            }
+            None => permissions.request(&url, script)?,
+        }
+    };
 
-    fn try_from(value: HederaCredentialsPayload) -> Result<Self, Self::Error> {
-        let network = match value.network.to_lowercase().as_str() {
-            "mainnet" => HederaNetwork::Mainnet,
-            "testnet" => HederaNetwork::Testnet,
-            _ => return Err("Unsupported Hedera network".to_string()),
-        };
+    app.emit("browser_script_approval_requested", &request)
+        .map_err(|error| error.to_string())?;
+    append_browser_log(
+        app,
+        &format!("script awaiting approval for {}", request.origin),
+    );
 
-        Ok(Self {
-            account_id: value.account_id,
-            private_key: value.private_key,
-            network,
-        })
+    let decision = tokio::time::timeout(TokioDuration::from_secs(120), receiver).await;
+    let approved = match decision {
+        Ok(Ok(decision)) => decision.approved,
+        Ok(Err(_)) => false,
+        Err(_) => {
+            permissions.lock().await.cancel(&request.request_id);
+            return Err("Script approval timed out".to_string());
+        }
+    };
+    if approved {
+        Ok(request.origin)
+    } else {
+        Err(format!("Script was denied for {}", request.origin))
     }
 }
 
-#[derive(Deserialize)]
-struct LlmCredentialsPayload {
-    api_key: String,
-    model: String,
-}
-
-impl From<LlmCredentialsPayload> for LlmCredentials {
-    fn from(value: LlmCredentialsPayload) -> Self {
-        Self {
-            api_key: value.api_key,
-            model: value.model,
+/// Runs a script in the browser view. The app's own page scripts run inside
+/// Rust, so a script that reaches this command may come from the agent and
+/// needs the user's approval for the page's origin. It only runs if the
+/// page is still on that origin.
+#[tauri::command]
+async fn browser_execute_js(app: AppHandle<Wry>, script: String) -> Result<Value, String> {
+    let origin = match authorize_script(&app, &script).await {
+        Ok(origin) => origin,
+        Err(error) => {
+            append_browser_log(&app, &format!("browser_execute_js rejected: {error}"));
+            return Err(error);
         }
+    };
+    let result = with_browser_manager_async(app.clone(), |handle| async move {
+        let manager = handle.state::<ActiveBrowserManager>();
+        manager.execute_js_in_origin(&handle, &origin, script).await
+    })
+    .await;
+    if let Err(error) = &result {
+        append_browser_log(&app, &format!("browser_execute_js error: {error}"));
     }
+    result
 }
 
 #[tauri::command]
-async fn connection_test_hedera(
-    state: State<'_, Mutex<ConnectionService>>,
-    credentials: HederaCredentialsPayload,
-) -> Result<HederaTestResponse, String> {
-    let converted = HederaCredentials::try_from(credentials)?;
-    state
-        .lock()
-        .await
-        .test_hedera(converted)
-        .await
-        .map_err(|error| error.to_string())
+async fn browser_resolve_script_approval(
+    state: State<'_, Mutex<BrowserScriptPermissions>>,
+    request_id: String,
+    decision: ScriptApprovalDecision,
+) -> Result<(), String> {
+    state.lock().await.resolve(&request_id, decision)
 }
 
 #[tauri::command]
-async fn connection_test_openai(
-    state: State<'_, Mutex<ConnectionService>>,
-    credentials: LlmCredentialsPayload,
-) -> Result<LlmTestResponse, String> {
-    state
-        .lock()
-        .await
-        .test_openai(credentials.into())
-        .await
-        .map_err(|error| error.to_string())
+async fn browser_list_script_permissions(
+    state: State<'_, Mutex<BrowserScriptPermissions>>,
+) -> Result<BTreeMap<String, bool>, String> {
+    Ok(state.lock().await.remembered())
 }
 
 #[tauri::command]
-async fn connection_test_anthropic(
-    state: State<'_, Mutex<ConnectionService>>,
-    credentials: LlmCredentialsPayload,
-) -> Result<LlmTestResponse, String> {
-    state
-        .lock()
-        .await
-        .test_anthropic(credentials.into())
-        .await
-        .map_err(|error| error.to_string())
+async fn browser_forget_script_permission(
+    state: State<'_, Mutex<BrowserScriptPermissions>>,
+    origin: String,
+) -> Result<bool, String> {
+    state.lock().await.forget(&origin)
 }
 
 #[tauri::command]
-async fn agent_initialize(
-    app: AppHandle<Wry>,
-    state: State<'_, Mutex<AgentService>>,
-    mcp_state: State<'_, Mutex<McpService>>,
-    wallet_bridge: State<'_, WalletBridgeState>,
-    wallet_info: State<'_, Arc<Mutex<Option<WalletBridgeInfo>>>>,
-    mut config: AgentInitializeConfig,
-) -> Result<AgentInitializeResponse, String> {
-    if config.mcp_servers.is_none() {
-        if let Ok(servers) = mcp_state.lock().await.load().await {
-            config.mcp_servers = Some(Value::Array(servers));
-        }
-    }
-    wallet_bridge.set_app_handle(&app);
-    let bridge_clone = wallet_bridge.inner().clone();
-    let wallet_info_arc = Arc::clone(&*wallet_info);
-    let wallet_account_log = wallet_info_arc
-        .lock()
-        .await
-        .as_ref()
-        .map(|info| info.account_id.clone())
-        .unwrap_or_else(|| "<none>".to_string());
-    log::info!(
-        "agent_initialize payload: account={} user_account={} op_mode={} private_key_present={} wallet_active={}",
-        config.account_id,
-        config
-            .user_account_id
-            .clone()
-            .unwrap_or_else(|| "<none>".to_string()),
-        config
-            .operational_mode
-            .clone()
-            .unwrap_or_else(|| "<unspecified>".to_string()),
-        !config.private_key.trim().is_empty(),
-        wallet_account_log
-    );
-    state
-        .lock()
-        .await
-        .initialize(config, bridge_clone, wallet_info_arc)
-        .await
-        .map_err(|error| error.to_string())
+async fn browser_open_devtools(app: AppHandle<Wry>) -> Result<(), String> {
+    with_browser_manager_async(app.clone(), |handle| async move {
+        let manager = handle.state::<ActiveBrowserManager>();
+        manager.open_devtools(&handle).await
+    })
+    .await
 }
 
 #[tauri::command]
-async fn agent_status(
-    state: State<'_, Mutex<AgentService>>,
-) -> Result<CommandResponse<AgentStatusResponse>, String> {
-    Ok(CommandResponse::ok(state.lock().await.status().await))
+fn browser_open_external(url: String) -> Result<(), String> {
+    open::that(url).map_err(|error| error.to_string())
 }
 
-#[tauri::command]
-async fn agent_disconnect(
-    state: State<'_, Mutex<AgentService>>,
-) -> Result<CommandResponse<()>, String> {
-    state.lock().await.disconnect().await;
-    Ok(CommandResponse::ok(()))
+fn with_browser_manager<F, R>(app: &AppHandle<Wry>, f: F) -> Result<R, String>
+where
+    F: FnOnce(&ActiveBrowserManager) -> Result<R, String>,
+{
+    let state = app
+        .try_state::<ActiveBrowserManager>()
+        .ok_or_else(|| "Browser manager unavailable".to_string())?;
+    f(&*state)
 }
 
-#[tauri::command]
-async fn agent_send_message(
-    state: State<'_, Mutex<AgentService>>,
-    session_state: State<'_, Mutex<SessionService>>,
-    request: AgentMessageRequest,
-) -> Result<AgentMessageResponse, String> {
-    let session_id_hint = request.session_id.clone();
-    let agent = state.lock().await;
-    let resolved_session_id = match session_id_hint {
-        Some(id) => id,
-        None => agent
-            .status()
-            .await
-            .session_id
-            .ok_or_else(|| "Agent session is not initialized".to_string())?,
-    };
-
-    ensure_session_exists(&session_state, &resolved_session_id).await?;
+async fn with_browser_manager_async<F, Fut, R>(app: AppHandle<Wry>, f: F) -> Result<R, String>
+where
+    F: FnOnce(AppHandle<Wry>) -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = Result<R, String>> + Send + 'static,
+    R: Send + 'static,
+{
+    let _ = app
+        .try_state::<ActiveBrowserManager>()
+        .ok_or_else(|| "Browser manager unavailable".to_string())?;
+    f(app).await
+}
 
-    let session_guard = session_state.lock().await;
-    let response = agent
-        .send_message(
-            &session_guard,
-            AgentMessageRequest {
-                session_id: Some(resolved_session_id),
-                ..request
-            },
-        )
-        .await
-        .map_err(|error| error.to_string())?;
+fn append_browser_log(app: &AppHandle<Wry>, message: &str) {
+    if let Ok(config_dir) = app.path().app_data_dir() {
+        if let Err(err) = std::fs::create_dir_all(&config_dir) {
+            log::warn!("Failed to create browser log directory: {err}");
+            return;
+        }
+        let log_path = config_dir.join("browser.log");
+        if let Ok(mut file) = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)
+        {
+            let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+            let _ = writeln!(file, "[{timestamp}] {message}");
+            log::info!("[browser_log] {} => {}", timestamp, message);
+            log::info!("[browser_log] path: {}", log_path.display());
+        }
+    }
+}
 
-    Ok(response)
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BackupCreatePayload {
+    destination_path: String,
+    passphrase: String,
 }
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct CreateSessionPayload {
+struct BackupRestorePayload {
+    source_path: String,
     #[serde(default)]
-    name: Option<String>,
+    passphrase: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StartupPlanPayload {
     #[serde(default)]
-    mode: Option<String>,
+    profile_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SettingsSyncEnablePayload {
+    folder_path: String,
+    passphrase: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionSyncEnablePayload {
+    passphrase: String,
     #[serde(default)]
     topic_id: Option<String>,
-    #[serde(default = "default_is_active")]
-    is_active: bool,
 }
 
-fn default_is_active() -> bool {
-    true
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WorkspaceCreatePayload {
+    name: String,
+    #[serde(default)]
+    persona_id: Option<String>,
 }
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct SessionIdPayload {
-    session_id: String,
+struct OutboxEnqueuePayload {
+    kind: OutboxKind,
+    payload: Value,
+    #[serde(default)]
+    depends_on: Option<String>,
 }
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct SaveSessionPayload {
-    session: ChatSession,
+struct OutboxListPayload {
+    #[serde(default)]
+    include_finished: bool,
 }
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct SaveMessagePayload {
-    session_id: String,
-    message: ChatMessage,
+struct OutboxIdPayload {
+    id: String,
 }
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct SessionContextPayload {
-    session_id: String,
-    mode: String,
-    topic_id: Option<String>,
+struct WorkspaceIdPayload {
+    workspace_id: String,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct SettingsSyncPullPayload {
+    #[serde(default)]
+    resolutions: HashMap<String, ConflictChoice>,
+}
+
+async fn collect_settings_snapshot(app: &AppHandle<Wry>) -> Result<SettingsSnapshot, String> {
+    let mut config = load_config(app.clone(), app.state::<ConfigState>())?.config;
+    config::wipe_secrets(&mut config);
+    let mcp_servers = app.state::<Mutex<McpService>>().lock().await.load().await?;
+    Ok(SettingsSnapshot {
+        config: serde_json::to_value(config).map_err(|error| error.to_string())?,
+        mcp_servers,
+    })
+}
+
+async fn apply_settings_snapshot(
+    app: &AppHandle<Wry>,
+    snapshot: &SettingsSnapshot,
+) -> Result<(), String> {
+    let mut config = serde_json::from_value(snapshot.config.clone())
+        .map_err(|error| format!("Synced config is invalid: {error}"))?;
+    let mut current = load_config(app.clone(), app.state::<ConfigState>())?.config;
+    config::keep_secrets(&mut config, &current);
+    config::wipe_secrets(&mut current);
+    save_config(
+        app.clone(),
+        app.state::<ConfigState>(),
+        app.state::<OsAuthGate>(),
+        config,
+    )
+    .await?;
+    app.state::<Mutex<McpService>>()
+        .lock()
+        .await
+        .save(snapshot.mcp_servers.clone())
+        .await
 }
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct FormStatePayload {
-    session_id: String,
-    form_id: String,
-    completion_state: String,
-    completion_data: Option<Value>,
+struct ConfigHistoryPayload {
+    #[serde(default)]
+    limit: Option<usize>,
 }
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct MessageMetadataPayload {
-    session_id: String,
-    message_id: String,
-    metadata: Value,
+struct ConfigRevertPayload {
+    version: i64,
 }
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct Hcs10RegisterPayload {
-    #[serde(rename = "profileData")]
-    profile_data: Value,
+struct ConfigImportEnvPayload {
+    path: String,
+    #[serde(default)]
+    confirm: bool,
+    #[serde(default)]
+    keys: Option<Vec<String>>,
 }
 
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct Hcs10ProfileNamePayload {
-    #[serde(rename = "profileName")]
-    profile_name: String,
+#[tauri::command]
+async fn config_get_history(
+    history: State<'_, ConfigHistory>,
+    payload: Option<ConfigHistoryPayload>,
+) -> Result<CommandResponse<Vec<ConfigHistoryEntry>>, String> {
+    let limit = payload.and_then(|request| request.limit).unwrap_or(50);
+    match history.entries(limit) {
+        Ok(entries) => Ok(CommandResponse::ok(entries)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
 }
 
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct Hcs10ValidatePayload {
-    #[serde(rename = "profileData")]
-    profile_data: Value,
+#[tauri::command]
+async fn power_get_state(
+    app: AppHandle<Wry>,
+    monitor: State<'_, PowerMonitor>,
+) -> Result<CommandResponse<PowerState>, String> {
+    Ok(CommandResponse::ok(monitor.state(&power_policy(&app))))
 }
 
-#[derive(Deserialize)]
+/// Restores the config saved as `version`. The revert is itself saved, so it
+/// shows up as the newest history entry and can be undone the same way.
+#[tauri::command]
+async fn config_revert_to(
+    app: AppHandle<Wry>,
+    config_state: State<'_, ConfigState>,
+    gate: State<'_, OsAuthGate>,
+    history: State<'_, ConfigHistory>,
+    payload: ConfigRevertPayload,
+) -> Result<CommandResponse<AppConfig>, String> {
+    let mut config = match history.snapshot(payload.version) {
+        Ok(config) => config,
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+    if let Err(error) = config::decrypt_sensitive_fields(&mut config, &config_state.master_password)
+    {
+        return Ok(CommandResponse::error(error));
+    }
+    match save_config(app.clone(), config_state, gate, config.clone()).await {
+        Ok(()) => Ok(CommandResponse::ok(config)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
-struct Hcs10RetrievePayload {
-    #[serde(rename = "accountId")]
-    account_id: String,
+struct KeyRotationReport {
+    #[serde(flatten)]
+    config: config::ConfigRotation,
+    pending_restore: bool,
+    session_sync_passphrase: bool,
+    settings_sync_snapshot: bool,
 }
 
+/// Rewraps every secret encrypted with the master password under the current
+/// encryption envelope, so older key derivations can be retired.
 #[tauri::command]
-async fn chat_create_session(
-    state: State<'_, Mutex<SessionService>>,
-    payload: Option<CreateSessionPayload>,
-) -> Result<CommandResponse<ChatSession>, String> {
-    let request = payload.unwrap_or(CreateSessionPayload {
-        name: None,
-        mode: None,
-        topic_id: None,
-        is_active: default_is_active(),
-    });
+async fn config_rotate_keys(
+    app: AppHandle<Wry>,
+    config_state: State<'_, ConfigState>,
+    settings_sync: State<'_, Mutex<SettingsSyncService>>,
+    session_sync: State<'_, Mutex<SessionSyncService>>,
+) -> Result<CommandResponse<KeyRotationReport>, String> {
+    let config = match config::rotate_keys(&app, &config_state) {
+        Ok(rotated) => rotated,
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+    let pending_restore = match app.path().app_data_dir() {
+        Ok(data_dir) => {
+            match data_backup::rewrap_pending_restore(&data_dir, &config_state.master_password) {
+                Ok(rotated) => rotated,
+                Err(error) => return Ok(CommandResponse::error(error)),
+            }
+        }
+        Err(error) => return Ok(CommandResponse::error(error.to_string())),
+    };
+    let session_sync_passphrase = match session_sync.lock().await.rotate_keys() {
+        Ok(rotated) => rotated,
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+    let settings_sync_snapshot = match settings_sync.lock().await.rotate_keys() {
+        Ok(rotated) => rotated,
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+    Ok(CommandResponse::ok(KeyRotationReport {
+        config,
+        pending_restore,
+        session_sync_passphrase,
+        settings_sync_snapshot,
+    }))
+}
 
-    let CreateSessionPayload {
-        name,
-        mode,
-        topic_id,
-        is_active,
-    } = request;
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConfigResetPayload {
+    #[serde(default)]
+    section: Option<ConfigSection>,
+}
 
-    let resolved_name = name.unwrap_or_else(|| "Untitled Session".to_string());
-    let resolved_mode = mode.unwrap_or_else(|| "personal".to_string());
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConfigChangedEvent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    section: Option<ConfigSection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    backup_path: Option<String>,
+}
 
-    let session = state
-        .lock()
-        .await
-        .create_session(CreateSessionInput {
-            name: resolved_name,
-            mode: resolved_mode,
-            topic_id,
-            is_active,
-        })
-        .await;
-    Ok(CommandResponse::ok(session))
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConfigResetResponse {
+    config: AppConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    backup_path: Option<String>,
 }
 
+/// Restores the default config, or only one section of it, after copying
+/// config.json to a timestamped backup. Windows reload on `config_changed`.
 #[tauri::command]
-async fn chat_load_session(
-    state: State<'_, Mutex<SessionService>>,
-    payload: SessionIdPayload,
-) -> Result<CommandResponse<ChatSession>, String> {
-    let service = state.lock().await;
-    match service.load_session(&payload.session_id).await {
-        Some(session) => Ok(CommandResponse::ok(session)),
-        None => Ok(CommandResponse::error("Session not found".to_string())),
+async fn config_reset(
+    app: AppHandle<Wry>,
+    config_state: State<'_, ConfigState>,
+    gate: State<'_, OsAuthGate>,
+    payload: Option<ConfigResetPayload>,
+) -> Result<CommandResponse<ConfigResetResponse>, String> {
+    let section = payload.and_then(|request| request.section);
+    let (config, backup) = match config::reset_config(&app, &config_state, &gate, section).await {
+        Ok(reset) => reset,
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+    let backup_path = backup.map(|path| path.display().to_string());
+    log::info!(
+        "Reset config section {:?}; backup at {:?}",
+        section,
+        backup_path
+    );
+    if let Err(error) = app.emit(
+        "config_changed",
+        ConfigChangedEvent {
+            section,
+            backup_path: backup_path.clone(),
+        },
+    ) {
+        log::warn!("Failed to emit config_changed: {}", error);
     }
+    Ok(CommandResponse::ok(ConfigResetResponse {
+        config,
+        backup_path,
+    }))
 }
 
-#[tauri::command]
-async fn chat_save_session(
-    state: State<'_, Mutex<SessionService>>,
-    payload: SaveSessionPayload,
-) -> Result<CommandResponse<ChatSession>, String> {
-    let session = payload.session;
-    state
-        .lock()
-        .await
-        .save_session(session.clone())
-        .await
-        .map_err(|error| error.to_string())?;
-    Ok(CommandResponse::ok(session))
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetActiveAccountPayload {
+    name: String,
 }
 
+/// Switches the operator credentials to a saved Hedera account. Windows
+/// reload on `config_changed` and restart the agent with the new account.
 #[tauri::command]
-async fn chat_delete_session(
-    state: State<'_, Mutex<SessionService>>,
-    payload: SessionIdPayload,
-) -> Result<CommandResponse<bool>, String> {
-    let removed = state.lock().await.delete_session(&payload.session_id).await;
-    Ok(CommandResponse::ok(removed))
+async fn config_set_active_account(
+    app: AppHandle<Wry>,
+    config_state: State<'_, ConfigState>,
+    payload: SetActiveAccountPayload,
+) -> Result<CommandResponse<AppConfig>, String> {
+    let config = match config::set_active_account(&app, &config_state, payload.name.trim()) {
+        Ok(config) => config,
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+    log::info!(
+        "Active Hedera account is now {} ({})",
+        payload.name.trim(),
+        config.hedera.account_id
+    );
+    if let Err(error) = app.emit(
+        "config_changed",
+        ConfigChangedEvent {
+            section: Some(ConfigSection::Hedera),
+            backup_path: None,
+        },
+    ) {
+        log::warn!("Failed to emit config_changed: {}", error);
+    }
+    Ok(CommandResponse::ok(config))
 }
 
-#[tauri::command]
-async fn chat_load_all_sessions(
-    state: State<'_, Mutex<SessionService>>,
-) -> Result<CommandResponse<Vec<ChatSession>>, String> {
-    let sessions = state.lock().await.load_all_sessions().await;
-    Ok(CommandResponse::ok(sessions))
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LegalAcceptancePayload {
+    #[serde(default)]
+    terms: bool,
+    #[serde(default)]
+    privacy: bool,
+    /// The markdown the user was shown. Defaults to the one configured
+    /// through the environment.
+    #[serde(default)]
+    terms_markdown: Option<String>,
+    #[serde(default)]
+    privacy_markdown: Option<String>,
 }
 
+/// Records that the user accepted the terms, the privacy policy or both,
+/// with the time and a hash of the accepted text.
 #[tauri::command]
-async fn chat_save_message(
-    state: State<'_, Mutex<SessionService>>,
-    payload: SaveMessagePayload,
-) -> Result<CommandResponse<ChatMessage>, String> {
-    state
-        .lock()
-        .await
-        .save_message(&payload.session_id, payload.message.clone())
-        .await
-        .map_err(|error| error.to_string())?;
-    Ok(CommandResponse::ok(payload.message))
-}
+async fn legal_record_acceptance(
+    app: AppHandle<Wry>,
+    config_state: State<'_, ConfigState>,
+    payload: LegalAcceptancePayload,
+) -> Result<CommandResponse<LegalAcceptanceConfig>, String> {
+    if !payload.terms && !payload.privacy {
+        return Ok(CommandResponse::error(
+            "Nothing to accept; set terms or privacy".to_string(),
+        ));
+    }
+    let legal = match config::get_environment_config() {
+        Ok(environment) => environment.legal.unwrap_or_default(),
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+    let terms = payload
+        .terms
+        .then(|| payload.terms_markdown.or(legal.terms_markdown));
+    let privacy = payload
+        .privacy
+        .then(|| payload.privacy_markdown.or(legal.privacy_markdown));
+    if matches!(terms, Some(None)) {
+        return Ok(CommandResponse::error("No terms to accept".to_string()));
+    }
+    if matches!(privacy, Some(None)) {
+        return Ok(CommandResponse::error(
+            "No privacy policy to accept".to_string(),
+        ));
+    }
 
-#[tauri::command]
-async fn chat_load_session_messages(
-    state: State<'_, Mutex<SessionService>>,
-    payload: SessionIdPayload,
-) -> Result<CommandResponse<Vec<ChatMessage>>, String> {
-    let messages = state
-        .lock()
-        .await
-        .load_messages(&payload.session_id)
-        .await
-        .unwrap_or_default();
-    Ok(CommandResponse::ok(messages))
+    let terms = terms.flatten();
+    let privacy = privacy.flatten();
+    match config::record_legal_acceptance(&app, &config_state, terms.as_deref(), privacy.as_deref())
+    {
+        Ok(acceptance) => Ok(CommandResponse::ok(acceptance)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
 }
 
+/// Checks the saved config field by field, including whether the mirror node
+/// for its network answers, so the settings screen can flag each input.
 #[tauri::command]
-async fn agent_update_session_context(
-    agent_state: State<'_, Mutex<AgentService>>,
-    session_state: State<'_, Mutex<SessionService>>,
-    payload: SessionContextPayload,
-) -> Result<CommandResponse<()>, String> {
-    let context = AgentSessionContext {
-        session_id: payload.session_id.clone(),
-        mode: payload.mode.clone(),
-        topic_id: payload.topic_id.clone(),
+async fn config_validate(
+    app: AppHandle<Wry>,
+    config_state: State<'_, ConfigState>,
+    connection: State<'_, Mutex<ConnectionService>>,
+) -> Result<CommandResponse<ConfigValidationReport>, String> {
+    let config = load_config(app, config_state)?.config;
+    let mut errors = config_validation::validate(&config);
+    let network = match config.hedera.network {
+        Network::Mainnet => HederaNetwork::Mainnet,
+        Network::Testnet => HederaNetwork::Testnet,
     };
-    agent_state
-        .lock()
-        .await
-        .update_session_context(context)
-        .await;
-    session_state
-        .lock()
-        .await
-        .update_session_context(SessionContext {
-            session_id: payload.session_id,
-            mode: payload.mode,
-            topic_id: payload.topic_id,
-        })
-        .await;
-    Ok(CommandResponse::ok(()))
+    if let Err(error) = connection.lock().await.test_mirror(network).await {
+        errors.push(ConfigFieldError::new(
+            "hedera.network",
+            format!("Mirror node is unreachable: {error}"),
+        ));
+    }
+    Ok(CommandResponse::ok(errors.into()))
 }
 
+/// Reads a `.env` file and reports where each variable would go. Nothing is
+/// written until called again with `confirm`, optionally limited to `keys`.
 #[tauri::command]
-async fn chat_update_session_context(
-    state: State<'_, Mutex<SessionService>>,
-    payload: SessionContextPayload,
-) -> Result<CommandResponse<()>, String> {
-    state
-        .lock()
-        .await
-        .update_session_context(SessionContext {
-            session_id: payload.session_id,
-            mode: payload.mode,
-            topic_id: payload.topic_id,
-        })
-        .await;
-    Ok(CommandResponse::ok(()))
-}
+async fn config_import_env(
+    app: AppHandle<Wry>,
+    config_state: State<'_, ConfigState>,
+    credential_state: State<'_, Mutex<CredentialManager>>,
+    payload: ConfigImportEnvPayload,
+) -> Result<CommandResponse<EnvImportPlan>, String> {
+    let vars = match env_import::read_env_file(&PathBuf::from(&payload.path)) {
+        Ok(vars) => vars,
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+    let current = load_config(app.clone(), config_state.clone())?.config;
+    let mut plan = env_import::plan_import(&current, &vars, payload.keys.as_deref());
+    if !payload.confirm {
+        return Ok(CommandResponse::ok(plan));
+    }
 
-#[tauri::command]
-async fn chat_update_form_state(
-    state: State<'_, Mutex<SessionService>>,
-    payload: FormStatePayload,
-) -> Result<CommandResponse<Option<ChatMessage>>, String> {
-    let result = state
-        .lock()
-        .await
-        .update_form_state(
-            &payload.session_id,
-            &payload.form_id,
-            payload.completion_state,
-            payload.completion_data,
+    if plan
+        .entries
+        .iter()
+        .any(|entry| entry.target == EnvImportTarget::Config && entry.changed)
+        && let Err(error) = save_config(
+            app.clone(),
+            config_state,
+            app.state::<OsAuthGate>(),
+            plan.config.clone(),
         )
-        .await?;
-    Ok(CommandResponse::ok(result))
+        .await
+    {
+        return Ok(CommandResponse::error(error));
+    }
+    let credentials = credential_state.lock().await;
+    for (service, account, scope) in &plan.credentials {
+        let Some(value) = env_import::credential_value(&vars, account) else {
+            continue;
+        };
+        if let Err(error) = credentials
+            .store_with_scope(service, account, value, Some(*scope))
+            .await
+        {
+            return Ok(CommandResponse::error(format!(
+                "Failed to store {account}: {error}"
+            )));
+        }
+    }
+    log::info!(
+        "Imported {} variables from {}",
+        plan.entries.len(),
+        payload.path
+    );
+    plan.applied = true;
+    Ok(CommandResponse::ok(plan))
 }
 
+/// What the startup integrity checks found, so the renderer can tell the
+/// user about recovered or lost history.
 #[tauri::command]
-async fn chat_update_message_metadata(
-    state: State<'_, Mutex<SessionService>>,
-    payload: MessageMetadataPayload,
-) -> Result<CommandResponse<Option<ChatMessage>>, String> {
-    let updated = state
-        .lock()
-        .await
-        .update_message_metadata(&payload.session_id, &payload.message_id, payload.metadata)
-        .await?;
-    Ok(CommandResponse::ok(updated))
+async fn storage_recovery_report(
+    report: State<'_, StorageRecoveryReport>,
+) -> Result<CommandResponse<StorageRecoveryReport>, String> {
+    Ok(CommandResponse::ok(report.inner().clone()))
 }
 
+/// Local state worth attaching to a bug report.
 #[tauri::command]
-async fn browser_attach(app: AppHandle<Wry>) -> Result<(), String> {
-    log::info!("browser_attach invoked");
-    append_browser_log(&app, "browser_attach invoked");
-    let result = with_browser_manager_async(app.clone(), |handle| async move {
-        let manager = handle.state::<ActiveBrowserManager>();
-        manager.attach(&handle).await
-    })
-    .await;
-    if let Err(error) = &result {
-        append_browser_log(&app, &format!("browser_attach error: {error}"));
-    }
-    result
+async fn diagnostics_bundle(
+    app: AppHandle<Wry>,
+    report: State<'_, StorageRecoveryReport>,
+    hcs10: State<'_, Hcs10Service>,
+) -> Result<CommandResponse<DiagnosticsBundle>, String> {
+    let hcs10_states = hcs10.list_states().await.unwrap_or_else(|error| {
+        log::warn!("Failed to list HCS-10 states for diagnostics: {}", error);
+        Vec::new()
+    });
+    Ok(CommandResponse::ok(DiagnosticsBundle::new(
+        app.package_info().version.to_string(),
+        report.inner().clone(),
+        hcs10_states,
+    )))
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BridgeSelftestPayload {
+    /// Also query the testnet mirror node through the mirror bridge.
+    #[serde(default)]
+    include_network: bool,
 }
 
+/// Sends a canned request to each bundled Node bridge, so a broken install
+/// (missing resources, an old Node) shows up in one place.
 #[tauri::command]
-async fn browser_detach(app: AppHandle<Wry>) -> Result<(), String> {
-    append_browser_log(&app, "browser_detach invoked");
-    let result = with_browser_manager_async(app.clone(), |handle| async move {
-        let manager = handle.state::<ActiveBrowserManager>();
-        manager.detach(&handle).await
-    })
-    .await;
-    if let Err(error) = &result {
-        append_browser_log(&app, &format!("browser_detach error: {error}"));
+async fn diagnostics_run_bridge_selftest(
+    app: AppHandle<Wry>,
+    payload: Option<BridgeSelftestPayload>,
+) -> Result<CommandResponse<BridgeSelftestReport>, String> {
+    let payload = payload.unwrap_or_default();
+    let mirror_script = resolve_mirror_bridge_script(&app);
+    let mut probes = vec![
+        BridgeProbe::agent(resolve_bridge_script(&app)),
+        BridgeProbe::hcs10(resolve_hcs10_bridge_script(&app)),
+        BridgeProbe::mirror(mirror_script.clone()),
+        BridgeProbe::parser(resolve_transaction_parser_script(&app)),
+    ];
+    if payload.include_network {
+        probes.push(BridgeProbe::mirror_testnet(mirror_script));
     }
-    result
+    let report = run_bridge_selftest("node", &probes, TokioDuration::from_secs(30)).await;
+    if !report.passed {
+        log::warn!(
+            "Bridge self-test failed: {}",
+            report
+                .bridges
+                .iter()
+                .filter(|result| !result.passed)
+                .map(|result| result.bridge.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    Ok(CommandResponse::ok(report))
 }
 
 #[tauri::command]
-async fn browser_navigate(app: AppHandle<Wry>, url: String) -> Result<(), String> {
-    append_browser_log(&app, &format!("browser_navigate: {url}"));
-    let result = with_browser_manager_async(app.clone(), |handle| async move {
-        let manager = handle.state::<ActiveBrowserManager>();
-        manager.navigate(&handle, url).await
+async fn data_backup_create(
+    app: AppHandle<Wry>,
+    config_state: State<'_, ConfigState>,
+    payload: BackupCreatePayload,
+) -> Result<CommandResponse<BackupSummary>, String> {
+    let data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|error| error.to_string())?;
+    let master_password = config_state.master_password.clone();
+    let destination = PathBuf::from(payload.destination_path);
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        data_backup::create_backup(
+            &data_dir,
+            &destination,
+            &master_password,
+            &payload.passphrase,
+        )
     })
-    .await;
-    if let Err(error) = &result {
-        append_browser_log(&app, &format!("browser_navigate error: {error}"));
+    .await
+    .map_err(|error| error.to_string())?;
+    match result {
+        Ok(summary) => Ok(CommandResponse::ok(summary)),
+        Err(error) => Ok(CommandResponse::error(error)),
     }
-    result
 }
 
 #[tauri::command]
-async fn browser_reload(app: AppHandle<Wry>) -> Result<(), String> {
-    append_browser_log(&app, "browser_reload invoked");
-    let result = with_browser_manager_async(app.clone(), |handle| async move {
-        let manager = handle.state::<ActiveBrowserManager>();
-        manager.reload(&handle).await
+async fn data_backup_restore(
+    app: AppHandle<Wry>,
+    config_state: State<'_, ConfigState>,
+    payload: BackupRestorePayload,
+) -> Result<CommandResponse<RestoreSummary>, String> {
+    let data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|error| error.to_string())?;
+    let master_password = config_state.master_password.clone();
+    let source = PathBuf::from(payload.source_path);
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        data_backup::restore_backup(
+            &data_dir,
+            &source,
+            &master_password,
+            payload.passphrase.as_deref(),
+        )
     })
-    .await;
-    if let Err(error) = &result {
-        append_browser_log(&app, &format!("browser_reload error: {error}"));
+    .await
+    .map_err(|error| error.to_string())?;
+    match result {
+        Ok(summary) => Ok(CommandResponse::ok(summary)),
+        Err(error) => Ok(CommandResponse::error(error)),
     }
-    result
 }
 
 #[tauri::command]
-async fn browser_go_back(app: AppHandle<Wry>) -> Result<(), String> {
-    append_browser_log(&app, "browser_go_back invoked");
-    let result = with_browser_manager_async(app.clone(), |handle| async move {
-        let manager = handle.state::<ActiveBrowserManager>();
-        manager.go_back(&handle).await
-    })
-    .await;
-    if let Err(error) = &result {
-        append_browser_log(&app, &format!("browser_go_back error: {error}"));
-    }
-    result
+async fn startup_get_plan(
+    app: AppHandle<Wry>,
+    session_state: State<'_, Mutex<SessionService>>,
+    payload: Option<StartupPlanPayload>,
+) -> Result<CommandResponse<StartupPlan>, String> {
+    let profile_id = payload.and_then(|request| request.profile_id);
+    let config = match load_config(app.clone(), app.state::<ConfigState>()) {
+        Ok(response) => response.config,
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+    let preferences = config.startup.preferences_for(profile_id.as_deref());
+    let sessions = session_state.lock().await.load_all_sessions(&[]).await;
+    let workspace_persona = app
+        .state::<Mutex<WorkspaceRegistry>>()
+        .lock()
+        .await
+        .active()
+        .persona_id
+        .clone();
+    Ok(CommandResponse::ok(plan_startup(
+        preferences,
+        workspace_persona.as_deref(),
+        &sessions,
+    )))
 }
 
 #[tauri::command]
-async fn browser_go_forward(app: AppHandle<Wry>) -> Result<(), String> {
-    append_browser_log(&app, "browser_go_forward invoked");
-    let result = with_browser_manager_async(app.clone(), |handle| async move {
-        let manager = handle.state::<ActiveBrowserManager>();
-        manager.go_forward(&handle).await
-    })
-    .await;
-    if let Err(error) = &result {
-        append_browser_log(&app, &format!("browser_go_forward error: {error}"));
+async fn settings_sync_get_status(
+    state: State<'_, Mutex<SettingsSyncService>>,
+) -> Result<CommandResponse<SyncSettings>, String> {
+    Ok(CommandResponse::ok(state.lock().await.settings()))
+}
+
+#[tauri::command]
+async fn settings_sync_enable(
+    state: State<'_, Mutex<SettingsSyncService>>,
+    payload: SettingsSyncEnablePayload,
+) -> Result<CommandResponse<SyncSettings>, String> {
+    match state
+        .lock()
+        .await
+        .enable(PathBuf::from(payload.folder_path), &payload.passphrase)
+    {
+        Ok(settings) => Ok(CommandResponse::ok(settings)),
+        Err(error) => Ok(CommandResponse::error(error)),
     }
-    result
 }
 
 #[tauri::command]
-async fn browser_set_bounds(
+async fn settings_sync_disable(
+    state: State<'_, Mutex<SettingsSyncService>>,
+) -> Result<CommandResponse<SyncSettings>, String> {
+    let settings = state.lock().await.disable()?;
+    Ok(CommandResponse::ok(settings))
+}
+
+#[tauri::command]
+async fn settings_sync_push(
     app: AppHandle<Wry>,
-    bounds: browser::BoundsPayload,
-) -> Result<(), String> {
-    let result = with_browser_manager_async(app.clone(), |handle| async move {
-        let manager = handle.state::<ActiveBrowserManager>();
-        manager.set_bounds(&handle, bounds).await
-    })
-    .await;
-    if let Err(error) = &result {
-        append_browser_log(&app, &format!("browser_set_bounds error: {error}"));
+    state: State<'_, Mutex<SettingsSyncService>>,
+) -> Result<CommandResponse<u64>, String> {
+    let snapshot = collect_settings_snapshot(&app).await?;
+    match state.lock().await.push(&snapshot) {
+        Ok(revision) => Ok(CommandResponse::ok(revision)),
+        Err(error) => Ok(CommandResponse::error(error)),
     }
-    result
 }
 
+/// Merges the sync file into local settings. When fields changed on both
+/// sides the conflicts are returned untouched so the renderer can prompt,
+/// then call again with a `resolutions` entry per conflicting path.
 #[tauri::command]
-async fn browser_set_layout(
+async fn settings_sync_pull(
     app: AppHandle<Wry>,
-    layout: browser::LayoutPayload,
-) -> Result<(), String> {
-    let result = with_browser_manager_async(app.clone(), |handle| async move {
-        let manager = handle.state::<ActiveBrowserManager>();
-        manager.set_layout(&handle, layout).await
-    })
-    .await;
-    if let Err(error) = &result {
-        append_browser_log(&app, &format!("browser_set_layout error: {error}"));
+    state: State<'_, Mutex<SettingsSyncService>>,
+    payload: Option<SettingsSyncPullPayload>,
+) -> Result<CommandResponse<SyncCheck>, String> {
+    let resolutions = payload.unwrap_or_default().resolutions;
+    let local = collect_settings_snapshot(&app).await?;
+    let mut service = state.lock().await;
+    let check = match service.check(&local, &resolutions) {
+        Ok(check) => check,
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+
+    if check.state == SyncState::RemoteChanges
+        && let Some(merged) = check.merged.as_ref()
+    {
+        apply_settings_snapshot(&app, merged).await?;
+        service.mark_synced(merged, check.remote_revision)?;
+        if check.remote.as_ref() != Some(merged) {
+            service.push(merged)?;
+        }
     }
-    result
+
+    Ok(CommandResponse::ok(check))
 }
 
 #[tauri::command]
-async fn browser_capture_context(app: AppHandle<Wry>) -> Result<Option<Value>, String> {
-    with_browser_manager_async(app.clone(), |handle| async move {
-        let manager = handle.state::<ActiveBrowserManager>();
-        manager.capture_context(&handle).await
-    })
-    .await
+async fn session_sync_get_status(
+    state: State<'_, Mutex<SessionSyncService>>,
+) -> Result<CommandResponse<SessionSyncStatus>, String> {
+    Ok(CommandResponse::ok(state.lock().await.status()))
 }
 
+/// Joins the sync topic in `topicId`, or creates a private topic through the
+/// agent bridge when none is given. Other desktops join with the returned
+/// topic id and the same passphrase.
 #[tauri::command]
-async fn browser_get_state(app: AppHandle<Wry>) -> Result<BrowserStateDto, String> {
-    with_browser_manager_async(app.clone(), |handle| async move {
-        let manager = handle.state::<ActiveBrowserManager>();
-        manager.get_state().await
-    })
-    .await
+async fn session_sync_enable(
+    app: AppHandle<Wry>,
+    state: State<'_, Mutex<SessionSyncService>>,
+    payload: SessionSyncEnablePayload,
+) -> Result<CommandResponse<SessionSyncStatus>, String> {
+    let topic_id = match payload
+        .topic_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        Some(topic_id) => match parse_entity_id(topic_id, &configured_network(&app)) {
+            Ok(id) => id.to_string(),
+            Err(error) => return Ok(CommandResponse::error(error.into())),
+        },
+        None => {
+            let backend = app
+                .state::<Mutex<AgentService>>()
+                .lock()
+                .await
+                .backend()
+                .await;
+            let created = match backend {
+                Ok(backend) => backend
+                    .create_private_topic("hol-desktop session sync")
+                    .await
+                    .map_err(|error| error.to_string()),
+                Err(error) => Err(error),
+            };
+            match created {
+                Ok(topic_id) => topic_id,
+                Err(error) => return Ok(CommandResponse::error(error)),
+            }
+        }
+    };
+
+    match state.lock().await.enable(&topic_id, &payload.passphrase) {
+        Ok(status) => Ok(CommandResponse::ok(status)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
 }
 
 #[tauri::command]
-async fn browser_execute_js(app: AppHandle<Wry>, script: String) -> Result<Value, String> {
-    let result = with_browser_manager_async(app.clone(), |handle| async move {
-        let manager = handle.state::<ActiveBrowserManager>();
-        manager.execute_js(&handle, script).await
-    })
-    .await;
-    if let Err(error) = &result {
-        append_browser_log(&app, &format!("browser_execute_js error: {error}"));
+async fn session_sync_disable(
+    state: State<'_, Mutex<SessionSyncService>>,
+) -> Result<CommandResponse<SessionSyncStatus>, String> {
+    let status = state.lock().await.disable()?;
+    Ok(CommandResponse::ok(status))
+}
+
+#[tauri::command]
+async fn session_sync_now(
+    app: AppHandle<Wry>,
+) -> Result<CommandResponse<SessionSyncReport>, String> {
+    match run_session_sync(&app).await {
+        Ok(report) => Ok(CommandResponse::ok(report)),
+        Err(error) => Ok(CommandResponse::error(error)),
     }
-    result
 }
 
 #[tauri::command]
-async fn browser_open_devtools(app: AppHandle<Wry>) -> Result<(), String> {
-    with_browser_manager_async(app.clone(), |handle| async move {
-        let manager = handle.state::<ActiveBrowserManager>();
-        manager.open_devtools(&handle).await
-    })
-    .await
+async fn workspace_list(
+    state: State<'_, Mutex<WorkspaceRegistry>>,
+) -> Result<CommandResponse<WorkspaceList>, String> {
+    Ok(CommandResponse::ok(state.lock().await.list()))
 }
 
 #[tauri::command]
-fn browser_open_external(url: String) -> Result<(), String> {
-    open::that(url).map_err(|error| error.to_string())
+async fn workspace_create(
+    state: State<'_, Mutex<WorkspaceRegistry>>,
+    payload: WorkspaceCreatePayload,
+) -> Result<CommandResponse<Workspace>, String> {
+    match state.lock().await.create(&payload.name, payload.persona_id) {
+        Ok(workspace) => Ok(CommandResponse::ok(workspace)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
 }
 
-fn with_browser_manager<F, R>(app: &AppHandle<Wry>, f: F) -> Result<R, String>
-where
-    F: FnOnce(&ActiveBrowserManager) -> Result<R, String>,
-{
-    let state = app
-        .try_state::<ActiveBrowserManager>()
-        .ok_or_else(|| "Browser manager unavailable".to_string())?;
-    f(&*state)
+/// Reopens sessions, entities, contacts, the HCS-10 inbox and MCP servers on
+/// the workspace's own files. The agent is disconnected because its session and MCP tools belong to the
+/// workspace being left.
+#[tauri::command]
+async fn workspace_switch(
+    app: AppHandle<Wry>,
+    state: State<'_, Mutex<WorkspaceRegistry>>,
+    payload: WorkspaceIdPayload,
+) -> Result<CommandResponse<Workspace>, String> {
+    let mut registry = state.lock().await;
+    if registry.active().id == payload.workspace_id {
+        return Ok(CommandResponse::ok(registry.active().clone()));
+    }
+    if registry.get(&payload.workspace_id).is_none() {
+        return Ok(CommandResponse::error(format!(
+            "Workspace not found: {}",
+            payload.workspace_id
+        )));
+    }
+
+    let paths = registry.paths(&payload.workspace_id);
+    let session_service = SessionService::from_path(&paths.chat_db)?;
+    let entity_service = EntityService::from_path(&paths.chat_db)?;
+    let hcs10_inbox = Hcs10Inbox::from_path(&paths.chat_db)?;
+    let contacts = ContactBook::from_path(&paths.chat_db)?;
+    let registry_db_path = app
+        .path()
+        .app_data_dir()
+        .map_err(|error| error.to_string())?
+        .join("mcp-registry.sqlite");
+    let credentials = app.state::<Mutex<CredentialManager>>().lock().await.clone();
+    let sampling = app
+        .state::<tokio::sync::mpsc::UnboundedSender<SamplingCall>>()
+        .inner()
+        .clone();
+    let tool_list_changes = app
+        .state::<tokio::sync::mpsc::UnboundedSender<String>>()
+        .inner()
+        .clone();
+    let mcp_service = McpService::new(paths.mcp_servers, Some(registry_db_path))
+        .with_credentials(credentials)
+        .with_sampling(sampling)
+        .with_tool_list_changes(tool_list_changes)
+        .with_tool_calls(app.state::<Arc<McpToolCalls>>().inner().clone())
+        .with_process_groups(app.state::<Arc<ProcessGroups>>().inner().clone());
+
+    let workspace = match registry.switch(&payload.workspace_id) {
+        Ok(workspace) => workspace,
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+    drop(registry);
+
+    app.state::<Mutex<AgentService>>()
+        .lock()
+        .await
+        .disconnect()
+        .await;
+    *app.state::<Mutex<SessionService>>().lock().await = session_service;
+    *app.state::<Mutex<EntityService>>().lock().await = entity_service;
+    app.state::<Hcs10Inbox>().replace(hcs10_inbox).await;
+    app.state::<ContactBook>().replace(contacts).await;
+    let previous_mcp = std::mem::replace(
+        &mut *app.state::<Mutex<McpService>>().lock().await,
+        mcp_service,
+    );
+    previous_mcp.shutdown().await;
+    tauri::async_runtime::spawn(initialize_mcp_service(app.clone()));
+    tauri::async_runtime::spawn(dispatch_outbox(app.clone()));
+
+    log::info!("Switched to workspace {}", workspace.name);
+    if let Err(error) = app.emit(EVENT_WORKSPACE_SWITCHED, &workspace) {
+        log::warn!("Failed to emit {}: {}", EVENT_WORKSPACE_SWITCHED, error);
+    }
+    Ok(CommandResponse::ok(workspace))
 }
 
-async fn with_browser_manager_async<F, Fut, R>(app: AppHandle<Wry>, f: F) -> Result<R, String>
-where
-    F: FnOnce(AppHandle<Wry>) -> Fut + Send + 'static,
-    Fut: std::future::Future<Output = Result<R, String>> + Send + 'static,
-    R: Send + 'static,
-{
-    let _ = app
-        .try_state::<ActiveBrowserManager>()
-        .ok_or_else(|| "Browser manager unavailable".to_string())?;
-    f(app).await
+async fn active_workspace_id(app: &AppHandle<Wry>) -> String {
+    app.state::<Mutex<WorkspaceRegistry>>()
+        .lock()
+        .await
+        .active()
+        .id
+        .clone()
 }
 
-fn append_browser_log(app: &AppHandle<Wry>, message: &str) {
-    if let Ok(config_dir) = app.path().app_data_dir() {
-        if let Err(err) = std::fs::create_dir_all(&config_dir) {
-            log::warn!("Failed to create browser log directory: {err}");
-            return;
+#[tauri::command]
+async fn network_get_status(
+    state: State<'_, NetworkMonitor>,
+) -> Result<CommandResponse<NetworkState>, String> {
+    Ok(CommandResponse::ok(state.state()))
+}
+
+/// Queues a message or transaction for when it can be sent. Payloads are the
+/// same as for `agent_send_message` and `execute-transaction-bytes`; queued
+/// messages must name their session.
+#[tauri::command]
+async fn outbox_enqueue(
+    app: AppHandle<Wry>,
+    outbox: State<'_, Outbox>,
+    network: State<'_, NetworkMonitor>,
+    payload: OutboxEnqueuePayload,
+) -> Result<CommandResponse<OutboxItem>, String> {
+    let validation = match payload.kind {
+        OutboxKind::Message => {
+            serde_json::from_value::<AgentMessageRequest>(payload.payload.clone())
+                .map_err(|error| format!("Invalid message: {error}"))
+                .and_then(|request| match request.session_id {
+                    Some(_) => Ok(()),
+                    None => Err("Queued messages need a sessionId".to_string()),
+                })
         }
-        let log_path = config_dir.join("browser.log");
-        if let Ok(mut file) = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&log_path)
-        {
-            let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
-            let _ = writeln!(file, "[{timestamp}] {message}");
-            log::info!("[browser_log] {} => {}", timestamp, message);
-            log::info!("[browser_log] path: {}", log_path.display());
+        OutboxKind::Transaction => {
+            serde_json::from_value::<ExecuteTransactionPayload>(payload.payload.clone())
+                .map(|_| ())
+                .map_err(|error| format!("Invalid transaction: {error}"))
+        }
+    };
+    if let Err(error) = validation {
+        return Ok(CommandResponse::error(error));
+    }
+
+    let workspace_id = active_workspace_id(&app).await;
+    let item = match outbox
+        .enqueue(
+            &workspace_id,
+            payload.kind,
+            &payload.payload,
+            payload.depends_on.as_deref(),
+        )
+        .await
+    {
+        Ok(item) => item,
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+
+    emit_outbox_updated(&app, std::slice::from_ref(&item));
+    if network.state().online {
+        tauri::async_runtime::spawn(dispatch_outbox(app.clone()));
+    }
+    Ok(CommandResponse::ok(item))
+}
+
+#[tauri::command]
+async fn outbox_list(
+    app: AppHandle<Wry>,
+    outbox: State<'_, Outbox>,
+    payload: OutboxListPayload,
+) -> Result<CommandResponse<Vec<OutboxItem>>, String> {
+    let workspace_id = active_workspace_id(&app).await;
+    match outbox.list(&workspace_id, payload.include_finished).await {
+        Ok(items) => Ok(CommandResponse::ok(items)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+/// Cancels a queued item and everything queued behind it as a dependency.
+#[tauri::command]
+async fn outbox_cancel(
+    app: AppHandle<Wry>,
+    outbox: State<'_, Outbox>,
+    payload: OutboxIdPayload,
+) -> Result<CommandResponse<Vec<OutboxItem>>, String> {
+    match outbox.cancel(&payload.id).await {
+        Ok(items) => {
+            emit_outbox_updated(&app, &items);
+            Ok(CommandResponse::ok(items))
         }
+        Err(error) => Ok(CommandResponse::error(error)),
     }
 }
 
@@ -2493,6 +7837,254 @@ async fn mcp_disconnect_server(
     }
 }
 
+#[tauri::command]
+async fn mcp_get_health(
+    state: State<'_, Mutex<McpService>>,
+) -> Result<CommandResponse<Vec<McpServerHealth>>, String> {
+    let service = state.lock().await;
+    Ok(CommandResponse::ok(service.health().await))
+}
+
+/// Signs in to an HTTP MCP server whose `config.auth.type` is `oauth`,
+/// through the system browser. The service lock is released while the user
+/// completes the sign-in.
+#[tauri::command]
+async fn mcp_oauth_start(
+    state: State<'_, Mutex<McpService>>,
+    server_id: String,
+) -> Result<CommandResponse<bool>, String> {
+    let target = state.lock().await.oauth_target(&server_id).await;
+    let (url, config, credentials) = match target {
+        Ok(target) => target,
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+    let client = match proxy::client() {
+        Ok(client) => client,
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+    let result = mcp_oauth::authorize(
+        &client,
+        &credentials,
+        &server_id,
+        &url,
+        &config,
+        |authorization_url| {
+            open::that(authorization_url)
+                .map_err(|error| format!("Failed to open the browser: {error}"))
+        },
+    )
+    .await;
+    match result {
+        Ok(_) => Ok(CommandResponse::ok(true)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn mcp_oauth_sign_out(
+    state: State<'_, Mutex<McpService>>,
+    server_id: String,
+) -> Result<CommandResponse<bool>, String> {
+    let target = state.lock().await.oauth_target(&server_id).await;
+    let result = match target {
+        Ok((_, _, credentials)) => mcp_oauth::sign_out(&credentials, &server_id).await,
+        Err(error) => Err(error),
+    };
+    match result {
+        Ok(removed) => Ok(CommandResponse::ok(removed)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn mcp_check_updates(
+    state: State<'_, Mutex<McpService>>,
+) -> Result<CommandResponse<Vec<McpServerUpdate>>, String> {
+    let targets = state.lock().await.update_targets().await;
+    match mcp_updates::check_updates(targets).await {
+        Ok(updates) => Ok(CommandResponse::ok(updates)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+/// Reinstalls a server's npm or PyPI package at `version`, or at the latest
+/// published version when none is given.
+#[tauri::command]
+async fn mcp_upgrade_server(
+    state: State<'_, Mutex<McpService>>,
+    server_id: String,
+    version: Option<String>,
+) -> Result<CommandResponse<Value>, String> {
+    let service = state.lock().await;
+    let result = service.upgrade_server(&server_id, version).await;
+    match result {
+        Ok(server) => Ok(CommandResponse::ok(server)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+/// Adds the servers of a `claude_desktop_config.json` at `path`.
+#[tauri::command]
+async fn mcp_import_config(
+    state: State<'_, Mutex<McpService>>,
+    path: String,
+) -> Result<CommandResponse<McpConfigImport>, String> {
+    let service = state.lock().await;
+    let result = service.import_claude_config(Path::new(&path)).await;
+    match result {
+        Ok(report) => Ok(CommandResponse::ok(report)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+/// Asks where to save `file_name` with the native save dialog. `None` when
+/// the user cancels.
+async fn pick_save_path(app: &AppHandle<Wry>, file_name: &str) -> Result<Option<PathBuf>, String> {
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .file()
+        .set_file_name(file_name)
+        .add_filter("JSON", &["json"])
+        .save_file(move |path| {
+            let _ = sender.send(path);
+        });
+    let picked = receiver
+        .await
+        .map_err(|_| "The save dialog closed unexpectedly".to_string())?;
+    picked
+        .map(|path| {
+            path.into_path()
+                .map_err(|error| format!("Invalid save location: {error}"))
+        })
+        .transpose()
+}
+
+/// Writes the configured servers to a Claude Desktop config the user picks
+/// in a save dialog, so the renderer never chooses where secrets land.
+#[tauri::command]
+async fn mcp_export_config(
+    app: AppHandle<Wry>,
+    state: State<'_, Mutex<McpService>>,
+    include_secrets: Option<bool>,
+) -> Result<CommandResponse<McpConfigExport>, String> {
+    let path = match pick_save_path(&app, "claude_desktop_config.json").await {
+        Ok(Some(path)) => path,
+        Ok(None) => return Ok(CommandResponse::error("Export cancelled".to_string())),
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+    let service = state.lock().await;
+    let result = service
+        .export_claude_config(&path, include_secrets.unwrap_or(false))
+        .await;
+    match result {
+        Ok(report) => Ok(CommandResponse::ok(report)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn mcp_list_groups(
+    state: State<'_, Mutex<McpService>>,
+) -> Result<CommandResponse<Vec<McpServerGroup>>, String> {
+    let service = state.lock().await;
+    Ok(CommandResponse::ok(service.list_groups().await))
+}
+
+/// Creates a named group of servers, or replaces the servers of an
+/// existing group with the same name.
+#[tauri::command]
+async fn mcp_save_group(
+    state: State<'_, Mutex<McpService>>,
+    name: String,
+    server_ids: Vec<String>,
+) -> Result<CommandResponse<McpServerGroup>, String> {
+    let service = state.lock().await;
+    let result = service.save_group(&name, server_ids).await;
+    match result {
+        Ok(group) => Ok(CommandResponse::ok(group)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn mcp_delete_group(
+    state: State<'_, Mutex<McpService>>,
+    name: String,
+) -> Result<CommandResponse<bool>, String> {
+    let service = state.lock().await;
+    let result = service.delete_group(&name).await;
+    match result {
+        Ok(deleted) => Ok(CommandResponse::ok(deleted)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+/// Connects exactly the servers of the named group, disconnecting the rest.
+#[tauri::command]
+async fn mcp_activate_group(
+    state: State<'_, Mutex<McpService>>,
+    name: String,
+) -> Result<CommandResponse<McpGroupActivation>, String> {
+    let service = state.lock().await;
+    let result = service.activate_group(&name).await;
+    match result {
+        Ok(activation) => Ok(CommandResponse::ok(activation)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+/// Clears the active group and connects every enabled server again.
+#[tauri::command]
+async fn mcp_deactivate_group(
+    state: State<'_, Mutex<McpService>>,
+) -> Result<CommandResponse<McpGroupActivation>, String> {
+    let service = state.lock().await;
+    let result = service.deactivate_group().await;
+    match result {
+        Ok(activation) => Ok(CommandResponse::ok(activation)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+/// Runs a tool on an MCP server from the renderer, without the agent
+/// bridge. `arguments` must match the tool's input schema.
+#[tauri::command]
+async fn mcp_call_tool(
+    state: State<'_, Mutex<McpService>>,
+    server_id: String,
+    tool_name: String,
+    arguments: Option<Value>,
+    call_id: Option<String>,
+) -> Result<CommandResponse<McpToolCallResult>, String> {
+    // Only preparing needs the service; the call itself runs unlocked so
+    // other MCP commands are not stuck behind a slow tool.
+    let prepared = state
+        .lock()
+        .await
+        .prepare_tool_call(&server_id, &tool_name, arguments, call_id)
+        .await;
+    let result = match prepared {
+        Ok(prepared) => prepared.run().await,
+        Err(error) => Err(error),
+    };
+    match result {
+        Ok(result) => Ok(CommandResponse::ok(result)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+/// Cancels a running `mcp_call_tool` by the `callId` it was given.
+#[tauri::command]
+async fn mcp_cancel_tool_call(
+    calls: State<'_, Arc<McpToolCalls>>,
+    call_id: String,
+) -> Result<CommandResponse<bool>, String> {
+    match calls.cancel(&call_id) {
+        Ok(()) => Ok(CommandResponse::ok(true)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
 #[tauri::command]
 async fn mcp_get_server_tools(
     state: State<'_, Mutex<McpService>>,
@@ -2508,11 +8100,13 @@ async fn mcp_get_server_tools(
 
 #[tauri::command]
 async fn mcp_refresh_server_tools(
+    app: AppHandle<Wry>,
     state: State<'_, Mutex<McpService>>,
     server_id: String,
 ) -> Result<CommandResponse<McpConnectionResponse>, String> {
     let service = state.lock().await;
     let result = service.refresh_tools(&server_id).await;
+    emit_mcp_tool_changes(&app, &service).await;
     match result {
         Ok(result) => Ok(CommandResponse::ok(result.into())),
         Err(error) => Ok(CommandResponse::error(error)),
@@ -2574,6 +8168,35 @@ async fn mcp_install_from_registry(
     }
 }
 
+/// Fills in the API keys, paths and other values a registry install left
+/// pending, and saves the server once they validate.
+#[tauri::command]
+async fn mcp_resolve_install_variables(
+    state: State<'_, Mutex<McpService>>,
+    server_id: String,
+    values: HashMap<String, String>,
+) -> Result<CommandResponse<Value>, String> {
+    let service = state.lock().await;
+    let result = service.resolve_install_variables(&server_id, values).await;
+    match result {
+        Ok(server) => Ok(CommandResponse::ok(server)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+/// Approves or declines a server's pending sampling request.
+#[tauri::command]
+async fn mcp_resolve_sampling(
+    state: State<'_, Mutex<SamplingApprovals>>,
+    request_id: String,
+    decision: SamplingDecision,
+) -> Result<CommandResponse<bool>, String> {
+    match state.lock().await.resolve(&request_id, decision) {
+        Ok(()) => Ok(CommandResponse::ok(true)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
 #[tauri::command]
 async fn mcp_clear_registry_cache(
     state: State<'_, Mutex<McpService>>,
@@ -2586,6 +8209,28 @@ async fn mcp_clear_registry_cache(
     }
 }
 
+#[tauri::command]
+async fn mcp_get_tool_catalog(
+    state: State<'_, Mutex<McpService>>,
+) -> Result<CommandResponse<Vec<Value>>, String> {
+    let service = state.lock().await;
+    match service.tool_catalog().await {
+        Ok(catalog) => Ok(CommandResponse::ok(catalog)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn mcp_get_memory_stats(
+    state: State<'_, Mutex<McpService>>,
+) -> Result<CommandResponse<Value>, String> {
+    let service = state.lock().await;
+    match service.memory_stats().await {
+        Ok(stats) => Ok(CommandResponse::ok(stats)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
 #[tauri::command]
 async fn mcp_get_cache_stats(
     state: State<'_, Mutex<McpService>>,
@@ -2722,6 +8367,27 @@ async fn hcs10_clear_all_states(
     Ok(CommandResponse::ok(()))
 }
 
+#[tauri::command]
+async fn hcs10_list_states(
+    service: State<'_, Hcs10Service>,
+) -> Result<CommandResponse<Vec<Hcs10StateSummary>>, String> {
+    match service.list_states().await {
+        Ok(states) => Ok(CommandResponse::ok(states)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+#[tauri::command]
+async fn hcs10_delete_state(
+    service: State<'_, Hcs10Service>,
+    payload: Hcs10StateNamePayload,
+) -> Result<CommandResponse<bool>, String> {
+    match service.delete_state(&payload.name).await {
+        Ok(deleted) => Ok(CommandResponse::ok(deleted)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
 #[tauri::command]
 async fn hcs10_retrieve_profile(
     app: AppHandle<Wry>,
@@ -2740,7 +8406,9 @@ async fn hcs10_retrieve_profile(
 
     let LoadConfigResponse { config, .. } = load_config(app.clone(), config_state.clone())?;
 
-    if config.hedera.account_id.trim().is_empty() || config.hedera.private_key.trim().is_empty() {
+    if config.hedera.account_id.trim().is_empty()
+        || config.hedera.private_key.expose().trim().is_empty()
+    {
         return Ok(CommandResponse::error(
             "Missing Hedera credentials. Please configure your Hedera account.".to_string(),
         ));
@@ -2759,7 +8427,179 @@ async fn hcs10_retrieve_profile(
     });
 
     match bridge.retrieve_profile(request).await {
-        Ok(value) => Ok(CommandResponse::ok(value)),
+        Ok(value) => {
+            if value.get("success").and_then(Value::as_bool) != Some(false)
+                && let Err(error) = app
+                    .state::<ContactBook>()
+                    .cache_profile(&payload.account_id, &value)
+                    .await
+            {
+                log::warn!(
+                    "Failed to cache HCS-10 profile for {}: {}",
+                    payload.account_id,
+                    error
+                );
+            }
+            Ok(CommandResponse::ok(value))
+        }
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+fn hcs10_credentials(config: &AppConfig) -> Result<Value, String> {
+    if config.hedera.account_id.trim().is_empty()
+        || config.hedera.private_key.expose().trim().is_empty()
+    {
+        return Err(
+            "Missing Hedera credentials. Please configure your Hedera account.".to_string(),
+        );
+    }
+    Ok(json!({
+        "accountId": config.hedera.account_id,
+        "privateKey": config.hedera.private_key,
+        "network": match config.hedera.network {
+            Network::Mainnet => "mainnet",
+            Network::Testnet => "testnet",
+        }
+    }))
+}
+
+/// Reads the local profile's inbound topic through the HCS10 bridge and
+/// stores any new connection requests, notifying the renderer about them.
+/// Does nothing until the configured account has a registered profile.
+async fn refresh_connection_requests(
+    app: &AppHandle<Wry>,
+) -> Result<Vec<ConnectionRequest>, String> {
+    let LoadConfigResponse { config, .. } = load_config(app.clone(), app.state::<ConfigState>())?;
+    let account_id = config.hedera.account_id.trim().to_string();
+    if !config
+        .hcs10_profiles
+        .iter()
+        .any(|profile| profile.account_id == account_id)
+    {
+        return Ok(Vec::new());
+    }
+
+    let hedera = hcs10_credentials(&config)?;
+    let bridge = app
+        .state::<Hcs10Service>()
+        .bridge()
+        .ok_or_else(|| "HCS10 bridge not available".to_string())?;
+    let inbox = app.state::<Hcs10Inbox>();
+    let inbound_topic_id = inbox.inbound_topic_for(&account_id).await?;
+    let after_sequence = match inbound_topic_id.as_deref() {
+        Some(topic_id) => inbox.fetch_after(topic_id).await?,
+        None => 0,
+    };
+
+    let mut request = json!({ "hedera": hedera, "afterSequence": after_sequence });
+    if let Some(topic_id) = inbound_topic_id {
+        request["inboundTopicId"] = Value::String(topic_id);
+    }
+    let response = bridge.fetch_connection_requests(request).await?;
+    let discovered: DiscoveredRequests = serde_json::from_value(response)
+        .map_err(|error| format!("Invalid connection requests from HCS10 bridge: {error}"))?;
+
+    let received = inbox.record(&account_id, &discovered).await?;
+    if !received.is_empty()
+        && let Err(error) = app.emit(EVENT_CONNECTION_REQUESTS, &received)
+    {
+        log::warn!("Failed to emit {}: {}", EVENT_CONNECTION_REQUESTS, error);
+    }
+    Ok(received)
+}
+
+#[tauri::command]
+async fn hcs10_list_connection_requests(
+    app: AppHandle<Wry>,
+    inbox: State<'_, Hcs10Inbox>,
+    payload: Option<Hcs10ConnectionRequestsPayload>,
+) -> Result<CommandResponse<Vec<ConnectionRequest>>, String> {
+    let payload = payload.unwrap_or_default();
+    if payload.refresh
+        && let Err(error) = refresh_connection_requests(&app).await
+    {
+        return Ok(CommandResponse::error(error));
+    }
+
+    match inbox.list(payload.status).await {
+        Ok(requests) => Ok(CommandResponse::ok(requests)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+/// Accepts a pending request by creating the shared connection topic and
+/// posting `connection_created` on the inbound topic.
+#[tauri::command]
+async fn hcs10_accept_connection_request(
+    app: AppHandle<Wry>,
+    service: State<'_, Hcs10Service>,
+    inbox: State<'_, Hcs10Inbox>,
+    payload: Hcs10ConnectionRequestPayload,
+) -> Result<CommandResponse<ConnectionRequest>, String> {
+    let Some(bridge) = service.bridge() else {
+        return Ok(CommandResponse::error(
+            "HCS10 bridge not available".to_string(),
+        ));
+    };
+    let request = match inbox.pending(&payload.request_id).await {
+        Ok(request) => request,
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+
+    let LoadConfigResponse { config, .. } = load_config(app.clone(), app.state::<ConfigState>())?;
+    if config.hedera.account_id.trim() != request.account_id {
+        return Ok(CommandResponse::error(format!(
+            "Connection request was sent to {}, not the configured account",
+            request.account_id
+        )));
+    }
+    let hedera = match hcs10_credentials(&config) {
+        Ok(hedera) => hedera,
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+
+    let accepted = bridge
+        .accept_connection_request(json!({
+            "hedera": hedera,
+            "inboundTopicId": request.inbound_topic_id,
+            "requesterAccountId": request.requester_account_id,
+            "sequenceNumber": request.sequence_number,
+        }))
+        .await;
+    let connection_topic_id = match accepted {
+        Ok(value) => value
+            .get("connectionTopicId")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        Err(error) => return Ok(CommandResponse::error(error)),
+    };
+
+    match inbox
+        .respond(
+            &request.id,
+            ConnectionRequestStatus::Accepted,
+            connection_topic_id.as_deref(),
+        )
+        .await
+    {
+        Ok(request) => Ok(CommandResponse::ok(request)),
+        Err(error) => Ok(CommandResponse::error(error)),
+    }
+}
+
+/// HCS-10 has no decline message, so a rejected request is simply left
+/// unanswered on the inbound topic and hidden from the pending list.
+#[tauri::command]
+async fn hcs10_reject_connection_request(
+    inbox: State<'_, Hcs10Inbox>,
+    payload: Hcs10ConnectionRequestPayload,
+) -> Result<CommandResponse<ConnectionRequest>, String> {
+    match inbox
+        .respond(&payload.request_id, ConnectionRequestStatus::Rejected, None)
+        .await
+    {
+        Ok(request) => Ok(CommandResponse::ok(request)),
         Err(error) => Ok(CommandResponse::error(error)),
     }
 }
@@ -3122,6 +8962,11 @@ async fn ensure_session_exists(
             last_message_at: None,
             is_active: true,
             messages: Vec::new(),
+            tags: Vec::new(),
+            pinned_message_ids: Vec::new(),
+            last_read_at: Some(now.clone()),
+            unread_count: 0,
+            llm: None,
         };
         session_state.lock().await.save_session(session).await?;
     }
@@ -0,0 +1,273 @@
+use std::path::Path;
+
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use rand::{RngCore, rngs::OsRng};
+
+use crate::config::{self, LEGACY_MASTER_PASSWORD};
+use crate::credentials::CredentialManager;
+use crate::session_sync::SessionSyncService;
+use crate::settings_sync::SettingsSyncService;
+
+const KEYCHAIN_SERVICE: &str = "com.hashgraphonline.desktop";
+const KEYCHAIN_ACCOUNT: &str = "master-key";
+/// Written next to the app data once the master key is in the keychain, so
+/// a keychain that cannot be read later is not mistaken for one without a
+/// key.
+const KEYCHAIN_MARKER: &str = "master-key.keychain";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MasterKeySource {
+    /// `MASTER_PASSWORD` is set. It overrides the keychain, for setups that
+    /// share one key across machines, such as settings sync.
+    Environment,
+    Keychain,
+    /// Generated and stored on this launch. Data written under the legacy
+    /// password has to be migrated to it.
+    Created,
+    /// The keychain could not be used, so the legacy password is in effect.
+    Legacy,
+}
+
+pub struct MasterKey {
+    pub password: String,
+    pub source: MasterKeySource,
+}
+
+/// Where the master key is kept between launches.
+pub trait SecretStore {
+    fn get(&self) -> Result<Option<String>, String>;
+    fn set(&self, secret: &str) -> Result<(), String>;
+}
+
+/// The OS keychain: Keychain on macOS, the Credential Manager (DPAPI) on
+/// Windows and the Secret Service on Linux.
+pub struct Keychain;
+
+impl Keychain {
+    fn entry() -> Result<keyring::Entry, String> {
+        keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT)
+            .map_err(|error| format!("Failed to open keychain entry: {error}"))
+    }
+}
+
+impl SecretStore for Keychain {
+    fn get(&self) -> Result<Option<String>, String> {
+        match Self::entry()?.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(error) => Err(format!("Failed to read master key from keychain: {error}")),
+        }
+    }
+
+    fn set(&self, secret: &str) -> Result<(), String> {
+        Self::entry()?
+            .set_password(secret)
+            .map_err(|error| format!("Failed to store master key in keychain: {error}"))
+    }
+}
+
+/// Picks the master key: `MASTER_PASSWORD` when set, else the one in
+/// `store`, else a new random key saved to `store`. Fails when `store` once
+/// held the key but cannot be read now, rather than falling back to the
+/// legacy password the data is no longer encrypted with.
+pub fn load(
+    env_password: Option<String>,
+    store: &impl SecretStore,
+    config_dir: &Path,
+) -> Result<MasterKey, String> {
+    if let Some(password) = env_password.filter(|password| !password.is_empty()) {
+        return Ok(MasterKey {
+            password,
+            source: MasterKeySource::Environment,
+        });
+    }
+
+    let marker = config_dir.join(KEYCHAIN_MARKER);
+    let legacy = || MasterKey {
+        password: LEGACY_MASTER_PASSWORD.to_string(),
+        source: MasterKeySource::Legacy,
+    };
+    match store.get() {
+        Ok(Some(password)) if !password.is_empty() => {
+            mark_keychain_in_use(&marker);
+            Ok(MasterKey {
+                password,
+                source: MasterKeySource::Keychain,
+            })
+        }
+        Ok(_) => {
+            if marker.exists() {
+                log::error!("The master key is missing from the keychain; creating a new one");
+            }
+            let mut key = [0u8; 32];
+            OsRng.fill_bytes(&mut key);
+            let password = STANDARD.encode(key);
+            match store.set(&password) {
+                Ok(()) => {
+                    mark_keychain_in_use(&marker);
+                    Ok(MasterKey {
+                        password,
+                        source: MasterKeySource::Created,
+                    })
+                }
+                Err(error) => {
+                    log::warn!("{}; secrets stay under the legacy password", error);
+                    Ok(legacy())
+                }
+            }
+        }
+        Err(error) if marker.exists() => Err(format!(
+            "{error}. The master key is in the OS keychain; unlock it and restart the app"
+        )),
+        Err(error) => {
+            log::warn!("{}; secrets stay under the legacy password", error);
+            Ok(legacy())
+        }
+    }
+}
+
+fn mark_keychain_in_use(marker: &Path) {
+    if !marker.exists()
+        && let Err(error) = std::fs::write(marker, b"")
+    {
+        log::warn!("Failed to record that the master key is in the keychain: {error}");
+    }
+}
+
+/// Moves what the app encrypts in `config_dir` from the legacy password to
+/// `master_password`. A store that fails is logged and left as it was.
+pub async fn migrate_legacy_data(config_dir: &Path, master_password: &str) {
+    if master_password == LEGACY_MASTER_PASSWORD {
+        return;
+    }
+
+    match config::rewrap_config_file(
+        &config_dir.join("config.json"),
+        LEGACY_MASTER_PASSWORD,
+        master_password,
+    ) {
+        Ok(count) => log::info!("Moved {} config secrets to the new master key", count),
+        Err(error) => log::error!(
+            "Failed to move config secrets to the new master key: {}",
+            error
+        ),
+    }
+    match config::rewrap_config_backups(
+        &config_dir.join("config.json"),
+        LEGACY_MASTER_PASSWORD,
+        master_password,
+    ) {
+        Ok(count) => log::info!("Moved {} config backups to the new master key", count),
+        Err(error) => log::error!(
+            "Failed to move config backups to the new master key: {}",
+            error
+        ),
+    }
+
+    let mut credentials =
+        CredentialManager::new(config_dir.join("credentials.dat"), LEGACY_MASTER_PASSWORD);
+    match credentials.rekey(master_password).await {
+        Ok(count) => log::info!("Moved {} credentials to the new master key", count),
+        Err(error) => log::error!(
+            "Failed to move credentials to the new master key: {}",
+            error
+        ),
+    }
+
+    if let Err(error) =
+        SessionSyncService::new(config_dir, LEGACY_MASTER_PASSWORD).rekey(master_password)
+    {
+        log::error!("Failed to move the session sync passphrase: {}", error);
+    }
+    if let Err(error) =
+        SettingsSyncService::new(config_dir, LEGACY_MASTER_PASSWORD).rekey(master_password)
+    {
+        log::error!("Failed to move the settings sync snapshot: {}", error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret::Secret;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryStore {
+        secret: Mutex<Option<String>>,
+        unavailable: bool,
+    }
+
+    impl SecretStore for MemoryStore {
+        fn get(&self) -> Result<Option<String>, String> {
+            if self.unavailable {
+                return Err("no keychain".to_string());
+            }
+            Ok(self.secret.lock().unwrap().clone())
+        }
+
+        fn set(&self, secret: &str) -> Result<(), String> {
+            *self.secret.lock().unwrap() = Some(secret.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn master_key_is_created_once_and_then_read_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MemoryStore::default();
+        let created = load(None, &store, dir.path()).unwrap();
+        assert_eq!(created.source, MasterKeySource::Created);
+        assert_ne!(created.password, LEGACY_MASTER_PASSWORD);
+
+        let loaded = load(None, &store, dir.path()).unwrap();
+        assert_eq!(loaded.source, MasterKeySource::Keychain);
+        assert_eq!(loaded.password, created.password);
+
+        let env = load(Some("from-env".to_string()), &store, dir.path()).unwrap();
+        assert_eq!(env.source, MasterKeySource::Environment);
+        assert_eq!(env.password, "from-env");
+
+        let unavailable = MemoryStore {
+            unavailable: true,
+            ..MemoryStore::default()
+        };
+        assert!(load(None, &unavailable, dir.path()).is_err());
+        let fresh = tempfile::tempdir().unwrap();
+        assert_eq!(
+            load(None, &unavailable, fresh.path()).unwrap().source,
+            MasterKeySource::Legacy
+        );
+    }
+
+    #[tokio::test]
+    async fn legacy_secrets_move_to_the_new_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = config::AppConfig::default();
+        config.openai.api_key = config::encrypt_value("sk-secret", LEGACY_MASTER_PASSWORD)
+            .unwrap()
+            .unwrap()
+            .into();
+        std::fs::write(
+            dir.path().join("config.json"),
+            serde_json::to_string(&config).unwrap(),
+        )
+        .unwrap();
+        CredentialManager::new(dir.path().join("credentials.dat"), LEGACY_MASTER_PASSWORD)
+            .store("github", "token", "gh-secret")
+            .await
+            .unwrap();
+
+        migrate_legacy_data(dir.path(), "new-key").await;
+
+        let raw = std::fs::read_to_string(dir.path().join("config.json")).unwrap();
+        let mut migrated: config::AppConfig = serde_json::from_str(&raw).unwrap();
+        config::decrypt_sensitive_fields(&mut migrated, "new-key").unwrap();
+        assert_eq!(migrated.openai.api_key, "sk-secret");
+        let credentials = CredentialManager::new(dir.path().join("credentials.dat"), "new-key");
+        assert_eq!(
+            credentials.get("github", "token").await.unwrap(),
+            Some(Secret::from("gh-secret"))
+        );
+    }
+}
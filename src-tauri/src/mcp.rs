@@ -1,6 +1,6 @@
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use chrono::{DateTime, Utc};
 use once_cell::sync::Lazy;
@@ -8,18 +8,195 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Number, Value, json};
 use std::process::Stdio;
-use std::sync::Mutex as StdMutex;
+use std::sync::Arc;
 use tokio::fs;
 use tokio::process::Command;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, mpsc, oneshot};
 use uuid::Uuid;
 
-use crate::mcp_registry::McpRegistryStore;
-
-use rmcp::service::serve_client;
+use crate::credentials::{CredentialConsumer, CredentialManager, CredentialScope};
+use crate::json_schema;
+use crate::mcp_claude_config::{self, McpConfigExport, McpConfigImport};
+use crate::mcp_groups::{self, McpGroupActivation, McpGroupFailure, McpServerGroup};
+use crate::mcp_install_variables;
+use crate::mcp_oauth::{self, McpHttpClient, McpOAuthConfig};
+use crate::mcp_registry::{McpRegistryStore, RegistryStats};
+use crate::mcp_registry_source::{RegistryPage, RegistrySource, default_registry_sources};
+use crate::mcp_sampling::{McpClientHandler, SamplingCall};
+use crate::mcp_sandbox::{self, ProcessGroups, SandboxPolicy};
+use crate::mcp_tool_cache::{
+    self, ToolCache, ToolCacheBudget, ToolListChange, ToolResultCache, ToolResultKey,
+    ToolResultPolicy,
+};
+use crate::mcp_tool_calls::{self, McpToolCalls};
+use crate::mcp_updates::{self, UpdateTarget};
+use crate::proxy;
+use crate::rate_limit::RequestRateLimiter;
+
+use rmcp::model::{
+    CallToolRequestParam, CallToolResult, CancelledNotificationParam, ClientRequest, PingRequest,
+    Request, ServerResult,
+};
+use rmcp::service::{
+    Peer, PeerRequestOptions, RequestHandle, RoleClient, RunningService, serve_client,
+};
 use rmcp::transport::child_process::{ConfigureCommandExt, TokioChildProcess};
+use rmcp::transport::common::client_side_sse::{ExponentialBackoff, SseRetryPolicy};
+use rmcp::transport::streamable_http_client::StreamableHttpClientTransportConfig;
+use rmcp::transport::{
+    SseClientTransport, StreamableHttpClientTransport, sse_client::SseClientConfig,
+};
 
 const INVALID_PULSE_PACKAGES: &[&str] = &["bitcoin-mcp", "mcp-notes"];
+/// Cached registry servers that no sync has returned for this long are
+/// dropped, since the registry no longer lists them.
+const REGISTRY_STORE_MAX_AGE_SECS: i64 = 30 * 24 * 60 * 60;
+const REGISTRY_REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+/// Most pages a search walks on a registry that pages by cursor.
+const MAX_REGISTRY_CURSOR_PAGES: usize = 5;
+/// Tool results kept across all servers that opt into result caching.
+const MAX_TOOL_RESULT_CACHE_ENTRIES: usize = 256;
+/// Working directories of custom servers without a `config.cwd`, next to
+/// `mcp-servers.json`.
+const SANDBOX_DIR: &str = "mcp-sandbox";
+/// Written once servers configured before [`SANDBOX_DIR`] existed have been
+/// pinned to the directory they used to run in.
+const LEGACY_CWD_MARKER: &str = "mcp-servers.cwd-pinned";
+/// How long a rate-limited tool call queues when the server's config does
+/// not set `rateLimit.maxWaitSeconds`.
+const DEFAULT_RATE_LIMIT_MAX_WAIT_SECS: u64 = 30;
+/// Marks an env or header value that is entirely a credential reference.
+const CREDENTIAL_VALUE_PREFIX: &str = "credential:";
+/// How many times a remote server's dropped event stream is reopened.
+const REMOTE_RECONNECT_MAX_RETRIES: u64 = 5;
+const REMOTE_RECONNECT_BASE_DELAY_MS: u64 = 500;
+/// How long a remote server gets to accept a connection, and how long an
+/// open response may go without data. An idle event stream that hits the
+/// read timeout is reopened by the retry policy.
+const REMOTE_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const REMOTE_READ_TIMEOUT: Duration = Duration::from_secs(300);
+/// How long closing a session may take. The transport kills a custom
+/// server that is still running after three seconds.
+const SESSION_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Server types that run as an MCP client session, as opposed to the
+/// built-in filesystem server.
+const CLIENT_SERVER_TYPES: &[&str] = &["custom", "http", "streamable-http", "sse"];
+
+type McpClient = RunningService<RoleClient, McpClientHandler>;
+
+/// Connected servers: their tool lists, bounded by the tool cache budget,
+/// and the live client sessions tool calls go through. A session stays
+/// open until its server is disconnected or the app exits, even when its
+/// tool list is evicted.
+struct McpConnections {
+    tools: ToolCache,
+    clients: HashMap<String, McpClient>,
+    /// Servers whose session ended without a disconnect, not yet picked up
+    /// for reconnecting.
+    dropped: HashSet<String>,
+    /// Servers being reconnected. A disconnect removes the server, which
+    /// stops its reconnect attempts.
+    reconnecting: HashSet<String>,
+    health: HashMap<String, McpServerHealth>,
+    results: ToolResultCache<McpToolCallResult>,
+    /// Tool list changes not yet reported to the app.
+    tool_changes: Vec<ToolListChange>,
+}
+
+impl McpConnections {
+    fn new(budget: ToolCacheBudget) -> Self {
+        Self {
+            tools: ToolCache::new(budget),
+            clients: HashMap::new(),
+            dropped: HashSet::new(),
+            reconnecting: HashSet::new(),
+            health: HashMap::new(),
+            results: ToolResultCache::new(MAX_TOOL_RESULT_CACHE_ENTRIES),
+            tool_changes: Vec::new(),
+        }
+    }
+
+    /// Forgets a session whose transport closed so its tools are no longer
+    /// served, and queues the server for reconnecting.
+    fn mark_dropped(&mut self, server_id: &str) {
+        log::warn!("MCP session for {} closed unexpectedly", server_id);
+        if let Some(client) = self.clients.remove(server_id) {
+            client.service().kill_process_group();
+        }
+        self.tools.remove(server_id);
+        self.dropped.insert(server_id.to_string());
+    }
+}
+
+/// Consecutive failed pings after which a session is treated as dropped.
+const MCP_HEALTH_FAILURE_LIMIT: u32 = 3;
+const MCP_PING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Heartbeat results for a connected server.
+#[derive(Clone, Debug, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpServerHealth {
+    pub server_id: String,
+    pub healthy: bool,
+    /// Round trip of the last successful ping.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checked_at: Option<String>,
+    pub checks: u64,
+    pub failures: u64,
+    pub consecutive_failures: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// How many times a dropped session is reconnected before giving up.
+pub const MCP_RECONNECT_MAX_ATTEMPTS: u32 = 6;
+const MCP_RECONNECT_MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// The wait after failed reconnect attempt `attempt`, counting from 1: one
+/// second, doubling each time up to a minute.
+pub fn mcp_reconnect_delay(attempt: u32) -> Duration {
+    Duration::from_secs(1)
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(MCP_RECONNECT_MAX_DELAY)
+}
+
+/// Checks call arguments against the tool's `inputSchema`, when it has one.
+fn check_tool_arguments(tool: &Value, arguments: &Map<String, Value>) -> Result<(), String> {
+    let Some(schema) = tool.get("inputSchema") else {
+        return Ok(());
+    };
+    let problems = json_schema::validate(schema, &Value::Object(arguments.clone()));
+    if problems.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "Invalid arguments for {}: {}",
+        tool.get("name").and_then(Value::as_str).unwrap_or("tool"),
+        problems.join("; ")
+    ))
+}
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum McpServerStatus {
+    Connecting,
+    Connected,
+    Failed,
+}
+
+/// Payload of the `mcp_server_status` event.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpServerStatusEvent {
+    pub server_id: String,
+    pub status: McpServerStatus,
+    pub attempt: u32,
+    /// Why the previous attempt failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct McpConnectionResult {
@@ -28,6 +205,22 @@ pub struct McpConnectionResult {
     pub error: Option<String>,
 }
 
+/// The outcome of a tool call. `is_error` reports a failure inside the tool;
+/// failures to reach it are returned as errors instead.
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpToolCallResult {
+    pub server_id: String,
+    pub tool_name: String,
+    pub content: Vec<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<Value>,
+    pub is_error: bool,
+    pub duration_ms: u64,
+    /// Whether the result was reused from the server's result cache.
+    pub cached: bool,
+}
+
 #[derive(Clone, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct McpRegistrySearchResult {
@@ -41,18 +234,6 @@ pub struct McpRegistrySearchResult {
     pub metric_freshness: HashMap<String, Value>,
 }
 
-#[derive(Default)]
-struct RegistryStats {
-    total_searches: usize,
-    cache_hits: usize,
-    cache_entries: usize,
-    total_response_time_ms: u128,
-    installations: usize,
-    oldest_entry: Option<DateTime<Utc>>,
-    newest_entry: Option<DateTime<Utc>>,
-    last_background_sync: Option<DateTime<Utc>>,
-}
-
 #[derive(Clone)]
 struct RemoteCatalogPayload {
     servers: Vec<Value>,
@@ -87,17 +268,6 @@ struct RegistrySearchOptions {
     offset: Option<usize>,
 }
 
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
-struct PulseQuery<'a> {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    query: Option<&'a str>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    offset: Option<usize>,
-    #[serde(rename = "count_per_page")]
-    count_per_page: usize,
-}
-
 static REGISTRY_CATALOG: Lazy<RegistryCatalog> = Lazy::new(|| {
     serde_json::from_str(include_str!(
         "../../src/renderer/data/popularMCPServers.json"
@@ -105,7 +275,7 @@ static REGISTRY_CATALOG: Lazy<RegistryCatalog> = Lazy::new(|| {
     .expect("failed to load registry catalog")
 });
 
-pub(crate) static REMOTE_ENV_GUARD: Lazy<StdMutex<()>> = Lazy::new(|| StdMutex::new(()));
+pub(crate) static REMOTE_ENV_GUARD: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
 
 pub(crate) fn remote_registry_enabled() -> bool {
     match std::env::var("TAURI_MCP_REGISTRY_REMOTE") {
@@ -127,14 +297,236 @@ pub(crate) fn remote_registry_enabled() -> bool {
 pub struct McpService {
     path: PathBuf,
     cache: Mutex<Vec<Value>>,
-    connections: Mutex<HashMap<String, Vec<Value>>>,
+    connections: Arc<Mutex<McpConnections>>,
     registry_stats: Mutex<RegistryStats>,
-    remote_cache: Mutex<RemoteCatalogCache>,
+    /// Keyed by registry source name.
+    remote_cache: Mutex<HashMap<String, RemoteCatalogCache>>,
+    registry_sources: Vec<Arc<dyn RegistrySource>>,
     registry_store: Option<McpRegistryStore>,
+    credentials: Option<CredentialManager>,
+    rate_limiter: RequestRateLimiter,
+    sampling: Option<mpsc::UnboundedSender<SamplingCall>>,
+    tool_list_changed: Option<mpsc::UnboundedSender<String>>,
+    tool_calls: Arc<McpToolCalls>,
+    process_groups: Arc<ProcessGroups>,
+}
+
+/// What `McpService::prepare_tool_call` hands back: a result still in the
+/// cache, or a call to send.
+pub enum PreparedToolCall {
+    Cached(McpToolCallResult),
+    Pending(PendingToolCall),
+}
+
+impl PreparedToolCall {
+    pub async fn run(self) -> Result<McpToolCallResult, String> {
+        match self {
+            Self::Cached(result) => Ok(result),
+            Self::Pending(call) => call.run().await,
+        }
+    }
+}
+
+/// Open sessions to ping, taken from the service so the pings run without
+/// holding it.
+pub struct PendingHealthCheck {
+    peers: Vec<(String, Peer<RoleClient>)>,
+    connections: Arc<Mutex<McpConnections>>,
+}
+
+impl PendingHealthCheck {
+    /// Pings every session concurrently and records the outcome. Returns the
+    /// servers whose health changed; a server that misses
+    /// `MCP_HEALTH_FAILURE_LIMIT` pings in a row is treated as dropped and
+    /// reconnected.
+    pub async fn run(self) -> Vec<McpServerHealth> {
+        let pings: Vec<_> = self
+            .peers
+            .into_iter()
+            .map(|(server_id, peer)| {
+                let ping = tokio::spawn(async move {
+                    let started = Instant::now();
+                    match tokio::time::timeout(
+                        MCP_PING_TIMEOUT,
+                        peer.send_request(ClientRequest::PingRequest(PingRequest::default())),
+                    )
+                    .await
+                    {
+                        Ok(Ok(_)) => Ok(started.elapsed().as_millis() as u64),
+                        Ok(Err(error)) => Err(error.to_string()),
+                        Err(_) => Err(format!(
+                            "No ping reply within {} seconds",
+                            MCP_PING_TIMEOUT.as_secs()
+                        )),
+                    }
+                });
+                (server_id, ping)
+            })
+            .collect();
+
+        let mut changed = Vec::new();
+        for (server_id, ping) in pings {
+            let outcome = ping
+                .await
+                .unwrap_or_else(|error| Err(format!("Ping task failed: {error}")));
+
+            let mut connections = self.connections.lock().await;
+            if !connections.clients.contains_key(&server_id) {
+                continue;
+            }
+            let health = connections
+                .health
+                .entry(server_id.clone())
+                .or_insert_with(|| McpServerHealth {
+                    server_id: server_id.clone(),
+                    healthy: true,
+                    ..McpServerHealth::default()
+                });
+            let was_healthy = health.healthy;
+            health.checks += 1;
+            health.checked_at = Some(Utc::now().to_rfc3339());
+            match outcome {
+                Ok(latency_ms) => {
+                    health.healthy = true;
+                    health.latency_ms = Some(latency_ms);
+                    health.consecutive_failures = 0;
+                    health.last_error = None;
+                }
+                Err(error) => {
+                    log::debug!("MCP ping to {} failed: {}", server_id, error);
+                    health.healthy = false;
+                    health.failures += 1;
+                    health.consecutive_failures += 1;
+                    health.last_error = Some(error);
+                }
+            }
+            let exhausted = health.consecutive_failures >= MCP_HEALTH_FAILURE_LIMIT;
+            if health.healthy != was_healthy {
+                changed.push(health.clone());
+            }
+            if exhausted {
+                connections.mark_dropped(&server_id);
+            }
+        }
+        changed
+    }
+}
+
+/// A checked tool call with its session and rate-limit slot.
+pub struct PendingToolCall {
+    peer: Peer<RoleClient>,
+    server_id: String,
+    tool_name: String,
+    arguments: Map<String, Value>,
+    call_id: String,
+    /// How long to queue for the reserved rate-limit slot.
+    wait: Duration,
+    cache: Option<(ToolResultKey, Duration)>,
+    connections: Arc<Mutex<McpConnections>>,
+    tool_calls: Arc<McpToolCalls>,
+}
+
+impl PendingToolCall {
+    pub async fn run(self) -> Result<McpToolCallResult, String> {
+        if !self.wait.is_zero() {
+            log::info!(
+                "Queueing {} on {} for {}ms to stay under its rate limit",
+                self.tool_name,
+                self.server_id,
+                self.wait.as_millis()
+            );
+            tokio::time::sleep(self.wait).await;
+        }
+
+        let cancelled = self
+            .tool_calls
+            .start(&self.call_id, &self.server_id, &self.tool_name)?;
+        let started = Instant::now();
+        let result = Self::send(
+            &self.peer,
+            &self.call_id,
+            &self.tool_name,
+            self.arguments,
+            cancelled,
+        )
+        .await;
+        self.tool_calls.finish(&self.call_id);
+        let result = result?;
+        let content = result
+            .content
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|error| format!("Failed to serialize MCP tool result: {error}"))?;
+        let result = McpToolCallResult {
+            server_id: self.server_id,
+            tool_name: self.tool_name,
+            content,
+            structured_content: result.structured_content,
+            is_error: result.is_error.unwrap_or(false),
+            duration_ms: started.elapsed().as_millis() as u64,
+            cached: false,
+        };
+        if let Some((key, ttl)) = self.cache
+            && !result.is_error
+        {
+            self.connections
+                .lock()
+                .await
+                .results
+                .insert(key, result.clone(), ttl, Instant::now());
+        }
+        Ok(result)
+    }
+
+    /// Sends a `tools/call` request and waits for its result, or tells the
+    /// server to stop when the call is cancelled first.
+    async fn send(
+        peer: &Peer<RoleClient>,
+        call_id: &str,
+        tool_name: &str,
+        arguments: Map<String, Value>,
+        cancelled: oneshot::Receiver<()>,
+    ) -> Result<CallToolResult, String> {
+        let RequestHandle { rx, id, .. } = peer
+            .send_cancellable_request(
+                ClientRequest::CallToolRequest(Request::new(CallToolRequestParam {
+                    name: tool_name.to_string().into(),
+                    arguments: Some(arguments),
+                })),
+                PeerRequestOptions {
+                    meta: Some(mcp_tool_calls::progress_meta(call_id)),
+                    ..PeerRequestOptions::default()
+                },
+            )
+            .await
+            .map_err(|error| format!("MCP tool call failed: {error}"))?;
+
+        tokio::select! {
+            response = rx => match response {
+                Ok(Ok(ServerResult::CallToolResult(result))) => Ok(result),
+                Ok(Ok(_)) => Err("MCP tool call failed: unexpected response".to_string()),
+                Ok(Err(error)) => Err(format!("MCP tool call failed: {error}")),
+                Err(_) => Err("MCP tool call failed: the session closed".to_string()),
+            },
+            _ = cancelled => {
+                if let Err(error) = peer
+                    .notify_cancelled(CancelledNotificationParam {
+                        request_id: id,
+                        reason: Some("Cancelled by the user".to_string()),
+                    })
+                    .await
+                {
+                    log::debug!("Failed to send MCP cancellation for {}: {}", call_id, error);
+                }
+                Err("MCP tool call was cancelled".to_string())
+            }
+        }
+    }
 }
 
 impl McpService {
-    fn normalize_github_url(input: &str) -> Option<String> {
+    pub(crate) fn normalize_github_url(input: &str) -> Option<String> {
         let trimmed = input.trim();
         if trimmed.is_empty() {
             return None;
@@ -236,7 +628,7 @@ impl McpService {
             .map(|segment| segment.to_string())
     }
 
-    fn build_template_payload(
+    pub(crate) fn build_template_payload(
         command: String,
         args: Vec<String>,
         env: Option<Map<String, Value>>,
@@ -484,7 +876,10 @@ impl McpService {
         Some(Value::Object(normalized))
     }
 
-    fn normalize_pulse_payload(raw: Value, now: DateTime<Utc>) -> (Vec<Value>, usize, bool) {
+    pub(crate) fn normalize_pulse_payload(
+        raw: Value,
+        now: DateTime<Utc>,
+    ) -> (Vec<Value>, usize, bool) {
         let servers = raw
             .get("servers")
             .and_then(Value::as_array)
@@ -521,6 +916,21 @@ impl McpService {
                 log::warn!("Failed to persist default MCP servers: {}", error);
             }
         }
+        let legacy_cwd_marker = default_root.join(LEGACY_CWD_MARKER);
+        if !legacy_cwd_marker.exists() {
+            if let Ok(cwd) = std::env::current_dir()
+                && mcp_sandbox::keep_legacy_cwd(&mut initial, &cwd)
+                && let Err(error) = Self::write_to_disk_sync(&path, &initial)
+            {
+                log::warn!(
+                    "Failed to keep MCP servers in their old directory: {}",
+                    error
+                );
+            }
+            if let Err(error) = std::fs::write(&legacy_cwd_marker, "") {
+                log::warn!("Failed to mark MCP servers as sandboxed: {}", error);
+            }
+        }
 
         let registry_store =
             registry_db_path.and_then(|db_path| match McpRegistryStore::new(db_path) {
@@ -531,14 +941,208 @@ impl McpService {
                 }
             });
 
+        let registry_stats = registry_store
+            .as_ref()
+            .and_then(|store| match store.registry_stats() {
+                Ok(stats) => Some(stats),
+                Err(error) => {
+                    log::warn!("Failed to load MCP registry stats: {}", error);
+                    None
+                }
+            })
+            .unwrap_or_default();
+
         Self {
             path,
             cache: Mutex::new(initial),
-            connections: Mutex::new(HashMap::new()),
-            registry_stats: Mutex::new(RegistryStats::default()),
-            remote_cache: Mutex::new(RemoteCatalogCache::default()),
+            connections: Arc::new(Mutex::new(McpConnections::new(ToolCacheBudget::default()))),
+            registry_stats: Mutex::new(registry_stats),
+            remote_cache: Mutex::new(HashMap::new()),
+            registry_sources: default_registry_sources(),
             registry_store,
+            credentials: None,
+            rate_limiter: RequestRateLimiter::new(),
+            sampling: None,
+            tool_list_changed: None,
+            tool_calls: Arc::new(McpToolCalls::default()),
+            process_groups: Arc::new(ProcessGroups::default()),
+        }
+    }
+
+    /// Lets server `env` and `headers` values reference `mcp`-scoped
+    /// credentials, either embedded as `${credential:<service>/<account>}` or
+    /// as a whole value written `credential:<service>/<account>`. Secrets
+    /// saved in plain text are moved into the credential store.
+    pub fn with_credentials(mut self, credentials: CredentialManager) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Advertises sampling to servers and forwards their
+    /// `sampling/createMessage` requests to `sampling`.
+    pub fn with_sampling(mut self, sampling: mpsc::UnboundedSender<SamplingCall>) -> Self {
+        self.sampling = Some(sampling);
+        self
+    }
+
+    /// Sends the id of each server that reports `tools/list_changed`.
+    pub fn with_tool_list_changes(mut self, changed: mpsc::UnboundedSender<String>) -> Self {
+        self.tool_list_changed = Some(changed);
+        self
+    }
+
+    /// Tracks running tool calls in `tool_calls`, which the app shares to
+    /// cancel calls and receive their progress.
+    pub fn with_tool_calls(mut self, tool_calls: Arc<McpToolCalls>) -> Self {
+        self.tool_calls = tool_calls;
+        self
+    }
+
+    /// Records custom servers' process groups in `process_groups`, which the
+    /// app shares to kill them on exit.
+    pub fn with_process_groups(mut self, process_groups: Arc<ProcessGroups>) -> Self {
+        self.process_groups = process_groups;
+        self
+    }
+
+    fn client_handler(&self, server: &Value) -> McpClientHandler {
+        let server_id = Self::resolve_id(server).unwrap_or_default();
+        McpClientHandler::new(&server_id, self.sampling.clone())
+            .with_tool_list_changes(self.tool_list_changed.clone())
+            .with_tool_calls(self.tool_calls.clone())
+    }
+
+    /// Replaces the remote catalogs registry searches draw from, such as
+    /// with test fixtures.
+    #[cfg(test)]
+    pub fn with_registry_sources(mut self, sources: Vec<Arc<dyn RegistrySource>>) -> Self {
+        self.registry_sources = sources;
+        self
+    }
+
+    async fn resolve_env_value(&self, value: &str) -> Result<String, String> {
+        let value = match value.trim().strip_prefix(CREDENTIAL_VALUE_PREFIX) {
+            Some(reference) if reference.contains('/') && !reference.contains('}') => {
+                format!("${{credential:{reference}}}")
+            }
+            _ => value.to_string(),
+        };
+        if !value.contains("${credential:") {
+            return Ok(value);
         }
+        let credentials = self
+            .credentials
+            .as_ref()
+            .ok_or_else(|| "Credential references are not available".to_string())?;
+        credentials
+            .scoped(CredentialConsumer::Mcp)
+            .resolve(&value)
+            .await
+            .map_err(|error| format!("Failed to resolve MCP env: {error}"))
+    }
+
+    /// Resolves `${credential:...}` references embedded in an arg or URL,
+    /// which install variables put there for secret values.
+    async fn resolve_embedded_credentials(&self, text: &str) -> Result<String, String> {
+        if text.contains("${credential:") {
+            self.resolve_env_value(text).await
+        } else {
+            Ok(text.to_string())
+        }
+    }
+
+    /// Returns `server` with credential references in its `config.env`,
+    /// `config.headers`, `config.args` and `config.url` replaced, for
+    /// handing to a process that spawns the server itself.
+    pub async fn resolve_server_env(&self, server: &Value) -> Result<Value, String> {
+        let mut server = server.clone();
+        if let Some(config) = server.get_mut("config") {
+            if let Some(args) = config.get_mut("args").and_then(Value::as_array_mut) {
+                for arg in args.iter_mut() {
+                    if let Some(text) = arg.as_str() {
+                        *arg = Value::String(self.resolve_embedded_credentials(text).await?);
+                    }
+                }
+            }
+            if let Some(url) = config.get_mut("url")
+                && let Some(text) = url.as_str()
+            {
+                *url = Value::String(self.resolve_embedded_credentials(text).await?);
+            }
+        }
+        for field in ["env", "headers"] {
+            let Some(values) = server
+                .get_mut("config")
+                .and_then(|config| config.get_mut(field))
+                .and_then(Value::as_object_mut)
+            else {
+                continue;
+            };
+            for value in values.values_mut() {
+                if let Some(text) = value.as_str() {
+                    *value = Value::String(self.resolve_env_value(text).await?);
+                }
+            }
+        }
+        Ok(server)
+    }
+
+    /// Whether an env variable or header probably holds a secret.
+    fn is_secret_name(name: &str) -> bool {
+        let name = name.to_ascii_uppercase();
+        ["KEY", "TOKEN", "SECRET", "PASSWORD", "AUTHORIZATION"]
+            .iter()
+            .any(|marker| name.contains(marker))
+    }
+
+    /// The credential service a server's secrets are stored under.
+    fn credential_service(server_id: &str) -> String {
+        format!("mcp-{}", server_id.replace('/', "-"))
+    }
+
+    /// Stores plain-text secrets in `config.env` and `config.headers` as
+    /// `mcp` credentials named `mcp-<server id>/<env|headers>/<name>` and
+    /// leaves a reference in their place, so they never reach
+    /// `mcp-servers.json`.
+    async fn seal_server_secrets(&self, servers: &mut [Value]) -> Result<(), String> {
+        let Some(credentials) = self.credentials.as_ref() else {
+            return Ok(());
+        };
+        for server in servers.iter_mut() {
+            let Some(server_id) = Self::resolve_id(server) else {
+                continue;
+            };
+            let service = Self::credential_service(&server_id);
+            for field in ["env", "headers"] {
+                let Some(values) = server
+                    .get_mut("config")
+                    .and_then(|config| config.get_mut(field))
+                    .and_then(Value::as_object_mut)
+                else {
+                    continue;
+                };
+                for (name, value) in values.iter_mut() {
+                    let Some(text) = value.as_str() else {
+                        continue;
+                    };
+                    if text.is_empty()
+                        || !Self::is_secret_name(name)
+                        || text.contains("${credential:")
+                        || text.trim().starts_with(CREDENTIAL_VALUE_PREFIX)
+                        || name.contains('}')
+                    {
+                        continue;
+                    }
+                    let account = format!("{field}/{name}");
+                    credentials
+                        .store_with_scope(&service, &account, text, Some(CredentialScope::Mcp))
+                        .await
+                        .map_err(|error| format!("Failed to store MCP secret {name}: {error}"))?;
+                    *value = Value::String(format!("{CREDENTIAL_VALUE_PREFIX}{service}/{account}"));
+                }
+            }
+        }
+        Ok(())
     }
 
     fn filter_servers_with_source(
@@ -600,7 +1204,9 @@ impl McpService {
         Ok(cache.clone())
     }
 
-    pub async fn save(&self, servers: Vec<Value>) -> Result<(), String> {
+    pub async fn save(&self, mut servers: Vec<Value>) -> Result<(), String> {
+        self.seal_server_secrets(&mut servers).await?;
+
         let parent = self
             .path
             .parent()
@@ -620,7 +1226,34 @@ impl McpService {
             .map_err(|error| format!("Failed to write MCP servers: {error}"))?;
 
         let mut cache = self.cache.lock().await;
+        let kept: HashSet<String> = servers.iter().filter_map(Self::resolve_id).collect();
+        let removed: Vec<String> = cache
+            .iter()
+            .filter_map(Self::resolve_id)
+            .filter(|id| !kept.contains(id))
+            .collect();
+        for removed in &removed {
+            self.rate_limiter.clear(removed);
+            if let Some(store) = self.registry_store.as_ref()
+                && let Err(error) = store.remove_server_tools(removed)
+            {
+                log::debug!("Failed to drop cached tools for {}: {}", removed, error);
+            }
+        }
         *cache = servers;
+        drop(cache);
+
+        if let Some(credentials) = self.credentials.as_ref() {
+            for removed in removed {
+                if let Err(error) = credentials.clear(&Self::credential_service(&removed)).await {
+                    log::warn!(
+                        "Failed to remove secrets of MCP server {}: {}",
+                        removed,
+                        error
+                    );
+                }
+            }
+        }
         Ok(())
     }
 
@@ -629,7 +1262,10 @@ impl McpService {
 
         match server_type.as_str() {
             "filesystem" => self.test_filesystem_connection(server).await,
-            "custom" => self.test_custom_connection(server).await,
+            kind if CLIENT_SERVER_TYPES.contains(&kind) => {
+                let client = self.start_client(server, kind).await?;
+                Self::list_tools_and_close(client).await
+            }
             unsupported => Ok(McpConnectionResult {
                 success: false,
                 tools: Vec::new(),
@@ -638,7 +1274,27 @@ impl McpService {
         }
     }
 
-    async fn test_custom_connection(&self, server: &Value) -> Result<McpConnectionResult, String> {
+    async fn start_client(&self, server: &Value, server_type: &str) -> Result<McpClient, String> {
+        let pending = mcp_install_variables::pending(server);
+        if !pending.is_empty() {
+            let names: Vec<_> = pending
+                .iter()
+                .map(|variable| variable.name.as_str())
+                .collect();
+            return Err(format!(
+                "MCP server needs install variables: {}",
+                names.join(", ")
+            ));
+        }
+        match server_type {
+            "custom" => self.start_custom_client(server).await,
+            "http" | "streamable-http" => self.start_streamable_http_client(server).await,
+            "sse" => self.start_sse_client(server).await,
+            unsupported => Err(format!("Unsupported MCP server type: {unsupported}")),
+        }
+    }
+
+    async fn start_custom_client(&self, server: &Value) -> Result<McpClient, String> {
         let config = server
             .get("config")
             .and_then(Value::as_object)
@@ -662,11 +1318,6 @@ impl McpService {
             })
             .unwrap_or_default();
 
-        let cwd = config
-            .get("cwd")
-            .and_then(Value::as_str)
-            .map(str::to_string);
-
         let env_pairs: Vec<(String, String)> = config
             .get("env")
             .and_then(Value::as_object)
@@ -689,89 +1340,1238 @@ impl McpService {
             })
             .unwrap_or_default();
 
-        let args_for_spawn = args.clone();
-        let env_for_spawn = env_pairs.clone();
-        let cwd_for_spawn = cwd.clone();
+        let servers_dir = self
+            .path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(SANDBOX_DIR);
+        let sandbox = SandboxPolicy::from_server(server, &servers_dir);
+        if sandbox.working_dir.starts_with(&servers_dir) {
+            fs::create_dir_all(&sandbox.working_dir)
+                .await
+                .map_err(|error| format!("Failed to create MCP server directory: {error}"))?;
+        }
+        let (command, args) = sandbox.command(&command, &args)?;
+        #[cfg(target_os = "linux")]
+        let seccomp = if sandbox.os {
+            Some(crate::mcp_sandbox::seccomp_filter(sandbox.allow_network)?)
+        } else {
+            None
+        };
+
+        let mut env_for_spawn = sandbox.passed_env(std::env::vars());
+        // The configured proxy lives in the app, not its environment, so
+        // the scrub above would leave the server going direct.
+        env_for_spawn.extend(
+            proxy::env_vars()
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value)),
+        );
+        for (key, value) in env_pairs {
+            let value = self.resolve_env_value(&value).await?;
+            env_for_spawn.push((key, value));
+        }
+        let mut args_for_spawn = Vec::with_capacity(args.len());
+        for arg in &args {
+            args_for_spawn.push(self.resolve_embedded_credentials(arg).await?);
+        }
 
-        let process = TokioChildProcess::new(Command::new(&command).configure(move |cmd| {
+        let command = Command::new(&command).configure(move |cmd| {
             cmd.stdin(Stdio::piped());
             cmd.stdout(Stdio::piped());
             cmd.stderr(Stdio::inherit());
             for arg in &args_for_spawn {
                 cmd.arg(arg);
             }
-            if let Some(ref dir) = cwd_for_spawn {
-                cmd.current_dir(dir);
+            cmd.current_dir(&sandbox.working_dir);
+            if !sandbox.inherit_env {
+                cmd.env_clear();
+            }
+            #[cfg(target_os = "linux")]
+            if let Some(filter) = seccomp {
+                // SAFETY: the hook only makes the prctl calls of
+                // `restrict_current_process`, which are async-signal-safe.
+                unsafe {
+                    cmd.pre_exec(move || crate::mcp_sandbox::restrict_current_process(&filter));
+                }
             }
             for (key, value) in &env_for_spawn {
                 cmd.env(key, value);
             }
-        }))
-        .map_err(|error| format!("Failed to spawn MCP server: {error}"))?;
+        });
+        let process = TokioChildProcess::new(mcp_sandbox::with_process_tree(command))
+            .map_err(|error| format!("Failed to spawn MCP server: {error}"))?;
+        let handler = self
+            .client_handler(server)
+            .with_process_group(&self.process_groups, process.id());
+
+        serve_client(handler, process)
+            .await
+            .map_err(|error| format!("Failed to initialize MCP server: {error}"))
+    }
+
+    /// Connects to a hosted server over the streamable HTTP transport, where
+    /// `config.url` is the single MCP endpoint. The session id the server
+    /// assigns is sent on every later request, dropped streams are resumed
+    /// from the last event id, and the session is deleted on close. Set
+    /// `config.requireSession` to reject servers that run stateless.
+    async fn start_streamable_http_client(&self, server: &Value) -> Result<McpClient, String> {
+        let (url, http_client) = self.remote_http_client(server).await?;
+        let require_session = server
+            .pointer("/config/requireSession")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let transport = StreamableHttpClientTransport::with_client(
+            http_client,
+            StreamableHttpClientTransportConfig {
+                uri: url.into(),
+                retry_config: Self::remote_retry_policy(server),
+                allow_stateless: !require_session,
+                ..Default::default()
+            },
+        );
 
-        let client = serve_client((), process)
+        serve_client(self.client_handler(server), transport)
             .await
-            .map_err(|error| format!("Failed to initialize MCP server: {error}"))?;
+            .map_err(|error| format!("Failed to initialize MCP server: {error}"))
+    }
+
+    /// Connects to a hosted server over the SSE transport: `config.url` is
+    /// the SSE endpoint and `config.headers` are sent with every request.
+    async fn start_sse_client(&self, server: &Value) -> Result<McpClient, String> {
+        let (url, http_client) = self.remote_http_client(server).await?;
+        let transport = SseClientTransport::start_with_client(
+            http_client,
+            SseClientConfig {
+                sse_endpoint: url.into(),
+                retry_policy: Self::remote_retry_policy(server),
+                ..Default::default()
+            },
+        )
+        .await
+        .map_err(|error| format!("Failed to connect to MCP server: {error}"))?;
 
-        let tools = client
-            .peer()
-            .list_all_tools()
+        serve_client(self.client_handler(server), transport)
             .await
-            .map_err(|error| format!("Failed to list MCP tools: {error}"))?;
+            .map_err(|error| format!("Failed to initialize MCP server: {error}"))
+    }
+
+    /// How often a dropped event stream is reopened, from
+    /// `config.reconnect.maxRetries` and `config.reconnect.baseDelayMs`. The
+    /// delay doubles after each attempt.
+    fn remote_retry_policy(server: &Value) -> Arc<dyn SseRetryPolicy> {
+        let reconnect = server.pointer("/config/reconnect");
+        let max_retries = reconnect
+            .and_then(|reconnect| reconnect.get("maxRetries"))
+            .and_then(Value::as_u64)
+            .unwrap_or(REMOTE_RECONNECT_MAX_RETRIES);
+        let base_delay_ms = reconnect
+            .and_then(|reconnect| reconnect.get("baseDelayMs"))
+            .and_then(Value::as_u64)
+            .unwrap_or(REMOTE_RECONNECT_BASE_DELAY_MS);
+        Arc::new(ExponentialBackoff {
+            max_times: Some(max_retries as usize),
+            base_duration: std::time::Duration::from_millis(base_delay_ms),
+        })
+    }
 
-        let tools = match tools
+    /// The URL of a remote server and an HTTP client carrying its headers.
+    /// Header values may reference stored credentials, as env values do, and
+    /// servers with `config.auth.type` of `oauth` get the signed-in bearer
+    /// token, looked up again for every request.
+    async fn remote_http_client(&self, server: &Value) -> Result<(String, McpHttpClient), String> {
+        let config = server
+            .get("config")
+            .and_then(Value::as_object)
+            .ok_or_else(|| "Remote server missing config".to_string())?;
+        let url = config
+            .get("url")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+            .ok_or_else(|| "Remote server needs an http(s) config.url".to_string())?;
+        let url = self.resolve_embedded_credentials(url).await?;
+
+        let mut headers = reqwest::header::HeaderMap::new();
+        for (name, value) in config
+            .get("headers")
+            .and_then(Value::as_object)
             .into_iter()
-            .map(|tool| serde_json::to_value(tool))
-            .collect::<Result<Vec<Value>, _>>()
+            .flatten()
         {
-            Ok(values) => values,
-            Err(error) => {
-                let _ = client.cancel().await;
-                return Err(format!("Failed to serialize MCP tool: {error}"));
-            }
-        };
+            let Some(value) = value.as_str() else {
+                continue;
+            };
+            let value = self.resolve_env_value(value).await?;
+            let name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|error| format!("Invalid header name {name}: {error}"))?;
+            let mut value = reqwest::header::HeaderValue::from_str(&value)
+                .map_err(|error| format!("Invalid value for header {name}: {error}"))?;
+            value.set_sensitive(true);
+            headers.insert(name, value);
+        }
 
-        match client.cancel().await {
-            Ok(reason) => log::debug!("MCP custom server exited: {:?}", reason),
-            Err(error) => log::debug!("Failed to shutdown MCP custom server: {error}"),
+        let http = proxy::client_builder()
+            .user_agent("hol-desktop-tauri/0.0.1")
+            .default_headers(headers)
+            .connect_timeout(REMOTE_CONNECT_TIMEOUT)
+            .read_timeout(REMOTE_READ_TIMEOUT)
+            .build()
+            .map_err(|error| error.to_string())?;
+        let mut client = McpHttpClient::new(http);
+
+        if config
+            .get("auth")
+            .and_then(|auth| auth.get("type"))
+            .and_then(Value::as_str)
+            == Some("oauth")
+        {
+            let server_id = server.get("id").and_then(Value::as_str).unwrap_or_default();
+            let credentials = self
+                .credentials
+                .as_ref()
+                .ok_or_else(|| "Credential storage is unavailable for OAuth".to_string())?;
+            let token_client = proxy::client()?;
+            mcp_oauth::access_token(&token_client, credentials, server_id)
+                .await?
+                .ok_or_else(|| format!("Sign in to MCP server {server_id} to connect"))?;
+            client = client.with_oauth(token_client, credentials.clone(), server_id);
         }
+        Ok((url, client))
+    }
 
+    async fn list_tools_and_close(client: McpClient) -> Result<McpConnectionResult, String> {
+        let tools = Self::list_tools(client.peer()).await;
+        Self::close_client(client).await;
         Ok(McpConnectionResult {
             success: true,
-            tools,
+            tools: tools?,
             error: None,
         })
     }
 
+    async fn list_tools(peer: &Peer<RoleClient>) -> Result<Vec<Value>, String> {
+        peer.list_all_tools()
+            .await
+            .map_err(|error| format!("Failed to list MCP tools: {error}"))?
+            .into_iter()
+            .map(|tool| {
+                serde_json::to_value(tool)
+                    .map_err(|error| format!("Failed to serialize MCP tool: {error}"))
+            })
+            .collect()
+    }
+
+    async fn close_client(client: McpClient) {
+        let handler = client.service().clone();
+        match client.cancel().await {
+            Ok(reason) => log::debug!("MCP server session ended: {:?}", reason),
+            Err(error) => log::debug!("Failed to shutdown MCP server session: {error}"),
+        }
+        handler.kill_process_group();
+    }
+
+    /// Starts the server and keeps its session open for tool calls. A server
+    /// that is already connected is restarted.
     pub async fn connect(&self, server_id: &str) -> Result<McpConnectionResult, String> {
-        let cache = self.cache.lock().await;
-        let server = cache
-            .iter()
-            .find(|item| Self::resolve_id(item).as_deref() == Some(server_id))
-            .cloned()
-            .ok_or_else(|| format!("MCP server not found: {server_id}"))?;
-        drop(cache);
+        let server = self.find_server(server_id).await?;
+
+        let server_type = Self::resolve_type(&server)?;
+        let (result, client) = if CLIENT_SERVER_TYPES.contains(&server_type.as_str()) {
+            let client = self.start_client(&server, &server_type).await?;
+            match Self::list_tools(client.peer()).await {
+                Ok(tools) => (
+                    McpConnectionResult {
+                        success: true,
+                        tools,
+                        error: None,
+                    },
+                    Some(client),
+                ),
+                Err(error) => {
+                    Self::close_client(client).await;
+                    return Err(error);
+                }
+            }
+        } else {
+            (self.test_connection(&server).await?, None)
+        };
 
-        let result = self.test_connection(&server).await?;
         if result.success {
-            let mut connections = self.connections.lock().await;
-            connections.insert(server_id.to_string(), result.tools.clone());
+            self.remember_tools(server_id, result.tools.clone()).await;
+            let replaced = {
+                let mut connections = self.connections.lock().await;
+                connections.dropped.remove(server_id);
+                connections.reconnecting.remove(server_id);
+                let health = connections
+                    .health
+                    .entry(server_id.to_string())
+                    .or_insert_with(|| McpServerHealth {
+                        server_id: server_id.to_string(),
+                        ..McpServerHealth::default()
+                    });
+                health.healthy = true;
+                health.consecutive_failures = 0;
+                match client {
+                    Some(client) => connections.clients.insert(server_id.to_string(), client),
+                    None => connections.clients.remove(server_id),
+                }
+            };
+            if let Some(previous) = replaced {
+                Self::close_client(previous).await;
+            }
         }
         Ok(result)
     }
 
+    async fn find_server(&self, server_id: &str) -> Result<Value, String> {
+        self.cache
+            .lock()
+            .await
+            .iter()
+            .find(|item| Self::resolve_id(item).as_deref() == Some(server_id))
+            .cloned()
+            .ok_or_else(|| format!("MCP server not found: {server_id}"))
+    }
+
+    /// Caches the server's tools and queues how they differ from the ones
+    /// cached or stored before, for `take_tool_changes`.
+    async fn remember_tools(&self, server_id: &str, tools: Vec<Value>) {
+        let cached = self.connections.lock().await.tools.get(server_id);
+        let previous = match cached {
+            Some(tools) => Some(tools),
+            None => self
+                .registry_store
+                .as_ref()
+                .and_then(|store| store.server_tools(server_id).ok().flatten())
+                .map(|cached| cached.tools),
+        };
+        if let Some(store) = self.registry_store.as_ref()
+            && let Err(error) = store.save_server_tools(server_id, &tools)
+        {
+            log::warn!("Failed to persist tools for {}: {}", server_id, error);
+        }
+        let mut connections = self.connections.lock().await;
+        if let Some(previous) = previous {
+            let change = mcp_tool_cache::diff_tools(server_id, &previous, &tools);
+            if !change.is_empty() {
+                connections.results.clear_server(server_id);
+                connections.tool_changes.push(change);
+            }
+        }
+        let evicted = connections.tools.insert(server_id, tools);
+        if !evicted.is_empty() {
+            log::debug!(
+                "Evicted MCP tool caches to stay within memory budget: {:?}",
+                evicted
+            );
+        }
+    }
+
+    /// Adds the servers in a `claude_desktop_config.json` file, replacing
+    /// those brought in by an earlier import of the same names. Secrets in
+    /// their env move to the credential store as on any save.
+    pub async fn import_claude_config(&self, path: &Path) -> Result<McpConfigImport, String> {
+        let raw = fs::read_to_string(path)
+            .await
+            .map_err(|error| format!("Failed to read {}: {error}", path.display()))?;
+        let raw: Value = serde_json::from_str(&raw)
+            .map_err(|error| format!("Invalid Claude Desktop config: {error}"))?;
+        let (imported, skipped) =
+            mcp_claude_config::from_claude_desktop(&raw, &Utc::now().to_rfc3339())?;
+
+        let mut servers = self.load().await?;
+        let mut report = McpConfigImport {
+            skipped,
+            ..McpConfigImport::default()
+        };
+        for server in imported {
+            let id = Self::resolve_id(&server).unwrap_or_default();
+            match servers
+                .iter()
+                .position(|existing| Self::resolve_id(existing).as_deref() == Some(id.as_str()))
+            {
+                Some(index) => {
+                    let mut server = server;
+                    Self::keep_secrets_left_empty(&mut server, &servers[index]);
+                    servers[index] = server;
+                    report.replaced.push(id);
+                }
+                None => {
+                    servers.push(server);
+                    report.added.push(id);
+                }
+            }
+        }
+        self.save(servers).await?;
+        Ok(report)
+    }
+
+    /// Keeps `existing`'s value for each secret `env` or `headers` entry that
+    /// `server` leaves empty, as an export without secrets does, so
+    /// re-importing it doesn't wipe working credentials.
+    fn keep_secrets_left_empty(server: &mut Value, existing: &Value) {
+        for field in ["env", "headers"] {
+            let Some(Value::Object(values)) = server
+                .get_mut("config")
+                .and_then(|config| config.get_mut(field))
+            else {
+                continue;
+            };
+            for (name, value) in values.iter_mut() {
+                if value.as_str() != Some("") || !Self::is_secret_name(name) {
+                    continue;
+                }
+                if let Some(previous) = existing
+                    .get("config")
+                    .and_then(|config| config.get(field))
+                    .and_then(|values| values.get(name))
+                {
+                    *value = previous.clone();
+                }
+            }
+        }
+    }
+
+    /// Writes the servers as the `mcpServers` of a Claude Desktop config,
+    /// keeping any other settings already in the file. Stored credentials
+    /// are written out only with `include_secrets`; otherwise their values
+    /// are left empty.
+    pub async fn export_claude_config(
+        &self,
+        path: &Path,
+        include_secrets: bool,
+    ) -> Result<McpConfigExport, String> {
+        let mut servers = self.load().await?;
+        for server in &mut servers {
+            for field in ["env", "headers"] {
+                let Some(Value::Object(values)) = server
+                    .get_mut("config")
+                    .and_then(|config| config.get_mut(field))
+                else {
+                    continue;
+                };
+                for value in values.values_mut() {
+                    let Some(text) = value.as_str() else {
+                        continue;
+                    };
+                    let is_reference = text.contains("${credential:")
+                        || text.trim().starts_with(CREDENTIAL_VALUE_PREFIX);
+                    if !is_reference {
+                        continue;
+                    }
+                    *value = if include_secrets {
+                        json!(self.resolve_env_value(text).await?)
+                    } else {
+                        json!("")
+                    };
+                }
+            }
+        }
+        let (entries, skipped) = mcp_claude_config::to_claude_desktop(&servers);
+
+        let mut document = match fs::read_to_string(path).await {
+            Ok(existing) => match serde_json::from_str::<Value>(&existing) {
+                Ok(Value::Object(document)) => document,
+                _ => {
+                    return Err(format!(
+                        "{} exists and is not a JSON object",
+                        path.display()
+                    ));
+                }
+            },
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Map::new(),
+            Err(error) => return Err(format!("Failed to read {}: {error}", path.display())),
+        };
+        let exported = entries.len();
+        document.insert("mcpServers".to_string(), Value::Object(entries));
+        let serialized = serde_json::to_string_pretty(&Value::Object(document))
+            .map_err(|error| format!("Failed to serialize Claude Desktop config: {error}"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|error| format!("Failed to create {}: {error}", parent.display()))?;
+        }
+        fs::write(path, serialized)
+            .await
+            .map_err(|error| format!("Failed to write {}: {error}", path.display()))?;
+        Ok(McpConfigExport {
+            path: path.display().to_string(),
+            exported,
+            skipped,
+        })
+    }
+
+    /// The servers run from an npm or PyPI package, for
+    /// [`mcp_updates::check_updates`].
+    pub async fn update_targets(&self) -> Vec<UpdateTarget> {
+        self.cache
+            .lock()
+            .await
+            .iter()
+            .filter_map(|server| {
+                let server_id = Self::resolve_id(server)?;
+                let package = mcp_updates::installed_package(server)?;
+                let name = server
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or(&server_id)
+                    .to_string();
+                Some(UpdateTarget {
+                    server_id,
+                    name,
+                    package,
+                })
+            })
+            .collect()
+    }
+
+    /// Pins the server's package to `version`, or to the registry's latest,
+    /// and saves it. An open session is restarted so `npx` or `uvx` fetches
+    /// the new version.
+    pub async fn upgrade_server(
+        &self,
+        server_id: &str,
+        version: Option<String>,
+    ) -> Result<Value, String> {
+        let mut servers = self.load().await?;
+        let index = servers
+            .iter()
+            .position(|server| Self::resolve_id(server).as_deref() == Some(server_id))
+            .ok_or_else(|| format!("MCP server not found: {server_id}"))?;
+        let package = mcp_updates::installed_package(&servers[index]).ok_or_else(|| {
+            format!("MCP server {server_id} is not run from an npm or PyPI package")
+        })?;
+        let version = match version {
+            Some(version) => version.trim().to_string(),
+            None => {
+                mcp_updates::latest_version(
+                    &mcp_updates::registry_client()?,
+                    package.registry,
+                    &package.name,
+                )
+                .await?
+            }
+        };
+        if !mcp_updates::is_valid_version(&version) {
+            return Err(format!("Invalid package version: {version}"));
+        }
+
+        let pinned = package.registry.pin(&package.name, &version);
+        let server = &mut servers[index];
+        let previous = server
+            .pointer(&format!("/config/args/{}", package.arg_index))
+            .cloned();
+        if let Some(arg) = server.pointer_mut(&format!("/config/args/{}", package.arg_index)) {
+            *arg = json!(pinned);
+        }
+        if let Some(Value::Array(args)) = server.pointer_mut("/installCommand/args") {
+            for arg in args
+                .iter_mut()
+                .filter(|arg| Some(&**arg) == previous.as_ref())
+            {
+                *arg = json!(pinned);
+            }
+        }
+        if let Value::Object(map) = server {
+            map.insert("updatedAt".to_string(), json!(Utc::now().to_rfc3339()));
+        }
+        let upgraded = server.clone();
+        self.save(servers).await?;
+
+        if self
+            .connections
+            .lock()
+            .await
+            .clients
+            .contains_key(server_id)
+        {
+            self.connect(server_id).await?;
+        }
+        Ok(upgraded)
+    }
+
+    /// What the OAuth flow needs for an HTTP server: its URL, its
+    /// `config.auth` settings and the credential store for its tokens.
+    pub async fn oauth_target(
+        &self,
+        server_id: &str,
+    ) -> Result<(String, McpOAuthConfig, CredentialManager), String> {
+        let server = self.find_server(server_id).await?;
+        let config = server.get("config").cloned().unwrap_or(Value::Null);
+        let url = config
+            .get("url")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+            .ok_or_else(|| "OAuth sign-in needs an HTTP server with config.url".to_string())?
+            .to_string();
+        let auth = config
+            .get("auth")
+            .cloned()
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|error| format!("Invalid config.auth: {error}"))?
+            .unwrap_or_default();
+        let credentials = self
+            .credentials
+            .clone()
+            .ok_or_else(|| "Credential storage is unavailable for OAuth".to_string())?;
+        Ok((url, auth, credentials))
+    }
+
+    /// Ends the server's session, if any, and forgets its tools.
     pub async fn disconnect(&self, server_id: &str) -> Result<bool, String> {
+        let (had_tools, client) = {
+            let mut connections = self.connections.lock().await;
+            connections.dropped.remove(server_id);
+            connections.health.remove(server_id);
+            connections.results.clear_server(server_id);
+            let was_reconnecting = connections.reconnecting.remove(server_id);
+            (
+                connections.tools.remove(server_id) || was_reconnecting,
+                connections.clients.remove(server_id),
+            )
+        };
+        let had_client = client.is_some();
+        if let Some(client) = client {
+            Self::close_client(client).await;
+        }
+        Ok(had_tools || had_client)
+    }
+
+    /// Ends every open session. Called when the app exits or the workspace
+    /// changes. Sessions close at once and pending reconnects stop; a custom
+    /// server that does not exit once its stdin closes is killed together
+    /// with the processes it started, so none outlive the app.
+    pub async fn shutdown(&self) {
+        let clients: Vec<(String, McpClient)> = {
+            let mut connections = self.connections.lock().await;
+            connections.reconnecting.clear();
+            connections.dropped.clear();
+            connections.clients.drain().collect()
+        };
+        let mut closing = tokio::task::JoinSet::new();
+        for (server_id, client) in clients {
+            closing.spawn(async move {
+                log::debug!("Closing MCP session for {}", server_id);
+                let handler = client.service().clone();
+                if tokio::time::timeout(SESSION_CLOSE_TIMEOUT, Self::close_client(client))
+                    .await
+                    .is_err()
+                {
+                    log::warn!("MCP server {} did not shut down in time", server_id);
+                    handler.kill_process_group();
+                }
+            });
+        }
+        while closing.join_next().await.is_some() {}
+    }
+
+    fn groups_path(&self) -> PathBuf {
+        self.path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(mcp_groups::GROUPS_FILE)
+    }
+
+    fn active_group_path(&self) -> PathBuf {
+        self.groups_path()
+            .with_file_name(mcp_groups::ACTIVE_GROUP_FILE)
+    }
+
+    pub async fn list_groups(&self) -> Vec<McpServerGroup> {
+        let raw = fs::read_to_string(self.groups_path()).await.ok();
+        mcp_groups::parse(raw.as_deref())
+    }
+
+    async fn write_groups(&self, groups: &[McpServerGroup]) -> Result<(), String> {
+        let serialized = serde_json::to_string_pretty(groups)
+            .map_err(|error| format!("Failed to serialize MCP groups: {error}"))?;
+        fs::write(self.groups_path(), serialized)
+            .await
+            .map_err(|error| format!("Failed to write MCP groups: {error}"))
+    }
+
+    /// Creates the named group, or replaces the servers of an existing one.
+    pub async fn save_group(
+        &self,
+        name: &str,
+        server_ids: Vec<String>,
+    ) -> Result<McpServerGroup, String> {
+        let known: HashSet<String> = self
+            .load()
+            .await?
+            .iter()
+            .filter_map(Self::resolve_id)
+            .collect();
+        let mut groups = self.list_groups().await;
+        let group = mcp_groups::upsert(
+            &mut groups,
+            name,
+            server_ids,
+            &known,
+            &Utc::now().to_rfc3339(),
+        )?;
+        self.write_groups(&groups).await?;
+        Ok(group)
+    }
+
+    /// Removes the named group, deactivating it first when it is active.
+    pub async fn delete_group(&self, name: &str) -> Result<bool, String> {
+        let mut groups = self.list_groups().await;
+        if !mcp_groups::remove(&mut groups, name) {
+            return Ok(false);
+        }
+        if self
+            .active_group()
+            .await
+            .is_some_and(|active| active.name.eq_ignore_ascii_case(name.trim()))
+        {
+            self.write_active_group(None).await?;
+        }
+        self.write_groups(&groups).await?;
+        Ok(true)
+    }
+
+    /// The group activated with [`Self::activate_group`], if it still exists.
+    pub async fn active_group(&self) -> Option<McpServerGroup> {
+        let name = fs::read_to_string(self.active_group_path()).await.ok()?;
+        let groups = self.list_groups().await;
+        mcp_groups::find(&groups, &name).cloned()
+    }
+
+    async fn write_active_group(&self, name: Option<&str>) -> Result<(), String> {
+        let path = self.active_group_path();
+        let result = match name {
+            Some(name) => fs::write(&path, name).await,
+            None => match fs::remove_file(&path).await {
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                other => other,
+            },
+        };
+        result.map_err(|error| format!("Failed to write the active MCP group: {error}"))
+    }
+
+    /// Whether a server should be connected: it is enabled and, while a group
+    /// is active, one of its members.
+    fn is_selected(server: &Value, group: Option<&McpServerGroup>) -> bool {
+        let enabled = server
+            .get("enabled")
+            .and_then(Value::as_bool)
+            .unwrap_or(true);
+        let member = match (group, Self::resolve_id(server)) {
+            (Some(group), Some(server_id)) => group.server_ids.contains(&server_id),
+            _ => true,
+        };
+        enabled && member
+    }
+
+    /// The servers to connect at startup.
+    pub async fn startup_servers(&self) -> Result<Vec<String>, String> {
+        let group = self.active_group().await;
+        Ok(self
+            .load()
+            .await?
+            .iter()
+            .filter(|server| Self::is_selected(server, group.as_ref()))
+            .filter_map(Self::resolve_id)
+            .collect())
+    }
+
+    /// The enabled servers of the named group, in the group's order.
+    /// Members removed from `mcp-servers.json` since are skipped.
+    pub async fn group_servers(&self, name: &str) -> Result<Vec<Value>, String> {
+        let groups = self.list_groups().await;
+        let group = mcp_groups::find(&groups, name)
+            .ok_or_else(|| format!("MCP server group not found: {name}"))?;
+        let servers = self.load().await?;
+        Ok(group
+            .server_ids
+            .iter()
+            .filter_map(|id| {
+                servers
+                    .iter()
+                    .find(|server| Self::resolve_id(server).as_deref() == Some(id.as_str()))
+                    .filter(|server| Self::is_selected(server, None))
+                    .cloned()
+            })
+            .collect())
+    }
+
+    /// Connects exactly the enabled servers of the named group and
+    /// disconnects the rest. The group stays active, so the same set
+    /// connects at the next launch, until [`Self::deactivate_group`].
+    pub async fn activate_group(&self, name: &str) -> Result<McpGroupActivation, String> {
+        let groups = self.list_groups().await;
+        let group = mcp_groups::find(&groups, name)
+            .ok_or_else(|| format!("MCP server group not found: {name}"))?;
+        self.write_active_group(Some(&group.name)).await?;
+        self.apply_group(group.name.clone(), Some(group)).await
+    }
+
+    /// Clears the active group and connects every enabled server it left
+    /// out.
+    pub async fn deactivate_group(&self) -> Result<McpGroupActivation, String> {
+        let group = self.active_group().await;
+        self.write_active_group(None).await?;
+        self.apply_group(group.map(|group| group.name).unwrap_or_default(), None)
+            .await
+    }
+
+    async fn apply_group(
+        &self,
+        name: String,
+        group: Option<&McpServerGroup>,
+    ) -> Result<McpGroupActivation, String> {
+        let mut selected = Vec::new();
+        let mut others = Vec::new();
+        for server in self.load().await? {
+            let Some(server_id) = Self::resolve_id(&server) else {
+                continue;
+            };
+            if Self::is_selected(&server, group) {
+                selected.push(server_id);
+            } else {
+                others.push(server_id);
+            }
+        }
+        let selected: Vec<String> = match group {
+            Some(group) => group
+                .server_ids
+                .iter()
+                .filter(|id| selected.contains(id))
+                .cloned()
+                .collect(),
+            None => selected,
+        };
+
+        let mut activation = McpGroupActivation {
+            group: name,
+            ..McpGroupActivation::default()
+        };
+        for server_id in others {
+            if self.disconnect(&server_id).await? {
+                activation.disconnected.push(server_id);
+            }
+        }
+        for server_id in selected {
+            if self.live_peer(&server_id).await.is_some() {
+                activation.connected.push(server_id);
+                continue;
+            }
+            match self.connect(&server_id).await {
+                Ok(result) if result.success => activation.connected.push(server_id),
+                Ok(result) => activation.failed.push(McpGroupFailure {
+                    server_id,
+                    error: result
+                        .error
+                        .unwrap_or_else(|| "Unknown MCP connection failure".to_string()),
+                }),
+                Err(error) => activation.failed.push(McpGroupFailure { server_id, error }),
+            }
+        }
+        Ok(activation)
+    }
+
+    /// The peer of the server's open session. A session whose transport has
+    /// closed, such as a server process that exited, is dropped.
+    async fn live_peer(&self, server_id: &str) -> Option<Peer<RoleClient>> {
         let mut connections = self.connections.lock().await;
-        Ok(connections.remove(server_id).is_some())
+        let peer = connections.clients.get(server_id)?.peer().clone();
+        if peer.is_transport_closed() {
+            connections.mark_dropped(server_id);
+            return None;
+        }
+        Some(peer)
+    }
+
+    /// The open sessions to ping, for [`PendingHealthCheck::run`].
+    pub async fn prepare_health_check(&self) -> PendingHealthCheck {
+        let peers = {
+            let connections = self.connections.lock().await;
+            connections
+                .clients
+                .iter()
+                .map(|(server_id, client)| (server_id.clone(), client.peer().clone()))
+                .collect()
+        };
+        PendingHealthCheck {
+            peers,
+            connections: self.connections.clone(),
+        }
+    }
+
+    /// Heartbeat results for each connected or reconnecting server, ordered
+    /// by server id.
+    pub async fn health(&self) -> Vec<McpServerHealth> {
+        let mut health: Vec<McpServerHealth> = self
+            .connections
+            .lock()
+            .await
+            .health
+            .values()
+            .cloned()
+            .collect();
+        health.sort_by(|left, right| left.server_id.cmp(&right.server_id));
+        health
+    }
+
+    /// Servers whose session ended without a disconnect since the last call.
+    /// Each is marked as reconnecting until it connects, is disconnected or
+    /// `finish_reconnect` is called.
+    pub async fn take_dropped_sessions(&self) -> Vec<String> {
+        let mut connections = self.connections.lock().await;
+        let closed: Vec<String> = connections
+            .clients
+            .iter()
+            .filter(|(_, client)| client.peer().is_transport_closed())
+            .map(|(server_id, _)| server_id.clone())
+            .collect();
+        for server_id in &closed {
+            connections.mark_dropped(server_id);
+        }
+        let mut dropped: Vec<String> = connections.dropped.drain().collect();
+        dropped.sort();
+        connections.reconnecting.extend(dropped.iter().cloned());
+        dropped
+    }
+
+    /// Tool list changes seen since the last call, oldest first.
+    pub async fn take_tool_changes(&self) -> Vec<ToolListChange> {
+        std::mem::take(&mut self.connections.lock().await.tool_changes)
+    }
+
+    /// Whether a dropped server should still be reconnected.
+    pub async fn reconnect_pending(&self, server_id: &str) -> bool {
+        self.connections
+            .lock()
+            .await
+            .reconnecting
+            .contains(server_id)
+    }
+
+    pub async fn finish_reconnect(&self, server_id: &str) {
+        self.connections.lock().await.reconnecting.remove(server_id);
+    }
+
+    /// Calls a tool through the server's open session, connecting first when
+    /// the server has none. Arguments are checked against the tool's input
+    /// schema before anything is sent. `call_id` names the call for
+    /// cancelling and progress; one is generated when it is `None`.
+    pub async fn call_tool(
+        &self,
+        server_id: &str,
+        tool_name: &str,
+        arguments: Option<Value>,
+        call_id: Option<String>,
+    ) -> Result<McpToolCallResult, String> {
+        self.prepare_tool_call(server_id, tool_name, arguments, call_id)
+            .await?
+            .run()
+            .await
+    }
+
+    /// Does everything `call_tool` needs the service for: connecting,
+    /// checking arguments and reserving a rate-limit slot. Running the
+    /// returned call does not, so callers can release their lock first.
+    pub async fn prepare_tool_call(
+        &self,
+        server_id: &str,
+        tool_name: &str,
+        arguments: Option<Value>,
+        call_id: Option<String>,
+    ) -> Result<PreparedToolCall, String> {
+        let arguments = match arguments {
+            None | Some(Value::Null) => Map::new(),
+            Some(Value::Object(map)) => map,
+            Some(_) => return Err("MCP tool arguments must be an object".to_string()),
+        };
+        let server = self.find_server(server_id).await?;
+        if server.get("enabled").and_then(Value::as_bool) == Some(false) {
+            return Err(format!("MCP server {server_id} is disabled"));
+        }
+        let cache_policy =
+            ToolResultPolicy::from_server(&server).filter(|policy| policy.covers(tool_name));
+        let cache = cache_policy.map(|policy| {
+            (
+                ToolResultKey::new(server_id, tool_name, &Value::Object(arguments.clone())),
+                policy.ttl,
+            )
+        });
+        if let Some((key, _)) = &cache
+            && let Some(mut cached) = self
+                .connections
+                .lock()
+                .await
+                .results
+                .get(key, Instant::now())
+        {
+            cached.cached = true;
+            cached.duration_ms = 0;
+            return Ok(PreparedToolCall::Cached(cached));
+        }
+
+        let peer = match self.live_peer(server_id).await {
+            Some(peer) => peer,
+            None => {
+                let result = self.connect(server_id).await?;
+                if !result.success {
+                    return Err(result
+                        .error
+                        .unwrap_or_else(|| format!("Failed to connect to {server_id}")));
+                }
+                self.live_peer(server_id)
+                    .await
+                    .ok_or_else(|| format!("MCP server {server_id} does not run tools"))?
+            }
+        };
+
+        // The guard has to go before refreshing, which locks again.
+        let cached_tools = self.connections.lock().await.tools.get(server_id);
+        let tools = match cached_tools {
+            Some(tools) => tools,
+            None => self.refresh_tools(server_id).await?.tools,
+        };
+        let tool = tools
+            .iter()
+            .find(|tool| tool.get("name").and_then(Value::as_str) == Some(tool_name))
+            .ok_or_else(|| format!("MCP server {server_id} has no tool named {tool_name}"))?;
+        check_tool_arguments(tool, &arguments)?;
+
+        let mut wait = Duration::ZERO;
+        if let Some((per_minute, max_wait)) = Self::rate_limit(&server) {
+            wait = self
+                .rate_limiter
+                .reserve(server_id, per_minute, max_wait, Utc::now())
+                .map_err(|retry_in| {
+                    format!(
+                        "MCP server {server_id} is rate limited to {per_minute} requests per minute; try again in {}s",
+                        retry_in.as_secs().max(1)
+                    )
+                })?;
+        }
+
+        Ok(PreparedToolCall::Pending(PendingToolCall {
+            peer,
+            server_id: server_id.to_string(),
+            tool_name: tool_name.to_string(),
+            arguments,
+            call_id: call_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            wait,
+            cache,
+            connections: self.connections.clone(),
+            tool_calls: self.tool_calls.clone(),
+        }))
+    }
+
+    /// A server's `config.rateLimit`: calls per minute and how long a call
+    /// may queue for a slot before it fails.
+    fn rate_limit(server: &Value) -> Option<(u32, Duration)> {
+        let limit = server.get("config")?.get("rateLimit")?;
+        let per_minute = limit
+            .get("requestsPerMinute")
+            .and_then(Value::as_u64)
+            .filter(|per_minute| *per_minute > 0)?;
+        let max_wait = limit
+            .get("maxWaitSeconds")
+            .and_then(Value::as_u64)
+            .unwrap_or(DEFAULT_RATE_LIMIT_MAX_WAIT_SECS);
+        Some((
+            per_minute.min(u32::MAX as u64) as u32,
+            Duration::from_secs(max_wait),
+        ))
     }
 
     pub async fn connected_tools(&self, server_id: &str) -> Result<Vec<Value>, String> {
-        let connections = self.connections.lock().await;
-        Ok(connections.get(server_id).cloned().unwrap_or_default())
+        let was_evicted = {
+            let mut connections = self.connections.lock().await;
+            if let Some(tools) = connections.tools.get(server_id) {
+                return Ok(tools);
+            }
+            connections.tools.was_evicted(server_id)
+        };
+
+        if !was_evicted {
+            return self.offline_tools(server_id);
+        }
+
+        let result = self.refresh_tools(server_id).await?;
+        Ok(if result.success {
+            result.tools
+        } else {
+            Vec::new()
+        })
+    }
+
+    /// Tools captured the last time the server connected, each flagged as
+    /// cached and offline. Empty when the server never connected.
+    fn offline_tools(&self, server_id: &str) -> Result<Vec<Value>, String> {
+        let Some(store) = self.registry_store.as_ref() else {
+            return Ok(Vec::new());
+        };
+        Ok(store
+            .server_tools(server_id)?
+            .map(|cached| Self::mark_offline_tools(cached.tools, cached.captured_at))
+            .unwrap_or_default())
     }
 
+    fn mark_offline_tools(tools: Vec<Value>, captured_at: i64) -> Vec<Value> {
+        let captured_at =
+            DateTime::<Utc>::from_timestamp(captured_at, 0).map(|timestamp| timestamp.to_rfc3339());
+        tools
+            .into_iter()
+            .map(|mut tool| {
+                if let Some(object) = tool.as_object_mut() {
+                    object.insert("cached".to_string(), Value::Bool(true));
+                    object.insert("offline".to_string(), Value::Bool(true));
+                    object.insert("capturedAt".to_string(), json!(captured_at));
+                }
+                tool
+            })
+            .collect()
+    }
+
+    /// Tool schemas for every configured server, live where connected and
+    /// from the persisted catalog otherwise, so the agent can plan tool use
+    /// before servers are started.
+    pub async fn tool_catalog(&self) -> Result<Vec<Value>, String> {
+        let servers = self.cache.lock().await.clone();
+        let mut catalog = Vec::with_capacity(servers.len());
+
+        for server in &servers {
+            let Some(server_id) = Self::resolve_id(server) else {
+                continue;
+            };
+            let name = server
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or(&server_id)
+                .to_string();
+            let enabled = server
+                .get("enabled")
+                .and_then(Value::as_bool)
+                .unwrap_or(true);
+
+            let live = self.connections.lock().await.tools.get(&server_id);
+            let entry = match live {
+                Some(tools) => json!({
+                    "serverId": server_id,
+                    "serverName": name,
+                    "enabled": enabled,
+                    "connected": true,
+                    "cached": false,
+                    "tools": tools,
+                }),
+                None => {
+                    let cached = match self.registry_store.as_ref() {
+                        Some(store) => store.server_tools(&server_id)?,
+                        None => None,
+                    };
+                    let Some(cached) = cached else {
+                        continue;
+                    };
+                    json!({
+                        "serverId": server_id,
+                        "serverName": name,
+                        "enabled": enabled,
+                        "connected": false,
+                        "cached": true,
+                        "capturedAt": DateTime::<Utc>::from_timestamp(cached.captured_at, 0)
+                            .map(|timestamp| timestamp.to_rfc3339()),
+                        "tools": cached.tools,
+                    })
+                }
+            };
+            catalog.push(entry);
+        }
+
+        Ok(catalog)
+    }
+
+    #[cfg(test)]
+    pub(crate) async fn set_tool_cache_budget(&self, budget: ToolCacheBudget) {
+        self.connections.lock().await.tools = ToolCache::new(budget);
+    }
+
+    /// Evicts idle tool caches and trims the persisted registry cache.
+    pub async fn compact_memory(&self) -> Result<Value, String> {
+        let evicted = self
+            .connections
+            .lock()
+            .await
+            .tools
+            .evict_idle(Instant::now());
+        if !evicted.is_empty() {
+            log::debug!("Evicted idle MCP tool caches: {:?}", evicted);
+        }
+
+        if let Some(store) = self.registry_store.as_ref() {
+            let pruned = store.prune(REGISTRY_STORE_MAX_AGE_SECS)?;
+            if pruned > 0 {
+                log::debug!("Pruned {} stale registry cache entries", pruned);
+            }
+        }
+
+        self.memory_stats().await
+    }
+
+    pub async fn memory_stats(&self) -> Result<Value, String> {
+        let (tool_cache, (results, result_hits, result_misses)) = {
+            let connections = self.connections.lock().await;
+            (connections.tools.stats(), connections.results.stats())
+        };
+        let remote_catalog_bytes = {
+            let caches = self.remote_cache.lock().await;
+            caches
+                .values()
+                .filter_map(|cache| cache.payload.as_ref())
+                .filter_map(|payload| serde_json::to_vec(&payload.servers).ok())
+                .map(|encoded| encoded.len())
+                .sum::<usize>()
+        };
+        let registry_entries = match self.registry_store.as_ref() {
+            Some(store) => Some(store.server_count()?),
+            None => None,
+        };
+
+        Ok(json!({
+            "toolCache": tool_cache,
+            "toolResultCache": {
+                "entries": results,
+                "hits": result_hits,
+                "misses": result_misses,
+            },
+            "remoteCatalogBytes": remote_catalog_bytes,
+            "registryStoreEntries": registry_entries,
+            "registryStoreMaxAgeSecs": REGISTRY_STORE_MAX_AGE_SECS,
+        }))
+    }
+
+    /// Lists the server's tools again, through its open session when it has
+    /// one and by connecting otherwise.
     pub async fn refresh_tools(&self, server_id: &str) -> Result<McpConnectionResult, String> {
-        self.connect(server_id).await
+        let Some(tools) = self.reload_tools(server_id).await? else {
+            return self.connect(server_id).await;
+        };
+        Ok(McpConnectionResult {
+            success: true,
+            tools,
+            error: None,
+        })
+    }
+
+    /// Lists the tools of a connected server again. `None` when the server
+    /// has no open session.
+    pub async fn reload_tools(&self, server_id: &str) -> Result<Option<Vec<Value>>, String> {
+        let Some(peer) = self.live_peer(server_id).await else {
+            return Ok(None);
+        };
+        let tools = Self::list_tools(&peer).await?;
+        self.remember_tools(server_id, tools.clone()).await;
+        Ok(Some(tools))
     }
 
     pub async fn search_registry(
@@ -895,11 +2695,69 @@ impl McpService {
         if stats.oldest_entry.is_none() {
             stats.oldest_entry = stats.newest_entry;
         }
+        self.persist_registry_stats(&stats);
         drop(stats);
 
         Ok(config_value)
     }
 
+    /// Fills the `installVariables` a registry install left pending into the
+    /// server's config and saves it. Nothing is saved when a required
+    /// variable is missing. Secret values go to the credential store and
+    /// the config gets a reference wherever they are used.
+    pub async fn resolve_install_variables(
+        &self,
+        server_id: &str,
+        mut values: HashMap<String, String>,
+    ) -> Result<Value, String> {
+        let mut servers = self.load().await?;
+        let server = servers
+            .iter_mut()
+            .find(|server| Self::resolve_id(server).as_deref() == Some(server_id))
+            .ok_or_else(|| format!("MCP server not found: {server_id}"))?;
+        let variables = mcp_install_variables::pending(server);
+        if variables.is_empty() {
+            return Err(format!(
+                "MCP server {server_id} has no pending install variables"
+            ));
+        }
+        let service = Self::credential_service(server_id);
+        let mut sealed = Vec::new();
+        if self.credentials.is_some() {
+            for variable in variables.iter().filter(|variable| variable.secret) {
+                let name = &variable.name;
+                if name.contains(['/', '}']) {
+                    continue;
+                }
+                if let Some(value) = values.get_mut(name)
+                    && !value.trim().is_empty()
+                {
+                    let reference = format!("${{credential:{service}/{name}}}");
+                    let secret = std::mem::replace(value, reference);
+                    sealed.push((name.clone(), secret.trim().to_string()));
+                }
+            }
+        }
+        let config = server
+            .get_mut("config")
+            .ok_or_else(|| format!("MCP server {server_id} has no config"))?;
+        mcp_install_variables::apply(config, &variables, &values)?;
+        if let Some(credentials) = self.credentials.as_ref() {
+            for (name, secret) in sealed {
+                credentials
+                    .store_with_scope(&service, &name, &secret, Some(CredentialScope::Mcp))
+                    .await
+                    .map_err(|error| format!("Failed to store MCP secret {name}: {error}"))?;
+            }
+        }
+        if let Value::Object(map) = server {
+            map.remove("installVariables");
+            map.insert("updatedAt".to_string(), json!(Utc::now().to_rfc3339()));
+        }
+        self.save(servers).await?;
+        self.find_server(server_id).await
+    }
+
     pub async fn clear_registry_cache(&self) -> Result<(), String> {
         let mut stats = self.registry_stats.lock().await;
         stats.cache_entries = 0;
@@ -907,6 +2765,7 @@ impl McpService {
         stats.total_response_time_ms = 0;
         stats.oldest_entry = None;
         stats.newest_entry = None;
+        self.persist_registry_stats(&stats);
         Ok(())
     }
 
@@ -924,15 +2783,24 @@ impl McpService {
             100.0
         };
         let remote_snapshot = {
-            let cache = self.remote_cache.lock().await;
-            json!({
-                "etag": cache.etag,
-                "consecutiveErrors": cache.consecutive_errors,
-                "lastError": cache.last_error,
-                "lastErrorAt": cache.last_error_at.map(|dt| dt.to_rfc3339()),
-                "lastLatencyMs": cache.last_latency_ms,
-                "lastStatus": cache.last_status,
-            })
+            let caches = self.remote_cache.lock().await;
+            let sources: Map<String, Value> = caches
+                .iter()
+                .map(|(source, cache)| {
+                    (
+                        source.clone(),
+                        json!({
+                            "etag": cache.etag,
+                            "consecutiveErrors": cache.consecutive_errors,
+                            "lastError": cache.last_error,
+                            "lastErrorAt": cache.last_error_at.map(|dt| dt.to_rfc3339()),
+                            "lastLatencyMs": cache.last_latency_ms,
+                            "lastStatus": cache.last_status,
+                        }),
+                    )
+                })
+                .collect();
+            Value::Object(sources)
         };
         let registry_snapshot = self
             .registry_store
@@ -946,6 +2814,8 @@ impl McpService {
             "cacheEntries": stats.cache_entries,
             "averageResponseTime": average_response,
             "cacheHitRate": hit_rate,
+            "totalSearches": stats.total_searches,
+            "installations": stats.installations,
             "oldestEntry": stats.oldest_entry.map(|dt| dt.to_rfc3339()),
             "newestEntry": stats.newest_entry.map(|dt| dt.to_rfc3339()),
             "lastBackgroundSync": stats.last_background_sync.map(|dt| dt.to_rfc3339()),
@@ -970,6 +2840,7 @@ impl McpService {
 
         let mut stats = self.registry_stats.lock().await;
         stats.last_background_sync = Some(Utc::now());
+        self.persist_registry_stats(&stats);
         drop(stats);
         self.cache_stats().await
     }
@@ -977,9 +2848,18 @@ impl McpService {
     pub async fn enrich_metrics(&self, _options: &Value) -> Result<(), String> {
         let mut stats = self.registry_stats.lock().await;
         stats.cache_hits += 1;
+        self.persist_registry_stats(&stats);
         Ok(())
     }
 
+    fn persist_registry_stats(&self, stats: &RegistryStats) {
+        if let Some(store) = self.registry_store.as_ref()
+            && let Err(error) = store.save_registry_stats(stats)
+        {
+            log::debug!("Failed to persist MCP registry stats: {}", error);
+        }
+    }
+
     fn persist_registry_servers(
         &self,
         registry: &str,
@@ -993,6 +2873,8 @@ impl McpService {
                     registry,
                     error
                 );
+            } else if let Err(error) = store.prune(REGISTRY_STORE_MAX_AGE_SECS) {
+                log::debug!("Failed to prune registry cache for {}: {}", registry, error);
             } else if let Err(error) =
                 store.record_sync_success(registry, servers.len(), duration_ms)
             {
@@ -1052,6 +2934,14 @@ impl McpService {
         config_object.insert("type".to_string(), json!(server_type));
         config_object.insert("status".to_string(), json!("disconnected"));
         config_object.insert("enabled".to_string(), json!(false));
+        let variables = mcp_install_variables::template_variables(
+            &config_value,
+            &mcp_install_variables::declared(server),
+            Self::is_secret_name,
+        );
+        if !variables.is_empty() {
+            config_object.insert("installVariables".to_string(), json!(variables));
+        }
         config_object.insert("config".to_string(), config_value);
         config_object.insert("createdAt".to_string(), json!(Utc::now().to_rfc3339()));
         config_object.insert("updatedAt".to_string(), json!(Utc::now().to_rfc3339()));
@@ -1253,6 +3143,9 @@ impl McpService {
         })
     }
 
+    /// Searches every registry source and merges what they return. A server
+    /// listed by several sources appears once, under the first source to
+    /// list it, with all of them in `sources`.
     async fn fetch_remote_registry(
         &self,
         options: &RegistrySearchOptions,
@@ -1261,36 +3154,152 @@ impl McpService {
             return Ok(None);
         }
 
-        let registry_name = "pulsemcp";
-        let base_url = std::env::var("TAURI_MCP_REGISTRY_BASE_URL")
-            .unwrap_or_else(|_| "https://api.pulsemcp.com/v0beta".to_string());
-        let base_url = base_url.trim_end_matches('/').to_string();
-        let client = Client::new();
-
-        let query_payload = PulseQuery {
-            query: options.query.as_ref().and_then(|value| {
-                if value.is_empty() {
-                    None
-                } else {
-                    Some(value.as_str())
-                }
-            }),
+        let client = proxy::client_builder()
+            .timeout(REGISTRY_REQUEST_TIMEOUT)
+            .build()
+            .map_err(|error| format!("Failed to build HTTP client: {error}"))?;
+        let page = RegistryPage {
+            query: options.query.as_deref().filter(|query| !query.is_empty()),
+            limit: options.limit.unwrap_or(50),
             offset: options.offset,
-            count_per_page: options.limit.unwrap_or(50),
+            cursor: None,
         };
+        let mut merged: Option<McpRegistrySearchResult> = None;
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for source in &self.registry_sources {
+            let Some(result) = self
+                .fetch_registry_source(source.as_ref(), &client, &page)
+                .await?
+            else {
+                continue;
+            };
+            let merged = merged.get_or_insert_with(|| McpRegistrySearchResult {
+                servers: Vec::new(),
+                total: 0,
+                has_more: false,
+                categories: result.categories.clone(),
+                metric_statuses: HashMap::new(),
+                metric_freshness: HashMap::new(),
+            });
+            merged.total += result.total;
+            merged.has_more |= result.has_more;
+            for mut server in result.servers {
+                let key = Self::registry_merge_key(&server);
+                if let Some(&index) = key.as_ref().and_then(|key| positions.get(key)) {
+                    if let Some(Value::Array(sources)) = merged.servers[index].get_mut("sources") {
+                        sources.push(json!(source.name()));
+                    }
+                    merged.total = merged.total.saturating_sub(1);
+                    continue;
+                }
+                if let Value::Object(map) = &mut server {
+                    map.insert("sources".to_string(), json!([source.name()]));
+                }
+                if let Some(key) = key {
+                    positions.insert(key, merged.servers.len());
+                }
+                merged.servers.push(server);
+            }
+            for (id, statuses) in result.metric_statuses {
+                merged.metric_statuses.entry(id).or_insert(statuses);
+            }
+            for (id, freshness) in result.metric_freshness {
+                merged.metric_freshness.entry(id).or_insert(freshness);
+            }
+        }
+        Ok(merged)
+    }
 
-        let mut request = client
-            .get(format!("{}/servers", base_url))
-            .query(&query_payload)
-            .header(reqwest::header::ACCEPT, "application/json")
-            .header(
-                reqwest::header::USER_AGENT,
-                "HashgraphOnlineDesktop/1.0 (+https://hashgraphonline.com)",
-            );
+    /// Servers from different sources are the same when they install the
+    /// same package, or failing that share an id.
+    fn registry_merge_key(server: &Value) -> Option<String> {
+        server
+            .get("packageName")
+            .and_then(Value::as_str)
+            .map(|package| format!("package:{}", package.to_lowercase()))
+            .or_else(|| {
+                server
+                    .get("id")
+                    .and_then(Value::as_str)
+                    .map(|id| format!("id:{}", id.to_lowercase()))
+            })
+    }
+
+    /// Normalizes `first` and, for sources that page by cursor, the pages
+    /// after it until `page` is filled, then applies `page`'s offset.
+    async fn normalize_registry_pages(
+        &self,
+        source: &dyn RegistrySource,
+        client: &Client,
+        page: &RegistryPage<'_>,
+        first: Value,
+        now: DateTime<Utc>,
+    ) -> (Vec<Value>, usize, bool) {
+        if !source.pages_by_cursor() {
+            return source.normalize(first, now);
+        }
+        let offset = page.offset.unwrap_or_default();
+        let wanted = offset + page.limit;
+        let mut cursor = source.next_cursor(&first);
+        let (mut servers, mut total, mut has_more) = source.normalize(first, now);
+        let mut pages = 1;
+        while servers.len() < wanted
+            && pages < MAX_REGISTRY_CURSOR_PAGES
+            && let Some(next) = cursor.take()
+        {
+            let next_page = RegistryPage {
+                cursor: Some(&next),
+                ..*page
+            };
+            let Some(request) = source.search_request(client, &next_page) else {
+                break;
+            };
+            let raw = match request
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+            {
+                Ok(response) => response.json::<Value>().await,
+                Err(error) => Err(error),
+            };
+            let raw = match raw {
+                Ok(raw) => raw,
+                Err(error) => {
+                    log::debug!("Failed to fetch the next {} page: {error}", source.name());
+                    break;
+                }
+            };
+            cursor = source.next_cursor(&raw);
+            let (more, more_total, more_pending) = source.normalize(raw, now);
+            servers.extend(more);
+            total += more_total;
+            has_more = more_pending;
+            pages += 1;
+        }
+        has_more |= servers.len() > wanted;
+        servers.drain(..offset.min(servers.len()));
+        servers.truncate(page.limit);
+        (servers, total, has_more)
+    }
+
+    async fn fetch_registry_source(
+        &self,
+        source: &dyn RegistrySource,
+        client: &Client,
+        page: &RegistryPage<'_>,
+    ) -> Result<Option<McpRegistrySearchResult>, String> {
+        let registry_name = source.name();
+        let Some(mut request) = source.search_request(client, page) else {
+            return Ok(None);
+        };
         let now = Utc::now();
         let (cached_etag, cached_payload) = {
-            let cache = self.remote_cache.lock().await;
-            (cache.etag.clone(), cache.payload.clone())
+            let caches = self.remote_cache.lock().await;
+            let cache = caches.get(registry_name);
+            (
+                cache.and_then(|cache| cache.etag.clone()),
+                cache.and_then(|cache| cache.payload.clone()),
+            )
         };
 
         if let Some(etag) = cached_etag.as_ref() {
@@ -1309,7 +3318,8 @@ impl McpService {
                 if status_code == reqwest::StatusCode::NOT_MODIFIED {
                     if let Some(mut payload) = cached_payload.clone() {
                         payload.fetched_at = now;
-                        let mut cache = self.remote_cache.lock().await;
+                        let mut caches = self.remote_cache.lock().await;
+                        let cache = caches.entry(registry_name.to_string()).or_default();
                         cache.payload = Some(payload.clone());
                         cache.consecutive_errors = 0;
                         if cache.etag.is_none() {
@@ -1331,15 +3341,17 @@ impl McpService {
                         .map(|value| value.to_string());
                     match response.json::<Value>().await {
                         Ok(raw_value) => {
-                            let (servers, total, has_more) =
-                                Self::normalize_pulse_payload(raw_value, now);
+                            let (servers, total, has_more) = self
+                                .normalize_registry_pages(source, client, page, raw_value, now)
+                                .await;
 
                             if servers.is_empty() {
                                 fallback_error_code = Some("REMOTE_EMPTY".to_string());
                                 fallback_error_message = Some(
                                     "Remote registry returned no installable servers".to_string(),
                                 );
-                                let mut cache = self.remote_cache.lock().await;
+                                let mut caches = self.remote_cache.lock().await;
+                                let cache = caches.entry(registry_name.to_string()).or_default();
                                 cache.consecutive_errors =
                                     cache.consecutive_errors.saturating_add(1);
                                 cache.last_latency_ms = Some(elapsed_ms);
@@ -1359,7 +3371,8 @@ impl McpService {
                                     has_more,
                                     fetched_at: now,
                                 };
-                                let mut cache = self.remote_cache.lock().await;
+                                let mut caches = self.remote_cache.lock().await;
+                                let cache = caches.entry(registry_name.to_string()).or_default();
                                 cache.etag = etag_value;
                                 cache.payload = Some(payload.clone());
                                 cache.consecutive_errors = 0;
@@ -1375,7 +3388,8 @@ impl McpService {
                             fallback_error_code = Some("REMOTE_PARSE_ERROR".to_string());
                             fallback_error_message =
                                 Some(format!("Failed to parse remote registry response: {error}"));
-                            let mut cache = self.remote_cache.lock().await;
+                            let mut caches = self.remote_cache.lock().await;
+                            let cache = caches.entry(registry_name.to_string()).or_default();
                             cache.consecutive_errors = cache.consecutive_errors.saturating_add(1);
                             cache.last_latency_ms = Some(elapsed_ms);
                             cache.last_status = Some(status_code.as_u16() as i32);
@@ -1396,7 +3410,8 @@ impl McpService {
                         Some(format!("Remote registry returned status {}", status_code));
                     if let Some(payload) = cached_payload.clone() {
                         used_cached_on_error = true;
-                        let mut cache = self.remote_cache.lock().await;
+                        let mut caches = self.remote_cache.lock().await;
+                        let cache = caches.entry(registry_name.to_string()).or_default();
                         cache.consecutive_errors = cache.consecutive_errors.saturating_add(1);
                         cache.last_latency_ms = Some(elapsed_ms);
                         cache.last_status = Some(status_code.as_u16() as i32);
@@ -1404,7 +3419,8 @@ impl McpService {
                         cache.last_error_at = Some(now);
                         Some(payload)
                     } else {
-                        let mut cache = self.remote_cache.lock().await;
+                        let mut caches = self.remote_cache.lock().await;
+                        let cache = caches.entry(registry_name.to_string()).or_default();
                         cache.consecutive_errors = cache.consecutive_errors.saturating_add(1);
                         cache.last_latency_ms = Some(elapsed_ms);
                         cache.last_status = Some(status_code.as_u16() as i32);
@@ -1420,13 +3436,15 @@ impl McpService {
                 fallback_error_message = Some(format!("Remote registry request failed: {error}"));
                 if let Some(payload) = cached_payload.clone() {
                     used_cached_on_error = true;
-                    let mut cache = self.remote_cache.lock().await;
+                    let mut caches = self.remote_cache.lock().await;
+                    let cache = caches.entry(registry_name.to_string()).or_default();
                     cache.consecutive_errors = cache.consecutive_errors.saturating_add(1);
                     cache.last_error = fallback_error_message.clone();
                     cache.last_error_at = Some(now);
                     Some(payload)
                 } else {
-                    let mut cache = self.remote_cache.lock().await;
+                    let mut caches = self.remote_cache.lock().await;
+                    let cache = caches.entry(registry_name.to_string()).or_default();
                     cache.consecutive_errors = cache.consecutive_errors.saturating_add(1);
                     cache.last_error = fallback_error_message.clone();
                     cache.last_error_at = Some(now);
@@ -1522,6 +3540,7 @@ impl McpService {
         if !has_more {
             stats.last_background_sync = stats.last_background_sync.or(stats.newest_entry);
         }
+        self.persist_registry_stats(&stats);
     }
 
     fn build_metric_maps(servers: &[Value]) -> (HashMap<String, Value>, HashMap<String, Value>) {
@@ -1689,45 +3708,342 @@ impl McpService {
         }
     }
 
-    fn build_server_metrics_value(server: &Value) -> Option<Value> {
-        let servers = vec![server.clone()];
-        let (metrics, _) = Self::build_metric_maps(&servers);
-        server
-            .get("id")
-            .and_then(Value::as_str)
-            .and_then(|id| metrics.get(id).cloned())
-    }
+    fn build_server_metrics_value(server: &Value) -> Option<Value> {
+        let servers = vec![server.clone()];
+        let (metrics, _) = Self::build_metric_maps(&servers);
+        server
+            .get("id")
+            .and_then(Value::as_str)
+            .and_then(|id| metrics.get(id).cloned())
+    }
+
+    fn build_server_freshness_value(server: &Value) -> Option<Value> {
+        let servers = vec![server.clone()];
+        let (_, freshness) = Self::build_metric_maps(&servers);
+        server
+            .get("id")
+            .and_then(Value::as_str)
+            .and_then(|id| freshness.get(id).cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{McpService, REMOTE_ENV_GUARD};
+    use crate::credentials::{CredentialManager, CredentialScope};
+    use crate::mcp_registry_source::{OfficialRegistrySource, PulseMcpSource};
+    use crate::mcp_tool_cache::ToolCacheBudget;
+    use crate::mcp_updates;
+    use httpmock::{Method, MockServer};
+    use serde_json::{Value, json};
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    use tauri::utils::acl::{capability::CapabilityFile, manifest::PermissionFile};
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn load_returns_empty_when_file_missing() {
+        let dir = tempdir().expect("create tempdir");
+        let path = dir.path().join("mcp-servers.json");
+        let service = McpService::new(path, None);
+
+        let servers = service.load().await.expect("load servers");
+        assert_eq!(servers.len(), 1);
+        let server = servers.first().expect("default server present");
+        assert_eq!(server.get("id"), Some(&json!("default-filesystem")));
+    }
+
+    #[tokio::test]
+    async fn claude_desktop_configs_are_imported_and_exported() {
+        let dir = tempdir().expect("create tempdir");
+        let credentials =
+            CredentialManager::new(dir.path().join("credentials.dat"), "master-secret");
+        let service = McpService::new(dir.path().join("mcp-servers.json"), None)
+            .with_credentials(credentials);
+        let claude_path = dir.path().join("claude_desktop_config.json");
+        std::fs::write(
+            &claude_path,
+            json!({
+                "globalShortcut": "Ctrl+Space",
+                "mcpServers": {
+                    "github": {
+                        "command": "npx",
+                        "args": ["-y", "@modelcontextprotocol/server-github"],
+                        "env": { "GITHUB_TOKEN": "ghp_secret" }
+                    }
+                }
+            })
+            .to_string(),
+        )
+        .expect("write claude config");
+
+        let report = service
+            .import_claude_config(&claude_path)
+            .await
+            .expect("import");
+        assert_eq!(report.added, vec!["claude-desktop-github"]);
+        let again = service
+            .import_claude_config(&claude_path)
+            .await
+            .expect("import again");
+        assert_eq!(again.replaced, vec!["claude-desktop-github"]);
+        let servers = service.load().await.expect("load");
+        assert_eq!(servers.len(), 2);
+        assert_eq!(
+            servers[1]["config"]["env"]["GITHUB_TOKEN"],
+            "credential:mcp-claude-desktop-github/env/GITHUB_TOKEN"
+        );
+
+        let export_path = dir.path().join("export").join("claude_desktop_config.json");
+        let export = service
+            .export_claude_config(&export_path, false)
+            .await
+            .expect("export");
+        assert_eq!(export.exported, 2);
+        let written: Value =
+            serde_json::from_str(&std::fs::read_to_string(&export_path).unwrap()).unwrap();
+        assert_eq!(written["mcpServers"]["github"]["env"]["GITHUB_TOKEN"], "");
+        assert_eq!(
+            written["mcpServers"]["Local Filesystem"]["args"][1],
+            "@modelcontextprotocol/server-filesystem"
+        );
+
+        service
+            .import_claude_config(&export_path)
+            .await
+            .expect("re-import export");
+        let servers = service.load().await.expect("load");
+        assert_eq!(
+            servers[1]["config"]["env"]["GITHUB_TOKEN"],
+            "credential:mcp-claude-desktop-github/env/GITHUB_TOKEN"
+        );
+
+        service
+            .export_claude_config(&claude_path, true)
+            .await
+            .expect("export with secrets");
+        let written: Value =
+            serde_json::from_str(&std::fs::read_to_string(&claude_path).unwrap()).unwrap();
+        assert_eq!(written["globalShortcut"], "Ctrl+Space");
+        assert_eq!(
+            written["mcpServers"]["github"]["env"]["GITHUB_TOKEN"],
+            "ghp_secret"
+        );
+    }
+
+    #[tokio::test]
+    async fn package_servers_are_checked_and_upgraded() {
+        let _guard = REMOTE_ENV_GUARD.lock().await;
+        let mock = MockServer::start_async().await;
+        mock.mock_async(|when, then| {
+            when.method(Method::GET)
+                .path("/npm/-/package/@acme%2Fweather/dist-tags");
+            then.status(200)
+                .json_body(json!({ "latest": "1.3.0", "next": "2.0.0-rc.1" }));
+        })
+        .await;
+        mock.mock_async(|when, then| {
+            when.method(Method::GET).path("/pypi/mcp-server-git/json");
+            then.status(200)
+                .json_body(json!({ "info": { "version": "0.6.2" } }));
+        })
+        .await;
+        unsafe {
+            std::env::set_var("TAURI_MCP_NPM_REGISTRY_URL", mock.url("/npm"));
+            std::env::set_var("TAURI_MCP_PYPI_URL", mock.base_url());
+        }
+
+        let dir = tempdir().expect("create tempdir");
+        let service = McpService::new(dir.path().join("mcp-servers.json"), None);
+        service
+            .save(vec![
+                json!({
+                    "id": "weather",
+                    "name": "Weather",
+                    "type": "custom",
+                    "enabled": true,
+                    "config": { "type": "custom", "command": "npx", "args": ["-y", "@acme/weather@1.2.0"] },
+                    "installCommand": { "command": "npx", "args": ["-y", "@acme/weather@1.2.0"] }
+                }),
+                json!({
+                    "id": "git",
+                    "name": "Git",
+                    "type": "custom",
+                    "enabled": true,
+                    "config": { "type": "custom", "command": "uvx", "args": ["mcp-server-git==0.6.2"] }
+                }),
+                json!({
+                    "id": "local",
+                    "name": "Local",
+                    "type": "custom",
+                    "enabled": true,
+                    "config": { "type": "custom", "command": "node", "args": ["server.js"] }
+                }),
+            ])
+            .await
+            .expect("save servers");
+
+        let updates = mcp_updates::check_updates(service.update_targets().await)
+            .await
+            .unwrap();
+        let summary: Vec<(&str, Option<&str>, Option<&str>, bool)> = updates
+            .iter()
+            .map(|update| {
+                (
+                    update.server_id.as_str(),
+                    update.installed_version.as_deref(),
+                    update.latest_version.as_deref(),
+                    update.update_available,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("weather", Some("1.2.0"), Some("1.3.0"), true),
+                ("git", Some("0.6.2"), Some("0.6.2"), false),
+            ]
+        );
+
+        let upgraded = service
+            .upgrade_server("weather", None)
+            .await
+            .expect("upgrade");
+        assert_eq!(
+            upgraded["config"]["args"],
+            json!(["-y", "@acme/weather@1.3.0"])
+        );
+        assert_eq!(
+            upgraded["installCommand"]["args"],
+            json!(["-y", "@acme/weather@1.3.0"])
+        );
+        let git = service
+            .upgrade_server("git", Some("0.7.0".to_string()))
+            .await
+            .expect("pin version");
+        assert_eq!(git["config"]["args"], json!(["mcp-server-git==0.7.0"]));
+        assert!(
+            service
+                .upgrade_server("git", Some("1; rm -rf ~".to_string()))
+                .await
+                .is_err()
+        );
+        assert!(service.upgrade_server("local", None).await.is_err());
+
+        unsafe {
+            std::env::remove_var("TAURI_MCP_NPM_REGISTRY_URL");
+            std::env::remove_var("TAURI_MCP_PYPI_URL");
+        }
+        let saved = std::fs::read_to_string(dir.path().join("mcp-servers.json")).expect("read");
+        assert!(saved.contains("@acme/weather@1.3.0"));
+    }
+
+    #[tokio::test]
+    async fn server_env_resolves_only_mcp_credentials() {
+        let dir = tempdir().expect("create tempdir");
+        let credentials =
+            CredentialManager::new(dir.path().join("credentials.dat"), "master-secret");
+        credentials
+            .store_with_scope("github", "token", "gh-secret", Some(CredentialScope::Mcp))
+            .await
+            .expect("store mcp credential");
+        credentials
+            .store_with_scope("openai", "api-key", "sk-secret", Some(CredentialScope::Llm))
+            .await
+            .expect("store llm credential");
+        let service = McpService::new(dir.path().join("mcp-servers.json"), None)
+            .with_credentials(credentials.clone());
 
-    fn build_server_freshness_value(server: &Value) -> Option<Value> {
-        let servers = vec![server.clone()];
-        let (_, freshness) = Self::build_metric_maps(&servers);
-        server
-            .get("id")
-            .and_then(Value::as_str)
-            .and_then(|id| freshness.get(id).cloned())
-    }
-}
+        let server = json!({
+            "id": "github",
+            "config": { "env": { "GITHUB_TOKEN": "${credential:github/token}", "DEBUG": "1" } }
+        });
+        let resolved = service
+            .resolve_server_env(&server)
+            .await
+            .expect("resolve env");
+        assert_eq!(
+            resolved["config"]["env"]["GITHUB_TOKEN"],
+            json!("gh-secret")
+        );
+        assert_eq!(resolved["config"]["env"]["DEBUG"], json!("1"));
 
-#[cfg(test)]
-mod tests {
-    use super::{McpService, REMOTE_ENV_GUARD};
-    use httpmock::{Method, MockServer};
-    use serde_json::{Value, json};
-    use std::path::PathBuf;
-    use std::str::FromStr;
-    use tauri::utils::acl::{capability::CapabilityFile, manifest::PermissionFile};
-    use tempfile::tempdir;
+        let exfiltrating = json!({
+            "id": "evil",
+            "config": { "env": { "KEY": "${credential:openai/api-key}" } }
+        });
+        assert!(service.resolve_server_env(&exfiltrating).await.is_err());
+        assert!(!credentials.access_log()[0].granted);
+    }
 
     #[tokio::test]
-    async fn load_returns_empty_when_file_missing() {
+    async fn plain_text_secrets_are_moved_to_the_credential_store() {
         let dir = tempdir().expect("create tempdir");
+        let credentials =
+            CredentialManager::new(dir.path().join("credentials.dat"), "master-secret");
+        credentials
+            .store_with_scope("github", "token", "gh-secret", Some(CredentialScope::Mcp))
+            .await
+            .expect("store mcp credential");
         let path = dir.path().join("mcp-servers.json");
-        let service = McpService::new(path, None);
+        let service = McpService::new(path.clone(), None).with_credentials(credentials);
 
-        let servers = service.load().await.expect("load servers");
-        assert_eq!(servers.len(), 1);
-        let server = servers.first().expect("default server present");
-        assert_eq!(server.get("id"), Some(&json!("default-filesystem")));
+        service
+            .save(vec![json!({
+                "id": "github",
+                "type": "custom",
+                "config": {
+                    "type": "custom",
+                    "command": "github-mcp",
+                    "env": {
+                        "API_KEY": "sk-plain",
+                        "GITHUB_TOKEN": "credential:github/token",
+                        "Authorization": "env-auth",
+                        "DEBUG": "1"
+                    },
+                    "headers": { "Authorization": "Bearer abc" }
+                }
+            })])
+            .await
+            .expect("save servers");
+
+        let written = std::fs::read_to_string(&path).expect("read servers file");
+        assert!(!written.contains("sk-plain"));
+        assert!(!written.contains("Bearer abc"));
+        let saved = service.load().await.expect("load servers");
+        let env = &saved[0]["config"]["env"];
+        assert_eq!(env["API_KEY"], json!("credential:mcp-github/env/API_KEY"));
+        assert_eq!(env["GITHUB_TOKEN"], json!("credential:github/token"));
+        assert_eq!(env["DEBUG"], json!("1"));
+
+        let resolved = service
+            .resolve_server_env(&saved[0])
+            .await
+            .expect("resolve env");
+        assert_eq!(resolved["config"]["env"]["API_KEY"], json!("sk-plain"));
+        assert_eq!(
+            resolved["config"]["env"]["GITHUB_TOKEN"],
+            json!("gh-secret")
+        );
+        assert_eq!(
+            resolved["config"]["headers"]["Authorization"],
+            json!("Bearer abc")
+        );
+        assert_eq!(
+            resolved["config"]["env"]["Authorization"],
+            json!("env-auth")
+        );
+
+        service.save(Vec::new()).await.expect("delete server");
+        let credentials = service.credentials.as_ref().unwrap();
+        assert_eq!(
+            credentials.get("mcp-github", "env/API_KEY").await.unwrap(),
+            None
+        );
+        assert!(credentials.get("github", "token").await.unwrap().is_some());
     }
 
     #[tokio::test]
@@ -1780,54 +4096,728 @@ mod tests {
             "updatedAt": "2024-01-01T00:00:00.000Z"
         });
 
-        let result = service
-            .test_connection(&server)
+        let result = service
+            .test_connection(&server)
+            .await
+            .expect("test connection");
+        assert!(result.success);
+        assert!(result.error.is_none());
+        assert!(!result.tools.is_empty());
+    }
+
+    #[tokio::test]
+    async fn custom_servers_support_pretty_json_streams() {
+        let dir = tempdir().expect("create tempdir");
+        let path = dir.path().join("mcp-servers.json");
+        let service = McpService::new(path, None);
+
+        let script_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/mock-mcp-server-pretty.js");
+
+        let server = json!({
+            "id": "custom-pretty",
+            "name": "Pretty Custom",
+            "type": "custom",
+            "status": "disconnected",
+            "enabled": true,
+            "config": {
+                "type": "custom",
+                "command": "node",
+                "args": [script_path.to_string_lossy()],
+            },
+            "createdAt": "2024-01-01T00:00:00.000Z",
+            "updatedAt": "2024-01-01T00:00:00.000Z"
+        });
+
+        let result = service
+            .test_connection(&server)
+            .await
+            .expect("test connection");
+
+        assert!(result.success);
+        assert!(result.error.is_none());
+        assert_eq!(
+            result
+                .tools
+                .first()
+                .and_then(|tool| tool.get("name"))
+                .and_then(Value::as_str),
+            Some("prettyTool")
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn shutdown_kills_processes_left_by_custom_servers() {
+        let dir = tempdir().expect("create tempdir");
+        let service = McpService::new(dir.path().join("mcp-servers.json"), None);
+        let script_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/mock-mcp-server-with-child.mjs");
+        let pid_path = dir.path().join("child.pid");
+        service
+            .save(vec![json!({
+                "id": "spawner",
+                "name": "Spawner",
+                "type": "custom",
+                "enabled": true,
+                "config": {
+                    "type": "custom",
+                    "command": "node",
+                    "args": [script_path.to_string_lossy(), pid_path.to_string_lossy()],
+                }
+            })])
+            .await
+            .expect("persist servers");
+
+        let result = service.connect("spawner").await.expect("connect");
+        assert!(result.success, "{:?}", result.error);
+        let child_pid: i32 = std::fs::read_to_string(&pid_path)
+            .expect("child pid")
+            .trim()
+            .parse()
+            .expect("numeric pid");
+        // SAFETY: signal 0 only checks that the process exists. A zombie
+        // waiting on whoever adopted it counts as gone.
+        let alive = || {
+            let exists = unsafe { libc::kill(child_pid, 0) } == 0;
+            exists
+                && !std::fs::read_to_string(format!("/proc/{child_pid}/stat"))
+                    .is_ok_and(|stat| stat.contains(") Z "))
+        };
+        assert!(alive());
+
+        service.shutdown().await;
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while alive() && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert!(!alive(), "the server's child outlived the app");
+    }
+
+    /// Reads one HTTP/1.1 request, returning its head and body.
+    async fn read_http_request(stream: &mut tokio::net::TcpStream) -> Option<(String, String)> {
+        use tokio::io::AsyncReadExt;
+
+        let mut buffer = Vec::new();
+        let mut chunk = [0u8; 4096];
+        loop {
+            let read = stream.read(&mut chunk).await.ok()?;
+            if read == 0 {
+                return None;
+            }
+            buffer.extend_from_slice(&chunk[..read]);
+            let text = String::from_utf8_lossy(&buffer).to_string();
+            let Some(end) = text.find("\r\n\r\n") else {
+                continue;
+            };
+            let head = text[..end].to_string();
+            let length = head
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            if buffer.len() >= end + 4 + length {
+                let body = String::from_utf8_lossy(&buffer[end + 4..end + 4 + length]);
+                return Some((head, body.to_string()));
+            }
+        }
+    }
+
+    /// A minimal MCP server speaking the SSE transport: `GET /sse` opens the
+    /// event stream and `POST /message` carries requests, whose responses
+    /// are pushed down the stream. `MockServer` can't hold a stream open, so
+    /// this one is served by hand.
+    async fn spawn_sse_mcp_server() -> String {
+        use tokio::io::AsyncWriteExt;
+        use tokio::net::TcpListener;
+        use tokio::sync::mpsc;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (events, pending) = mpsc::unbounded_channel::<Value>();
+        let mut pending = Some(pending);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let Some((head, body)) = read_http_request(&mut stream).await else {
+                    continue;
+                };
+                if head.starts_with("GET /sse") {
+                    let authorized = head
+                        .to_ascii_lowercase()
+                        .contains("x-api-key: remote-secret");
+                    let Some(mut pending) = pending.take().filter(|_| authorized) else {
+                        let _ = stream
+                            .write_all(b"HTTP/1.1 401 Unauthorized\r\ncontent-length: 0\r\n\r\n")
+                            .await;
+                        continue;
+                    };
+                    tokio::spawn(async move {
+                        let _ = stream
+                            .write_all(
+                                b"HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncache-control: no-cache\r\n\r\nevent: endpoint\ndata: /message\n\n",
+                            )
+                            .await;
+                        while let Some(event) = pending.recv().await {
+                            let frame = format!("event: message\ndata: {event}\n\n");
+                            if stream.write_all(frame.as_bytes()).await.is_err() {
+                                break;
+                            }
+                        }
+                    });
+                    continue;
+                }
+
+                let _ = stream
+                    .write_all(
+                        b"HTTP/1.1 202 Accepted\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                    )
+                    .await;
+                let Ok(request) = serde_json::from_str::<Value>(&body) else {
+                    continue;
+                };
+                let result = match request.get("method").and_then(Value::as_str) {
+                    Some("initialize") => json!({
+                        "protocolVersion": "2024-11-05",
+                        "capabilities": { "tools": {} },
+                        "serverInfo": { "name": "remote-mock", "version": "1.0.0" }
+                    }),
+                    Some("tools/list") => json!({
+                        "tools": [{
+                            "name": "remoteTool",
+                            "description": "Served over SSE",
+                            "inputSchema": { "type": "object", "properties": {} }
+                        }]
+                    }),
+                    _ => continue,
+                };
+                let _ = events.send(json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "result": result
+                }));
+            }
+        });
+        format!("http://{address}/sse")
+    }
+
+    #[tokio::test]
+    async fn sse_servers_connect_with_configured_headers() {
+        let dir = tempdir().expect("create tempdir");
+        let credentials =
+            CredentialManager::new(dir.path().join("credentials.dat"), "master-secret");
+        credentials
+            .store_with_scope("remote", "key", "remote-secret", Some(CredentialScope::Mcp))
+            .await
+            .expect("store mcp credential");
+        let service = McpService::new(dir.path().join("mcp-servers.json"), None)
+            .with_credentials(credentials);
+        let url = spawn_sse_mcp_server().await;
+
+        let server = json!({
+            "id": "remote",
+            "name": "Remote",
+            "type": "sse",
+            "status": "disconnected",
+            "enabled": true,
+            "config": {
+                "type": "sse",
+                "url": url,
+                "headers": { "X-Api-Key": "${credential:remote/key}" }
+            },
+            "createdAt": "2024-01-01T00:00:00.000Z",
+            "updatedAt": "2024-01-01T00:00:00.000Z"
+        });
+        let result = tokio::time::timeout(
+            std::time::Duration::from_secs(10),
+            service.test_connection(&server),
+        )
+        .await
+        .expect("connection finishes")
+        .expect("test connection");
+        assert!(result.success);
+        assert_eq!(
+            result
+                .tools
+                .first()
+                .and_then(|tool| tool.get("name"))
+                .and_then(Value::as_str),
+            Some("remoteTool")
+        );
+
+        let missing_url = json!({ "id": "remote", "type": "http", "config": { "type": "http" } });
+        assert!(service.test_connection(&missing_url).await.is_err());
+    }
+
+    /// Request ids the streamable HTTP mock answers. Each connection numbers
+    /// its requests from zero.
+    const MOCK_REQUEST_IDS: std::ops::Range<u64> = 0..16;
+    /// Tool call messages the streamable HTTP mock echoes back.
+    const MOCK_ECHO_MESSAGES: [&str; 7] = ["one", "two", "", "lookup", "other", "first", "second"];
+
+    /// Mocks an MCP server speaking the streamable HTTP transport on `/mcp`.
+    /// When `session` is set it is handed out on initialize and required on
+    /// every later request. Returns the initialize and session delete mocks.
+    async fn mock_streamable_mcp_server<'a>(
+        mock: &'a MockServer,
+        session: Option<&'static str>,
+    ) -> (httpmock::Mock<'a>, httpmock::Mock<'a>) {
+        let with_session = |when: httpmock::When| match session {
+            Some(id) => when.header("mcp-session-id", id),
+            None => when,
+        };
+        let rpc = |id: u64, result: Value| json!({ "jsonrpc": "2.0", "id": id, "result": result });
+
+        let initialize = mock
+            .mock_async(|when, then| {
+                when.method(Method::POST)
+                    .path("/mcp")
+                    .json_body_partial(r#"{"id":0,"method":"initialize"}"#);
+                let then = then
+                    .status(200)
+                    .header("content-type", "application/json")
+                    .json_body(rpc(
+                        0,
+                        json!({
+                            "protocolVersion": "2025-03-26",
+                            "capabilities": { "tools": {} },
+                            "serverInfo": { "name": "streamable-mock", "version": "1.0.0" }
+                        }),
+                    ));
+                if let Some(id) = session {
+                    then.header("mcp-session-id", id);
+                }
+            })
+            .await;
+
+        for id in MOCK_REQUEST_IDS {
+            let tools = rpc(
+                id,
+                json!({
+                    "tools": [{
+                        "name": "streamedTool",
+                        "description": "Served over streamable HTTP",
+                        "inputSchema": {
+                            "type": "object",
+                            "required": ["message"],
+                            "properties": { "message": { "type": "string" } }
+                        }
+                    }]
+                }),
+            );
+            mock.mock_async(|when, then| {
+                with_session(when)
+                    .method(Method::POST)
+                    .path("/mcp")
+                    .json_body_partial(json!({ "id": id, "method": "tools/list" }).to_string());
+                then.status(200)
+                    .header("content-type", "text/event-stream")
+                    .body(format!("id: 1\ndata: {tools}\n\n"));
+            })
+            .await;
+            mock.mock_async(|when, then| {
+                with_session(when)
+                    .method(Method::POST)
+                    .path("/mcp")
+                    .json_body_partial(json!({ "id": id, "method": "ping" }).to_string());
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .json_body(rpc(id, json!({})));
+            })
+            .await;
+            for message in MOCK_ECHO_MESSAGES {
+                mock.mock_async(|when, then| {
+                    with_session(when)
+                        .method(Method::POST)
+                        .path("/mcp")
+                        .json_body_partial(
+                            json!({
+                                "id": id,
+                                "method": "tools/call",
+                                "params": { "arguments": { "message": message } }
+                            })
+                            .to_string(),
+                        );
+                    then.status(200)
+                        .header("content-type", "application/json")
+                        .json_body(rpc(
+                            id,
+                            json!({
+                                "content": [{ "type": "text", "text": format!("echo: {message}") }],
+                                "structuredContent": { "echoed": message },
+                                "isError": message.is_empty()
+                            }),
+                        ));
+                })
+                .await;
+            }
+        }
+
+        mock.mock_async(|when, then| {
+            with_session(when).method(Method::POST).path("/mcp");
+            then.status(202);
+        })
+        .await;
+        mock.mock_async(|when, then| {
+            when.method(Method::POST).path("/mcp");
+            then.status(404);
+        })
+        .await;
+        mock.mock_async(|when, then| {
+            when.method(Method::GET).path("/mcp");
+            then.status(405);
+        })
+        .await;
+        let delete = mock
+            .mock_async(|when, then| {
+                with_session(when).method(Method::DELETE).path("/mcp");
+                then.status(200);
+            })
+            .await;
+        (initialize, delete)
+    }
+
+    /// Whether `mock` is hit within five seconds.
+    async fn mock_hit_soon(mock: &httpmock::Mock<'_>) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while mock.hits_async().await == 0 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        mock.hits_async().await > 0
+    }
+
+    #[tokio::test]
+    async fn streamable_http_servers_track_their_session() {
+        let dir = tempdir().expect("create tempdir");
+        let service = McpService::new(dir.path().join("mcp-servers.json"), None);
+        let mock = MockServer::start_async().await;
+        let (_, delete) = mock_streamable_mcp_server(&mock, Some("session-1")).await;
+        let url = mock.url("/mcp");
+
+        let server = json!({
+            "id": "streamable",
+            "name": "Streamable",
+            "type": "http",
+            "status": "disconnected",
+            "enabled": true,
+            "config": {
+                "type": "http",
+                "url": url,
+                "requireSession": true,
+                "reconnect": { "maxRetries": 1, "baseDelayMs": 10 }
+            },
+            "createdAt": "2024-01-01T00:00:00.000Z",
+            "updatedAt": "2024-01-01T00:00:00.000Z"
+        });
+        let result = tokio::time::timeout(
+            std::time::Duration::from_secs(10),
+            service.test_connection(&server),
+        )
+        .await
+        .expect("connection finishes")
+        .expect("test connection");
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(
+            result
+                .tools
+                .first()
+                .and_then(|tool| tool.get("name"))
+                .and_then(Value::as_str),
+            Some("streamedTool")
+        );
+        assert!(mock_hit_soon(&delete).await, "session deleted on close");
+
+        let stateless_mock = MockServer::start_async().await;
+        mock_streamable_mcp_server(&stateless_mock, None).await;
+        let mut stateless = server.clone();
+        stateless["config"]["url"] = json!(stateless_mock.url("/mcp"));
+        assert!(service.test_connection(&stateless).await.is_err());
+        stateless["config"]["requireSession"] = json!(false);
+        let result = service
+            .test_connection(&stateless)
+            .await
+            .expect("stateless connection");
+        assert!(result.success);
+    }
+
+    #[tokio::test]
+    async fn connected_servers_keep_their_session_for_tool_calls() {
+        let dir = tempdir().expect("create tempdir");
+        let service = McpService::new(dir.path().join("mcp-servers.json"), None);
+        let mock = MockServer::start_async().await;
+        let (initialize, delete) = mock_streamable_mcp_server(&mock, Some("session-2")).await;
+        let url = mock.url("/mcp");
+        service
+            .save(vec![json!({
+                "id": "persistent",
+                "name": "Persistent",
+                "type": "http",
+                "status": "disconnected",
+                "enabled": true,
+                "config": { "type": "http", "url": url },
+                "createdAt": "2024-01-01T00:00:00.000Z",
+                "updatedAt": "2024-01-01T00:00:00.000Z"
+            })])
+            .await
+            .expect("persist server");
+
+        let first = service
+            .call_tool(
+                "persistent",
+                "streamedTool",
+                Some(json!({ "message": "one" })),
+                None,
+            )
+            .await
+            .expect("call connects on demand");
+        assert_eq!(first.content[0]["text"], json!("echo: one"));
+        assert_eq!(first.structured_content, Some(json!({ "echoed": "one" })));
+        assert!(!first.is_error);
+        // A tool list missing from the cache is fetched again mid-call.
+        service.connections.lock().await.tools.remove("persistent");
+        let second = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            service.call_tool(
+                "persistent",
+                "streamedTool",
+                Some(json!({ "message": "two" })),
+                None,
+            ),
+        )
+        .await
+        .expect("refetching tools does not deadlock")
+        .expect("call reuses the session");
+        assert_eq!(second.content[0]["text"], json!("echo: two"));
+        assert!(
+            service
+                .call_tool(
+                    "persistent",
+                    "streamedTool",
+                    Some(json!({ "message": "" })),
+                    None
+                )
+                .await
+                .expect("tool errors are results")
+                .is_error
+        );
+        service
+            .refresh_tools("persistent")
+            .await
+            .expect("refresh through the session");
+        assert_eq!(initialize.hits_async().await, 1);
+        assert!(
+            service
+                .call_tool("persistent", "streamedTool", Some(json!(["bad"])), None)
+                .await
+                .is_err()
+        );
+        let invalid = service
+            .call_tool(
+                "persistent",
+                "streamedTool",
+                Some(json!({ "message": 1 })),
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(invalid.contains("/message: expected string"), "{invalid}");
+        assert!(
+            service
+                .call_tool("persistent", "missingTool", None, None)
+                .await
+                .unwrap_err()
+                .contains("no tool named missingTool")
+        );
+
+        assert!(service.disconnect("persistent").await.expect("disconnect"));
+        assert!(
+            mock_hit_soon(&delete).await,
+            "session deleted on disconnect"
+        );
+        assert!(service.live_peer("persistent").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn opted_in_servers_reuse_tool_results() {
+        let dir = tempdir().expect("create tempdir");
+        let service = McpService::new(dir.path().join("mcp-servers.json"), None);
+        let mock = MockServer::start_async().await;
+        mock_streamable_mcp_server(&mock, None).await;
+        let url = mock.url("/mcp");
+        service
+            .save(vec![json!({
+                "id": "docs",
+                "name": "Docs",
+                "type": "http",
+                "enabled": true,
+                "config": { "type": "http", "url": url, "resultCache": { "ttlSeconds": 60 } }
+            })])
+            .await
+            .expect("persist server");
+
+        let call = |message: &'static str| {
+            service.call_tool(
+                "docs",
+                "streamedTool",
+                Some(json!({ "message": message })),
+                None,
+            )
+        };
+        assert!(!call("lookup").await.expect("first call").cached);
+        let repeated = call("lookup").await.expect("repeated call");
+        assert!(repeated.cached);
+        assert_eq!(repeated.content[0]["text"], json!("echo: lookup"));
+        assert!(!call("other").await.expect("other args").cached);
+
+        assert!(call("").await.expect("tool error").is_error);
+        assert!(!call("").await.expect("tool error again").cached);
+
+        service.disconnect("docs").await.expect("disconnect");
+        assert!(!call("lookup").await.expect("after disconnect").cached);
+        let stats = service.memory_stats().await.expect("stats");
+        assert_eq!(stats["toolResultCache"]["hits"], json!(1));
+    }
+
+    #[tokio::test]
+    async fn rate_limited_servers_reject_calls_past_the_max_wait() {
+        let dir = tempdir().expect("create tempdir");
+        let service = McpService::new(dir.path().join("mcp-servers.json"), None);
+        let mock = MockServer::start_async().await;
+        mock_streamable_mcp_server(&mock, None).await;
+        let url = mock.url("/mcp");
+        service
+            .save(vec![json!({
+                "id": "metered",
+                "name": "Metered",
+                "type": "http",
+                "enabled": true,
+                "config": {
+                    "type": "http",
+                    "url": url,
+                    "rateLimit": { "requestsPerMinute": 1, "maxWaitSeconds": 0 },
+                    "resultCache": { "ttlSeconds": 60 }
+                }
+            })])
             .await
-            .expect("test connection");
-        assert!(result.success);
-        assert!(result.error.is_none());
-        assert!(!result.tools.is_empty());
+            .expect("persist server");
+
+        let call = |message: &'static str| {
+            service.call_tool(
+                "metered",
+                "streamedTool",
+                Some(json!({ "message": message })),
+                None,
+            )
+        };
+        call("first").await.expect("first call");
+        assert!(call("first").await.expect("cached call").cached);
+        let error = call("second").await.expect_err("rate limited");
+        assert!(error.contains("rate limited to 1 requests per minute"));
     }
 
     #[tokio::test]
-    async fn custom_servers_support_pretty_json_streams() {
+    async fn dropped_sessions_are_queued_for_reconnect() {
         let dir = tempdir().expect("create tempdir");
-        let path = dir.path().join("mcp-servers.json");
-        let service = McpService::new(path, None);
+        let service = McpService::new(dir.path().join("mcp-servers.json"), None);
+        let mock = MockServer::start_async().await;
+        let (initialize, _) = mock_streamable_mcp_server(&mock, Some("session-3")).await;
+        let url = mock.url("/mcp");
+        service
+            .save(vec![json!({
+                "id": "flaky",
+                "name": "Flaky",
+                "type": "http",
+                "enabled": true,
+                "config": { "type": "http", "url": url }
+            })])
+            .await
+            .expect("persist server");
+        service.connect("flaky").await.expect("connect");
+        assert!(service.take_dropped_sessions().await.is_empty());
 
-        let script_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-            .join("tests/fixtures/mock-mcp-server-pretty.js");
+        let token = service.connections.lock().await.clients["flaky"].cancellation_token();
+        token.cancel();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
 
-        let server = json!({
-            "id": "custom-pretty",
-            "name": "Pretty Custom",
-            "type": "custom",
-            "status": "disconnected",
-            "enabled": true,
-            "config": {
-                "type": "custom",
-                "command": "node",
-                "args": [script_path.to_string_lossy()],
-            },
-            "createdAt": "2024-01-01T00:00:00.000Z",
-            "updatedAt": "2024-01-01T00:00:00.000Z"
-        });
+        assert_eq!(service.take_dropped_sessions().await, vec!["flaky"]);
+        assert!(service.take_dropped_sessions().await.is_empty());
+        assert!(
+            service
+                .connections
+                .lock()
+                .await
+                .tools
+                .get("flaky")
+                .is_none()
+        );
+        assert!(service.reconnect_pending("flaky").await);
 
-        let result = service
-            .test_connection(&server)
+        service.connect("flaky").await.expect("reconnect");
+        assert!(!service.reconnect_pending("flaky").await);
+        assert_eq!(initialize.hits_async().await, 2);
+        assert!(service.live_peer("flaky").await.is_some());
+
+        service
+            .connections
+            .lock()
             .await
-            .expect("test connection");
+            .reconnecting
+            .insert("flaky".to_string());
+        service.disconnect("flaky").await.expect("disconnect");
+        assert!(!service.reconnect_pending("flaky").await);
 
-        assert!(result.success);
-        assert!(result.error.is_none());
-        assert_eq!(
-            result
-                .tools
-                .first()
-                .and_then(|tool| tool.get("name"))
-                .and_then(Value::as_str),
-            Some("prettyTool")
-        );
+        let delays: Vec<u64> = (1..=8)
+            .map(|attempt| super::mcp_reconnect_delay(attempt).as_secs())
+            .collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 16, 32, 60, 60]);
+    }
+
+    #[tokio::test]
+    async fn heartbeats_track_latency_and_failures() {
+        let dir = tempdir().expect("create tempdir");
+        let service = McpService::new(dir.path().join("mcp-servers.json"), None);
+        let mock = MockServer::start_async().await;
+        mock_streamable_mcp_server(&mock, Some("session-4")).await;
+        let url = mock.url("/mcp");
+        service
+            .save(vec![json!({
+                "id": "pinged",
+                "name": "Pinged",
+                "type": "http",
+                "enabled": true,
+                "config": { "type": "http", "url": url }
+            })])
+            .await
+            .expect("persist server");
+        service.connect("pinged").await.expect("connect");
+
+        assert!(service.prepare_health_check().await.run().await.is_empty());
+        let health = service.health().await;
+        assert_eq!(health.len(), 1);
+        assert!(health[0].healthy);
+        assert_eq!(health[0].checks, 1);
+        assert!(health[0].latency_ms.is_some());
+
+        let token = service.connections.lock().await.clients["pinged"].cancellation_token();
+        token.cancel();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let changed = service.prepare_health_check().await.run().await;
+        assert_eq!(changed.len(), 1);
+        assert!(!changed[0].healthy);
+        assert_eq!(changed[0].consecutive_failures, 1);
+        assert!(changed[0].last_error.is_some());
+        assert!(service.prepare_health_check().await.run().await.is_empty());
+        service.prepare_health_check().await.run().await;
+        assert!(service.connections.lock().await.clients.is_empty());
+        assert_eq!(service.take_dropped_sessions().await, vec!["pinged"]);
+        let health = service.health().await;
+        assert_eq!((health[0].checks, health[0].failures), (4, 3));
+
+        service.disconnect("pinged").await.expect("disconnect");
+        assert!(service.health().await.is_empty());
     }
 
     #[tokio::test]
@@ -1897,9 +4887,166 @@ mod tests {
         assert!(tools_after.is_empty());
     }
 
+    #[tokio::test]
+    async fn activating_a_group_connects_exactly_its_servers() {
+        let dir = tempdir().expect("create tempdir");
+        let service = McpService::new(dir.path().join("mcp-servers.json"), None);
+        let server = |id: &str| {
+            json!({
+                "id": id,
+                "name": id,
+                "type": "filesystem",
+                "enabled": true,
+                "config": {
+                    "type": "filesystem",
+                    "rootPath": dir.path().to_string_lossy().to_string()
+                }
+            })
+        };
+        service
+            .save(vec![server("docs"), server("notes"), server("scratch")])
+            .await
+            .expect("persist servers");
+
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        service
+            .save_group("Research", ids(&["docs", "notes"]))
+            .await
+            .expect("save research");
+        service
+            .save_group("Scratch", ids(&["scratch"]))
+            .await
+            .expect("save scratch");
+        assert_eq!(service.list_groups().await.len(), 2);
+
+        let research = service.activate_group("research").await.expect("activate");
+        assert_eq!(research.group, "Research");
+        assert_eq!(research.connected, ids(&["docs", "notes"]));
+        assert!(research.disconnected.is_empty() && research.failed.is_empty());
+
+        let scratch = service.activate_group("Scratch").await.expect("activate");
+        assert_eq!(scratch.connected, ids(&["scratch"]));
+        assert_eq!(scratch.disconnected, ids(&["docs", "notes"]));
+        let enabled: Vec<_> = service
+            .load()
+            .await
+            .expect("load")
+            .iter()
+            .map(|server| server["enabled"].as_bool().unwrap_or(true))
+            .collect();
+        assert_eq!(enabled, vec![true, true, true]);
+        assert_eq!(service.startup_servers().await.unwrap(), ids(&["scratch"]));
+
+        let cleared = service.deactivate_group().await.expect("deactivate");
+        assert_eq!(cleared.group, "Scratch");
+        assert_eq!(cleared.connected, ids(&["docs", "notes", "scratch"]));
+        assert!(service.active_group().await.is_none());
+
+        let mut servers = service.load().await.expect("load");
+        servers[1]["enabled"] = json!(false);
+        service.save(servers).await.expect("disable notes");
+        let agent_servers = service.group_servers("Research").await.expect("servers");
+        assert_eq!(agent_servers.len(), 1);
+        assert!(service.activate_group("missing").await.is_err());
+
+        service.activate_group("Scratch").await.expect("activate");
+        assert!(service.delete_group("Scratch").await.expect("delete"));
+        assert!(service.active_group().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn evicted_tool_cache_is_refetched_on_demand() {
+        let dir = tempdir().expect("create tempdir");
+        let path = dir.path().join("mcp-servers.json");
+        let service = McpService::new(path, None);
+        service
+            .set_tool_cache_budget(ToolCacheBudget {
+                max_entries: 1,
+                ..ToolCacheBudget::default()
+            })
+            .await;
+
+        let servers = ["fs-a", "fs-b"]
+            .iter()
+            .map(|id| {
+                json!({
+                    "id": id,
+                    "name": id,
+                    "type": "filesystem",
+                    "enabled": true,
+                    "config": {
+                        "type": "filesystem",
+                        "rootPath": dir.path().to_string_lossy().to_string()
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        service.save(servers).await.expect("persist servers");
+
+        assert!(service.connect("fs-a").await.expect("connect a").success);
+        assert!(service.connect("fs-b").await.expect("connect b").success);
+
+        let stats = service.memory_stats().await.expect("memory stats");
+        assert_eq!(stats["toolCache"]["entries"], json!(1));
+        assert_eq!(stats["toolCache"]["evictions"], json!(1));
+
+        let tools = service
+            .connected_tools("fs-a")
+            .await
+            .expect("refetch tools");
+        assert!(!tools.is_empty());
+        assert!(service.connected_tools("unknown").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn disconnected_servers_serve_persisted_tool_catalog() {
+        let dir = tempdir().expect("create tempdir");
+        let path = dir.path().join("mcp-servers.json");
+        let registry_path = dir.path().join("mcp-registry.sqlite");
+        let service = McpService::new(path.clone(), Some(registry_path.clone()));
+
+        let server = json!({
+            "id": "fs-offline",
+            "name": "Offline Files",
+            "type": "filesystem",
+            "enabled": true,
+            "config": {
+                "type": "filesystem",
+                "rootPath": dir.path().to_string_lossy().to_string()
+            }
+        });
+        service.save(vec![server]).await.expect("persist server");
+        assert!(
+            service
+                .connected_tools("fs-offline")
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let connection = service.connect("fs-offline").await.expect("connect");
+        assert!(connection.success);
+        service.disconnect("fs-offline").await.expect("disconnect");
+
+        let restarted = McpService::new(path, Some(registry_path));
+        let tools = restarted
+            .connected_tools("fs-offline")
+            .await
+            .expect("offline tools");
+        assert_eq!(tools.len(), connection.tools.len());
+        assert_eq!(tools[0]["cached"], json!(true));
+        assert_eq!(tools[0]["offline"], json!(true));
+
+        let catalog = restarted.tool_catalog().await.expect("tool catalog");
+        assert_eq!(catalog.len(), 1);
+        assert_eq!(catalog[0]["serverId"], json!("fs-offline"));
+        assert_eq!(catalog[0]["connected"], json!(false));
+        assert_eq!(catalog[0]["cached"], json!(true));
+    }
+
     #[tokio::test]
     async fn registry_features_return_explicit_errors() {
-        let _guard = REMOTE_ENV_GUARD.lock().expect("remote env guard poisoned");
+        let _guard = REMOTE_ENV_GUARD.lock().await;
         unsafe {
             std::env::set_var("TAURI_MCP_REGISTRY_REMOTE", "false");
         }
@@ -1939,6 +5086,50 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn registry_stats_survive_a_restart() {
+        let _guard = REMOTE_ENV_GUARD.lock().await;
+        unsafe {
+            std::env::set_var("TAURI_MCP_REGISTRY_REMOTE", "false");
+        }
+        let dir = tempdir().expect("create tempdir");
+        let path = dir.path().join("mcp-servers.json");
+        let registry_path = dir.path().join("mcp-registry.sqlite");
+        let service = McpService::new(path.clone(), Some(registry_path.clone()));
+
+        service.search_registry(&Value::Null).await.expect("search");
+        service.search_registry(&Value::Null).await.expect("search");
+        service
+            .install_from_registry("filesystem-local", None, None)
+            .await
+            .expect("install");
+        let before = service.cache_stats().await.expect("cache stats");
+        assert_eq!(before["totalSearches"], json!(2));
+        assert_eq!(before["installations"], json!(1));
+        drop(service);
+
+        let restarted = McpService::new(path, Some(registry_path));
+        let after = restarted.cache_stats().await.expect("cache stats");
+        for key in [
+            "totalSearches",
+            "installations",
+            "cacheHitRate",
+            "averageResponseTime",
+        ] {
+            assert_eq!(after[key], before[key], "{key}");
+        }
+        assert!(after["newestEntry"].is_string());
+
+        restarted.clear_registry_cache().await.expect("clear");
+        let cleared = McpService::new(
+            dir.path().join("mcp-servers.json"),
+            Some(dir.path().join("mcp-registry.sqlite")),
+        );
+        let stats = cleared.cache_stats().await.expect("cache stats");
+        assert_eq!(stats["installations"], json!(1));
+        assert_eq!(stats["oldestEntry"], Value::Null);
+    }
+
     #[tokio::test]
     async fn install_payload_populates_missing_command_fields() {
         let dir = tempdir().expect("create tempdir");
@@ -1983,9 +5174,125 @@ mod tests {
         assert_eq!(args[0].as_str(), Some("script.js"));
     }
 
+    #[tokio::test]
+    async fn registry_installs_wait_for_their_variables() {
+        let dir = tempdir().expect("create tempdir");
+        let service = McpService::new(dir.path().join("mcp-servers.json"), None);
+
+        let server_detail = json!({
+            "id": "acme-files",
+            "name": "Acme Files",
+            "template": {
+                "type": "custom",
+                "config": {
+                    "type": "custom",
+                    "command": "npx",
+                    "args": ["-y", "@acme/files", "${ROOT_DIR}"],
+                    "env": { "ACME_REGION": "<your-region>" }
+                }
+            }
+        });
+        let server = service
+            .convert_registry_server_to_config(&server_detail, None)
+            .expect("config value");
+        let server_id = server["id"].as_str().expect("id").to_string();
+        assert_eq!(
+            server["installVariables"],
+            json!([
+                { "name": "ACME_REGION", "required": true, "secret": false },
+                { "name": "ROOT_DIR", "required": true, "secret": false }
+            ])
+        );
+        service.save(vec![server]).await.expect("save");
+
+        let error = service.connect(&server_id).await.unwrap_err();
+        assert!(error.contains("ACME_REGION, ROOT_DIR"), "{error}");
+
+        let partial = HashMap::from([("ROOT_DIR".to_string(), "/srv".to_string())]);
+        let error = service
+            .resolve_install_variables(&server_id, partial)
+            .await
+            .unwrap_err();
+        assert_eq!(error, "Missing install variables: ACME_REGION");
+
+        let values = HashMap::from([
+            ("ROOT_DIR".to_string(), "/srv".to_string()),
+            ("ACME_REGION".to_string(), "eu-west".to_string()),
+        ]);
+        let resolved = service
+            .resolve_install_variables(&server_id, values)
+            .await
+            .expect("resolve");
+        assert!(resolved.get("installVariables").is_none());
+        assert_eq!(resolved["config"]["args"][2], "/srv");
+        assert_eq!(resolved["config"]["env"]["ACME_REGION"], "eu-west");
+        assert_eq!(service.load().await.expect("load")[0], resolved);
+    }
+
+    #[tokio::test]
+    async fn secret_install_variables_are_stored_as_credentials() {
+        let dir = tempdir().expect("create tempdir");
+        let credentials =
+            CredentialManager::new(dir.path().join("credentials.dat"), "master-secret");
+        let path = dir.path().join("mcp-servers.json");
+        let service = McpService::new(path.clone(), None).with_credentials(credentials);
+
+        let server_detail = json!({
+            "id": "acme-db",
+            "name": "Acme DB",
+            "template": {
+                "type": "custom",
+                "config": {
+                    "type": "custom",
+                    "command": "npx",
+                    "args": ["-y", "@acme/db", "--dsn", "postgres://${DB_LOGIN}@db"],
+                    "env": { "ACME_CREDS": "" }
+                }
+            },
+            "variables": [
+                { "name": "ACME_CREDS", "secret": true },
+                { "name": "DB_LOGIN", "secret": true }
+            ]
+        });
+        let server = service
+            .convert_registry_server_to_config(&server_detail, None)
+            .expect("config value");
+        let server_id = server["id"].as_str().expect("id").to_string();
+        assert_eq!(
+            server["installVariables"],
+            json!([
+                { "name": "ACME_CREDS", "required": true, "secret": true },
+                { "name": "DB_LOGIN", "required": true, "secret": true }
+            ])
+        );
+        service.save(vec![server]).await.expect("save");
+
+        let values = HashMap::from([
+            ("ACME_CREDS".to_string(), "creds-1".to_string()),
+            ("DB_LOGIN".to_string(), "admin:pw".to_string()),
+        ]);
+        let resolved = service
+            .resolve_install_variables(&server_id, values)
+            .await
+            .expect("resolve");
+        let written = std::fs::read_to_string(&path).expect("read servers file");
+        assert!(!written.contains("creds-1"));
+        assert!(!written.contains("admin:pw"));
+
+        let spawned = service
+            .resolve_server_env(&resolved)
+            .await
+            .expect("resolve env");
+        assert_eq!(spawned["config"]["env"]["ACME_CREDS"], json!("creds-1"));
+        assert_eq!(
+            spawned["config"]["args"][3],
+            json!("postgres://admin:pw@db")
+        );
+    }
+
     #[tokio::test]
     async fn search_registry_uses_remote_when_enabled() {
-        let _guard = REMOTE_ENV_GUARD.lock().expect("remote env guard poisoned");
+        let _guard = REMOTE_ENV_GUARD.lock().await;
         let mock = MockServer::start_async().await;
         let response_body = json!({
             "servers": [
@@ -2041,7 +5348,7 @@ mod tests {
 
     #[tokio::test]
     async fn remote_registry_uses_conditional_requests_and_cached_payload() {
-        let _guard = REMOTE_ENV_GUARD.lock().expect("remote env guard poisoned");
+        let _guard = REMOTE_ENV_GUARD.lock().await;
         let mock = MockServer::start_async().await;
         let response_body = json!({
             "servers": [
@@ -2110,9 +5417,175 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn registry_sources_are_merged_with_attribution() {
+        let _guard = REMOTE_ENV_GUARD.lock().await;
+        let mock = MockServer::start_async().await;
+        let pulse = mock
+            .mock_async(|when, then| {
+                when.method(Method::GET)
+                    .path("/pulse/servers")
+                    .query_param("query", "weather");
+                then.status(200).json_body(json!({
+                    "servers": [
+                        {
+                            "name": "Weather",
+                            "package_registry": "npm",
+                            "package_name": "@acme/weather"
+                        },
+                        {
+                            "name": "Maps",
+                            "package_registry": "npm",
+                            "package_name": "@acme/maps"
+                        }
+                    ],
+                    "total_count": 2,
+                    "next": null
+                }));
+            })
+            .await;
+        let official = mock
+            .mock_async(|when, then| {
+                when.method(Method::GET)
+                    .path("/v0/servers")
+                    .query_param("search", "weather");
+                then.status(200).json_body(json!({
+                    "servers": [
+                        {
+                            "server": {
+                                "name": "io.github.acme/weather",
+                                "packages": [{ "registryType": "npm", "identifier": "@acme/weather" }]
+                            }
+                        },
+                        {
+                            "server": {
+                                "name": "com.example/forecast",
+                                "remotes": [{ "type": "sse", "url": "https://forecast.example.com/sse" }]
+                            }
+                        }
+                    ],
+                    "metadata": { "count": 2 }
+                }));
+            })
+            .await;
+
+        unsafe {
+            std::env::set_var("TAURI_MCP_REGISTRY_REMOTE", "true");
+            std::env::set_var("TAURI_MCP_REGISTRY_BASE_URL", mock.url("/pulse"));
+            std::env::set_var("TAURI_MCP_OFFICIAL_REGISTRY_URL", mock.base_url());
+        }
+
+        let dir = tempdir().expect("create tempdir");
+        let service = McpService::new(dir.path().join("mcp-servers.json"), None)
+            .with_registry_sources(vec![
+                Arc::new(PulseMcpSource),
+                Arc::new(OfficialRegistrySource),
+            ]);
+        let result = service
+            .search_registry(&json!({ "query": "weather" }))
+            .await
+            .expect("search result");
+
+        unsafe {
+            std::env::set_var("TAURI_MCP_REGISTRY_REMOTE", "false");
+            std::env::remove_var("TAURI_MCP_REGISTRY_BASE_URL");
+            std::env::remove_var("TAURI_MCP_OFFICIAL_REGISTRY_URL");
+        }
+
+        pulse.assert_async().await;
+        official.assert_async().await;
+        assert_eq!(result.total, 3);
+        let attribution: Vec<(&str, &str, Value)> = result
+            .servers
+            .iter()
+            .map(|server| {
+                (
+                    server["name"].as_str().unwrap(),
+                    server["source"].as_str().unwrap(),
+                    server["sources"].clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            attribution,
+            vec![
+                ("Weather", "pulsemcp", json!(["pulsemcp", "official"])),
+                ("Maps", "pulsemcp", json!(["pulsemcp"])),
+                ("com.example/forecast", "official", json!(["official"])),
+            ]
+        );
+        assert_eq!(result.servers[2]["template"]["type"], "sse");
+
+        let stats = service.cache_stats().await.expect("cache stats");
+        assert_eq!(stats["remote"]["official"]["consecutiveErrors"], 0);
+        assert_eq!(stats["remote"]["pulsemcp"]["consecutiveErrors"], 0);
+    }
+
+    #[tokio::test]
+    async fn official_registry_search_follows_next_cursor() {
+        let _guard = REMOTE_ENV_GUARD.lock().await;
+        let mock = MockServer::start_async().await;
+        let official_server = |name: &str| {
+            json!({
+                "server": {
+                    "name": name,
+                    "packages": [{ "registryType": "npm", "identifier": format!("@acme/{name}") }]
+                }
+            })
+        };
+        let second = mock
+            .mock_async(|when, then| {
+                when.method(Method::GET)
+                    .path("/v0/servers")
+                    .query_param("cursor", "page-2");
+                then.status(200).json_body(json!({
+                    "servers": [official_server("gamma"), official_server("delta")],
+                    "metadata": { "count": 2 }
+                }));
+            })
+            .await;
+        let first = mock
+            .mock_async(|when, then| {
+                when.method(Method::GET).path("/v0/servers");
+                then.status(200).json_body(json!({
+                    "servers": [official_server("alpha"), official_server("beta")],
+                    "metadata": { "count": 2, "nextCursor": "page-2" }
+                }));
+            })
+            .await;
+
+        unsafe {
+            std::env::set_var("TAURI_MCP_REGISTRY_REMOTE", "true");
+            std::env::set_var("TAURI_MCP_OFFICIAL_REGISTRY_URL", mock.base_url());
+        }
+
+        let dir = tempdir().expect("create tempdir");
+        let service = McpService::new(dir.path().join("mcp-servers.json"), None)
+            .with_registry_sources(vec![Arc::new(OfficialRegistrySource)]);
+        let result = service
+            .search_registry(&json!({ "offset": 1, "limit": 2 }))
+            .await
+            .expect("search result");
+
+        unsafe {
+            std::env::set_var("TAURI_MCP_REGISTRY_REMOTE", "false");
+            std::env::remove_var("TAURI_MCP_OFFICIAL_REGISTRY_URL");
+        }
+
+        first.assert_async().await;
+        second.assert_async().await;
+        let names: Vec<&str> = result
+            .servers
+            .iter()
+            .map(|server| server["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["beta", "gamma"]);
+        assert!(result.has_more);
+    }
+
     #[tokio::test]
     async fn remote_registry_marks_metrics_stale_on_error() {
-        let _guard = REMOTE_ENV_GUARD.lock().expect("remote env guard poisoned");
+        let _guard = REMOTE_ENV_GUARD.lock().await;
         let mock = MockServer::start_async().await;
         let response_body = json!({
             "servers": [
@@ -2222,11 +5695,26 @@ mod tests {
             "mcp_test_connection",
             "mcp_connect_server",
             "mcp_disconnect_server",
+            "mcp_call_tool",
+            "mcp_cancel_tool_call",
+            "mcp_get_health",
+            "mcp_oauth_start",
+            "mcp_oauth_sign_out",
+            "mcp_check_updates",
+            "mcp_upgrade_server",
+            "mcp_import_config",
+            "mcp_export_config",
+            "mcp_list_groups",
+            "mcp_save_group",
+            "mcp_delete_group",
+            "mcp_activate_group",
             "mcp_get_server_tools",
             "mcp_refresh_server_tools",
             "mcp_search_registry",
             "mcp_get_registry_server_details",
             "mcp_install_from_registry",
+            "mcp_resolve_install_variables",
+            "mcp_resolve_sampling",
             "mcp_clear_registry_cache",
             "mcp_get_cache_stats",
             "mcp_trigger_background_sync",
@@ -0,0 +1,261 @@
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+
+/// `source` of servers imported from a Claude Desktop config.
+pub const CLAUDE_DESKTOP_SOURCE: &str = "claude-desktop";
+const FILESYSTEM_PACKAGE: &str = "@modelcontextprotocol/server-filesystem";
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpConfigImport {
+    /// Ids of servers that were not configured before.
+    pub added: Vec<String>,
+    /// Ids of servers that replaced an earlier import.
+    pub replaced: Vec<String>,
+    /// Names of entries with neither a `command` nor a `url`.
+    pub skipped: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpConfigExport {
+    pub path: String,
+    pub exported: usize,
+    /// Names of servers whose type has no Claude Desktop equivalent.
+    pub skipped: Vec<String>,
+}
+
+/// Turns the `mcpServers` of a `claude_desktop_config.json` into server
+/// entries for `mcp-servers.json`, keyed by an id derived from each name so
+/// importing again replaces rather than duplicates. Returns the servers and
+/// the names that were skipped.
+pub fn from_claude_desktop(raw: &Value, now: &str) -> Result<(Vec<Value>, Vec<String>), String> {
+    let entries = raw
+        .get("mcpServers")
+        .and_then(Value::as_object)
+        .ok_or_else(|| "The file has no mcpServers object".to_string())?;
+
+    let mut servers = Vec::new();
+    let mut skipped = Vec::new();
+    for (name, entry) in entries {
+        let config = if let Some(command) = entry.get("command").and_then(Value::as_str) {
+            let mut config = json!({
+                "type": "custom",
+                "command": command,
+                "args": entry.get("args").cloned().unwrap_or_else(|| json!([])),
+            });
+            if let Some(env) = string_map(entry.get("env")) {
+                config["env"] = Value::Object(env);
+            }
+            if let Some(cwd) = entry.get("cwd").and_then(Value::as_str) {
+                config["cwd"] = json!(cwd);
+            }
+            config
+        } else if let Some(url) = entry.get("url").and_then(Value::as_str) {
+            let server_type = match entry.get("type").and_then(Value::as_str) {
+                Some("sse") => "sse",
+                _ => "http",
+            };
+            let mut config = json!({ "type": server_type, "url": url });
+            if let Some(headers) = string_map(entry.get("headers")) {
+                config["headers"] = Value::Object(headers);
+            }
+            config
+        } else {
+            skipped.push(name.clone());
+            continue;
+        };
+
+        let disabled = entry
+            .get("disabled")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        servers.push(json!({
+            "id": format!("{CLAUDE_DESKTOP_SOURCE}-{}", slug(name)),
+            "name": name,
+            "type": config["type"].clone(),
+            "status": "disconnected",
+            "enabled": !disabled,
+            "config": config,
+            "tools": [],
+            "source": CLAUDE_DESKTOP_SOURCE,
+            "createdAt": now,
+            "updatedAt": now,
+        }));
+    }
+    Ok((servers, skipped))
+}
+
+/// The `mcpServers` object for `servers`, keyed by name, and the names of
+/// servers that could not be expressed in it.
+pub fn to_claude_desktop(servers: &[Value]) -> (Map<String, Value>, Vec<String>) {
+    let mut entries = Map::new();
+    let mut skipped = Vec::new();
+    for server in servers {
+        let name = server
+            .get("name")
+            .or_else(|| server.get("id"))
+            .and_then(Value::as_str)
+            .unwrap_or("server");
+        let config = server.get("config").cloned().unwrap_or(Value::Null);
+        let server_type = config
+            .get("type")
+            .or_else(|| server.get("type"))
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+
+        let mut entry = match server_type {
+            "custom" => {
+                let Some(command) = config.get("command").and_then(Value::as_str) else {
+                    skipped.push(name.to_string());
+                    continue;
+                };
+                let mut entry = json!({
+                    "command": command,
+                    "args": config.get("args").cloned().unwrap_or_else(|| json!([])),
+                });
+                if let Some(env) = config.get("env").filter(|env| is_non_empty(env)) {
+                    entry["env"] = env.clone();
+                }
+                if let Some(cwd) = config.get("cwd").and_then(Value::as_str) {
+                    entry["cwd"] = json!(cwd);
+                }
+                entry
+            }
+            "filesystem" => {
+                let Some(root) = config.get("rootPath").and_then(Value::as_str) else {
+                    skipped.push(name.to_string());
+                    continue;
+                };
+                json!({ "command": "npx", "args": ["-y", FILESYSTEM_PACKAGE, root] })
+            }
+            "http" | "streamable-http" | "sse" => {
+                let Some(url) = config.get("url").and_then(Value::as_str) else {
+                    skipped.push(name.to_string());
+                    continue;
+                };
+                let mut entry = json!({
+                    "type": if server_type == "sse" { "sse" } else { "http" },
+                    "url": url,
+                });
+                if let Some(headers) = config
+                    .get("headers")
+                    .filter(|headers| is_non_empty(headers))
+                {
+                    entry["headers"] = headers.clone();
+                }
+                entry
+            }
+            _ => {
+                skipped.push(name.to_string());
+                continue;
+            }
+        };
+        if server.get("enabled").and_then(Value::as_bool) == Some(false) {
+            entry["disabled"] = json!(true);
+        }
+
+        let mut key = name.to_string();
+        let mut suffix = 2;
+        while entries.contains_key(&key) {
+            key = format!("{name} ({suffix})");
+            suffix += 1;
+        }
+        entries.insert(key, entry);
+    }
+    (entries, skipped)
+}
+
+fn string_map(value: Option<&Value>) -> Option<Map<String, Value>> {
+    let map: Map<String, Value> = value?
+        .as_object()?
+        .iter()
+        .filter_map(|(key, value)| {
+            let text = match value {
+                Value::String(text) => text.clone(),
+                Value::Number(number) => number.to_string(),
+                Value::Bool(flag) => flag.to_string(),
+                _ => return None,
+            };
+            Some((key.clone(), Value::String(text)))
+        })
+        .collect();
+    (!map.is_empty()).then_some(map)
+}
+
+fn is_non_empty(value: &Value) -> bool {
+    value.as_object().is_some_and(|map| !map.is_empty())
+}
+
+fn slug(name: &str) -> String {
+    let mut slug = String::new();
+    for ch in name.chars() {
+        if ch.is_ascii_alphanumeric() {
+            slug.push(ch.to_ascii_lowercase());
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() {
+        "server".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn claude_desktop_entries_round_trip() {
+        let raw = json!({
+            "mcpServers": {
+                "GitHub Tools": {
+                    "command": "npx",
+                    "args": ["-y", "@modelcontextprotocol/server-github"],
+                    "env": { "GITHUB_TOKEN": "ghp_x", "RETRIES": 3 }
+                },
+                "remote": { "type": "sse", "url": "https://mcp.example.com/sse", "disabled": true },
+                "broken": { "args": [] }
+            }
+        });
+        let (servers, skipped) = from_claude_desktop(&raw, "2025-01-01T00:00:00Z").unwrap();
+        assert_eq!(skipped, vec!["broken"]);
+        assert_eq!(servers[0]["id"], "claude-desktop-github-tools");
+        assert_eq!(servers[0]["config"]["env"]["RETRIES"], "3");
+        assert_eq!(servers[1]["type"], "sse");
+        assert_eq!(servers[1]["enabled"], false);
+
+        let mut servers = servers;
+        servers.push(json!({
+            "name": "Local Filesystem",
+            "config": { "type": "filesystem", "rootPath": "/home/me" }
+        }));
+        servers.push(json!({ "name": "Tools", "config": { "type": "github" } }));
+        servers.push(json!({
+            "name": "GitHub Tools",
+            "config": { "type": "custom", "command": "node", "args": [] }
+        }));
+        let (entries, skipped) = to_claude_desktop(&servers);
+        assert_eq!(skipped, vec!["Tools"]);
+        assert_eq!(
+            Value::Object(entries),
+            json!({
+                "GitHub Tools": {
+                    "command": "npx",
+                    "args": ["-y", "@modelcontextprotocol/server-github"],
+                    "env": { "GITHUB_TOKEN": "ghp_x", "RETRIES": "3" }
+                },
+                "remote": { "type": "sse", "url": "https://mcp.example.com/sse", "disabled": true },
+                "Local Filesystem": {
+                    "command": "npx",
+                    "args": ["-y", FILESYSTEM_PACKAGE, "/home/me"]
+                },
+                "GitHub Tools (2)": { "command": "node", "args": [] }
+            })
+        );
+        assert!(from_claude_desktop(&json!({}), "now").is_err());
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+/// File next to `mcp-servers.json` holding the workspace's server groups.
+pub const GROUPS_FILE: &str = "mcp-groups.json";
+/// Holds the name of the active group. While one is active only its enabled
+/// members are connected; the servers' own `enabled` flags are untouched.
+pub const ACTIVE_GROUP_FILE: &str = "mcp-active-group";
+const MAX_GROUP_NAME_CHARS: usize = 80;
+
+/// A named set of servers, such as "research" or "devops", that can be
+/// connected together or handed to the agent instead of every server.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpServerGroup {
+    pub name: String,
+    pub server_ids: Vec<String>,
+    pub updated_at: String,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpGroupFailure {
+    pub server_id: String,
+    pub error: String,
+}
+
+/// What activating or deactivating a group changed.
+#[derive(Clone, Debug, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpGroupActivation {
+    pub group: String,
+    pub connected: Vec<String>,
+    /// Servers outside the group whose sessions were closed.
+    pub disconnected: Vec<String>,
+    pub failed: Vec<McpGroupFailure>,
+}
+
+/// Reads a groups file, treating a missing or unreadable one as empty.
+pub fn parse(raw: Option<&str>) -> Vec<McpServerGroup> {
+    let Some(raw) = raw.filter(|raw| !raw.trim().is_empty()) else {
+        return Vec::new();
+    };
+    serde_json::from_str(raw).unwrap_or_else(|error| {
+        log::warn!("Ignoring unreadable MCP groups file: {}", error);
+        Vec::new()
+    })
+}
+
+/// Looks a group up by name, ignoring case.
+pub fn find<'a>(groups: &'a [McpServerGroup], name: &str) -> Option<&'a McpServerGroup> {
+    let name = name.trim();
+    groups
+        .iter()
+        .find(|group| group.name.eq_ignore_ascii_case(name))
+}
+
+/// Adds a group, or replaces the members of the one with the same name.
+/// Every member must be one of `known_ids`; duplicates are dropped.
+pub fn upsert(
+    groups: &mut Vec<McpServerGroup>,
+    name: &str,
+    server_ids: Vec<String>,
+    known_ids: &HashSet<String>,
+    now: &str,
+) -> Result<McpServerGroup, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Group name is required".to_string());
+    }
+    if name.chars().count() > MAX_GROUP_NAME_CHARS {
+        return Err(format!(
+            "Group name must be at most {MAX_GROUP_NAME_CHARS} characters"
+        ));
+    }
+    if let Some(unknown) = server_ids.iter().find(|id| !known_ids.contains(*id)) {
+        return Err(format!("MCP server not found: {unknown}"));
+    }
+    let mut seen = HashSet::new();
+    let server_ids: Vec<String> = server_ids
+        .into_iter()
+        .filter(|id| seen.insert(id.clone()))
+        .collect();
+
+    let group = McpServerGroup {
+        name: name.to_string(),
+        server_ids,
+        updated_at: now.to_string(),
+    };
+    match groups
+        .iter_mut()
+        .find(|existing| existing.name.eq_ignore_ascii_case(name))
+    {
+        Some(existing) => *existing = group.clone(),
+        None => groups.push(group.clone()),
+    }
+    Ok(group)
+}
+
+/// Removes the named group. Returns whether it existed.
+pub fn remove(groups: &mut Vec<McpServerGroup>, name: &str) -> bool {
+    let before = groups.len();
+    groups.retain(|group| !group.name.eq_ignore_ascii_case(name.trim()));
+    groups.len() != before
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_are_validated_and_replaced_by_name() {
+        let known: HashSet<String> = ["github", "search", "shell"]
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let mut groups = parse(None);
+
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            upsert(&mut groups, " ", ids(&["github"]), &known, "t0").unwrap_err(),
+            "Group name is required"
+        );
+        assert_eq!(
+            upsert(&mut groups, "Research", ids(&["missing"]), &known, "t0").unwrap_err(),
+            "MCP server not found: missing"
+        );
+
+        let group = upsert(
+            &mut groups,
+            " Research ",
+            ids(&["search", "github", "search"]),
+            &known,
+            "t1",
+        )
+        .expect("create");
+        assert_eq!(group.name, "Research");
+        assert_eq!(group.server_ids, ids(&["search", "github"]));
+
+        upsert(&mut groups, "research", ids(&["shell"]), &known, "t2").expect("replace");
+        assert_eq!(groups.len(), 1);
+        assert_eq!(
+            find(&groups, "RESEARCH").unwrap().server_ids,
+            ids(&["shell"])
+        );
+
+        let raw = serde_json::to_string(&groups).expect("serialize");
+        assert_eq!(parse(Some(&raw)), groups);
+        assert!(parse(Some("not json")).is_empty());
+
+        assert!(remove(&mut groups, "Research"));
+        assert!(!remove(&mut groups, "Research"));
+    }
+}
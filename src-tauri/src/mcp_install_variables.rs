@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// A value a registry server needs before it can run, such as an API key or
+/// a path, found in its template or declared by its registry.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallVariable {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+    /// Whether the renderer should mask the value. Secret `env` and header
+    /// values still go to the credential store when the server is saved.
+    #[serde(default)]
+    pub secret: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+}
+
+/// The variables a server `config` from a registry template needs: `env`
+/// entries left empty or holding a placeholder such as `<your-api-key>` or
+/// `YOUR_API_KEY`, and `${NAME}` tokens in `env`, `args`, `cwd`, `url` and
+/// `headers`. `declared` comes from the registry entry's `variables` and
+/// fills in descriptions and defaults; a variable is secret when either
+/// side says so, and one the template needs stays required.
+pub fn template_variables(
+    config: &Value,
+    declared: &[InstallVariable],
+    is_secret: impl Fn(&str) -> bool,
+) -> Vec<InstallVariable> {
+    let mut found: Vec<InstallVariable> = Vec::new();
+    let mut add = |name: &str| {
+        if !found.iter().any(|variable| variable.name == name) {
+            found.push(InstallVariable {
+                name: name.to_string(),
+                required: true,
+                secret: is_secret(name),
+                ..InstallVariable::default()
+            });
+        }
+    };
+
+    if let Some(env) = config.get("env").and_then(Value::as_object) {
+        for (key, value) in env {
+            let text = value.as_str().unwrap_or_default();
+            if is_placeholder(text) {
+                add(key);
+            }
+        }
+    }
+    for text in template_strings(config) {
+        for name in tokens(text) {
+            add(name);
+        }
+    }
+
+    for variable in declared {
+        match found.iter_mut().find(|found| found.name == variable.name) {
+            Some(found) => {
+                *found = InstallVariable {
+                    required: found.required || variable.required,
+                    secret: found.secret || variable.secret,
+                    ..variable.clone()
+                }
+            }
+            None if variable.required => found.push(variable.clone()),
+            None => {}
+        }
+    }
+    found
+}
+
+/// Fills `config` in with `values`, falling back to each variable's
+/// default. Fails without changing `config` when a required variable has no
+/// value or `values` names a variable the template does not have.
+pub fn apply(
+    config: &mut Value,
+    variables: &[InstallVariable],
+    values: &HashMap<String, String>,
+) -> Result<(), String> {
+    if let Some(unknown) = values
+        .keys()
+        .find(|name| !variables.iter().any(|variable| &variable.name == *name))
+    {
+        return Err(format!("Unknown install variable: {unknown}"));
+    }
+
+    let mut resolved = HashMap::new();
+    let mut missing = Vec::new();
+    for variable in variables {
+        let value = values
+            .get(&variable.name)
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+            .or(variable.default.as_deref());
+        match value {
+            Some(value) => {
+                resolved.insert(variable.name.as_str(), value.to_string());
+            }
+            None if variable.required => missing.push(variable.name.as_str()),
+            None => {
+                resolved.insert(variable.name.as_str(), String::new());
+            }
+        }
+    }
+    if !missing.is_empty() {
+        return Err(format!("Missing install variables: {}", missing.join(", ")));
+    }
+
+    if let Some(env) = config.get_mut("env").and_then(Value::as_object_mut) {
+        for (key, value) in env.iter_mut() {
+            if value.as_str().is_some_and(is_placeholder)
+                && let Some(resolved) = resolved.get(key.as_str())
+            {
+                *value = Value::String(resolved.clone());
+            }
+        }
+    }
+    substitute(config, &resolved);
+    if let Some(env) = config.get_mut("env").and_then(Value::as_object_mut) {
+        env.retain(|_, value| value.as_str() != Some(""));
+    }
+    Ok(())
+}
+
+/// Reads the `installVariables` still pending on a saved server.
+pub fn pending(server: &Value) -> Vec<InstallVariable> {
+    server
+        .get("installVariables")
+        .cloned()
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+/// Reads the `variables` a registry entry declares.
+pub fn declared(server: &Value) -> Vec<InstallVariable> {
+    server
+        .get("variables")
+        .cloned()
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+/// The `variables` entry of a registry server, from the official registry's
+/// `environmentVariables`.
+pub fn from_registry_env(variables: &[Value]) -> Value {
+    Value::Array(
+        variables
+            .iter()
+            .filter_map(|variable| {
+                let name = variable.get("name").and_then(Value::as_str)?;
+                let mut entry = Map::new();
+                entry.insert("name".to_string(), Value::String(name.to_string()));
+                for (from, to) in [
+                    ("description", "description"),
+                    ("default", "default"),
+                    ("isRequired", "required"),
+                    ("isSecret", "secret"),
+                ] {
+                    if let Some(value) = variable.get(from) {
+                        entry.insert(to.to_string(), value.clone());
+                    }
+                }
+                Some(Value::Object(entry))
+            })
+            .collect(),
+    )
+}
+
+fn template_strings(config: &Value) -> Vec<&str> {
+    let mut strings = Vec::new();
+    for field in ["env", "headers"] {
+        if let Some(values) = config.get(field).and_then(Value::as_object) {
+            strings.extend(values.values().filter_map(Value::as_str));
+        }
+    }
+    if let Some(args) = config.get("args").and_then(Value::as_array) {
+        strings.extend(args.iter().filter_map(Value::as_str));
+    }
+    for field in ["cwd", "url"] {
+        if let Some(text) = config.get(field).and_then(Value::as_str) {
+            strings.push(text);
+        }
+    }
+    strings
+}
+
+fn substitute(config: &mut Value, resolved: &HashMap<&str, String>) {
+    let replace = |value: &mut Value| {
+        if let Some(text) = value.as_str()
+            && text.contains("${")
+        {
+            let mut replaced = text.to_string();
+            for name in tokens(text) {
+                if let Some(resolved) = resolved.get(name) {
+                    replaced = replaced.replace(&format!("${{{name}}}"), resolved);
+                }
+            }
+            *value = Value::String(replaced);
+        }
+    };
+    let Some(config) = config.as_object_mut() else {
+        return;
+    };
+    for field in ["env", "headers"] {
+        if let Some(values) = config.get_mut(field).and_then(Value::as_object_mut) {
+            values.values_mut().for_each(&replace);
+        }
+    }
+    if let Some(args) = config.get_mut("args").and_then(Value::as_array_mut) {
+        args.iter_mut().for_each(&replace);
+    }
+    for field in ["cwd", "url"] {
+        if let Some(value) = config.get_mut(field) {
+            replace(value);
+        }
+    }
+}
+
+/// The `NAME`s of `${NAME}` tokens in `text`, skipping credential
+/// references.
+fn tokens(text: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            break;
+        };
+        let name = &after[..end];
+        if !name.is_empty()
+            && name
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+            && !names.contains(&name)
+        {
+            names.push(name);
+        }
+        rest = &after[end + 1..];
+    }
+    names
+}
+
+fn is_placeholder(text: &str) -> bool {
+    let text = text.trim();
+    if text.is_empty() || (text.starts_with('<') && text.ends_with('>')) {
+        return true;
+    }
+    let lower = text.to_ascii_lowercase();
+    ["your_", "your-", "your "]
+        .iter()
+        .any(|prefix| lower.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn template_placeholders_become_variables() {
+        let config = json!({
+            "type": "custom",
+            "command": "npx",
+            "args": ["-y", "@acme/files", "${ROOT_DIR}"],
+            "env": {
+                "ACME_API_KEY": "",
+                "ACME_REGION": "<your-region>",
+                "ACME_MODE": "fast",
+                "ACME_TOKEN": "credential:mcp-acme/ACME_TOKEN"
+            }
+        });
+        let declared = vec![InstallVariable {
+            name: "ACME_REGION".to_string(),
+            description: Some("Data center".to_string()),
+            default: Some("us-east".to_string()),
+            ..InstallVariable::default()
+        }];
+
+        let variables = template_variables(&config, &declared, |name| name.ends_with("_KEY"));
+        let names: Vec<_> = variables.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, vec!["ACME_API_KEY", "ACME_REGION", "ROOT_DIR"]);
+        assert!(variables[0].required && variables[0].secret);
+        assert_eq!(variables[1].description.as_deref(), Some("Data center"));
+        assert!(variables[1].required);
+        assert!(!variables[2].secret);
+    }
+
+    #[test]
+    fn apply_validates_then_fills_the_template() {
+        let template = json!({
+            "args": ["--root", "${ROOT_DIR}/data"],
+            "env": { "ACME_API_KEY": "", "ACME_REGION": "<your-region>", "ACME_DEBUG": "" }
+        });
+        let variables = vec![
+            InstallVariable {
+                name: "ACME_API_KEY".to_string(),
+                required: true,
+                ..InstallVariable::default()
+            },
+            InstallVariable {
+                name: "ACME_REGION".to_string(),
+                default: Some("us-east".to_string()),
+                ..InstallVariable::default()
+            },
+            InstallVariable {
+                name: "ACME_DEBUG".to_string(),
+                ..InstallVariable::default()
+            },
+            InstallVariable {
+                name: "ROOT_DIR".to_string(),
+                required: true,
+                ..InstallVariable::default()
+            },
+        ];
+
+        let mut config = template.clone();
+        let values = HashMap::from([("ROOT_DIR".to_string(), "/srv".to_string())]);
+        let error = apply(&mut config, &variables, &values).unwrap_err();
+        assert_eq!(error, "Missing install variables: ACME_API_KEY");
+        assert_eq!(config, template);
+
+        let values = HashMap::from([("OTHER".to_string(), "x".to_string())]);
+        assert!(apply(&mut config, &variables, &values).is_err());
+
+        let values = HashMap::from([
+            ("ROOT_DIR".to_string(), "/srv".to_string()),
+            ("ACME_API_KEY".to_string(), "sk-1".to_string()),
+        ]);
+        apply(&mut config, &variables, &values).expect("apply");
+        assert_eq!(
+            config,
+            json!({
+                "args": ["--root", "/srv/data"],
+                "env": { "ACME_API_KEY": "sk-1", "ACME_REGION": "us-east" }
+            })
+        );
+    }
+}
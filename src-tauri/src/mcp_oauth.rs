@@ -0,0 +1,955 @@
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::Utc;
+use http::Uri;
+use rand::RngCore;
+use reqwest::{Client, StatusCode, Url};
+use rmcp::model::ClientJsonRpcMessage;
+use rmcp::transport::common::client_side_sse::BoxedSseResponse;
+use rmcp::transport::sse_client::{SseClient, SseTransportError};
+use rmcp::transport::streamable_http_client::{
+    StreamableHttpClient, StreamableHttpError, StreamableHttpPostResponse,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tokio::task::JoinSet;
+
+use crate::credentials::{CredentialManager, CredentialScope};
+
+/// Credential service the tokens of each server are stored under, with the
+/// server id as the account.
+pub const OAUTH_CREDENTIAL_SERVICE: &str = "mcp-oauth";
+const CALLBACK_PATH: &str = "/callback";
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+/// How long one callback connection may stay silent before it is dropped.
+const CALLBACK_READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// Tokens this close to expiry are refreshed before use.
+const REFRESH_MARGIN_SECS: i64 = 60;
+const CLIENT_NAME: &str = "HOL Desktop";
+
+/// The endpoints of the authorization server protecting an MCP server.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+pub struct AuthorizationServerMetadata {
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    #[serde(default)]
+    pub registration_endpoint: Option<String>,
+}
+
+/// What is kept in the credential store for a signed-in server.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpOAuthTokens {
+    pub access_token: String,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// Unix seconds.
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    #[serde(default)]
+    pub scope: Option<String>,
+    pub client_id: String,
+    pub token_endpoint: String,
+    pub resource: String,
+}
+
+impl McpOAuthTokens {
+    fn needs_refresh(&self, now: i64) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at - REFRESH_MARGIN_SECS <= now)
+    }
+}
+
+/// The OAuth settings of an HTTP server, from `config.auth`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpOAuthConfig {
+    /// A pre-registered client; without one the client registers itself.
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    expires_in: Option<i64>,
+    #[serde(default)]
+    scope: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProtectedResourceMetadata {
+    #[serde(default)]
+    authorization_servers: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ClientRegistration {
+    client_id: String,
+}
+
+/// A PKCE verifier and its S256 challenge.
+struct Pkce {
+    verifier: String,
+    challenge: String,
+}
+
+impl Pkce {
+    fn generate() -> Self {
+        let verifier = random_token();
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+        Self {
+            verifier,
+            challenge,
+        }
+    }
+}
+
+fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// `https://host/.well-known/<name>/path`, then `https://host/.well-known/<name>`
+/// when the URL has a path, per RFC 8414 and RFC 9728.
+fn well_known_urls(base: &Url, name: &str) -> Vec<Url> {
+    let mut urls = Vec::new();
+    let path = base.path().trim_end_matches('/');
+    if !path.is_empty() {
+        let mut url = base.clone();
+        url.set_path(&format!("/.well-known/{name}{path}"));
+        url.set_query(None);
+        urls.push(url);
+    }
+    let mut url = base.clone();
+    url.set_path(&format!("/.well-known/{name}"));
+    url.set_query(None);
+    urls.push(url);
+    urls
+}
+
+async fn fetch_json<T: for<'de> Deserialize<'de>>(client: &Client, url: Url) -> Option<T> {
+    let response = client.get(url).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response.json().await.ok()
+}
+
+/// Finds the authorization server for `server_url` through its protected
+/// resource metadata and reads that server's metadata. Servers that publish
+/// neither are assumed to use `/authorize`, `/token` and `/register` on
+/// their own origin.
+pub async fn discover(
+    client: &Client,
+    server_url: &str,
+) -> Result<AuthorizationServerMetadata, String> {
+    let server_url =
+        Url::parse(server_url).map_err(|error| format!("Invalid MCP server URL: {error}"))?;
+
+    let mut issuer = None;
+    for url in well_known_urls(&server_url, "oauth-protected-resource") {
+        if let Some(metadata) = fetch_json::<ProtectedResourceMetadata>(client, url).await
+            && let Some(server) = metadata.authorization_servers.into_iter().next()
+        {
+            issuer = Some(
+                Url::parse(&server)
+                    .map_err(|error| format!("Invalid authorization server URL: {error}"))?,
+            );
+            break;
+        }
+    }
+    let issuer = match issuer {
+        Some(issuer) => issuer,
+        None => {
+            let mut origin = server_url.clone();
+            origin.set_path("/");
+            origin.set_query(None);
+            origin
+        }
+    };
+
+    let mut candidates = well_known_urls(&issuer, "oauth-authorization-server");
+    candidates.extend(well_known_urls(&issuer, "openid-configuration"));
+    for url in candidates {
+        if let Some(metadata) = fetch_json::<AuthorizationServerMetadata>(client, url).await {
+            return Ok(metadata);
+        }
+    }
+
+    let endpoint = |path: &str| {
+        let mut url = issuer.clone();
+        url.set_path(path);
+        url.set_query(None);
+        url.to_string()
+    };
+    Ok(AuthorizationServerMetadata {
+        authorization_endpoint: endpoint("/authorize"),
+        token_endpoint: endpoint("/token"),
+        registration_endpoint: Some(endpoint("/register")),
+    })
+}
+
+/// Registers a public client for the loopback redirect (RFC 7591).
+async fn register_client(
+    client: &Client,
+    registration_endpoint: &str,
+    redirect_uri: &str,
+) -> Result<String, String> {
+    let response = client
+        .post(registration_endpoint)
+        .json(&serde_json::json!({
+            "client_name": CLIENT_NAME,
+            "redirect_uris": [redirect_uri],
+            "grant_types": ["authorization_code", "refresh_token"],
+            "response_types": ["code"],
+            "token_endpoint_auth_method": "none",
+        }))
+        .send()
+        .await
+        .map_err(|error| format!("Client registration failed: {error}"))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Client registration returned {}",
+            response.status()
+        ));
+    }
+    let registration: ClientRegistration = response
+        .json()
+        .await
+        .map_err(|error| format!("Invalid client registration response: {error}"))?;
+    Ok(registration.client_id)
+}
+
+async fn request_tokens(
+    client: &Client,
+    token_endpoint: &str,
+    form: &[(&str, &str)],
+) -> Result<TokenResponse, String> {
+    let response = client
+        .post(token_endpoint)
+        .form(form)
+        .send()
+        .await
+        .map_err(|error| format!("Token request failed: {error}"))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Token endpoint returned {status}: {body}"));
+    }
+    response
+        .json()
+        .await
+        .map_err(|error| format!("Invalid token response: {error}"))
+}
+
+/// Reads the request target of one callback connection, giving up on
+/// sockets that stay silent, such as a browser's preconnects.
+async fn read_request_target(mut stream: TcpStream) -> Option<(TcpStream, String)> {
+    let mut buffer = vec![0u8; 8192];
+    let read = tokio::time::timeout(CALLBACK_READ_TIMEOUT, stream.read(&mut buffer))
+        .await
+        .ok()?
+        .ok()?;
+    let request = String::from_utf8_lossy(&buffer[..read]);
+    let target = request
+        .lines()
+        .next()?
+        .split_whitespace()
+        .nth(1)?
+        .to_string();
+    Some((stream, target))
+}
+
+/// Waits for the browser to be redirected back with the authorization code.
+/// Connections are read side by side, so one that never sends a request
+/// does not hold up the one carrying the callback.
+async fn wait_for_callback(
+    listener: TcpListener,
+    state: &str,
+    timeout: Duration,
+) -> Result<String, String> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut reads = JoinSet::new();
+    loop {
+        let (mut stream, target) = tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) =
+                    accepted.map_err(|error| format!("OAuth callback failed: {error}"))?;
+                reads.spawn(read_request_target(stream));
+                continue;
+            }
+            Some(read) = reads.join_next() => match read {
+                Ok(Some(read)) => read,
+                _ => continue,
+            },
+            _ = tokio::time::sleep_until(deadline) => {
+                return Err("Timed out waiting for the browser sign-in".to_string());
+            }
+        };
+        let Ok(url) = Url::parse(&format!("http://localhost{target}")) else {
+            continue;
+        };
+        if url.path() != CALLBACK_PATH {
+            let _ = stream
+                .write_all(b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n")
+                .await;
+            continue;
+        }
+
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        };
+        let outcome = if param("state").as_deref() != Some(state) {
+            Err("OAuth callback state did not match".to_string())
+        } else if let Some(error) = param("error") {
+            Err(format!(
+                "Authorization was denied: {}",
+                param("error_description").unwrap_or(error)
+            ))
+        } else {
+            param("code").ok_or_else(|| "OAuth callback carried no code".to_string())
+        };
+
+        let message = match &outcome {
+            Ok(_) => "Signed in. You can close this window and return to HOL Desktop.",
+            Err(_) => "Sign-in failed. Return to HOL Desktop for details.",
+        };
+        let body = format!("<!doctype html><title>{CLIENT_NAME}</title><p>{message}</p>");
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: text/html; charset=utf-8\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        let _ = stream.write_all(response.as_bytes()).await;
+        return outcome;
+    }
+}
+
+async fn store_tokens(
+    credentials: &CredentialManager,
+    server_id: &str,
+    tokens: &McpOAuthTokens,
+) -> Result<(), String> {
+    let encoded = serde_json::to_string(tokens).map_err(|error| error.to_string())?;
+    credentials
+        .store_with_scope(
+            OAUTH_CREDENTIAL_SERVICE,
+            server_id,
+            &encoded,
+            Some(CredentialScope::Mcp),
+        )
+        .await
+        .map_err(|error| format!("Failed to store OAuth tokens: {error}"))?;
+    Ok(())
+}
+
+pub async fn load_tokens(
+    credentials: &CredentialManager,
+    server_id: &str,
+) -> Result<Option<McpOAuthTokens>, String> {
+    let Some(encoded) = credentials
+        .get(OAUTH_CREDENTIAL_SERVICE, server_id)
+        .await
+        .map_err(|error| format!("Failed to read OAuth tokens: {error}"))?
+    else {
+        return Ok(None);
+    };
+    serde_json::from_str(encoded.expose())
+        .map(Some)
+        .map_err(|error| format!("Stored OAuth tokens are unreadable: {error}"))
+}
+
+/// Runs the authorization code flow with PKCE for the server at
+/// `server_url`: `open_browser` is handed the authorization URL, the
+/// redirect is caught on a loopback port, and the tokens are stored.
+pub async fn authorize<F>(
+    client: &Client,
+    credentials: &CredentialManager,
+    server_id: &str,
+    server_url: &str,
+    config: &McpOAuthConfig,
+    open_browser: F,
+) -> Result<McpOAuthTokens, String>
+where
+    F: FnOnce(&str) -> Result<(), String>,
+{
+    let metadata = discover(client, server_url).await?;
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .map_err(|error| format!("Failed to listen for the OAuth callback: {error}"))?;
+    let port = listener
+        .local_addr()
+        .map_err(|error| error.to_string())?
+        .port();
+    let redirect_uri = format!("http://127.0.0.1:{port}{CALLBACK_PATH}");
+
+    let client_id = match &config.client_id {
+        Some(client_id) => client_id.clone(),
+        None => {
+            let endpoint = metadata.registration_endpoint.as_deref().ok_or_else(|| {
+                "The authorization server does not support client registration; set config.auth.clientId"
+                    .to_string()
+            })?;
+            register_client(client, endpoint, &redirect_uri).await?
+        }
+    };
+
+    let pkce = Pkce::generate();
+    let state = random_token();
+    let mut authorization_url = Url::parse(&metadata.authorization_endpoint)
+        .map_err(|error| format!("Invalid authorization endpoint: {error}"))?;
+    {
+        let mut query = authorization_url.query_pairs_mut();
+        query
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &client_id)
+            .append_pair("redirect_uri", &redirect_uri)
+            .append_pair("code_challenge", &pkce.challenge)
+            .append_pair("code_challenge_method", "S256")
+            .append_pair("state", &state)
+            .append_pair("resource", server_url);
+        if let Some(scope) = &config.scope {
+            query.append_pair("scope", scope);
+        }
+    }
+    open_browser(authorization_url.as_str())?;
+
+    let code = wait_for_callback(listener, &state, CALLBACK_TIMEOUT).await?;
+
+    let response = request_tokens(
+        client,
+        &metadata.token_endpoint,
+        &[
+            ("grant_type", "authorization_code"),
+            ("code", &code),
+            ("redirect_uri", &redirect_uri),
+            ("client_id", &client_id),
+            ("code_verifier", &pkce.verifier),
+            ("resource", server_url),
+        ],
+    )
+    .await?;
+    let tokens = McpOAuthTokens {
+        access_token: response.access_token,
+        refresh_token: response.refresh_token,
+        expires_at: response
+            .expires_in
+            .map(|seconds| Utc::now().timestamp() + seconds),
+        scope: response.scope.or_else(|| config.scope.clone()),
+        client_id,
+        token_endpoint: metadata.token_endpoint,
+        resource: server_url.to_string(),
+    };
+    store_tokens(credentials, server_id, &tokens).await?;
+    Ok(tokens)
+}
+
+/// The access token for a signed-in server, refreshed first when it is
+/// about to expire. `None` when the server was never signed in to.
+pub async fn access_token(
+    client: &Client,
+    credentials: &CredentialManager,
+    server_id: &str,
+) -> Result<Option<String>, String> {
+    fresh_access_token(client, credentials, server_id, None).await
+}
+
+/// Like [`access_token`], but also refreshes when the stored token is the
+/// `rejected` one a server just answered 401 to.
+async fn fresh_access_token(
+    client: &Client,
+    credentials: &CredentialManager,
+    server_id: &str,
+    rejected: Option<&str>,
+) -> Result<Option<String>, String> {
+    let Some(tokens) = load_tokens(credentials, server_id).await? else {
+        return Ok(None);
+    };
+    if !tokens.needs_refresh(Utc::now().timestamp())
+        && rejected != Some(tokens.access_token.as_str())
+    {
+        return Ok(Some(tokens.access_token));
+    }
+    let Some(refresh_token) = tokens.refresh_token.clone() else {
+        return Err("The OAuth session expired; sign in again".to_string());
+    };
+
+    let response = request_tokens(
+        client,
+        &tokens.token_endpoint,
+        &[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", &refresh_token),
+            ("client_id", &tokens.client_id),
+            ("resource", &tokens.resource),
+        ],
+    )
+    .await
+    .map_err(|error| format!("Failed to refresh the OAuth session: {error}"))?;
+    let refreshed = McpOAuthTokens {
+        access_token: response.access_token,
+        refresh_token: response.refresh_token.or(Some(refresh_token)),
+        expires_at: response
+            .expires_in
+            .map(|seconds| Utc::now().timestamp() + seconds),
+        scope: response.scope.or(tokens.scope),
+        ..tokens
+    };
+    store_tokens(credentials, server_id, &refreshed).await?;
+    Ok(Some(refreshed.access_token))
+}
+
+pub async fn sign_out(credentials: &CredentialManager, server_id: &str) -> Result<bool, String> {
+    credentials
+        .delete(OAUTH_CREDENTIAL_SERVICE, server_id)
+        .await
+        .map_err(|error| format!("Failed to remove OAuth tokens: {error}"))
+}
+
+#[derive(Clone)]
+struct OAuthSession {
+    token_client: Client,
+    credentials: CredentialManager,
+    server_id: Arc<str>,
+    refreshing: Arc<Mutex<()>>,
+}
+
+/// The HTTP client of a remote MCP transport. For a server signed in with
+/// OAuth it looks the bearer token up for every request, so a live session
+/// picks up refreshed tokens, and a 401 refreshes the token and retries once.
+#[derive(Clone)]
+pub struct McpHttpClient {
+    http: Client,
+    oauth: Option<OAuthSession>,
+}
+
+impl McpHttpClient {
+    pub fn new(http: Client) -> Self {
+        Self { http, oauth: None }
+    }
+
+    /// Sends `server_id`'s tokens, refreshing them through `token_client`.
+    pub fn with_oauth(
+        mut self,
+        token_client: Client,
+        credentials: CredentialManager,
+        server_id: &str,
+    ) -> Self {
+        self.oauth = Some(OAuthSession {
+            token_client,
+            credentials,
+            server_id: server_id.into(),
+            refreshing: Arc::new(Mutex::new(())),
+        });
+        self
+    }
+
+    async fn bearer(&self, rejected: Option<&str>) -> Result<Option<String>, String> {
+        let Some(oauth) = &self.oauth else {
+            return Ok(None);
+        };
+        // One refresh at a time, so a rotated refresh token is used once.
+        let _refreshing = oauth.refreshing.lock().await;
+        fresh_access_token(
+            &oauth.token_client,
+            &oauth.credentials,
+            &oauth.server_id,
+            rejected,
+        )
+        .await?
+        .map(Some)
+        .ok_or_else(|| format!("Sign in to MCP server {} to connect", oauth.server_id))
+    }
+
+    async fn with_bearer<T, E, Fut>(
+        &self,
+        unauthorized: impl Fn(&E) -> bool,
+        token_error: impl Fn(String) -> E,
+        send: impl Fn(Option<String>) -> Fut,
+    ) -> Result<T, E>
+    where
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let token = self.bearer(None).await.map_err(&token_error)?;
+        match send(token.clone()).await {
+            Err(error) if token.is_some() && unauthorized(&error) => {
+                let token = self.bearer(token.as_deref()).await.map_err(&token_error)?;
+                send(token).await
+            }
+            result => result,
+        }
+    }
+}
+
+fn is_unauthorized(error: &reqwest::Error) -> bool {
+    error.status() == Some(StatusCode::UNAUTHORIZED)
+}
+
+fn streamable_unauthorized(error: &StreamableHttpError<reqwest::Error>) -> bool {
+    match error {
+        StreamableHttpError::AuthRequired(_) => true,
+        StreamableHttpError::Client(error) => is_unauthorized(error),
+        _ => false,
+    }
+}
+
+fn streamable_token_error(error: String) -> StreamableHttpError<reqwest::Error> {
+    StreamableHttpError::UnexpectedServerResponse(error.into())
+}
+
+fn sse_unauthorized(error: &SseTransportError<reqwest::Error>) -> bool {
+    matches!(error, SseTransportError::Client(error) if is_unauthorized(error))
+}
+
+fn sse_token_error(error: String) -> SseTransportError<reqwest::Error> {
+    SseTransportError::Io(std::io::Error::other(error))
+}
+
+impl StreamableHttpClient for McpHttpClient {
+    type Error = reqwest::Error;
+
+    async fn post_message(
+        &self,
+        uri: Arc<str>,
+        message: ClientJsonRpcMessage,
+        session_id: Option<Arc<str>>,
+        _auth_header: Option<String>,
+    ) -> Result<StreamableHttpPostResponse, StreamableHttpError<Self::Error>> {
+        self.with_bearer(streamable_unauthorized, streamable_token_error, |token| {
+            StreamableHttpClient::post_message(
+                &self.http,
+                uri.clone(),
+                message.clone(),
+                session_id.clone(),
+                token,
+            )
+        })
+        .await
+    }
+
+    async fn delete_session(
+        &self,
+        uri: Arc<str>,
+        session_id: Arc<str>,
+        _auth_header: Option<String>,
+    ) -> Result<(), StreamableHttpError<Self::Error>> {
+        self.with_bearer(streamable_unauthorized, streamable_token_error, |token| {
+            self.http
+                .delete_session(uri.clone(), session_id.clone(), token)
+        })
+        .await
+    }
+
+    async fn get_stream(
+        &self,
+        uri: Arc<str>,
+        session_id: Arc<str>,
+        last_event_id: Option<String>,
+        _auth_header: Option<String>,
+    ) -> Result<BoxedSseResponse, StreamableHttpError<Self::Error>> {
+        self.with_bearer(streamable_unauthorized, streamable_token_error, |token| {
+            StreamableHttpClient::get_stream(
+                &self.http,
+                uri.clone(),
+                session_id.clone(),
+                last_event_id.clone(),
+                token,
+            )
+        })
+        .await
+    }
+}
+
+impl SseClient for McpHttpClient {
+    type Error = reqwest::Error;
+
+    async fn post_message(
+        &self,
+        uri: Uri,
+        message: ClientJsonRpcMessage,
+        _auth_token: Option<String>,
+    ) -> Result<(), SseTransportError<Self::Error>> {
+        self.with_bearer(sse_unauthorized, sse_token_error, |token| {
+            SseClient::post_message(&self.http, uri.clone(), message.clone(), token)
+        })
+        .await
+    }
+
+    async fn get_stream(
+        &self,
+        uri: Uri,
+        last_event_id: Option<String>,
+        _auth_token: Option<String>,
+    ) -> Result<BoxedSseResponse, SseTransportError<Self::Error>> {
+        self.with_bearer(sse_unauthorized, sse_token_error, |token| {
+            SseClient::get_stream(&self.http, uri.clone(), last_event_id.clone(), token)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::{Method, MockServer};
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn pkce_challenge_is_the_hashed_verifier() {
+        let pkce = Pkce::generate();
+        assert_eq!(pkce.verifier.len(), 43);
+        assert_eq!(
+            pkce.challenge,
+            URL_SAFE_NO_PAD.encode(Sha256::digest(pkce.verifier.as_bytes()))
+        );
+        let base = Url::parse("https://example.com/tenant/mcp").unwrap();
+        let urls: Vec<String> = well_known_urls(&base, "oauth-protected-resource")
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/.well-known/oauth-protected-resource/tenant/mcp",
+                "https://example.com/.well-known/oauth-protected-resource",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn callback_is_not_held_up_by_silent_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let waiting = tokio::spawn(async move {
+            wait_for_callback(listener, "state-1", Duration::from_secs(5)).await
+        });
+
+        let _preconnect = TcpStream::connect(address).await.unwrap();
+        let body = Client::new()
+            .get(format!(
+                "http://{address}{CALLBACK_PATH}?code=code-1&state=state-1"
+            ))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(body.contains("Signed in"));
+        assert_eq!(waiting.await.unwrap().unwrap(), "code-1");
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let timed_out = wait_for_callback(listener, "state-1", Duration::from_millis(50)).await;
+        assert!(timed_out.unwrap_err().contains("Timed out"));
+    }
+
+    #[tokio::test]
+    async fn live_transport_refreshes_the_token_the_server_rejects() {
+        let server = MockServer::start_async().await;
+        let rejected = server
+            .mock_async(|when, then| {
+                when.method(Method::POST)
+                    .path("/mcp")
+                    .header("authorization", "Bearer access-1");
+                then.status(401);
+            })
+            .await;
+        let accepted = server
+            .mock_async(|when, then| {
+                when.method(Method::POST)
+                    .path("/mcp")
+                    .header("authorization", "Bearer access-2");
+                then.status(202);
+            })
+            .await;
+        let refresh = server
+            .mock_async(|when, then| {
+                when.method(Method::POST)
+                    .path("/token")
+                    .body_contains("refresh_token=refresh-1");
+                then.status(200)
+                    .json_body(json!({ "access_token": "access-2", "expires_in": 3600 }));
+            })
+            .await;
+
+        let dir = tempdir().unwrap();
+        let credentials = CredentialManager::new(dir.path().join("credentials.dat"), "secret");
+        let tokens = McpOAuthTokens {
+            access_token: "access-1".into(),
+            refresh_token: Some("refresh-1".into()),
+            expires_at: Some(Utc::now().timestamp() + 3600),
+            scope: None,
+            client_id: "client-1".into(),
+            token_endpoint: server.url("/token"),
+            resource: server.url("/mcp"),
+        };
+        store_tokens(&credentials, "remote", &tokens).await.unwrap();
+
+        let client = McpHttpClient::new(Client::new()).with_oauth(
+            Client::new(),
+            credentials.clone(),
+            "remote",
+        );
+        let message: ClientJsonRpcMessage = serde_json::from_value(json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/initialized"
+        }))
+        .unwrap();
+        let response = StreamableHttpClient::post_message(
+            &client,
+            server.url("/mcp").into(),
+            message,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(matches!(response, StreamableHttpPostResponse::Accepted));
+        rejected.assert_async().await;
+        accepted.assert_async().await;
+        refresh.assert_async().await;
+        let stored = load_tokens(&credentials, "remote").await.unwrap().unwrap();
+        assert_eq!(stored.access_token, "access-2");
+    }
+
+    #[tokio::test]
+    async fn authorizes_with_pkce_and_refreshes_tokens() {
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(Method::GET)
+                    .path("/.well-known/oauth-protected-resource");
+                then.status(200)
+                    .json_body(json!({ "authorization_servers": [server.url("/")] }));
+            })
+            .await;
+        server
+            .mock_async(|when, then| {
+                when.method(Method::GET)
+                    .path("/.well-known/oauth-authorization-server");
+                then.status(200).json_body(json!({
+                    "authorization_endpoint": server.url("/authorize"),
+                    "token_endpoint": server.url("/token"),
+                    "registration_endpoint": server.url("/register"),
+                }));
+            })
+            .await;
+        let registration = server
+            .mock_async(|when, then| {
+                when.method(Method::POST)
+                    .path("/register")
+                    .json_body_partial(r#"{ "token_endpoint_auth_method": "none" }"#);
+                then.status(201)
+                    .json_body(json!({ "client_id": "client-1" }));
+            })
+            .await;
+        let exchange = server
+            .mock_async(|when, then| {
+                when.method(Method::POST)
+                    .path("/token")
+                    .body_contains("grant_type=authorization_code")
+                    .body_contains("code=code-1")
+                    .body_contains("code_verifier=");
+                then.status(200).json_body(json!({
+                    "access_token": "access-1",
+                    "refresh_token": "refresh-1",
+                    "expires_in": 30,
+                    "token_type": "Bearer"
+                }));
+            })
+            .await;
+        let refresh = server
+            .mock_async(|when, then| {
+                when.method(Method::POST)
+                    .path("/token")
+                    .body_contains("grant_type=refresh_token")
+                    .body_contains("refresh_token=refresh-1");
+                then.status(200)
+                    .json_body(json!({ "access_token": "access-2", "expires_in": 3600 }));
+            })
+            .await;
+
+        let dir = tempdir().unwrap();
+        let credentials = CredentialManager::new(dir.path().join("credentials.dat"), "secret");
+        let client = Client::new();
+        let server_url = server.url("/mcp");
+
+        let (opened_tx, opened_rx) = tokio::sync::oneshot::channel::<String>();
+        let browser = tokio::spawn(async move {
+            let url = Url::parse(&opened_rx.await.unwrap()).unwrap();
+            let param = |name: &str| {
+                url.query_pairs()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.into_owned())
+                    .unwrap()
+            };
+            assert_eq!(param("code_challenge_method"), "S256");
+            assert_eq!(param("client_id"), "client-1");
+            let callback = format!(
+                "{}?code=code-1&state={}",
+                param("redirect_uri"),
+                param("state")
+            );
+            Client::new()
+                .get(callback)
+                .send()
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap()
+        });
+
+        let tokens = authorize(
+            &client,
+            &credentials,
+            "remote",
+            &server_url,
+            &McpOAuthConfig::default(),
+            |url| {
+                opened_tx.send(url.to_string()).unwrap();
+                Ok(())
+            },
+        )
+        .await
+        .unwrap();
+        assert!(browser.await.unwrap().contains("Signed in"));
+        registration.assert_async().await;
+        exchange.assert_async().await;
+        assert_eq!(tokens.access_token, "access-1");
+        assert_eq!(tokens.resource, server_url);
+
+        // The stored token expires within the refresh margin.
+        assert_eq!(
+            access_token(&client, &credentials, "remote").await.unwrap(),
+            Some("access-2".to_string())
+        );
+        refresh.assert_async().await;
+        let stored = load_tokens(&credentials, "remote").await.unwrap().unwrap();
+        assert_eq!(stored.refresh_token.as_deref(), Some("refresh-1"));
+        assert_eq!(
+            access_token(&client, &credentials, "remote").await.unwrap(),
+            Some("access-2".to_string())
+        );
+
+        assert!(sign_out(&credentials, "remote").await.unwrap());
+        assert_eq!(
+            access_token(&client, &credentials, "remote").await.unwrap(),
+            None
+        );
+    }
+}
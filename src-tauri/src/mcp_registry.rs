@@ -1,13 +1,34 @@
 use std::path::{Path, PathBuf};
 
-use chrono::Utc;
-use rusqlite::{Connection, params};
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, params};
 use serde_json::{Value, json};
 
 pub struct McpRegistryStore {
     path: PathBuf,
 }
 
+/// Tool schemas captured the last time a server connected.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CachedServerTools {
+    pub server_id: String,
+    pub tools: Vec<Value>,
+    pub captured_at: i64,
+}
+
+/// Registry search and install counters, kept across launches.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RegistryStats {
+    pub total_searches: usize,
+    pub cache_hits: usize,
+    pub cache_entries: usize,
+    pub total_response_time_ms: u128,
+    pub installations: usize,
+    pub oldest_entry: Option<DateTime<Utc>>,
+    pub newest_entry: Option<DateTime<Utc>>,
+    pub last_background_sync: Option<DateTime<Utc>>,
+}
+
 impl McpRegistryStore {
     pub fn new(path: PathBuf) -> Result<Self, String> {
         let store = Self { path };
@@ -42,6 +63,22 @@ CREATE TABLE IF NOT EXISTS mcp_registry_sync (
     error_message TEXT,
     sync_duration_ms INTEGER
 );
+CREATE TABLE IF NOT EXISTS mcp_server_tools (
+    server_id TEXT PRIMARY KEY,
+    tools TEXT NOT NULL,
+    captured_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS mcp_registry_stats (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    total_searches INTEGER NOT NULL DEFAULT 0,
+    cache_hits INTEGER NOT NULL DEFAULT 0,
+    cache_entries INTEGER NOT NULL DEFAULT 0,
+    total_response_time_ms INTEGER NOT NULL DEFAULT 0,
+    installations INTEGER NOT NULL DEFAULT 0,
+    oldest_entry INTEGER,
+    newest_entry INTEGER,
+    last_background_sync INTEGER
+);
 ",
             )
             .map_err(|error| format!("Failed to initialize registry store: {error}"))?;
@@ -127,6 +164,79 @@ CREATE TABLE IF NOT EXISTS mcp_registry_sync (
         Ok(servers)
     }
 
+    pub fn server_count(&self) -> Result<usize, String> {
+        let connection = self.open_connection()?;
+        connection
+            .query_row("SELECT COUNT(*) FROM mcp_registry_servers", [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .map(|count| count as usize)
+            .map_err(|error| format!("Failed to count registry servers: {error}"))
+    }
+
+    /// Drops servers no registry has listed in the last `max_age_secs`
+    /// seconds. Every sync refreshes the servers it returns, so the catalog
+    /// itself is never cut short.
+    pub fn prune(&self, max_age_secs: i64) -> Result<usize, String> {
+        let connection = self.open_connection()?;
+        connection
+            .execute(
+                "DELETE FROM mcp_registry_servers WHERE updated_at < ?1",
+                params![Utc::now().timestamp() - max_age_secs],
+            )
+            .map_err(|error| format!("Failed to prune registry servers: {error}"))
+    }
+
+    pub fn save_server_tools(&self, server_id: &str, tools: &[Value]) -> Result<(), String> {
+        let payload = serde_json::to_string(tools)
+            .map_err(|error| format!("Failed to serialize tools for {server_id}: {error}"))?;
+        let connection = self.open_connection()?;
+        connection
+            .execute(
+                "INSERT INTO mcp_server_tools (server_id, tools, captured_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(server_id) DO UPDATE SET
+                   tools=excluded.tools,
+                   captured_at=excluded.captured_at",
+                params![server_id, payload, Utc::now().timestamp()],
+            )
+            .map_err(|error| format!("Failed to store tools for {server_id}: {error}"))?;
+        Ok(())
+    }
+
+    pub fn server_tools(&self, server_id: &str) -> Result<Option<CachedServerTools>, String> {
+        let connection = self.open_connection()?;
+        let row = connection
+            .query_row(
+                "SELECT tools, captured_at FROM mcp_server_tools WHERE server_id = ?1",
+                params![server_id],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+            )
+            .optional()
+            .map_err(|error| format!("Failed to read tools for {server_id}: {error}"))?;
+
+        let Some((payload, captured_at)) = row else {
+            return Ok(None);
+        };
+        let tools = serde_json::from_str::<Vec<Value>>(&payload)
+            .map_err(|error| format!("Failed to parse cached tools for {server_id}: {error}"))?;
+        Ok(Some(CachedServerTools {
+            server_id: server_id.to_string(),
+            tools,
+            captured_at,
+        }))
+    }
+
+    pub fn remove_server_tools(&self, server_id: &str) -> Result<bool, String> {
+        let connection = self.open_connection()?;
+        connection
+            .execute(
+                "DELETE FROM mcp_server_tools WHERE server_id = ?1",
+                params![server_id],
+            )
+            .map(|removed| removed > 0)
+            .map_err(|error| format!("Failed to remove tools for {server_id}: {error}"))
+    }
+
     pub fn record_sync_success(
         &self,
         registry: &str,
@@ -167,6 +277,64 @@ CREATE TABLE IF NOT EXISTS mcp_registry_sync (
         Ok(())
     }
 
+    pub fn save_registry_stats(&self, stats: &RegistryStats) -> Result<(), String> {
+        let millis = |time: Option<DateTime<Utc>>| time.map(|time| time.timestamp_millis());
+        let connection = self.open_connection()?;
+        connection
+            .execute(
+                "INSERT INTO mcp_registry_stats (id, total_searches, cache_hits, cache_entries, total_response_time_ms, installations, oldest_entry, newest_entry, last_background_sync)
+                 VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                 ON CONFLICT(id) DO UPDATE SET
+                   total_searches=excluded.total_searches,
+                   cache_hits=excluded.cache_hits,
+                   cache_entries=excluded.cache_entries,
+                   total_response_time_ms=excluded.total_response_time_ms,
+                   installations=excluded.installations,
+                   oldest_entry=excluded.oldest_entry,
+                   newest_entry=excluded.newest_entry,
+                   last_background_sync=excluded.last_background_sync",
+                params![
+                    stats.total_searches as i64,
+                    stats.cache_hits as i64,
+                    stats.cache_entries as i64,
+                    i64::try_from(stats.total_response_time_ms).unwrap_or(i64::MAX),
+                    stats.installations as i64,
+                    millis(stats.oldest_entry),
+                    millis(stats.newest_entry),
+                    millis(stats.last_background_sync),
+                ],
+            )
+            .map_err(|error| format!("Failed to store registry stats: {error}"))?;
+        Ok(())
+    }
+
+    /// The counters saved by the last launch, or defaults on first run.
+    pub fn registry_stats(&self) -> Result<RegistryStats, String> {
+        let time = |millis: Option<i64>| millis.and_then(DateTime::<Utc>::from_timestamp_millis);
+        let connection = self.open_connection()?;
+        let stats = connection
+            .query_row(
+                "SELECT total_searches, cache_hits, cache_entries, total_response_time_ms, installations, oldest_entry, newest_entry, last_background_sync
+                 FROM mcp_registry_stats WHERE id = 1",
+                [],
+                |row| {
+                    Ok(RegistryStats {
+                        total_searches: row.get::<_, i64>(0)?.max(0) as usize,
+                        cache_hits: row.get::<_, i64>(1)?.max(0) as usize,
+                        cache_entries: row.get::<_, i64>(2)?.max(0) as usize,
+                        total_response_time_ms: row.get::<_, i64>(3)?.max(0) as u128,
+                        installations: row.get::<_, i64>(4)?.max(0) as usize,
+                        oldest_entry: time(row.get(5)?),
+                        newest_entry: time(row.get(6)?),
+                        last_background_sync: time(row.get(7)?),
+                    })
+                },
+            )
+            .optional()
+            .map_err(|error| format!("Failed to read registry stats: {error}"))?;
+        Ok(stats.unwrap_or_default())
+    }
+
     pub fn sync_snapshot(&self) -> Value {
         let connection = match self.open_connection() {
             Ok(conn) => conn,
@@ -217,3 +385,30 @@ fn _ensure_path(path: &Path) -> Result<(), String> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn prune_drops_only_servers_no_sync_returned_recently() {
+        let dir = tempdir().unwrap();
+        let store = McpRegistryStore::new(dir.path().join("registry.sqlite")).unwrap();
+        let servers: Vec<Value> = (0..2_500)
+            .map(|index| json!({ "id": format!("server-{index}") }))
+            .collect();
+        store.upsert_servers("pulsemcp", &servers).unwrap();
+        store
+            .open_connection()
+            .unwrap()
+            .execute(
+                "UPDATE mcp_registry_servers SET updated_at = updated_at - 100 WHERE id = 'server-0'",
+                [],
+            )
+            .unwrap();
+
+        assert_eq!(store.prune(50).unwrap(), 1);
+        assert_eq!(store.server_count().unwrap(), 2_499);
+    }
+}
@@ -0,0 +1,420 @@
+use chrono::{DateTime, Utc};
+use reqwest::{Client, RequestBuilder};
+use serde_json::{Map, Value, json};
+use std::sync::Arc;
+
+use crate::mcp::McpService;
+use crate::mcp_install_variables;
+
+pub const PULSEMCP_SOURCE: &str = "pulsemcp";
+pub const OFFICIAL_SOURCE: &str = "official";
+
+const USER_AGENT: &str = "HashgraphOnlineDesktop/1.0 (+https://hashgraphonline.com)";
+const OFFICIAL_META_KEY: &str = "io.modelcontextprotocol.registry/official";
+const OFFICIAL_PAGE_LIMIT: usize = 100;
+
+/// One page of a registry search.
+#[derive(Clone, Copy)]
+pub struct RegistryPage<'a> {
+    pub query: Option<&'a str>,
+    pub limit: usize,
+    pub offset: Option<usize>,
+    /// Where to continue, for sources that page by cursor.
+    pub cursor: Option<&'a str>,
+}
+
+/// A remote catalog of MCP servers. Searches query every source and merge
+/// the results, so each normalized server carries its source's name.
+pub trait RegistrySource: Send + Sync {
+    /// Recorded as each server's `source` and used to key cached payloads
+    /// and sync state.
+    fn name(&self) -> &str;
+
+    /// The request for `page`, or `None` when the source cannot serve it.
+    fn search_request(&self, client: &Client, page: &RegistryPage<'_>) -> Option<RequestBuilder>;
+
+    /// The servers in a response in the registry's shape, the total number
+    /// of matches and whether more pages follow.
+    fn normalize(&self, raw: Value, now: DateTime<Utc>) -> (Vec<Value>, usize, bool);
+
+    /// Whether the source pages by cursor rather than offset. Searches then
+    /// walk its pages from the first and apply the offset themselves.
+    fn pages_by_cursor(&self) -> bool {
+        false
+    }
+
+    /// The cursor of the page after `raw`, for sources that page by cursor.
+    fn next_cursor(&self, _raw: &Value) -> Option<String> {
+        None
+    }
+}
+
+/// PulseMCP first, then the official registry.
+pub fn default_registry_sources() -> Vec<Arc<dyn RegistrySource>> {
+    vec![Arc::new(PulseMcpSource), Arc::new(OfficialRegistrySource)]
+}
+
+/// The PulseMCP directory, at `TAURI_MCP_REGISTRY_BASE_URL` when set.
+pub struct PulseMcpSource;
+
+impl RegistrySource for PulseMcpSource {
+    fn name(&self) -> &str {
+        PULSEMCP_SOURCE
+    }
+
+    fn search_request(&self, client: &Client, page: &RegistryPage<'_>) -> Option<RequestBuilder> {
+        let base_url = std::env::var("TAURI_MCP_REGISTRY_BASE_URL")
+            .unwrap_or_else(|_| "https://api.pulsemcp.com/v0beta".to_string());
+        let mut query = vec![("count_per_page", page.limit.to_string())];
+        if let Some(text) = page.query {
+            query.push(("query", text.to_string()));
+        }
+        if let Some(offset) = page.offset {
+            query.push(("offset", offset.to_string()));
+        }
+        Some(
+            client
+                .get(format!("{}/servers", base_url.trim_end_matches('/')))
+                .query(&query)
+                .header(reqwest::header::ACCEPT, "application/json")
+                .header(reqwest::header::USER_AGENT, USER_AGENT),
+        )
+    }
+
+    fn normalize(&self, raw: Value, now: DateTime<Utc>) -> (Vec<Value>, usize, bool) {
+        McpService::normalize_pulse_payload(raw, now)
+    }
+}
+
+/// The official modelcontextprotocol registry, at
+/// `TAURI_MCP_OFFICIAL_REGISTRY_URL` when set. It pages by `nextCursor`.
+pub struct OfficialRegistrySource;
+
+impl RegistrySource for OfficialRegistrySource {
+    fn name(&self) -> &str {
+        OFFICIAL_SOURCE
+    }
+
+    fn search_request(&self, client: &Client, page: &RegistryPage<'_>) -> Option<RequestBuilder> {
+        let base_url = std::env::var("TAURI_MCP_OFFICIAL_REGISTRY_URL")
+            .unwrap_or_else(|_| "https://registry.modelcontextprotocol.io".to_string());
+        let wanted = page.offset.unwrap_or_default() + page.limit;
+        let mut query = vec![("limit", wanted.min(OFFICIAL_PAGE_LIMIT).to_string())];
+        if let Some(text) = page.query {
+            query.push(("search", text.to_string()));
+        }
+        if let Some(cursor) = page.cursor {
+            query.push(("cursor", cursor.to_string()));
+        }
+        Some(
+            client
+                .get(format!("{}/v0/servers", base_url.trim_end_matches('/')))
+                .query(&query)
+                .header(reqwest::header::ACCEPT, "application/json")
+                .header(reqwest::header::USER_AGENT, USER_AGENT),
+        )
+    }
+
+    fn normalize(&self, raw: Value, now: DateTime<Utc>) -> (Vec<Value>, usize, bool) {
+        let servers: Vec<Value> = raw
+            .get("servers")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| normalize_official_server(entry, now))
+            .collect();
+        let total = servers.len();
+        let has_more = self.next_cursor(&raw).is_some();
+        (servers, total, has_more)
+    }
+
+    fn pages_by_cursor(&self) -> bool {
+        true
+    }
+
+    fn next_cursor(&self, raw: &Value) -> Option<String> {
+        raw.pointer("/metadata/nextCursor")
+            .or_else(|| raw.pointer("/metadata/next_cursor"))
+            .and_then(Value::as_str)
+            .filter(|cursor| !cursor.is_empty())
+            .map(str::to_string)
+    }
+}
+
+/// Maps an entry of the official registry, which wraps the server document
+/// in `server` next to the registry's own `_meta`.
+fn normalize_official_server(entry: &Value, now: DateTime<Utc>) -> Option<Value> {
+    let server = entry.get("server").unwrap_or(entry);
+    let meta = entry
+        .get("_meta")
+        .or_else(|| server.get("_meta"))
+        .and_then(|meta| meta.get(OFFICIAL_META_KEY));
+    if meta
+        .and_then(|meta| meta.get("isLatest"))
+        .and_then(Value::as_bool)
+        == Some(false)
+        || meta
+            .and_then(|meta| meta.get("status"))
+            .and_then(Value::as_str)
+            == Some("deleted")
+    {
+        return None;
+    }
+
+    let id = server.get("name").and_then(Value::as_str)?.trim();
+    if id.is_empty() {
+        return None;
+    }
+    let name = server
+        .get("title")
+        .and_then(Value::as_str)
+        .filter(|title| !title.trim().is_empty())
+        .unwrap_or(id);
+
+    let mut normalized = Map::new();
+    normalized.insert("id".to_string(), json!(id));
+    normalized.insert("name".to_string(), json!(name));
+    normalized.insert(
+        "description".to_string(),
+        json!(
+            server
+                .get("description")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+        ),
+    );
+
+    let package = server
+        .get("packages")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .find(|package| {
+            package
+                .get("transport")
+                .and_then(|transport| transport.get("type"))
+                .and_then(Value::as_str)
+                .is_none_or(|transport| transport == "stdio")
+                && package_command(package).is_some()
+        });
+    if let Some(package) = package {
+        let (registry, identifier, command, args) = package_command(package)?;
+        let variables = package
+            .get("environmentVariables")
+            .or_else(|| package.get("environment_variables"))
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let env: Map<String, Value> = variables
+            .iter()
+            .filter_map(|variable| {
+                let name = variable.get("name").and_then(Value::as_str)?;
+                let default = variable
+                    .get("default")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                Some((name.to_string(), json!(default)))
+            })
+            .collect();
+        let (template, install_command) =
+            McpService::build_template_payload(command, args, (!env.is_empty()).then_some(env));
+        normalized.insert("template".to_string(), template);
+        normalized.insert("installCommand".to_string(), install_command);
+        if !variables.is_empty() {
+            normalized.insert(
+                "variables".to_string(),
+                mcp_install_variables::from_registry_env(variables),
+            );
+        }
+        normalized.insert("packageRegistry".to_string(), json!(registry));
+        normalized.insert("packageName".to_string(), json!(identifier));
+    } else {
+        let remote = server
+            .get("remotes")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .find_map(|remote| {
+                let server_type = match remote.get("type").and_then(Value::as_str)? {
+                    "streamable-http" | "http" => "http",
+                    "sse" => "sse",
+                    _ => return None,
+                };
+                let url = remote.get("url").and_then(Value::as_str)?;
+                Some((server_type, url))
+            })?;
+        let (server_type, url) = remote;
+        normalized.insert(
+            "template".to_string(),
+            json!({ "type": server_type, "config": { "type": server_type, "url": url } }),
+        );
+    }
+
+    if let Some(version) = server.get("version").and_then(Value::as_str) {
+        normalized.insert("version".to_string(), json!(version));
+    }
+    if let Some(repository) = server
+        .get("repository")
+        .and_then(|repository| repository.get("url"))
+        .and_then(Value::as_str)
+        .filter(|url| !url.is_empty())
+    {
+        let url =
+            McpService::normalize_github_url(repository).unwrap_or_else(|| repository.to_string());
+        normalized.insert(
+            "repository".to_string(),
+            json!({ "type": "git", "url": url }),
+        );
+    }
+    if let Some(published_at) = meta
+        .and_then(|meta| meta.get("publishedAt"))
+        .and_then(Value::as_str)
+    {
+        normalized.insert("createdAt".to_string(), json!(published_at));
+    }
+    let updated_at = meta
+        .and_then(|meta| meta.get("updatedAt").or_else(|| meta.get("publishedAt")))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| now.to_rfc3339());
+    normalized.insert("updatedAt".to_string(), json!(updated_at));
+    normalized.insert("source".to_string(), json!(OFFICIAL_SOURCE));
+    Some(Value::Object(normalized))
+}
+
+/// The registry, identifier, command and arguments that run a package.
+fn package_command(package: &Value) -> Option<(String, String, String, Vec<String>)> {
+    let registry = package
+        .get("registryType")
+        .or_else(|| package.get("registry_type"))
+        .or_else(|| package.get("registry_name"))
+        .and_then(Value::as_str)?;
+    let identifier = package
+        .get("identifier")
+        .or_else(|| package.get("name"))
+        .and_then(Value::as_str)?;
+    let (command, args) = match registry {
+        "npm" => ("npx", vec!["-y".to_string(), identifier.to_string()]),
+        "pypi" => ("uvx", vec![identifier.to_string()]),
+        "oci" | "docker" => (
+            "docker",
+            vec![
+                "run".to_string(),
+                "-i".to_string(),
+                "--rm".to_string(),
+                identifier.to_string(),
+            ],
+        ),
+        _ => return None,
+    };
+    Some((
+        registry.to_string(),
+        identifier.to_string(),
+        command.to_string(),
+        args,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn official_entries_become_installable_servers() {
+        let now = Utc::now();
+        let raw = json!({
+            "servers": [
+                {
+                    "server": {
+                        "name": "io.github.acme/weather",
+                        "title": "Weather",
+                        "description": "Forecasts",
+                        "version": "1.2.0",
+                        "repository": { "url": "https://github.com/acme/weather", "source": "github" },
+                        "packages": [
+                            { "registryType": "nuget", "identifier": "Acme.Weather" },
+                            {
+                                "registryType": "npm",
+                                "identifier": "@acme/weather",
+                                "transport": { "type": "stdio" },
+                                "environmentVariables": [{ "name": "WEATHER_API_KEY", "isSecret": true }]
+                            }
+                        ]
+                    },
+                    "_meta": {
+                        "io.modelcontextprotocol.registry/official": {
+                            "status": "active",
+                            "publishedAt": "2025-09-01T00:00:00Z",
+                            "isLatest": true
+                        }
+                    }
+                },
+                {
+                    "server": {
+                        "name": "com.example/remote",
+                        "remotes": [{ "type": "streamable-http", "url": "https://mcp.example.com/mcp" }]
+                    }
+                },
+                {
+                    "server": { "name": "io.github.acme/weather", "version": "1.1.0" },
+                    "_meta": { "io.modelcontextprotocol.registry/official": { "isLatest": false } }
+                },
+                { "server": { "name": "com.example/nothing-to-run" } }
+            ],
+            "metadata": { "nextCursor": "abc", "count": 4 }
+        });
+
+        let (servers, total, has_more) = OfficialRegistrySource.normalize(raw, now);
+        assert_eq!((total, has_more), (2, true));
+
+        let weather = &servers[0];
+        assert_eq!(weather["id"], "io.github.acme/weather");
+        assert_eq!(weather["name"], "Weather");
+        assert_eq!(weather["source"], OFFICIAL_SOURCE);
+        assert_eq!(weather["packageName"], "@acme/weather");
+        assert_eq!(weather["updatedAt"], "2025-09-01T00:00:00Z");
+        assert_eq!(
+            weather["template"]["config"],
+            json!({
+                "type": "custom",
+                "command": "npx",
+                "args": ["-y", "@acme/weather"],
+                "env": { "WEATHER_API_KEY": "" }
+            })
+        );
+        assert_eq!(
+            weather["variables"],
+            json!([{ "name": "WEATHER_API_KEY", "secret": true }])
+        );
+
+        let remote = &servers[1];
+        assert_eq!(
+            remote["template"],
+            json!({ "type": "http", "config": { "type": "http", "url": "https://mcp.example.com/mcp" } })
+        );
+        assert!(remote.get("installCommand").is_none());
+
+        let client = Client::new();
+        let page = |limit, cursor| RegistryPage {
+            query: Some("weather"),
+            limit,
+            offset: Some(10),
+            cursor,
+        };
+        let request = |page| {
+            OfficialRegistrySource
+                .search_request(&client, &page)
+                .unwrap()
+                .build()
+                .unwrap()
+        };
+        assert_eq!(
+            request(page(500, None)).url().query(),
+            Some("limit=100&search=weather")
+        );
+        assert_eq!(
+            request(page(20, Some("abc"))).url().query(),
+            Some("limit=30&search=weather&cursor=abc")
+        );
+    }
+}
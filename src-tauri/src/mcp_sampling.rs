@@ -0,0 +1,398 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use rmcp::ClientHandler;
+use rmcp::ErrorData as McpError;
+use rmcp::model::{
+    ClientInfo, Content, CreateMessageRequestParam, CreateMessageResult, JsonObject,
+    ProgressNotificationParam, Role, SamplingMessage,
+};
+use rmcp::service::{NotificationContext, RequestContext, RoleClient};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::agent::AgentLlmSettings;
+use crate::connection::{LlmCompletion, LlmCompletionRequest, LlmMessage, LlmStopReason};
+use crate::mcp_sandbox::ProcessGroups;
+use crate::mcp_tool_calls::McpToolCalls;
+
+/// A server's `sampling/createMessage` request, handed to the app to approve
+/// and run. The reply goes back to the server as the request's result.
+pub struct SamplingCall {
+    pub server_id: String,
+    pub params: CreateMessageRequestParam,
+    pub reply: oneshot::Sender<Result<CreateMessageResult, String>>,
+}
+
+/// The client side of every MCP session. Sampling is only advertised when
+/// the app listens for sampling calls.
+#[derive(Clone)]
+pub struct McpClientHandler {
+    server_id: String,
+    sampling: Option<mpsc::UnboundedSender<SamplingCall>>,
+    tool_list_changed: Option<mpsc::UnboundedSender<String>>,
+    tool_calls: Arc<McpToolCalls>,
+    process_group: Option<(Arc<ProcessGroups>, u32)>,
+}
+
+impl McpClientHandler {
+    pub fn new(server_id: &str, sampling: Option<mpsc::UnboundedSender<SamplingCall>>) -> Self {
+        Self {
+            server_id: server_id.to_string(),
+            sampling,
+            tool_list_changed: None,
+            tool_calls: Arc::new(McpToolCalls::default()),
+            process_group: None,
+        }
+    }
+
+    /// Sends the server id on `tools/list_changed` so its tools are listed
+    /// again.
+    pub fn with_tool_list_changes(
+        mut self,
+        tool_list_changed: Option<mpsc::UnboundedSender<String>>,
+    ) -> Self {
+        self.tool_list_changed = tool_list_changed;
+        self
+    }
+
+    /// Reports progress on the server's running calls through `tool_calls`.
+    pub fn with_tool_calls(mut self, tool_calls: Arc<McpToolCalls>) -> Self {
+        self.tool_calls = tool_calls;
+        self
+    }
+
+    /// Records, in `groups`, the process group a custom server leads, to
+    /// kill what it leaves behind when the session closes.
+    pub fn with_process_group(mut self, groups: &Arc<ProcessGroups>, id: Option<u32>) -> Self {
+        if let Some(id) = id {
+            groups.track(id);
+            self.process_group = Some((groups.clone(), id));
+        }
+        self
+    }
+
+    /// Kills whatever is left of the server's process group. The transport
+    /// only waits for the server itself, so children it started, such as
+    /// the `node` process behind `npx`, can outlive it.
+    pub fn kill_process_group(&self) {
+        if let Some((groups, id)) = self.process_group.as_ref() {
+            groups.kill(*id);
+        }
+    }
+}
+
+impl ClientHandler for McpClientHandler {
+    async fn create_message(
+        &self,
+        params: CreateMessageRequestParam,
+        _context: RequestContext<RoleClient>,
+    ) -> Result<CreateMessageResult, McpError> {
+        let Some(sampling) = self.sampling.as_ref() else {
+            return Err(McpError::invalid_request("Sampling is not available", None));
+        };
+        let (reply, receiver) = oneshot::channel();
+        sampling
+            .send(SamplingCall {
+                server_id: self.server_id.clone(),
+                params,
+                reply,
+            })
+            .map_err(|_| McpError::internal_error("Sampling is not available", None))?;
+        match receiver.await {
+            Ok(Ok(result)) => Ok(result),
+            Ok(Err(error)) => Err(McpError::invalid_request(error, None)),
+            Err(_) => Err(McpError::internal_error(
+                "Sampling request was dropped",
+                None,
+            )),
+        }
+    }
+
+    async fn on_tool_list_changed(&self, _context: NotificationContext<RoleClient>) {
+        if let Some(changed) = self.tool_list_changed.as_ref() {
+            let _ = changed.send(self.server_id.clone());
+        }
+    }
+
+    async fn on_progress(
+        &self,
+        params: ProgressNotificationParam,
+        _context: NotificationContext<RoleClient>,
+    ) {
+        self.tool_calls.report(&self.server_id, params);
+    }
+
+    fn get_info(&self) -> ClientInfo {
+        let mut info = ClientInfo::default();
+        if self.sampling.is_some() {
+            info.capabilities.sampling = Some(JsonObject::new());
+        }
+        info
+    }
+}
+
+/// What the renderer shows the user before a server may use the LLM.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SamplingApprovalRequest {
+    pub request_id: String,
+    pub server_id: String,
+    pub system_prompt: Option<String>,
+    pub messages: Vec<SamplingPreviewMessage>,
+    pub max_tokens: u32,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SamplingPreviewMessage {
+    pub role: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SamplingDecision {
+    pub approved: bool,
+    /// Approves the server's later requests until the app restarts.
+    #[serde(default)]
+    pub remember: bool,
+}
+
+/// Sampling requests waiting on the user, and the servers they have trusted
+/// for this run of the app.
+#[derive(Default)]
+pub struct SamplingApprovals {
+    trusted: HashSet<String>,
+    pending: HashMap<String, (String, oneshot::Sender<SamplingDecision>)>,
+}
+
+impl SamplingApprovals {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_trusted(&self, server_id: &str) -> bool {
+        self.trusted.contains(server_id)
+    }
+
+    pub fn request(
+        &mut self,
+        server_id: &str,
+        params: &CreateMessageRequestParam,
+    ) -> (SamplingApprovalRequest, oneshot::Receiver<SamplingDecision>) {
+        let (sender, receiver) = oneshot::channel();
+        let request = SamplingApprovalRequest {
+            request_id: uuid::Uuid::new_v4().to_string(),
+            server_id: server_id.to_string(),
+            system_prompt: params.system_prompt.clone(),
+            messages: params
+                .messages
+                .iter()
+                .map(|message| SamplingPreviewMessage {
+                    role: role_name(&message.role).to_string(),
+                    text: message
+                        .content
+                        .as_text()
+                        .map(|content| content.text.clone())
+                        .unwrap_or_else(|| "[non-text content]".to_string()),
+                })
+                .collect(),
+            max_tokens: params.max_tokens,
+        };
+        self.pending
+            .insert(request.request_id.clone(), (server_id.to_string(), sender));
+        (request, receiver)
+    }
+
+    pub fn resolve(&mut self, request_id: &str, decision: SamplingDecision) -> Result<(), String> {
+        let (server_id, sender) = self
+            .pending
+            .remove(request_id)
+            .ok_or_else(|| "Sampling request not found or already resolved".to_string())?;
+        if decision.approved && decision.remember {
+            self.trusted.insert(server_id);
+        }
+        let _ = sender.send(decision);
+        Ok(())
+    }
+
+    pub fn cancel(&mut self, request_id: &str) {
+        self.pending.remove(request_id);
+    }
+}
+
+/// Builds the completion for a sampling request on the agent's LLM. Only
+/// text messages are supported; model hints are ignored in favour of the
+/// configured model.
+pub fn completion_request(
+    params: &CreateMessageRequestParam,
+    settings: &AgentLlmSettings,
+) -> Result<LlmCompletionRequest, String> {
+    let model = settings
+        .model
+        .clone()
+        .ok_or_else(|| "No model is configured for the agent".to_string())?;
+    let messages = params
+        .messages
+        .iter()
+        .map(|message| {
+            let text = message
+                .content
+                .as_text()
+                .ok_or_else(|| "Only text sampling messages are supported".to_string())?;
+            Ok(LlmMessage {
+                role: role_name(&message.role).to_string(),
+                content: text.text.clone(),
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    if messages.is_empty() {
+        return Err("Sampling request has no messages".to_string());
+    }
+
+    Ok(LlmCompletionRequest {
+        provider: settings.provider,
+        api_key: settings.api_key.clone(),
+        model,
+        base_url: settings.base_url.clone(),
+        system: params
+            .system_prompt
+            .clone()
+            .filter(|prompt| !prompt.trim().is_empty()),
+        messages,
+        max_tokens: params.max_tokens,
+        temperature: params.temperature,
+        stop_sequences: params.stop_sequences.clone().unwrap_or_default(),
+    })
+}
+
+pub fn into_result(completion: LlmCompletion) -> CreateMessageResult {
+    let stop_reason = completion.stop_reason.map(|reason| match reason {
+        LlmStopReason::EndTurn => CreateMessageResult::STOP_REASON_END_TURN.to_string(),
+        LlmStopReason::MaxTokens => CreateMessageResult::STOP_REASON_END_MAX_TOKEN.to_string(),
+        LlmStopReason::StopSequence => CreateMessageResult::STOP_REASON_END_SEQUENCE.to_string(),
+        LlmStopReason::Other(reason) => reason,
+    });
+    CreateMessageResult {
+        model: completion.model,
+        stop_reason,
+        message: SamplingMessage {
+            role: Role::Assistant,
+            content: Content::text(completion.text),
+        },
+    }
+}
+
+fn role_name(role: &Role) -> &'static str {
+    match role {
+        Role::User => "user",
+        Role::Assistant => "assistant",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LlmProvider;
+
+    fn params(messages: Vec<SamplingMessage>) -> CreateMessageRequestParam {
+        CreateMessageRequestParam {
+            messages,
+            model_preferences: None,
+            system_prompt: Some("Answer in one line.".to_string()),
+            include_context: None,
+            temperature: Some(0.2),
+            max_tokens: 128,
+            stop_sequences: None,
+            metadata: None,
+        }
+    }
+
+    fn settings(model: Option<&str>) -> AgentLlmSettings {
+        AgentLlmSettings {
+            provider: LlmProvider::Openai,
+            api_key: "sk-test".into(),
+            model: model.map(str::to_string),
+            base_url: None,
+        }
+    }
+
+    #[test]
+    fn sampling_params_become_a_completion_on_the_agent_model() {
+        let text = params(vec![
+            SamplingMessage {
+                role: Role::User,
+                content: Content::text("What is HCS?"),
+            },
+            SamplingMessage {
+                role: Role::Assistant,
+                content: Content::text("A consensus service."),
+            },
+        ]);
+
+        let request = completion_request(&text, &settings(Some("gpt-5"))).unwrap();
+        assert_eq!(request.model, "gpt-5");
+        assert_eq!(request.system.as_deref(), Some("Answer in one line."));
+        assert_eq!(request.messages[1].role, "assistant");
+        assert_eq!(request.max_tokens, 128);
+
+        assert_eq!(
+            completion_request(&text, &settings(None)).unwrap_err(),
+            "No model is configured for the agent"
+        );
+        let image = params(vec![SamplingMessage {
+            role: Role::User,
+            content: Content::image("aGk=", "image/png"),
+        }]);
+        assert!(completion_request(&image, &settings(Some("gpt-5"))).is_err());
+
+        let result = into_result(LlmCompletion {
+            model: "gpt-5".to_string(),
+            text: "Hedera Consensus Service.".to_string(),
+            stop_reason: Some(LlmStopReason::MaxTokens),
+            headers: HashMap::new(),
+        });
+        assert_eq!(result.stop_reason.as_deref(), Some("maxTokens"));
+        assert_eq!(
+            result.message.content.as_text().unwrap().text,
+            "Hedera Consensus Service."
+        );
+    }
+
+    #[tokio::test]
+    async fn remembered_approvals_trust_the_server() {
+        let mut approvals = SamplingApprovals::new();
+        let call = params(vec![SamplingMessage {
+            role: Role::User,
+            content: Content::text("Summarize"),
+        }]);
+
+        let (request, receiver) = approvals.request("search", &call);
+        assert_eq!(request.messages[0].text, "Summarize");
+        assert!(!approvals.is_trusted("search"));
+
+        approvals
+            .resolve(
+                &request.request_id,
+                SamplingDecision {
+                    approved: true,
+                    remember: true,
+                },
+            )
+            .unwrap();
+        assert!(receiver.await.unwrap().approved);
+        assert!(approvals.is_trusted("search"));
+        assert!(
+            approvals
+                .resolve(
+                    &request.request_id,
+                    SamplingDecision {
+                        approved: true,
+                        remember: false,
+                    },
+                )
+                .is_err()
+        );
+    }
+}
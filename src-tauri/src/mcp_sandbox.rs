@@ -0,0 +1,529 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[cfg(unix)]
+use process_wrap::tokio::ProcessGroup;
+use process_wrap::tokio::TokioCommandWrap;
+#[cfg(windows)]
+use process_wrap::tokio::{JobObject, KillOnDrop};
+use serde_json::Value;
+
+/// App environment variables every custom server keeps: what runtimes such
+/// as `npx` and `uvx` need to start, reach the network through a proxy and
+/// find their config, display and `nvm` install. Matched case-insensitively
+/// because Windows variable names are.
+const BASE_ENV: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "SHELL",
+    "LANG",
+    "LC_ALL",
+    "LC_CTYPE",
+    "TERM",
+    "TMPDIR",
+    "TEMP",
+    "TMP",
+    "SYSTEMROOT",
+    "SYSTEMDRIVE",
+    "WINDIR",
+    "COMSPEC",
+    "PATHEXT",
+    "USERPROFILE",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "PROGRAMFILES",
+    "PROGRAMDATA",
+    "HTTP_PROXY",
+    "HTTPS_PROXY",
+    "ALL_PROXY",
+    "NO_PROXY",
+    "XDG_CONFIG_HOME",
+    "XDG_CACHE_HOME",
+    "XDG_DATA_HOME",
+    "XDG_STATE_HOME",
+    "XDG_RUNTIME_DIR",
+    "DISPLAY",
+    "WAYLAND_DISPLAY",
+    "NVM_DIR",
+    "NVM_BIN",
+    "NVM_INC",
+];
+
+/// How a custom server's process is confined, from its `config.sandbox`.
+/// By default the process gets only [`BASE_ENV`], the variables named in
+/// `passEnv` and its configured `env`, and runs in `config.cwd` or a
+/// directory of its own rather than the app's. `inheritEnv` restores the
+/// full app environment. `os` adds OS-level sandboxing: writes outside the
+/// working directory and caches are denied on macOS and privileged syscalls
+/// are refused on Linux; Windows has nothing beyond the job object every
+/// server runs in. `allowNetwork: false` also blocks IP sockets on macOS and
+/// Linux.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SandboxPolicy {
+    pub inherit_env: bool,
+    pub pass_env: Vec<String>,
+    pub working_dir: PathBuf,
+    pub os: bool,
+    pub allow_network: bool,
+}
+
+impl SandboxPolicy {
+    /// `servers_dir` holds the working directories of servers without a
+    /// `config.cwd`.
+    pub fn from_server(server: &Value, servers_dir: &Path) -> Self {
+        let config = server.get("config");
+        let sandbox = config.and_then(|config| config.get("sandbox"));
+        let flag = |name: &str, default: bool| {
+            sandbox
+                .and_then(|sandbox| sandbox.get(name))
+                .and_then(Value::as_bool)
+                .unwrap_or(default)
+        };
+        let pass_env = sandbox
+            .and_then(|sandbox| sandbox.get("passEnv"))
+            .and_then(Value::as_array)
+            .map(|names| {
+                names
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let working_dir = config
+            .and_then(|config| config.get("cwd"))
+            .and_then(Value::as_str)
+            .filter(|cwd| !cwd.trim().is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| {
+                let id = server.get("id").and_then(Value::as_str).unwrap_or("server");
+                servers_dir.join(dir_name(id))
+            });
+
+        Self {
+            inherit_env: flag("inheritEnv", false),
+            pass_env,
+            working_dir,
+            os: flag("os", false),
+            allow_network: flag("allowNetwork", true),
+        }
+    }
+
+    /// The variables taken from `app_env`, before the server's own `env` is
+    /// applied. Empty when the full environment is inherited.
+    pub fn passed_env(
+        &self,
+        app_env: impl IntoIterator<Item = (String, String)>,
+    ) -> Vec<(String, String)> {
+        if self.inherit_env {
+            return Vec::new();
+        }
+        app_env
+            .into_iter()
+            .filter(|(key, _)| {
+                BASE_ENV
+                    .iter()
+                    .copied()
+                    .chain(self.pass_env.iter().map(String::as_str))
+                    .any(|name| name.eq_ignore_ascii_case(key))
+            })
+            .collect()
+    }
+
+    /// The program and arguments to spawn, wrapped in `sandbox-exec` when
+    /// macOS sandboxing is on.
+    pub fn command(&self, command: &str, args: &[String]) -> Result<(String, Vec<String>), String> {
+        if !self.os {
+            return Ok((command.to_string(), args.to_vec()));
+        }
+        if cfg!(target_os = "macos") {
+            let home = std::env::var("HOME").ok().map(PathBuf::from);
+            let mut wrapped = vec![
+                "-p".to_string(),
+                self.macos_profile(&std::env::temp_dir(), home.as_deref()),
+                command.to_string(),
+            ];
+            wrapped.extend(args.iter().cloned());
+            Ok(("/usr/bin/sandbox-exec".to_string(), wrapped))
+        } else if cfg!(any(target_os = "linux", windows)) {
+            Ok((command.to_string(), args.to_vec()))
+        } else {
+            Err("OS sandboxing of MCP servers is not supported on this platform".to_string())
+        }
+    }
+
+    /// A `sandbox-exec` profile allowing writes only to the working
+    /// directory, temp directories and package caches under `home`.
+    pub fn macos_profile(&self, temp_dir: &Path, home: Option<&Path>) -> String {
+        let mut writable = vec![
+            self.working_dir.clone(),
+            temp_dir.to_path_buf(),
+            PathBuf::from("/private/tmp"),
+            PathBuf::from("/private/var/folders"),
+        ];
+        if let Some(home) = home {
+            writable.push(home.join(".npm"));
+            writable.push(home.join(".cache"));
+            writable.push(home.join("Library/Caches"));
+        }
+        let subpaths = writable
+            .iter()
+            .map(|path| format!("(subpath {})", quote(&path.to_string_lossy())))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut profile = format!(
+            "(version 1)\n(allow default)\n(deny file-write*)\n(allow file-write* {subpaths} (literal \"/dev/null\") (literal \"/dev/tty\"))\n"
+        );
+        if !self.allow_network {
+            profile.push_str("(deny network* (remote ip))\n");
+        }
+        profile
+    }
+}
+
+/// Pins custom servers without a `config.cwd` to `cwd`, the directory they
+/// ran in before servers got directories of their own, so relative paths in
+/// their args keep working. Returns whether any server changed.
+pub fn keep_legacy_cwd(servers: &mut [Value], cwd: &Path) -> bool {
+    let mut changed = false;
+    for server in servers {
+        let Some(config) = server.get_mut("config").and_then(Value::as_object_mut) else {
+            continue;
+        };
+        let custom = config.get("type").and_then(Value::as_str) == Some("custom");
+        let has_cwd = config
+            .get("cwd")
+            .and_then(Value::as_str)
+            .is_some_and(|cwd| !cwd.trim().is_empty());
+        if custom && !has_cwd {
+            config.insert(
+                "cwd".to_string(),
+                Value::String(cwd.to_string_lossy().into_owned()),
+            );
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Wraps `command` so killing the server also kills what it started, such
+/// as the `node` process behind `npx`: the server leads its own process
+/// group on unix and runs in a job object, terminated when the session
+/// drops the child, on Windows.
+pub fn with_process_tree(command: tokio::process::Command) -> TokioCommandWrap {
+    let mut wrapped = TokioCommandWrap::from(command);
+    #[cfg(unix)]
+    wrapped.wrap(ProcessGroup::leader());
+    #[cfg(windows)]
+    wrapped.wrap(KillOnDrop).wrap(JobObject);
+    wrapped
+}
+
+/// Process groups led by running custom servers. The app keeps these apart
+/// from the MCP service so it can kill them on exit even while the service
+/// is busy.
+#[derive(Default)]
+pub struct ProcessGroups {
+    groups: Mutex<HashSet<u32>>,
+}
+
+impl ProcessGroups {
+    pub fn track(&self, id: u32) {
+        self.lock().insert(id);
+    }
+
+    /// Kills the group, if it is still tracked, and stops tracking it.
+    pub fn kill(&self, id: u32) {
+        if self.lock().remove(&id) {
+            kill_group(id);
+        }
+    }
+
+    pub fn kill_all(&self) {
+        let groups: Vec<u32> = self.lock().drain().collect();
+        for id in groups {
+            kill_group(id);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashSet<u32>> {
+        self.groups
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Kills what is left of a server's process group. A group id is its
+/// leader's pid and is only handed out again once the whole group is gone,
+/// to a process that then leads a group of its own. A live process with
+/// that pid outside the app's session is therefore someone else's, and is
+/// left alone. Windows servers run in a job object that ends with the
+/// session instead.
+fn kill_group(id: u32) {
+    #[cfg(unix)]
+    if let Ok(group) = libc::pid_t::try_from(id) {
+        // SAFETY: getsid and killpg take no pointers; a group that already
+        // exited just reports ESRCH.
+        unsafe {
+            let leader_session = libc::getsid(group);
+            if leader_session != -1 && leader_session != libc::getsid(0) {
+                log::warn!("Not killing process group {id}: its id was reused");
+                return;
+            }
+            libc::killpg(group, libc::SIGKILL);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = id;
+}
+
+/// Installs `filter` in the calling process. Only meant for a forked child
+/// before it execs, so it does not allocate.
+#[cfg(target_os = "linux")]
+pub fn restrict_current_process(filter: &[libc::sock_filter]) -> std::io::Result<()> {
+    let program = libc::sock_fprog {
+        len: filter.len() as libc::c_ushort,
+        filter: filter.as_ptr() as *mut libc::sock_filter,
+    };
+    // SAFETY: `program` points at `filter`, which outlives both calls, and
+    // these prctl options take no other pointers.
+    unsafe {
+        if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        if libc::prctl(
+            libc::PR_SET_SECCOMP,
+            libc::SECCOMP_MODE_FILTER,
+            &program as *const libc::sock_fprog,
+        ) != 0
+        {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// A seccomp program refusing with `EPERM` the syscalls an MCP server has no
+/// business making, and IP sockets unless `allow_network`.
+#[cfg(target_os = "linux")]
+pub fn seccomp_filter(allow_network: bool) -> Result<Vec<libc::sock_filter>, String> {
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xC000_003E;
+    #[cfg(target_arch = "x86_64")]
+    const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xC000_00B7;
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    return Err("OS sandboxing of MCP servers is not supported on this architecture".to_string());
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    {
+        const ARCH_OFFSET: u32 = 4;
+        const NR_OFFSET: u32 = 0;
+        const ARG0_OFFSET: u32 = 16;
+        let denied = [
+            libc::SYS_ptrace,
+            libc::SYS_process_vm_readv,
+            libc::SYS_process_vm_writev,
+            libc::SYS_mount,
+            libc::SYS_umount2,
+            libc::SYS_pivot_root,
+            libc::SYS_chroot,
+            libc::SYS_reboot,
+            libc::SYS_kexec_load,
+            libc::SYS_init_module,
+            libc::SYS_finit_module,
+            libc::SYS_delete_module,
+            libc::SYS_bpf,
+            libc::SYS_perf_event_open,
+            libc::SYS_keyctl,
+            libc::SYS_add_key,
+            libc::SYS_request_key,
+            libc::SYS_setns,
+        ];
+        let load =
+            |offset: u32| bpf_stmt((libc::BPF_LD | libc::BPF_W | libc::BPF_ABS) as u16, offset);
+        let jump_if = |value: u32, jt: u8, jf: u8| libc::sock_filter {
+            code: (libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K) as u16,
+            jt,
+            jf,
+            k: value,
+        };
+        let ret = |value: u32| bpf_stmt((libc::BPF_RET | libc::BPF_K) as u16, value);
+        let refuse = ret(libc::SECCOMP_RET_ERRNO | libc::EPERM as u32);
+
+        let mut filter = vec![
+            load(ARCH_OFFSET),
+            jump_if(AUDIT_ARCH, 1, 0),
+            ret(libc::SECCOMP_RET_KILL_PROCESS),
+            load(NR_OFFSET),
+        ];
+        // x32 syscalls share the x86_64 arch but set this bit in the number,
+        // which would otherwise slip past the deny list.
+        #[cfg(target_arch = "x86_64")]
+        filter.extend([
+            libc::sock_filter {
+                code: (libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K) as u16,
+                jt: 0,
+                jf: 1,
+                k: X32_SYSCALL_BIT,
+            },
+            ret(libc::SECCOMP_RET_KILL_PROCESS),
+        ]);
+        for syscall in denied {
+            filter.push(jump_if(syscall as u32, 0, 1));
+            filter.push(refuse);
+        }
+        if !allow_network {
+            filter.extend([
+                jump_if(libc::SYS_socket as u32, 0, 5),
+                load(ARG0_OFFSET),
+                jump_if(libc::AF_INET as u32, 2, 0),
+                jump_if(libc::AF_INET6 as u32, 1, 0),
+                ret(libc::SECCOMP_RET_ALLOW),
+                refuse,
+            ]);
+        }
+        filter.push(ret(libc::SECCOMP_RET_ALLOW));
+        Ok(filter)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn bpf_stmt(code: u16, k: u32) -> libc::sock_filter {
+    libc::sock_filter {
+        code,
+        jt: 0,
+        jf: 0,
+        k,
+    }
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn dir_name(id: &str) -> String {
+    let name: String = id
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_') {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() {
+        "server".to_string()
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn sandbox_scrubs_the_environment_and_working_directory() {
+        let servers_dir = Path::new("/data/mcp-sandbox");
+        let server = json!({
+            "id": "../weather",
+            "config": { "type": "custom", "command": "npx", "sandbox": { "passEnv": ["AWS_PROFILE"] } }
+        });
+        let policy = SandboxPolicy::from_server(&server, servers_dir);
+        assert_eq!(policy.working_dir, servers_dir.join("___weather"));
+        assert!(!policy.os);
+
+        let app_env = [
+            ("Path", "/usr/bin"),
+            ("OPENAI_API_KEY", "sk-secret"),
+            ("AWS_PROFILE", "dev"),
+            ("https_proxy", "http://proxy:3128"),
+        ]
+        .map(|(key, value)| (key.to_string(), value.to_string()));
+        let passed = policy.passed_env(app_env.clone());
+        assert_eq!(
+            passed
+                .iter()
+                .map(|(key, _)| key.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Path", "AWS_PROFILE", "https_proxy"]
+        );
+
+        let inherited = SandboxPolicy::from_server(
+            &json!({ "config": { "cwd": "/srv/tools", "sandbox": { "inheritEnv": true, "os": true, "allowNetwork": false } } }),
+            servers_dir,
+        );
+        assert!(inherited.passed_env(app_env).is_empty());
+        assert_eq!(inherited.working_dir, PathBuf::from("/srv/tools"));
+
+        let profile = inherited.macos_profile(Path::new("/tmp"), Some(Path::new("/Users/me")));
+        assert!(profile.contains("(deny file-write*)"));
+        assert!(profile.contains("(subpath \"/srv/tools\")"));
+        assert!(profile.contains("(subpath \"/Users/me/.npm\")"));
+        assert!(profile.contains("(deny network* (remote ip))"));
+    }
+
+    #[test]
+    fn servers_from_before_the_sandbox_keep_the_app_directory() {
+        let mut servers = vec![
+            json!({ "id": "old", "config": { "type": "custom", "command": "node", "args": ["server.js"] } }),
+            json!({ "id": "pinned", "config": { "type": "custom", "command": "node", "cwd": "/srv" } }),
+            json!({ "id": "files", "config": { "type": "filesystem", "rootPath": "/data" } }),
+        ];
+        assert!(keep_legacy_cwd(&mut servers, Path::new("/app")));
+        assert_eq!(servers[0]["config"]["cwd"], json!("/app"));
+        assert_eq!(servers[1]["config"]["cwd"], json!("/srv"));
+        assert!(servers[2]["config"].get("cwd").is_none());
+        assert!(!keep_legacy_cwd(&mut servers, Path::new("/app")));
+    }
+
+    #[cfg(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    #[test]
+    fn seccomp_filter_refuses_privileged_syscalls_in_the_child() {
+        use std::os::unix::process::CommandExt;
+
+        let filter = seccomp_filter(false).expect("filter");
+        let mut command = std::process::Command::new("/bin/sh");
+        command.args(["-c", "echo sandboxed"]);
+        // SAFETY: the closure only makes the prctl calls of
+        // `restrict_current_process`, which are async-signal-safe.
+        unsafe {
+            command.pre_exec(move || restrict_current_process(&filter));
+        }
+        let output = command.output().expect("run sandboxed shell");
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "sandboxed\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn process_groups_kill_only_tracked_groups_once() {
+        use std::os::unix::process::{CommandExt, ExitStatusExt};
+
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .process_group(0)
+            .spawn()
+            .expect("spawn sleep");
+        let groups = ProcessGroups::default();
+        groups.kill(child.id());
+        assert!(child.try_wait().expect("poll child").is_none());
+
+        groups.track(child.id());
+        groups.kill_all();
+        let status = child.wait().expect("wait for child");
+        assert_eq!(status.signal(), Some(libc::SIGKILL));
+        assert!(groups.lock().is_empty());
+    }
+}
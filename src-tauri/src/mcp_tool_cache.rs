@@ -0,0 +1,480 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+#[derive(Clone, Debug)]
+pub struct ToolCacheBudget {
+    pub max_entries: usize,
+    pub max_bytes: usize,
+    pub idle_ttl: Duration,
+}
+
+impl Default for ToolCacheBudget {
+    fn default() -> Self {
+        Self {
+            max_entries: 32,
+            max_bytes: 8 * 1024 * 1024,
+            idle_ttl: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolCacheStats {
+    pub entries: usize,
+    pub bytes: usize,
+    pub max_entries: usize,
+    pub max_bytes: usize,
+    pub evictions: u64,
+    pub evicted_servers: usize,
+}
+
+struct ToolCacheEntry {
+    tools: Vec<Value>,
+    bytes: usize,
+    last_used: Instant,
+}
+
+/// Tool lists for connected MCP servers, bounded by entry count and
+/// serialized size. Least recently used entries are evicted first; evicted
+/// servers are remembered so callers can refetch their tools on demand.
+pub struct ToolCache {
+    budget: ToolCacheBudget,
+    entries: HashMap<String, ToolCacheEntry>,
+    evicted: HashSet<String>,
+    total_bytes: usize,
+    evictions: u64,
+}
+
+impl ToolCache {
+    pub fn new(budget: ToolCacheBudget) -> Self {
+        Self {
+            budget,
+            entries: HashMap::new(),
+            evicted: HashSet::new(),
+            total_bytes: 0,
+            evictions: 0,
+        }
+    }
+
+    pub fn insert(&mut self, server_id: &str, tools: Vec<Value>) -> Vec<String> {
+        self.remove(server_id);
+        let bytes = serde_json::to_vec(&tools)
+            .map(|encoded| encoded.len())
+            .unwrap_or_default();
+        self.total_bytes += bytes;
+        self.entries.insert(
+            server_id.to_string(),
+            ToolCacheEntry {
+                tools,
+                bytes,
+                last_used: Instant::now(),
+            },
+        );
+        self.enforce_budget(server_id)
+    }
+
+    pub fn get(&mut self, server_id: &str) -> Option<Vec<Value>> {
+        let entry = self.entries.get_mut(server_id)?;
+        entry.last_used = Instant::now();
+        Some(entry.tools.clone())
+    }
+
+    pub fn remove(&mut self, server_id: &str) -> bool {
+        self.evicted.remove(server_id);
+        match self.entries.remove(server_id) {
+            Some(entry) => {
+                self.total_bytes -= entry.bytes;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn was_evicted(&self, server_id: &str) -> bool {
+        self.evicted.contains(server_id)
+    }
+
+    /// Drops entries that have not been read within the idle TTL.
+    pub fn evict_idle(&mut self, now: Instant) -> Vec<String> {
+        let idle: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| now.duration_since(entry.last_used) >= self.budget.idle_ttl)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &idle {
+            self.evict(id);
+        }
+        idle
+    }
+
+    pub fn stats(&self) -> ToolCacheStats {
+        ToolCacheStats {
+            entries: self.entries.len(),
+            bytes: self.total_bytes,
+            max_entries: self.budget.max_entries,
+            max_bytes: self.budget.max_bytes,
+            evictions: self.evictions,
+            evicted_servers: self.evicted.len(),
+        }
+    }
+
+    fn enforce_budget(&mut self, keep: &str) -> Vec<String> {
+        let mut evicted = Vec::new();
+        while self.entries.len() > self.budget.max_entries
+            || (self.total_bytes > self.budget.max_bytes && self.entries.len() > 1)
+        {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .filter(|(id, _)| id.as_str() != keep)
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(id, _)| id.clone())
+            else {
+                break;
+            };
+            self.evict(&oldest);
+            evicted.push(oldest);
+        }
+        evicted
+    }
+
+    fn evict(&mut self, server_id: &str) {
+        if let Some(entry) = self.entries.remove(server_id) {
+            self.total_bytes -= entry.bytes;
+            self.evictions += 1;
+            self.evicted.insert(server_id.to_string());
+        }
+    }
+}
+
+/// How long results of a server's tools are reused, from its
+/// `config.resultCache`: `ttlSeconds`, and optionally the `tools` to cache
+/// when not all of them are deterministic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ToolResultPolicy {
+    pub ttl: Duration,
+    pub tools: Option<HashSet<String>>,
+}
+
+impl ToolResultPolicy {
+    pub fn from_server(server: &Value) -> Option<Self> {
+        let config = server.get("config")?.get("resultCache")?;
+        let ttl = config
+            .get("ttlSeconds")
+            .and_then(Value::as_u64)
+            .filter(|seconds| *seconds > 0)?;
+        let tools = config.get("tools").and_then(Value::as_array).map(|tools| {
+            tools
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect()
+        });
+        Some(Self {
+            ttl: Duration::from_secs(ttl),
+            tools,
+        })
+    }
+
+    pub fn covers(&self, tool_name: &str) -> bool {
+        self.tools
+            .as_ref()
+            .is_none_or(|tools| tools.contains(tool_name))
+    }
+}
+
+/// Identifies a tool call by server, tool and a hash of its arguments with
+/// object keys sorted, so argument order does not matter.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ToolResultKey {
+    server_id: String,
+    tool_name: String,
+    arguments_hash: String,
+}
+
+impl ToolResultKey {
+    pub fn new(server_id: &str, tool_name: &str, arguments: &Value) -> Self {
+        let mut hasher = Sha256::new();
+        hash_canonical(arguments, &mut hasher);
+        let arguments_hash = hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        Self {
+            server_id: server_id.to_string(),
+            tool_name: tool_name.to_string(),
+            arguments_hash,
+        }
+    }
+}
+
+fn hash_canonical(value: &Value, hasher: &mut Sha256) {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            hasher.update(b"{");
+            for key in keys {
+                hasher.update(serde_json::to_string(key).unwrap_or_default().as_bytes());
+                hasher.update(b":");
+                hash_canonical(&map[key], hasher);
+                hasher.update(b",");
+            }
+            hasher.update(b"}");
+        }
+        Value::Array(items) => {
+            hasher.update(b"[");
+            for item in items {
+                hash_canonical(item, hasher);
+                hasher.update(b",");
+            }
+            hasher.update(b"]");
+        }
+        other => hasher.update(other.to_string().as_bytes()),
+    }
+}
+
+struct CachedToolResult<T> {
+    result: T,
+    expires_at: Instant,
+}
+
+/// Results of tool calls on servers that opt in, kept until their TTL
+/// passes. When full, expired results go first and then those closest to
+/// expiring.
+pub struct ToolResultCache<T> {
+    max_entries: usize,
+    entries: HashMap<ToolResultKey, CachedToolResult<T>>,
+    hits: u64,
+    misses: u64,
+}
+
+impl<T: Clone> ToolResultCache<T> {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            entries: HashMap::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn get(&mut self, key: &ToolResultKey, now: Instant) -> Option<T> {
+        match self.entries.get(key) {
+            Some(entry) if entry.expires_at > now => {
+                self.hits += 1;
+                Some(entry.result.clone())
+            }
+            Some(_) => {
+                self.entries.remove(key);
+                self.misses += 1;
+                None
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn insert(&mut self, key: ToolResultKey, result: T, ttl: Duration, now: Instant) {
+        if self.entries.len() >= self.max_entries && !self.entries.contains_key(&key) {
+            self.entries.retain(|_, entry| entry.expires_at > now);
+            if self.entries.len() >= self.max_entries
+                && let Some(soonest) = self
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.expires_at)
+                    .map(|(key, _)| key.clone())
+            {
+                self.entries.remove(&soonest);
+            }
+        }
+        self.entries.insert(
+            key,
+            CachedToolResult {
+                result,
+                expires_at: now + ttl,
+            },
+        );
+    }
+
+    /// Forgets every result from `server_id`.
+    pub fn clear_server(&mut self, server_id: &str) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|key, _| key.server_id != server_id);
+        before - self.entries.len()
+    }
+
+    /// Entries, hits and misses.
+    pub fn stats(&self) -> (usize, u64, u64) {
+        (self.entries.len(), self.hits, self.misses)
+    }
+}
+
+/// How a server's tool list changed between two listings. Tools are
+/// matched by name; a tool whose description or schema differs is modified.
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolListChange {
+    pub server_id: String,
+    pub added: Vec<Value>,
+    pub removed: Vec<String>,
+    pub modified: Vec<Value>,
+}
+
+impl ToolListChange {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+pub fn diff_tools(server_id: &str, previous: &[Value], current: &[Value]) -> ToolListChange {
+    let tool_name = |tool: &Value| tool.get("name").and_then(Value::as_str).map(str::to_string);
+    let before: HashMap<String, &Value> = previous
+        .iter()
+        .filter_map(|tool| Some((tool_name(tool)?, tool)))
+        .collect();
+    let after: HashSet<String> = current.iter().filter_map(tool_name).collect();
+
+    let mut change = ToolListChange {
+        server_id: server_id.to_string(),
+        ..ToolListChange::default()
+    };
+    for tool in current {
+        let Some(name) = tool_name(tool) else {
+            continue;
+        };
+        match before.get(&name) {
+            None => change.added.push(tool.clone()),
+            Some(previous) if *previous != tool => change.modified.push(tool.clone()),
+            Some(_) => {}
+        }
+    }
+    change.removed = previous
+        .iter()
+        .filter_map(tool_name)
+        .filter(|name| !after.contains(name))
+        .collect();
+    change
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn budget(max_entries: usize, max_bytes: usize) -> ToolCacheBudget {
+        ToolCacheBudget {
+            max_entries,
+            max_bytes,
+            idle_ttl: Duration::from_secs(60),
+        }
+    }
+
+    #[test]
+    fn evicts_least_recently_used_entry_over_budget() {
+        let mut cache = ToolCache::new(budget(2, usize::MAX));
+        cache.insert("a", vec![json!({ "name": "read" })]);
+        cache.insert("b", vec![json!({ "name": "write" })]);
+        assert!(cache.get("a").is_some());
+
+        let evicted = cache.insert("c", vec![json!({ "name": "list" })]);
+        assert_eq!(evicted, vec!["b".to_string()]);
+        assert!(cache.was_evicted("b"));
+        assert!(cache.get("b").is_none());
+        assert_eq!(cache.stats().entries, 2);
+        assert_eq!(cache.stats().evictions, 1);
+    }
+
+    #[test]
+    fn byte_budget_keeps_newest_entry() {
+        let mut cache = ToolCache::new(budget(10, 40));
+        cache.insert("a", vec![json!({ "name": "a".repeat(30) })]);
+        let evicted = cache.insert("b", vec![json!({ "name": "b".repeat(30) })]);
+        assert_eq!(evicted, vec!["a".to_string()]);
+        assert!(cache.get("b").is_some());
+
+        assert!(cache.remove("b"));
+        assert_eq!(cache.stats().bytes, 0);
+    }
+
+    #[test]
+    fn tool_results_expire_and_ignore_argument_order() {
+        let server = json!({
+            "config": { "resultCache": { "ttlSeconds": 30, "tools": ["lookup"] } }
+        });
+        let policy = ToolResultPolicy::from_server(&server).unwrap();
+        assert!(policy.covers("lookup") && !policy.covers("write"));
+        assert!(ToolResultPolicy::from_server(&json!({ "config": {} })).is_none());
+
+        let now = Instant::now();
+        let mut cache = ToolResultCache::new(2);
+        let key = ToolResultKey::new(
+            "docs",
+            "lookup",
+            &json!({ "q": "a", "page": { "n": 1, "size": 5 } }),
+        );
+        cache.insert(key, "first", policy.ttl, now);
+        let reordered = ToolResultKey::new(
+            "docs",
+            "lookup",
+            &json!({ "page": { "size": 5, "n": 1 }, "q": "a" }),
+        );
+        assert_eq!(cache.get(&reordered, now), Some("first"));
+        assert_eq!(
+            cache.get(
+                &ToolResultKey::new("docs", "lookup", &json!({ "q": "b" })),
+                now
+            ),
+            None
+        );
+        assert_eq!(cache.get(&reordered, now + Duration::from_secs(31)), None);
+
+        let key = |q: &str| ToolResultKey::new("docs", "lookup", &json!({ "q": q }));
+        cache.insert(key("a"), "a", Duration::from_secs(10), now);
+        cache.insert(key("b"), "b", Duration::from_secs(20), now);
+        cache.insert(key("c"), "c", Duration::from_secs(30), now);
+        assert_eq!(cache.get(&key("a"), now), None);
+        assert_eq!(cache.get(&key("c"), now), Some("c"));
+        assert_eq!(cache.clear_server("docs"), 2);
+        assert_eq!(cache.stats().0, 0);
+    }
+
+    #[test]
+    fn evict_idle_drops_stale_entries() {
+        let mut cache = ToolCache::new(budget(10, usize::MAX));
+        cache.insert("a", vec![json!({ "name": "read" })]);
+        let later = Instant::now() + Duration::from_secs(120);
+        assert_eq!(cache.evict_idle(later), vec!["a".to_string()]);
+        assert!(cache.was_evicted("a"));
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn tool_list_changes_are_matched_by_name() {
+        let previous = vec![
+            json!({ "name": "search", "inputSchema": { "type": "object" } }),
+            json!({ "name": "fetch", "description": "Fetch a page" }),
+            json!({ "name": "old" }),
+        ];
+        let current = vec![
+            json!({ "name": "search", "inputSchema": { "type": "object" } }),
+            json!({ "name": "fetch", "description": "Fetch a URL" }),
+            json!({ "name": "new" }),
+        ];
+
+        let change = diff_tools("docs", &previous, &current);
+        assert_eq!(change.added, vec![json!({ "name": "new" })]);
+        assert_eq!(change.removed, vec!["old".to_string()]);
+        assert_eq!(change.modified[0]["description"], "Fetch a URL");
+        assert!(diff_tools("docs", &current, &current).is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use rmcp::model::{Meta, NumberOrString, ProgressNotificationParam, ProgressToken};
+use serde::Serialize;
+use tokio::sync::{mpsc, oneshot};
+
+/// Progress a server reported for a running tool call.
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpToolProgress {
+    pub call_id: String,
+    pub server_id: String,
+    pub tool_name: String,
+    pub progress: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+struct RunningToolCall {
+    server_id: String,
+    tool_name: String,
+    cancel: oneshot::Sender<()>,
+}
+
+/// Tool calls waiting on their server, by call id. It lives outside the MCP
+/// service lock, which a running call holds, so calls can be cancelled and
+/// report progress while they run.
+#[derive(Default)]
+pub struct McpToolCalls {
+    running: Mutex<HashMap<String, RunningToolCall>>,
+    progress: Option<mpsc::UnboundedSender<McpToolProgress>>,
+}
+
+impl McpToolCalls {
+    /// Sends the progress servers report for running calls to `progress`.
+    pub fn new(progress: mpsc::UnboundedSender<McpToolProgress>) -> Self {
+        Self {
+            running: Mutex::new(HashMap::new()),
+            progress: Some(progress),
+        }
+    }
+
+    /// Registers a call. The receiver completes when the call is cancelled.
+    pub fn start(
+        &self,
+        call_id: &str,
+        server_id: &str,
+        tool_name: &str,
+    ) -> Result<oneshot::Receiver<()>, String> {
+        let mut running = self.lock();
+        if running.contains_key(call_id) {
+            return Err(format!("MCP tool call {call_id} is already running"));
+        }
+        let (cancel, cancelled) = oneshot::channel();
+        running.insert(
+            call_id.to_string(),
+            RunningToolCall {
+                server_id: server_id.to_string(),
+                tool_name: tool_name.to_string(),
+                cancel,
+            },
+        );
+        Ok(cancelled)
+    }
+
+    pub fn finish(&self, call_id: &str) {
+        self.lock().remove(call_id);
+    }
+
+    pub fn cancel(&self, call_id: &str) -> Result<(), String> {
+        let call = self
+            .lock()
+            .remove(call_id)
+            .ok_or_else(|| "MCP tool call not found or already finished".to_string())?;
+        let _ = call.cancel.send(());
+        Ok(())
+    }
+
+    /// Forwards a server's progress notification when its token names one
+    /// of that server's running calls.
+    pub fn report(&self, server_id: &str, params: ProgressNotificationParam) {
+        let Some(progress) = self.progress.as_ref() else {
+            return;
+        };
+        let ProgressToken(NumberOrString::String(call_id)) = params.progress_token else {
+            return;
+        };
+        let running = self.lock();
+        let Some(call) = running
+            .get(call_id.as_ref())
+            .filter(|call| call.server_id == server_id)
+        else {
+            return;
+        };
+        let _ = progress.send(McpToolProgress {
+            call_id: call_id.to_string(),
+            server_id: call.server_id.clone(),
+            tool_name: call.tool_name.clone(),
+            progress: params.progress,
+            total: params.total,
+            message: params.message,
+        });
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, RunningToolCall>> {
+        self.running
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Request metadata that makes the call id the progress token, so progress
+/// can be traced back to the call.
+pub fn progress_meta(call_id: &str) -> Meta {
+    let mut meta = Meta::new();
+    meta.set_progress_token(ProgressToken(NumberOrString::String(call_id.into())));
+    meta
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(call_id: &str, progress: f64) -> ProgressNotificationParam {
+        ProgressNotificationParam {
+            progress_token: ProgressToken(NumberOrString::String(call_id.into())),
+            progress,
+            total: Some(10.0),
+            message: Some("Indexing".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn running_calls_report_progress_and_can_be_cancelled() {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let calls = McpToolCalls::new(sender);
+
+        let cancelled = calls.start("call-1", "search", "index").unwrap();
+        assert!(calls.start("call-1", "search", "index").is_err());
+
+        calls.report("other", progress("call-1", 1.0));
+        calls.report("search", progress("call-1", 4.0));
+        let reported = receiver.try_recv().unwrap();
+        assert_eq!(reported.tool_name, "index");
+        assert_eq!(reported.progress, 4.0);
+        assert!(receiver.try_recv().is_err());
+
+        calls.cancel("call-1").unwrap();
+        assert!(cancelled.await.is_ok());
+        assert!(calls.cancel("call-1").is_err());
+        calls.report("search", progress("call-1", 5.0));
+        assert!(receiver.try_recv().is_err());
+    }
+}
@@ -0,0 +1,288 @@
+use crate::proxy;
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+
+const REGISTRY_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Where an installed server's package comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PackageRegistry {
+    Npm,
+    Pypi,
+}
+
+impl PackageRegistry {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Npm => "npm",
+            Self::Pypi => "pypi",
+        }
+    }
+
+    /// The argument that runs `name` at `version`.
+    pub fn pin(self, name: &str, version: &str) -> String {
+        match self {
+            Self::Npm => format!("{name}@{version}"),
+            Self::Pypi => format!("{name}=={version}"),
+        }
+    }
+}
+
+/// The package a server runs through `npx` or `uvx`, read from its args.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstalledPackage {
+    pub registry: PackageRegistry,
+    pub name: String,
+    /// The pinned version, when the args pin one.
+    pub version: Option<String>,
+    /// The position of the package argument in `config.args`.
+    pub arg_index: usize,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpServerUpdate {
+    pub server_id: String,
+    pub name: String,
+    pub package_registry: String,
+    pub package_name: String,
+    pub installed_version: Option<String>,
+    pub latest_version: Option<String>,
+    /// Set for pinned installs older than the registry's latest, and for
+    /// every unpinned install.
+    pub update_available: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A server run from a registry package, taken from the MCP service so the
+/// registry lookups can run without holding it.
+#[derive(Clone, Debug)]
+pub struct UpdateTarget {
+    pub server_id: String,
+    pub name: String,
+    pub package: InstalledPackage,
+}
+
+pub fn installed_package(server: &Value) -> Option<InstalledPackage> {
+    let config = server.get("config")?;
+    let command = config.get("command").and_then(Value::as_str)?;
+    let registry = match command.rsplit(['/', '\\']).next()? {
+        "npx" | "npx.cmd" => PackageRegistry::Npm,
+        "uvx" | "uvx.exe" => PackageRegistry::Pypi,
+        _ => return None,
+    };
+    let (arg_index, spec) = config
+        .get("args")
+        .and_then(Value::as_array)?
+        .iter()
+        .enumerate()
+        .find_map(|(index, arg)| {
+            arg.as_str()
+                .filter(|arg| !arg.is_empty() && !arg.starts_with('-'))
+                .map(|arg| (index, arg))
+        })?;
+    if spec.contains(':') {
+        return None;
+    }
+
+    let (name, version) = match registry {
+        PackageRegistry::Npm => match spec.rfind('@').filter(|&at| at > 0) {
+            Some(at) => (&spec[..at], Some(&spec[at + 1..])),
+            None => (spec, None),
+        },
+        PackageRegistry::Pypi => match spec.split_once("==") {
+            Some((name, version)) => (name, Some(version)),
+            None => (spec, None),
+        },
+    };
+    Some(InstalledPackage {
+        registry,
+        name: name.to_string(),
+        version: version
+            .filter(|version| !version.is_empty() && *version != "latest")
+            .map(str::to_string),
+        arg_index,
+    })
+}
+
+/// Versions are put on a command line, so only the characters npm and
+/// PyPI versions use are accepted.
+pub fn is_valid_version(version: &str) -> bool {
+    !version.is_empty()
+        && version
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '.' | '-' | '+' | '!'))
+}
+
+/// Whether `latest` is newer than `installed`, by semver when both parse and
+/// by inequality otherwise.
+pub fn is_newer(latest: &str, installed: &str) -> bool {
+    let parse = |version: &str| semver::Version::parse(version.trim_start_matches('v')).ok();
+    match (parse(latest), parse(installed)) {
+        (Some(latest), Some(installed)) => latest > installed,
+        _ => latest != installed,
+    }
+}
+
+/// Whether a server at `installed` should be offered `latest`. `npx` and
+/// `uvx` may run a cached copy of an unpinned package, so pinning one to the
+/// latest version is always offered.
+pub fn has_update(latest: &str, installed: Option<&str>) -> bool {
+    installed.is_none_or(|installed| is_newer(latest, installed))
+}
+
+/// Client for registry lookups, with the proxy applied and a timeout so an
+/// unresponsive registry can't stall an update check.
+pub fn registry_client() -> Result<Client, String> {
+    proxy::client_builder()
+        .timeout(REGISTRY_TIMEOUT)
+        .build()
+        .map_err(|error| format!("Failed to build HTTP client: {error}"))
+}
+
+/// Looks up the latest version of every target concurrently. Lookup
+/// failures are reported per server.
+pub async fn check_updates(targets: Vec<UpdateTarget>) -> Result<Vec<McpServerUpdate>, String> {
+    let client = registry_client()?;
+    let lookups: Vec<_> = targets
+        .into_iter()
+        .map(|target| {
+            let client = client.clone();
+            tokio::spawn(async move {
+                let latest =
+                    latest_version(&client, target.package.registry, &target.package.name).await;
+                (target, latest)
+            })
+        })
+        .collect();
+
+    let mut updates = Vec::with_capacity(lookups.len());
+    for lookup in lookups {
+        let (target, latest) = lookup
+            .await
+            .map_err(|error| format!("Update check failed: {error}"))?;
+        let update_available = latest
+            .as_ref()
+            .is_ok_and(|latest| has_update(latest, target.package.version.as_deref()));
+        updates.push(McpServerUpdate {
+            server_id: target.server_id,
+            name: target.name,
+            package_registry: target.package.registry.as_str().to_string(),
+            package_name: target.package.name,
+            installed_version: target.package.version,
+            update_available,
+            error: latest.as_ref().err().cloned(),
+            latest_version: latest.ok(),
+        });
+    }
+    Ok(updates)
+}
+
+/// The registry's latest version of a package. The registries can be
+/// pointed elsewhere with `TAURI_MCP_NPM_REGISTRY_URL` and
+/// `TAURI_MCP_PYPI_URL`.
+pub async fn latest_version(
+    client: &Client,
+    registry: PackageRegistry,
+    name: &str,
+) -> Result<String, String> {
+    let (url, pointer) = match registry {
+        PackageRegistry::Npm => {
+            let base = std::env::var("TAURI_MCP_NPM_REGISTRY_URL")
+                .unwrap_or_else(|_| "https://registry.npmjs.org".to_string());
+            (
+                format!(
+                    "{}/-/package/{}/dist-tags",
+                    base.trim_end_matches('/'),
+                    name.replace('/', "%2F")
+                ),
+                "/latest",
+            )
+        }
+        PackageRegistry::Pypi => {
+            let base = std::env::var("TAURI_MCP_PYPI_URL")
+                .unwrap_or_else(|_| "https://pypi.org".to_string());
+            (
+                format!("{}/pypi/{}/json", base.trim_end_matches('/'), name),
+                "/info/version",
+            )
+        }
+    };
+    let response = client
+        .get(url)
+        .header(reqwest::header::ACCEPT, "application/json")
+        .send()
+        .await
+        .map_err(|error| {
+            format!(
+                "Failed to reach the {} registry: {error}",
+                registry.as_str()
+            )
+        })?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "The {} registry returned {} for {name}",
+            registry.as_str(),
+            response.status()
+        ));
+    }
+    let body: Value = response
+        .json()
+        .await
+        .map_err(|error| format!("Invalid {} registry response: {error}", registry.as_str()))?;
+    body.pointer(pointer)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| format!("No latest version of {name} was published"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn server(command: &str, args: Value) -> Value {
+        json!({ "id": "s", "config": { "type": "custom", "command": command, "args": args } })
+    }
+
+    #[test]
+    fn packages_are_read_from_npx_and_uvx_args() {
+        assert_eq!(
+            installed_package(&server(
+                "npx",
+                json!(["-y", "@acme/weather@1.2.0", "--port"])
+            )),
+            Some(InstalledPackage {
+                registry: PackageRegistry::Npm,
+                name: "@acme/weather".to_string(),
+                version: Some("1.2.0".to_string()),
+                arg_index: 1,
+            })
+        );
+        let unpinned = installed_package(&server("npx", json!(["-y", "mcp-notes"]))).unwrap();
+        assert_eq!(
+            (unpinned.name.as_str(), unpinned.version),
+            ("mcp-notes", None)
+        );
+        let python = installed_package(&server("uvx", json!(["mcp-server-git==0.6.2"]))).unwrap();
+        assert_eq!(
+            python.registry.pin(&python.name, "0.7.0"),
+            "mcp-server-git==0.7.0"
+        );
+        assert_eq!(python.version.as_deref(), Some("0.6.2"));
+
+        assert!(installed_package(&server("npx", json!(["-y", "github:acme/tool"]))).is_none());
+        assert!(installed_package(&server("node", json!(["server.js"]))).is_none());
+
+        assert!(is_newer("1.10.0", "1.9.3"));
+        assert!(!is_newer("1.2.0", "v1.2.0"));
+        assert!(is_newer("2024.1", "2023.12"));
+        assert!(has_update("1.2.0", None));
+        assert!(!has_update("1.2.0", Some("1.2.0")));
+        assert!(is_valid_version("1.0.0-beta.1"));
+        assert!(!is_valid_version("1.0.0; rm -rf ~"));
+    }
+}
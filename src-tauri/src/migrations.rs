@@ -0,0 +1,280 @@
+use chrono::Utc;
+use rusqlite::{Connection, params};
+use serde::Serialize;
+
+pub type MigrationStep = fn(&Connection) -> rusqlite::Result<()>;
+
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub up: MigrationStep,
+    pub down: MigrationStep,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationPlanStep {
+    pub scope: String,
+    pub version: i64,
+    pub description: String,
+}
+
+fn ensure_migrations_table(connection: &Connection) -> Result<(), String> {
+    connection
+        .execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS schema_migrations (
+                scope TEXT NOT NULL,
+                version INTEGER NOT NULL,
+                description TEXT NOT NULL,
+                applied_at TEXT NOT NULL,
+                PRIMARY KEY (scope, version)
+            );
+            "#,
+        )
+        .map_err(|err| err.to_string())
+}
+
+fn applied_versions(connection: &Connection, scope: &str) -> Result<Vec<i64>, String> {
+    let mut statement = connection
+        .prepare("SELECT version FROM schema_migrations WHERE scope = ?1 ORDER BY version ASC")
+        .map_err(|err| err.to_string())?;
+    let rows = statement
+        .query_map(params![scope], |row| row.get::<_, i64>(0))
+        .map_err(|err| err.to_string())?;
+
+    let mut versions = Vec::new();
+    for row in rows {
+        versions.push(row.map_err(|err| err.to_string())?);
+    }
+    Ok(versions)
+}
+
+pub fn current_version(connection: &Connection, scope: &str) -> Result<i64, String> {
+    ensure_migrations_table(connection)?;
+    Ok(applied_versions(connection, scope)?
+        .last()
+        .copied()
+        .unwrap_or(0))
+}
+
+/// Lists the migrations that `apply` would run without touching the schema.
+pub fn plan(
+    connection: &Connection,
+    scope: &str,
+    migrations: &[Migration],
+) -> Result<Vec<MigrationPlanStep>, String> {
+    ensure_migrations_table(connection)?;
+    let applied = applied_versions(connection, scope)?;
+
+    Ok(migrations
+        .iter()
+        .filter(|migration| !applied.contains(&migration.version))
+        .map(|migration| MigrationPlanStep {
+            scope: scope.to_string(),
+            version: migration.version,
+            description: migration.description.to_string(),
+        })
+        .collect())
+}
+
+/// Runs every pending migration in version order, each inside its own transaction.
+pub fn apply(
+    connection: &Connection,
+    scope: &str,
+    migrations: &[Migration],
+) -> Result<Vec<i64>, String> {
+    let pending = plan(connection, scope, migrations)?;
+    let mut applied = Vec::with_capacity(pending.len());
+
+    for step in pending {
+        let Some(migration) = migrations
+            .iter()
+            .find(|migration| migration.version == step.version)
+        else {
+            continue;
+        };
+
+        let transaction = connection
+            .unchecked_transaction()
+            .map_err(|err| err.to_string())?;
+        (migration.up)(&transaction).map_err(|err| {
+            format!(
+                "Migration {}#{} ({}) failed: {}",
+                scope, migration.version, migration.description, err
+            )
+        })?;
+        transaction
+            .execute(
+                "INSERT INTO schema_migrations (scope, version, description, applied_at) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    scope,
+                    migration.version,
+                    migration.description,
+                    Utc::now().to_rfc3339()
+                ],
+            )
+            .map_err(|err| err.to_string())?;
+        transaction.commit().map_err(|err| err.to_string())?;
+
+        log::info!(
+            "Applied schema migration {}#{}: {}",
+            scope,
+            migration.version,
+            migration.description
+        );
+        applied.push(migration.version);
+    }
+
+    Ok(applied)
+}
+
+/// Reverts applied migrations newer than `target_version`, newest first.
+pub fn rollback(
+    connection: &Connection,
+    scope: &str,
+    migrations: &[Migration],
+    target_version: i64,
+) -> Result<Vec<i64>, String> {
+    ensure_migrations_table(connection)?;
+    let applied = applied_versions(connection, scope)?;
+    let mut reverted = Vec::new();
+
+    for version in applied.into_iter().rev() {
+        if version <= target_version {
+            break;
+        }
+
+        let migration = migrations
+            .iter()
+            .find(|migration| migration.version == version)
+            .ok_or_else(|| format!("Unknown migration {scope}#{version}; cannot roll back"))?;
+
+        let transaction = connection
+            .unchecked_transaction()
+            .map_err(|err| err.to_string())?;
+        (migration.down)(&transaction).map_err(|err| {
+            format!(
+                "Rollback of {}#{} ({}) failed: {}",
+                scope, migration.version, migration.description, err
+            )
+        })?;
+        transaction
+            .execute(
+                "DELETE FROM schema_migrations WHERE scope = ?1 AND version = ?2",
+                params![scope, version],
+            )
+            .map_err(|err| err.to_string())?;
+        transaction.commit().map_err(|err| err.to_string())?;
+
+        log::info!(
+            "Rolled back schema migration {}#{}: {}",
+            scope,
+            migration.version,
+            migration.description
+        );
+        reverted.push(version);
+    }
+
+    Ok(reverted)
+}
+
+pub fn column_exists(connection: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+    let mut statement = connection.prepare(&format!("PRAGMA table_info({table})"))?;
+    let mut rows = statement.query([])?;
+    while let Some(row) = rows.next()? {
+        let name: String = row.get(1)?;
+        if name == column {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+pub fn add_column_if_missing(
+    connection: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> rusqlite::Result<()> {
+    if column_exists(connection, table, column)? {
+        return Ok(());
+    }
+    connection.execute(
+        &format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"),
+        [],
+    )?;
+    Ok(())
+}
+
+pub fn drop_column_if_present(
+    connection: &Connection,
+    table: &str,
+    column: &str,
+) -> rusqlite::Result<()> {
+    if !column_exists(connection, table, column)? {
+        return Ok(());
+    }
+    connection.execute(&format!("ALTER TABLE {table} DROP COLUMN {column}"), [])?;
+    Ok(())
+}
+
+pub fn noop(_connection: &Connection) -> rusqlite::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_MIGRATIONS: &[Migration] = &[
+        Migration {
+            version: 1,
+            description: "create widgets",
+            up: |connection| {
+                connection.execute_batch("CREATE TABLE widgets (id INTEGER PRIMARY KEY);")
+            },
+            down: |connection| connection.execute_batch("DROP TABLE widgets;"),
+        },
+        Migration {
+            version: 2,
+            description: "add widget label",
+            up: |connection| add_column_if_missing(connection, "widgets", "label", "TEXT"),
+            down: |connection| drop_column_if_present(connection, "widgets", "label"),
+        },
+    ];
+
+    #[test]
+    fn plan_lists_pending_without_applying() {
+        let connection = Connection::open_in_memory().expect("in-memory sqlite");
+        let pending = plan(&connection, "test", TEST_MIGRATIONS).expect("plan");
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].version, 1);
+        assert_eq!(current_version(&connection, "test").unwrap(), 0);
+        assert!(!column_exists(&connection, "widgets", "id").unwrap());
+    }
+
+    #[test]
+    fn apply_records_versions_and_is_idempotent() {
+        let connection = Connection::open_in_memory().expect("in-memory sqlite");
+        let applied = apply(&connection, "test", TEST_MIGRATIONS).expect("apply");
+        assert_eq!(applied, vec![1, 2]);
+        assert!(column_exists(&connection, "widgets", "label").unwrap());
+        assert_eq!(current_version(&connection, "test").unwrap(), 2);
+
+        let second = apply(&connection, "test", TEST_MIGRATIONS).expect("reapply");
+        assert!(second.is_empty());
+        assert_eq!(current_version(&connection, "other").unwrap(), 0);
+    }
+
+    #[test]
+    fn rollback_reverts_to_target_version() {
+        let connection = Connection::open_in_memory().expect("in-memory sqlite");
+        apply(&connection, "test", TEST_MIGRATIONS).expect("apply");
+
+        let reverted = rollback(&connection, "test", TEST_MIGRATIONS, 1).expect("rollback");
+        assert_eq!(reverted, vec![2]);
+        assert!(!column_exists(&connection, "widgets", "label").unwrap());
+        assert_eq!(current_version(&connection, "test").unwrap(), 1);
+    }
+}
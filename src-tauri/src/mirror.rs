@@ -1,9 +1,12 @@
+use base64::{Engine as _, engine::general_purpose::STANDARD};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use std::path::PathBuf;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::process::Command;
 
+use crate::proxy;
+
 #[derive(Clone)]
 pub struct MirrorNodeBridge {
     script_path: PathBuf,
@@ -122,9 +125,62 @@ impl MirrorNodeBridge {
         }
     }
 
+    pub async fn get_account_info(
+        &self,
+        account_id: &str,
+        network: MirrorNetwork,
+    ) -> Result<Option<Value>, String> {
+        let payload = json!({
+            "accountId": account_id,
+            "network": network.as_str(),
+        });
+
+        let data = self
+            .request("mirror_node_get_account_info", payload)
+            .await?;
+        Ok(Some(data).filter(|data| !data.is_null()))
+    }
+
+    /// Topic info with the latest `sequence_number` merged in, read from the
+    /// mirror node at `mirror_url`.
+    pub async fn get_topic_info(
+        &self,
+        topic_id: &str,
+        network: MirrorNetwork,
+        mirror_url: &str,
+    ) -> Result<Option<Value>, String> {
+        let payload = json!({
+            "topicId": topic_id,
+            "network": network.as_str(),
+            "mirrorUrl": mirror_url.trim_end_matches('/'),
+        });
+
+        let data = self.request("mirror_node_get_topic_info", payload).await?;
+        Ok(Some(data).filter(|data| !data.is_null()))
+    }
+
+    /// Decompresses `data` in the bridge, for algorithms the Rust side has no
+    /// decoder for.
+    pub async fn decompress(&self, algorithm: &str, data: &[u8]) -> Result<Vec<u8>, String> {
+        let payload = json!({
+            "algorithm": algorithm,
+            "data": STANDARD.encode(data),
+        });
+
+        let response = self.request("mirror_node_decompress", payload).await?;
+        let encoded = response
+            .get("data")
+            .and_then(Value::as_str)
+            .ok_or_else(|| "Decompression returned no data".to_string())?;
+        STANDARD
+            .decode(encoded.as_bytes())
+            .map_err(|error| format!("Invalid decompressed data: {error}"))
+    }
+
     async fn request(&self, action: &str, payload: Value) -> Result<Value, String> {
         let mut command = Command::new("node");
         command.arg(&self.script_path);
+        command.envs(proxy::env_vars());
         command.stdin(std::process::Stdio::piped());
         command.stdout(std::process::Stdio::piped());
         command.stderr(std::process::Stdio::piped());
@@ -228,7 +284,7 @@ impl MirrorNetwork {
         }
     }
 
-    fn as_str(&self) -> &str {
+    pub(crate) fn as_str(&self) -> &str {
         match self {
             Self::Testnet => "testnet",
             Self::Mainnet => "mainnet",
@@ -0,0 +1,195 @@
+use serde_json::Value;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub const EVENT_ENTITY_HYDRATED: &str = "entity_hydrated";
+
+/// What the SDKs use when a transaction does not set its own.
+pub const DEFAULT_VALID_DURATION: Duration = Duration::from_secs(120);
+const DEFAULT_INGEST_LATENCY: Duration = Duration::from_secs(3);
+const MAX_INGEST_LATENCY: Duration = Duration::from_secs(60);
+const MIN_INTERVAL: Duration = Duration::from_millis(500);
+const MAX_INTERVAL: Duration = Duration::from_secs(5);
+/// Time past the valid window, plus ingest, before giving up on a record.
+const DEADLINE_GRACE: Duration = Duration::from_secs(10);
+
+/// A running estimate of how long the mirror node takes to show a
+/// transaction after consensus.
+pub struct IngestLatency {
+    estimate: Mutex<Duration>,
+}
+
+impl IngestLatency {
+    pub fn new() -> Self {
+        Self {
+            estimate: Mutex::new(DEFAULT_INGEST_LATENCY),
+        }
+    }
+
+    pub fn estimate(&self) -> Duration {
+        *self
+            .estimate
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Folds in a sample, weighting it at 30%.
+    pub fn observe(&self, sample: Duration) {
+        let sample = sample.min(MAX_INGEST_LATENCY);
+        let mut estimate = self
+            .estimate
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *estimate = estimate.mul_f64(0.7) + sample.mul_f64(0.3);
+    }
+}
+
+/// How a mirror node record settles the question of the created entity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordStatus {
+    Success,
+    Failed(String),
+    Unknown,
+}
+
+pub fn record_status(transaction: &Value) -> RecordStatus {
+    match transaction.get("result").and_then(Value::as_str) {
+        Some("SUCCESS") => RecordStatus::Success,
+        Some(result) => RecordStatus::Failed(result.to_string()),
+        None => RecordStatus::Unknown,
+    }
+}
+
+/// When to poll for a transaction. The first poll waits for the expected
+/// ingest latency after the transaction's valid start, later ones back off,
+/// and polling stops once the transaction can no longer have reached
+/// consensus and been ingested.
+pub struct PollSchedule {
+    deadline: Instant,
+    next_delay: Duration,
+}
+
+impl PollSchedule {
+    pub fn new(
+        transaction_id: &str,
+        valid_duration: Option<Duration>,
+        ingest_latency: Duration,
+        now: SystemTime,
+    ) -> Self {
+        let valid_duration = valid_duration.unwrap_or(DEFAULT_VALID_DURATION);
+        let since_start = valid_start(transaction_id)
+            .and_then(|start| now.duration_since(start).ok())
+            .unwrap_or_default();
+        let remaining = valid_duration.saturating_sub(since_start);
+        Self {
+            deadline: Instant::now() + remaining + ingest_latency * 2 + DEADLINE_GRACE,
+            next_delay: ingest_latency
+                .saturating_sub(since_start)
+                .clamp(MIN_INTERVAL, MAX_INTERVAL),
+        }
+    }
+
+    /// The wait before the next poll, or `None` once past the deadline.
+    pub fn next_delay(&mut self, now: Instant) -> Option<Duration> {
+        let left = self.deadline.checked_duration_since(now)?;
+        if left.is_zero() {
+            return None;
+        }
+        let delay = self.next_delay.min(left);
+        self.next_delay = (self.next_delay * 2).min(MAX_INTERVAL);
+        Some(delay)
+    }
+}
+
+fn parse_timestamp(seconds: &str, nanos: &str) -> Option<SystemTime> {
+    let seconds: u64 = seconds.parse().ok()?;
+    let nanos: u32 = if nanos.is_empty() {
+        0
+    } else {
+        format!("{nanos:0<9}").get(..9)?.parse().ok()?
+    };
+    UNIX_EPOCH.checked_add(Duration::new(seconds, nanos))
+}
+
+/// The valid start encoded in `0.0.x@seconds.nanos` or `0.0.x-seconds-nanos`.
+pub fn valid_start(transaction_id: &str) -> Option<SystemTime> {
+    if let Some((_, timestamp)) = transaction_id.split_once('@') {
+        let (seconds, nanos) = timestamp.split_once('.').unwrap_or((timestamp, ""));
+        return parse_timestamp(seconds, nanos);
+    }
+    let mut parts = transaction_id.splitn(3, '-').skip(1);
+    let seconds: u64 = parts.next()?.parse().ok()?;
+    let nanos: u32 = parts.next().map_or(Some(0), |nanos| nanos.parse().ok())?;
+    UNIX_EPOCH.checked_add(Duration::new(seconds, nanos))
+}
+
+pub fn consensus_time(transaction: &Value) -> Option<SystemTime> {
+    let timestamp = transaction.get("consensus_timestamp")?.as_str()?;
+    let (seconds, nanos) = timestamp.split_once('.').unwrap_or((timestamp, ""));
+    parse_timestamp(seconds, nanos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn schedule_waits_for_ingest_then_backs_off_until_the_deadline() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(valid_start("0.0.1234@1700000000.000000000"), Some(start));
+        assert_eq!(
+            valid_start("0.0.1234-1700000000-5"),
+            Some(start + Duration::from_nanos(5))
+        );
+
+        let mut schedule = PollSchedule::new(
+            "0.0.1234@1700000000.0",
+            Some(Duration::from_secs(30)),
+            Duration::from_secs(4),
+            start + Duration::from_secs(1),
+        );
+        let now = Instant::now();
+        assert_eq!(schedule.next_delay(now), Some(Duration::from_secs(3)));
+        assert_eq!(schedule.next_delay(now), Some(Duration::from_secs(5)));
+        assert_eq!(schedule.next_delay(now), Some(MAX_INTERVAL));
+        assert!(
+            schedule
+                .next_delay(now + Duration::from_secs(29 + 8 + 10))
+                .is_none()
+        );
+
+        let mut expired = PollSchedule::new(
+            "0.0.1234@1700000000.0",
+            Some(Duration::from_secs(30)),
+            Duration::from_secs(4),
+            start + Duration::from_secs(600),
+        );
+        assert_eq!(expired.next_delay(Instant::now()), Some(MIN_INTERVAL));
+        assert!(
+            expired
+                .next_delay(Instant::now() + Duration::from_secs(19))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn records_and_latency_are_read() {
+        assert_eq!(
+            record_status(&json!({ "result": "INVALID_SIGNATURE" })),
+            RecordStatus::Failed("INVALID_SIGNATURE".to_string())
+        );
+        assert_eq!(
+            record_status(&json!({ "result": "SUCCESS" })),
+            RecordStatus::Success
+        );
+        assert_eq!(
+            consensus_time(&json!({ "consensus_timestamp": "1700000000.5" })),
+            Some(UNIX_EPOCH + Duration::from_millis(1_700_000_000_500))
+        );
+
+        let latency = IngestLatency::new();
+        latency.observe(Duration::from_secs(13));
+        assert!((latency.estimate().as_secs_f64() - 6.0).abs() < 0.001);
+    }
+}
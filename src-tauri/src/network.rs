@@ -0,0 +1,99 @@
+use chrono::Utc;
+use reqwest::Client;
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::proxy;
+
+pub const EVENT_NETWORK_STATE_CHANGED: &str = "network_state_changed";
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkState {
+    pub online: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checked_at: Option<String>,
+}
+
+/// Tracks whether the Hedera mirror node can be reached. The app starts out
+/// assuming it is online; the first failed probe says otherwise.
+pub struct NetworkMonitor {
+    client: Client,
+    state: Mutex<NetworkState>,
+}
+
+impl NetworkMonitor {
+    pub fn new() -> Self {
+        let client = proxy::client_builder()
+            .user_agent("hol-desktop-tauri/0.0.1")
+            .timeout(PROBE_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            client,
+            state: Mutex::new(NetworkState {
+                online: true,
+                checked_at: None,
+            }),
+        }
+    }
+
+    pub fn state(&self) -> NetworkState {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Probes `url` and records the outcome. Any HTTP response counts as
+    /// reachable. Returns the new state and whether `online` changed.
+    pub async fn check(&self, url: &str) -> (NetworkState, bool) {
+        let online = self.client.get(url).send().await.is_ok();
+        self.record(online)
+    }
+
+    fn record(&self, online: bool) -> (NetworkState, bool) {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let changed = state.online != online;
+        state.online = online;
+        state.checked_at = Some(Utc::now().to_rfc3339());
+        (state.clone(), changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::{Method::GET, MockServer};
+
+    #[tokio::test]
+    async fn check_reports_transitions() {
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/v1");
+                then.status(200);
+            })
+            .await;
+
+        let monitor = NetworkMonitor::new();
+        assert!(monitor.state().online);
+
+        let (state, changed) = monitor.check(&server.url("/api/v1")).await;
+        assert!(state.online && !changed);
+        assert!(state.checked_at.is_some());
+
+        let (state, changed) = monitor.check("http://127.0.0.1:9/api/v1").await;
+        assert!(!state.online && changed);
+        assert!(!monitor.state().online);
+
+        let (state, changed) = monitor.check(&server.url("/api/v1")).await;
+        assert!(state.online && changed);
+    }
+}
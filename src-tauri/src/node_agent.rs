@@ -1,7 +1,9 @@
 use std::path::PathBuf;
 use std::process::Stdio;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
@@ -11,16 +13,37 @@ use uuid::Uuid;
 
 use crate::AgentBackend;
 use crate::BackendError;
+use crate::BridgeTimeout;
 use crate::agent::{AgentMessageData, AgentMessageRequest};
+use crate::config::{BridgeRequestConfig, LlmProvider};
+use crate::connection::GEMINI_OPENAI_BASE_URL;
+use crate::proxy;
+use crate::rate_limit::{ProviderHeaders, ProviderRateLimiter};
+use crate::secret::Secret;
 use crate::wallet_bridge::{
     WalletBridgeInfo, WalletBridgeState, wallet_execute_bytes, wallet_fetch_inscription,
     wallet_start_inscription, wallet_status_json,
 };
 
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Bridge actions that are safe to send again after a timeout. `initialize`
+/// is not, since the first attempt may still be running in Node.
+const RETRYABLE_ACTIONS: &[&str] = &["status"];
+
+static REQUEST_POLICY: Lazy<RwLock<BridgeRequestConfig>> = Lazy::new(RwLock::default);
+
+/// Applies `config` to the requests running bridges send from now on.
+pub fn set_request_policy(config: &BridgeRequestConfig) {
+    *REQUEST_POLICY
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = *config;
+}
+
 pub struct NodeAgentBackend {
     process: Mutex<NodeProcess>,
     wallet_bridge: WalletBridgeState,
     wallet_info: Arc<Mutex<Option<WalletBridgeInfo>>>,
+    rate_limiter: Arc<ProviderRateLimiter>,
 }
 
 struct NodeProcess {
@@ -36,13 +59,16 @@ impl NodeAgentBackend {
         script_path: PathBuf,
         wallet_bridge: WalletBridgeState,
         wallet_info: Arc<Mutex<Option<WalletBridgeInfo>>>,
+        rate_limiter: Arc<ProviderRateLimiter>,
     ) -> Result<Self, String> {
         let mut command = Command::new("node");
         command
             .arg(&script_path)
+            .envs(proxy::env_vars())
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
 
         if let Some(parent) = script_path.parent() {
             command.current_dir(parent);
@@ -75,10 +101,56 @@ impl NodeAgentBackend {
             }),
             wallet_bridge,
             wallet_info,
+            rate_limiter,
         })
     }
 
-    async fn request(&self, action: &str, payload: Value) -> Result<Value, String> {
+    /// Sends `action` under the configured timeout, retrying it when that is
+    /// safe.
+    async fn request(&self, action: &str, payload: Value) -> Result<Value, BackendError> {
+        let policy = *REQUEST_POLICY
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let timeout = Duration::from_secs(policy.timeout_seconds.max(1));
+        let attempts = if RETRYABLE_ACTIONS.contains(&action) {
+            policy.max_retries.saturating_add(1)
+        } else {
+            1
+        };
+
+        let mut attempt = 1;
+        loop {
+            match self.exchange(action, payload.clone(), timeout).await {
+                Err(BackendError::Timeout(_)) if attempt < attempts => {
+                    log::warn!(
+                        "Agent bridge timed out on {} (attempt {} of {}); retrying",
+                        action,
+                        attempt,
+                        attempts
+                    );
+                    tokio::time::sleep(Duration::from_millis(policy.retry_delay_ms)).await;
+                    attempt += 1;
+                }
+                Err(BackendError::Timeout(timed_out)) => {
+                    return Err(BackendError::Timeout(BridgeTimeout {
+                        attempts,
+                        ..timed_out
+                    }));
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Sends one request and reads its response. `timeout` limits each wait
+    /// for the bridge's next line, so time spent on the bridge's own requests,
+    /// such as a wallet approval, does not count against it.
+    async fn exchange(
+        &self,
+        action: &str,
+        payload: Value,
+        timeout: Duration,
+    ) -> Result<Value, BackendError> {
         let mut guard = self.process.lock().await;
         guard.next_id += 1;
         let request_id = guard.next_id;
@@ -113,13 +185,17 @@ impl NodeAgentBackend {
 
         loop {
             response_line.clear();
-            let read_bytes = tokio::time::timeout(
-                std::time::Duration::from_secs(120),
-                guard.stdout.read_line(&mut response_line),
-            )
-            .await
-            .map_err(|_| "Agent bridge timed out while waiting for response".to_string())?
-            .map_err(|error| format!("Failed to read agent bridge response: {error}"))?;
+            let read_bytes =
+                tokio::time::timeout(timeout, guard.stdout.read_line(&mut response_line))
+                    .await
+                    .map_err(|_| {
+                        BackendError::Timeout(BridgeTimeout {
+                            action: action.to_string(),
+                            timeout_ms: timeout.as_millis() as u64,
+                            attempts: 1,
+                        })
+                    })?
+                    .map_err(|error| format!("Failed to read agent bridge response: {error}"))?;
 
             if read_bytes == 0 {
                 let mut stderr_output = String::new();
@@ -132,7 +208,7 @@ impl NodeAgentBackend {
                 };
 
                 log::error!("{}", error_msg);
-                return Err(error_msg);
+                return Err(BackendError::Failure(error_msg));
             }
 
             let trimmed = response_line.trim();
@@ -217,10 +293,17 @@ impl NodeAgentBackend {
                     format!("Failed to deserialize bridge response: {parse_error}")
                 })?;
 
+            let now = chrono::Utc::now();
+            for headers in &response.rate_limits {
+                self.rate_limiter.observe(headers, now);
+            }
+
             if !response.success {
-                return Err(response
-                    .error
-                    .unwrap_or_else(|| "Unknown agent bridge error".to_string()));
+                return Err(BackendError::Failure(
+                    response
+                        .error
+                        .unwrap_or_else(|| "Unknown agent bridge error".to_string()),
+                ));
             }
 
             let payload = response.data.unwrap_or(Value::Null);
@@ -265,6 +348,7 @@ impl NodeAgentBackend {
         )
         .await
         .map(|_| ())
+        .map_err(|error| error.to_string())
     }
 
     async fn handle_bridge_request(&self, request: &BridgeRequestPayload) -> Result<Value, String> {
@@ -335,6 +419,8 @@ struct BridgeResponse {
     data: Option<Value>,
     #[serde(default)]
     error: Option<String>,
+    #[serde(default, rename = "rateLimits")]
+    rate_limits: Vec<ProviderHeaders>,
 }
 
 #[derive(Deserialize)]
@@ -349,12 +435,18 @@ struct BridgeRequestPayload {
 #[serde(rename_all = "camelCase")]
 pub struct AgentInitializeConfigPayload {
     pub account_id: String,
-    pub private_key: String,
+    pub private_key: Secret,
     pub network: String,
     #[serde(rename = "openAIApiKey")]
-    pub open_ai_api_key: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_ai_api_key: Secret,
+    #[serde(rename = "openAIModelName", skip_serializing_if = "Option::is_none")]
     pub model_name: Option<String>,
+    #[serde(rename = "openAIBaseURL", skip_serializing_if = "Option::is_none")]
+    pub open_ai_base_url: Option<String>,
+    #[serde(rename = "openRouterApiKey", skip_serializing_if = "Option::is_none")]
+    pub open_router_api_key: Option<Secret>,
+    #[serde(rename = "openRouterBaseURL", skip_serializing_if = "Option::is_none")]
+    pub open_router_base_url: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub llm_provider: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -394,12 +486,15 @@ impl AgentBackend for NodeAgentBackend {
             "attachments": request.attachments.clone(),
             "formSubmission": request.form_submission.clone(),
             "metadata": metadata,
+            "llm": request.llm.as_ref().map(|llm| json!({
+                "provider": llm.provider,
+                "apiKey": llm.api_key,
+                "modelName": llm.model,
+                "baseURL": llm.base_url,
+            })),
         });
 
-        let response_value = self
-            .request("sendMessage", payload)
-            .await
-            .map_err(BackendError::Failure)?;
+        let response_value = self.request("sendMessage", payload).await?;
 
         let payload: BridgeMessagePayload = serde_json::from_value(response_value)
             .map_err(|error| BackendError::Failure(error.to_string()))?;
@@ -424,6 +519,14 @@ impl AgentBackend for NodeAgentBackend {
                 ("notes", message_value.get("notes")),
                 ("formMessage", message_value.get("formMessage")),
                 ("hashLinkBlock", message_value.get("hashLinkBlock")),
+                ("receipt", message_value.get("receipt")),
+                ("entityId", message_value.get("entityId")),
+                ("entityType", message_value.get("entityType")),
+                ("tokenUsage", message_value.get("tokenUsage")),
+                ("cost", message_value.get("cost")),
+                ("answeredBy", message_value.get("answeredBy")),
+                ("toolCalls", message_value.get("toolCalls")),
+                ("intermediateSteps", message_value.get("intermediateSteps")),
             ] {
                 if let Some(v) = value {
                     map.insert(key.to_string(), v.clone());
@@ -446,10 +549,67 @@ impl AgentBackend for NodeAgentBackend {
     }
 
     async fn disconnect(&self) -> Result<(), BackendError> {
-        self.request("disconnect", Value::Null)
-            .await
-            .map(|_| ())
-            .map_err(BackendError::Failure)
+        self.request("disconnect", Value::Null).await.map(|_| ())
+    }
+
+    /// A bridge busy with a request counts as healthy; the request has its
+    /// own timeout.
+    async fn health_check(&self) -> Result<(), BackendError> {
+        {
+            let Ok(mut process) = self.process.try_lock() else {
+                return Ok(());
+            };
+            if let Ok(Some(status)) = process.child.try_wait() {
+                return Err(BackendError::Failure(format!(
+                    "Agent bridge exited ({status})"
+                )));
+            }
+        }
+        match self.exchange("status", json!({}), HEARTBEAT_TIMEOUT).await {
+            Ok(_) => Ok(()),
+            Err(BackendError::Timeout(_)) => Err(BackendError::Failure(
+                "Agent bridge stopped responding".to_string(),
+            )),
+            Err(error) => Err(error),
+        }
+    }
+
+    async fn create_private_topic(&self, memo: &str) -> Result<String, BackendError> {
+        let payload = self.request("createTopic", json!({ "memo": memo })).await?;
+        payload
+            .get("topicId")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| {
+                BackendError::Failure("Agent bridge did not return a topic ID".to_string())
+            })
+    }
+
+    async fn submit_topic_message(
+        &self,
+        topic_id: &str,
+        message: &str,
+    ) -> Result<u64, BackendError> {
+        let payload = self
+            .request(
+                "submitTopicMessage",
+                json!({ "topicId": topic_id, "message": message }),
+            )
+            .await?;
+        payload
+            .get("sequenceNumber")
+            .and_then(|value| {
+                value
+                    .as_u64()
+                    .or_else(|| value.as_str().and_then(|text| text.parse().ok()))
+            })
+            .ok_or_else(|| {
+                BackendError::Failure("Agent bridge did not return a sequence number".to_string())
+            })
+    }
+
+    async fn raw_request(&self, action: &str, payload: Value) -> Result<Value, BackendError> {
+        self.request(action, payload).await
     }
 }
 
@@ -461,15 +621,38 @@ impl Drop for NodeAgentBackend {
     }
 }
 
+/// The bridge takes OpenRouter credentials in their own fields and has no
+/// Gemini client, so Gemini goes through its OpenAI client pointed at
+/// Gemini's OpenAI-compatible endpoint.
 impl From<crate::agent::AgentInitializeConfig> for AgentInitializeConfigPayload {
     fn from(value: crate::agent::AgentInitializeConfig) -> Self {
+        let mut llm_provider = value.llm_provider;
+        let mut open_ai_base_url = value.open_ai_base_url;
+        let mut open_router_api_key = None;
+        let mut open_router_base_url = None;
+        match llm_provider.as_deref().and_then(LlmProvider::from_name) {
+            Some(LlmProvider::Openrouter) => {
+                open_router_api_key = Some(value.open_ai_api_key.clone());
+                open_router_base_url = open_ai_base_url.take();
+            }
+            Some(LlmProvider::Gemini) => {
+                llm_provider = Some("openai".to_string());
+                open_ai_base_url =
+                    open_ai_base_url.or_else(|| Some(GEMINI_OPENAI_BASE_URL.to_string()));
+            }
+            _ => {}
+        }
+
         Self {
             account_id: value.account_id,
             private_key: value.private_key,
             network: value.network,
             open_ai_api_key: value.open_ai_api_key,
             model_name: value.model_name,
-            llm_provider: value.llm_provider,
+            open_ai_base_url,
+            open_router_api_key,
+            open_router_base_url,
+            llm_provider,
             user_account_id: value.user_account_id,
             operational_mode: value.operational_mode,
             mcp_servers: value.mcp_servers,
@@ -490,18 +673,21 @@ mod tests {
     fn payload_includes_optional_fields() {
         let config = crate::agent::AgentInitializeConfig {
             account_id: "0.0.1001".to_string(),
-            private_key: "302e020100300506032b657004220420".to_string(),
+            private_key: "302e020100300506032b657004220420".into(),
             network: "testnet".to_string(),
-            open_ai_api_key: "sk-test".to_string(),
+            open_ai_api_key: "sk-test".into(),
             model_name: Some("gpt-test".to_string()),
+            open_ai_base_url: None,
             llm_provider: Some("openai".to_string()),
             user_account_id: Some("0.0.2002".to_string()),
             operational_mode: Some("provideBytes".to_string()),
             mcp_servers: Some(json!([{ "id": "server-1" }])),
+            mcp_group: None,
             verbose: None,
             disable_logging: None,
             disabled_plugins: None,
             additional_plugins: None,
+            backend: None,
         };
 
         let payload = AgentInitializeConfigPayload::from(config);
@@ -515,14 +701,16 @@ mod tests {
     fn payload_includes_additional_plugins() {
         let config = crate::agent::AgentInitializeConfig {
             account_id: "0.0.1001".to_string(),
-            private_key: "302e020100300506032b657004220420".to_string(),
+            private_key: "302e020100300506032b657004220420".into(),
             network: "testnet".to_string(),
-            open_ai_api_key: "sk-test".to_string(),
+            open_ai_api_key: "sk-test".into(),
             model_name: Some("gpt-test".to_string()),
+            open_ai_base_url: None,
             llm_provider: Some("openai".to_string()),
             user_account_id: Some("0.0.2002".to_string()),
             operational_mode: Some("provideBytes".to_string()),
             mcp_servers: None,
+            mcp_group: None,
             verbose: None,
             disable_logging: None,
             disabled_plugins: None,
@@ -537,6 +725,7 @@ mod tests {
                     }),
                 },
             ]),
+            backend: None,
         };
 
         let payload = AgentInitializeConfigPayload::from(config);
@@ -546,4 +735,38 @@ mod tests {
         assert_eq!(plugins[0].plugin_type, "swarm");
         assert!(plugins[0].config.get("beeApiUrl").is_some());
     }
+
+    #[test]
+    fn openrouter_and_gemini_map_onto_the_bridge_fields() {
+        let config = |provider: &str| crate::agent::AgentInitializeConfig {
+            account_id: "0.0.1001".to_string(),
+            private_key: "302e020100300506032b657004220420".into(),
+            network: "testnet".to_string(),
+            open_ai_api_key: "provider-key".into(),
+            model_name: Some("model".to_string()),
+            open_ai_base_url: None,
+            llm_provider: Some(provider.to_string()),
+            user_account_id: None,
+            operational_mode: None,
+            mcp_servers: None,
+            mcp_group: None,
+            verbose: None,
+            disable_logging: None,
+            disabled_plugins: None,
+            additional_plugins: None,
+            backend: None,
+        };
+
+        let openrouter =
+            serde_json::to_value(AgentInitializeConfigPayload::from(config("openrouter"))).unwrap();
+        assert_eq!(openrouter["llmProvider"], "openrouter");
+        assert_eq!(openrouter["openRouterApiKey"], "provider-key");
+        assert_eq!(openrouter["openAIModelName"], "model");
+
+        let gemini =
+            serde_json::to_value(AgentInitializeConfigPayload::from(config("gemini"))).unwrap();
+        assert_eq!(gemini["llmProvider"], "openai");
+        assert_eq!(gemini["openAIBaseURL"], GEMINI_OPENAI_BASE_URL);
+        assert!(gemini.get("openRouterApiKey").is_none());
+    }
 }
@@ -0,0 +1,188 @@
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+use crate::config::CredentialAuthConfig;
+
+/// Polkit action checked on Linux, installed from
+/// `resources/linux/com.hashgraphonline.desktop.policy` by the deb and rpm
+/// packages.
+#[cfg(all(unix, not(target_os = "macos")))]
+const POLKIT_ACTION: &str = "com.hashgraphonline.desktop.read-credential";
+/// Checked instead when that policy is not installed, as in an AppImage.
+/// It ships with polkit and asks for an administrator's password.
+#[cfg(all(unix, not(target_os = "macos")))]
+const FALLBACK_POLKIT_ACTION: &str = "org.freedesktop.policykit.exec";
+
+/// Remembers the last successful OS authentication so reads within the
+/// grace period do not prompt again.
+#[derive(Default)]
+pub struct OsAuthGate {
+    verified_at: Mutex<Option<Instant>>,
+}
+
+impl OsAuthGate {
+    /// Prompts for OS authentication unless `config` leaves the gate off or
+    /// the last prompt is still within the grace period. Concurrent callers
+    /// share a single prompt.
+    pub async fn authorize(
+        &self,
+        config: &CredentialAuthConfig,
+        reason: &str,
+    ) -> Result<(), String> {
+        if !config.enabled {
+            return Ok(());
+        }
+        self.confirm(Duration::from_secs(config.grace_period_seconds), reason)
+            .await
+    }
+
+    /// Prompts for OS authentication before a config change that would turn
+    /// the gate off or lengthen its grace period. Always asks afresh.
+    pub async fn authorize_change(
+        &self,
+        current: &CredentialAuthConfig,
+        next: &CredentialAuthConfig,
+    ) -> Result<(), String> {
+        if !weakens(current, next) {
+            return Ok(());
+        }
+        self.confirm(Duration::ZERO, "turn off OS authentication for credentials")
+            .await
+    }
+
+    /// Prompts for OS authentication even when the gate is off, unless the
+    /// last prompt is within `grace`.
+    pub async fn confirm(&self, grace: Duration, reason: &str) -> Result<(), String> {
+        let mut verified_at = self.verified_at.lock().await;
+        if within_grace(*verified_at, Instant::now(), grace) {
+            return Ok(());
+        }
+
+        let reason = reason.to_string();
+        let verified = tokio::task::spawn_blocking(move || verify(&reason))
+            .await
+            .map_err(|error| format!("OS authentication failed: {error}"))??;
+        if !verified {
+            return Err("OS authentication was cancelled or refused".to_string());
+        }
+        *verified_at = Some(Instant::now());
+        Ok(())
+    }
+}
+
+fn weakens(current: &CredentialAuthConfig, next: &CredentialAuthConfig) -> bool {
+    current.enabled && (!next.enabled || next.grace_period_seconds > current.grace_period_seconds)
+}
+
+fn within_grace(verified_at: Option<Instant>, now: Instant, grace: Duration) -> bool {
+    verified_at.is_some_and(|at| now.saturating_duration_since(at) < grace)
+}
+
+/// Touch ID, falling back to the login password when it is unavailable.
+#[cfg(target_os = "macos")]
+fn verify(reason: &str) -> Result<bool, String> {
+    use objc2::runtime::Bool;
+    use objc2_foundation::{NSError, NSString};
+    use objc2_local_authentication::{LAContext, LAPolicy};
+    use std::sync::mpsc;
+
+    let (sender, receiver) = mpsc::channel();
+    let reply = block2::RcBlock::new(move |success: Bool, _error: *mut NSError| {
+        let _ = sender.send(success.as_bool());
+    });
+    unsafe {
+        let context = LAContext::new();
+        context.evaluatePolicy_localizedReason_reply(
+            LAPolicy::DeviceOwnerAuthentication,
+            &NSString::from_str(reason),
+            &reply,
+        );
+    }
+    receiver
+        .recv()
+        .map_err(|_| "Touch ID prompt closed without an answer".to_string())
+}
+
+#[cfg(windows)]
+fn verify(reason: &str) -> Result<bool, String> {
+    use windows::Security::Credentials::UI::{UserConsentVerificationResult, UserConsentVerifier};
+    use windows::core::HSTRING;
+
+    let result = UserConsentVerifier::RequestVerificationAsync(&HSTRING::from(reason))
+        .and_then(|operation| operation.get())
+        .map_err(|error| format!("Windows Hello failed: {error}"))?;
+    match result {
+        UserConsentVerificationResult::Verified => Ok(true),
+        UserConsentVerificationResult::Canceled
+        | UserConsentVerificationResult::RetriesExhausted => Ok(false),
+        other => Err(format!("Windows Hello is unavailable ({})", other.0)),
+    }
+}
+
+/// Asks polkit through `pkcheck`, which shows the desktop's authentication
+/// agent.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn verify(_reason: &str) -> Result<bool, String> {
+    match pkcheck(POLKIT_ACTION) {
+        Err(error) if error.contains("not registered") => {
+            log::warn!("{error}; checking {FALLBACK_POLKIT_ACTION} instead");
+            pkcheck(FALLBACK_POLKIT_ACTION)
+        }
+        result => result,
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn pkcheck(action: &str) -> Result<bool, String> {
+    let output = std::process::Command::new("pkcheck")
+        .args(["--action-id", action, "--process"])
+        .arg(std::process::id().to_string())
+        .arg("--allow-user-interaction")
+        .output()
+        .map_err(|error| format!("polkit is unavailable: {error}"))?;
+    match output.status.code() {
+        Some(0) => Ok(true),
+        Some(1..=3) => Ok(false),
+        _ => Err(format!(
+            "polkit check failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn verify(_reason: &str) -> Result<bool, String> {
+    Err("OS authentication is not supported on this platform".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grace_period_covers_recent_verification_only() {
+        let now = Instant::now();
+        let grace = Duration::from_secs(300);
+        let earlier = |secs| now.checked_sub(Duration::from_secs(secs)).unwrap();
+
+        assert!(!within_grace(None, now, grace));
+        assert!(within_grace(Some(earlier(299)), now, grace));
+        assert!(!within_grace(Some(earlier(300)), now, grace));
+        assert!(!within_grace(Some(now), now, Duration::ZERO));
+    }
+
+    #[test]
+    fn turning_the_gate_off_or_lengthening_its_grace_weakens_it() {
+        let gate = |enabled, grace_period_seconds| CredentialAuthConfig {
+            enabled,
+            grace_period_seconds,
+        };
+
+        assert!(weakens(&gate(true, 300), &gate(false, 300)));
+        assert!(weakens(&gate(true, 300), &gate(true, 600)));
+        assert!(!weakens(&gate(true, 300), &gate(true, 0)));
+        assert!(!weakens(&gate(false, 300), &gate(false, 600)));
+        assert!(!weakens(&gate(false, 300), &gate(true, 300)));
+    }
+}
@@ -0,0 +1,575 @@
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use tokio::sync::{Mutex, MutexGuard};
+use uuid::Uuid;
+
+use crate::migrations::{self, Migration};
+
+pub const EVENT_OUTBOX_UPDATED: &str = "outbox_updated";
+
+pub const OUTBOX_MIGRATION_SCOPE: &str = "outbox";
+
+pub const OUTBOX_MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    description: "create outbox items",
+    up: |connection| {
+        connection.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS outbox_items (
+                id TEXT PRIMARY KEY,
+                workspace_id TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                depends_on TEXT,
+                status TEXT NOT NULL DEFAULT 'queued',
+                attempts INTEGER NOT NULL DEFAULT 0,
+                last_error TEXT,
+                result TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_outbox_items_queue
+                ON outbox_items(workspace_id, status, position);
+            CREATE INDEX IF NOT EXISTS idx_outbox_items_depends_on
+                ON outbox_items(depends_on);
+            "#,
+        )
+    },
+    down: |connection| connection.execute_batch("DROP TABLE IF EXISTS outbox_items;"),
+}];
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutboxKind {
+    Message,
+    Transaction,
+}
+
+impl OutboxKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Message => "message",
+            Self::Transaction => "transaction",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "transaction" => Self::Transaction,
+            _ => Self::Message,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutboxStatus {
+    Queued,
+    Dispatching,
+    Sent,
+    Failed,
+    Cancelled,
+}
+
+impl OutboxStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Dispatching => "dispatching",
+            Self::Sent => "sent",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "dispatching" => Self::Dispatching,
+            "sent" => Self::Sent,
+            "failed" => Self::Failed,
+            "cancelled" => Self::Cancelled,
+            _ => Self::Queued,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxItem {
+    pub id: String,
+    pub workspace_id: String,
+    pub kind: OutboxKind,
+    pub payload: Value,
+    pub position: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depends_on: Option<String>,
+    pub status: OutboxStatus,
+    pub attempts: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Messages and transactions composed while offline, kept in the order they
+/// were queued until they can be dispatched. An item that depends on another
+/// is held back until that one was sent, and is dropped with it when it is
+/// cancelled or fails.
+pub struct Outbox {
+    db: Mutex<Connection>,
+    dispatch: Mutex<()>,
+}
+
+impl Outbox {
+    /// Items left `dispatching` by a previous run may or may not have gone
+    /// out, so they are failed rather than sent twice, along with their
+    /// dependents.
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        let connection = Connection::open(path).map_err(|err| err.to_string())?;
+        migrations::apply(&connection, OUTBOX_MIGRATION_SCOPE, OUTBOX_MIGRATIONS)?;
+        let interrupted = connection
+            .prepare("SELECT id FROM outbox_items WHERE status = 'dispatching'")
+            .and_then(|mut statement| {
+                statement
+                    .query_map([], |row| row.get::<_, String>(0))?
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .map_err(|err| err.to_string())?;
+        for id in interrupted {
+            Self::close_with_dependents(
+                &connection,
+                &id,
+                OutboxStatus::Failed,
+                Some("Interrupted while dispatching"),
+            )?;
+        }
+        Ok(Self {
+            db: Mutex::new(connection),
+            dispatch: Mutex::new(()),
+        })
+    }
+
+    #[cfg(test)]
+    pub fn new_in_memory() -> Self {
+        let connection = Connection::open_in_memory().expect("in-memory sqlite");
+        migrations::apply(&connection, OUTBOX_MIGRATION_SCOPE, OUTBOX_MIGRATIONS)
+            .expect("init schema");
+        Self {
+            db: Mutex::new(connection),
+            dispatch: Mutex::new(()),
+        }
+    }
+
+    fn map_row(row: &Row<'_>) -> Result<OutboxItem, rusqlite::Error> {
+        let payload: String = row.get("payload")?;
+        let result: Option<String> = row.get("result")?;
+        Ok(OutboxItem {
+            id: row.get("id")?,
+            workspace_id: row.get("workspace_id")?,
+            kind: OutboxKind::parse(&row.get::<_, String>("kind")?),
+            payload: serde_json::from_str(&payload).unwrap_or(Value::Null),
+            position: row.get("position")?,
+            depends_on: row.get("depends_on")?,
+            status: OutboxStatus::parse(&row.get::<_, String>("status")?),
+            attempts: row.get("attempts")?,
+            last_error: row.get("last_error")?,
+            result: result.and_then(|result| serde_json::from_str(&result).ok()),
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+
+    /// Held for the length of a dispatch run; `None` while another run is in
+    /// progress.
+    pub fn begin_dispatch(&self) -> Option<MutexGuard<'_, ()>> {
+        self.dispatch.try_lock().ok()
+    }
+
+    pub async fn enqueue(
+        &self,
+        workspace_id: &str,
+        kind: OutboxKind,
+        payload: &Value,
+        depends_on: Option<&str>,
+    ) -> Result<OutboxItem, String> {
+        let connection = self.db.lock().await;
+        if let Some(dependency_id) = depends_on {
+            let dependency = Self::fetch(&connection, dependency_id)?
+                .filter(|item| item.workspace_id == workspace_id)
+                .ok_or_else(|| format!("Outbox item {dependency_id} not found"))?;
+            if matches!(
+                dependency.status,
+                OutboxStatus::Failed | OutboxStatus::Cancelled
+            ) {
+                return Err(format!(
+                    "Outbox item {dependency_id} was {} and will not be sent",
+                    dependency.status.as_str()
+                ));
+            }
+        }
+
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now().to_rfc3339();
+        connection
+            .execute(
+                "INSERT INTO outbox_items (
+                    id, workspace_id, kind, payload, position, depends_on, created_at, updated_at
+                 ) VALUES (
+                    ?1, ?2, ?3, ?4,
+                    (SELECT COALESCE(MAX(position), 0) + 1 FROM outbox_items),
+                    ?5, ?6, ?6
+                 )",
+                params![
+                    &id,
+                    workspace_id,
+                    kind.as_str(),
+                    payload.to_string(),
+                    depends_on,
+                    &now
+                ],
+            )
+            .map_err(|err| err.to_string())?;
+        Self::fetch(&connection, &id)?.ok_or_else(|| format!("Outbox item {id} not found"))
+    }
+
+    /// Oldest first. Sent, failed and cancelled items are only included with
+    /// `include_finished`.
+    pub async fn list(
+        &self,
+        workspace_id: &str,
+        include_finished: bool,
+    ) -> Result<Vec<OutboxItem>, String> {
+        let connection = self.db.lock().await;
+        let mut statement = connection
+            .prepare(
+                "SELECT * FROM outbox_items
+                 WHERE workspace_id = ?1 AND (?2 OR status IN ('queued', 'dispatching'))
+                 ORDER BY position",
+            )
+            .map_err(|err| err.to_string())?;
+        statement
+            .query_map(params![workspace_id, include_finished], Self::map_row)
+            .map_err(|err| err.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| err.to_string())
+    }
+
+    /// The oldest queued item whose dependency, if any, has been sent.
+    pub async fn next_ready(&self, workspace_id: &str) -> Result<Option<OutboxItem>, String> {
+        self.db
+            .lock()
+            .await
+            .query_row(
+                "SELECT item.* FROM outbox_items item
+                 LEFT JOIN outbox_items dependency ON dependency.id = item.depends_on
+                 WHERE item.workspace_id = ?1 AND item.status = 'queued'
+                   AND (item.depends_on IS NULL OR dependency.status = 'sent')
+                 ORDER BY item.position LIMIT 1",
+                params![workspace_id],
+                Self::map_row,
+            )
+            .optional()
+            .map_err(|err| err.to_string())
+    }
+
+    pub async fn mark_dispatching(&self, id: &str) -> Result<OutboxItem, String> {
+        self.transition(
+            id,
+            "UPDATE outbox_items
+             SET status = 'dispatching', attempts = attempts + 1, updated_at = ?2
+             WHERE id = ?1 AND status = 'queued'",
+            None,
+        )
+        .await
+    }
+
+    pub async fn mark_sent(&self, id: &str, result: &Value) -> Result<OutboxItem, String> {
+        self.transition(
+            id,
+            "UPDATE outbox_items
+             SET status = 'sent', last_error = NULL, result = ?3, updated_at = ?2
+             WHERE id = ?1 AND status = 'dispatching'",
+            Some(result.to_string()),
+        )
+        .await
+    }
+
+    /// Puts an item back in the queue after a dispatch that could not reach
+    /// the network, keeping the error for display.
+    pub async fn requeue(&self, id: &str, error: &str) -> Result<OutboxItem, String> {
+        self.transition(
+            id,
+            "UPDATE outbox_items
+             SET status = 'queued', last_error = ?3, updated_at = ?2
+             WHERE id = ?1 AND status = 'dispatching'",
+            Some(error.to_string()),
+        )
+        .await
+    }
+
+    /// Fails `id` and every queued item that depends on it. Returns the
+    /// items that changed, `id` first.
+    pub async fn mark_failed(&self, id: &str, error: &str) -> Result<Vec<OutboxItem>, String> {
+        self.close(
+            id,
+            OutboxStatus::Dispatching,
+            OutboxStatus::Failed,
+            Some(error),
+        )
+        .await
+    }
+
+    /// Cancels a queued item along with everything that depends on it.
+    /// Returns the items that changed, `id` first.
+    pub async fn cancel(&self, id: &str) -> Result<Vec<OutboxItem>, String> {
+        self.close(id, OutboxStatus::Queued, OutboxStatus::Cancelled, None)
+            .await
+    }
+
+    async fn transition(
+        &self,
+        id: &str,
+        sql: &str,
+        value: Option<String>,
+    ) -> Result<OutboxItem, String> {
+        let connection = self.db.lock().await;
+        let now = Utc::now().to_rfc3339();
+        let updated = match value {
+            Some(value) => connection.execute(sql, params![id, &now, value]),
+            None => connection.execute(sql, params![id, &now]),
+        }
+        .map_err(|err| err.to_string())?;
+        let item =
+            Self::fetch(&connection, id)?.ok_or_else(|| format!("Outbox item {id} not found"))?;
+        if updated == 0 {
+            return Err(format!("Outbox item {id} is {}", item.status.as_str()));
+        }
+        Ok(item)
+    }
+
+    async fn close(
+        &self,
+        id: &str,
+        from: OutboxStatus,
+        to: OutboxStatus,
+        error: Option<&str>,
+    ) -> Result<Vec<OutboxItem>, String> {
+        let connection = self.db.lock().await;
+        let item =
+            Self::fetch(&connection, id)?.ok_or_else(|| format!("Outbox item {id} not found"))?;
+        if item.status != from {
+            return Err(format!(
+                "Outbox item {id} is {} and can no longer be {}",
+                item.status.as_str(),
+                to.as_str()
+            ));
+        }
+        Self::close_with_dependents(&connection, id, to, error)
+    }
+
+    fn close_with_dependents(
+        connection: &Connection,
+        id: &str,
+        to: OutboxStatus,
+        error: Option<&str>,
+    ) -> Result<Vec<OutboxItem>, String> {
+        let transaction = connection
+            .unchecked_transaction()
+            .map_err(|err| err.to_string())?;
+        let dependent_error = format!("Depends on outbox item {id}, which was {}", to.as_str());
+        let mut statement = transaction
+            .prepare(
+                "WITH RECURSIVE affected(id) AS (
+                    SELECT ?1
+                    UNION
+                    SELECT item.id FROM outbox_items item
+                    JOIN affected ON item.depends_on = affected.id
+                 )
+                 UPDATE outbox_items
+                 SET status = ?2,
+                     last_error = CASE WHEN id = ?1 THEN ?3 ELSE ?4 END,
+                     updated_at = ?5
+                 WHERE id IN affected AND (id = ?1 OR status = 'queued')
+                 RETURNING *",
+            )
+            .map_err(|err| err.to_string())?;
+        let mut changed = statement
+            .query_map(
+                params![
+                    id,
+                    to.as_str(),
+                    error,
+                    &dependent_error,
+                    Utc::now().to_rfc3339()
+                ],
+                Self::map_row,
+            )
+            .map_err(|err| err.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| err.to_string())?;
+        drop(statement);
+        transaction.commit().map_err(|err| err.to_string())?;
+
+        changed.sort_by_key(|item| (item.id != id, item.position));
+        Ok(changed)
+    }
+
+    fn fetch(connection: &Connection, id: &str) -> Result<Option<OutboxItem>, String> {
+        connection
+            .query_row(
+                "SELECT * FROM outbox_items WHERE id = ?1",
+                params![id],
+                Self::map_row,
+            )
+            .optional()
+            .map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn items_dispatch_in_order_after_their_dependencies() {
+        let outbox = Outbox::new_in_memory();
+        let first = outbox
+            .enqueue(
+                "default",
+                OutboxKind::Message,
+                &json!({"content": "a"}),
+                None,
+            )
+            .await
+            .unwrap();
+        let second = outbox
+            .enqueue(
+                "default",
+                OutboxKind::Transaction,
+                &json!({"transactionBytes": "AA=="}),
+                Some(&first.id),
+            )
+            .await
+            .unwrap();
+        outbox
+            .enqueue("other", OutboxKind::Message, &json!({}), None)
+            .await
+            .unwrap();
+        assert!(second.position > first.position);
+        assert!(
+            outbox
+                .enqueue("other", OutboxKind::Message, &json!({}), Some(&first.id))
+                .await
+                .is_err()
+        );
+
+        let next = outbox.next_ready("default").await.unwrap().unwrap();
+        assert_eq!(next.id, first.id);
+        let dispatching = outbox.mark_dispatching(&first.id).await.unwrap();
+        assert_eq!(dispatching.attempts, 1);
+        assert!(outbox.next_ready("default").await.unwrap().is_none());
+
+        outbox.requeue(&first.id, "offline").await.unwrap();
+        outbox.mark_dispatching(&first.id).await.unwrap();
+        let sent = outbox
+            .mark_sent(&first.id, &json!({"ok": true}))
+            .await
+            .unwrap();
+        assert_eq!(sent.status, OutboxStatus::Sent);
+        assert_eq!(sent.attempts, 2);
+        assert!(sent.last_error.is_none());
+
+        assert_eq!(
+            outbox.next_ready("default").await.unwrap().unwrap().id,
+            second.id
+        );
+        assert_eq!(outbox.list("default", false).await.unwrap().len(), 1);
+        assert_eq!(outbox.list("default", true).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn interrupted_items_fail_their_dependents_on_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outbox.sqlite");
+        let (root, child) = {
+            let outbox = Outbox::from_path(&path).unwrap();
+            let root = outbox
+                .enqueue("default", OutboxKind::Message, &json!({}), None)
+                .await
+                .unwrap();
+            let child = outbox
+                .enqueue("default", OutboxKind::Message, &json!({}), Some(&root.id))
+                .await
+                .unwrap();
+            outbox.mark_dispatching(&root.id).await.unwrap();
+            (root, child)
+        };
+
+        let outbox = Outbox::from_path(&path).unwrap();
+        let items = outbox.list("default", true).await.unwrap();
+        assert_eq!(items.len(), 2);
+        assert!(items.iter().all(|item| item.status == OutboxStatus::Failed));
+        assert_eq!(
+            items[0].last_error.as_deref(),
+            Some("Interrupted while dispatching")
+        );
+        assert_eq!((&items[0].id, &items[1].id), (&root.id, &child.id));
+        assert!(outbox.next_ready("default").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn cancel_and_failure_carry_over_to_dependents() {
+        let outbox = Outbox::new_in_memory();
+        let root = outbox
+            .enqueue("default", OutboxKind::Message, &json!({}), None)
+            .await
+            .unwrap();
+        let child = outbox
+            .enqueue("default", OutboxKind::Message, &json!({}), Some(&root.id))
+            .await
+            .unwrap();
+        let grandchild = outbox
+            .enqueue("default", OutboxKind::Message, &json!({}), Some(&child.id))
+            .await
+            .unwrap();
+
+        let cancelled = outbox.cancel(&child.id).await.unwrap();
+        assert_eq!(
+            cancelled.iter().map(|item| &item.id).collect::<Vec<_>>(),
+            vec![&child.id, &grandchild.id]
+        );
+        assert!(cancelled[0].last_error.is_none());
+        assert!(cancelled[1].last_error.is_some());
+        assert!(outbox.cancel(&child.id).await.is_err());
+        assert!(
+            outbox
+                .enqueue("default", OutboxKind::Message, &json!({}), Some(&child.id))
+                .await
+                .is_err()
+        );
+
+        let sibling = outbox
+            .enqueue("default", OutboxKind::Message, &json!({}), Some(&root.id))
+            .await
+            .unwrap();
+        outbox.mark_dispatching(&root.id).await.unwrap();
+        assert!(outbox.cancel(&root.id).await.is_err());
+        let failed = outbox.mark_failed(&root.id, "rejected").await.unwrap();
+        assert_eq!(failed.len(), 2);
+        assert_eq!(failed[0].last_error.as_deref(), Some("rejected"));
+        assert_eq!(failed[1].id, sibling.id);
+        assert_eq!(failed[1].status, OutboxStatus::Failed);
+        assert!(outbox.next_ready("default").await.unwrap().is_none());
+    }
+}
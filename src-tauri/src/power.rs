@@ -0,0 +1,264 @@
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::{Instant, sleep_until};
+
+use crate::config::PowerPolicyConfig;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerState {
+    pub window_focused: bool,
+    pub on_battery: bool,
+    pub throttled: bool,
+    /// What background loop intervals are currently multiplied by.
+    pub interval_factor: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PowerInputs {
+    window_focused: bool,
+    on_battery: bool,
+}
+
+/// Tracks window focus and the power source so background loops can back
+/// off while nobody is looking or the machine runs on battery.
+pub struct PowerMonitor {
+    inputs: Mutex<PowerInputs>,
+    changed: Notify,
+}
+
+impl PowerMonitor {
+    pub fn new(on_battery: bool) -> Self {
+        Self {
+            inputs: Mutex::new(PowerInputs {
+                window_focused: true,
+                on_battery,
+            }),
+            changed: Notify::new(),
+        }
+    }
+
+    /// Returns whether the focus state changed.
+    pub fn set_window_focused(&self, focused: bool) -> bool {
+        self.update(|inputs| std::mem::replace(&mut inputs.window_focused, focused) != focused)
+    }
+
+    /// Returns whether the power source changed.
+    pub fn set_on_battery(&self, on_battery: bool) -> bool {
+        self.update(|inputs| std::mem::replace(&mut inputs.on_battery, on_battery) != on_battery)
+    }
+
+    pub fn state(&self, policy: &PowerPolicyConfig) -> PowerState {
+        let inputs = self.inputs();
+        let factor = interval_factor(inputs.window_focused, inputs.on_battery, policy);
+        PowerState {
+            window_focused: inputs.window_focused,
+            on_battery: inputs.on_battery,
+            throttled: factor > 1,
+            interval_factor: factor,
+        }
+    }
+
+    /// Sleeps for `base` stretched by the current interval factor. A change in
+    /// focus or power source re-evaluates the deadline, so refocusing the
+    /// window cuts a stretched wait short.
+    pub async fn sleep(&self, base: Duration, policy: &PowerPolicyConfig) {
+        let started = Instant::now();
+        loop {
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+
+            let factor = self.state(policy).interval_factor;
+            let deadline = started + base.saturating_mul(factor);
+            if Instant::now() >= deadline {
+                return;
+            }
+            tokio::select! {
+                _ = sleep_until(deadline) => return,
+                _ = changed => {}
+            }
+        }
+    }
+
+    fn inputs(&self) -> PowerInputs {
+        match self.inputs.lock() {
+            Ok(inputs) => *inputs,
+            Err(poisoned) => *poisoned.into_inner(),
+        }
+    }
+
+    fn update(&self, apply: impl FnOnce(&mut PowerInputs) -> bool) -> bool {
+        let changed = match self.inputs.lock() {
+            Ok(mut inputs) => apply(&mut inputs),
+            Err(poisoned) => apply(&mut poisoned.into_inner()),
+        };
+        if changed {
+            self.changed.notify_waiters();
+        }
+        changed
+    }
+}
+
+/// The larger of the factors that apply; a disabled policy never throttles.
+pub fn interval_factor(window_focused: bool, on_battery: bool, policy: &PowerPolicyConfig) -> u32 {
+    if !policy.enabled {
+        return 1;
+    }
+    let mut factor = 1;
+    if !window_focused {
+        factor = factor.max(policy.unfocused_factor);
+    }
+    if on_battery {
+        factor = factor.max(policy.battery_factor);
+    }
+    factor
+}
+
+/// Whether the machine is running on battery, or `None` when the platform
+/// does not say. Only Linux and macOS are probed; elsewhere the app behaves
+/// as if on mains power.
+pub fn detect_on_battery() -> Option<bool> {
+    #[cfg(target_os = "linux")]
+    {
+        on_battery_from_sysfs(Path::new("/sys/class/power_supply"))
+    }
+    #[cfg(target_os = "macos")]
+    {
+        std::process::Command::new("pmset")
+            .args(["-g", "batt"])
+            .output()
+            .ok()
+            .and_then(|output| on_battery_from_pmset(&String::from_utf8_lossy(&output.stdout)))
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        None
+    }
+}
+
+/// Reads `/sys/class/power_supply`: any online mains adapter means AC power,
+/// otherwise a discharging battery means battery power.
+#[cfg_attr(not(any(target_os = "linux", test)), allow(dead_code))]
+fn on_battery_from_sysfs(root: &Path) -> Option<bool> {
+    let read = |path: &Path| {
+        fs::read_to_string(path)
+            .map(|value| value.trim().to_string())
+            .unwrap_or_default()
+    };
+
+    let mut has_mains = false;
+    let mut mains_online = false;
+    let mut discharging = false;
+    let mut has_battery = false;
+    for entry in fs::read_dir(root).ok()?.flatten() {
+        let supply = entry.path();
+        match read(&supply.join("type")).as_str() {
+            "Mains" => {
+                has_mains = true;
+                mains_online |= read(&supply.join("online")) == "1";
+            }
+            "Battery" => {
+                has_battery = true;
+                discharging |= read(&supply.join("status")) == "Discharging";
+            }
+            _ => {}
+        }
+    }
+
+    match (has_battery, has_mains) {
+        (false, _) => None,
+        (true, true) => Some(!mains_online),
+        (true, false) => Some(discharging),
+    }
+}
+
+#[cfg_attr(not(any(target_os = "macos", test)), allow(dead_code))]
+fn on_battery_from_pmset(output: &str) -> Option<bool> {
+    let first_line = output.lines().next()?;
+    if first_line.contains("'Battery Power'") {
+        Some(true)
+    } else if first_line.contains("'AC Power'") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn factor_takes_the_larger_applicable_multiplier() {
+        let policy = PowerPolicyConfig::default();
+        assert_eq!(interval_factor(true, false, &policy), 1);
+        assert_eq!(interval_factor(false, false, &policy), 2);
+        assert_eq!(interval_factor(false, true, &policy), 4);
+
+        let disabled = PowerPolicyConfig {
+            enabled: false,
+            ..PowerPolicyConfig::default()
+        };
+        assert_eq!(interval_factor(false, true, &disabled), 1);
+
+        let monitor = PowerMonitor::new(false);
+        assert!(monitor.set_window_focused(false));
+        assert!(!monitor.set_window_focused(false));
+        assert!(monitor.state(&policy).throttled);
+    }
+
+    #[test]
+    fn power_source_is_read_from_sysfs_and_pmset() {
+        let dir = tempdir().unwrap();
+        let write = |name: &str, file: &str, value: &str| {
+            fs::create_dir_all(dir.path().join(name)).unwrap();
+            fs::write(dir.path().join(name).join(file), value).unwrap();
+        };
+        assert_eq!(on_battery_from_sysfs(dir.path()), None);
+
+        write("BAT0", "type", "Battery\n");
+        write("BAT0", "status", "Discharging\n");
+        assert_eq!(on_battery_from_sysfs(dir.path()), Some(true));
+
+        write("AC", "type", "Mains\n");
+        write("AC", "online", "1\n");
+        assert_eq!(on_battery_from_sysfs(dir.path()), Some(false));
+
+        assert_eq!(
+            on_battery_from_pmset("Now drawing from 'Battery Power'\n -InternalBattery-0"),
+            Some(true)
+        );
+        assert_eq!(
+            on_battery_from_pmset("Now drawing from 'AC Power'"),
+            Some(false)
+        );
+    }
+
+    #[tokio::test]
+    async fn refocusing_cuts_a_stretched_sleep_short() {
+        let monitor = std::sync::Arc::new(PowerMonitor::new(false));
+        let policy = PowerPolicyConfig::default();
+        monitor.set_window_focused(false);
+
+        let sleeper = {
+            let monitor = monitor.clone();
+            let policy = policy.clone();
+            tokio::spawn(async move {
+                let started = Instant::now();
+                monitor.sleep(Duration::from_millis(200), &policy).await;
+                started.elapsed()
+            })
+        };
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        monitor.set_window_focused(true);
+        let elapsed = sleeper.await.unwrap();
+        assert!(elapsed >= Duration::from_millis(250) && elapsed < Duration::from_millis(380));
+    }
+}
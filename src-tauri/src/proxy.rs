@@ -0,0 +1,208 @@
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+use reqwest::{Client, ClientBuilder, Proxy, Url};
+
+use crate::config::ProxyConfig;
+
+/// Hosts that never go through the proxy, since a remote proxy cannot reach
+/// services running on this machine.
+const LOOPBACK_HOSTS: &[&str] = &["localhost", "127.0.0.1", "::1", "[::1]"];
+
+const PROXY_SCHEMES: &[&str] = &["http", "https", "socks5", "socks5h"];
+
+/// Stands in for a configured proxy URL that does not parse, so requests
+/// fail instead of going direct. `.invalid` names never resolve.
+const UNUSABLE_PROXY: &str = "http://unusable-proxy.invalid";
+
+static ACTIVE: Lazy<RwLock<Option<ProxyConfig>>> = Lazy::new(|| RwLock::new(None));
+
+/// Routes requests sent from now on through `config`, including those of
+/// clients built before the change.
+pub fn set(config: &ProxyConfig) {
+    let active = (config.enabled && !config.url.trim().is_empty()).then(|| config.clone());
+    if let Err(error) = check(config) {
+        log::error!("{error}; requests that would use the proxy will fail");
+    }
+    *ACTIVE
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = active;
+}
+
+fn active() -> Option<ProxyConfig> {
+    ACTIVE
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+/// `Client::builder()` with the proxy settings applied. Without them the
+/// usual `HTTP_PROXY`, `HTTPS_PROXY`, `ALL_PROXY` and `NO_PROXY` variables
+/// are honoured, as reqwest does by default.
+pub fn client_builder() -> ClientBuilder {
+    Client::builder().proxy(Proxy::custom(|url| proxy_for(active().as_ref(), url)))
+}
+
+/// `Client::new()` with the proxy settings applied.
+pub fn client() -> Result<Client, String> {
+    client_builder()
+        .build()
+        .map_err(|error| format!("Failed to build HTTP client: {error}"))
+}
+
+/// Rejects an enabled proxy whose URL is not an http, https or SOCKS URL
+/// with a host.
+pub fn check(config: &ProxyConfig) -> Result<(), String> {
+    if !config.enabled || config.url.trim().is_empty() {
+        return Ok(());
+    }
+    parse_proxy_url(config.url.trim()).map(drop)
+}
+
+fn parse_proxy_url(url: &str) -> Result<Url, String> {
+    let parsed = Url::parse(url).map_err(|error| format!("Invalid proxy URL {url}: {error}"))?;
+    if !PROXY_SCHEMES.contains(&parsed.scheme()) {
+        return Err(format!(
+            "Proxy URL {url} must use http, https, socks5 or socks5h"
+        ));
+    }
+    if parsed.host_str().is_none_or(str::is_empty) {
+        return Err(format!("Proxy URL {url} has no host"));
+    }
+    Ok(parsed)
+}
+
+/// Environment for the Node bridges, so their requests take the same proxy.
+/// Empty when no proxy is configured, leaving the inherited variables alone.
+pub fn env_vars() -> Vec<(&'static str, String)> {
+    let Some(config) = active() else {
+        return Vec::new();
+    };
+    let url = match parse_proxy_url(config.url.trim()) {
+        Ok(_) => config.url.trim().to_string(),
+        Err(_) => UNUSABLE_PROXY.to_string(),
+    };
+    let no_proxy = LOOPBACK_HOSTS
+        .iter()
+        .map(|host| host.to_string())
+        .chain(config.no_proxy.iter().map(|host| host.trim().to_string()))
+        .collect::<Vec<_>>()
+        .join(",");
+    vec![
+        ("HTTP_PROXY", url.clone()),
+        ("HTTPS_PROXY", url.clone()),
+        ("ALL_PROXY", url.clone()),
+        ("NO_PROXY", no_proxy.clone()),
+        ("http_proxy", url.clone()),
+        ("https_proxy", url.clone()),
+        ("all_proxy", url),
+        ("no_proxy", no_proxy),
+        // Makes Node's built-in fetch and http modules read the variables above.
+        ("NODE_USE_ENV_PROXY", "1".to_string()),
+    ]
+}
+
+fn proxy_for(config: Option<&ProxyConfig>, url: &Url) -> Option<Url> {
+    let host = url.host_str()?.to_ascii_lowercase();
+    if LOOPBACK_HOSTS.contains(&host.as_str()) {
+        return None;
+    }
+
+    let Some(config) = config else {
+        let no_proxy = env_var("NO_PROXY").unwrap_or_default();
+        let no_proxy: Vec<String> = no_proxy.split(',').map(str::to_string).collect();
+        if bypassed(&no_proxy, &host) {
+            return None;
+        }
+        let scheme_var = if url.scheme() == "https" {
+            "HTTPS_PROXY"
+        } else {
+            "HTTP_PROXY"
+        };
+        return env_var(scheme_var)
+            .or_else(|| env_var("ALL_PROXY"))
+            .and_then(|proxy| Url::parse(&proxy).ok());
+    };
+    if bypassed(&config.no_proxy, &host) {
+        return None;
+    }
+    parse_proxy_url(config.url.trim())
+        .ok()
+        .or_else(|| Url::parse(UNUSABLE_PROXY).ok())
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name)
+        .or_else(|_| std::env::var(name.to_ascii_lowercase()))
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+}
+
+/// Whether `host` matches a `no_proxy` entry: `*`, the host itself, or a
+/// domain it belongs to, written with or without a leading dot.
+fn bypassed(no_proxy: &[String], host: &str) -> bool {
+    no_proxy.iter().any(|entry| {
+        let entry = entry.trim().trim_start_matches('.').to_ascii_lowercase();
+        !entry.is_empty() && (entry == "*" || host == entry || host.ends_with(&format!(".{entry}")))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configured_proxy_skips_loopback_and_no_proxy_hosts() {
+        let config = ProxyConfig {
+            enabled: true,
+            url: "socks5h://proxy.corp.example:1080".to_string(),
+            no_proxy: vec![
+                ".internal.example".to_string(),
+                "api.openai.com".to_string(),
+            ],
+        };
+        let proxy = |url: &str| proxy_for(Some(&config), &Url::parse(url).unwrap());
+
+        assert_eq!(
+            proxy("https://testnet.mirrornode.hedera.com/api/v1").map(String::from),
+            Some("socks5h://proxy.corp.example:1080".to_string())
+        );
+        assert_eq!(proxy("http://localhost:7546/api"), None);
+        assert_eq!(proxy("http://127.0.0.1:3000"), None);
+        assert_eq!(proxy("https://registry.internal.example/v0/servers"), None);
+        assert_eq!(proxy("https://api.openai.com/v1"), None);
+        assert!(proxy("https://api.anthropic.com/v1").is_some());
+    }
+
+    #[test]
+    fn malformed_proxy_url_fails_closed() {
+        let config = ProxyConfig {
+            enabled: true,
+            url: "proxy.corp.example:8080".to_string(),
+            no_proxy: Vec::new(),
+        };
+        assert!(check(&config).is_err());
+        assert_eq!(
+            proxy_for(
+                Some(&config),
+                &Url::parse("https://api.openai.com/v1").unwrap()
+            )
+            .map(String::from),
+            Some(format!("{UNUSABLE_PROXY}/"))
+        );
+
+        let disabled = ProxyConfig {
+            enabled: false,
+            ..config
+        };
+        assert!(check(&disabled).is_ok());
+        assert!(
+            check(&ProxyConfig {
+                enabled: true,
+                url: "socks5h://proxy.corp.example:1080".to_string(),
+                no_proxy: Vec::new(),
+            })
+            .is_ok()
+        );
+    }
+}
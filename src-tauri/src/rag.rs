@@ -0,0 +1,721 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::agent::{AgentLlmSettings, ChatEntry};
+use crate::config::LlmProvider;
+use crate::migrations::{self, Migration};
+
+pub const RAG_MIGRATION_SCOPE: &str = "rag";
+
+pub const RAG_MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    description: "create document index",
+    up: |connection| {
+        connection.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS rag_documents (
+                id TEXT PRIMARY KEY,
+                path TEXT NOT NULL UNIQUE,
+                embedder TEXT NOT NULL,
+                size_bytes INTEGER NOT NULL,
+                modified_at TEXT,
+                chunk_count INTEGER NOT NULL,
+                indexed_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS rag_chunks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                document_id TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                text TEXT NOT NULL,
+                embedding BLOB NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_rag_chunks_document
+                ON rag_chunks(document_id, position);
+            "#,
+        )
+    },
+    down: |connection| {
+        connection.execute_batch(
+            "DROP TABLE IF EXISTS rag_chunks;
+             DROP TABLE IF EXISTS rag_documents;",
+        )
+    },
+}];
+
+/// Characters per chunk, about 300 tokens.
+const CHUNK_CHARS: usize = 1_200;
+
+/// Characters a chunk repeats from the end of the one before, so a passage
+/// split across the boundary is still found whole.
+const CHUNK_OVERLAP: usize = 200;
+
+/// Larger files are usually data dumps or build output rather than notes.
+const MAX_FILE_BYTES: u64 = 2 * 1024 * 1024;
+
+/// Directories never worth indexing when a whole tree is added.
+const SKIPPED_DIRECTORIES: &[&str] = &["node_modules", "target", "dist", "build", "__pycache__"];
+
+/// Most files one `collect_files` call returns, so adding a home directory
+/// by mistake fails instead of embedding all of it.
+const MAX_FILES: usize = 2_000;
+
+/// Words too common to say what a passage is about, left out of local
+/// embeddings so they do not make every chunk look similar.
+const STOP_WORDS: &[&str] = &[
+    "a", "about", "an", "and", "are", "as", "at", "be", "but", "by", "can", "do", "does", "for",
+    "from", "has", "have", "how", "i", "if", "in", "is", "it", "its", "me", "my", "not", "of",
+    "on", "or", "so", "that", "the", "their", "there", "this", "to", "was", "we", "what", "when",
+    "which", "who", "why", "will", "with", "you", "your",
+];
+
+const LOCAL_DIMENSIONS: usize = 512;
+
+/// Models used when documents are embedded with the agent's provider.
+const EMBEDDING_MODELS: &[(LlmProvider, &str)] = &[
+    (LlmProvider::Openai, "text-embedding-3-small"),
+    (LlmProvider::Openrouter, "openai/text-embedding-3-small"),
+    (LlmProvider::Gemini, "gemini-embedding-001"),
+];
+
+/// What turns text into vectors. Chunks are only compared with queries
+/// embedded the same way, so the index records each document's embedder.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Embedder {
+    /// Hashed word counts; needs no network and no key, but only matches on
+    /// shared words.
+    Local,
+    Provider {
+        settings: AgentLlmSettings,
+        model: String,
+    },
+}
+
+impl Embedder {
+    pub fn provider(settings: AgentLlmSettings) -> Result<Self, String> {
+        let model = EMBEDDING_MODELS
+            .iter()
+            .find(|(provider, _)| *provider == settings.provider)
+            .map(|(_, model)| model.to_string())
+            .ok_or_else(|| {
+                format!(
+                    "{:?} does not offer embeddings; index documents with the local embedder instead",
+                    settings.provider
+                )
+            })?;
+        Ok(Self::Provider { settings, model })
+    }
+
+    pub fn id(&self) -> String {
+        match self {
+            // Bumped when local vectors change, so older ones are indexed again.
+            Self::Local => format!("local-hash-{LOCAL_DIMENSIONS}-v2"),
+            Self::Provider { settings, model } => format!("{}:{model}", settings.provider.id()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedDocument {
+    pub id: String,
+    pub path: String,
+    pub embedder: String,
+    pub size_bytes: u64,
+    pub modified_at: Option<String>,
+    pub chunk_count: usize,
+    pub indexed_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RetrievedChunk {
+    pub path: String,
+    pub position: usize,
+    pub text: String,
+    /// Cosine similarity to the query.
+    pub score: f32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RagIndexSummary {
+    pub embedder: String,
+    pub indexed: usize,
+    pub chunks: usize,
+    /// Files already indexed with the same contents and embedder.
+    pub unchanged: usize,
+    /// Files left out because they are not text or are too large.
+    pub skipped: Vec<String>,
+}
+
+/// A text file ready to be chunked, with what identifies its version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceFile {
+    pub path: String,
+    pub size_bytes: u64,
+    pub modified_at: Option<String>,
+    pub text: String,
+}
+
+impl SourceFile {
+    /// `None` for files that are too large or not UTF-8 text.
+    pub fn read(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        if metadata.len() > MAX_FILE_BYTES {
+            return None;
+        }
+        let bytes = fs::read(path).ok()?;
+        if bytes.contains(&0) {
+            return None;
+        }
+        let text = String::from_utf8(bytes).ok()?;
+        Some(Self {
+            path: path.to_string_lossy().to_string(),
+            size_bytes: metadata.len(),
+            modified_at: metadata
+                .modified()
+                .ok()
+                .map(|modified| DateTime::<Utc>::from(modified).to_rfc3339()),
+            text,
+        })
+    }
+
+    fn matches(&self, document: &IndexedDocument, embedder: &str) -> bool {
+        document.embedder == embedder
+            && document.size_bytes == self.size_bytes
+            && document.modified_at == self.modified_at
+    }
+}
+
+/// The files under `path`, or `path` itself when it is a file. Hidden
+/// entries, dependency or build directories and `app_dir`, the app's own
+/// data, are left out. Fails past `MAX_FILES` files.
+pub fn collect_files(path: &Path, app_dir: &Path) -> Result<Vec<PathBuf>, String> {
+    if path.starts_with(app_dir) {
+        return Err(format!(
+            "{} holds the app's own data and cannot be indexed",
+            path.display()
+        ));
+    }
+    let metadata =
+        fs::metadata(path).map_err(|error| format!("Cannot read {}: {error}", path.display()))?;
+    if metadata.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(directory) = pending.pop() {
+        let entries = fs::read_dir(&directory)
+            .map_err(|error| format!("Cannot read {}: {error}", directory.display()))?;
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') {
+                continue;
+            }
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                if !SKIPPED_DIRECTORIES.contains(&name.as_str()) && entry.path() != app_dir {
+                    pending.push(entry.path());
+                }
+            } else if file_type.is_file() {
+                if files.len() == MAX_FILES {
+                    return Err(format!(
+                        "{} holds more than {MAX_FILES} files; add a smaller directory",
+                        path.display()
+                    ));
+                }
+                files.push(entry.path());
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Splits `text` into overlapping chunks, breaking between paragraphs or
+/// words where one falls in the second half of a chunk.
+pub fn chunk_text(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+        let mut end = (start + CHUNK_CHARS).min(chars.len());
+        if end < chars.len() {
+            let window = &chars[start..end];
+            let floor = CHUNK_CHARS / 2;
+            let paragraph = (floor..window.len().saturating_sub(1))
+                .rev()
+                .find(|&index| window[index] == '\n' && window[index + 1] == '\n');
+            let word = || {
+                (floor..window.len())
+                    .rev()
+                    .find(|&index| window[index].is_whitespace())
+            };
+            if let Some(split) = paragraph.or_else(word) {
+                end = start + split;
+            }
+        }
+        let chunk: String = chars[start..end].iter().collect();
+        if !chunk.trim().is_empty() {
+            chunks.push(chunk.trim().to_string());
+        }
+        if end == chars.len() {
+            break;
+        }
+        start = end.saturating_sub(CHUNK_OVERLAP).max(start + 1);
+    }
+    chunks
+}
+
+/// FNV-1a, which unlike the standard hasher is stable across releases.
+fn fnv1a(word: &str) -> u64 {
+    word.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// The local embedder: each lowercased word other than a stop word adds to
+/// one of a fixed number of buckets, with a sign from its hash so collisions
+/// tend to cancel out.
+pub fn local_embedding(text: &str) -> Vec<f32> {
+    let mut vector = vec![0.0_f32; LOCAL_DIMENSIONS];
+    for word in text
+        .split(|character: char| !character.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+    {
+        let hash = fnv1a(&word);
+        let bucket = (hash % LOCAL_DIMENSIONS as u64) as usize;
+        vector[bucket] += if hash >> 63 == 0 { 1.0 } else { -1.0 };
+    }
+    let norm = vector.iter().map(|value| value * value).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|value| *value /= norm);
+    }
+    vector
+}
+
+pub fn cosine_similarity(left: &[f32], right: &[f32]) -> f32 {
+    if left.len() != right.len() {
+        return 0.0;
+    }
+    let dot: f32 = left
+        .iter()
+        .zip(right)
+        .map(|(left, right)| left * right)
+        .sum();
+    let norms = left.iter().map(|value| value * value).sum::<f32>().sqrt()
+        * right.iter().map(|value| value * value).sum::<f32>().sqrt();
+    if norms == 0.0 { 0.0 } else { dot / norms }
+}
+
+fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+fn decode_vector(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect()
+}
+
+/// The retrieved chunks as a system entry to send along with the history.
+pub fn context_entry(chunks: &[RetrievedChunk]) -> Option<ChatEntry> {
+    if chunks.is_empty() {
+        return None;
+    }
+    let mut content = String::from(
+        "Excerpts from the user's indexed documents that may help with the next message. \
+         Cite the file when you use one.\n",
+    );
+    for (index, chunk) in chunks.iter().enumerate() {
+        content.push_str(&format!(
+            "\n[{}] {} (part {})\n{}\n",
+            index + 1,
+            chunk.path,
+            chunk.position + 1,
+            chunk.text
+        ));
+    }
+    Some(ChatEntry {
+        entry_type: "system".to_string(),
+        content,
+        id: None,
+    })
+}
+
+/// Chunks of local files and their embeddings, kept in their own database so
+/// the index can be dropped without touching chat history.
+pub struct DocumentStore {
+    db: Mutex<Connection>,
+}
+
+impl DocumentStore {
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        let connection = Connection::open(path).map_err(|err| err.to_string())?;
+        migrations::apply(&connection, RAG_MIGRATION_SCOPE, RAG_MIGRATIONS)?;
+        Ok(Self {
+            db: Mutex::new(connection),
+        })
+    }
+
+    #[cfg(test)]
+    pub fn new_in_memory() -> Self {
+        let connection = Connection::open_in_memory().expect("in-memory sqlite");
+        migrations::apply(&connection, RAG_MIGRATION_SCOPE, RAG_MIGRATIONS).expect("init schema");
+        Self {
+            db: Mutex::new(connection),
+        }
+    }
+
+    fn map_document(row: &Row<'_>) -> Result<IndexedDocument, rusqlite::Error> {
+        Ok(IndexedDocument {
+            id: row.get("id")?,
+            path: row.get("path")?,
+            embedder: row.get("embedder")?,
+            size_bytes: row.get::<_, i64>("size_bytes")? as u64,
+            modified_at: row.get("modified_at")?,
+            chunk_count: row.get::<_, i64>("chunk_count")? as usize,
+            indexed_at: row.get("indexed_at")?,
+        })
+    }
+
+    pub async fn list(&self) -> Result<Vec<IndexedDocument>, String> {
+        let db = self.db.lock().await;
+        let mut statement = db
+            .prepare("SELECT * FROM rag_documents ORDER BY path")
+            .map_err(|err| err.to_string())?;
+        statement
+            .query_map([], Self::map_document)
+            .map_err(|err| err.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| err.to_string())
+    }
+
+    pub async fn is_empty(&self) -> Result<bool, String> {
+        let db = self.db.lock().await;
+        db.query_row("SELECT NOT EXISTS (SELECT 1 FROM rag_chunks)", [], |row| {
+            row.get(0)
+        })
+        .map_err(|err| err.to_string())
+    }
+
+    /// Whether `file` is indexed as it is now with `embedder`.
+    pub async fn is_current(&self, file: &SourceFile, embedder: &str) -> Result<bool, String> {
+        let db = self.db.lock().await;
+        let document = db
+            .query_row(
+                "SELECT * FROM rag_documents WHERE path = ?1",
+                params![file.path],
+                Self::map_document,
+            )
+            .optional()
+            .map_err(|err| err.to_string())?;
+        Ok(document.is_some_and(|document| file.matches(&document, embedder)))
+    }
+
+    /// Replaces whatever was indexed for `file.path` with `chunks`, each
+    /// paired with its embedding.
+    pub async fn replace(
+        &self,
+        file: &SourceFile,
+        embedder: &str,
+        chunks: &[(String, Vec<f32>)],
+    ) -> Result<IndexedDocument, String> {
+        let mut db = self.db.lock().await;
+        let transaction = db.transaction().map_err(|err| err.to_string())?;
+        transaction
+            .execute(
+                "DELETE FROM rag_chunks WHERE document_id IN
+                 (SELECT id FROM rag_documents WHERE path = ?1)",
+                params![file.path],
+            )
+            .map_err(|err| err.to_string())?;
+        transaction
+            .execute(
+                "DELETE FROM rag_documents WHERE path = ?1",
+                params![file.path],
+            )
+            .map_err(|err| err.to_string())?;
+
+        let document = IndexedDocument {
+            id: Uuid::new_v4().to_string(),
+            path: file.path.clone(),
+            embedder: embedder.to_string(),
+            size_bytes: file.size_bytes,
+            modified_at: file.modified_at.clone(),
+            chunk_count: chunks.len(),
+            indexed_at: Utc::now().to_rfc3339(),
+        };
+        transaction
+            .execute(
+                "INSERT INTO rag_documents
+                 (id, path, embedder, size_bytes, modified_at, chunk_count, indexed_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    document.id,
+                    document.path,
+                    document.embedder,
+                    document.size_bytes as i64,
+                    document.modified_at,
+                    document.chunk_count as i64,
+                    document.indexed_at,
+                ],
+            )
+            .map_err(|err| err.to_string())?;
+        for (position, (text, embedding)) in chunks.iter().enumerate() {
+            transaction
+                .execute(
+                    "INSERT INTO rag_chunks (document_id, position, text, embedding)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![document.id, position as i64, text, encode_vector(embedding)],
+                )
+                .map_err(|err| err.to_string())?;
+        }
+        transaction.commit().map_err(|err| err.to_string())?;
+        Ok(document)
+    }
+
+    /// Returns whether a document was removed.
+    pub async fn remove(&self, id: &str) -> Result<bool, String> {
+        let mut db = self.db.lock().await;
+        let transaction = db.transaction().map_err(|err| err.to_string())?;
+        transaction
+            .execute("DELETE FROM rag_chunks WHERE document_id = ?1", params![id])
+            .map_err(|err| err.to_string())?;
+        let removed = transaction
+            .execute("DELETE FROM rag_documents WHERE id = ?1", params![id])
+            .map_err(|err| err.to_string())?;
+        transaction.commit().map_err(|err| err.to_string())?;
+        Ok(removed > 0)
+    }
+
+    /// The `top_k` chunks embedded with `embedder` that are closest to
+    /// `query`, leaving out those scoring under `min_score`.
+    pub async fn search(
+        &self,
+        embedder: &str,
+        query: &[f32],
+        top_k: usize,
+        min_score: f32,
+    ) -> Result<Vec<RetrievedChunk>, String> {
+        let db = self.db.lock().await;
+        let mut statement = db
+            .prepare(
+                "SELECT d.path, c.position, c.text, c.embedding
+                 FROM rag_chunks c JOIN rag_documents d ON d.id = c.document_id
+                 WHERE d.embedder = ?1",
+            )
+            .map_err(|err| err.to_string())?;
+        let rows = statement
+            .query_map(params![embedder], |row| {
+                let embedding: Vec<u8> = row.get(3)?;
+                Ok(RetrievedChunk {
+                    path: row.get(0)?,
+                    position: row.get::<_, i64>(1)? as usize,
+                    text: row.get(2)?,
+                    score: cosine_similarity(query, &decode_vector(&embedding)),
+                })
+            })
+            .map_err(|err| err.to_string())?;
+
+        let mut chunks = Vec::new();
+        for chunk in rows {
+            let chunk = chunk.map_err(|err| err.to_string())?;
+            if chunk.score >= min_score {
+                chunks.push(chunk);
+            }
+        }
+        chunks.sort_by(|left, right| right.score.total_cmp(&left.score));
+        chunks.truncate(top_k);
+        Ok(chunks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn source(path: &str, text: &str) -> SourceFile {
+        SourceFile {
+            path: path.to_string(),
+            size_bytes: text.len() as u64,
+            modified_at: Some("2026-01-01T00:00:00+00:00".to_string()),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn chunks_overlap_and_break_between_words() {
+        assert_eq!(chunk_text("  short note \n"), vec!["short note"]);
+        assert!(chunk_text(" \n ").is_empty());
+
+        let text = (0..600)
+            .map(|index| format!("w{index}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let chunks = chunk_text(&text);
+        assert!(chunks.len() > 1);
+        for pair in chunks.windows(2) {
+            assert!(pair[0].chars().count() <= CHUNK_CHARS);
+            let last_word = pair[0].rsplit(' ').next().unwrap();
+            assert!(pair[1].contains(&format!(" {last_word}")));
+        }
+        assert!(chunks.last().unwrap().ends_with("w599"));
+    }
+
+    #[test]
+    fn local_embeddings_rank_shared_words_higher() {
+        let query = local_embedding("How do I associate a token?");
+        let related = local_embedding("To associate a token with an account, sign TokenAssociate.");
+        let unrelated = local_embedding("Consensus timestamps are nanoseconds.");
+        assert!(cosine_similarity(&query, &related) > cosine_similarity(&query, &unrelated));
+        assert_eq!(local_embedding("Token"), local_embedding("token"));
+        assert_eq!(local_embedding("the token"), local_embedding("token"));
+        assert_eq!(
+            cosine_similarity(
+                &local_embedding("What is the fee for this?"),
+                &local_embedding("What is in the box and what is it for?")
+            ),
+            0.0
+        );
+        assert_eq!(decode_vector(&encode_vector(&query)), query);
+    }
+
+    #[test]
+    fn provider_embedders_need_an_embedding_api() {
+        let settings = AgentLlmSettings {
+            provider: LlmProvider::Openai,
+            api_key: "sk-test".into(),
+            model: Some("gpt-4o".to_string()),
+            base_url: None,
+        };
+        let embedder = Embedder::provider(settings.clone()).unwrap();
+        assert_eq!(embedder.id(), "openai:text-embedding-3-small");
+        assert_eq!(Embedder::Local.id(), "local-hash-512-v2");
+        assert!(
+            Embedder::provider(AgentLlmSettings {
+                provider: LlmProvider::Anthropic,
+                ..settings
+            })
+            .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn search_matches_the_embedder_and_reindexing_replaces_chunks() {
+        let store = DocumentStore::new_in_memory();
+        assert!(store.is_empty().await.unwrap());
+        let embedder = Embedder::Local.id();
+        let notes = source("/notes/tokens.md", "Associating a token");
+        let chunks = |texts: &[&str]| -> Vec<(String, Vec<f32>)> {
+            texts
+                .iter()
+                .map(|text| (text.to_string(), local_embedding(text)))
+                .collect()
+        };
+
+        store
+            .replace(
+                &notes,
+                &embedder,
+                &chunks(&["Associate the token first.", "Then transfer it."]),
+            )
+            .await
+            .unwrap();
+        store
+            .replace(
+                &source("/notes/other.md", "Topics"),
+                "openai:text-embedding-3-small",
+                &chunks(&["Associate the token first."]),
+            )
+            .await
+            .unwrap();
+        assert!(store.is_current(&notes, &embedder).await.unwrap());
+        assert!(
+            !store
+                .is_current(&source("/notes/tokens.md", "Edited"), &embedder)
+                .await
+                .unwrap()
+        );
+
+        let query = local_embedding("associate token");
+        let found = store.search(&embedder, &query, 5, 0.1).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path, "/notes/tokens.md");
+        assert_eq!(found[0].position, 0);
+
+        let document = store
+            .replace(&notes, &embedder, &chunks(&["Rewritten."]))
+            .await
+            .unwrap();
+        assert_eq!(document.chunk_count, 1);
+        assert!(
+            store
+                .search(&embedder, &query, 5, 0.1)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(store.list().await.unwrap().len(), 2);
+
+        assert!(store.remove(&document.id).await.unwrap());
+        assert!(!store.remove(&document.id).await.unwrap());
+        assert_eq!(store.list().await.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn collecting_skips_hidden_dependency_and_app_directories() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("docs")).unwrap();
+        fs::create_dir_all(dir.path().join("node_modules/pkg")).unwrap();
+        fs::create_dir_all(dir.path().join(".git")).unwrap();
+        fs::write(dir.path().join("docs/guide.md"), "guide").unwrap();
+        fs::write(dir.path().join("node_modules/pkg/index.js"), "x").unwrap();
+        fs::write(dir.path().join(".git/HEAD"), "ref").unwrap();
+        fs::write(dir.path().join("image.bin"), [0_u8, 1, 2]).unwrap();
+        let app_dir = dir.path().join("app");
+        fs::create_dir_all(&app_dir).unwrap();
+        fs::write(app_dir.join("chat.sqlite"), "").unwrap();
+
+        let files = collect_files(dir.path(), &app_dir).unwrap();
+        assert_eq!(
+            files,
+            vec![
+                dir.path().join("docs/guide.md"),
+                dir.path().join("image.bin")
+            ]
+        );
+        assert!(SourceFile::read(&files[0]).is_some());
+        assert!(SourceFile::read(&files[1]).is_none());
+        assert_eq!(
+            collect_files(&files[0], &app_dir).unwrap(),
+            vec![dir.path().join("docs/guide.md")]
+        );
+        assert!(collect_files(&app_dir, &app_dir).is_err());
+
+        for index in 0..MAX_FILES {
+            fs::write(dir.path().join(format!("{index}.txt")), "").unwrap();
+        }
+        assert!(collect_files(dir.path(), &app_dir).is_err());
+    }
+}
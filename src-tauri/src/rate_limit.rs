@@ -0,0 +1,556 @@
+use chrono::{DateTime, Duration as ChronoDuration, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::config::LlmProvider;
+
+/// Longest a single request is held back; past this the call goes out anyway
+/// and the provider gets to decide.
+const MAX_WAIT: Duration = Duration::from_secs(120);
+/// Requests kept in hand before a provider's window resets.
+const REQUEST_RESERVE: u64 = 1;
+/// Token budget below which requests wait, as a percentage of the limit.
+const TOKEN_RESERVE_PERCENT: u64 = 5;
+const ROLLING_WINDOW_SECS: i64 = 60;
+
+/// Rate-limit headers captured by the bridge from one provider response.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct ProviderHeaders {
+    pub provider: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RateWindow {
+    pub limit: Option<u64>,
+    pub remaining: u64,
+    pub resets_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitSnapshot {
+    pub observed_at: DateTime<Utc>,
+    pub requests: Option<RateWindow>,
+    pub tokens: Option<RateWindow>,
+    pub retry_after: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderRateStatus {
+    pub provider: LlmProvider,
+    pub snapshot: Option<RateLimitSnapshot>,
+    /// Requests left once sends made after the snapshot are accounted for.
+    pub estimated_remaining_requests: Option<u64>,
+    pub sent_last_minute: usize,
+    pub throttled_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RateStatusReport {
+    pub providers: Vec<ProviderRateStatus>,
+    pub queued: usize,
+}
+
+#[derive(Default)]
+struct ProviderBudget {
+    snapshot: Option<RateLimitSnapshot>,
+    sent: VecDeque<DateTime<Utc>>,
+}
+
+impl ProviderBudget {
+    fn prune(&mut self, now: DateTime<Utc>) {
+        let cutoff = now - ChronoDuration::seconds(ROLLING_WINDOW_SECS);
+        while self.sent.front().is_some_and(|sent| *sent < cutoff) {
+            self.sent.pop_front();
+        }
+    }
+
+    fn estimated_remaining_requests(&self) -> Option<u64> {
+        let snapshot = self.snapshot.as_ref()?;
+        let requests = snapshot.requests.as_ref()?;
+        let since = self
+            .sent
+            .iter()
+            .filter(|sent| **sent > snapshot.observed_at)
+            .count() as u64;
+        Some(requests.remaining.saturating_sub(since))
+    }
+
+    /// When the next request may go out, or `None` if it can go now.
+    fn throttled_until(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let snapshot = self.snapshot.as_ref()?;
+        let mut until = snapshot.retry_after.filter(|at| *at > now);
+
+        let requests_exhausted = self
+            .estimated_remaining_requests()
+            .is_some_and(|remaining| remaining <= REQUEST_RESERVE);
+        if requests_exhausted
+            && let Some(resets_at) = snapshot.requests.as_ref().and_then(|w| w.resets_at)
+            && resets_at > now
+        {
+            until = until.max(Some(resets_at));
+        }
+
+        if let Some(tokens) = &snapshot.tokens
+            && let Some(limit) = tokens.limit
+            && tokens.remaining * 100 <= limit * TOKEN_RESERVE_PERCENT
+            && let Some(resets_at) = tokens.resets_at
+            && resets_at > now
+        {
+            until = until.max(Some(resets_at));
+        }
+        until
+    }
+}
+
+/// Tracks each provider's rate-limit budget from response headers plus the
+/// sends made since, and holds requests back when a window is nearly spent.
+#[derive(Default)]
+pub struct ProviderRateLimiter {
+    budgets: Mutex<HashMap<LlmProvider, ProviderBudget>>,
+    gate: tokio::sync::Mutex<()>,
+    queued: AtomicUsize,
+}
+
+impl ProviderRateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn observe(&self, headers: &ProviderHeaders, now: DateTime<Utc>) {
+        let Some(provider) = LlmProvider::from_id(&headers.provider) else {
+            log::debug!(
+                "Ignoring rate-limit headers for unknown provider {}",
+                headers.provider
+            );
+            return;
+        };
+        let Some(snapshot) = parse_headers(provider, &headers.headers, now) else {
+            return;
+        };
+        self.with_budgets(|budgets| {
+            budgets.entry(provider).or_default().snapshot = Some(snapshot);
+        });
+    }
+
+    /// Waits until `provider` has budget for another request, then counts it.
+    /// Callers queue behind each other so held-back requests leave in order.
+    pub async fn acquire(&self, provider: LlmProvider) {
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        let _gate = self.gate.lock().await;
+        if let Some(until) = self.throttled_until(provider, Utc::now()) {
+            let wait = (until - Utc::now()).to_std().unwrap_or_default();
+            if wait > MAX_WAIT {
+                log::warn!(
+                    "{provider:?} rate limit resets in {}s; sending without waiting",
+                    wait.as_secs()
+                );
+            } else {
+                log::info!(
+                    "Delaying {provider:?} request {}ms for its rate limit",
+                    wait.as_millis()
+                );
+                tokio::time::sleep(wait).await;
+            }
+        }
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+        self.record_send(provider, Utc::now());
+    }
+
+    pub fn throttled_until(
+        &self,
+        provider: LlmProvider,
+        now: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        self.with_budgets(|budgets| {
+            budgets
+                .get(&provider)
+                .and_then(|budget| budget.throttled_until(now))
+        })
+    }
+
+    pub fn record_send(&self, provider: LlmProvider, now: DateTime<Utc>) {
+        self.with_budgets(|budgets| {
+            let budget = budgets.entry(provider).or_default();
+            budget.sent.push_back(now);
+            budget.prune(now);
+        });
+    }
+
+    pub fn status(&self, now: DateTime<Utc>) -> RateStatusReport {
+        let mut providers = self.with_budgets(|budgets| {
+            budgets
+                .iter_mut()
+                .map(|(provider, budget)| {
+                    budget.prune(now);
+                    ProviderRateStatus {
+                        provider: *provider,
+                        snapshot: budget.snapshot.clone(),
+                        estimated_remaining_requests: budget.estimated_remaining_requests(),
+                        sent_last_minute: budget.sent.len(),
+                        throttled_until: budget.throttled_until(now),
+                    }
+                })
+                .collect::<Vec<_>>()
+        });
+        providers.sort_by_key(|status| status.provider as u8);
+        RateStatusReport {
+            providers,
+            queued: self.queued.load(Ordering::SeqCst),
+        }
+    }
+
+    fn with_budgets<T>(
+        &self,
+        apply: impl FnOnce(&mut HashMap<LlmProvider, ProviderBudget>) -> T,
+    ) -> T {
+        match self.budgets.lock() {
+            Ok(mut budgets) => apply(&mut budgets),
+            Err(poisoned) => apply(&mut poisoned.into_inner()),
+        }
+    }
+}
+
+/// Requests-per-minute limits set by the user, keyed by whatever the caller
+/// limits (an MCP server id, say). Each request reserves the next free slot
+/// in a rolling one-minute window, so queued callers leave in order.
+#[derive(Default)]
+pub struct RequestRateLimiter {
+    slots: Mutex<HashMap<String, VecDeque<DateTime<Utc>>>>,
+}
+
+impl RequestRateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reserves a slot for `key` under `per_minute` and returns how long the
+    /// caller must wait for it. When that is longer than `max_wait` nothing
+    /// is reserved and the wait is returned as the error.
+    pub fn reserve(
+        &self,
+        key: &str,
+        per_minute: u32,
+        max_wait: Duration,
+        now: DateTime<Utc>,
+    ) -> Result<Duration, Duration> {
+        let mut slots = match self.slots.lock() {
+            Ok(slots) => slots,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let window = ChronoDuration::seconds(ROLLING_WINDOW_SECS);
+        let taken = slots.entry(key.to_string()).or_default();
+        while taken.front().is_some_and(|at| *at + window <= now) {
+            taken.pop_front();
+        }
+
+        let per_minute = per_minute.max(1) as usize;
+        let slot = if taken.len() < per_minute {
+            now
+        } else {
+            taken[taken.len() - per_minute] + window
+        };
+        let wait = (slot - now).to_std().unwrap_or_default();
+        if wait > max_wait {
+            return Err(wait);
+        }
+        taken.push_back(slot);
+        Ok(wait)
+    }
+
+    /// Forgets `key`'s slots, for when whatever it limits is removed.
+    pub fn clear(&self, key: &str) {
+        match self.slots.lock() {
+            Ok(mut slots) => slots.remove(key),
+            Err(poisoned) => poisoned.into_inner().remove(key),
+        };
+    }
+}
+
+/// Reads the provider's rate-limit headers. Returns `None` when the response
+/// carried none of them.
+pub fn parse_headers(
+    provider: LlmProvider,
+    headers: &HashMap<String, String>,
+    now: DateTime<Utc>,
+) -> Option<RateLimitSnapshot> {
+    let headers: HashMap<String, &str> = headers
+        .iter()
+        .map(|(name, value)| (name.to_ascii_lowercase(), value.trim()))
+        .collect();
+    let number = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.parse::<u64>().ok())
+    };
+
+    let window = |limit: &str, remaining: &str, reset: &str| {
+        let reset = headers.get(reset).copied();
+        Some(RateWindow {
+            limit: number(limit),
+            remaining: number(remaining)?,
+            resets_at: reset.and_then(|value| match provider {
+                LlmProvider::Openai | LlmProvider::Gemini => {
+                    parse_reset_duration(value).map(|after| now + after)
+                }
+                LlmProvider::Anthropic => DateTime::parse_from_rfc3339(value)
+                    .ok()
+                    .map(|at| at.with_timezone(&Utc)),
+                LlmProvider::Openrouter => value
+                    .parse::<i64>()
+                    .ok()
+                    .and_then(|millis| Utc.timestamp_millis_opt(millis).single()),
+            }),
+        })
+    };
+
+    let (requests, tokens) = match provider {
+        LlmProvider::Openai => (
+            window(
+                "x-ratelimit-limit-requests",
+                "x-ratelimit-remaining-requests",
+                "x-ratelimit-reset-requests",
+            ),
+            window(
+                "x-ratelimit-limit-tokens",
+                "x-ratelimit-remaining-tokens",
+                "x-ratelimit-reset-tokens",
+            ),
+        ),
+        LlmProvider::Anthropic => (
+            window(
+                "anthropic-ratelimit-requests-limit",
+                "anthropic-ratelimit-requests-remaining",
+                "anthropic-ratelimit-requests-reset",
+            ),
+            window(
+                "anthropic-ratelimit-tokens-limit",
+                "anthropic-ratelimit-tokens-remaining",
+                "anthropic-ratelimit-tokens-reset",
+            ),
+        ),
+        LlmProvider::Openrouter => (
+            window(
+                "x-ratelimit-limit",
+                "x-ratelimit-remaining",
+                "x-ratelimit-reset",
+            ),
+            None,
+        ),
+        // Gemini sends no quota headers; only `retry-after` on a 429.
+        LlmProvider::Gemini => (None, None),
+    };
+
+    let retry_after = headers
+        .get("retry-after-ms")
+        .and_then(|value| value.parse::<f64>().ok())
+        .map(|millis| now + ChronoDuration::milliseconds(millis as i64))
+        .or_else(|| {
+            headers
+                .get("retry-after")
+                .and_then(|value| value.parse::<f64>().ok())
+                .map(|secs| now + ChronoDuration::milliseconds((secs * 1000.0) as i64))
+        });
+
+    if requests.is_none() && tokens.is_none() && retry_after.is_none() {
+        return None;
+    }
+    Some(RateLimitSnapshot {
+        observed_at: now,
+        requests,
+        tokens,
+        retry_after,
+    })
+}
+
+/// Parses OpenAI's reset durations such as `1s`, `6m0s`, `59.6s` or `20ms`.
+fn parse_reset_duration(value: &str) -> Option<ChronoDuration> {
+    let mut total_ms = 0.0;
+    let mut rest = value.trim();
+    if rest.is_empty() {
+        return None;
+    }
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let amount: f64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let factor = match &rest[..unit_len] {
+            "ms" => 1.0,
+            "s" => 1_000.0,
+            "m" => 60_000.0,
+            "h" => 3_600_000.0,
+            _ => return None,
+        };
+        total_ms += amount * factor;
+        rest = &rest[unit_len..];
+    }
+    Some(ChronoDuration::milliseconds(total_ms as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(provider: &str, pairs: &[(&str, &str)]) -> ProviderHeaders {
+        ProviderHeaders {
+            provider: provider.to_string(),
+            headers: pairs
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn provider_headers_are_parsed() {
+        let now = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+
+        let openai = parse_headers(
+            LlmProvider::Openai,
+            &headers(
+                "openai",
+                &[
+                    ("X-RateLimit-Limit-Requests", "500"),
+                    ("x-ratelimit-remaining-requests", "499"),
+                    ("x-ratelimit-reset-requests", "1m30.5s"),
+                    ("x-ratelimit-limit-tokens", "30000"),
+                    ("x-ratelimit-remaining-tokens", "29000"),
+                    ("x-ratelimit-reset-tokens", "20ms"),
+                ],
+            )
+            .headers,
+            now,
+        )
+        .unwrap();
+        let requests = openai.requests.unwrap();
+        assert_eq!((requests.limit, requests.remaining), (Some(500), 499));
+        assert_eq!(
+            requests.resets_at,
+            Some(now + ChronoDuration::milliseconds(90_500))
+        );
+        assert_eq!(
+            openai.tokens.unwrap().resets_at,
+            Some(now + ChronoDuration::milliseconds(20))
+        );
+
+        let anthropic = parse_headers(
+            LlmProvider::Anthropic,
+            &headers(
+                "anthropic",
+                &[
+                    ("anthropic-ratelimit-requests-limit", "50"),
+                    ("anthropic-ratelimit-requests-remaining", "0"),
+                    ("anthropic-ratelimit-requests-reset", "2026-01-01T00:00:30Z"),
+                    ("retry-after", "12"),
+                ],
+            )
+            .headers,
+            now,
+        )
+        .unwrap();
+        assert_eq!(
+            anthropic.requests.unwrap().resets_at,
+            Some(now + ChronoDuration::seconds(30))
+        );
+        assert_eq!(
+            anthropic.retry_after,
+            Some(now + ChronoDuration::seconds(12))
+        );
+
+        assert!(parse_headers(LlmProvider::Openai, &HashMap::new(), now).is_none());
+        assert_eq!(
+            parse_reset_duration("6m0s"),
+            Some(ChronoDuration::minutes(6))
+        );
+        assert_eq!(parse_reset_duration("soon"), None);
+    }
+
+    #[test]
+    fn sends_after_a_snapshot_draw_down_the_budget() {
+        let limiter = ProviderRateLimiter::new();
+        let now = Utc::now();
+        limiter.observe(
+            &headers(
+                "openai",
+                &[
+                    ("x-ratelimit-limit-requests", "3"),
+                    ("x-ratelimit-remaining-requests", "2"),
+                    ("x-ratelimit-reset-requests", "10s"),
+                ],
+            ),
+            now,
+        );
+        assert_eq!(limiter.throttled_until(LlmProvider::Openai, now), None);
+
+        limiter.record_send(LlmProvider::Openai, now + ChronoDuration::milliseconds(5));
+        let until = limiter.throttled_until(LlmProvider::Openai, now + ChronoDuration::seconds(1));
+        assert_eq!(until, Some(now + ChronoDuration::seconds(10)));
+        assert_eq!(
+            limiter.throttled_until(LlmProvider::Openai, now + ChronoDuration::seconds(11)),
+            None
+        );
+
+        let report = limiter.status(now + ChronoDuration::seconds(1));
+        assert_eq!(report.providers.len(), 1);
+        assert_eq!(report.providers[0].estimated_remaining_requests, Some(1));
+        assert_eq!(report.providers[0].sent_last_minute, 1);
+        assert_eq!(report.queued, 0);
+    }
+
+    #[tokio::test]
+    async fn acquire_waits_for_a_near_reset() {
+        let limiter = ProviderRateLimiter::new();
+        limiter.observe(
+            &headers(
+                "anthropic",
+                &[
+                    ("anthropic-ratelimit-tokens-limit", "1000"),
+                    ("anthropic-ratelimit-tokens-remaining", "10"),
+                    ("retry-after-ms", "150"),
+                ],
+            ),
+            Utc::now(),
+        );
+
+        let started = std::time::Instant::now();
+        limiter.acquire(LlmProvider::Anthropic).await;
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        assert_eq!(limiter.status(Utc::now()).providers[0].sent_last_minute, 1);
+    }
+
+    #[test]
+    fn request_limits_queue_within_the_max_wait() {
+        let limiter = RequestRateLimiter::new();
+        let now = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+        let max_wait = Duration::from_secs(30);
+
+        assert_eq!(limiter.reserve("a", 2, max_wait, now), Ok(Duration::ZERO));
+        let later = now + ChronoDuration::seconds(40);
+        assert_eq!(limiter.reserve("a", 2, max_wait, later), Ok(Duration::ZERO));
+        assert_eq!(
+            limiter.reserve("a", 2, max_wait, later),
+            Ok(Duration::from_secs(20))
+        );
+        assert_eq!(
+            limiter.reserve("a", 2, max_wait, later),
+            Err(Duration::from_secs(60))
+        );
+        assert_eq!(limiter.reserve("b", 2, max_wait, later), Ok(Duration::ZERO));
+
+        limiter.clear("a");
+        assert_eq!(limiter.reserve("a", 2, max_wait, later), Ok(Duration::ZERO));
+    }
+}
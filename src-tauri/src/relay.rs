@@ -0,0 +1,373 @@
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::process::{Child, Command};
+use tokio::sync::Mutex;
+
+use crate::config::JsonRpcRelayConfig;
+use crate::proxy;
+
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RelaySource {
+    Public,
+    Custom,
+    Local,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayEndpoint {
+    pub network: String,
+    pub chain_id: u64,
+    pub rpc_url: String,
+    pub source: RelaySource,
+    pub explorer_url: String,
+}
+
+/// Parameters for MetaMask's `wallet_addEthereumChain`.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MetaMaskNetwork {
+    pub chain_id: String,
+    pub chain_name: String,
+    pub rpc_urls: Vec<String>,
+    pub native_currency: Value,
+    pub block_explorer_urls: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FoundrySettings {
+    /// `[rpc_endpoints]` section for `foundry.toml`.
+    pub foundry_toml: String,
+    /// Environment for `forge`/`cast` without a config file.
+    pub env: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayToolSettings {
+    pub endpoint: RelayEndpoint,
+    pub metamask: MetaMaskNetwork,
+    pub foundry: FoundrySettings,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayHealth {
+    pub healthy: bool,
+    pub rpc_url: String,
+    pub expected_chain_id: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u64>,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The EVM chain id for `network`. Only mainnet and testnet can be
+/// configured, so anything else is rejected rather than mapped to testnet.
+pub fn chain_id(network: &str) -> Result<u64, String> {
+    match network {
+        "mainnet" => Ok(295),
+        "testnet" => Ok(296),
+        other => Err(format!("Unsupported relay network: {other}")),
+    }
+}
+
+fn public_relay_url(network: &str) -> String {
+    format!("https://{network}.hashio.io/api")
+}
+
+fn display_name(network: &str) -> String {
+    let mut chars = network.chars();
+    match chars.next() {
+        Some(first) => format!("Hedera {}{}", first.to_uppercase(), chars.as_str()),
+        None => "Hedera".to_string(),
+    }
+}
+
+/// The relay EVM tools should use: a running local relay first, then the
+/// configured URL, then the public relay for `network`.
+pub fn resolve_endpoint(
+    network: &str,
+    config: &JsonRpcRelayConfig,
+    local_port: Option<u16>,
+) -> Result<RelayEndpoint, String> {
+    let chain_id = chain_id(network)?;
+    let custom = config
+        .url
+        .as_deref()
+        .map(str::trim)
+        .filter(|url| !url.is_empty());
+    let (rpc_url, source) = match (local_port, custom) {
+        (Some(port), _) => (format!("http://127.0.0.1:{port}"), RelaySource::Local),
+        (None, Some(url)) => (url.to_string(), RelaySource::Custom),
+        (None, None) => (public_relay_url(network), RelaySource::Public),
+    };
+    Ok(RelayEndpoint {
+        network: network.to_string(),
+        chain_id,
+        rpc_url,
+        source,
+        explorer_url: format!("https://hashscan.io/{network}"),
+    })
+}
+
+pub fn tool_settings(endpoint: RelayEndpoint) -> RelayToolSettings {
+    let alias = format!("hedera_{}", endpoint.network);
+    let metamask = MetaMaskNetwork {
+        chain_id: format!("0x{:x}", endpoint.chain_id),
+        chain_name: display_name(&endpoint.network),
+        rpc_urls: vec![endpoint.rpc_url.clone()],
+        native_currency: json!({ "name": "HBAR", "symbol": "HBAR", "decimals": 18 }),
+        block_explorer_urls: vec![endpoint.explorer_url.clone()],
+    };
+    let foundry = FoundrySettings {
+        foundry_toml: format!("[rpc_endpoints]\n{alias} = \"{}\"\n", endpoint.rpc_url),
+        env: format!(
+            "ETH_RPC_URL={}\nCHAIN={}\n",
+            endpoint.rpc_url, endpoint.chain_id
+        ),
+    };
+    RelayToolSettings {
+        endpoint,
+        metamask,
+        foundry,
+    }
+}
+
+async fn rpc_call(client: &Client, url: &str, method: &str) -> Result<u64, String> {
+    let response: Value = client
+        .post(url)
+        .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": [] }))
+        .send()
+        .await
+        .map_err(|error| format!("Relay request failed: {error}"))?
+        .error_for_status()
+        .map_err(|error| format!("Relay returned an error: {error}"))?
+        .json()
+        .await
+        .map_err(|error| format!("Relay returned invalid JSON: {error}"))?;
+    if let Some(error) = response.get("error") {
+        return Err(format!("{method} failed: {error}"));
+    }
+    let result = response
+        .get("result")
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("{method} returned no result"))?;
+    u64::from_str_radix(result.trim_start_matches("0x"), 16)
+        .map_err(|_| format!("{method} returned {result}"))
+}
+
+/// Asks the relay for its chain id and latest block. Healthy means it
+/// answered both and serves the chain the app is configured for.
+pub async fn check_health(client: &Client, endpoint: &RelayEndpoint) -> RelayHealth {
+    let started = Instant::now();
+    let outcome = async {
+        let chain_id = rpc_call(client, &endpoint.rpc_url, "eth_chainId").await?;
+        let block_number = rpc_call(client, &endpoint.rpc_url, "eth_blockNumber").await?;
+        Ok::<_, String>((chain_id, block_number))
+    };
+    let outcome = tokio::time::timeout(HEALTH_TIMEOUT, outcome)
+        .await
+        .unwrap_or_else(|_| Err("Relay did not answer in time".to_string()));
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    let mut health = RelayHealth {
+        healthy: false,
+        rpc_url: endpoint.rpc_url.clone(),
+        expected_chain_id: endpoint.chain_id,
+        chain_id: None,
+        block_number: None,
+        latency_ms,
+        error: None,
+    };
+    match outcome {
+        Ok((chain_id, block_number)) => {
+            health.chain_id = Some(chain_id);
+            health.block_number = Some(block_number);
+            if chain_id == endpoint.chain_id {
+                health.healthy = true;
+            } else {
+                health.error = Some(format!(
+                    "Relay serves chain {chain_id}, expected {}",
+                    endpoint.chain_id
+                ));
+            }
+        }
+        Err(error) => health.error = Some(error),
+    }
+    health
+}
+
+/// Operator account and network handed to a local relay.
+pub struct LocalRelayEnv<'a> {
+    pub network: &'a str,
+    pub mirror_node_url: &'a str,
+    pub operator_id: &'a str,
+    pub operator_key: &'a str,
+}
+
+/// A relay server started from `JsonRpcRelayConfig::local_command`. The
+/// process is killed when stopped or when the app exits.
+#[derive(Default)]
+pub struct LocalRelay {
+    process: Mutex<Option<(Child, u16)>>,
+}
+
+impl LocalRelay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The port of the running relay, if it is still alive.
+    pub async fn port(&self) -> Option<u16> {
+        let mut process = self.process.lock().await;
+        let exited = match process.as_mut() {
+            Some((child, _)) => !matches!(child.try_wait(), Ok(None)),
+            None => return None,
+        };
+        if exited {
+            log::warn!("Local JSON-RPC relay exited");
+            *process = None;
+            return None;
+        }
+        process.as_ref().map(|(_, port)| *port)
+    }
+
+    pub async fn start(
+        &self,
+        config: &JsonRpcRelayConfig,
+        env: LocalRelayEnv<'_>,
+    ) -> Result<u16, String> {
+        let command = config
+            .local_command
+            .as_deref()
+            .map(str::trim)
+            .filter(|command| !command.is_empty())
+            .ok_or_else(|| "No local relay command is configured".to_string())?;
+        if self.port().await.is_some() {
+            return Err("The local relay is already running".to_string());
+        }
+        let chain_id = chain_id(env.network)?;
+
+        let child = Command::new(command)
+            .args(&config.local_args)
+            .env("HEDERA_NETWORK", env.network)
+            .env("CHAIN_ID", format!("0x{chain_id:x}"))
+            .env("MIRROR_NODE_URL", env.mirror_node_url)
+            .env("OPERATOR_ID_MAIN", env.operator_id)
+            .env("OPERATOR_KEY_MAIN", env.operator_key)
+            .env("SERVER_PORT", config.local_port.to_string())
+            .envs(proxy::env_vars())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|error| format!("Failed to start local relay: {error}"))?;
+
+        log::info!(
+            "Started local JSON-RPC relay on port {} for {}",
+            config.local_port,
+            env.network
+        );
+        *self.process.lock().await = Some((child, config.local_port));
+        Ok(config.local_port)
+    }
+
+    /// Returns whether a relay was running.
+    pub async fn stop(&self) -> bool {
+        let Some((mut child, _)) = self.process.lock().await.take() else {
+            return false;
+        };
+        if let Err(error) = child.kill().await {
+            log::warn!("Failed to stop local relay: {}", error);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::{Method::POST, MockServer};
+
+    #[test]
+    fn endpoint_prefers_local_then_custom_then_public() {
+        let mut config = JsonRpcRelayConfig::default();
+        let public = resolve_endpoint("testnet", &config, None).unwrap();
+        assert_eq!(public.rpc_url, "https://testnet.hashio.io/api");
+        assert_eq!(public.source, RelaySource::Public);
+        assert_eq!(public.chain_id, 296);
+
+        config.url = Some("https://relay.example.com".to_string());
+        let custom = resolve_endpoint("mainnet", &config, None).unwrap();
+        assert_eq!(custom.source, RelaySource::Custom);
+        assert_eq!(custom.chain_id, 295);
+
+        let local = resolve_endpoint("mainnet", &config, Some(7546)).unwrap();
+        assert_eq!(local.rpc_url, "http://127.0.0.1:7546");
+
+        assert!(resolve_endpoint("previewnet", &config, None).is_err());
+
+        let settings = tool_settings(public);
+        assert_eq!(settings.metamask.chain_id, "0x128");
+        assert_eq!(settings.metamask.chain_name, "Hedera Testnet");
+        assert!(
+            settings
+                .foundry
+                .foundry_toml
+                .contains("hedera_testnet = \"https://testnet.hashio.io/api\"")
+        );
+    }
+
+    #[tokio::test]
+    async fn health_checks_chain_id() {
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(POST).body_contains("eth_chainId");
+                then.status(200)
+                    .json_body(json!({ "jsonrpc": "2.0", "id": 1, "result": "0x128" }));
+            })
+            .await;
+        server
+            .mock_async(|when, then| {
+                when.method(POST).body_contains("eth_blockNumber");
+                then.status(200)
+                    .json_body(json!({ "jsonrpc": "2.0", "id": 1, "result": "0x10" }));
+            })
+            .await;
+
+        let config = JsonRpcRelayConfig {
+            url: Some(server.url("/api")),
+            ..JsonRpcRelayConfig::default()
+        };
+        let client = Client::new();
+
+        let health = check_health(
+            &client,
+            &resolve_endpoint("testnet", &config, None).unwrap(),
+        )
+        .await;
+        assert!(health.healthy, "{:?}", health.error);
+        assert_eq!(health.block_number, Some(16));
+
+        let health = check_health(
+            &client,
+            &resolve_endpoint("mainnet", &config, None).unwrap(),
+        )
+        .await;
+        assert!(!health.healthy);
+        assert_eq!(health.chain_id, Some(296));
+    }
+}
@@ -2,9 +2,19 @@ use chrono::{DateTime, Utc};
 use rusqlite::{Connection, OptionalExtension, params};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value, json};
+use std::collections::HashMap;
 use std::path::Path;
 use tokio::sync::Mutex;
 
+use crate::config::LlmProvider;
+use crate::config_history::mask_secrets;
+use crate::migrations::{self, Migration};
+
+/// Bounds on what one agent turn can report, so a malformed usage payload
+/// can't inflate a session's totals or overflow SQLite's integers.
+const MAX_TURN_TOKENS: u64 = 10_000_000;
+const MAX_TURN_TOOL_CALLS: u64 = 1_000;
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct ChatMessage {
@@ -16,6 +26,8 @@ pub struct ChatMessage {
     pub message_type: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread_id: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -33,6 +45,62 @@ pub struct ChatSession {
     pub is_active: bool,
     #[serde(default)]
     pub messages: Vec<ChatMessage>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub pinned_message_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_read_at: Option<String>,
+    /// Non-user messages newer than `last_read_at`.
+    #[serde(default)]
+    pub unread_count: u64,
+    /// Provider and model this session talks to instead of the configured
+    /// ones.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub llm: Option<SessionLlm>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionLlm {
+    pub provider: LlmProvider,
+    pub model: String,
+}
+
+/// A rolling summary of the start of a conversation, sent to the agent in
+/// place of the history entries it covers.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSummary {
+    pub text: String,
+    /// Id of the last message the summary covers; it replaces that message
+    /// and every one before it.
+    pub covered_through: String,
+    pub updated_at: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatThread {
+    pub id: String,
+    pub session_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_message_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub created_at: String,
+    pub message_count: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_message_at: Option<String>,
+}
+
+/// Which part of a session's timeline to load.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MessageScope {
+    All,
+    /// Only messages that do not belong to a thread.
+    MainTimeline,
+    Thread(String),
 }
 
 #[derive(Clone, Debug)]
@@ -43,6 +111,261 @@ pub struct CreateSessionInput {
     pub is_active: bool,
 }
 
+/// Criteria for bulk session deletion; every set field must match.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SessionDeleteFilter {
+    /// RFC 3339 timestamp or `YYYY-MM-DD`; matches sessions whose last
+    /// activity is before it.
+    pub older_than: Option<String>,
+    pub mode: Option<String>,
+    pub inactive_only: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PruneSummary {
+    pub expired_messages: usize,
+    pub overflow_messages: usize,
+    pub total_removed: usize,
+}
+
+/// Usage reported for a single agent turn.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SessionUsage {
+    pub tool_calls: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Provider and model the tokens were spent on, when known.
+    pub provider: Option<String>,
+    pub model: Option<String>,
+}
+
+impl SessionUsage {
+    /// Reads usage from agent response metadata. Token counts may arrive under
+    /// `usage` or `tokenUsage` in either camelCase or snake_case; tool calls
+    /// are counted from a `toolCalls` array or taken from `toolCallCount`.
+    /// The provider and model fall back to the bridge's `answeredBy`.
+    /// Counts that aren't non-negative integers within the per-turn bounds
+    /// are ignored.
+    pub fn from_agent_metadata(metadata: &Value) -> Self {
+        let usage = metadata
+            .get("usage")
+            .or_else(|| metadata.get("tokenUsage"))
+            .filter(|usage| usage.is_object())
+            .unwrap_or(&Value::Null);
+        let read = |keys: &[&str]| {
+            keys.iter()
+                .find_map(|key| usage.get(*key).and_then(Value::as_u64))
+                .filter(|count| *count <= MAX_TURN_TOKENS)
+                .unwrap_or_default()
+        };
+        let tool_calls = metadata
+            .get("toolCalls")
+            .and_then(Value::as_array)
+            .map(|calls| calls.len() as u64)
+            .or_else(|| metadata.get("toolCallCount").and_then(Value::as_u64))
+            .filter(|count| *count <= MAX_TURN_TOOL_CALLS)
+            .unwrap_or_default();
+
+        let text = |keys: &[&str]| {
+            keys.iter()
+                .find_map(|key| usage.get(*key).and_then(Value::as_str))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        let answered_by = |key: &str| {
+            metadata
+                .get("answeredBy")
+                .and_then(|answered_by| answered_by.get(key))
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+
+        Self {
+            tool_calls,
+            prompt_tokens: read(&["promptTokens", "prompt_tokens", "inputTokens"]),
+            completion_tokens: read(&["completionTokens", "completion_tokens", "outputTokens"]),
+            provider: text(&["provider"]).or_else(|| answered_by("provider")),
+            model: text(&["model", "modelName", "model_name"]).or_else(|| answered_by("model")),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tool_calls == 0 && !self.has_tokens()
+    }
+
+    pub fn has_tokens(&self) -> bool {
+        self.prompt_tokens > 0 || self.completion_tokens > 0
+    }
+
+    /// The `tokenUsage` entry kept on the message, in the shape
+    /// `from_agent_metadata` reads back.
+    pub fn to_metadata(&self) -> Value {
+        let mut usage = Map::new();
+        usage.insert("promptTokens".into(), json!(self.prompt_tokens));
+        usage.insert("completionTokens".into(), json!(self.completion_tokens));
+        usage.insert(
+            "totalTokens".into(),
+            json!(self.prompt_tokens + self.completion_tokens),
+        );
+        if let Some(provider) = &self.provider {
+            usage.insert("provider".into(), json!(provider));
+        }
+        if let Some(model) = &self.model {
+            usage.insert("model".into(), json!(model));
+        }
+        Value::Object(usage)
+    }
+}
+
+/// Tokens a session spent on one model. Turns recorded before models were
+/// tracked have an empty provider and model.
+#[derive(Clone, Debug, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelUsage {
+    pub provider: String,
+    pub model: String,
+    pub responses: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+/// One tool invocation reported with an agent reply.
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentToolCall {
+    pub tool_name: String,
+    pub arguments: Value,
+    pub result: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AgentToolCall {
+    /// Reads the calls from a `toolCalls` array in agent response metadata,
+    /// or from LangChain's `intermediateSteps` when that is all there is.
+    /// Secrets in arguments and results, such as private keys, are masked.
+    pub fn from_agent_metadata(metadata: &Value) -> Vec<Self> {
+        let entries = metadata
+            .get("toolCalls")
+            .and_then(Value::as_array)
+            .or_else(|| metadata.get("intermediateSteps").and_then(Value::as_array));
+        entries
+            .into_iter()
+            .flatten()
+            .filter_map(Self::from_entry)
+            .collect()
+    }
+
+    fn from_entry(entry: &Value) -> Option<Self> {
+        let action = entry.get("action").unwrap_or(entry);
+        let field = |source: &Value, keys: &[&str]| {
+            keys.iter()
+                .find_map(|key| source.get(*key))
+                .filter(|value| !value.is_null())
+                .cloned()
+        };
+        let tool_name = field(action, &["toolName", "name", "tool"])?
+            .as_str()?
+            .trim()
+            .to_string();
+        if tool_name.is_empty() {
+            return None;
+        }
+        // LangChain passes arguments and observations as JSON text.
+        let parse = |value: Value| match value {
+            Value::String(text) => serde_json::from_str(&text).unwrap_or(Value::String(text)),
+            other => other,
+        };
+        let error = field(entry, &["error"]).map(|error| match error {
+            Value::String(message) => message,
+            other => other.to_string(),
+        });
+
+        Some(Self {
+            tool_name,
+            arguments: mask_secrets(
+                &field(action, &["arguments", "args", "input", "toolInput"])
+                    .map(parse)
+                    .unwrap_or(Value::Null),
+            ),
+            result: mask_secrets(
+                &field(entry, &["result", "output", "observation"])
+                    .map(parse)
+                    .unwrap_or(Value::Null),
+            ),
+            duration_ms: field(entry, &["durationMs", "duration_ms", "duration"])
+                .and_then(|value| value.as_f64())
+                .map(|millis| millis.max(0.0).round() as u64),
+            success: field(entry, &["success"])
+                .and_then(|value| value.as_bool())
+                .unwrap_or(error.is_none()),
+            error,
+        })
+    }
+}
+
+/// A stored tool call and the agent reply it was reported with.
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolCallRecord {
+    pub id: String,
+    pub session_id: String,
+    pub message_id: String,
+    /// Order of the call within its reply.
+    pub position: u32,
+    pub recorded_at: String,
+    #[serde(flatten)]
+    pub call: AgentToolCall,
+}
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionStats {
+    pub session_id: String,
+    pub message_count: u64,
+    pub tool_call_count: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_message_at: Option<String>,
+}
+
+/// Local changes past a sync watermark.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SessionChanges {
+    /// Changed sessions, each carrying only messages added after the watermark.
+    pub sessions: Vec<ChatSession>,
+    pub updated_through: Option<String>,
+    pub last_message_rowid: i64,
+}
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconcileSummary {
+    pub sessions_created: usize,
+    pub sessions_updated: usize,
+    pub messages_added: usize,
+    /// Highest message rowid before and after the remote messages were added.
+    #[serde(skip)]
+    pub rowids: (i64, i64),
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionReadState {
+    pub session_id: String,
+    pub last_read_at: String,
+    pub unread_count: u64,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct SessionContext {
@@ -56,33 +379,14 @@ pub struct SessionService {
     db: Mutex<Connection>,
 }
 
-impl SessionService {
-    pub fn from_path(path: &Path) -> Result<Self, String> {
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
-        }
-        let connection = Connection::open(path).map_err(|err| err.to_string())?;
-        Self::configure_connection(&connection)?;
-        Ok(Self {
-            db: Mutex::new(connection),
-        })
-    }
-
-    #[cfg(test)]
-    pub fn new_in_memory() -> Self {
-        let connection = Connection::open_in_memory().expect("in-memory sqlite");
-        Self::configure_connection(&connection).expect("init schema");
-        Self {
-            db: Mutex::new(connection),
-        }
-    }
+pub const SESSION_MIGRATION_SCOPE: &str = "session";
 
-    fn configure_connection(connection: &Connection) -> Result<(), String> {
-        connection
-            .pragma_update(None, "foreign_keys", &"ON")
-            .map_err(|err| err.to_string())?;
-        connection
-            .execute_batch(
+pub const SESSION_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create chat session tables",
+        up: |connection| {
+            connection.execute_batch(
                 r#"
                 CREATE TABLE IF NOT EXISTS chat_sessions (
                     id TEXT PRIMARY KEY,
@@ -112,7 +416,48 @@ impl SessionService {
                     metadata TEXT,
                     FOREIGN KEY(session_id) REFERENCES chat_sessions(id) ON DELETE CASCADE
                 );
-
+                "#,
+            )
+        },
+        down: |connection| {
+            connection.execute_batch(
+                r#"
+                DROP TABLE IF EXISTS chat_messages;
+                DROP TABLE IF EXISTS chat_session_contexts;
+                DROP TABLE IF EXISTS chat_sessions;
+                "#,
+            )
+        },
+    },
+    Migration {
+        // Databases created before these columns existed still need them; fresh
+        // databases already have them from version 1, so there is nothing to undo.
+        version: 2,
+        description: "backfill legacy chat columns",
+        up: |connection| {
+            migrations::add_column_if_missing(connection, "chat_sessions", "name", "TEXT")?;
+            migrations::add_column_if_missing(
+                connection,
+                "chat_sessions",
+                "last_message_at",
+                "TEXT",
+            )?;
+            migrations::add_column_if_missing(
+                connection,
+                "chat_sessions",
+                "is_active",
+                "INTEGER DEFAULT 1",
+            )?;
+            migrations::add_column_if_missing(connection, "chat_messages", "message_type", "TEXT")
+        },
+        down: migrations::noop,
+    },
+    Migration {
+        version: 3,
+        description: "create chat session indexes",
+        up: |connection| {
+            connection.execute_batch(
+                r#"
                 CREATE INDEX IF NOT EXISTS idx_chat_messages_session_timestamp
                     ON chat_messages(session_id, timestamp);
 
@@ -135,33 +480,328 @@ impl SessionService {
                     ON chat_messages(message_type);
                 "#,
             )
-            .map_err(|err| err.to_string())?;
-        Self::ensure_column(connection, "chat_sessions", "name", "TEXT");
-        Self::ensure_column(connection, "chat_sessions", "last_message_at", "TEXT");
-        Self::ensure_column(
-            connection,
-            "chat_sessions",
-            "is_active",
-            "INTEGER DEFAULT 1",
-        );
-        Self::ensure_column(connection, "chat_messages", "message_type", "TEXT");
-        Ok(())
-    }
+        },
+        down: |connection| {
+            connection.execute_batch(
+                r#"
+                DROP INDEX IF EXISTS idx_chat_messages_session_timestamp;
+                DROP INDEX IF EXISTS idx_chat_sessions_mode;
+                DROP INDEX IF EXISTS idx_chat_sessions_topic;
+                DROP INDEX IF EXISTS idx_chat_sessions_last_message;
+                DROP INDEX IF EXISTS idx_chat_sessions_active;
+                DROP INDEX IF EXISTS idx_chat_sessions_mode_topic;
+                DROP INDEX IF EXISTS idx_chat_messages_type;
+                "#,
+            )
+        },
+    },
+    Migration {
+        version: 4,
+        description: "add chat message threads",
+        up: |connection| {
+            connection.execute_batch(
+                r#"
+                CREATE TABLE IF NOT EXISTS chat_threads (
+                    id TEXT PRIMARY KEY,
+                    session_id TEXT NOT NULL,
+                    parent_message_id TEXT,
+                    title TEXT,
+                    created_at TEXT NOT NULL,
+                    FOREIGN KEY(session_id) REFERENCES chat_sessions(id) ON DELETE CASCADE
+                );
 
-    fn ensure_column(connection: &Connection, table: &str, column: &str, definition: &str) {
-        let pragma = format!("PRAGMA table_info({table})");
-        if let Ok(mut stmt) = connection.prepare(&pragma) {
-            if let Ok(mut rows) = stmt.query([]) {
-                while let Ok(Some(row)) = rows.next() {
-                    let name: String = row.get(1).unwrap_or_default();
-                    if name == column {
-                        return;
-                    }
-                }
+                CREATE INDEX IF NOT EXISTS idx_chat_threads_session
+                    ON chat_threads(session_id);
+                "#,
+            )?;
+            migrations::add_column_if_missing(connection, "chat_messages", "thread_id", "TEXT")?;
+            connection.execute_batch(
+                r#"
+                CREATE INDEX IF NOT EXISTS idx_chat_messages_thread
+                    ON chat_messages(thread_id);
+                "#,
+            )
+        },
+        down: |connection| {
+            connection.execute_batch(
+                r#"
+                DROP INDEX IF EXISTS idx_chat_messages_thread;
+                DROP INDEX IF EXISTS idx_chat_threads_session;
+                DROP TABLE IF EXISTS chat_threads;
+                "#,
+            )?;
+            migrations::drop_column_if_present(connection, "chat_messages", "thread_id")
+        },
+    },
+    Migration {
+        version: 5,
+        description: "add chat session tags",
+        up: |connection| {
+            connection.execute_batch(
+                r#"
+                CREATE TABLE IF NOT EXISTS chat_session_tags (
+                    session_id TEXT NOT NULL,
+                    tag TEXT NOT NULL,
+                    created_at TEXT NOT NULL,
+                    PRIMARY KEY (session_id, tag),
+                    FOREIGN KEY(session_id) REFERENCES chat_sessions(id) ON DELETE CASCADE
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_chat_session_tags_tag
+                    ON chat_session_tags(tag);
+                "#,
+            )
+        },
+        down: |connection| {
+            connection.execute_batch(
+                r#"
+                DROP INDEX IF EXISTS idx_chat_session_tags_tag;
+                DROP TABLE IF EXISTS chat_session_tags;
+                "#,
+            )
+        },
+    },
+    Migration {
+        version: 6,
+        description: "add pinned chat messages",
+        up: |connection| {
+            connection.execute_batch(
+                r#"
+                CREATE TABLE IF NOT EXISTS chat_pinned_messages (
+                    message_id TEXT PRIMARY KEY,
+                    session_id TEXT NOT NULL,
+                    pinned_at TEXT NOT NULL,
+                    FOREIGN KEY(session_id) REFERENCES chat_sessions(id) ON DELETE CASCADE,
+                    FOREIGN KEY(message_id) REFERENCES chat_messages(id) ON DELETE CASCADE
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_chat_pinned_messages_session
+                    ON chat_pinned_messages(session_id, pinned_at);
+                "#,
+            )
+        },
+        down: |connection| {
+            connection.execute_batch(
+                r#"
+                DROP INDEX IF EXISTS idx_chat_pinned_messages_session;
+                DROP TABLE IF EXISTS chat_pinned_messages;
+                "#,
+            )
+        },
+    },
+    Migration {
+        // Message counts are kept in step by triggers so forks, pruning and
+        // deletes never need to touch them; usage totals are only ever added to.
+        version: 7,
+        description: "add per-session usage statistics",
+        up: |connection| {
+            for column in [
+                "message_count",
+                "tool_call_count",
+                "prompt_tokens",
+                "completion_tokens",
+            ] {
+                migrations::add_column_if_missing(
+                    connection,
+                    "chat_sessions",
+                    column,
+                    "INTEGER NOT NULL DEFAULT 0",
+                )?;
+            }
+            connection.execute_batch(
+                r#"
+                UPDATE chat_sessions SET message_count = (
+                    SELECT COUNT(*) FROM chat_messages
+                    WHERE chat_messages.session_id = chat_sessions.id
+                );
+
+                CREATE TRIGGER IF NOT EXISTS trg_chat_messages_count_insert
+                AFTER INSERT ON chat_messages
+                BEGIN
+                    UPDATE chat_sessions SET message_count = message_count + 1
+                    WHERE id = NEW.session_id;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS trg_chat_messages_count_delete
+                AFTER DELETE ON chat_messages
+                BEGIN
+                    UPDATE chat_sessions SET message_count = MAX(message_count - 1, 0)
+                    WHERE id = OLD.session_id;
+                END;
+                "#,
+            )
+        },
+        down: |connection| {
+            connection.execute_batch(
+                r#"
+                DROP TRIGGER IF EXISTS trg_chat_messages_count_insert;
+                DROP TRIGGER IF EXISTS trg_chat_messages_count_delete;
+                "#,
+            )?;
+            for column in [
+                "message_count",
+                "tool_call_count",
+                "prompt_tokens",
+                "completion_tokens",
+            ] {
+                migrations::drop_column_if_present(connection, "chat_sessions", column)?;
             }
+            Ok(())
+        },
+    },
+    Migration {
+        version: 8,
+        description: "add chat session read markers",
+        up: |connection| {
+            migrations::add_column_if_missing(connection, "chat_sessions", "last_read_at", "TEXT")?;
+            // Existing history counts as read so upgrading does not flag every session.
+            connection.execute_batch(
+                r#"
+                UPDATE chat_sessions
+                SET last_read_at = COALESCE(last_message_at, updated_at)
+                WHERE last_read_at IS NULL;
+                "#,
+            )
+        },
+        down: |connection| {
+            connection.execute_batch("ALTER TABLE chat_sessions DROP COLUMN last_read_at;")
+        },
+    },
+    Migration {
+        version: 9,
+        description: "add per-session LLM overrides",
+        up: |connection| {
+            migrations::add_column_if_missing(connection, "chat_sessions", "llm_provider", "TEXT")?;
+            migrations::add_column_if_missing(connection, "chat_sessions", "llm_model", "TEXT")
+        },
+        down: |connection| {
+            migrations::drop_column_if_present(connection, "chat_sessions", "llm_provider")?;
+            migrations::drop_column_if_present(connection, "chat_sessions", "llm_model")
+        },
+    },
+    Migration {
+        // Totals recorded before this have no model, so they carry over as
+        // one unattributed row per session.
+        version: 10,
+        description: "add per-model session usage",
+        up: |connection| {
+            connection.execute_batch(
+                r#"
+                CREATE TABLE IF NOT EXISTS chat_session_model_usage (
+                    session_id TEXT NOT NULL,
+                    provider TEXT NOT NULL DEFAULT '',
+                    model TEXT NOT NULL DEFAULT '',
+                    responses INTEGER NOT NULL DEFAULT 0,
+                    prompt_tokens INTEGER NOT NULL DEFAULT 0,
+                    completion_tokens INTEGER NOT NULL DEFAULT 0,
+                    PRIMARY KEY (session_id, provider, model),
+                    FOREIGN KEY(session_id) REFERENCES chat_sessions(id) ON DELETE CASCADE
+                );
+
+                INSERT OR IGNORE INTO chat_session_model_usage
+                    (session_id, prompt_tokens, completion_tokens)
+                SELECT id, prompt_tokens, completion_tokens FROM chat_sessions
+                WHERE prompt_tokens > 0 OR completion_tokens > 0;
+                "#,
+            )
+        },
+        down: |connection| {
+            connection.execute_batch("DROP TABLE IF EXISTS chat_session_model_usage;")
+        },
+    },
+    Migration {
+        version: 11,
+        description: "add agent tool call transcripts",
+        up: |connection| {
+            connection.execute_batch(
+                r#"
+                CREATE TABLE IF NOT EXISTS chat_tool_calls (
+                    id TEXT PRIMARY KEY,
+                    session_id TEXT NOT NULL,
+                    message_id TEXT NOT NULL,
+                    position INTEGER NOT NULL,
+                    tool_name TEXT NOT NULL,
+                    arguments TEXT NOT NULL,
+                    result TEXT NOT NULL,
+                    duration_ms INTEGER,
+                    success INTEGER NOT NULL,
+                    error TEXT,
+                    recorded_at TEXT NOT NULL,
+                    FOREIGN KEY(session_id) REFERENCES chat_sessions(id) ON DELETE CASCADE,
+                    FOREIGN KEY(message_id) REFERENCES chat_messages(id) ON DELETE CASCADE
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_chat_tool_calls_message
+                    ON chat_tool_calls(session_id, message_id, position);
+                "#,
+            )
+        },
+        down: |connection| {
+            connection.execute_batch(
+                r#"
+                DROP INDEX IF EXISTS idx_chat_tool_calls_message;
+                DROP TABLE IF EXISTS chat_tool_calls;
+                "#,
+            )
+        },
+    },
+    Migration {
+        version: 12,
+        description: "add rolling session summaries",
+        up: |connection| {
+            migrations::add_column_if_missing(connection, "chat_sessions", "summary", "TEXT")?;
+            migrations::add_column_if_missing(
+                connection,
+                "chat_sessions",
+                "summary_covered_through",
+                "TEXT",
+            )?;
+            migrations::add_column_if_missing(
+                connection,
+                "chat_sessions",
+                "summary_updated_at",
+                "TEXT",
+            )
+        },
+        down: |connection| {
+            migrations::drop_column_if_present(connection, "chat_sessions", "summary")?;
+            migrations::drop_column_if_present(
+                connection,
+                "chat_sessions",
+                "summary_covered_through",
+            )?;
+            migrations::drop_column_if_present(connection, "chat_sessions", "summary_updated_at")
+        },
+    },
+];
+
+impl SessionService {
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        let connection = Connection::open(path).map_err(|err| err.to_string())?;
+        Self::configure_connection(&connection)?;
+        Ok(Self {
+            db: Mutex::new(connection),
+        })
+    }
+
+    #[cfg(test)]
+    pub fn new_in_memory() -> Self {
+        let connection = Connection::open_in_memory().expect("in-memory sqlite");
+        Self::configure_connection(&connection).expect("init schema");
+        Self {
+            db: Mutex::new(connection),
         }
-        let alter = format!("ALTER TABLE {table} ADD COLUMN {column} {definition}");
-        let _ = connection.execute(&alter, []);
+    }
+
+    fn configure_connection(connection: &Connection) -> Result<(), String> {
+        connection
+            .pragma_update(None, "foreign_keys", &"ON")
+            .map_err(|err| err.to_string())?;
+        migrations::apply(connection, SESSION_MIGRATION_SCOPE, SESSION_MIGRATIONS)?;
+        Ok(())
     }
 
     pub async fn create_session(&self, input: CreateSessionInput) -> ChatSession {
@@ -184,13 +824,18 @@ impl SessionService {
             last_message_at: None,
             is_active,
             messages: Vec::new(),
+            tags: Vec::new(),
+            pinned_message_ids: Vec::new(),
+            last_read_at: Some(now.clone()),
+            unread_count: 0,
+            llm: None,
         };
 
         let connection = self.db.lock().await;
         connection
             .execute(
-                "INSERT INTO chat_sessions (id, name, mode, topic_id, created_at, updated_at, last_message_at, is_active)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                "INSERT INTO chat_sessions (id, name, mode, topic_id, created_at, updated_at, last_message_at, is_active, last_read_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?6)",
                 params![
                     session.id,
                     name,
@@ -215,11 +860,13 @@ impl SessionService {
         Some(session)
     }
 
-    pub async fn load_all_sessions(&self) -> Vec<ChatSession> {
+    /// Loads sessions carrying every tag in `tags`; an empty filter returns all sessions.
+    pub async fn load_all_sessions(&self, tags: &[String]) -> Vec<ChatSession> {
+        let required: Vec<String> = tags.iter().filter_map(|tag| normalize_tag(tag)).collect();
         let connection = self.db.lock().await;
         let mut stmt = connection
             .prepare(
-                "SELECT id, name, mode, topic_id, created_at, updated_at, last_message_at, is_active
+                "SELECT id, name, mode, topic_id, created_at, updated_at, last_message_at, is_active, last_read_at, llm_provider, llm_model
                  FROM chat_sessions ORDER BY datetime(COALESCE(last_message_at, updated_at)) DESC",
             )
             .expect("prepare load sessions");
@@ -228,7 +875,9 @@ impl SessionService {
         let mut sessions = Vec::new();
         while let Some(row) = rows.next().expect("next row") {
             let base = Self::map_session_row(&connection, row).expect("map session");
-            sessions.push(base);
+            if required.iter().all(|tag| base.tags.contains(tag)) {
+                sessions.push(base);
+            }
         }
         sessions
     }
@@ -248,6 +897,11 @@ impl SessionService {
             last_message_at,
             is_active,
             messages,
+            tags: _,
+            pinned_message_ids: _,
+            last_read_at: _,
+            unread_count: _,
+            llm: _,
         } = session;
 
         let is_active_flag = if is_active { 1 } else { 0 };
@@ -256,8 +910,8 @@ impl SessionService {
         let connection = self.db.lock().await;
         connection
             .execute(
-                "INSERT INTO chat_sessions (id, name, mode, topic_id, created_at, updated_at, last_message_at, is_active)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                "INSERT INTO chat_sessions (id, name, mode, topic_id, created_at, updated_at, last_message_at, is_active, last_read_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?6)
                  ON CONFLICT(id) DO UPDATE SET
                     name=excluded.name,
                     mode=excluded.mode,
@@ -292,15 +946,16 @@ impl SessionService {
                     .unwrap_or_else(|| "text".to_string());
                 connection
                     .execute(
-                        "INSERT INTO chat_messages (id, session_id, role, content, timestamp, message_type, metadata)
-                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                        "INSERT INTO chat_messages (id, session_id, role, content, timestamp, message_type, metadata, thread_id)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
                          ON CONFLICT(id) DO UPDATE SET
                             session_id=excluded.session_id,
                             role=excluded.role,
                             content=excluded.content,
                             timestamp=excluded.timestamp,
                             message_type=excluded.message_type,
-                            metadata=excluded.metadata",
+                            metadata=excluded.metadata,
+                            thread_id=excluded.thread_id",
                         params![
                             &message.id,
                             &id,
@@ -308,7 +963,8 @@ impl SessionService {
                             &message.content,
                             &message.timestamp,
                             &message_type,
-                            metadata_ref
+                            metadata_ref,
+                            message.thread_id.as_deref()
                         ],
                     )
                     .map_err(|err| err.to_string())?;
@@ -318,40 +974,919 @@ impl SessionService {
         Ok(())
     }
 
-    pub async fn delete_session(&self, session_id: &str) -> bool {
+    pub async fn add_session_tag(
+        &self,
+        session_id: &str,
+        tag: &str,
+    ) -> Result<Vec<String>, String> {
+        let tag = normalize_tag(tag).ok_or_else(|| "Tag cannot be empty".to_string())?;
         let connection = self.db.lock().await;
-        match connection.execute(
-            "DELETE FROM chat_sessions WHERE id = ?1",
-            params![session_id],
-        ) {
-            Ok(rows) => rows > 0,
-            Err(_) => false,
+        if Self::fetch_session(&connection, session_id)?.is_none() {
+            return Err("Session not found".to_string());
         }
+        connection
+            .execute(
+                "INSERT OR IGNORE INTO chat_session_tags (session_id, tag, created_at) VALUES (?1, ?2, ?3)",
+                params![session_id, tag, current_timestamp()],
+            )
+            .map_err(|err| err.to_string())?;
+        Self::fetch_tags(&connection, session_id).map_err(|err| err.to_string())
     }
 
-    pub async fn save_message(&self, session_id: &str, message: ChatMessage) -> Result<(), String> {
+    pub async fn remove_session_tag(
+        &self,
+        session_id: &str,
+        tag: &str,
+    ) -> Result<Vec<String>, String> {
+        let tag = normalize_tag(tag).ok_or_else(|| "Tag cannot be empty".to_string())?;
         let connection = self.db.lock().await;
-        let message_type = message
-            .message_type
-            .clone()
-            .unwrap_or_else(|| "text".to_string());
-        let metadata_text = message
-            .metadata
-            .as_ref()
-            .and_then(|value| serde_json::to_string(value).ok());
-        let metadata_ref = metadata_text.as_deref();
-        let message_timestamp = message.timestamp.clone();
         connection
             .execute(
-                "INSERT INTO chat_messages (id, session_id, role, content, timestamp, message_type, metadata)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
-                 ON CONFLICT(id) DO UPDATE SET
-                    session_id=excluded.session_id,
-                    role=excluded.role,
-                    content=excluded.content,
+                "DELETE FROM chat_session_tags WHERE session_id = ?1 AND tag = ?2",
+                params![session_id, tag],
+            )
+            .map_err(|err| err.to_string())?;
+        Self::fetch_tags(&connection, session_id).map_err(|err| err.to_string())
+    }
+
+    /// Pins or unpins a message and returns the session's pinned message ids.
+    pub async fn set_message_pinned(
+        &self,
+        session_id: &str,
+        message_id: &str,
+        pinned: bool,
+    ) -> Result<Vec<String>, String> {
+        let connection = self.db.lock().await;
+        if pinned {
+            let exists = connection
+                .query_row(
+                    "SELECT 1 FROM chat_messages WHERE id = ?1 AND session_id = ?2",
+                    params![message_id, session_id],
+                    |_| Ok(()),
+                )
+                .optional()
+                .map_err(|err| err.to_string())?
+                .is_some();
+            if !exists {
+                return Err("Message not found in session".to_string());
+            }
+            connection
+                .execute(
+                    "INSERT OR IGNORE INTO chat_pinned_messages (message_id, session_id, pinned_at)
+                     VALUES (?1, ?2, ?3)",
+                    params![message_id, session_id, current_timestamp()],
+                )
+                .map_err(|err| err.to_string())?;
+        } else {
+            connection
+                .execute(
+                    "DELETE FROM chat_pinned_messages WHERE message_id = ?1 AND session_id = ?2",
+                    params![message_id, session_id],
+                )
+                .map_err(|err| err.to_string())?;
+        }
+        Self::fetch_pinned_ids(&connection, session_id).map_err(|err| err.to_string())
+    }
+
+    pub async fn list_pinned_messages(&self, session_id: &str) -> Result<Vec<ChatMessage>, String> {
+        let connection = self.db.lock().await;
+        let mut stmt = connection
+            .prepare(
+                "SELECT m.id, m.role, m.content, m.timestamp, m.message_type, m.metadata, m.thread_id
+                 FROM chat_pinned_messages p
+                 JOIN chat_messages m ON m.id = p.message_id
+                 WHERE p.session_id = ?1
+                 ORDER BY datetime(p.pinned_at) ASC",
+            )
+            .map_err(|err| err.to_string())?;
+
+        let iter = stmt
+            .query_map(params![session_id], Self::map_message_row)
+            .map_err(|err| err.to_string())?;
+
+        let mut messages = Vec::new();
+        for message in iter {
+            messages.push(message.map_err(|err| err.to_string())?);
+        }
+        Ok(messages)
+    }
+
+    /// Copies a session's messages, up to and including `up_to_message_id`
+    /// when given, into a new session. Threads used by the copied messages and
+    /// the session's tags come along; pins do not.
+    pub async fn fork_session(
+        &self,
+        source_session_id: &str,
+        up_to_message_id: Option<&str>,
+        name: Option<String>,
+    ) -> Result<ChatSession, String> {
+        let connection = self.db.lock().await;
+        let source = Self::fetch_session(&connection, source_session_id)?
+            .ok_or_else(|| "Session not found".to_string())?;
+
+        let messages = match up_to_message_id {
+            Some(message_id) => {
+                let cutoff = source
+                    .messages
+                    .iter()
+                    .position(|message| message.id == message_id)
+                    .ok_or_else(|| "Message not found in session".to_string())?;
+                source.messages[..=cutoff].to_vec()
+            }
+            None => source.messages.clone(),
+        };
+
+        let now = current_timestamp();
+        let fork_id = uuid::Uuid::new_v4().to_string();
+        let fork_name = name
+            .filter(|value| !value.trim().is_empty())
+            .unwrap_or_else(|| format!("{} (fork)", source.name));
+        let last_message_at = messages.last().map(|message| message.timestamp.clone());
+
+        let transaction = connection
+            .unchecked_transaction()
+            .map_err(|err| err.to_string())?;
+        transaction
+            .execute(
+                "INSERT INTO chat_sessions (id, name, mode, topic_id, created_at, updated_at, last_message_at, is_active, last_read_at, llm_provider, llm_model)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?5, ?6, 1, ?5, ?7, ?8)",
+                params![
+                    &fork_id,
+                    &fork_name,
+                    &source.mode,
+                    source.topic_id.as_deref(),
+                    &now,
+                    last_message_at.as_deref(),
+                    source.llm.as_ref().map(|llm| llm.provider.id()),
+                    source.llm.as_ref().map(|llm| llm.model.as_str())
+                ],
+            )
+            .map_err(|err| err.to_string())?;
+
+        let mut message_ids = HashMap::new();
+        for message in &messages {
+            message_ids.insert(message.id.clone(), uuid::Uuid::new_v4().to_string());
+        }
+
+        let mut thread_ids = HashMap::new();
+        for thread_id in messages
+            .iter()
+            .filter_map(|message| message.thread_id.as_ref())
+        {
+            if thread_ids.contains_key(thread_id) {
+                continue;
+            }
+            let (parent_message_id, title) = transaction
+                .query_row(
+                    "SELECT parent_message_id, title FROM chat_threads WHERE id = ?1",
+                    params![thread_id],
+                    |row| {
+                        Ok((
+                            row.get::<_, Option<String>>(0)?,
+                            row.get::<_, Option<String>>(1)?,
+                        ))
+                    },
+                )
+                .map_err(|err| err.to_string())?;
+            let new_thread_id = uuid::Uuid::new_v4().to_string();
+            transaction
+                .execute(
+                    "INSERT INTO chat_threads (id, session_id, parent_message_id, title, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        &new_thread_id,
+                        &fork_id,
+                        parent_message_id.and_then(|id| message_ids.get(&id)),
+                        title,
+                        &now
+                    ],
+                )
+                .map_err(|err| err.to_string())?;
+            thread_ids.insert(thread_id.clone(), new_thread_id);
+        }
+
+        let mut forked_messages = Vec::with_capacity(messages.len());
+        for message in messages {
+            let forked = ChatMessage {
+                id: message_ids[&message.id].clone(),
+                thread_id: message
+                    .thread_id
+                    .as_ref()
+                    .and_then(|thread_id| thread_ids.get(thread_id).cloned()),
+                ..message
+            };
+            let metadata = forked
+                .metadata
+                .as_ref()
+                .and_then(|value| serde_json::to_string(value).ok());
+            transaction
+                .execute(
+                    "INSERT INTO chat_messages (id, session_id, role, content, timestamp, message_type, metadata, thread_id)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![
+                        &forked.id,
+                        &fork_id,
+                        &forked.role,
+                        &forked.content,
+                        &forked.timestamp,
+                        forked.message_type.as_deref().unwrap_or("text"),
+                        metadata.as_deref(),
+                        forked.thread_id.as_deref()
+                    ],
+                )
+                .map_err(|err| err.to_string())?;
+            forked_messages.push(forked);
+        }
+
+        for tag in &source.tags {
+            transaction
+                .execute(
+                    "INSERT INTO chat_session_tags (session_id, tag, created_at) VALUES (?1, ?2, ?3)",
+                    params![&fork_id, tag, &now],
+                )
+                .map_err(|err| err.to_string())?;
+        }
+
+        transaction.commit().map_err(|err| err.to_string())?;
+
+        Ok(ChatSession {
+            id: fork_id,
+            name: fork_name,
+            mode: source.mode,
+            topic_id: source.topic_id,
+            created_at: now.clone(),
+            updated_at: now.clone(),
+            last_message_at,
+            is_active: true,
+            messages: forked_messages,
+            tags: source.tags,
+            pinned_message_ids: Vec::new(),
+            last_read_at: Some(now),
+            unread_count: 0,
+            llm: source.llm,
+        })
+    }
+
+    /// Deletes messages older than `max_age_days`, then trims each session to
+    /// its newest `max_messages_per_session` messages. Pinned messages are
+    /// never removed and do not count toward the per-session limit.
+    pub async fn prune_messages(
+        &self,
+        max_age_days: Option<u32>,
+        max_messages_per_session: Option<u32>,
+    ) -> Result<PruneSummary, String> {
+        let connection = self.db.lock().await;
+        let transaction = connection
+            .unchecked_transaction()
+            .map_err(|err| err.to_string())?;
+        let mut summary = PruneSummary::default();
+
+        if let Some(days) = max_age_days {
+            let cutoff = (Utc::now() - chrono::Duration::days(i64::from(days))).to_rfc3339();
+            summary.expired_messages = transaction
+                .execute(
+                    "DELETE FROM chat_messages
+                     WHERE datetime(timestamp) < datetime(?1)
+                       AND id NOT IN (SELECT message_id FROM chat_pinned_messages)",
+                    params![cutoff],
+                )
+                .map_err(|err| err.to_string())?;
+        }
+
+        if let Some(limit) = max_messages_per_session {
+            summary.overflow_messages = transaction
+                .execute(
+                    "DELETE FROM chat_messages WHERE id IN (
+                        SELECT id FROM (
+                            SELECT id, ROW_NUMBER() OVER (
+                                PARTITION BY session_id ORDER BY datetime(timestamp) DESC
+                            ) AS position
+                            FROM chat_messages
+                            WHERE id NOT IN (SELECT message_id FROM chat_pinned_messages)
+                        ) WHERE position > ?1
+                    )",
+                    params![limit],
+                )
+                .map_err(|err| err.to_string())?;
+        }
+
+        transaction.commit().map_err(|err| err.to_string())?;
+        summary.total_removed = summary.expired_messages + summary.overflow_messages;
+        Ok(summary)
+    }
+
+    pub async fn record_usage(&self, session_id: &str, usage: &SessionUsage) -> Result<(), String> {
+        if usage.is_empty() {
+            return Ok(());
+        }
+        let connection = self.db.lock().await;
+        let transaction = connection
+            .unchecked_transaction()
+            .map_err(|err| err.to_string())?;
+        let updated = transaction
+            .execute(
+                "UPDATE chat_sessions SET
+                    tool_call_count = tool_call_count + ?1,
+                    prompt_tokens = prompt_tokens + ?2,
+                    completion_tokens = completion_tokens + ?3
+                 WHERE id = ?4",
+                params![
+                    usage.tool_calls as i64,
+                    usage.prompt_tokens as i64,
+                    usage.completion_tokens as i64,
+                    session_id
+                ],
+            )
+            .map_err(|err| err.to_string())?;
+        if updated == 0 {
+            return Err("Session not found".to_string());
+        }
+        if usage.has_tokens() {
+            transaction
+                .execute(
+                    "INSERT INTO chat_session_model_usage
+                        (session_id, provider, model, responses, prompt_tokens, completion_tokens)
+                     VALUES (?1, ?2, ?3, 1, ?4, ?5)
+                     ON CONFLICT(session_id, provider, model) DO UPDATE SET
+                        responses = responses + 1,
+                        prompt_tokens = prompt_tokens + excluded.prompt_tokens,
+                        completion_tokens = completion_tokens + excluded.completion_tokens",
+                    params![
+                        session_id,
+                        usage.provider.as_deref().unwrap_or_default(),
+                        usage.model.as_deref().unwrap_or_default(),
+                        usage.prompt_tokens as i64,
+                        usage.completion_tokens as i64
+                    ],
+                )
+                .map_err(|err| err.to_string())?;
+        }
+        transaction.commit().map_err(|err| err.to_string())
+    }
+
+    /// Stores the tool calls reported with `message_id`, replacing any kept
+    /// from an earlier save of the same reply.
+    pub async fn record_tool_calls(
+        &self,
+        session_id: &str,
+        message_id: &str,
+        calls: &[AgentToolCall],
+    ) -> Result<(), String> {
+        let connection = self.db.lock().await;
+        let transaction = connection
+            .unchecked_transaction()
+            .map_err(|err| err.to_string())?;
+        transaction
+            .execute(
+                "DELETE FROM chat_tool_calls WHERE message_id = ?1",
+                params![message_id],
+            )
+            .map_err(|err| err.to_string())?;
+        let recorded_at = current_timestamp();
+        for (position, call) in calls.iter().enumerate() {
+            transaction
+                .execute(
+                    "INSERT INTO chat_tool_calls
+                        (id, session_id, message_id, position, tool_name, arguments, result,
+                         duration_ms, success, error, recorded_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                    params![
+                        uuid::Uuid::new_v4().to_string(),
+                        session_id,
+                        message_id,
+                        position as i64,
+                        &call.tool_name,
+                        call.arguments.to_string(),
+                        call.result.to_string(),
+                        call.duration_ms.map(|millis| millis as i64),
+                        call.success,
+                        call.error.as_deref(),
+                        &recorded_at
+                    ],
+                )
+                .map_err(|err| err.to_string())?;
+        }
+        transaction.commit().map_err(|err| err.to_string())
+    }
+
+    /// The session's tool calls in the order they were made, optionally only
+    /// those reported with one reply.
+    pub async fn load_tool_calls(
+        &self,
+        session_id: &str,
+        message_id: Option<&str>,
+    ) -> Result<Vec<ToolCallRecord>, String> {
+        let connection = self.db.lock().await;
+        let mut statement = connection
+            .prepare(
+                "SELECT tool_calls.id, tool_calls.message_id, tool_calls.position,
+                        tool_calls.tool_name, tool_calls.arguments, tool_calls.result,
+                        tool_calls.duration_ms, tool_calls.success, tool_calls.error,
+                        tool_calls.recorded_at
+                 FROM chat_tool_calls AS tool_calls
+                 JOIN chat_messages AS messages ON messages.id = tool_calls.message_id
+                 WHERE tool_calls.session_id = ?1
+                   AND (?2 IS NULL OR tool_calls.message_id = ?2)
+                 ORDER BY datetime(messages.timestamp), tool_calls.position",
+            )
+            .map_err(|err| err.to_string())?;
+        let json = |text: String| serde_json::from_str(&text).unwrap_or(Value::String(text));
+        statement
+            .query_map(params![session_id, message_id], |row| {
+                Ok(ToolCallRecord {
+                    id: row.get(0)?,
+                    session_id: session_id.to_string(),
+                    message_id: row.get(1)?,
+                    position: row.get::<_, i64>(2)? as u32,
+                    recorded_at: row.get(9)?,
+                    call: AgentToolCall {
+                        tool_name: row.get(3)?,
+                        arguments: json(row.get(4)?),
+                        result: json(row.get(5)?),
+                        duration_ms: row.get::<_, Option<i64>>(6)?.map(|millis| millis as u64),
+                        success: row.get(7)?,
+                        error: row.get(8)?,
+                    },
+                })
+            })
+            .map_err(|err| err.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| err.to_string())
+    }
+
+    /// Token totals per provider and model, for one session or across all.
+    pub async fn model_usage(&self, session_id: Option<&str>) -> Result<Vec<ModelUsage>, String> {
+        let connection = self.db.lock().await;
+        let mut statement = connection
+            .prepare(
+                "SELECT provider, model, SUM(responses), SUM(prompt_tokens), SUM(completion_tokens)
+                 FROM chat_session_model_usage
+                 WHERE ?1 IS NULL OR session_id = ?1
+                 GROUP BY provider, model
+                 ORDER BY SUM(prompt_tokens) + SUM(completion_tokens) DESC, provider, model",
+            )
+            .map_err(|err| err.to_string())?;
+        statement
+            .query_map(params![session_id], |row| {
+                Ok(ModelUsage {
+                    provider: row.get(0)?,
+                    model: row.get(1)?,
+                    responses: row.get::<_, i64>(2)? as u64,
+                    prompt_tokens: row.get::<_, i64>(3)? as u64,
+                    completion_tokens: row.get::<_, i64>(4)? as u64,
+                })
+            })
+            .map_err(|err| err.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| err.to_string())
+    }
+
+    pub async fn session_stats(&self, session_id: &str) -> Result<SessionStats, String> {
+        let connection = self.db.lock().await;
+        connection
+            .query_row(
+                "SELECT message_count, tool_call_count, prompt_tokens, completion_tokens, last_message_at
+                 FROM chat_sessions WHERE id = ?1",
+                params![session_id],
+                |row| {
+                    let prompt_tokens = row.get::<_, i64>(2)? as u64;
+                    let completion_tokens = row.get::<_, i64>(3)? as u64;
+                    Ok(SessionStats {
+                        session_id: session_id.to_string(),
+                        message_count: row.get::<_, i64>(0)? as u64,
+                        tool_call_count: row.get::<_, i64>(1)? as u64,
+                        prompt_tokens,
+                        completion_tokens,
+                        total_tokens: prompt_tokens + completion_tokens,
+                        last_message_at: row.get(4)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(|err| err.to_string())?
+            .ok_or_else(|| "Session not found".to_string())
+    }
+
+    /// Moves the session's read marker to `read_at` (RFC 3339) and returns
+    /// what is still unread after it. Without `read_at` everything received so
+    /// far is marked read, including topic messages stamped ahead of the local
+    /// clock.
+    pub async fn mark_read(
+        &self,
+        session_id: &str,
+        read_at: Option<&str>,
+    ) -> Result<SessionReadState, String> {
+        let connection = self.db.lock().await;
+        let last_message_at: Option<String> = connection
+            .query_row(
+                "SELECT last_message_at FROM chat_sessions WHERE id = ?1",
+                params![session_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|err| err.to_string())?
+            .ok_or_else(|| "Session not found".to_string())?;
+
+        let last_read_at = match read_at {
+            Some(value) => DateTime::parse_from_rfc3339(value)
+                .map_err(|_| format!("Invalid read timestamp: {value}"))?
+                .with_timezone(&Utc),
+            None => last_message_at
+                .as_deref()
+                .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+                .map(|value| value.with_timezone(&Utc))
+                .filter(|latest| *latest > Utc::now())
+                .unwrap_or_else(Utc::now),
+        }
+        .to_rfc3339();
+
+        connection
+            .execute(
+                "UPDATE chat_sessions SET last_read_at = ?1 WHERE id = ?2",
+                params![&last_read_at, session_id],
+            )
+            .map_err(|err| err.to_string())?;
+        let unread_count = Self::count_unread(&connection, session_id, Some(&last_read_at))
+            .map_err(|err| err.to_string())?;
+        Ok(SessionReadState {
+            session_id: session_id.to_string(),
+            last_read_at,
+            unread_count,
+        })
+    }
+
+    /// Sets the provider and model the session talks to. `None` goes back to
+    /// the configured ones.
+    pub async fn set_session_llm(
+        &self,
+        session_id: &str,
+        llm: Option<SessionLlm>,
+    ) -> Result<Option<SessionLlm>, String> {
+        let llm = llm
+            .map(|llm| SessionLlm {
+                model: llm.model.trim().to_string(),
+                ..llm
+            })
+            .filter(|llm| !llm.model.is_empty());
+        let connection = self.db.lock().await;
+        let updated = connection
+            .execute(
+                "UPDATE chat_sessions SET llm_provider = ?1, llm_model = ?2 WHERE id = ?3",
+                params![
+                    llm.as_ref().map(|llm| llm.provider.id()),
+                    llm.as_ref().map(|llm| llm.model.as_str()),
+                    session_id
+                ],
+            )
+            .map_err(|err| err.to_string())?;
+        if updated == 0 {
+            return Err("Session not found".to_string());
+        }
+        Ok(llm)
+    }
+
+    pub async fn session_llm(&self, session_id: &str) -> Result<Option<SessionLlm>, String> {
+        let connection = self.db.lock().await;
+        let (provider, model) = connection
+            .query_row(
+                "SELECT llm_provider, llm_model FROM chat_sessions WHERE id = ?1",
+                params![session_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|err| err.to_string())?
+            .ok_or_else(|| "Session not found".to_string())?;
+        Ok(session_llm(provider, model))
+    }
+
+    /// Replaces the session's rolling summary; `None` clears it.
+    pub async fn set_session_summary(
+        &self,
+        session_id: &str,
+        summary: Option<&SessionSummary>,
+    ) -> Result<(), String> {
+        let connection = self.db.lock().await;
+        let updated = connection
+            .execute(
+                "UPDATE chat_sessions
+                 SET summary = ?1, summary_covered_through = ?2, summary_updated_at = ?3
+                 WHERE id = ?4",
+                params![
+                    summary.map(|summary| summary.text.as_str()),
+                    summary.map(|summary| summary.covered_through.as_str()),
+                    summary.map(|summary| summary.updated_at.as_str()),
+                    session_id
+                ],
+            )
+            .map_err(|err| err.to_string())?;
+        if updated == 0 {
+            return Err("Session not found".to_string());
+        }
+        Ok(())
+    }
+
+    pub async fn session_summary(
+        &self,
+        session_id: &str,
+    ) -> Result<Option<SessionSummary>, String> {
+        let connection = self.db.lock().await;
+        let (text, covered_through, updated_at): (Option<String>, Option<String>, Option<String>) =
+            connection
+                .query_row(
+                    "SELECT summary, summary_covered_through, summary_updated_at
+                     FROM chat_sessions WHERE id = ?1",
+                    params![session_id],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                )
+                .optional()
+                .map_err(|err| err.to_string())?
+                .ok_or_else(|| "Session not found".to_string())?;
+        Ok(text.map(|text| SessionSummary {
+            text,
+            covered_through: covered_through.unwrap_or_default(),
+            updated_at: updated_at.unwrap_or_default(),
+        }))
+    }
+
+    /// Collects sessions updated after `updated_after` or holding messages
+    /// inserted after `after_rowid`.
+    pub async fn changes_since(
+        &self,
+        updated_after: Option<&str>,
+        after_rowid: i64,
+    ) -> Result<SessionChanges, String> {
+        let connection = self.db.lock().await;
+        let mut stmt = connection
+            .prepare(
+                "SELECT id FROM chat_sessions
+                 WHERE ?1 IS NULL OR julianday(updated_at) > julianday(?1)
+                 UNION
+                 SELECT DISTINCT session_id FROM chat_messages WHERE rowid > ?2",
+            )
+            .map_err(|err| err.to_string())?;
+        let ids = stmt
+            .query_map(params![updated_after, after_rowid], |row| {
+                row.get::<_, String>(0)
+            })
+            .map_err(|err| err.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| err.to_string())?;
+
+        let mut message_stmt = connection
+            .prepare(
+                "SELECT id, role, content, timestamp, message_type, metadata, thread_id
+                 FROM chat_messages WHERE session_id = ?1 AND rowid > ?2
+                 ORDER BY datetime(timestamp) ASC",
+            )
+            .map_err(|err| err.to_string())?;
+        let mut changes = SessionChanges {
+            sessions: Vec::new(),
+            updated_through: updated_after.map(str::to_string),
+            last_message_rowid: Self::max_message_rowid(&connection)?.max(after_rowid),
+        };
+        for id in ids {
+            let Some(mut session) = Self::fetch_session(&connection, &id)? else {
+                continue;
+            };
+            session.messages = message_stmt
+                .query_map(params![&id, after_rowid], Self::map_message_row)
+                .map_err(|err| err.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| err.to_string())?;
+            session.unread_count = 0;
+            if changes
+                .updated_through
+                .as_deref()
+                .is_none_or(|latest| is_later(&session.updated_at, latest))
+            {
+                changes.updated_through = Some(session.updated_at.clone());
+            }
+            changes.sessions.push(session);
+        }
+        Ok(changes)
+    }
+
+    /// Merges sessions received from another device. Unknown sessions are
+    /// created, metadata is taken from whichever side was updated last, and
+    /// messages are only ever added, never overwritten.
+    pub async fn reconcile_remote(
+        &self,
+        sessions: &[ChatSession],
+    ) -> Result<ReconcileSummary, String> {
+        let connection = self.db.lock().await;
+        let transaction = connection
+            .unchecked_transaction()
+            .map_err(|err| err.to_string())?;
+        let mut summary = ReconcileSummary::default();
+        let rowid_before = Self::max_message_rowid(&transaction)?;
+
+        for remote in sessions {
+            let local_updated_at: Option<String> = transaction
+                .query_row(
+                    "SELECT updated_at FROM chat_sessions WHERE id = ?1",
+                    params![&remote.id],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|err| err.to_string())?;
+
+            match local_updated_at {
+                None => {
+                    transaction
+                        .execute(
+                            "INSERT INTO chat_sessions (id, name, mode, topic_id, created_at, updated_at, last_message_at, is_active, last_read_at)
+                             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?7)",
+                            params![
+                                &remote.id,
+                                &remote.name,
+                                &remote.mode,
+                                remote.topic_id.as_deref(),
+                                &remote.created_at,
+                                &remote.updated_at,
+                                remote.last_message_at.as_deref(),
+                                if remote.is_active { 1 } else { 0 }
+                            ],
+                        )
+                        .map_err(|err| err.to_string())?;
+                    summary.sessions_created += 1;
+                }
+                Some(local) if is_later(&remote.updated_at, &local) => {
+                    transaction
+                        .execute(
+                            "UPDATE chat_sessions SET name = ?1, mode = ?2, topic_id = ?3, is_active = ?4, updated_at = ?5
+                             WHERE id = ?6",
+                            params![
+                                &remote.name,
+                                &remote.mode,
+                                remote.topic_id.as_deref(),
+                                if remote.is_active { 1 } else { 0 },
+                                &remote.updated_at,
+                                &remote.id
+                            ],
+                        )
+                        .map_err(|err| err.to_string())?;
+                    summary.sessions_updated += 1;
+                }
+                Some(_) => {}
+            }
+
+            for message in &remote.messages {
+                let metadata = message
+                    .metadata
+                    .as_ref()
+                    .and_then(|value| serde_json::to_string(value).ok());
+                summary.messages_added += transaction
+                    .execute(
+                        "INSERT INTO chat_messages (id, session_id, role, content, timestamp, message_type, metadata, thread_id)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                         ON CONFLICT(id) DO NOTHING",
+                        params![
+                            &message.id,
+                            &remote.id,
+                            &message.role,
+                            &message.content,
+                            &message.timestamp,
+                            message.message_type.as_deref().unwrap_or("text"),
+                            metadata.as_deref(),
+                            message.thread_id.as_deref()
+                        ],
+                    )
+                    .map_err(|err| err.to_string())?;
+            }
+
+            transaction
+                .execute(
+                    "UPDATE chat_sessions SET last_message_at = (
+                        SELECT timestamp FROM chat_messages WHERE session_id = ?1
+                        ORDER BY datetime(timestamp) DESC LIMIT 1
+                     ) WHERE id = ?1",
+                    params![&remote.id],
+                )
+                .map_err(|err| err.to_string())?;
+        }
+
+        summary.rowids = (rowid_before, Self::max_message_rowid(&transaction)?);
+        transaction.commit().map_err(|err| err.to_string())?;
+        Ok(summary)
+    }
+
+    fn max_message_rowid(connection: &Connection) -> Result<i64, String> {
+        connection
+            .query_row(
+                "SELECT COALESCE(MAX(rowid), 0) FROM chat_messages",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|err| err.to_string())
+    }
+
+    pub async fn delete_session(&self, session_id: &str) -> bool {
+        let connection = self.db.lock().await;
+        match connection.execute(
+            "DELETE FROM chat_sessions WHERE id = ?1",
+            params![session_id],
+        ) {
+            Ok(rows) => rows > 0,
+            Err(_) => false,
+        }
+    }
+
+    /// Deletes every session matching `filter` and returns their ids. At least
+    /// one criterion is required so an empty filter cannot wipe all history.
+    pub async fn delete_sessions_matching(
+        &self,
+        filter: &SessionDeleteFilter,
+    ) -> Result<Vec<String>, String> {
+        let older_than = filter
+            .older_than
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty());
+        let mode = filter
+            .mode
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty());
+        if older_than.is_none() && mode.is_none() && !filter.inactive_only {
+            return Err("At least one filter is required for bulk deletion".to_string());
+        }
+        if let Some(value) = older_than
+            && DateTime::parse_from_rfc3339(value).is_err()
+            && chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").is_err()
+        {
+            return Err(format!("Invalid date filter: {value}"));
+        }
+
+        let connection = self.db.lock().await;
+        let transaction = connection
+            .unchecked_transaction()
+            .map_err(|err| err.to_string())?;
+        let ids = {
+            let mut stmt = transaction
+                .prepare(
+                    "SELECT id FROM chat_sessions
+                     WHERE (?1 IS NULL OR datetime(COALESCE(last_message_at, updated_at)) < datetime(?1))
+                       AND (?2 IS NULL OR mode = ?2)
+                       AND (?3 = 0 OR is_active = 0)",
+                )
+                .map_err(|err| err.to_string())?;
+            let rows = stmt
+                .query_map(
+                    params![older_than, mode, i64::from(filter.inactive_only)],
+                    |row| row.get::<_, String>(0),
+                )
+                .map_err(|err| err.to_string())?;
+            rows.collect::<Result<Vec<_>, _>>()
+                .map_err(|err| err.to_string())?
+        };
+
+        for id in &ids {
+            transaction
+                .execute("DELETE FROM chat_sessions WHERE id = ?1", params![id])
+                .map_err(|err| err.to_string())?;
+        }
+        transaction.commit().map_err(|err| err.to_string())?;
+        Ok(ids)
+    }
+
+    pub async fn save_message(&self, session_id: &str, message: ChatMessage) -> Result<(), String> {
+        let connection = self.db.lock().await;
+        if let Some(thread_id) = message.thread_id.as_deref() {
+            let thread_exists = connection
+                .query_row(
+                    "SELECT 1 FROM chat_threads WHERE id = ?1 AND session_id = ?2",
+                    params![thread_id, session_id],
+                    |_| Ok(()),
+                )
+                .optional()
+                .map_err(|err| err.to_string())?
+                .is_some();
+            if !thread_exists {
+                return Err("Thread not found in session".to_string());
+            }
+        }
+        let message_type = message
+            .message_type
+            .clone()
+            .unwrap_or_else(|| "text".to_string());
+        let metadata_text = message
+            .metadata
+            .as_ref()
+            .and_then(|value| serde_json::to_string(value).ok());
+        let metadata_ref = metadata_text.as_deref();
+        let message_timestamp = message.timestamp.clone();
+        connection
+            .execute(
+                "INSERT INTO chat_messages (id, session_id, role, content, timestamp, message_type, metadata, thread_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                 ON CONFLICT(id) DO UPDATE SET
+                    session_id=excluded.session_id,
+                    role=excluded.role,
+                    content=excluded.content,
                     timestamp=excluded.timestamp,
                     message_type=excluded.message_type,
-                    metadata=excluded.metadata",
+                    metadata=excluded.metadata,
+                    thread_id=excluded.thread_id",
                 params![
                     &message.id,
                     session_id,
@@ -359,7 +1894,8 @@ impl SessionService {
                     &message.content,
                     &message_timestamp,
                     &message_type,
-                    metadata_ref
+                    metadata_ref,
+                    message.thread_id.as_deref()
                 ],
             )
             .map_err(|err| err.to_string())?;
@@ -377,12 +1913,110 @@ impl SessionService {
                 params![current_timestamp(), &message_timestamp, session_id],
             )
             .map_err(|err| err.to_string())?;
-        Ok(())
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub async fn load_messages(&self, session_id: &str) -> Option<Vec<ChatMessage>> {
+        let connection = self.db.lock().await;
+        Self::fetch_messages(&connection, session_id).ok()
+    }
+
+    pub async fn load_messages_in_scope(
+        &self,
+        session_id: &str,
+        scope: &MessageScope,
+    ) -> Result<Vec<ChatMessage>, String> {
+        let connection = self.db.lock().await;
+        Self::fetch_messages_in_scope(&connection, session_id, scope)
+    }
+
+    pub async fn create_thread(
+        &self,
+        session_id: &str,
+        parent_message_id: Option<String>,
+        title: Option<String>,
+    ) -> Result<ChatThread, String> {
+        let connection = self.db.lock().await;
+        if Self::fetch_session(&connection, session_id)?.is_none() {
+            return Err("Session not found".to_string());
+        }
+
+        if let Some(parent_id) = parent_message_id.as_deref() {
+            let parent_exists = connection
+                .query_row(
+                    "SELECT 1 FROM chat_messages WHERE id = ?1 AND session_id = ?2",
+                    params![parent_id, session_id],
+                    |_| Ok(()),
+                )
+                .optional()
+                .map_err(|err| err.to_string())?
+                .is_some();
+            if !parent_exists {
+                return Err("Parent message not found in session".to_string());
+            }
+        }
+
+        let thread = ChatThread {
+            id: uuid::Uuid::new_v4().to_string(),
+            session_id: session_id.to_string(),
+            parent_message_id,
+            title: title.filter(|value| !value.trim().is_empty()),
+            created_at: current_timestamp(),
+            message_count: 0,
+            last_message_at: None,
+        };
+
+        connection
+            .execute(
+                "INSERT INTO chat_threads (id, session_id, parent_message_id, title, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    &thread.id,
+                    &thread.session_id,
+                    thread.parent_message_id.as_deref(),
+                    thread.title.as_deref(),
+                    &thread.created_at
+                ],
+            )
+            .map_err(|err| err.to_string())?;
+
+        Ok(thread)
     }
 
-    pub async fn load_messages(&self, session_id: &str) -> Option<Vec<ChatMessage>> {
+    pub async fn list_threads(&self, session_id: &str) -> Result<Vec<ChatThread>, String> {
         let connection = self.db.lock().await;
-        Self::fetch_messages(&connection, session_id).ok()
+        let mut stmt = connection
+            .prepare(
+                "SELECT t.id, t.session_id, t.parent_message_id, t.title, t.created_at,
+                        COUNT(m.id) AS message_count, MAX(m.timestamp) AS last_message_at
+                 FROM chat_threads t
+                 LEFT JOIN chat_messages m ON m.thread_id = t.id AND m.session_id = t.session_id
+                 WHERE t.session_id = ?1
+                 GROUP BY t.id
+                 ORDER BY datetime(t.created_at) ASC",
+            )
+            .map_err(|err| err.to_string())?;
+
+        let iter = stmt
+            .query_map(params![session_id], |row| {
+                Ok(ChatThread {
+                    id: row.get("id")?,
+                    session_id: row.get("session_id")?,
+                    parent_message_id: row.get("parent_message_id")?,
+                    title: row.get("title")?,
+                    created_at: row.get("created_at")?,
+                    message_count: row.get("message_count")?,
+                    last_message_at: row.get("last_message_at")?,
+                })
+            })
+            .map_err(|err| err.to_string())?;
+
+        let mut threads = Vec::new();
+        for thread in iter {
+            threads.push(thread.map_err(|err| err.to_string())?);
+        }
+        Ok(threads)
     }
 
     pub async fn update_session_context(&self, context: SessionContext) {
@@ -488,7 +2122,7 @@ impl SessionService {
         let connection = self.db.lock().await;
         let row = connection
             .query_row(
-                "SELECT id, role, content, timestamp, message_type, metadata FROM chat_messages
+                "SELECT id, role, content, timestamp, message_type, metadata, thread_id FROM chat_messages
                  WHERE id = ?1 AND session_id = ?2",
                 params![message_id, session_id],
                 |row| Self::map_message_row(row),
@@ -526,13 +2160,64 @@ impl SessionService {
         Ok(None)
     }
 
+    /// Appends `entry` to the array stored under `key` in a message's metadata.
+    pub async fn append_message_metadata_entry(
+        &self,
+        session_id: &str,
+        message_id: &str,
+        key: &str,
+        entry: Value,
+    ) -> Result<Option<ChatMessage>, String> {
+        let connection = self.db.lock().await;
+        let row = connection
+            .query_row(
+                "SELECT id, role, content, timestamp, message_type, metadata, thread_id FROM chat_messages
+                 WHERE id = ?1 AND session_id = ?2",
+                params![message_id, session_id],
+                Self::map_message_row,
+            )
+            .optional()
+            .map_err(|err| err.to_string())?;
+
+        let Some(mut message) = row else {
+            return Ok(None);
+        };
+
+        let mut current = message
+            .metadata
+            .as_ref()
+            .and_then(Value::as_object)
+            .cloned()
+            .unwrap_or_else(Map::new);
+        let entries = current
+            .entry(key.to_string())
+            .or_insert_with(|| Value::Array(Vec::new()));
+        if !entries.is_array() {
+            *entries = Value::Array(vec![entries.take()]);
+        }
+        if let Some(array) = entries.as_array_mut() {
+            array.push(entry);
+        }
+
+        let updated = Value::Object(current);
+        let serialized = serde_json::to_string(&updated).unwrap_or_default();
+        connection
+            .execute(
+                "UPDATE chat_messages SET metadata = ?1 WHERE id = ?2 AND session_id = ?3",
+                params![serialized, message.id, session_id],
+            )
+            .map_err(|err| err.to_string())?;
+        message.metadata = Some(updated);
+        Ok(Some(message))
+    }
+
     fn fetch_session(
         connection: &Connection,
         session_id: &str,
     ) -> Result<Option<ChatSession>, String> {
         let mut stmt = connection
             .prepare(
-                "SELECT id, name, mode, topic_id, created_at, updated_at, last_message_at, is_active
+                "SELECT id, name, mode, topic_id, created_at, updated_at, last_message_at, is_active, last_read_at, llm_provider, llm_model
                  FROM chat_sessions WHERE id = ?1",
             )
             .map_err(|err| err.to_string())?;
@@ -552,7 +2237,12 @@ impl SessionService {
     ) -> Result<ChatSession, rusqlite::Error> {
         let id: String = row.get("id")?;
         let messages = Self::fetch_messages(connection, &id).unwrap_or_default();
+        let tags = Self::fetch_tags(connection, &id)?;
+        let pinned_message_ids = Self::fetch_pinned_ids(connection, &id)?;
+        let last_read_at: Option<String> = row.get("last_read_at")?;
+        let unread_count = Self::count_unread(connection, &id, last_read_at.as_deref())?;
         let is_active: i64 = row.get("is_active")?;
+        let llm = session_llm(row.get("llm_provider")?, row.get("llm_model")?);
         Ok(ChatSession {
             id,
             name: row.get("name")?,
@@ -563,23 +2253,83 @@ impl SessionService {
             last_message_at: row.get("last_message_at")?,
             is_active: is_active != 0,
             messages,
+            tags,
+            pinned_message_ids,
+            last_read_at,
+            unread_count,
+            llm,
         })
     }
 
+    fn count_unread(
+        connection: &Connection,
+        session_id: &str,
+        last_read_at: Option<&str>,
+    ) -> Result<u64, rusqlite::Error> {
+        connection
+            .query_row(
+                "SELECT COUNT(*) FROM chat_messages
+                 WHERE session_id = ?1 AND role <> 'user'
+                   AND (?2 IS NULL OR datetime(timestamp) > datetime(?2))",
+                params![session_id, last_read_at],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|count| count as u64)
+    }
+
+    fn fetch_pinned_ids(
+        connection: &Connection,
+        session_id: &str,
+    ) -> Result<Vec<String>, rusqlite::Error> {
+        let mut stmt = connection.prepare(
+            "SELECT message_id FROM chat_pinned_messages WHERE session_id = ?1
+             ORDER BY datetime(pinned_at) ASC",
+        )?;
+        let rows = stmt.query_map(params![session_id], |row| row.get::<_, String>(0))?;
+        rows.collect()
+    }
+
+    fn fetch_tags(
+        connection: &Connection,
+        session_id: &str,
+    ) -> Result<Vec<String>, rusqlite::Error> {
+        let mut stmt = connection
+            .prepare("SELECT tag FROM chat_session_tags WHERE session_id = ?1 ORDER BY tag ASC")?;
+        let rows = stmt.query_map(params![session_id], |row| row.get::<_, String>(0))?;
+        rows.collect()
+    }
+
     fn fetch_messages(
         connection: &Connection,
         session_id: &str,
     ) -> Result<Vec<ChatMessage>, String> {
+        Self::fetch_messages_in_scope(connection, session_id, &MessageScope::All)
+    }
+
+    fn fetch_messages_in_scope(
+        connection: &Connection,
+        session_id: &str,
+        scope: &MessageScope,
+    ) -> Result<Vec<ChatMessage>, String> {
+        let thread_filter = match scope {
+            MessageScope::All => "",
+            MessageScope::MainTimeline => " AND thread_id IS NULL",
+            MessageScope::Thread(_) => " AND thread_id = ?2",
+        };
         let mut stmt = connection
-            .prepare(
-                "SELECT id, role, content, timestamp, message_type, metadata
-                 FROM chat_messages WHERE session_id = ?1 ORDER BY datetime(timestamp) ASC",
-            )
+            .prepare(&format!(
+                "SELECT id, role, content, timestamp, message_type, metadata, thread_id
+                 FROM chat_messages WHERE session_id = ?1{thread_filter} ORDER BY datetime(timestamp) ASC"
+            ))
             .map_err(|err| err.to_string())?;
 
-        let iter = stmt
-            .query_map(params![session_id], |row| Self::map_message_row(row))
-            .map_err(|err| err.to_string())?;
+        let iter = match scope {
+            MessageScope::Thread(thread_id) => {
+                stmt.query_map(params![session_id, thread_id], Self::map_message_row)
+            }
+            _ => stmt.query_map(params![session_id], Self::map_message_row),
+        }
+        .map_err(|err| err.to_string())?;
 
         let mut messages = Vec::new();
         for message in iter {
@@ -608,10 +2358,38 @@ impl SessionService {
             timestamp: row.get("timestamp")?,
             message_type,
             metadata,
+            thread_id: row.get("thread_id")?,
         })
     }
 }
 
+fn session_llm(provider: Option<String>, model: Option<String>) -> Option<SessionLlm> {
+    let provider = LlmProvider::from_id(provider.as_deref()?)?;
+    Some(SessionLlm {
+        provider,
+        model: model.filter(|model| !model.is_empty())?,
+    })
+}
+
+fn normalize_tag(tag: &str) -> Option<String> {
+    let trimmed = tag.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_lowercase())
+    }
+}
+
+pub(crate) fn is_later(candidate: &str, reference: &str) -> bool {
+    match (
+        DateTime::parse_from_rfc3339(candidate),
+        DateTime::parse_from_rfc3339(reference),
+    ) {
+        (Ok(candidate), Ok(reference)) => candidate > reference,
+        _ => candidate > reference,
+    }
+}
+
 fn current_timestamp() -> String {
     DateTime::<Utc>::from(Utc::now()).to_rfc3339()
 }
@@ -695,6 +2473,7 @@ mod tests {
             timestamp: current_timestamp(),
             message_type: Some("text".into()),
             metadata: None,
+            thread_id: None,
         };
         service
             .save_message(&session.id, message.clone())
@@ -729,6 +2508,7 @@ mod tests {
             timestamp: current_timestamp(),
             message_type: Some("text".into()),
             metadata: None,
+            thread_id: None,
         };
         service
             .save_message(&session.id, message.clone())
@@ -755,40 +2535,470 @@ mod tests {
         let initial_message = ChatMessage {
             id: "message-upsert".into(),
             role: "assistant".into(),
-            content: "Initial".into(),
-            timestamp: timestamp.clone(),
-            message_type: Some("text".into()),
-            metadata: Some(json!({ "pendingApproval": true })),
+            content: "Initial".into(),
+            timestamp: timestamp.clone(),
+            message_type: Some("text".into()),
+            metadata: Some(json!({ "pendingApproval": true })),
+            thread_id: None,
+        };
+
+        service
+            .save_message(&session.id, initial_message.clone())
+            .await
+            .unwrap();
+
+        let updated_message = ChatMessage {
+            id: initial_message.id.clone(),
+            role: "assistant".into(),
+            content: "Updated".into(),
+            timestamp,
+            message_type: Some("text".into()),
+            metadata: Some(json!({ "approved": true })),
+            thread_id: None,
+        };
+
+        service
+            .save_message(&session.id, updated_message.clone())
+            .await
+            .unwrap();
+
+        let messages = service.load_messages(&session.id).await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content, "Updated");
+        let approved = messages[0]
+            .metadata
+            .as_ref()
+            .and_then(|value| value.get("approved"))
+            .and_then(|value| value.as_bool());
+        assert_eq!(approved, Some(true));
+    }
+
+    #[tokio::test]
+    async fn session_stats_track_messages_and_usage() {
+        let service = SessionService::new_in_memory();
+        let session = service
+            .create_session(CreateSessionInput {
+                name: "Stats".into(),
+                mode: "personal".into(),
+                topic_id: None,
+                is_active: true,
+            })
+            .await;
+
+        for id in ["stats-1", "stats-2", "stats-2"] {
+            service
+                .save_message(
+                    &session.id,
+                    ChatMessage {
+                        id: id.into(),
+                        role: "user".into(),
+                        content: "hello".into(),
+                        timestamp: current_timestamp(),
+                        message_type: None,
+                        metadata: None,
+                        thread_id: None,
+                    },
+                )
+                .await
+                .unwrap();
+        }
+
+        let usage = SessionUsage::from_agent_metadata(&json!({
+            "usage": { "prompt_tokens": 120, "completionTokens": 30 },
+            "toolCalls": [{ "name": "transfer" }, { "name": "balance" }],
+        }));
+        service.record_usage(&session.id, &usage).await.unwrap();
+        service.record_usage(&session.id, &usage).await.unwrap();
+        assert!(service.record_usage("missing", &usage).await.is_err());
+        let bogus = SessionUsage::from_agent_metadata(&json!({
+            "usage": { "promptTokens": -5, "completionTokens": u64::MAX },
+            "toolCallCount": 1_000_000,
+        }));
+        assert!(bogus.is_empty());
+
+        let stats = service.session_stats(&session.id).await.unwrap();
+        assert_eq!(stats.message_count, 2);
+        assert_eq!(stats.tool_call_count, 4);
+        assert_eq!(stats.prompt_tokens, 240);
+        assert_eq!(stats.completion_tokens, 60);
+        assert_eq!(stats.total_tokens, 300);
+
+        service.prune_messages(None, Some(1)).await.unwrap();
+        let stats = service.session_stats(&session.id).await.unwrap();
+        assert_eq!(stats.message_count, 1);
+        assert_eq!(stats.total_tokens, 300);
+    }
+
+    #[tokio::test]
+    async fn usage_is_totalled_per_model_and_session() {
+        let service = SessionService::new_in_memory();
+        let mut sessions = Vec::new();
+        for name in ["First", "Second"] {
+            sessions.push(
+                service
+                    .create_session(CreateSessionInput {
+                        name: name.into(),
+                        mode: "personal".into(),
+                        topic_id: None,
+                        is_active: true,
+                    })
+                    .await,
+            );
+        }
+
+        let usage = SessionUsage::from_agent_metadata(&json!({
+            "tokenUsage": {
+                "promptTokens": 100,
+                "completionTokens": 20,
+                "provider": "openai",
+                "modelName": "gpt-4o"
+            },
+        }));
+        assert_eq!(usage.model.as_deref(), Some("gpt-4o"));
+        let answered = SessionUsage::from_agent_metadata(&json!({
+            "tokenUsage": { "promptTokens": 100, "completionTokens": 20 },
+            "answeredBy": { "provider": "anthropic", "model": "claude-3-5-haiku-latest" },
+        }));
+        assert_eq!(answered.provider.as_deref(), Some("anthropic"));
+        assert_eq!(answered.model.as_deref(), Some("claude-3-5-haiku-latest"));
+        assert_eq!(
+            SessionUsage::from_agent_metadata(&json!({ "tokenUsage": usage.to_metadata() })),
+            usage
+        );
+        service.record_usage(&sessions[0].id, &usage).await.unwrap();
+        service.record_usage(&sessions[1].id, &usage).await.unwrap();
+        let unattributed = SessionUsage {
+            prompt_tokens: 5,
+            ..SessionUsage::default()
+        };
+        service
+            .record_usage(&sessions[1].id, &unattributed)
+            .await
+            .unwrap();
+
+        let first = service.model_usage(Some(&sessions[0].id)).await.unwrap();
+        assert_eq!(
+            first,
+            [ModelUsage {
+                provider: "openai".into(),
+                model: "gpt-4o".into(),
+                responses: 1,
+                prompt_tokens: 100,
+                completion_tokens: 20,
+            }]
+        );
+
+        let all = service.model_usage(None).await.unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!((all[0].responses, all[0].prompt_tokens), (2, 200));
+        assert_eq!((all[1].model.as_str(), all[1].prompt_tokens), ("", 5));
+
+        assert!(service.delete_session(&sessions[1].id).await);
+        assert_eq!(
+            service.model_usage(None).await.unwrap()[0].prompt_tokens,
+            100
+        );
+    }
+
+    #[tokio::test]
+    async fn tool_calls_are_recorded_per_reply_with_secrets_masked() {
+        let service = SessionService::new_in_memory();
+        let session = service
+            .create_session(CreateSessionInput {
+                name: "Tools".into(),
+                mode: "personal".into(),
+                topic_id: None,
+                is_active: true,
+            })
+            .await;
+        for (id, timestamp) in [
+            ("reply-1", "2026-01-01T00:00:00Z"),
+            ("reply-2", "2026-01-01T00:01:00Z"),
+        ] {
+            service
+                .save_message(
+                    &session.id,
+                    ChatMessage {
+                        id: id.into(),
+                        role: "assistant".into(),
+                        content: "Done".into(),
+                        timestamp: timestamp.into(),
+                        message_type: Some("text".into()),
+                        metadata: None,
+                        thread_id: None,
+                    },
+                )
+                .await
+                .unwrap();
+        }
+
+        let steps = AgentToolCall::from_agent_metadata(&json!({
+            "intermediateSteps": [
+                {
+                    "action": {
+                        "tool": "transfer-hbar",
+                        "toolInput": "{\"amount\":5,\"privateKey\":\"302e\"}"
+                    },
+                    "observation": "{\"status\":\"SUCCESS\"}"
+                },
+                { "action": { "toolInput": {} }, "observation": "nameless" }
+            ]
+        }));
+        assert_eq!(
+            steps,
+            [AgentToolCall {
+                tool_name: "transfer-hbar".into(),
+                arguments: json!({ "amount": 5, "privateKey": "********" }),
+                result: json!({ "status": "SUCCESS" }),
+                duration_ms: None,
+                success: true,
+                error: None,
+            }]
+        );
+        let failed = AgentToolCall::from_agent_metadata(&json!({
+            "toolCalls": [{ "name": "get-balance", "args": {}, "error": "timeout", "durationMs": 1500 }]
+        }));
+        assert!(!failed[0].success);
+        assert_eq!(failed[0].duration_ms, Some(1500));
+
+        service
+            .record_tool_calls(&session.id, "reply-2", &failed)
+            .await
+            .unwrap();
+        service
+            .record_tool_calls(&session.id, "reply-1", &failed)
+            .await
+            .unwrap();
+        service
+            .record_tool_calls(&session.id, "reply-1", &steps)
+            .await
+            .unwrap();
+
+        let all = service.load_tool_calls(&session.id, None).await.unwrap();
+        let names: Vec<_> = all
+            .iter()
+            .map(|record| (record.message_id.as_str(), record.call.tool_name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            [("reply-1", "transfer-hbar"), ("reply-2", "get-balance")]
+        );
+        assert_eq!(all[1].call, failed[0]);
+        assert_eq!(
+            service
+                .load_tool_calls(&session.id, Some("reply-2"))
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        assert!(service.delete_session(&session.id).await);
+        assert!(
+            service
+                .load_tool_calls(&session.id, None)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn session_summary_is_stored_and_cleared() {
+        let service = SessionService::new_in_memory();
+        let session = service
+            .create_session(CreateSessionInput {
+                name: "Long".into(),
+                mode: "personal".into(),
+                topic_id: None,
+                is_active: true,
+            })
+            .await;
+        assert_eq!(service.session_summary(&session.id).await.unwrap(), None);
+
+        let summary = SessionSummary {
+            text: "The user set up a treasury account.".into(),
+            covered_through: "message-40".into(),
+            updated_at: current_timestamp(),
+        };
+        service
+            .set_session_summary(&session.id, Some(&summary))
+            .await
+            .unwrap();
+        assert_eq!(
+            service.session_summary(&session.id).await.unwrap(),
+            Some(summary)
+        );
+
+        service
+            .set_session_summary(&session.id, None)
+            .await
+            .unwrap();
+        assert_eq!(service.session_summary(&session.id).await.unwrap(), None);
+        assert!(service.set_session_summary("missing", None).await.is_err());
+        assert!(service.session_summary("missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn session_llm_override_is_stored_and_forked() {
+        let service = SessionService::new_in_memory();
+        let session = service
+            .create_session(CreateSessionInput {
+                name: "Claude".into(),
+                mode: "personal".into(),
+                topic_id: None,
+                is_active: true,
+            })
+            .await;
+        assert_eq!(service.session_llm(&session.id).await.unwrap(), None);
+
+        let llm = SessionLlm {
+            provider: LlmProvider::Anthropic,
+            model: " claude-3-7-sonnet-latest ".into(),
+        };
+        let stored = service
+            .set_session_llm(&session.id, Some(llm))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.model, "claude-3-7-sonnet-latest");
+        assert_eq!(
+            service.session_llm(&session.id).await.unwrap(),
+            Some(stored.clone())
+        );
+        assert!(service.set_session_llm("missing", None).await.is_err());
+
+        let fork = service.fork_session(&session.id, None, None).await.unwrap();
+        assert_eq!(fork.llm, Some(stored.clone()));
+        let loaded = service.load_session(&fork.id).await.unwrap();
+        assert_eq!(loaded.llm, Some(stored));
+
+        service.set_session_llm(&session.id, None).await.unwrap();
+        assert_eq!(service.load_session(&session.id).await.unwrap().llm, None);
+    }
+
+    #[tokio::test]
+    async fn unread_counts_follow_read_marker() {
+        let service = SessionService::new_in_memory();
+        let session = service
+            .create_session(CreateSessionInput {
+                name: "Unread".into(),
+                mode: "hcs10".into(),
+                topic_id: Some("0.0.4242".into()),
+                is_active: true,
+            })
+            .await;
+
+        for (id, role, timestamp) in [
+            ("unread-1", "user", "2030-01-01T00:00:00+00:00"),
+            ("unread-2", "assistant", "2030-01-01T00:01:00+00:00"),
+            ("unread-3", "assistant", "2030-01-01T00:02:00+00:00"),
+        ] {
+            service
+                .save_message(
+                    &session.id,
+                    ChatMessage {
+                        id: id.into(),
+                        role: role.into(),
+                        content: "topic message".into(),
+                        timestamp: timestamp.into(),
+                        message_type: None,
+                        metadata: None,
+                        thread_id: None,
+                    },
+                )
+                .await
+                .unwrap();
+        }
+
+        let sessions = service.load_all_sessions(&[]).await;
+        assert_eq!(sessions[0].unread_count, 2);
+
+        let state = service
+            .mark_read(&session.id, Some("2030-01-01T00:01:00Z"))
+            .await
+            .unwrap();
+        assert_eq!(state.unread_count, 1);
+
+        service.mark_read(&session.id, None).await.unwrap();
+        let loaded = service.load_session(&session.id).await.unwrap();
+        assert_eq!(loaded.unread_count, 0);
+        assert!(service.mark_read("missing", None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn remote_changes_reconcile_into_another_service() {
+        let local = SessionService::new_in_memory();
+        let remote = SessionService::new_in_memory();
+        let session = local
+            .create_session(CreateSessionInput {
+                name: "Synced".into(),
+                mode: "personal".into(),
+                topic_id: None,
+                is_active: true,
+            })
+            .await;
+        let message = |id: &str, timestamp: &str| ChatMessage {
+            id: id.into(),
+            role: "assistant".into(),
+            content: "hello".into(),
+            timestamp: timestamp.into(),
+            message_type: None,
+            metadata: None,
+            thread_id: None,
         };
-
-        service
-            .save_message(&session.id, initial_message.clone())
+        local
+            .save_message(&session.id, message("sync-1", "2030-01-01T00:00:00+00:00"))
             .await
             .unwrap();
 
-        let updated_message = ChatMessage {
-            id: initial_message.id.clone(),
-            role: "assistant".into(),
-            content: "Updated".into(),
-            timestamp,
-            message_type: Some("text".into()),
-            metadata: Some(json!({ "approved": true })),
-        };
+        let changes = local.changes_since(None, 0).await.unwrap();
+        assert_eq!(changes.sessions.len(), 1);
+        assert_eq!(changes.sessions[0].messages.len(), 1);
 
-        service
-            .save_message(&session.id, updated_message.clone())
+        let summary = remote.reconcile_remote(&changes.sessions).await.unwrap();
+        assert_eq!(summary.sessions_created, 1);
+        assert_eq!(summary.messages_added, 1);
+        assert_eq!(
+            remote
+                .reconcile_remote(&changes.sessions)
+                .await
+                .unwrap()
+                .messages_added,
+            0
+        );
+
+        let unchanged = local
+            .changes_since(
+                changes.updated_through.as_deref(),
+                changes.last_message_rowid,
+            )
             .await
             .unwrap();
+        assert!(unchanged.sessions.is_empty());
+        assert_eq!(unchanged.updated_through, changes.updated_through);
 
-        let messages = service.load_messages(&session.id).await.unwrap();
-        assert_eq!(messages.len(), 1);
-        assert_eq!(messages[0].content, "Updated");
-        let approved = messages[0]
-            .metadata
-            .as_ref()
-            .and_then(|value| value.get("approved"))
-            .and_then(|value| value.as_bool());
-        assert_eq!(approved, Some(true));
+        local
+            .save_message(&session.id, message("sync-2", "2030-01-01T00:01:00+00:00"))
+            .await
+            .unwrap();
+        let delta = local
+            .changes_since(
+                changes.updated_through.as_deref(),
+                changes.last_message_rowid,
+            )
+            .await
+            .unwrap();
+        assert_eq!(delta.sessions[0].messages.len(), 1);
+        remote.reconcile_remote(&delta.sessions).await.unwrap();
+
+        let merged = remote.load_session(&session.id).await.unwrap();
+        assert_eq!(merged.messages.len(), 2);
+        assert_eq!(
+            merged.last_message_at.as_deref(),
+            Some("2030-01-01T00:01:00+00:00")
+        );
+        assert_eq!(merged.unread_count, 1);
     }
 
     #[tokio::test]
@@ -810,6 +3020,7 @@ mod tests {
             timestamp: current_timestamp(),
             message_type: Some("text".into()),
             metadata: None,
+            thread_id: None,
         };
 
         service
@@ -831,6 +3042,44 @@ mod tests {
         assert_eq!(updated_session.name, "Renamed");
     }
 
+    #[tokio::test]
+    async fn legacy_database_is_upgraded_by_migrations() {
+        let connection = Connection::open_in_memory().expect("in-memory sqlite");
+        connection
+            .execute_batch(
+                r#"
+                CREATE TABLE chat_sessions (
+                    id TEXT PRIMARY KEY,
+                    mode TEXT NOT NULL,
+                    topic_id TEXT,
+                    created_at TEXT NOT NULL,
+                    updated_at TEXT NOT NULL
+                );
+                CREATE TABLE chat_messages (
+                    id TEXT PRIMARY KEY,
+                    session_id TEXT NOT NULL,
+                    role TEXT NOT NULL,
+                    content TEXT NOT NULL,
+                    timestamp TEXT NOT NULL,
+                    metadata TEXT
+                );
+                "#,
+            )
+            .expect("create legacy schema");
+
+        SessionService::configure_connection(&connection).expect("migrate legacy schema");
+
+        assert!(
+            migrations::column_exists(&connection, "chat_sessions", "last_message_at").unwrap()
+        );
+        assert!(migrations::column_exists(&connection, "chat_messages", "message_type").unwrap());
+        assert_eq!(
+            migrations::current_version(&connection, SESSION_MIGRATION_SCOPE).unwrap(),
+            12
+        );
+        assert!(migrations::column_exists(&connection, "chat_messages", "thread_id").unwrap());
+    }
+
     #[tokio::test]
     async fn update_session_context_is_persisted() {
         let service = SessionService::new_in_memory();
@@ -880,6 +3129,7 @@ mod tests {
                             "completionState": "active"
                         }
                     })),
+                    thread_id: None,
                 },
             )
             .await
@@ -926,6 +3176,7 @@ mod tests {
                     timestamp: current_timestamp(),
                     message_type: Some("text".into()),
                     metadata: Some(json!({ "pendingApproval": true })),
+                    thread_id: None,
                 },
             )
             .await
@@ -940,4 +3191,474 @@ mod tests {
         assert_eq!(metadata["pendingApproval"], Value::Bool(true));
         assert_eq!(metadata["approved"], Value::Bool(true));
     }
+
+    #[tokio::test]
+    async fn thread_messages_are_loaded_separately_from_main_timeline() {
+        let service = SessionService::new_in_memory();
+        let session = service
+            .create_session(CreateSessionInput {
+                name: "Threads".into(),
+                mode: "personal".into(),
+                topic_id: None,
+                is_active: true,
+            })
+            .await;
+        service
+            .save_message(
+                &session.id,
+                ChatMessage {
+                    id: "parent".into(),
+                    role: "user".into(),
+                    content: "Create a token".into(),
+                    timestamp: current_timestamp(),
+                    message_type: Some("text".into()),
+                    metadata: None,
+                    thread_id: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let thread = service
+            .create_thread(
+                &session.id,
+                Some("parent".into()),
+                Some("Tool calls".into()),
+            )
+            .await
+            .unwrap();
+        for index in 0..2 {
+            service
+                .save_message(
+                    &session.id,
+                    ChatMessage {
+                        id: format!("tool-{index}"),
+                        role: "assistant".into(),
+                        content: "Tool call".into(),
+                        timestamp: current_timestamp(),
+                        message_type: Some("text".into()),
+                        metadata: None,
+                        thread_id: Some(thread.id.clone()),
+                    },
+                )
+                .await
+                .unwrap();
+        }
+        let stray = service
+            .save_message(
+                &session.id,
+                ChatMessage {
+                    id: "stray".into(),
+                    role: "assistant".into(),
+                    content: "Elsewhere".into(),
+                    timestamp: current_timestamp(),
+                    message_type: Some("text".into()),
+                    metadata: None,
+                    thread_id: Some("other-session-thread".into()),
+                },
+            )
+            .await;
+        assert!(stray.is_err());
+
+        let main = service
+            .load_messages_in_scope(&session.id, &MessageScope::MainTimeline)
+            .await
+            .unwrap();
+        assert_eq!(main.len(), 1);
+        assert_eq!(main[0].id, "parent");
+
+        let threaded = service
+            .load_messages_in_scope(&session.id, &MessageScope::Thread(thread.id.clone()))
+            .await
+            .unwrap();
+        assert_eq!(threaded.len(), 2);
+        assert_eq!(service.load_messages(&session.id).await.unwrap().len(), 3);
+
+        let threads = service.list_threads(&session.id).await.unwrap();
+        assert_eq!(threads.len(), 1);
+        assert_eq!(threads[0].message_count, 2);
+        assert_eq!(threads[0].parent_message_id.as_deref(), Some("parent"));
+        assert!(threads[0].last_message_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn create_thread_rejects_parent_from_other_session() {
+        let service = SessionService::new_in_memory();
+        let session = service
+            .create_session(CreateSessionInput {
+                name: "Threads".into(),
+                mode: "personal".into(),
+                topic_id: None,
+                is_active: true,
+            })
+            .await;
+        let result = service
+            .create_thread(&session.id, Some("missing".into()), None)
+            .await;
+        assert!(result.is_err());
+        assert!(service.create_thread("unknown", None, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn session_tags_filter_loaded_sessions() {
+        let service = SessionService::new_in_memory();
+        let mut ids = Vec::new();
+        for name in ["Mainnet work", "Testnet work"] {
+            let session = service
+                .create_session(CreateSessionInput {
+                    name: name.into(),
+                    mode: "personal".into(),
+                    topic_id: None,
+                    is_active: true,
+                })
+                .await;
+            ids.push(session.id);
+        }
+
+        service.add_session_tag(&ids[0], "Project-X").await.unwrap();
+        service.add_session_tag(&ids[0], "mainnet").await.unwrap();
+        let tags = service
+            .add_session_tag(&ids[1], "project-x ")
+            .await
+            .unwrap();
+        assert_eq!(tags, vec!["project-x".to_string()]);
+        assert!(service.add_session_tag(&ids[1], "  ").await.is_err());
+        assert!(service.add_session_tag("unknown", "mainnet").await.is_err());
+
+        let project = service.load_all_sessions(&["project-x".into()]).await;
+        assert_eq!(project.len(), 2);
+
+        let mainnet = service
+            .load_all_sessions(&["project-x".into(), "MAINNET".into()])
+            .await;
+        assert_eq!(mainnet.len(), 1);
+        assert_eq!(mainnet[0].id, ids[0]);
+        assert_eq!(
+            mainnet[0].tags,
+            vec!["mainnet".to_string(), "project-x".to_string()]
+        );
+
+        let remaining = service
+            .remove_session_tag(&ids[0], "mainnet")
+            .await
+            .unwrap();
+        assert_eq!(remaining, vec!["project-x".to_string()]);
+        assert_eq!(service.load_all_sessions(&[]).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn append_message_metadata_entry_accumulates_values() {
+        let service = SessionService::new_in_memory();
+        let session = service
+            .create_session(CreateSessionInput {
+                name: "Receipts".into(),
+                mode: "personal".into(),
+                topic_id: None,
+                is_active: true,
+            })
+            .await;
+        service
+            .save_message(
+                &session.id,
+                ChatMessage {
+                    id: "message-3".into(),
+                    role: "assistant".into(),
+                    content: "Executed".into(),
+                    timestamp: current_timestamp(),
+                    message_type: Some("text".into()),
+                    metadata: Some(json!({ "transactionId": "0.0.1@1.1" })),
+                    thread_id: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        for id in ["0.0.1@1.1", "0.0.1@2.2"] {
+            service
+                .append_message_metadata_entry(
+                    &session.id,
+                    "message-3",
+                    "executionReceipts",
+                    json!({ "transactionId": id }),
+                )
+                .await
+                .unwrap()
+                .unwrap();
+        }
+
+        let messages = service.load_messages(&session.id).await.unwrap();
+        let metadata = messages[0].metadata.as_ref().unwrap();
+        assert_eq!(metadata["transactionId"], "0.0.1@1.1");
+        assert_eq!(metadata["executionReceipts"].as_array().unwrap().len(), 2);
+        assert!(
+            service
+                .append_message_metadata_entry(
+                    &session.id,
+                    "missing",
+                    "executionReceipts",
+                    json!({})
+                )
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn pinned_messages_are_listed_and_returned_with_session() {
+        let service = SessionService::new_in_memory();
+        let session = service
+            .create_session(CreateSessionInput {
+                name: "Pins".into(),
+                mode: "personal".into(),
+                topic_id: None,
+                is_active: true,
+            })
+            .await;
+        for id in ["message-a", "message-b"] {
+            service
+                .save_message(
+                    &session.id,
+                    ChatMessage {
+                        id: id.into(),
+                        role: "assistant".into(),
+                        content: format!("Created {id}"),
+                        timestamp: current_timestamp(),
+                        message_type: Some("text".into()),
+                        metadata: None,
+                        thread_id: None,
+                    },
+                )
+                .await
+                .unwrap();
+        }
+
+        let pinned = service
+            .set_message_pinned(&session.id, "message-b", true)
+            .await
+            .unwrap();
+        assert_eq!(pinned, vec!["message-b".to_string()]);
+        assert!(
+            service
+                .set_message_pinned(&session.id, "missing", true)
+                .await
+                .is_err()
+        );
+
+        let listed = service.list_pinned_messages(&session.id).await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].content, "Created message-b");
+        let loaded = service.load_session(&session.id).await.unwrap();
+        assert_eq!(loaded.pinned_message_ids, vec!["message-b".to_string()]);
+
+        let pinned = service
+            .set_message_pinned(&session.id, "message-b", false)
+            .await
+            .unwrap();
+        assert!(pinned.is_empty());
+    }
+
+    #[tokio::test]
+    async fn fork_session_copies_messages_up_to_cutoff() {
+        let service = SessionService::new_in_memory();
+        let session = service
+            .create_session(CreateSessionInput {
+                name: "Original".into(),
+                mode: "personal".into(),
+                topic_id: None,
+                is_active: true,
+            })
+            .await;
+        service
+            .add_session_tag(&session.id, "testnet")
+            .await
+            .unwrap();
+        for (index, id) in ["first", "second", "third"].iter().enumerate() {
+            service
+                .save_message(
+                    &session.id,
+                    ChatMessage {
+                        id: (*id).into(),
+                        role: "user".into(),
+                        content: format!("Message {index}"),
+                        timestamp: format!("2024-01-01T00:00:0{index}+00:00"),
+                        message_type: Some("text".into()),
+                        metadata: None,
+                        thread_id: None,
+                    },
+                )
+                .await
+                .unwrap();
+        }
+
+        let fork = service
+            .fork_session(&session.id, Some("second"), None)
+            .await
+            .unwrap();
+        assert_eq!(fork.name, "Original (fork)");
+        assert_eq!(fork.tags, vec!["testnet".to_string()]);
+        assert_eq!(fork.messages.len(), 2);
+        assert_ne!(fork.messages[0].id, "first");
+
+        let loaded = service.load_session(&fork.id).await.unwrap();
+        let contents: Vec<_> = loaded.messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["Message 0", "Message 1"]);
+        assert_eq!(
+            loaded.last_message_at.as_deref(),
+            Some("2024-01-01T00:00:01+00:00")
+        );
+        assert_eq!(service.load_messages(&session.id).await.unwrap().len(), 3);
+
+        assert!(
+            service
+                .fork_session(&session.id, Some("missing"), None)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn prune_messages_applies_age_and_count_limits() {
+        let service = SessionService::new_in_memory();
+        let session = service
+            .create_session(CreateSessionInput {
+                name: "Retention".into(),
+                mode: "personal".into(),
+                topic_id: None,
+                is_active: true,
+            })
+            .await;
+        let timestamps = [
+            "2020-01-01T00:00:00+00:00".to_string(),
+            "2020-01-02T00:00:00+00:00".to_string(),
+            (Utc::now() - chrono::Duration::minutes(3)).to_rfc3339(),
+            (Utc::now() - chrono::Duration::minutes(2)).to_rfc3339(),
+            (Utc::now() - chrono::Duration::minutes(1)).to_rfc3339(),
+        ];
+        for (index, timestamp) in timestamps.iter().enumerate() {
+            service
+                .save_message(
+                    &session.id,
+                    ChatMessage {
+                        id: format!("message-{index}"),
+                        role: "user".into(),
+                        content: format!("Message {index}"),
+                        timestamp: timestamp.clone(),
+                        message_type: Some("text".into()),
+                        metadata: None,
+                        thread_id: None,
+                    },
+                )
+                .await
+                .unwrap();
+        }
+        service
+            .set_message_pinned(&session.id, "message-0", true)
+            .await
+            .unwrap();
+
+        let summary = service.prune_messages(Some(30), Some(2)).await.unwrap();
+        assert_eq!(
+            summary,
+            PruneSummary {
+                expired_messages: 1,
+                overflow_messages: 1,
+                total_removed: 2,
+            }
+        );
+
+        let remaining: Vec<String> = service
+            .load_messages(&session.id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|message| message.id)
+            .collect();
+        assert_eq!(remaining, vec!["message-0", "message-3", "message-4"]);
+
+        let unchanged = service.prune_messages(None, None).await.unwrap();
+        assert_eq!(unchanged.total_removed, 0);
+    }
+
+    #[tokio::test]
+    async fn delete_sessions_matching_applies_all_filters() {
+        let service = SessionService::new_in_memory();
+        let mut ids = Vec::new();
+        for (name, mode, is_active) in [
+            ("old-personal", "personal", false),
+            ("old-hcs10", "hcs10", false),
+            ("active-personal", "personal", true),
+        ] {
+            let session = service
+                .create_session(CreateSessionInput {
+                    name: name.into(),
+                    mode: mode.into(),
+                    topic_id: None,
+                    is_active,
+                })
+                .await;
+            ids.push(session.id);
+        }
+        for id in &ids[..2] {
+            service
+                .save_message(
+                    id,
+                    ChatMessage {
+                        id: format!("{id}-message"),
+                        role: "user".into(),
+                        content: "Old".into(),
+                        timestamp: "2020-01-01T00:00:00+00:00".into(),
+                        message_type: Some("text".into()),
+                        metadata: None,
+                        thread_id: None,
+                    },
+                )
+                .await
+                .unwrap();
+        }
+        {
+            let connection = service.db.lock().await;
+            connection
+                .execute(
+                    "UPDATE chat_sessions SET updated_at = '2020-01-01T00:00:00+00:00'",
+                    [],
+                )
+                .unwrap();
+        }
+
+        assert!(
+            service
+                .delete_sessions_matching(&SessionDeleteFilter::default())
+                .await
+                .is_err()
+        );
+
+        let deleted = service
+            .delete_sessions_matching(&SessionDeleteFilter {
+                older_than: Some("2021-01-01".into()),
+                mode: Some("personal".into()),
+                inactive_only: true,
+            })
+            .await
+            .unwrap();
+        assert_eq!(deleted, vec![ids[0].clone()]);
+
+        let remaining: Vec<String> = service
+            .load_all_sessions(&[])
+            .await
+            .into_iter()
+            .map(|session| session.name)
+            .collect();
+        assert_eq!(remaining.len(), 2);
+        assert!(!remaining.contains(&"old-personal".to_string()));
+
+        let deleted = service
+            .delete_sessions_matching(&SessionDeleteFilter {
+                inactive_only: true,
+                ..SessionDeleteFilter::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(deleted, vec![ids[1].clone()]);
+    }
 }
@@ -0,0 +1,645 @@
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{
+    decrypt_local_value, decrypt_value, encrypt_portable_value, encrypt_value, rewrap_value,
+};
+use crate::connection::HederaMirrorConfig;
+use crate::mirror::MirrorNetwork;
+use crate::proxy;
+use crate::session::{ChatSession, ReconcileSummary};
+
+const SYNC_FORMAT_VERSION: u32 = 1;
+const SYNC_MESSAGE_KIND: &str = "hol-session-sync";
+const MIN_PASSPHRASE_CHARS: usize = 8;
+/// Plaintext bytes per topic message. Encryption and base64 bring this to
+/// roughly 15 KB, inside the 20 chunks the bridge allows per submission.
+const MAX_BATCH_BYTES: usize = 11 * 1024;
+const MIRROR_PAGE_LIMIT: usize = 100;
+const MAX_MIRROR_PAGES: usize = 20;
+/// How many sequence numbers an incomplete chunk group may trail the newest
+/// message before it is given up on, so one lost chunk can't stall sync.
+const MAX_PENDING_CHUNK_SPAN: u64 = 1_000;
+
+/// One message on the sync topic. The sender and format stay readable so
+/// other devices can skip their own messages without decrypting them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSyncEnvelope {
+    pub kind: String,
+    pub format: u32,
+    pub device_id: String,
+    pub sent_at: String,
+    pub payload: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct SessionSyncLocalState {
+    enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    topic_id: Option<String>,
+    device_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    passphrase: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    published_through: Option<String>,
+    published_rowid: i64,
+    consumed_sequence: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_synced_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSyncStatus {
+    pub enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topic_id: Option<String>,
+    pub device_id: String,
+    pub consumed_sequence: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_synced_at: Option<String>,
+}
+
+/// Where local publishing left off.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublishWatermark {
+    pub updated_through: Option<String>,
+    pub message_rowid: i64,
+}
+
+/// Remote sessions read from the topic, and the sequence number every
+/// message up to which has been fully read.
+#[derive(Debug, Clone, Default)]
+pub struct RemoteSessions {
+    pub sessions: Vec<ChatSession>,
+    pub consumed_through: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSyncReport {
+    pub messages_published: usize,
+    pub sessions_received: usize,
+    pub reconciled: ReconcileSummary,
+    pub status: SessionSyncStatus,
+}
+
+/// Chunks of one multi-part topic message seen so far.
+struct PendingChunks {
+    first_sequence: u64,
+    total: u32,
+    chunks: BTreeMap<u32, Vec<u8>>,
+}
+
+#[derive(Deserialize)]
+struct TopicMessagesResponse {
+    #[serde(default)]
+    messages: Vec<TopicMessage>,
+}
+
+#[derive(Deserialize)]
+struct TopicMessage {
+    sequence_number: u64,
+    message: String,
+    #[serde(default)]
+    chunk_info: Option<ChunkInfo>,
+}
+
+#[derive(Deserialize)]
+struct ChunkInfo {
+    #[serde(default)]
+    initial_transaction_id: Value,
+    number: u32,
+    total: u32,
+}
+
+/// Opt-in history sync between desktops through a private HCS topic.
+/// Sessions are published as encrypted deltas and read back from the
+/// mirror node; merging is left to `SessionService::reconcile_remote`.
+pub struct SessionSyncService {
+    state_path: PathBuf,
+    master_password: String,
+    state: SessionSyncLocalState,
+    client: Client,
+    mirror_config: HederaMirrorConfig,
+}
+
+impl SessionSyncService {
+    pub fn new(config_dir: &Path, master_password: impl Into<String>) -> Self {
+        Self::with_mirror_config(config_dir, master_password, HederaMirrorConfig::default())
+    }
+
+    pub fn with_mirror_config(
+        config_dir: &Path,
+        master_password: impl Into<String>,
+        mirror_config: HederaMirrorConfig,
+    ) -> Self {
+        let state_path = config_dir.join("session-sync.json");
+        let mut state = fs::read_to_string(&state_path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<SessionSyncLocalState>(&contents).ok())
+            .unwrap_or_default();
+        if state.device_id.trim().is_empty() {
+            state.device_id = uuid::Uuid::new_v4().to_string();
+        }
+
+        Self {
+            state_path,
+            master_password: master_password.into(),
+            state,
+            client: proxy::client_builder()
+                .user_agent("hol-desktop-tauri/0.0.1")
+                .build()
+                .unwrap_or_default(),
+            mirror_config,
+        }
+    }
+
+    pub fn status(&self) -> SessionSyncStatus {
+        SessionSyncStatus {
+            enabled: self.state.enabled,
+            topic_id: self.state.topic_id.clone(),
+            device_id: self.state.device_id.clone(),
+            consumed_sequence: self.state.consumed_sequence,
+            last_synced_at: self.state.last_synced_at.clone(),
+        }
+    }
+
+    /// Turns sync on for `topic_id`. Every desktop sharing the topic must use
+    /// the same passphrase. Switching topics starts over from the beginning;
+    /// a new passphrase reads the topic again, since messages skipped under
+    /// the old one may open now.
+    pub fn enable(
+        &mut self,
+        topic_id: &str,
+        passphrase: &str,
+    ) -> Result<SessionSyncStatus, String> {
+        if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+            return Err(format!(
+                "Sync passphrase must be at least {MIN_PASSPHRASE_CHARS} characters"
+            ));
+        }
+        if self.state.topic_id.as_deref() != Some(topic_id) {
+            self.state.published_through = None;
+            self.state.published_rowid = 0;
+            self.state.consumed_sequence = 0;
+            self.state.last_synced_at = None;
+        } else if self.passphrase().ok().as_deref() != Some(passphrase) {
+            self.state.consumed_sequence = 0;
+        }
+        self.state.topic_id = Some(topic_id.to_string());
+        self.state.passphrase = encrypt_value(passphrase, &self.master_password)?;
+        self.state.enabled = true;
+        self.persist_state()?;
+        Ok(self.status())
+    }
+
+    pub fn disable(&mut self) -> Result<SessionSyncStatus, String> {
+        self.state.enabled = false;
+        self.state.passphrase = None;
+        self.persist_state()?;
+        Ok(self.status())
+    }
+
+    /// Rewraps the stored passphrase under the current encryption envelope.
+    /// Returns whether there was one.
+    pub fn rotate_keys(&mut self) -> Result<bool, String> {
+        let master_password = self.master_password.clone();
+        self.rekey(&master_password)
+    }
+
+    /// Moves the stored passphrase to a new master password.
+    pub fn rekey(&mut self, master_password: &str) -> Result<bool, String> {
+        let rewrapped = match self.state.passphrase.as_deref() {
+            Some(stored) => rewrap_value(stored, &self.master_password, master_password)?,
+            None => None,
+        };
+        self.master_password = master_password.to_string();
+        let Some(rewrapped) = rewrapped else {
+            return Ok(false);
+        };
+        self.state.passphrase = Some(rewrapped);
+        self.persist_state()?;
+        Ok(true)
+    }
+
+    pub fn topic_id(&self) -> Result<String, String> {
+        match (self.state.enabled, self.state.topic_id.as_ref()) {
+            (true, Some(topic_id)) => Ok(topic_id.clone()),
+            _ => Err("Session sync is not enabled".to_string()),
+        }
+    }
+
+    pub fn watermark(&self) -> PublishWatermark {
+        PublishWatermark {
+            updated_through: self.state.published_through.clone(),
+            message_rowid: self.state.published_rowid,
+        }
+    }
+
+    pub fn mark_published(&mut self, watermark: PublishWatermark) -> Result<(), String> {
+        self.state.published_through = watermark.updated_through;
+        self.state.published_rowid = watermark.message_rowid;
+        self.persist_state()
+    }
+
+    pub fn mark_consumed(&mut self, sequence: u64) -> Result<(), String> {
+        self.state.consumed_sequence = self.state.consumed_sequence.max(sequence);
+        self.state.last_synced_at = Some(chrono::Utc::now().to_rfc3339());
+        self.persist_state()
+    }
+
+    /// Encrypts `sessions` into topic messages, splitting sessions with long
+    /// histories across several messages.
+    pub fn seal(&self, sessions: Vec<ChatSession>) -> Result<Vec<String>, String> {
+        let passphrase = self.passphrase()?;
+        batch_sessions(sessions, MAX_BATCH_BYTES)
+            .into_iter()
+            .map(|batch| {
+                let serialized = serde_json::to_string(&batch)
+                    .map_err(|error| format!("Failed to serialize session delta: {error}"))?;
+                let payload = encrypt_portable_value(&serialized, &passphrase)?
+                    .ok_or_else(|| "Session delta is empty".to_string())?;
+                serde_json::to_string(&SessionSyncEnvelope {
+                    kind: SYNC_MESSAGE_KIND.to_string(),
+                    format: SYNC_FORMAT_VERSION,
+                    device_id: self.state.device_id.clone(),
+                    sent_at: chrono::Utc::now().to_rfc3339(),
+                    payload,
+                })
+                .map_err(|error| format!("Failed to serialize sync envelope: {error}"))
+            })
+            .collect()
+    }
+
+    /// Decrypts a topic message. Messages from this device, from other apps,
+    /// or in a newer format yield `None`.
+    pub fn open(&self, message: &str) -> Result<Option<Vec<ChatSession>>, String> {
+        let Ok(envelope) = serde_json::from_str::<SessionSyncEnvelope>(message) else {
+            return Ok(None);
+        };
+        if envelope.kind != SYNC_MESSAGE_KIND
+            || envelope.format > SYNC_FORMAT_VERSION
+            || envelope.device_id == self.state.device_id
+        {
+            return Ok(None);
+        }
+        let decrypted = decrypt_value(&envelope.payload, &self.passphrase()?)
+            .map_err(|_| {
+                "Failed to decrypt session sync message; check the sync passphrase".to_string()
+            })?
+            .ok_or_else(|| "Session sync message is not encrypted".to_string())?;
+        serde_json::from_str(&decrypted)
+            .map(Some)
+            .map_err(|error| format!("Failed to parse session sync message: {error}"))
+    }
+
+    /// Reads new topic messages from the mirror node and decrypts those sent
+    /// by other devices. Chunked messages are reassembled; an incomplete one
+    /// holds back `consumed_through` so it is read again next time, until it
+    /// trails the newest message by more than [`MAX_PENDING_CHUNK_SPAN`].
+    pub async fn fetch_remote(&self, network: MirrorNetwork) -> Result<RemoteSessions, String> {
+        let topic_id = self.topic_id()?;
+        let base = match network {
+            MirrorNetwork::Mainnet => self.mirror_config.mainnet.trim_end_matches('/'),
+            MirrorNetwork::Testnet => self.mirror_config.testnet.trim_end_matches('/'),
+        };
+
+        let mut after = self.state.consumed_sequence;
+        let mut pending: BTreeMap<String, PendingChunks> = BTreeMap::new();
+        let mut remote = RemoteSessions {
+            consumed_through: after,
+            ..RemoteSessions::default()
+        };
+
+        for _ in 0..MAX_MIRROR_PAGES {
+            let url = format!(
+                "{base}/topics/{topic_id}/messages?sequencenumber=gt:{after}&order=asc&limit={MIRROR_PAGE_LIMIT}"
+            );
+            let response = self
+                .client
+                .get(&url)
+                .send()
+                .await
+                .map_err(|error| format!("Failed to contact Hedera mirror node: {error}"))?;
+            if !response.status().is_success() {
+                return Err(format!(
+                    "Mirror node returned {} for topic {topic_id} messages",
+                    response.status()
+                ));
+            }
+            let page: TopicMessagesResponse = response
+                .json()
+                .await
+                .map_err(|error| format!("Failed to parse mirror node topic messages: {error}"))?;
+            let page_len = page.messages.len();
+
+            for message in page.messages {
+                after = message.sequence_number;
+                let bytes = STANDARD
+                    .decode(message.message.as_bytes())
+                    .map_err(|error| format!("Invalid topic message encoding: {error}"))?;
+                let complete = match message.chunk_info.filter(|chunk| chunk.total > 1) {
+                    None => Some(bytes),
+                    Some(chunk) => {
+                        let key = chunk.initial_transaction_id.to_string();
+                        let entry = pending.entry(key.clone()).or_insert_with(|| PendingChunks {
+                            first_sequence: message.sequence_number,
+                            total: chunk.total,
+                            chunks: BTreeMap::new(),
+                        });
+                        entry.chunks.insert(chunk.number, bytes);
+                        if entry.chunks.len() as u32 >= entry.total {
+                            pending
+                                .remove(&key)
+                                .map(|group| group.chunks.into_values().flatten().collect())
+                        } else {
+                            None
+                        }
+                    }
+                };
+
+                if let Some(bytes) = complete {
+                    let text = String::from_utf8_lossy(&bytes);
+                    match self.open(&text) {
+                        Ok(Some(sessions)) => remote.sessions.extend(sessions),
+                        Ok(None) => {}
+                        Err(error) => log::warn!(
+                            "Skipping session sync message {} on topic {}: {}",
+                            message.sequence_number,
+                            topic_id,
+                            error
+                        ),
+                    }
+                }
+            }
+
+            pending.retain(|key, group| {
+                let stale = after.saturating_sub(group.first_sequence) > MAX_PENDING_CHUNK_SPAN;
+                if stale {
+                    log::warn!(
+                        "Dropping incomplete session sync message {} on topic {} ({} of {} chunks)",
+                        key,
+                        topic_id,
+                        group.chunks.len(),
+                        group.total
+                    );
+                }
+                !stale
+            });
+
+            if page_len < MIRROR_PAGE_LIMIT {
+                break;
+            }
+        }
+
+        remote.consumed_through = pending
+            .values()
+            .map(|group| group.first_sequence.saturating_sub(1))
+            .min()
+            .unwrap_or(after);
+        Ok(remote)
+    }
+
+    fn passphrase(&self) -> Result<String, String> {
+        let stored = self
+            .state
+            .passphrase
+            .as_deref()
+            .ok_or_else(|| "Session sync is not enabled".to_string())?;
+        decrypt_local_value(stored, &self.master_password)?
+            .ok_or_else(|| "Session sync passphrase is not encrypted".to_string())
+    }
+
+    fn persist_state(&self) -> Result<(), String> {
+        if let Some(parent) = self.state_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|error| format!("Failed to create directory: {error}"))?;
+        }
+        let contents = serde_json::to_string_pretty(&self.state)
+            .map_err(|error| format!("Failed to serialize session sync state: {error}"))?;
+        fs::write(&self.state_path, contents)
+            .map_err(|error| format!("Failed to save session sync state: {error}"))
+    }
+}
+
+/// Groups sessions into batches whose JSON stays under `budget` bytes. A
+/// session with more messages than fit is repeated across batches, each copy
+/// carrying the next run of messages.
+fn batch_sessions(sessions: Vec<ChatSession>, budget: usize) -> Vec<Vec<ChatSession>> {
+    let mut batches = Vec::new();
+    let mut current: Vec<ChatSession> = Vec::new();
+    let mut size = 2;
+
+    for mut session in sessions {
+        let messages = std::mem::take(&mut session.messages);
+        let header_len = json_len(&session) + 1;
+        if size + header_len > budget && !current.is_empty() {
+            batches.push(std::mem::take(&mut current));
+            size = 2;
+        }
+        current.push(session.clone());
+        size += header_len;
+
+        for message in messages {
+            let message_len = json_len(&message) + 1;
+            let holds_more_than_header =
+                current.len() > 1 || current.last().is_some_and(|last| !last.messages.is_empty());
+            if size + message_len > budget && holds_more_than_header {
+                batches.push(std::mem::take(&mut current));
+                current.push(session.clone());
+                size = 2 + header_len;
+            }
+            if let Some(last) = current.last_mut() {
+                last.messages.push(message);
+            }
+            size += message_len;
+        }
+    }
+
+    if !current.is_empty() {
+        batches.push(current);
+    }
+    batches
+}
+
+fn json_len<T: Serialize>(value: &T) -> usize {
+    serde_json::to_string(value)
+        .map(|json| json.len())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::ChatMessage;
+    use httpmock::MockServer;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn session_with_messages(count: usize, content_len: usize) -> ChatSession {
+        ChatSession {
+            id: "session-1".into(),
+            name: "Shared".into(),
+            mode: "personal".into(),
+            topic_id: None,
+            created_at: "2026-01-01T00:00:00+00:00".into(),
+            updated_at: "2026-01-01T00:00:00+00:00".into(),
+            last_message_at: None,
+            is_active: true,
+            messages: (0..count)
+                .map(|index| ChatMessage {
+                    id: format!("message-{index}"),
+                    role: "user".into(),
+                    content: "x".repeat(content_len),
+                    timestamp: "2026-01-01T00:00:00+00:00".into(),
+                    message_type: None,
+                    metadata: None,
+                    thread_id: None,
+                })
+                .collect(),
+            tags: Vec::new(),
+            pinned_message_ids: Vec::new(),
+            last_read_at: None,
+            unread_count: 0,
+            llm: None,
+        }
+    }
+
+    #[test]
+    fn sealed_deltas_open_only_on_other_devices_with_the_passphrase() {
+        let (first_dir, second_dir, third_dir) =
+            (tempdir().unwrap(), tempdir().unwrap(), tempdir().unwrap());
+        let mut first = SessionSyncService::new(first_dir.path(), "master-a");
+        let mut second = SessionSyncService::new(second_dir.path(), "master-b");
+        let mut stranger = SessionSyncService::new(third_dir.path(), "master-c");
+        first.enable("0.0.900", "correct horse").unwrap();
+        second.enable("0.0.900", "correct horse").unwrap();
+        stranger.enable("0.0.900", "wrong passphrase").unwrap();
+        assert!(first.enable("0.0.900", "short").is_err());
+
+        let envelopes = first.seal(vec![session_with_messages(40, 600)]).unwrap();
+        assert!(envelopes.len() > 1);
+
+        let mut received: Vec<ChatSession> = Vec::new();
+        for envelope in &envelopes {
+            assert!(first.open(envelope).unwrap().is_none());
+            assert!(stranger.open(envelope).is_err());
+            received.extend(second.open(envelope).unwrap().unwrap());
+        }
+        assert!(received.iter().all(|session| session.id == "session-1"));
+        assert_eq!(
+            received
+                .iter()
+                .map(|session| session.messages.len())
+                .sum::<usize>(),
+            40
+        );
+
+        let reloaded = SessionSyncService::new(first_dir.path(), "master-a");
+        assert_eq!(reloaded.status(), first.status());
+        assert!(reloaded.open(&envelopes[0]).unwrap().is_none());
+
+        stranger.mark_consumed(7).unwrap();
+        stranger.enable("0.0.900", "wrong passphrase").unwrap();
+        assert_eq!(stranger.status().consumed_sequence, 7);
+        stranger.enable("0.0.900", "correct horse").unwrap();
+        assert_eq!(stranger.status().consumed_sequence, 0);
+    }
+
+    #[tokio::test]
+    async fn fetch_remote_reassembles_chunks_and_holds_back_partial_groups() {
+        let (sender_dir, receiver_dir) = (tempdir().unwrap(), tempdir().unwrap());
+        let mut sender = SessionSyncService::new(sender_dir.path(), "master");
+        sender.enable("0.0.900", "correct horse").unwrap();
+        let envelope = sender
+            .seal(vec![session_with_messages(1, 10)])
+            .unwrap()
+            .remove(0);
+        let (head, tail) = envelope.split_at(envelope.len() / 2);
+
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method("GET")
+                .path("/api/v1/topics/0.0.900/messages")
+                .query_param("sequencenumber", "gt:0");
+            then.status(200).json_body(json!({
+                "messages": [
+                    {
+                        "sequence_number": 1,
+                        "message": STANDARD.encode(head),
+                        "chunk_info": { "initial_transaction_id": { "nonce": 1 }, "number": 1, "total": 2 }
+                    },
+                    {
+                        "sequence_number": 2,
+                        "message": STANDARD.encode(&envelope),
+                        "chunk_info": null
+                    },
+                    {
+                        "sequence_number": 3,
+                        "message": STANDARD.encode(tail),
+                        "chunk_info": { "initial_transaction_id": { "nonce": 1 }, "number": 2, "total": 2 }
+                    },
+                    {
+                        "sequence_number": 4,
+                        "message": STANDARD.encode(head),
+                        "chunk_info": { "initial_transaction_id": { "nonce": 2 }, "number": 1, "total": 2 }
+                    }
+                ]
+            }));
+        });
+
+        let mut receiver = SessionSyncService::with_mirror_config(
+            receiver_dir.path(),
+            "master",
+            HederaMirrorConfig {
+                mainnet: server.url("/api/v1"),
+                testnet: server.url("/api/v1"),
+            },
+        );
+        receiver.enable("0.0.900", "correct horse").unwrap();
+
+        let remote = receiver.fetch_remote(MirrorNetwork::Testnet).await.unwrap();
+        assert_eq!(remote.sessions.len(), 2);
+        assert_eq!(remote.consumed_through, 3);
+
+        let stale = MockServer::start();
+        stale.mock(|when, then| {
+            when.method("GET")
+                .path("/api/v1/topics/0.0.900/messages")
+                .query_param("sequencenumber", "gt:0");
+            then.status(200).json_body(json!({
+                "messages": [
+                    {
+                        "sequence_number": 1,
+                        "message": STANDARD.encode(head),
+                        "chunk_info": { "initial_transaction_id": { "nonce": 1 }, "number": 1, "total": 2 }
+                    },
+                    {
+                        "sequence_number": MAX_PENDING_CHUNK_SPAN + 2,
+                        "message": STANDARD.encode(&envelope),
+                        "chunk_info": null
+                    }
+                ]
+            }));
+        });
+        let receiver = SessionSyncService::with_mirror_config(
+            receiver_dir.path(),
+            "master",
+            HederaMirrorConfig {
+                mainnet: stale.url("/api/v1"),
+                testnet: stale.url("/api/v1"),
+            },
+        );
+        let remote = receiver.fetch_remote(MirrorNetwork::Testnet).await.unwrap();
+        assert_eq!(remote.sessions.len(), 1);
+        assert_eq!(remote.consumed_through, MAX_PENDING_CHUNK_SPAN + 2);
+    }
+}
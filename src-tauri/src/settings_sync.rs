@@ -0,0 +1,576 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::config::{
+    decrypt_local_value, decrypt_value, encrypt_portable_value, encrypt_value, rewrap_value,
+};
+
+pub const SYNC_FILE_NAME: &str = "hashgraph-desktop-settings.sync";
+/// Format 2 payloads are encrypted with the sync passphrase; format 1 used
+/// the device's master key.
+const SYNC_FORMAT_VERSION: u32 = 2;
+const MIN_PASSPHRASE_CHARS: usize = 8;
+const CONFIG_PREFIX: &str = "config.";
+const MCP_PREFIX: &str = "mcpServers.";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsSnapshot {
+    pub config: Value,
+    #[serde(default)]
+    pub mcp_servers: Vec<Value>,
+}
+
+/// On-disk sync file. Only `payload` is encrypted so watchers can compare
+/// revisions without deriving a key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncEnvelope {
+    pub format: u32,
+    pub revision: u64,
+    pub device_id: String,
+    pub updated_at: String,
+    #[serde(default)]
+    pub payload: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct SyncLocalState {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    folder: Option<PathBuf>,
+    device_id: String,
+    last_revision: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_synced_at: Option<String>,
+    /// The sync passphrase, encrypted with the master key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    passphrase: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncSettings {
+    pub enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub folder: Option<String>,
+    pub device_id: String,
+    pub last_revision: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_synced_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncState {
+    /// No sync file exists in the folder yet.
+    LocalOnly,
+    UpToDate,
+    LocalChanges,
+    RemoteChanges,
+    Conflict,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConflict {
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote: Option<Value>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictChoice {
+    Local,
+    Remote,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncCheck {
+    pub state: SyncState,
+    pub remote_revision: u64,
+    pub conflicts: Vec<SyncConflict>,
+    #[serde(skip)]
+    pub merged: Option<SettingsSnapshot>,
+    #[serde(skip)]
+    pub remote: Option<SettingsSnapshot>,
+}
+
+pub struct SettingsSyncService {
+    state_path: PathBuf,
+    base_path: PathBuf,
+    master_password: String,
+    state: SyncLocalState,
+}
+
+impl SettingsSyncService {
+    pub fn new(config_dir: &Path, master_password: impl Into<String>) -> Self {
+        let state_path = config_dir.join("settings-sync.json");
+        let base_path = config_dir.join("settings-sync.base");
+        let mut state = fs::read_to_string(&state_path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<SyncLocalState>(&contents).ok())
+            .unwrap_or_default();
+        if state.device_id.trim().is_empty() {
+            state.device_id = uuid::Uuid::new_v4().to_string();
+        }
+
+        Self {
+            state_path,
+            base_path,
+            master_password: master_password.into(),
+            state,
+        }
+    }
+
+    pub fn settings(&self) -> SyncSettings {
+        SyncSettings {
+            enabled: self.state.folder.is_some(),
+            folder: self
+                .state
+                .folder
+                .as_ref()
+                .map(|folder| folder.display().to_string()),
+            device_id: self.state.device_id.clone(),
+            last_revision: self.state.last_revision,
+            last_synced_at: self.state.last_synced_at.clone(),
+        }
+    }
+
+    pub fn enable(&mut self, folder: PathBuf, passphrase: &str) -> Result<SyncSettings, String> {
+        if !folder.is_dir() {
+            return Err(format!("Sync folder does not exist: {}", folder.display()));
+        }
+        if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+            return Err(format!(
+                "Sync passphrase must be at least {MIN_PASSPHRASE_CHARS} characters"
+            ));
+        }
+
+        self.state.folder = Some(folder);
+        self.state.passphrase = encrypt_value(passphrase, &self.master_password)?;
+        self.state.last_revision = 0;
+        self.state.last_synced_at = None;
+        let _ = fs::remove_file(&self.base_path);
+        self.persist_state()?;
+        Ok(self.settings())
+    }
+
+    pub fn disable(&mut self) -> Result<SyncSettings, String> {
+        self.state.folder = None;
+        self.state.passphrase = None;
+        self.persist_state()?;
+        Ok(self.settings())
+    }
+
+    /// Reads only the plaintext header of the sync file, if sync is enabled.
+    pub fn remote_envelope(&self) -> Result<Option<SyncEnvelope>, String> {
+        let path = self.sync_file_path()?;
+        read_envelope(&path)
+    }
+
+    pub fn has_unseen_remote_revision(&self) -> bool {
+        matches!(
+            self.remote_envelope(),
+            Ok(Some(envelope)) if envelope.revision > self.state.last_revision
+        )
+    }
+
+    /// Compares the local snapshot with the sync file and three-way merges
+    /// against the last synced snapshot. `resolutions` settles conflicting
+    /// paths; anything left unresolved is reported back for the user.
+    pub fn check(
+        &self,
+        local: &SettingsSnapshot,
+        resolutions: &HashMap<String, ConflictChoice>,
+    ) -> Result<SyncCheck, String> {
+        let path = self.sync_file_path()?;
+        let base = self.load_base()?;
+
+        let Some(envelope) = read_envelope(&path)? else {
+            return Ok(SyncCheck {
+                state: SyncState::LocalOnly,
+                remote_revision: 0,
+                conflicts: Vec::new(),
+                merged: None,
+                remote: None,
+            });
+        };
+
+        if envelope.revision <= self.state.last_revision {
+            let state = if base.as_ref() == Some(local) {
+                SyncState::UpToDate
+            } else {
+                SyncState::LocalChanges
+            };
+            return Ok(SyncCheck {
+                state,
+                remote_revision: envelope.revision,
+                conflicts: Vec::new(),
+                merged: None,
+                remote: None,
+            });
+        }
+
+        let remote = self.decrypt_snapshot(&envelope)?;
+        let (merged, conflicts) = merge_snapshots(base.as_ref(), local, &remote, resolutions);
+        let state = if conflicts.is_empty() {
+            SyncState::RemoteChanges
+        } else {
+            SyncState::Conflict
+        };
+
+        Ok(SyncCheck {
+            state,
+            remote_revision: envelope.revision,
+            conflicts,
+            merged: Some(merged),
+            remote: Some(remote),
+        })
+    }
+
+    /// Records `snapshot` as the agreed state for `revision` without writing the sync file.
+    pub fn mark_synced(
+        &mut self,
+        snapshot: &SettingsSnapshot,
+        revision: u64,
+    ) -> Result<(), String> {
+        self.store_base(snapshot)?;
+        self.state.last_revision = revision;
+        self.state.last_synced_at = Some(chrono::Utc::now().to_rfc3339());
+        self.persist_state()
+    }
+
+    /// Writes a new revision of the sync file. Fails if another device has
+    /// written a revision this device has not merged yet.
+    pub fn push(&mut self, snapshot: &SettingsSnapshot) -> Result<u64, String> {
+        let path = self.sync_file_path()?;
+        if let Some(remote) = read_envelope(&path)?
+            && remote.revision > self.state.last_revision
+        {
+            return Err(format!(
+                "Sync file has newer revision {} from another device; pull before pushing",
+                remote.revision
+            ));
+        }
+
+        let serialized = serde_json::to_string(snapshot)
+            .map_err(|error| format!("Failed to serialize settings snapshot: {error}"))?;
+        let payload = encrypt_portable_value(&serialized, &self.passphrase()?)?
+            .ok_or_else(|| "Settings snapshot is empty".to_string())?;
+        let revision = self.state.last_revision + 1;
+        let envelope = SyncEnvelope {
+            format: SYNC_FORMAT_VERSION,
+            revision,
+            device_id: self.state.device_id.clone(),
+            updated_at: chrono::Utc::now().to_rfc3339(),
+            payload,
+        };
+        let contents = serde_json::to_string_pretty(&envelope)
+            .map_err(|error| format!("Failed to serialize sync file: {error}"))?;
+        write_atomically(&path, contents.as_bytes())?;
+
+        self.mark_synced(snapshot, revision)?;
+        Ok(revision)
+    }
+
+    fn sync_file_path(&self) -> Result<PathBuf, String> {
+        self.state
+            .folder
+            .as_ref()
+            .map(|folder| folder.join(SYNC_FILE_NAME))
+            .ok_or_else(|| "Settings sync is not enabled".to_string())
+    }
+
+    fn decrypt_snapshot(&self, envelope: &SyncEnvelope) -> Result<SettingsSnapshot, String> {
+        if envelope.format > SYNC_FORMAT_VERSION {
+            return Err(format!(
+                "Sync file format {} is newer than this app supports",
+                envelope.format
+            ));
+        }
+        let decrypted = if envelope.format >= 2 {
+            decrypt_value(&envelope.payload, &self.passphrase()?)
+                .map_err(|_| "Failed to decrypt sync file; check the sync passphrase".to_string())?
+        } else {
+            decrypt_local_value(&envelope.payload, &self.master_password)?
+        }
+        .ok_or_else(|| "Sync file payload is not encrypted".to_string())?;
+        serde_json::from_str(&decrypted)
+            .map_err(|error| format!("Failed to parse sync file payload: {error}"))
+    }
+
+    /// Rewraps the last synced snapshot and the passphrase under the current
+    /// encryption envelope. Returns whether there was anything to rewrap.
+    pub fn rotate_keys(&mut self) -> Result<bool, String> {
+        let master_password = self.master_password.clone();
+        self.rekey(&master_password)
+    }
+
+    /// Moves the last synced snapshot and the passphrase to a new master
+    /// password.
+    pub fn rekey(&mut self, master_password: &str) -> Result<bool, String> {
+        let base = match fs::read_to_string(&self.base_path) {
+            Ok(contents) => rewrap_value(contents.trim(), &self.master_password, master_password)?,
+            Err(_) => None,
+        };
+        let passphrase = match self.state.passphrase.as_deref() {
+            Some(stored) => rewrap_value(stored, &self.master_password, master_password)?,
+            None => None,
+        };
+        self.master_password = master_password.to_string();
+        let rekeyed = base.is_some() || passphrase.is_some();
+        if let Some(base) = base {
+            write_atomically(&self.base_path, base.as_bytes())?;
+        }
+        if let Some(passphrase) = passphrase {
+            self.state.passphrase = Some(passphrase);
+            self.persist_state()?;
+        }
+        Ok(rekeyed)
+    }
+
+    fn passphrase(&self) -> Result<String, String> {
+        let stored = self.state.passphrase.as_deref().ok_or_else(|| {
+            "Settings sync has no passphrase; enable it again to set one".to_string()
+        })?;
+        decrypt_local_value(stored, &self.master_password)?
+            .ok_or_else(|| "Settings sync passphrase is not encrypted".to_string())
+    }
+
+    fn load_base(&self) -> Result<Option<SettingsSnapshot>, String> {
+        let Ok(contents) = fs::read_to_string(&self.base_path) else {
+            return Ok(None);
+        };
+        let Some(decrypted) = decrypt_local_value(contents.trim(), &self.master_password)? else {
+            return Ok(None);
+        };
+        Ok(serde_json::from_str(&decrypted).ok())
+    }
+
+    fn store_base(&self, snapshot: &SettingsSnapshot) -> Result<(), String> {
+        let serialized = serde_json::to_string(snapshot)
+            .map_err(|error| format!("Failed to serialize settings snapshot: {error}"))?;
+        let encrypted = encrypt_value(&serialized, &self.master_password)?
+            .ok_or_else(|| "Settings snapshot is empty".to_string())?;
+        write_atomically(&self.base_path, encrypted.as_bytes())
+    }
+
+    fn persist_state(&self) -> Result<(), String> {
+        let contents = serde_json::to_string_pretty(&self.state)
+            .map_err(|error| format!("Failed to serialize sync state: {error}"))?;
+        write_atomically(&self.state_path, contents.as_bytes())
+    }
+}
+
+fn read_envelope(path: &Path) -> Result<Option<SyncEnvelope>, String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(format!("Failed to read sync file: {error}")),
+    };
+    serde_json::from_str(&contents)
+        .map(Some)
+        .map_err(|error| format!("Failed to parse sync file: {error}"))
+}
+
+fn write_atomically(path: &Path, contents: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|error| format!("Failed to create directory: {error}"))?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    let mut file =
+        File::create(&tmp_path).map_err(|error| format!("Failed to create temp file: {error}"))?;
+    file.write_all(contents)
+        .map_err(|error| format!("Failed to write temp file: {error}"))?;
+    file.flush()
+        .map_err(|error| format!("Failed to flush temp file: {error}"))?;
+    fs::rename(&tmp_path, path).map_err(|error| format!("Failed to move file into place: {error}"))
+}
+
+/// Flattens a snapshot into mergeable units: one entry per top-level config
+/// section and one per MCP server, keyed by server id.
+fn snapshot_entries(snapshot: &SettingsSnapshot) -> BTreeMap<String, Value> {
+    let mut entries = BTreeMap::new();
+    if let Some(config) = snapshot.config.as_object() {
+        for (key, value) in config {
+            entries.insert(format!("{CONFIG_PREFIX}{key}"), value.clone());
+        }
+    }
+    for server in &snapshot.mcp_servers {
+        if let Some(id) = server.get("id").and_then(Value::as_str) {
+            entries.insert(format!("{MCP_PREFIX}{id}"), server.clone());
+        }
+    }
+    entries
+}
+
+fn merge_snapshots(
+    base: Option<&SettingsSnapshot>,
+    local: &SettingsSnapshot,
+    remote: &SettingsSnapshot,
+    resolutions: &HashMap<String, ConflictChoice>,
+) -> (SettingsSnapshot, Vec<SyncConflict>) {
+    let base_entries = base.map(snapshot_entries).unwrap_or_default();
+    let local_entries = snapshot_entries(local);
+    let remote_entries = snapshot_entries(remote);
+
+    let paths: BTreeSet<&String> = local_entries.keys().chain(remote_entries.keys()).collect();
+    let mut merged = BTreeMap::new();
+    let mut conflicts = Vec::new();
+
+    for path in paths {
+        let base_value = base_entries.get(path);
+        let local_value = local_entries.get(path);
+        let remote_value = remote_entries.get(path);
+
+        let chosen = if local_value == remote_value || remote_value == base_value {
+            local_value
+        } else if local_value == base_value {
+            remote_value
+        } else {
+            match resolutions.get(path) {
+                Some(ConflictChoice::Local) => local_value,
+                Some(ConflictChoice::Remote) => remote_value,
+                None => {
+                    conflicts.push(SyncConflict {
+                        path: path.clone(),
+                        local: local_value.cloned(),
+                        remote: remote_value.cloned(),
+                    });
+                    local_value
+                }
+            }
+        };
+
+        if let Some(value) = chosen {
+            merged.insert(path.clone(), value.clone());
+        }
+    }
+
+    let mut config = Map::new();
+    for (path, value) in &merged {
+        if let Some(key) = path.strip_prefix(CONFIG_PREFIX) {
+            config.insert(key.to_string(), value.clone());
+        }
+    }
+
+    let mut seen = BTreeSet::new();
+    let mut mcp_servers = Vec::new();
+    for server in local.mcp_servers.iter().chain(remote.mcp_servers.iter()) {
+        let Some(id) = server.get("id").and_then(Value::as_str) else {
+            continue;
+        };
+        if !seen.insert(id.to_string()) {
+            continue;
+        }
+        if let Some(value) = merged.get(&format!("{MCP_PREFIX}{id}")) {
+            mcp_servers.push(value.clone());
+        }
+    }
+
+    (
+        SettingsSnapshot {
+            config: Value::Object(config),
+            mcp_servers,
+        },
+        conflicts,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn snapshot(theme: &str, servers: Vec<Value>) -> SettingsSnapshot {
+        SettingsSnapshot {
+            config: json!({ "advanced": { "theme": theme }, "autonomousMode": false }),
+            mcp_servers: servers,
+        }
+    }
+
+    #[test]
+    fn merge_takes_one_sided_changes_and_reports_conflicts() {
+        let base = snapshot("light", vec![json!({ "id": "fs", "enabled": true })]);
+        let local = snapshot(
+            "dark",
+            vec![
+                json!({ "id": "fs", "enabled": false }),
+                json!({ "id": "git", "enabled": true }),
+            ],
+        );
+        let remote = snapshot(
+            "light",
+            vec![json!({ "id": "fs", "enabled": true, "cmd": "x" })],
+        );
+
+        let (merged, conflicts) = merge_snapshots(Some(&base), &local, &remote, &HashMap::new());
+        assert_eq!(merged.config["advanced"]["theme"], "dark");
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].path, "mcpServers.fs");
+
+        let resolutions = HashMap::from([("mcpServers.fs".to_string(), ConflictChoice::Remote)]);
+        let (merged, conflicts) = merge_snapshots(Some(&base), &local, &remote, &resolutions);
+        assert!(conflicts.is_empty());
+        assert_eq!(merged.mcp_servers.len(), 2);
+        assert_eq!(merged.mcp_servers[0]["cmd"], "x");
+        assert_eq!(merged.mcp_servers[1]["id"], "git");
+    }
+
+    #[test]
+    fn merge_applies_remote_deletions() {
+        let base = snapshot("light", vec![json!({ "id": "fs" })]);
+        let local = base.clone();
+        let remote = snapshot("light", Vec::new());
+
+        let (merged, conflicts) = merge_snapshots(Some(&base), &local, &remote, &HashMap::new());
+        assert!(conflicts.is_empty());
+        assert!(merged.mcp_servers.is_empty());
+    }
+
+    #[test]
+    fn devices_exchange_snapshots_through_sync_folder() {
+        let folder = tempdir().unwrap();
+        let first_dir = tempdir().unwrap();
+        let second_dir = tempdir().unwrap();
+        let mut first = SettingsSyncService::new(first_dir.path(), "first-device-key");
+        let mut second = SettingsSyncService::new(second_dir.path(), "second-device-key");
+        assert!(first.enable(folder.path().to_path_buf(), "short").is_err());
+        first
+            .enable(folder.path().to_path_buf(), "sync passphrase")
+            .unwrap();
+        second
+            .enable(folder.path().to_path_buf(), "sync passphrase")
+            .unwrap();
+
+        let local = snapshot("dark", vec![json!({ "id": "fs" })]);
+        assert_eq!(first.push(&local).unwrap(), 1);
+        let raw = fs::read_to_string(folder.path().join(SYNC_FILE_NAME)).unwrap();
+        assert!(!raw.contains("dark"));
+
+        assert!(second.has_unseen_remote_revision());
+        let check = second
+            .check(&snapshot("dark", Vec::new()), &HashMap::new())
+            .unwrap();
+        assert_eq!(check.state, SyncState::RemoteChanges);
+        assert_eq!(check.merged.as_ref(), Some(&local));
+        second.mark_synced(&local, check.remote_revision).unwrap();
+        assert!(!second.has_unseen_remote_revision());
+
+        let check = first.check(&local, &HashMap::new()).unwrap();
+        assert_eq!(check.state, SyncState::UpToDate);
+        assert!(second.push(&snapshot("light", Vec::new())).is_ok());
+        assert!(first.push(&local).is_err());
+    }
+}
@@ -0,0 +1,156 @@
+use serde::Serialize;
+
+use crate::config::{StartupBehavior, StartupPreferences};
+use crate::session::ChatSession;
+
+const PICKER_SESSION_LIMIT: usize = 20;
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupSessionSummary {
+    pub id: String,
+    pub name: String,
+    pub mode: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_message_at: Option<String>,
+    pub message_count: usize,
+}
+
+/// What the frontend should do once the app has launched.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(
+    tag = "action",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum StartupPlan {
+    ResumeSession {
+        session_id: String,
+    },
+    NewSession {
+        mode: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        persona_id: Option<String>,
+    },
+    ShowPicker {
+        sessions: Vec<StartupSessionSummary>,
+    },
+}
+
+/// Builds the launch plan from the preferences and the sessions ordered most
+/// recent first. Resuming or picking with no saved sessions falls back to a
+/// new session, which takes the workspace's persona unless the preferences
+/// name one.
+pub fn plan_startup(
+    preferences: &StartupPreferences,
+    workspace_persona: Option<&str>,
+    sessions: &[ChatSession],
+) -> StartupPlan {
+    let new_session = || StartupPlan::NewSession {
+        mode: preferences.session_mode.clone(),
+        persona_id: preferences
+            .persona_id
+            .clone()
+            .or_else(|| workspace_persona.map(str::to_string)),
+    };
+
+    match preferences.behavior {
+        StartupBehavior::ResumeRecent => sessions
+            .first()
+            .map(|session| StartupPlan::ResumeSession {
+                session_id: session.id.clone(),
+            })
+            .unwrap_or_else(new_session),
+        StartupBehavior::NewSession => new_session(),
+        StartupBehavior::Picker if sessions.is_empty() => new_session(),
+        StartupBehavior::Picker => StartupPlan::ShowPicker {
+            sessions: sessions
+                .iter()
+                .take(PICKER_SESSION_LIMIT)
+                .map(|session| StartupSessionSummary {
+                    id: session.id.clone(),
+                    name: session.name.clone(),
+                    mode: session.mode.clone(),
+                    last_message_at: session.last_message_at.clone(),
+                    message_count: session.messages.len(),
+                })
+                .collect(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::StartupConfig;
+    use serde_json::json;
+
+    fn session(id: &str) -> ChatSession {
+        ChatSession {
+            id: id.into(),
+            name: format!("Session {id}"),
+            mode: "personal".into(),
+            topic_id: None,
+            created_at: "2024-01-01T00:00:00+00:00".into(),
+            updated_at: "2024-01-01T00:00:00+00:00".into(),
+            last_message_at: None,
+            is_active: true,
+            messages: Vec::new(),
+            tags: Vec::new(),
+            pinned_message_ids: Vec::new(),
+            last_read_at: None,
+            unread_count: 0,
+            llm: None,
+        }
+    }
+
+    #[test]
+    fn resume_recent_falls_back_to_new_session() {
+        let preferences = StartupPreferences::default();
+        assert_eq!(
+            plan_startup(&preferences, None, &[session("a"), session("b")]),
+            StartupPlan::ResumeSession {
+                session_id: "a".into()
+            }
+        );
+        assert_eq!(
+            serde_json::to_value(plan_startup(&preferences, None, &[])).unwrap(),
+            json!({ "action": "newSession", "mode": "personal" })
+        );
+        assert_eq!(
+            serde_json::to_value(plan_startup(&preferences, Some("analyst"), &[])).unwrap(),
+            json!({ "action": "newSession", "mode": "personal", "personaId": "analyst" })
+        );
+    }
+
+    #[test]
+    fn profile_overrides_replace_defaults() {
+        let config: StartupConfig = serde_json::from_value(json!({
+            "defaults": { "behavior": "picker" },
+            "profiles": {
+                "agent-1": { "behavior": "newSession", "sessionMode": "hcs10", "personaId": "trader" }
+            }
+        }))
+        .unwrap();
+
+        let plan = plan_startup(
+            config.preferences_for(Some("agent-1")),
+            Some("analyst"),
+            &[session("a")],
+        );
+        assert_eq!(
+            serde_json::to_value(plan).unwrap(),
+            json!({ "action": "newSession", "mode": "hcs10", "personaId": "trader" })
+        );
+
+        let plan = plan_startup(
+            config.preferences_for(Some("unknown")),
+            None,
+            &[session("a")],
+        );
+        match plan {
+            StartupPlan::ShowPicker { sessions } => assert_eq!(sessions[0].id, "a"),
+            other => panic!("unexpected plan: {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,369 @@
+use chrono::Utc;
+use rusqlite::{Connection, ErrorCode, OpenFlags, params_from_iter, types::Value as SqlValue};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DatabaseHealth {
+    Healthy,
+    /// The file was corrupt; what could be read was copied into a fresh one.
+    Recovered,
+    /// The file was corrupt and nothing could be read back. It is kept aside
+    /// and the app starts with a new, empty database.
+    Unrecoverable,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TableRecovery {
+    pub name: String,
+    pub rows: usize,
+    /// Why copying stopped before the end of the table.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseRecovery {
+    pub name: String,
+    pub path: String,
+    pub health: DatabaseHealth,
+    /// What the integrity check reported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub problem: Option<String>,
+    /// Where the corrupt file was moved to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quarantined_path: Option<String>,
+    pub tables: Vec<TableRecovery>,
+    pub recovered_rows: usize,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageRecoveryReport {
+    pub checked_at: String,
+    pub databases: Vec<DatabaseRecovery>,
+    /// Whether any database was found corrupt, so the renderer can tell the
+    /// user instead of showing an unexpectedly short history.
+    pub needs_attention: bool,
+}
+
+/// Checks each existing database and recovers the corrupt ones before
+/// anything opens them. Missing files are skipped.
+pub fn check_databases(data_dir: &Path, paths: &[PathBuf]) -> StorageRecoveryReport {
+    let mut databases = Vec::new();
+    for path in paths {
+        let checked = databases
+            .iter()
+            .any(|database: &DatabaseRecovery| database.path == path_string(path));
+        if checked || !path.exists() {
+            continue;
+        }
+        let name = path
+            .strip_prefix(data_dir)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned();
+        databases.push(check_database(&name, path));
+    }
+    let needs_attention = databases
+        .iter()
+        .any(|database| database.health != DatabaseHealth::Healthy);
+    StorageRecoveryReport {
+        checked_at: Utc::now().to_rfc3339(),
+        databases,
+        needs_attention,
+    }
+}
+
+fn path_string(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+fn check_database(name: &str, path: &Path) -> DatabaseRecovery {
+    let mut report = DatabaseRecovery {
+        name: name.to_string(),
+        path: path_string(path),
+        health: DatabaseHealth::Healthy,
+        problem: None,
+        quarantined_path: None,
+        tables: Vec::new(),
+        recovered_rows: 0,
+    };
+    let problem = match quick_check(path) {
+        Ok(None) => return report,
+        Ok(Some(problem)) => problem,
+        Err(error) => {
+            log::warn!("Could not check {}, leaving it as it is: {}", name, error);
+            return report;
+        }
+    };
+    log::error!("{} failed its integrity check: {}", name, problem);
+    report.problem = Some(problem);
+    report.health = DatabaseHealth::Unrecoverable;
+
+    let quarantined = match quarantine(path) {
+        Ok(quarantined) => quarantined,
+        Err(error) => {
+            log::error!("Failed to move corrupt {} aside: {}", name, error);
+            report.problem = Some(format!(
+                "{}; the file could not be moved aside: {}",
+                report.problem.unwrap_or_default(),
+                error
+            ));
+            return report;
+        }
+    };
+    report.quarantined_path = Some(path_string(&quarantined));
+
+    match salvage(&quarantined, path) {
+        Ok(tables) => {
+            report.recovered_rows = tables.iter().map(|table| table.rows).sum();
+            report.tables = tables;
+            if report.recovered_rows > 0 {
+                report.health = DatabaseHealth::Recovered;
+            }
+        }
+        Err(error) => {
+            log::error!("Failed to salvage {}: {}", name, error);
+            // Start from a clean file rather than a half-written copy.
+            let _ = fs::remove_file(path);
+        }
+    }
+    log::warn!(
+        "Recovered {} rows from {}; the corrupt file was kept at {}",
+        report.recovered_rows,
+        name,
+        quarantined.display()
+    );
+    report
+}
+
+/// Returns the problems `PRAGMA quick_check` reports, or the error when
+/// SQLite finds the file corrupt or not a database at all. Other failures,
+/// such as a locked or unreadable file, are `Err` and do not count as
+/// corruption.
+fn quick_check(path: &Path) -> Result<Option<String>, String> {
+    match run_quick_check(path) {
+        Ok(messages) if messages.len() == 1 && messages[0] == "ok" => Ok(None),
+        Ok(messages) => Ok(Some(messages.join("; "))),
+        Err(error)
+            if matches!(
+                error.sqlite_error_code(),
+                Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase)
+            ) =>
+        {
+            Ok(Some(error.to_string()))
+        }
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+fn run_quick_check(path: &Path) -> rusqlite::Result<Vec<String>> {
+    let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut statement = connection.prepare("PRAGMA quick_check")?;
+    statement
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect()
+}
+
+/// Renames the database and its WAL and shared-memory files to
+/// `<name>.corrupt-<timestamp>`.
+fn quarantine(path: &Path) -> Result<PathBuf, String> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format!("{} is not a file", path.display()))?
+        .to_string_lossy();
+    let quarantined = path.with_file_name(format!(
+        "{file_name}.corrupt-{}",
+        Utc::now().format("%Y%m%dT%H%M%S")
+    ));
+    fs::rename(path, &quarantined).map_err(|error| error.to_string())?;
+    for suffix in ["-wal", "-shm"] {
+        let sidecar = path.with_file_name(format!("{file_name}{suffix}"));
+        if sidecar.exists() {
+            let target = format!("{}{suffix}", quarantined.display());
+            fs::rename(&sidecar, target).map_err(|error| error.to_string())?;
+        }
+    }
+    Ok(quarantined)
+}
+
+/// Copies the schema and every readable row of `source` into a new
+/// database at `target`, table by table, the way the sqlite3 shell's
+/// `.recover` does. A table stops at its first unreadable row; the other
+/// tables still get copied.
+fn salvage(source: &Path, target: &Path) -> Result<Vec<TableRecovery>, String> {
+    let source = Connection::open_with_flags(source, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|error| error.to_string())?;
+    let mut statement = source
+        .prepare(
+            "SELECT type, name, sql FROM sqlite_master
+             WHERE sql IS NOT NULL AND name NOT LIKE 'sqlite_%'
+             ORDER BY type = 'table' DESC",
+        )
+        .map_err(|error| error.to_string())?;
+    let schema = statement
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })
+        .map_err(|error| error.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| error.to_string())?;
+
+    let mut target = Connection::open(target).map_err(|error| error.to_string())?;
+    let mut tables = Vec::new();
+    for (kind, name, sql) in &schema {
+        if let Err(error) = target.execute_batch(sql) {
+            log::warn!("Skipping {} {} during recovery: {}", kind, name, error);
+            continue;
+        }
+        if kind == "table" {
+            tables.push(copy_table(&source, &mut target, name)?);
+        }
+    }
+    Ok(tables)
+}
+
+fn copy_table(
+    source: &Connection,
+    target: &mut Connection,
+    table: &str,
+) -> Result<TableRecovery, String> {
+    let quoted = format!("\"{}\"", table.replace('"', "\"\""));
+    let mut recovery = TableRecovery {
+        name: table.to_string(),
+        rows: 0,
+        error: None,
+    };
+    let mut select = match source.prepare(&format!("SELECT * FROM {quoted}")) {
+        Ok(select) => select,
+        Err(error) => {
+            recovery.error = Some(error.to_string());
+            return Ok(recovery);
+        }
+    };
+    let columns = select.column_count();
+    let transaction = target.transaction().map_err(|error| error.to_string())?;
+    {
+        let mut insert = transaction
+            .prepare(&format!(
+                "INSERT OR IGNORE INTO {quoted} VALUES ({})",
+                vec!["?"; columns].join(", ")
+            ))
+            .map_err(|error| error.to_string())?;
+        let mut rows = select.query([]).map_err(|error| error.to_string())?;
+        loop {
+            let row = match rows.next() {
+                Ok(Some(row)) => row,
+                Ok(None) => break,
+                Err(error) => {
+                    recovery.error = Some(error.to_string());
+                    break;
+                }
+            };
+            let values = match (0..columns)
+                .map(|index| row.get::<_, SqlValue>(index))
+                .collect::<Result<Vec<_>, _>>()
+            {
+                Ok(values) => values,
+                Err(error) => {
+                    recovery.error = Some(error.to_string());
+                    break;
+                }
+            };
+            insert
+                .execute(params_from_iter(values))
+                .map_err(|error| error.to_string())?;
+            recovery.rows += 1;
+        }
+    }
+    transaction.commit().map_err(|error| error.to_string())?;
+    Ok(recovery)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn corrupt_databases_are_moved_aside_and_salvaged() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("chat.sqlite");
+        {
+            let connection = Connection::open(&path).unwrap();
+            connection
+                .execute_batch(
+                    "CREATE TABLE sessions (id TEXT PRIMARY KEY, name TEXT);
+                     CREATE INDEX idx_sessions_name ON sessions(name);
+                     INSERT INTO sessions VALUES ('a', 'First'), ('b', 'Second');
+                     CREATE TABLE blobs (id INTEGER PRIMARY KEY, data BLOB);",
+                )
+                .unwrap();
+            for id in 0..40 {
+                connection
+                    .execute(
+                        "INSERT INTO blobs (id, data) VALUES (?1, zeroblob(2000))",
+                        [id],
+                    )
+                    .unwrap();
+            }
+        }
+        let healthy = check_databases(dir.path(), std::slice::from_ref(&path));
+        assert!(!healthy.needs_attention);
+        assert_eq!(healthy.databases[0].health, DatabaseHealth::Healthy);
+
+        // Zero the last pages, which belong to the blobs table.
+        let mut bytes = fs::read(&path).unwrap();
+        let length = bytes.len();
+        bytes[length - 8192..].fill(0);
+        fs::write(&path, bytes).unwrap();
+
+        let report = check_databases(dir.path(), &[path.clone(), path.clone()]);
+        assert!(report.needs_attention);
+        assert_eq!(report.databases.len(), 1);
+        let database = &report.databases[0];
+        assert_eq!(database.name, "chat.sqlite");
+        assert_eq!(database.health, DatabaseHealth::Recovered);
+        assert!(database.problem.is_some());
+        assert!(Path::new(database.quarantined_path.as_ref().unwrap()).exists());
+        assert_eq!(database.tables[0].name, "sessions");
+        assert_eq!(database.tables[0].rows, 2);
+
+        let connection = Connection::open(&path).unwrap();
+        let name: String = connection
+            .query_row("SELECT name FROM sessions WHERE id = 'b'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(name, "Second");
+        assert_eq!(quick_check(&path), Ok(None));
+
+        let garbage = dir.path().join("mcp-registry.sqlite");
+        fs::write(&garbage, b"not a database at all, just some text").unwrap();
+        let report = check_databases(dir.path(), std::slice::from_ref(&garbage));
+        assert_eq!(report.databases[0].health, DatabaseHealth::Unrecoverable);
+        assert!(!garbage.exists());
+    }
+
+    #[test]
+    fn databases_that_cannot_be_opened_are_left_alone() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("entities.sqlite");
+        fs::create_dir(&path).unwrap();
+
+        let report = check_databases(dir.path(), std::slice::from_ref(&path));
+        assert!(!report.needs_attention);
+        assert_eq!(report.databases[0].health, DatabaseHealth::Healthy);
+        assert!(report.databases[0].quarantined_path.is_none());
+        assert!(path.is_dir());
+    }
+}
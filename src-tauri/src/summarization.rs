@@ -0,0 +1,404 @@
+use crate::agent::{AgentLlmSettings, ChatEntry};
+use crate::connection::{LlmCompletionRequest, LlmMessage};
+use crate::session::{ChatMessage, SessionSummary};
+
+/// Context windows in tokens, matched against the start of a model name like
+/// the prices in `usage`. The longest match wins.
+const CONTEXT_WINDOWS: &[(&str, u32)] = &[
+    ("gpt-5", 400_000),
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4o", 128_000),
+    ("o4-mini", 200_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4", 8_192),
+    ("gpt-3.5-turbo", 16_385),
+    ("claude", 200_000),
+    ("gemini-2.5", 1_048_576),
+    ("gemini-2.0", 1_048_576),
+];
+
+/// Used for models missing from the table.
+const DEFAULT_CONTEXT_WINDOW: u32 = 32_000;
+
+/// History may fill this share of the window; the rest is left for the
+/// agent's instructions, its tool definitions and the reply.
+const HISTORY_SHARE: u32 = 2;
+
+/// Once history overflows, the newest entries filling this share of the
+/// history budget are kept word for word and the rest is summarized.
+const RECENT_SHARE: u32 = 2;
+
+const SUMMARY_MAX_TOKENS: u32 = 1_024;
+
+const SUMMARY_PROMPT: &str = "You maintain a running summary of a conversation between a user and \
+an assistant that operates on the Hedera network. Update the summary with the new messages. Keep \
+account, token and topic IDs, transaction IDs, amounts, decisions and open tasks exactly as \
+written. Reply with the summary only, in under 400 words.";
+
+pub fn context_window(model: &str) -> u32 {
+    let model = model.trim().to_ascii_lowercase();
+    let model = model.rsplit('/').next().unwrap_or_default();
+    CONTEXT_WINDOWS
+        .iter()
+        .filter(|(name, _)| model.starts_with(name))
+        .max_by_key(|(name, _)| name.len())
+        .map_or(DEFAULT_CONTEXT_WINDOW, |(_, window)| *window)
+}
+
+/// A rough count at four characters a token, which errs high for English.
+pub fn estimate_tokens(text: &str) -> u32 {
+    (text.chars().count() as u32).div_ceil(4)
+}
+
+fn entry_tokens(entry: &ChatEntry) -> u32 {
+    // Each message also costs a few tokens of framing.
+    estimate_tokens(&entry.content) + 4
+}
+
+/// Tokens of `model`'s context window that history may fill.
+pub fn history_budget(model: &str) -> u32 {
+    context_window(model) / HISTORY_SHARE
+}
+
+/// Index of the oldest entry in the newest run of `entries` that fits
+/// `budget`.
+fn keep_newest(entries: &[ChatEntry], budget: u32) -> usize {
+    keep_newest_by(entries, budget, entry_tokens)
+}
+
+/// `keep_newest` with each entry costing `cost` tokens.
+fn keep_newest_by(entries: &[ChatEntry], budget: u32, cost: impl Fn(&ChatEntry) -> u32) -> usize {
+    let mut tokens = 0;
+    let mut keep_from = entries.len();
+    while keep_from > 0 {
+        let entry = cost(&entries[keep_from - 1]);
+        if tokens + entry > budget {
+            break;
+        }
+        tokens += entry;
+        keep_from -= 1;
+    }
+    keep_from
+}
+
+/// A stored message as a history entry for the agent.
+pub fn chat_entry(message: &ChatMessage) -> ChatEntry {
+    let entry_type = match message.role.as_str() {
+        "assistant" | "ai" => "ai",
+        "system" => "system",
+        _ => "human",
+    };
+    ChatEntry {
+        entry_type: entry_type.to_string(),
+        content: message.content.clone(),
+        id: Some(message.id.clone()),
+    }
+}
+
+/// Which part of a chat history goes to the agent as it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryPlan {
+    /// Leading entries the stored summary stands in for; `0` when there is
+    /// no summary or it no longer matches the history.
+    pub covered: usize,
+    /// Entries before this index are to be summarized. Equal to `covered`
+    /// when the history fits.
+    pub summarize_to: usize,
+}
+
+impl HistoryPlan {
+    pub fn needs_summary(&self) -> bool {
+        self.summarize_to > self.covered
+    }
+}
+
+/// Decides how much of `history` has to be folded into the summary for it
+/// to fit `model`'s context window.
+pub fn plan(history: &[ChatEntry], summary: Option<&SessionSummary>, model: &str) -> HistoryPlan {
+    let matched = summary.and_then(|summary| {
+        history
+            .iter()
+            .position(|entry| entry.id.as_deref() == Some(summary.covered_through.as_str()))
+            .map(|last| (summary, last + 1))
+    });
+    let summary = matched.map(|(summary, _)| summary);
+    let covered = matched.map_or(0, |(_, covered)| covered);
+    let summary_tokens = summary.map_or(0, |summary| estimate_tokens(&summary.text));
+    let budget = history_budget(model);
+
+    let pending: u32 = history[covered..].iter().map(entry_tokens).sum();
+    if summary_tokens + pending <= budget {
+        return HistoryPlan {
+            covered,
+            summarize_to: covered,
+        };
+    }
+
+    HistoryPlan {
+        covered,
+        summarize_to: covered + keep_newest(&history[covered..], budget / RECENT_SHARE),
+    }
+}
+
+/// The newest entries of `history` that fit `model`'s history budget; older
+/// ones are dropped.
+pub fn truncate_oldest<'a>(history: &'a [ChatEntry], model: &str) -> &'a [ChatEntry] {
+    &history[keep_newest(history, history_budget(model))..]
+}
+
+/// The last `window` entries of `history`, led by the `pinned` ones that fell
+/// out of it. Pinned entries always go along; the window loses its oldest
+/// entries when it would not fit next to them. Entries are matched to pinned
+/// ones by message id.
+pub fn sliding_window(
+    history: &[ChatEntry],
+    pinned: &[ChatEntry],
+    window: usize,
+    model: &str,
+) -> Vec<ChatEntry> {
+    let is_pinned =
+        |entry: &ChatEntry| entry.id.is_some() && pinned.iter().any(|pinned| pinned.id == entry.id);
+    let recent = &history[history.len().saturating_sub(window)..];
+    // Pinned entries are paid for up front, so the window never trims them.
+    let pinned_tokens: u32 = pinned.iter().map(entry_tokens).sum();
+    let keep_from = keep_newest_by(
+        recent,
+        history_budget(model).saturating_sub(pinned_tokens),
+        |entry| {
+            if is_pinned(entry) {
+                0
+            } else {
+                entry_tokens(entry)
+            }
+        },
+    );
+    let kept = &recent[keep_from..];
+    pinned
+        .iter()
+        .filter(|entry| entry.id.is_none() || !kept.iter().any(|kept| kept.id == entry.id))
+        .chain(kept)
+        .cloned()
+        .collect()
+}
+
+/// The history the agent sees: the summary as a system entry, followed by
+/// the entries it does not cover.
+pub fn with_summary(summary: Option<&str>, recent: &[ChatEntry]) -> Vec<ChatEntry> {
+    summary
+        .map(|text| ChatEntry {
+            entry_type: "system".to_string(),
+            content: format!("Summary of the earlier conversation:\n{text}"),
+            id: None,
+        })
+        .into_iter()
+        .chain(recent.iter().cloned())
+        .collect()
+}
+
+/// Asks the session's LLM to fold `entries` into `previous`.
+pub fn completion_request(
+    settings: &AgentLlmSettings,
+    previous: Option<&str>,
+    entries: &[ChatEntry],
+) -> Result<LlmCompletionRequest, String> {
+    let model = settings
+        .model
+        .clone()
+        .ok_or_else(|| "No model is configured for the agent".to_string())?;
+    let mut transcript = String::new();
+    if let Some(previous) = previous {
+        transcript.push_str(&format!("Summary so far:\n{previous}\n\n"));
+    }
+    transcript.push_str("New messages:\n");
+    for entry in entries {
+        let speaker = match entry.entry_type.as_str() {
+            "ai" => "Assistant",
+            "system" => "System",
+            _ => "User",
+        };
+        transcript.push_str(&format!("{speaker}: {}\n", entry.content));
+    }
+
+    Ok(LlmCompletionRequest {
+        provider: settings.provider,
+        api_key: settings.api_key.clone(),
+        model,
+        base_url: settings.base_url.clone(),
+        system: Some(SUMMARY_PROMPT.to_string()),
+        messages: vec![LlmMessage {
+            role: "user".to_string(),
+            content: transcript,
+        }],
+        max_tokens: SUMMARY_MAX_TOKENS,
+        temperature: Some(0.2),
+        stop_sequences: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LlmProvider;
+
+    fn entry(entry_type: &str, characters: usize) -> ChatEntry {
+        ChatEntry {
+            entry_type: entry_type.to_string(),
+            content: "x".repeat(characters),
+            id: None,
+        }
+    }
+
+    #[test]
+    fn windows_match_the_longest_family() {
+        assert_eq!(context_window("gpt-4o-mini"), 128_000);
+        assert_eq!(context_window("gpt-4-0613"), 8_192);
+        assert_eq!(context_window("anthropic/claude-3-7-sonnet"), 200_000);
+        assert_eq!(context_window("llama-3-70b"), DEFAULT_CONTEXT_WINDOW);
+    }
+
+    #[test]
+    fn overflowing_history_folds_the_oldest_entries() {
+        // gpt-4 leaves 4,096 tokens for history and keeps 2,048 of them.
+        let with_id = |index: usize, entry_type: &str| ChatEntry {
+            id: Some(format!("m{index}")),
+            ..entry(entry_type, 3_984)
+        };
+        let history: Vec<ChatEntry> = (0..10).map(|index| with_id(index, "human")).collect();
+        assert_eq!(
+            plan(&history[..4], None, "gpt-4"),
+            HistoryPlan {
+                covered: 0,
+                summarize_to: 0,
+            }
+        );
+
+        let first = plan(&history, None, "gpt-4");
+        assert_eq!(
+            first,
+            HistoryPlan {
+                covered: 0,
+                summarize_to: 8,
+            }
+        );
+        assert!(first.needs_summary());
+
+        let summary = SessionSummary {
+            text: "x".repeat(400),
+            covered_through: "m7".to_string(),
+            updated_at: String::new(),
+        };
+        assert!(!plan(&history, Some(&summary), "gpt-4").needs_summary());
+
+        let longer: Vec<ChatEntry> = (0..13).map(|index| with_id(index, "ai")).collect();
+        assert_eq!(
+            plan(&longer, Some(&summary), "gpt-4"),
+            HistoryPlan {
+                covered: 8,
+                summarize_to: 11,
+            }
+        );
+        // The summary follows its last entry when earlier ones are gone.
+        assert_eq!(plan(&longer[3..], Some(&summary), "gpt-4").covered, 5);
+        // Without its last entry the summary belongs to another history.
+        assert_eq!(plan(&history[..5], Some(&summary), "gpt-4").covered, 0);
+    }
+
+    #[test]
+    fn truncation_and_the_sliding_window_fit_the_budget() {
+        // 253 tokens each against gpt-4's 4,096 for history.
+        let short: Vec<ChatEntry> = (0..10).map(|_| entry("human", 996)).collect();
+        assert_eq!(truncate_oldest(&short, "gpt-4"), &short[..]);
+        let long: Vec<ChatEntry> = (0..20).map(|_| entry("ai", 996)).collect();
+        assert_eq!(truncate_oldest(&long, "gpt-4"), &long[4..]);
+
+        let mut history: Vec<ChatEntry> = (0..6)
+            .map(|index| ChatEntry {
+                entry_type: "human".to_string(),
+                content: format!("message {index}"),
+                id: Some(format!("m{index}")),
+            })
+            .collect();
+        let pinned = vec![history[1].clone(), history[5].clone()];
+        let windowed = sliding_window(&history, &pinned, 3, "gpt-4o");
+        let contents: Vec<&str> = windowed
+            .iter()
+            .map(|entry| entry.content.as_str())
+            .collect();
+        assert_eq!(
+            contents,
+            vec!["message 1", "message 3", "message 4", "message 5"]
+        );
+
+        // Pinned entries stay even when the window has to give way.
+        history[4].content = "x".repeat(20_000);
+        let windowed = sliding_window(&history, &pinned, 3, "gpt-4");
+        let contents: Vec<&str> = windowed
+            .iter()
+            .map(|entry| entry.content.as_str())
+            .collect();
+        assert_eq!(contents, vec!["message 1", "message 5"]);
+
+        // A pinned entry inside the window that the window trims still goes
+        // along.
+        let windowed = sliding_window(&history, &[history[3].clone()], 3, "gpt-4");
+        let ids: Vec<&str> = windowed
+            .iter()
+            .filter_map(|entry| entry.id.as_deref())
+            .collect();
+        assert_eq!(ids, vec!["m3", "m5"]);
+
+        // Entries with the same text but another id are not the pinned one.
+        let mut lookalike = history[5].clone();
+        lookalike.id = Some("elsewhere".to_string());
+        let windowed = sliding_window(&history, &[lookalike], 3, "gpt-4o");
+        assert_eq!(windowed.len(), 4);
+    }
+
+    #[test]
+    fn stored_roles_map_to_entry_types() {
+        let message = |role: &str| ChatMessage {
+            id: "m".to_string(),
+            role: role.to_string(),
+            content: "hi".to_string(),
+            timestamp: String::new(),
+            message_type: None,
+            metadata: None,
+            thread_id: None,
+        };
+        assert_eq!(chat_entry(&message("assistant")).entry_type, "ai");
+        assert_eq!(chat_entry(&message("user")).entry_type, "human");
+        assert_eq!(chat_entry(&message("system")).entry_type, "system");
+    }
+
+    #[test]
+    fn summary_leads_the_history_and_the_request() {
+        let recent = [entry("human", 3)];
+        let history = with_summary(Some("Earlier."), &recent);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].entry_type, "system");
+        assert!(history[0].content.ends_with("Earlier."));
+        assert_eq!(with_summary(None, &recent), recent);
+
+        let settings = AgentLlmSettings {
+            provider: LlmProvider::Openai,
+            api_key: "sk-test".into(),
+            model: Some("gpt-4o".to_string()),
+            base_url: None,
+        };
+        let request = completion_request(&settings, Some("Earlier."), &recent).unwrap();
+        let transcript = &request.messages[0].content;
+        assert!(transcript.starts_with("Summary so far:\nEarlier."));
+        assert!(transcript.ends_with("User: xxx\n"));
+        assert!(
+            completion_request(
+                &AgentLlmSettings {
+                    model: None,
+                    ..settings
+                },
+                None,
+                &recent
+            )
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,443 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::connection::HederaMirrorConfig;
+use crate::entity_id::parse_entity_id;
+use crate::mirror::MirrorNetwork;
+use crate::proxy;
+
+const CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+const CACHE_CAPACITY: usize = 128;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+const DEFAULT_PAGE_LIMIT: u32 = 25;
+const MAX_PAGE_LIMIT: u32 = 100;
+const SUMMARY_MAX_PAGES: usize = 50;
+const SUMMARY_TOP_HOLDERS: usize = 10;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenHolder {
+    pub account: String,
+    pub balance: u64,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenHoldersPage {
+    pub token_id: String,
+    pub holders: Vec<TokenHolder>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TopHolder {
+    pub account: String,
+    pub balance: u64,
+    pub percentage: f64,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenDistributionSummary {
+    pub token_id: String,
+    pub holder_count: usize,
+    pub total_balance: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<u32>,
+    pub top_holders: Vec<TopHolder>,
+    pub top_holders_percentage: f64,
+    /// Set when the holder list was cut off after `SUMMARY_MAX_PAGES` pages.
+    pub truncated: bool,
+}
+
+#[derive(Deserialize)]
+struct MirrorBalancesResponse {
+    #[serde(default)]
+    balances: Vec<MirrorBalance>,
+    #[serde(default)]
+    links: MirrorLinks,
+}
+
+#[derive(Deserialize)]
+struct MirrorBalance {
+    account: String,
+    balance: u64,
+    #[serde(default)]
+    decimals: Option<u32>,
+}
+
+#[derive(Default, Deserialize)]
+struct MirrorLinks {
+    #[serde(default)]
+    next: Option<String>,
+}
+
+struct Cached<T> {
+    value: T,
+    fetched_at: Instant,
+}
+
+/// Results kept for `CACHE_TTL`, at most `CACHE_CAPACITY` of them. The lock
+/// is only taken to read or store an entry, never across a fetch.
+struct ResponseCache<T> {
+    entries: Mutex<HashMap<String, Cached<T>>>,
+}
+
+impl<T: Clone> ResponseCache<T> {
+    fn new() -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<T> {
+        let entries = self.entries.lock().ok()?;
+        entries
+            .get(key)
+            .filter(|cached| cached.fetched_at.elapsed() < CACHE_TTL)
+            .map(|cached| cached.value.clone())
+    }
+
+    fn insert(&self, key: String, value: T) {
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        entries.retain(|_, cached| cached.fetched_at.elapsed() < CACHE_TTL);
+        if entries.len() >= CACHE_CAPACITY
+            && let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, cached)| cached.fetched_at)
+                .map(|(key, _)| key.clone())
+        {
+            entries.remove(&oldest);
+        }
+        entries.insert(
+            key,
+            Cached {
+                value,
+                fetched_at: Instant::now(),
+            },
+        );
+    }
+}
+
+/// Token holder queries against the mirror node `/tokens/{id}/balances`
+/// endpoint, with results cached per network and token for a few minutes.
+pub struct TokenHolderService {
+    client: Client,
+    mirror_config: HederaMirrorConfig,
+    pages: ResponseCache<TokenHoldersPage>,
+    summaries: ResponseCache<TokenDistributionSummary>,
+}
+
+impl TokenHolderService {
+    pub fn new() -> Result<Self, String> {
+        Self::with_mirror_config(HederaMirrorConfig::default())
+    }
+
+    pub fn with_mirror_config(mirror_config: HederaMirrorConfig) -> Result<Self, String> {
+        let client = proxy::client_builder()
+            .user_agent("hol-desktop-tauri/0.0.1")
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|error| error.to_string())?;
+        Ok(Self {
+            client,
+            mirror_config,
+            pages: ResponseCache::new(),
+            summaries: ResponseCache::new(),
+        })
+    }
+
+    /// Returns one page of accounts holding a positive balance. `cursor` is
+    /// the `next` value of a previous page.
+    pub async fn get_holders(
+        &self,
+        token_id: &str,
+        network: MirrorNetwork,
+        limit: Option<u32>,
+        cursor: Option<&str>,
+    ) -> Result<TokenHoldersPage, String> {
+        let token_id = &validate_token_id(token_id, network)?;
+        let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT);
+        let key = format!(
+            "{}:{}:{}:{}",
+            network.as_str(),
+            token_id,
+            limit,
+            cursor.unwrap_or_default()
+        );
+        if let Some(page) = self.pages.get(&key) {
+            return Ok(page);
+        }
+
+        let url = self.page_url(network, token_id, limit, cursor)?;
+        let page = self.fetch_page(token_id, &url).await?;
+        self.pages.insert(key, page.clone());
+        Ok(page)
+    }
+
+    /// Walks every balance page to count holders and work out how much of the
+    /// held supply sits with the largest accounts.
+    pub async fn get_distribution_summary(
+        &self,
+        token_id: &str,
+        network: MirrorNetwork,
+    ) -> Result<TokenDistributionSummary, String> {
+        let token_id = &validate_token_id(token_id, network)?;
+        let key = format!("{}:{}", network.as_str(), token_id);
+        if let Some(summary) = self.summaries.get(&key) {
+            return Ok(summary);
+        }
+
+        let mut holders = Vec::new();
+        let mut decimals = None;
+        let mut url = self.page_url(network, token_id, MAX_PAGE_LIMIT, None)?;
+        let mut truncated = false;
+        for page_index in 0.. {
+            let page = self.fetch_page(token_id, &url).await?;
+            decimals = decimals.or(page.decimals);
+            holders.extend(page.holders);
+            let Some(next) = page.next else {
+                break;
+            };
+            if page_index + 1 >= SUMMARY_MAX_PAGES {
+                truncated = true;
+                break;
+            }
+            url = self.page_url(network, token_id, MAX_PAGE_LIMIT, Some(&next))?;
+        }
+
+        let summary = summarize_holders(token_id, holders, decimals, truncated);
+        self.summaries.insert(key, summary.clone());
+        Ok(summary)
+    }
+
+    fn base_url(&self, network: MirrorNetwork) -> &str {
+        match network {
+            MirrorNetwork::Mainnet => self.mirror_config.mainnet.trim_end_matches('/'),
+            MirrorNetwork::Testnet => self.mirror_config.testnet.trim_end_matches('/'),
+        }
+    }
+
+    fn page_url(
+        &self,
+        network: MirrorNetwork,
+        token_id: &str,
+        limit: u32,
+        cursor: Option<&str>,
+    ) -> Result<String, String> {
+        let base = self.base_url(network);
+        match cursor {
+            Some(cursor) => {
+                let path = format!("/tokens/{token_id}/balances?");
+                let suffix = cursor
+                    .find(&path)
+                    .map(|index| &cursor[index..])
+                    .ok_or_else(|| "Invalid token holder cursor".to_string())?;
+                Ok(format!("{base}{suffix}"))
+            }
+            None => Ok(format!(
+                "{base}/tokens/{token_id}/balances?account.balance=gt:0&order=asc&limit={limit}"
+            )),
+        }
+    }
+
+    async fn fetch_page(&self, token_id: &str, url: &str) -> Result<TokenHoldersPage, String> {
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .map_err(|error| format!("Failed to contact Hedera mirror node: {error}"))?;
+        if !response.status().is_success() {
+            return Err(format!(
+                "Mirror node returned {} for token {token_id} balances",
+                response.status()
+            ));
+        }
+        let body: MirrorBalancesResponse = response
+            .json()
+            .await
+            .map_err(|error| format!("Failed to parse mirror node balances: {error}"))?;
+
+        Ok(TokenHoldersPage {
+            token_id: token_id.to_string(),
+            decimals: body.balances.iter().find_map(|entry| entry.decimals),
+            holders: body
+                .balances
+                .into_iter()
+                .filter(|entry| entry.balance > 0)
+                .map(|entry| TokenHolder {
+                    account: entry.account,
+                    balance: entry.balance,
+                })
+                .collect(),
+            next: body.links.next.filter(|next| !next.is_empty()),
+        })
+    }
+}
+
+fn validate_token_id(token_id: &str, network: MirrorNetwork) -> Result<String, String> {
+    Ok(parse_entity_id(token_id, network.as_str())?.to_string())
+}
+
+fn percentage(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 / total as f64 * 100.0
+    }
+}
+
+fn summarize_holders(
+    token_id: &str,
+    mut holders: Vec<TokenHolder>,
+    decimals: Option<u32>,
+    truncated: bool,
+) -> TokenDistributionSummary {
+    let total_balance = holders
+        .iter()
+        .fold(0u64, |total, holder| total.saturating_add(holder.balance));
+    holders.sort_by(|a, b| b.balance.cmp(&a.balance).then(a.account.cmp(&b.account)));
+
+    let top_holders: Vec<TopHolder> = holders
+        .iter()
+        .take(SUMMARY_TOP_HOLDERS)
+        .map(|holder| TopHolder {
+            account: holder.account.clone(),
+            balance: holder.balance,
+            percentage: percentage(holder.balance, total_balance),
+        })
+        .collect();
+    let top_balance = top_holders
+        .iter()
+        .fold(0u64, |total, holder| total.saturating_add(holder.balance));
+
+    TokenDistributionSummary {
+        token_id: token_id.to_string(),
+        holder_count: holders.len(),
+        total_balance,
+        decimals,
+        top_holders,
+        top_holders_percentage: percentage(top_balance, total_balance),
+        truncated,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::MockServer;
+    use serde_json::json;
+
+    fn service_for(server: &MockServer) -> TokenHolderService {
+        TokenHolderService::with_mirror_config(HederaMirrorConfig {
+            mainnet: server.url("/api/v1"),
+            testnet: server.url("/api/v1"),
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn holders_are_paged_and_cached() {
+        let server = MockServer::start();
+        let first = server.mock(|when, then| {
+            when.method("GET")
+                .path("/api/v1/tokens/0.0.500/balances")
+                .query_param("account.balance", "gt:0")
+                .query_param("limit", "2");
+            then.status(200).json_body(json!({
+                "balances": [
+                    { "account": "0.0.10", "balance": 40, "decimals": 2 },
+                    { "account": "0.0.11", "balance": 60, "decimals": 2 }
+                ],
+                "links": { "next": "/api/v1/tokens/0.0.500/balances?limit=2&account.id=gt:0.0.11" }
+            }));
+        });
+        let second = server.mock(|when, then| {
+            when.method("GET")
+                .path("/api/v1/tokens/0.0.500/balances")
+                .query_param("account.id", "gt:0.0.11");
+            then.status(200).json_body(json!({
+                "balances": [{ "account": "0.0.12", "balance": 5, "decimals": 2 }],
+                "links": { "next": null }
+            }));
+        });
+
+        let service = service_for(&server);
+        let page = service
+            .get_holders("0.0.500", MirrorNetwork::Testnet, Some(2), None)
+            .await
+            .unwrap();
+        assert_eq!(page.holders.len(), 2);
+        assert_eq!(page.decimals, Some(2));
+        let cursor = page.next.clone().unwrap();
+
+        service
+            .get_holders("0.0.500", MirrorNetwork::Testnet, Some(2), None)
+            .await
+            .unwrap();
+        first.assert_hits(1);
+
+        let next = service
+            .get_holders("0.0.500", MirrorNetwork::Testnet, Some(2), Some(&cursor))
+            .await
+            .unwrap();
+        assert_eq!(next.holders[0].account, "0.0.12");
+        assert!(next.next.is_none());
+        second.assert_hits(1);
+    }
+
+    #[test]
+    fn cache_keeps_a_bounded_number_of_entries() {
+        let cache = ResponseCache::new();
+        for index in 0..=CACHE_CAPACITY {
+            cache.insert(index.to_string(), index);
+        }
+        assert_eq!(cache.entries.lock().unwrap().len(), CACHE_CAPACITY);
+        assert_eq!(cache.get("0"), None);
+        assert_eq!(cache.get(&CACHE_CAPACITY.to_string()), Some(CACHE_CAPACITY));
+    }
+
+    #[test]
+    fn summary_ranks_top_holders_by_balance() {
+        let holders = vec![
+            TokenHolder {
+                account: "0.0.1".into(),
+                balance: 25,
+            },
+            TokenHolder {
+                account: "0.0.2".into(),
+                balance: 75,
+            },
+        ];
+        let summary = summarize_holders("0.0.500", holders, Some(0), false);
+        assert_eq!(summary.holder_count, 2);
+        assert_eq!(summary.total_balance, 100);
+        assert_eq!(summary.top_holders[0].account, "0.0.2");
+        assert_eq!(summary.top_holders[0].percentage, 75.0);
+        assert_eq!(summary.top_holders_percentage, 100.0);
+    }
+
+    #[test]
+    fn invalid_token_ids_are_rejected() {
+        let network = MirrorNetwork::Testnet;
+        assert_eq!(validate_token_id("0.0.123", network).unwrap(), "0.0.123");
+        assert_eq!(
+            validate_token_id("0.0.123-esxsf", network).unwrap(),
+            "0.0.123"
+        );
+        assert!(validate_token_id("0.0.123-vfmkw", network).is_err());
+        assert!(validate_token_id("token", network).is_err());
+        assert!(validate_token_id("0.0.", network).is_err());
+    }
+}
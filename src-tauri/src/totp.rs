@@ -0,0 +1,101 @@
+use serde::Serialize;
+use totp_rs::{Algorithm, Secret as TotpSecret, TOTP};
+
+/// A one-time code and when it stops being valid, in milliseconds.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TotpCode {
+    pub code: String,
+    pub expires_at: u64,
+}
+
+/// Turns an `otpauth://totp/` URI or a bare base32 secret into the URI
+/// that is stored. Bare secrets use the common SHA-1, 6 digit, 30 second
+/// settings.
+pub fn normalize(secret: &str, account: &str) -> Result<String, String> {
+    let secret = secret.trim();
+    let totp = if secret.starts_with("otpauth://") {
+        TOTP::from_url_unchecked(secret).map_err(|error| format!("Invalid otpauth URI: {error}"))?
+    } else {
+        let encoded: String = secret
+            .chars()
+            .filter(|character| !character.is_whitespace() && *character != '-')
+            .collect::<String>()
+            .to_uppercase();
+        let bytes = TotpSecret::Encoded(encoded)
+            .to_bytes()
+            .map_err(|error| format!("TOTP secret is not valid base32: {error}"))?;
+        TOTP::new_unchecked(Algorithm::SHA1, 6, 1, 30, bytes, None, account.to_string())
+    };
+    validate(&totp)?;
+    Ok(totp.get_url())
+}
+
+/// The code for `uri` at `now`, in seconds since the epoch.
+pub fn generate(uri: &str, now: u64) -> Result<TotpCode, String> {
+    let totp = TOTP::from_url_unchecked(uri)
+        .map_err(|error| format!("Stored TOTP secret is invalid: {error}"))?;
+    validate(&totp)?;
+    Ok(TotpCode {
+        code: totp.generate(now),
+        expires_at: totp.next_step(now) * 1000,
+    })
+}
+
+fn validate(totp: &TOTP) -> Result<(), String> {
+    if totp.secret.is_empty() {
+        return Err("TOTP secret is empty".to_string());
+    }
+    if !(6..=8).contains(&totp.digits) {
+        return Err(format!(
+            "TOTP codes must have 6 to 8 digits, not {}",
+            totp.digits
+        ));
+    }
+    if totp.step == 0 {
+        return Err("TOTP period must be at least one second".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The SHA-1 secret from RFC 6238, appendix B.
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn generates_the_rfc_6238_codes() {
+        let uri = normalize(
+            &format!("otpauth://totp/acme:ops?secret={RFC_SECRET}&digits=8"),
+            "ops",
+        )
+        .unwrap();
+        assert_eq!(
+            generate(&uri, 59).unwrap(),
+            TotpCode {
+                code: "94287082".to_string(),
+                expires_at: 60_000,
+            }
+        );
+        assert_eq!(generate(&uri, 1_111_111_109).unwrap().code, "07081804");
+    }
+
+    #[test]
+    fn bare_secrets_accept_spacing_and_lowercase() {
+        let uri = normalize("gezd gnbv gy3t qojq gezd gnbv gy3t qojq", "ops").unwrap();
+        assert_eq!(uri, format!("otpauth://totp/ops?secret={RFC_SECRET}"));
+        assert_eq!(generate(&uri, 59).unwrap().code, "287082");
+
+        assert!(normalize("not base32!", "ops").is_err());
+        assert!(normalize("", "ops").is_err());
+        assert!(
+            normalize(
+                &format!("otpauth://totp/ops?secret={RFC_SECRET}&digits=12"),
+                "ops"
+            )
+            .is_err()
+        );
+    }
+}
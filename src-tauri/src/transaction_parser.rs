@@ -4,6 +4,8 @@ use std::path::PathBuf;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::process::Command;
 
+use crate::proxy;
+
 #[derive(Clone)]
 pub struct TransactionParserBridge {
     script_path: PathBuf,
@@ -27,6 +29,7 @@ impl TransactionParserBridge {
     async fn request(&self, action: &str, payload: Value) -> Result<Value, String> {
         let mut command = Command::new("node");
         command.arg(&self.script_path);
+        command.envs(proxy::env_vars());
         command.stdin(std::process::Stdio::piped());
         command.stdout(std::process::Stdio::piped());
         command.stderr(std::process::Stdio::piped());
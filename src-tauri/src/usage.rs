@@ -0,0 +1,144 @@
+use serde::Serialize;
+
+use crate::session::ModelUsage;
+
+/// US dollars per million prompt and completion tokens, matched against the
+/// start of a model name so dated snapshots such as `gpt-4o-2024-08-06`
+/// share their family's price. The longest match wins.
+const MODEL_PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-5", 1.25, 10.0),
+    ("gpt-5-mini", 0.25, 2.0),
+    ("gpt-5-nano", 0.05, 0.4),
+    ("gpt-4.1", 2.0, 8.0),
+    ("gpt-4.1-mini", 0.4, 1.6),
+    ("gpt-4.1-nano", 0.1, 0.4),
+    ("gpt-4o", 2.5, 10.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("o4-mini", 1.1, 4.4),
+    ("gpt-4", 30.0, 60.0),
+    ("gpt-4-turbo", 10.0, 30.0),
+    ("gpt-3.5-turbo", 0.5, 1.5),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("claude-3-opus", 15.0, 75.0),
+    ("gemini-2.5-pro", 1.25, 10.0),
+    ("gemini-2.5-flash", 0.3, 2.5),
+    ("gemini-2.5-flash-lite", 0.1, 0.4),
+    ("gemini-2.0-flash", 0.1, 0.4),
+];
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelCost {
+    pub provider: String,
+    pub model: String,
+    pub responses: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    /// `None` when the model is not in the pricing table.
+    pub estimated_cost_usd: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageSummary {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    /// Cost of the priced models only; see `unpriced_tokens`.
+    pub estimated_cost_usd: f64,
+    pub unpriced_tokens: u64,
+    pub models: Vec<ModelCost>,
+}
+
+/// Prompt and completion prices for `model`. OpenRouter names such as
+/// `openai/gpt-4o` are looked up without their vendor.
+pub fn price_for(model: &str) -> Option<(f64, f64)> {
+    let model = model.trim().to_ascii_lowercase();
+    let model = model.rsplit('/').next().unwrap_or_default();
+    MODEL_PRICES
+        .iter()
+        .filter(|(name, _, _)| model.starts_with(name))
+        .max_by_key(|(name, _, _)| name.len())
+        .map(|(_, prompt, completion)| (*prompt, *completion))
+}
+
+pub fn summarize(session_id: Option<String>, usage: Vec<ModelUsage>) -> UsageSummary {
+    let models: Vec<ModelCost> = usage
+        .into_iter()
+        .map(|usage| ModelCost {
+            estimated_cost_usd: price_for(&usage.model).map(|(prompt, completion)| {
+                (usage.prompt_tokens as f64 * prompt + usage.completion_tokens as f64 * completion)
+                    / 1_000_000.0
+            }),
+            total_tokens: usage.prompt_tokens + usage.completion_tokens,
+            provider: usage.provider,
+            model: usage.model,
+            responses: usage.responses,
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+        })
+        .collect();
+
+    UsageSummary {
+        session_id,
+        prompt_tokens: models.iter().map(|model| model.prompt_tokens).sum(),
+        completion_tokens: models.iter().map(|model| model.completion_tokens).sum(),
+        total_tokens: models.iter().map(|model| model.total_tokens).sum(),
+        estimated_cost_usd: models
+            .iter()
+            .filter_map(|model| model.estimated_cost_usd)
+            .sum(),
+        unpriced_tokens: models
+            .iter()
+            .filter(|model| model.estimated_cost_usd.is_none())
+            .map(|model| model.total_tokens)
+            .sum(),
+        models,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prices_match_the_longest_family_and_skip_vendors() {
+        assert_eq!(price_for("gpt-4o-mini-2024-07-18"), Some((0.15, 0.6)));
+        assert_eq!(price_for("openai/gpt-4o"), Some((2.5, 10.0)));
+        assert_eq!(price_for("gpt-5-mini-2025-08-07"), Some((0.25, 2.0)));
+        assert_eq!(price_for("gpt-4-turbo"), Some((10.0, 30.0)));
+        assert_eq!(price_for("gpt-4-0613"), Some((30.0, 60.0)));
+        assert_eq!(price_for("claude-3-7-sonnet-latest"), Some((3.0, 15.0)));
+        assert_eq!(price_for("llama-3-70b"), None);
+        assert_eq!(price_for(""), None);
+    }
+
+    #[test]
+    fn summary_totals_priced_and_unpriced_models() {
+        let usage = |model: &str, prompt_tokens, completion_tokens| ModelUsage {
+            provider: "openai".to_string(),
+            model: model.to_string(),
+            responses: 1,
+            prompt_tokens,
+            completion_tokens,
+        };
+        let summary = summarize(
+            None,
+            vec![
+                usage("gpt-4o", 1_000_000, 100_000),
+                usage("gpt-4o-mini", 2_000_000, 0),
+                usage("", 500, 500),
+            ],
+        );
+
+        assert_eq!(summary.total_tokens, 3_101_000);
+        assert_eq!(summary.unpriced_tokens, 1_000);
+        assert_eq!(summary.models[0].estimated_cost_usd, Some(3.5));
+        assert!((summary.estimated_cost_usd - 3.8).abs() < 1e-9);
+        assert_eq!(summary.models[2].estimated_cost_usd, None);
+    }
+}
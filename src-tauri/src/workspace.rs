@@ -0,0 +1,212 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+pub const DEFAULT_WORKSPACE_ID: &str = "default";
+pub const EVENT_WORKSPACE_SWITCHED: &str = "workspace_switched";
+const MAX_NAME_CHARS: usize = 80;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Workspace {
+    pub id: String,
+    pub name: String,
+    pub created_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persona_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceList {
+    pub active: String,
+    pub workspaces: Vec<Workspace>,
+}
+
+/// Files that belong to one workspace. Sessions and entities share the chat
+/// database, as they do outside workspaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspacePaths {
+    pub chat_db: PathBuf,
+    pub mcp_servers: PathBuf,
+}
+
+/// Keeps the workspaces known to this install and which one is open. The
+/// default workspace uses the files in the app data directory so installs
+/// from before workspaces keep their data; others live under
+/// `workspaces/<id>/`.
+pub struct WorkspaceRegistry {
+    root: PathBuf,
+    state: WorkspaceList,
+}
+
+impl WorkspaceRegistry {
+    pub fn new(root: &Path) -> Self {
+        let path = root.join("workspaces.json");
+        let mut state = fs::read_to_string(&path)
+            .ok()
+            .and_then(
+                |contents| match serde_json::from_str::<WorkspaceList>(&contents) {
+                    Ok(state) => Some(state),
+                    Err(error) => {
+                        log::warn!("Ignoring unreadable workspace list: {}", error);
+                        None
+                    }
+                },
+            )
+            .unwrap_or_else(|| WorkspaceList {
+                active: DEFAULT_WORKSPACE_ID.to_string(),
+                workspaces: Vec::new(),
+            });
+
+        if !state
+            .workspaces
+            .iter()
+            .any(|w| w.id == DEFAULT_WORKSPACE_ID)
+        {
+            state.workspaces.insert(
+                0,
+                Workspace {
+                    id: DEFAULT_WORKSPACE_ID.to_string(),
+                    name: "Default".to_string(),
+                    created_at: Utc::now().to_rfc3339(),
+                    persona_id: None,
+                },
+            );
+        }
+        if !state.workspaces.iter().any(|w| w.id == state.active) {
+            state.active = DEFAULT_WORKSPACE_ID.to_string();
+        }
+
+        Self {
+            root: root.to_path_buf(),
+            state,
+        }
+    }
+
+    pub fn list(&self) -> WorkspaceList {
+        self.state.clone()
+    }
+
+    pub fn active(&self) -> &Workspace {
+        self.get(&self.state.active)
+            .expect("active workspace is always registered")
+    }
+
+    pub fn paths(&self, workspace_id: &str) -> WorkspacePaths {
+        let dir = if workspace_id == DEFAULT_WORKSPACE_ID {
+            self.root.clone()
+        } else {
+            self.root.join("workspaces").join(workspace_id)
+        };
+        WorkspacePaths {
+            chat_db: dir.join("chat.sqlite"),
+            mcp_servers: dir.join("mcp-servers.json"),
+        }
+    }
+
+    pub fn create(&mut self, name: &str, persona_id: Option<String>) -> Result<Workspace, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Workspace name is required".to_string());
+        }
+        if name.chars().count() > MAX_NAME_CHARS {
+            return Err(format!(
+                "Workspace name must be at most {MAX_NAME_CHARS} characters"
+            ));
+        }
+        if self
+            .state
+            .workspaces
+            .iter()
+            .any(|w| w.name.eq_ignore_ascii_case(name))
+        {
+            return Err(format!("A workspace named \"{name}\" already exists"));
+        }
+
+        let workspace = Workspace {
+            id: Uuid::new_v4().simple().to_string(),
+            name: name.to_string(),
+            created_at: Utc::now().to_rfc3339(),
+            persona_id: persona_id.filter(|id| !id.trim().is_empty()),
+        };
+        let dir = self.root.join("workspaces").join(&workspace.id);
+        fs::create_dir_all(&dir)
+            .map_err(|error| format!("Failed to create workspace directory: {error}"))?;
+
+        self.state.workspaces.push(workspace.clone());
+        if let Err(error) = self.persist() {
+            self.state.workspaces.pop();
+            return Err(error);
+        }
+        Ok(workspace)
+    }
+
+    /// Marks `workspace_id` as the open workspace. Reopening the services on
+    /// its files is up to the caller.
+    pub fn switch(&mut self, workspace_id: &str) -> Result<Workspace, String> {
+        let workspace = self
+            .get(workspace_id)
+            .cloned()
+            .ok_or_else(|| format!("Workspace not found: {workspace_id}"))?;
+        let previous = std::mem::replace(&mut self.state.active, workspace.id.clone());
+        if let Err(error) = self.persist() {
+            self.state.active = previous;
+            return Err(error);
+        }
+        Ok(workspace)
+    }
+
+    pub fn get(&self, workspace_id: &str) -> Option<&Workspace> {
+        self.state.workspaces.iter().find(|w| w.id == workspace_id)
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        fs::create_dir_all(&self.root)
+            .map_err(|error| format!("Failed to create directory: {error}"))?;
+        let contents = serde_json::to_string_pretty(&self.state)
+            .map_err(|error| format!("Failed to serialize workspaces: {error}"))?;
+        fs::write(self.root.join("workspaces.json"), contents)
+            .map_err(|error| format!("Failed to save workspaces: {error}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn workspaces_are_created_switched_and_reloaded() {
+        let dir = tempdir().unwrap();
+        let mut registry = WorkspaceRegistry::new(dir.path());
+        assert_eq!(registry.active().id, DEFAULT_WORKSPACE_ID);
+        assert_eq!(
+            registry.paths(DEFAULT_WORKSPACE_ID).chat_db,
+            dir.path().join("chat.sqlite")
+        );
+
+        let client = registry
+            .create("  Acme audit ", Some("auditor".to_string()))
+            .unwrap();
+        assert_eq!(client.name, "Acme audit");
+        assert!(registry.create("acme AUDIT", None).is_err());
+        assert!(registry.create(" ", None).is_err());
+
+        let paths = registry.paths(&client.id);
+        assert_eq!(
+            paths.chat_db.parent().unwrap().file_name().unwrap(),
+            client.id.as_str()
+        );
+        assert!(paths.chat_db.parent().unwrap().is_dir());
+
+        registry.switch(&client.id).unwrap();
+        assert!(registry.switch("missing").is_err());
+
+        let reloaded = WorkspaceRegistry::new(dir.path());
+        assert_eq!(reloaded.active(), &client);
+        assert_eq!(reloaded.list().workspaces.len(), 2);
+    }
+}
@@ -0,0 +1,62 @@
+#!/usr/bin/env node
+
+// An MCP server that starts a long-running child, the way `npx` leaves a
+// `node` process behind, and writes the child's pid to the path in argv[2].
+// It exits when stdin closes without stopping the child.
+
+import { spawn } from 'node:child_process';
+import { writeFileSync } from 'node:fs';
+import { createInterface } from 'node:readline';
+import process from 'node:process';
+
+const child = spawn('sleep', ['300'], { stdio: 'ignore' });
+writeFileSync(process.argv[2], String(child.pid));
+child.unref();
+
+const rl = createInterface({ input: process.stdin, crlfDelay: Infinity });
+
+const send = (payload) => {
+  process.stdout.write(`${JSON.stringify(payload)}\n`);
+};
+
+rl.on('line', (line) => {
+  let message;
+  try {
+    message = JSON.parse(line.trim());
+  } catch {
+    return;
+  }
+
+  if (message.method === 'initialize' && message.id !== undefined) {
+    send({
+      jsonrpc: '2.0',
+      id: message.id,
+      result: {
+        protocolVersion: '2024-11-05',
+        capabilities: { tools: {} },
+        serverInfo: { name: 'mock-mcp-server-with-child', version: '1.0.0' },
+      },
+    });
+    return;
+  }
+
+  if (message.method === 'tools/list' && message.id !== undefined) {
+    send({
+      jsonrpc: '2.0',
+      id: message.id,
+      result: {
+        tools: [
+          {
+            name: 'childTool',
+            description: 'Mock tool',
+            inputSchema: { type: 'object', properties: {} },
+          },
+        ],
+      },
+    });
+  }
+});
+
+rl.on('close', () => {
+  process.exit(0);
+});
@@ -30,4 +30,5 @@ export interface EntityAssociation {
   updatedAt?: string | number | null;
   isActive?: boolean;
   metadata?: Record<string, unknown> | null;
+  favorite?: boolean;
 }
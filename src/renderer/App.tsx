@@ -13,6 +13,7 @@ import { Toaster } from './components/ui/sonner';
 import { HCS10Provider } from './contexts/HCS10Context';
 import DesktopShellRouter from './components/shell/DesktopShellRouter';
 import BuilderStudioRoutes from './components/shell/BuilderStudioRouter';
+import { BrowserScriptApproval } from './components/shell/browser/BrowserScriptApproval';
 
 const App: React.FC = () => {
   return (
@@ -36,6 +37,7 @@ const App: React.FC = () => {
                           <Route path='*' element={<Navigate to='/' replace />} />
                         </Routes>
                         <NotificationContainer />
+                        <BrowserScriptApproval />
                         <Toaster />
                       </KeyboardShortcutsProvider>
                     </HCS10Provider>
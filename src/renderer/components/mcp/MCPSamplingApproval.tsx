@@ -0,0 +1,100 @@
+import React, { useEffect, useState } from 'react';
+import {
+  Dialog,
+  DialogContent,
+  DialogDescription,
+  DialogFooter,
+  DialogHeader,
+  DialogTitle,
+} from '../ui/dialog';
+import { Button } from '../ui/Button';
+import { Checkbox } from '../ui/checkbox';
+import Typography from '../ui/Typography';
+import { useMCPStore } from '../../stores/mcpStore';
+import type { MCPSamplingDecision, MCPSamplingRequest } from '../../types/mcp';
+
+/**
+ * Asks the user before an MCP server may use the agent's LLM, one
+ * `mcp_sampling_requested` event at a time.
+ */
+export const MCPSamplingApproval: React.FC = () => {
+  const [queue, setQueue] = useState<MCPSamplingRequest[]>([]);
+  const [remember, setRemember] = useState(false);
+  const servers = useMCPStore((state) => state.servers);
+
+  useEffect(() => {
+    const off = window?.desktop?.on('mcp_sampling_requested', (payload) => {
+      setQueue((current) => [...current, payload as unknown as MCPSamplingRequest]);
+    });
+    return () => off?.();
+  }, []);
+
+  const request = queue[0];
+  if (!request) {
+    return null;
+  }
+  const serverName =
+    servers.find((server) => server.id === request.serverId)?.name ?? request.serverId;
+
+  const resolve = async (decision: MCPSamplingDecision) => {
+    setQueue((current) => current.slice(1));
+    setRemember(false);
+    try {
+      await window?.desktop?.resolveMCPSampling?.(request.requestId, decision);
+    } catch (error) {
+      console.warn('Failed to resolve MCP sampling request', error);
+    }
+  };
+
+  return (
+    <Dialog open onOpenChange={(open) => !open && resolve({ approved: false })}>
+      <DialogContent className='sm:max-w-lg'>
+        <DialogHeader>
+          <DialogTitle>{serverName} wants to use your LLM</DialogTitle>
+          <DialogDescription>
+            The server asked for a completion of up to {request.maxTokens} tokens, billed to
+            your configured provider.
+          </DialogDescription>
+        </DialogHeader>
+
+        <div className='max-h-64 space-y-2 overflow-y-auto'>
+          {request.systemPrompt && (
+            <div className='rounded-lg bg-muted/50 p-3'>
+              <Typography variant='caption' className='text-muted-foreground'>
+                System
+              </Typography>
+              <Typography variant='body2' className='whitespace-pre-wrap break-words'>
+                {request.systemPrompt}
+              </Typography>
+            </div>
+          )}
+          {request.messages.map((message, index) => (
+            <div key={index} className='rounded-lg bg-muted/50 p-3'>
+              <Typography variant='caption' className='text-muted-foreground capitalize'>
+                {message.role}
+              </Typography>
+              <Typography variant='body2' className='whitespace-pre-wrap break-words'>
+                {message.text}
+              </Typography>
+            </div>
+          ))}
+        </div>
+
+        <label className='flex items-center gap-2 text-sm'>
+          <Checkbox
+            checked={remember}
+            onCheckedChange={(checked) => setRemember(checked === true)}
+          />
+          Allow {serverName} until the app restarts
+        </label>
+
+        <DialogFooter>
+          <Button variant='outline' onClick={() => resolve({ approved: false })}>
+            Decline
+          </Button>
+          <Button onClick={() => resolve({ approved: true, remember })}>Approve</Button>
+        </DialogFooter>
+      </DialogContent>
+    </Dialog>
+  );
+};
@@ -0,0 +1,89 @@
+import React, { useEffect, useState } from 'react';
+import {
+  Dialog,
+  DialogContent,
+  DialogDescription,
+  DialogFooter,
+  DialogHeader,
+  DialogTitle,
+} from '../../ui/dialog';
+import { Button } from '../../ui/Button';
+import { Checkbox } from '../../ui/checkbox';
+import Typography from '../../ui/Typography';
+import type {
+  BrowserScriptApprovalDecision,
+  BrowserScriptApprovalRequest,
+} from '../../../types/desktop-bridge';
+
+/**
+ * Asks the user before a script runs in the browser view, one
+ * `browser_script_approval_requested` event at a time.
+ */
+export const BrowserScriptApproval: React.FC = () => {
+  const [queue, setQueue] = useState<BrowserScriptApprovalRequest[]>([]);
+  const [remember, setRemember] = useState(false);
+
+  useEffect(() => {
+    const off = window?.desktop?.on('browser_script_approval_requested', (payload) => {
+      setQueue((current) => [...current, payload as unknown as BrowserScriptApprovalRequest]);
+    });
+    return () => off?.();
+  }, []);
+
+  const request = queue[0];
+  if (!request) {
+    return null;
+  }
+
+  const resolve = async (decision: BrowserScriptApprovalDecision) => {
+    setQueue((current) => current.slice(1));
+    setRemember(false);
+    try {
+      await window?.desktop?.browser?.resolveScriptApproval(request.requestId, decision);
+    } catch (error) {
+      console.warn('Failed to resolve browser script approval', error);
+    }
+  };
+
+  const truncated = request.scriptLength > request.scriptPreview.length;
+
+  return (
+    <Dialog open onOpenChange={(open) => !open && resolve({ approved: false })}>
+      <DialogContent className='sm:max-w-lg'>
+        <DialogHeader>
+          <DialogTitle>Run a script on {request.origin}?</DialogTitle>
+          <DialogDescription>
+            The agent wants to run a script on {request.url}. It can read and change the page.
+          </DialogDescription>
+        </DialogHeader>
+
+        <div className='max-h-64 overflow-y-auto rounded-lg bg-muted/50 p-3'>
+          <Typography variant='body2' className='whitespace-pre-wrap break-words font-mono'>
+            {request.scriptPreview}
+            {truncated && '…'}
+          </Typography>
+          {truncated && (
+            <Typography variant='caption' className='text-muted-foreground'>
+              Showing {request.scriptPreview.length} of {request.scriptLength} characters
+            </Typography>
+          )}
+        </div>
+
+        <label className='flex items-center gap-2 text-sm'>
+          <Checkbox
+            checked={remember}
+            onCheckedChange={(checked) => setRemember(checked === true)}
+          />
+          Remember this choice for {request.origin}
+        </label>
+
+        <DialogFooter>
+          <Button variant='outline' onClick={() => resolve({ approved: false, remember })}>
+            Block
+          </Button>
+          <Button onClick={() => resolve({ approved: true, remember })}>Run script</Button>
+        </DialogFooter>
+      </DialogContent>
+    </Dialog>
+  );
+};
@@ -36,18 +36,6 @@ export const PAGE_CONTEXT_SCRIPT = String.raw`
 })();
 `;
 
-export const FAVICON_SCRIPT = String.raw`
-(() =>
-  Array.from(
-    document.querySelectorAll(
-      'link[rel~="icon"], link[rel="shortcut icon"], link[rel="apple-touch-icon"], link[rel="apple-touch-icon-precomposed"], link[rel="mask-icon"]'
-    )
-  )
-    .map((link) => link.href || link.getAttribute('href') || '')
-    .filter(Boolean)
-)();
-`;
-
 export interface PageContext {
   title?: string | null;
   description: string | null;
@@ -12,7 +12,6 @@ import type { BrowserState } from '@/types/desktop-bridge';
 import { SAFE_PROTOCOL, normalizeUrl } from './constants';
 import {
   PAGE_CONTEXT_SCRIPT,
-  FAVICON_SCRIPT,
   type PageContext,
 } from './scripts';
 import { isBrowserState } from './guards';
@@ -407,8 +406,8 @@ export const useBrowserBridge = ({
       return;
     }
     try {
-      const result = await api.executeJavaScript(FAVICON_SCRIPT);
-      const favicons = result as string[] | null;
+      const context = await api.captureContext();
+      const favicons = context?.favicons ?? null;
       if (Array.isArray(favicons) && favicons.length > 0) {
         const resolvedIcon = favicons
           .map((entry) => {
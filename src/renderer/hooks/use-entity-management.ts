@@ -82,13 +82,21 @@ export function useEntityManagement() {
     setError(null);
     
     try {
-      const result = await window?.desktop?.entity.getAll();
-      
-      if (result.success && result.data) {
-        setEntities(result.data);
-      } else {
-        setError(result.error || 'Failed to load entities');
-      }
+      const loaded: EntityAssociation[] = [];
+      let cursor: string | undefined;
+      do {
+        const result = await window?.desktop?.entity.getAll(
+          cursor ? { cursor } : undefined
+        );
+        if (!result.success || !result.data) {
+          setError(result.error || 'Failed to load entities');
+          return;
+        }
+        loaded.push(...result.data.entities);
+        cursor = result.data.nextCursor;
+      } while (cursor);
+
+      setEntities(loaded);
     } catch (err) {
       setError(err instanceof Error ? err.message : 'Failed to load entities');
     } finally {
@@ -8,7 +8,8 @@ interface ConfigInitProviderProps {
 /**
  * Provider that initializes configuration loading on app startup.
  * This ensures that configuration is loaded from storage before
- * any components that depend on it are rendered.
+ * any components that depend on it are rendered. The configuration is
+ * reloaded whenever the backend emits `config_changed`.
  *
  * @param children - Child components to render after configuration initialization
  * @returns React component that handles configuration initialization
@@ -24,5 +25,14 @@ export const ConfigInitProvider: React.FC<ConfigInitProviderProps> = ({
     });
   }, [loadConfig]);
 
+  useEffect(() => {
+    const off = window?.desktop?.on('config_changed', () => {
+      loadConfig().catch((error) => {
+        console.warn('Failed to reload config after config_changed', error);
+      });
+    });
+    return () => off?.();
+  }, [loadConfig]);
+
   return <>{children}</>;
 };
\ No newline at end of file
@@ -1,5 +1,7 @@
 import React, { useEffect, useRef } from 'react'
 import { useMCPStore } from '../stores/mcpStore'
+import { MCPSamplingApproval } from '../components/mcp/MCPSamplingApproval'
+import type { MCPToolsChangedEvent } from '../types/mcp'
 import { createElectronRendererLogger } from '../utils/electron-logger-adapter'
 
 interface MCPInitProviderProps {
@@ -62,10 +64,11 @@ const waitForRemoteOrigin = async (timeoutMs = 15000) => {
 }
 
 /**
- * Provider component that initializes MCP services on app startup
+ * Provider component that initializes MCP services on app startup and keeps
+ * each server's tool list current as `mcp_tools_changed` events arrive
  */
 export const MCPInitProvider: React.FC<MCPInitProviderProps> = ({ children }) => {
-  const { loadServers, error } = useMCPStore()
+  const { loadServers, applyToolChanges, error } = useMCPStore()
   const hasInitialized = useRef(false)
   const logger = useRef(createElectronRendererLogger({ module: 'MCPInitProvider' }))
 
@@ -148,6 +151,13 @@ export const MCPInitProvider: React.FC<MCPInitProviderProps> = ({ children }) =>
     }
   }, [error])
 
+  useEffect(() => {
+    const off = window?.desktop?.on('mcp_tools_changed', (payload) => {
+      applyToolChanges(payload as unknown as MCPToolsChangedEvent)
+    })
+    return () => off?.()
+  }, [applyToolChanges])
+
   useEffect(() => {
     const interval = setInterval(async () => {
       if (window.desktop?.triggerMCPBackgroundSync) {
@@ -162,5 +172,10 @@ export const MCPInitProvider: React.FC<MCPInitProviderProps> = ({ children }) =>
     return () => clearInterval(interval)
   }, [])
 
-  return <>{children}</>
+  return (
+    <>
+      {children}
+      <MCPSamplingApproval />
+    </>
+  )
 }
@@ -1293,6 +1293,7 @@ export const useAgentStore = create<AgentStore>((set, get) => {
         const chatHistory = messages.map((msg) => ({
           type: msg.role === 'user' ? ('human' as const) : ('ai' as const),
           content: msg.content,
+          id: msg.id,
         }));
 
         const result = await window?.desktop?.sendAgentMessage({
@@ -1932,6 +1933,7 @@ export const useAgentStore = create<AgentStore>((set, get) => {
             chatHistory: currentMessages.map((msg) => ({
               type: msg.role === 'user' ? ('human' as const) : ('ai' as const),
               content: msg.content,
+              id: msg.id,
             })),
         });
 
@@ -26,15 +26,25 @@ const waitForDesktopBridge = async (
   return Promise.race([checkBridgePromise(), timeoutPromise]);
 };
 
+export interface HederaAccount {
+  name: string;
+  accountId: string;
+  privateKey: string;
+  network: 'mainnet' | 'testnet';
+}
+
 export interface HederaConfig {
   accountId: string;
   privateKey: string;
   network: 'mainnet' | 'testnet';
+  accounts?: HederaAccount[];
+  activeAccount?: string;
 }
 
 export interface OpenAIConfig {
   apiKey: string;
   model: string;
+  baseUrl?: string;
 }
 
 export interface AnthropicConfig {
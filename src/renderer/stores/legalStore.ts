@@ -10,6 +10,8 @@ export interface LegalAcceptance {
   privacyAccepted: boolean;
   termsAcceptedAt?: string;
   privacyAcceptedAt?: string;
+  termsHash?: string;
+  privacyHash?: string;
 }
 
 export interface LegalStore {
@@ -6,6 +6,7 @@ import {
   MCPServerType,
   MCPServerTool,
   MCPRegistryMetricsEntry,
+  MCPToolsChangedEvent,
 } from '../types/mcp';
 import { toCommandResponse } from '../tauri/ipc';
 
@@ -260,6 +261,7 @@ export interface MCPStore {
   connectServer: (serverId: string) => Promise<void>;
   disconnectServer: (serverId: string) => Promise<void>;
   refreshServerTools: (serverId: string) => Promise<void>;
+  applyToolChanges: (change: MCPToolsChangedEvent) => void;
 
   loadServers: () => Promise<void>;
   reloadServers: () => Promise<void>;
@@ -612,6 +614,29 @@ export const useMCPStore = create<MCPStore>((set, get) => ({
     }
   },
 
+  applyToolChanges: (change: MCPToolsChangedEvent) => {
+    const replaced = new Set([
+      ...change.removed,
+      ...change.modified.map((tool) => tool.name),
+    ]);
+    set((state) => ({
+      servers: state.servers.map((server) =>
+        server.id === change.serverId
+          ? {
+              ...server,
+              tools: [
+                ...(server.tools ?? []).filter(
+                  (tool) => !replaced.has(tool.name)
+                ),
+                ...change.modified,
+                ...change.added,
+              ],
+            }
+          : server
+      ),
+    }));
+  },
+
   loadServers: async () => {
     set({ isLoading: true, error: null, initializationState: 'initializing' });
 
@@ -1,5 +1,5 @@
 import type { BrowserBounds, BrowserLayoutInfo } from '../../shared/browser-layout';
-import type { BrowserState } from '@/types/desktop-bridge';
+import type { BrowserScriptApprovalDecision, BrowserState } from '@/types/desktop-bridge';
 import type { MCPServerConfig } from '@/types/mcp';
 import type { AppConfig } from '@/stores/configStore';
 
@@ -251,6 +251,7 @@ if (typeof window !== 'undefined') {
       } satisfies LoadConfigResponse;
     },
     getEnvironmentConfig: () => invoke('get_environment_config'),
+    getEffectiveConfig: () => invoke('get_effective_config'),
     getWalletStatus: () => invoke('wallet_status'),
     testHederaConnection: (payload: { accountId: string; privateKey: string; network: 'mainnet' | 'testnet' }) =>
       invoke('connection_test_hedera', payload),
@@ -278,6 +279,28 @@ if (typeof window !== 'undefined') {
     testMCPConnection: (server: MCPServerConfig) => invoke('mcp_test_connection', { server }),
     connectMCPServer: (serverId: string) => invoke('mcp_connect_server', { serverId }),
     disconnectMCPServer: (serverId: string) => invoke('mcp_disconnect_server', { serverId }),
+    callMCPTool: (
+      serverId: string,
+      toolName: string,
+      args?: Record<string, unknown>,
+      callId?: string
+    ) => invoke('mcp_call_tool', { serverId, toolName, arguments: args, callId }),
+    cancelMCPToolCall: (callId: string) => invoke('mcp_cancel_tool_call', { callId }),
+    getMCPHealth: () => invoke('mcp_get_health'),
+    startMCPOAuth: (serverId: string) => invoke('mcp_oauth_start', { serverId }),
+    signOutMCPOAuth: (serverId: string) => invoke('mcp_oauth_sign_out', { serverId }),
+    checkMCPUpdates: () => invoke('mcp_check_updates'),
+    upgradeMCPServer: (serverId: string, version?: string) =>
+      invoke('mcp_upgrade_server', { serverId, version }),
+    importMCPConfig: (path: string) => invoke('mcp_import_config', { path }),
+    exportMCPConfig: (includeSecrets?: boolean) =>
+      invoke('mcp_export_config', { includeSecrets }),
+    listMCPGroups: () => invoke('mcp_list_groups'),
+    saveMCPGroup: (name: string, serverIds: string[]) =>
+      invoke('mcp_save_group', { name, serverIds }),
+    deleteMCPGroup: (name: string) => invoke('mcp_delete_group', { name }),
+    activateMCPGroup: (name: string) => invoke('mcp_activate_group', { name }),
+    deactivateMCPGroup: () => invoke('mcp_deactivate_group'),
     getMCPServerTools: (serverId: string) => invoke('mcp_get_server_tools', { serverId }),
     refreshMCPServerTools: (serverId: string) => invoke('mcp_refresh_server_tools', { serverId }),
     searchMCPRegistry: (options: Record<string, unknown>) =>
@@ -294,6 +317,10 @@ if (typeof window !== 'undefined') {
         packageName,
         installCommand,
       }),
+    resolveMCPInstallVariables: (serverId: string, values: Record<string, string>) =>
+      invoke('mcp_resolve_install_variables', { serverId, values }),
+    resolveMCPSampling: (requestId: string, decision: { approved: boolean; remember?: boolean }) =>
+      invoke('mcp_resolve_sampling', { requestId, decision }),
     clearMCPRegistryCache: () => invoke('mcp_clear_registry_cache'),
     getMCPCacheStats: async () => {
       try {
@@ -364,6 +391,8 @@ if (typeof window !== 'undefined') {
         invokeWithPayload('entity_bulk_delete', { entityIds }),
       rename: (entityId: string, newName: string) =>
         invokeWithPayload('entity_rename', { entityId, newName }),
+      setFavorite: (entityId: string, favorite: boolean) =>
+        invokeWithPayload('entity_set_favorite', { entityId, favorite }),
       export: (
         filters?: Record<string, unknown>,
         format: 'json' | 'csv' = 'json'
@@ -523,6 +552,8 @@ if (typeof window !== 'undefined') {
       setLayout: (layout: BrowserLayoutInfo) => invoke<void>('browser_set_layout', { layout }),
       getState: () => invoke<BrowserState>('browser_get_state'),
       executeJavaScript: <T = unknown>(script: string) => invoke<T>('browser_execute_js', { script }),
+      resolveScriptApproval: (requestId: string, decision: BrowserScriptApprovalDecision) =>
+        invoke<void>('browser_resolve_script_approval', { requestId, decision }),
       captureContext: () => invoke<{title?: string; description?: string; selection?: string; favicons?: string[]} | null>('browser_capture_context'),
       openDevTools: () => invoke<void>('browser_open_devtools'),
       attach: () => invoke<void>('browser_attach'),
@@ -1,6 +1,16 @@
 import { AppConfig } from '../stores/configStore'
 import { Message } from '../stores/agentStore'
-import { MCPServerConfig, MCPServerTool } from './mcp'
+import {
+  MCPConfigExport,
+  MCPConfigImport,
+  MCPGroupActivation,
+  MCPSamplingDecision,
+  MCPServerGroup,
+  MCPServerConfig,
+  MCPServerHealth,
+  MCPServerTool,
+  MCPServerUpdate,
+} from './mcp'
 import type { EntityAssociation } from '../../main/db/schema'
 import {
   PluginConfig,
@@ -31,6 +41,7 @@ interface AgentConfig {
   disableLogging?: boolean;
   disabledPlugins?: string[];
   additionalPlugins?: Array<{ pluginType: string; config: Record<string, unknown>}>;
+  mcpGroup?: string;
 }
 
 interface ChatSession {
@@ -80,6 +91,20 @@ export interface BrowserState {
   lastError: string | null;
 }
 
+/** Payload of the `browser_script_approval_requested` event. */
+export interface BrowserScriptApprovalRequest {
+  requestId: string;
+  origin: string;
+  url: string;
+  scriptPreview: string;
+  scriptLength: number;
+}
+
+export interface BrowserScriptApprovalDecision {
+  approved: boolean;
+  remember?: boolean;
+}
+
 export interface BrowserBounds {
   x: number;
   y: number;
@@ -136,6 +161,15 @@ declare global {
           privacyMarkdown?: string
         }
       }>
+      getEffectiveConfig: () => Promise<{
+        config: Record<string, unknown>
+        fields: Array<{
+          path: string
+          value: unknown
+          source: 'default' | 'file' | 'environment'
+          variable?: string
+        }>
+      }>
       getWalletStatus: () => Promise<{ success: boolean; data?: Record<string, unknown> | null; error?: string }>
       
       testHederaConnection: (credentials: {
@@ -169,7 +203,7 @@ declare global {
       preloadAgent: (config: AgentConfig) => Promise<{ success: boolean; error?: string }>
       sendAgentMessage: (data: {
         content: string
-        chatHistory?: Array<{ type: string; content: string }>
+        chatHistory?: Array<{ type: string; content: string; id?: string }>
         attachments?: Array<{ name: string; data: string; type: string; size: number }>
         formSubmission?: Record<string, unknown>
         sessionId?: string
@@ -192,6 +226,28 @@ declare global {
       testMCPConnection: (server: MCPServerConfig) => Promise<{ success: boolean; data?: { success: boolean; tools?: MCPServerTool[]; error?: string }; error?: string }>
       connectMCPServer: (serverId: string) => Promise<{ success: boolean; data?: { success: boolean; tools?: MCPServerTool[]; error?: string }; error?: string }>
       disconnectMCPServer: (serverId: string) => Promise<{ success: boolean; error?: string }>
+      callMCPTool: (serverId: string, toolName: string, args?: Record<string, unknown>, callId?: string) => Promise<{ success: boolean; data?: { serverId: string; toolName: string; content: unknown[]; structuredContent?: unknown; isError: boolean; durationMs: number; cached: boolean }; error?: string }>
+      cancelMCPToolCall?: (callId: string) => Promise<{ success: boolean; data?: boolean; error?: string }>
+      getMCPHealth: () => Promise<{ success: boolean; data?: MCPServerHealth[]; error?: string }>
+      startMCPOAuth: (serverId: string) => Promise<{ success: boolean; data?: boolean; error?: string }>
+      signOutMCPOAuth: (serverId: string) => Promise<{ success: boolean; data?: boolean; error?: string }>
+      checkMCPUpdates: () => Promise<{ success: boolean; data?: MCPServerUpdate[]; error?: string }>
+      upgradeMCPServer: (
+        serverId: string,
+        version?: string
+      ) => Promise<{ success: boolean; data?: MCPServerConfig; error?: string }>
+      importMCPConfig: (path: string) => Promise<{ success: boolean; data?: MCPConfigImport; error?: string }>
+      exportMCPConfig: (
+        includeSecrets?: boolean
+      ) => Promise<{ success: boolean; data?: MCPConfigExport; error?: string }>
+      listMCPGroups: () => Promise<{ success: boolean; data?: MCPServerGroup[]; error?: string }>
+      saveMCPGroup: (
+        name: string,
+        serverIds: string[]
+      ) => Promise<{ success: boolean; data?: MCPServerGroup; error?: string }>
+      deleteMCPGroup: (name: string) => Promise<{ success: boolean; data?: boolean; error?: string }>
+      activateMCPGroup: (name: string) => Promise<{ success: boolean; data?: MCPGroupActivation; error?: string }>
+      deactivateMCPGroup: () => Promise<{ success: boolean; data?: MCPGroupActivation; error?: string }>
       getMCPServerTools: (serverId: string) => Promise<{ success: boolean; data?: MCPServerTool[]; error?: string }>
       refreshMCPServerTools?: (serverId: string) => Promise<{ success: boolean; data?: { success?: boolean; tools?: MCPServerTool[]; error?: string }; error?: string }>
       searchMCPRegistry?: (options?: Record<string, unknown>) => Promise<{ success: boolean; data?: Record<string, unknown>; error?: string }>
@@ -201,6 +257,14 @@ declare global {
         packageName?: string,
         installCommand?: { command: string; args: string[] }
       ) => Promise<{ success: boolean; data?: Record<string, unknown>; error?: string }>
+      resolveMCPInstallVariables?: (
+        serverId: string,
+        values: Record<string, string>
+      ) => Promise<{ success: boolean; data?: MCPServerConfig; error?: string }>
+      resolveMCPSampling?: (
+        requestId: string,
+        decision: MCPSamplingDecision
+      ) => Promise<{ success: boolean; data?: boolean; error?: string }>
       clearMCPRegistryCache?: () => Promise<{ success: boolean; error?: string }>
       getMCPCacheStats?: () => Promise<{ success: boolean; data?: Record<string, unknown>; error?: string }>
       triggerMCPBackgroundSync?: () => Promise<{ success: boolean; data?: Record<string, unknown>; error?: string }>
@@ -244,7 +308,10 @@ declare global {
       ) => Promise<{ success: boolean; data?: Record<string, unknown>; error?: string }>
 
       entity: {
-        getAll: (filters?: Record<string, unknown>) => Promise<CommandResponse<EntityAssociation[]>>
+        getAll: (filters?: Record<string, unknown>) => Promise<CommandResponse<{
+          entities: EntityAssociation[]
+          nextCursor?: string
+        }>>
         delete: (entityId: string) => Promise<CommandResponse<Record<string, unknown>>>
         bulkDelete: (
           entityIds: string[]
@@ -254,6 +321,7 @@ declare global {
           totalRequested: number
         }>>
         rename: (entityId: string, newName: string) => Promise<CommandResponse<EntityAssociation>>
+        setFavorite: (entityId: string, favorite: boolean) => Promise<CommandResponse<EntityAssociation>>
         export: (
           filters?: Record<string, unknown>,
           format?: 'json' | 'csv'
@@ -353,6 +421,10 @@ declare global {
         }) => Promise<void>
         getState: () => Promise<BrowserState>
         executeJavaScript: <T = unknown>(script: string) => Promise<T>
+        resolveScriptApproval: (
+          requestId: string,
+          decision: BrowserScriptApprovalDecision
+        ) => Promise<void>
         captureContext: () => Promise<{title?: string; description?: string; selection?: string; favicons?: string[]} | null>
         openDevTools: () => Promise<void>
         attach: () => Promise<void>
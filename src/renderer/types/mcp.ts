@@ -1,13 +1,99 @@
-export type MCPServerType = 'filesystem' | 'github' | 'postgres' | 'sqlite' | 'custom'
+export type MCPServerType = 'filesystem' | 'github' | 'postgres' | 'sqlite' | 'custom' | 'http' | 'sse'
 
 export type MCPServerStatus = 'connected' | 'disconnected' | 'connecting' | 'handshaking' | 'ready' | 'error'
 
+export interface MCPServerHealth {
+  serverId: string
+  healthy: boolean
+  latencyMs?: number
+  checkedAt?: string
+  checks: number
+  failures: number
+  consecutiveFailures: number
+  lastError?: string
+}
+
+export interface MCPServerUpdate {
+  serverId: string
+  name: string
+  packageRegistry: 'npm' | 'pypi'
+  packageName: string
+  installedVersion?: string
+  latestVersion?: string
+  updateAvailable: boolean
+  error?: string
+}
+
+export interface MCPConfigImport {
+  added: string[]
+  replaced: string[]
+  skipped: string[]
+}
+
+export interface MCPConfigExport {
+  path: string
+  exported: number
+  skipped: string[]
+}
+
+export interface MCPServerGroup {
+  name: string
+  serverIds: string[]
+  updatedAt: string
+}
+
+export interface MCPGroupActivation {
+  group: string
+  connected: string[]
+  disconnected: string[]
+  failed: Array<{ serverId: string; error: string }>
+}
+
+export interface MCPServerStatusEvent {
+  serverId: string
+  status: 'connecting' | 'connected' | 'failed'
+  attempt: number
+  error?: string
+}
+
+/** Payload of the `mcp_sampling_requested` event. */
+export interface MCPSamplingRequest {
+  requestId: string
+  serverId: string
+  systemPrompt?: string | null
+  messages: Array<{ role: 'user' | 'assistant'; text: string }>
+  maxTokens: number
+}
+
+export interface MCPSamplingDecision {
+  approved: boolean
+  remember?: boolean
+}
+
 export interface MCPServerTool {
   name: string
   description: string
   inputSchema: JSONSchema
 }
 
+/** Payload of the `mcp_tool_progress` event. */
+export interface MCPToolProgress {
+  callId: string
+  serverId: string
+  toolName: string
+  progress: number
+  total?: number
+  message?: string
+}
+
+/** Payload of the `mcp_tools_changed` event. */
+export interface MCPToolsChangedEvent {
+  serverId: string
+  added: MCPServerTool[]
+  removed: string[]
+  modified: MCPServerTool[]
+}
+
 interface JSONSchema {
   type: 'object' | 'string' | 'number' | 'boolean' | 'array' | 'null'
   properties?: Record<string, JSONSchema>
@@ -42,6 +128,15 @@ export interface MCPServerConfig {
   source?: string
   metrics?: Record<string, MCPRegistryMetricsEntry>
   metricFreshness?: Record<string, string>
+  installVariables?: MCPInstallVariable[]
+}
+
+export interface MCPInstallVariable {
+  name: string
+  description?: string
+  required: boolean
+  secret: boolean
+  default?: string
 }
 
 export interface MCPRegistryMetricsEntry {
@@ -84,12 +179,50 @@ export interface MCPSqliteConfig {
   readOnly?: boolean
 }
 
+export interface MCPToolResultCacheConfig {
+  ttlSeconds: number
+  tools?: string[]
+}
+
+export interface MCPRateLimitConfig {
+  requestsPerMinute: number
+  maxWaitSeconds?: number
+}
+
+export interface MCPSandboxConfig {
+  inheritEnv?: boolean
+  passEnv?: string[]
+  os?: boolean
+  allowNetwork?: boolean
+}
+
 export interface MCPCustomConfig {
   type: 'custom'
   command: string
   args?: string[]
   env?: Record<string, string>
   cwd?: string
+  sandbox?: MCPSandboxConfig
+  resultCache?: MCPToolResultCacheConfig
+  rateLimit?: MCPRateLimitConfig
+}
+
+export interface MCPHttpConfig {
+  type: 'http' | 'sse'
+  url: string
+  headers?: Record<string, string>
+  requireSession?: boolean
+  reconnect?: {
+    maxRetries?: number
+    baseDelayMs?: number
+  }
+  auth?: {
+    type: 'oauth'
+    clientId?: string
+    scope?: string
+  }
+  resultCache?: MCPToolResultCacheConfig
+  rateLimit?: MCPRateLimitConfig
 }
 
 export type MCPServerConfigType = 
@@ -98,6 +231,7 @@ export type MCPServerConfigType =
   | MCPPostgresConfig
   | MCPSqliteConfig
   | MCPCustomConfig
+  | MCPHttpConfig
 
 export interface MCPConnectionTest {
   id: string